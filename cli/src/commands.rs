@@ -0,0 +1,495 @@
+use api3_common::abi::{encode, Int, Token};
+use api3_common::signer::{
+    Ed25519Signer, Ed25519Verifier, Secp256k1Signer, Secp256k1Verifier, SignedData,
+};
+use api3_common::{
+    decode_fulfillment_data, derive_beacon_id, derive_dapi_id, keccak_packed, Bytes, Bytes32,
+    RoleDeriver, SignatureManger, Timestamp,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The environment variable `sign` reads the secret key from without `--secret-key-file`
+pub const SECRET_KEY_ENV: &str = "API3_SECRET_KEY";
+
+/// Derives the IDs of API3 data feeds, encodes their data and signs it the way Airnode does
+#[derive(Parser, Debug)]
+#[command(name = "api3-cli", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Derives the Beacon ID from the Airnode and the template ID
+    DeriveBeaconId {
+        /// Airnode address or ed25519 public key, in hex
+        #[arg(long, value_parser = parse_hex)]
+        airnode: Bytes,
+        #[arg(long, value_parser = parse_bytes32)]
+        template_id: Bytes32,
+    },
+    /// Derives the dAPI ID from the Beacon IDs, in order
+    DeriveDapiId {
+        #[arg(required = true, num_args = 2.., value_parser = parse_bytes32)]
+        beacon_ids: Vec<Bytes32>,
+    },
+    /// Hashes the name of a data feed, e.g. `ETH/USD`, which is whitelisted for `read_with_name`
+    NameHash {
+        /// The name as a string of up to 31 bytes, or a `bytes32` in hex
+        #[arg(value_parser = parse_name)]
+        name: Bytes32,
+    },
+    /// Derives the role with the descriptions from the root role of the
+    /// manager, e.g. `admin role` then `Pauser`. Without descriptions it is the root role.
+    DeriveRole {
+        /// Manager address, in hex
+        #[arg(long, value_parser = parse_hex)]
+        manager: Bytes,
+        descriptions: Vec<String>,
+    },
+    /// Encodes a value as the `int256` data of a Beacon
+    EncodeData {
+        #[arg(allow_hyphen_values = true, value_parser = parse_int)]
+        value: Int,
+    },
+    /// Decodes the `int256` data of a Beacon
+    DecodeData {
+        #[arg(value_parser = parse_hex)]
+        data: Bytes,
+    },
+    /// Signs a value at a timestamp for a template the way Airnode does,
+    /// prints the arguments of `update_dapi_with_signed_data` for the Beacon as JSON
+    Sign {
+        #[arg(long, value_enum)]
+        scheme: Scheme,
+        /// File with the 32 byte secret key in hex, `-` to read it from stdin.
+        /// Without it the key is read from the `API3_SECRET_KEY` environment variable,
+        /// it is never passed as an argument so that it does not show up in the process list
+        #[arg(long)]
+        secret_key_file: Option<PathBuf>,
+        #[arg(long, value_parser = parse_bytes32)]
+        template_id: Bytes32,
+        /// Timestamp in seconds
+        #[arg(long)]
+        timestamp: u64,
+        #[arg(long, allow_hyphen_values = true, value_parser = parse_int)]
+        value: Int,
+    },
+    /// Verifies the signature of an Airnode over the template ID, the timestamp and the data
+    Verify {
+        #[arg(long, value_enum)]
+        scheme: Scheme,
+        /// Airnode address or ed25519 public key, in hex
+        #[arg(long, value_parser = parse_hex)]
+        airnode: Bytes,
+        #[arg(long, value_parser = parse_bytes32)]
+        template_id: Bytes32,
+        /// Timestamp in seconds
+        #[arg(long)]
+        timestamp: u64,
+        #[arg(long, value_parser = parse_hex)]
+        data: Bytes,
+        #[arg(long, value_parser = parse_hex)]
+        signature: Bytes,
+    },
+}
+
+/// The signature scheme of the Airnode
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Scheme {
+    /// NEAR, Solana and the other ed25519 chains
+    Ed25519,
+    /// EVM chains
+    Secp256k1,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    InvalidArgument(String),
+    Api3(api3_common::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::InvalidArgument(e) => write!(f, "{}", e),
+            CliError::Api3(e) => write!(f, "{} ({})", e, e.name()),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<api3_common::Error> for CliError {
+    fn from(e: api3_common::Error) -> Self {
+        CliError::Api3(e)
+    }
+}
+
+/// Runs the command, returns what it prints
+pub fn run(command: Command) -> Result<String, CliError> {
+    match command {
+        Command::DeriveBeaconId {
+            airnode,
+            template_id,
+        } => Ok(to_hex(&derive_beacon_id(airnode, template_id)?)),
+        Command::DeriveDapiId { beacon_ids } => Ok(to_hex(&derive_dapi_id(&beacon_ids))),
+        Command::NameHash { name } => {
+            Ok(to_hex(&keccak_packed(&[Token::FixedBytes(name.to_vec())])))
+        }
+        Command::DeriveRole {
+            manager,
+            descriptions,
+        } => {
+            let role = descriptions
+                .into_iter()
+                .fold(RoleDeriver::derive_root_role(&manager), |admin_role, d| {
+                    RoleDeriver::derive_role(admin_role, d)
+                });
+            Ok(to_hex(&role))
+        }
+        Command::EncodeData { value } => Ok(to_hex(&encode(&[Token::Int(value)]))),
+        Command::DecodeData { data } => Ok(format_int(decode_fulfillment_data(&data)?)),
+        Command::Sign {
+            scheme,
+            secret_key_file,
+            template_id,
+            timestamp,
+            value,
+        } => {
+            let secret_key = read_secret_key(secret_key_file.as_deref())?;
+            let timestamp = Timestamp::from_secs(timestamp);
+            let signed = match scheme {
+                Scheme::Ed25519 => SignedData::sign(
+                    &Ed25519Signer::from_secret_key(&secret_key)?,
+                    template_id,
+                    timestamp,
+                    value,
+                ),
+                Scheme::Secp256k1 => SignedData::sign(
+                    &Secp256k1Signer::from_secret_key(&secret_key)?,
+                    template_id,
+                    timestamp,
+                    value,
+                ),
+            };
+            Ok(signed_data_json(&signed)?)
+        }
+        Command::Verify {
+            scheme,
+            airnode,
+            template_id,
+            timestamp,
+            data,
+            signature,
+        } => {
+            let mut signed = SignedData::unsigned(airnode, template_id);
+            Int::from(timestamp).to_big_endian(&mut signed.timestamp);
+            signed.data = data;
+            signed.signature = signature;
+            let message = signed.message();
+            let valid = match scheme {
+                Scheme::Ed25519 => {
+                    Ed25519Verifier::verify(&signed.airnode, &message, &signed.signature)
+                }
+                Scheme::Secp256k1 => {
+                    Secp256k1Verifier::verify(&signed.airnode, &message, &signed.signature)
+                }
+            };
+            api3_common::ensure!(valid, api3_common::Error::InvalidSignature)?;
+            Ok(String::from("valid"))
+        }
+    }
+}
+
+/// Reads the hex secret key from the file, stdin for `-`, or `SECRET_KEY_ENV`
+fn read_secret_key(file: Option<&Path>) -> Result<Bytes, CliError> {
+    let secret_key = match file {
+        Some(path) if path == Path::new("-") => {
+            let mut secret_key = String::new();
+            std::io::stdin()
+                .read_to_string(&mut secret_key)
+                .map_err(|e| CliError::InvalidArgument(format!("cannot read stdin: {}", e)))?;
+            secret_key
+        }
+        Some(path) => std::fs::read_to_string(path).map_err(|e| {
+            CliError::InvalidArgument(format!("cannot read {}: {}", path.display(), e))
+        })?,
+        None => std::env::var(SECRET_KEY_ENV).map_err(|_| {
+            CliError::InvalidArgument(format!("pass --secret-key-file or set {}", SECRET_KEY_ENV))
+        })?,
+    };
+    // not `parse_hex`, its errors would print the key
+    hex::decode(secret_key.trim().trim_start_matches("0x"))
+        .map_err(|_| CliError::Api3(api3_common::Error::InvalidSecretKey))
+}
+
+fn signed_data_json(signed: &SignedData) -> Result<String, CliError> {
+    let json = serde_json::json!({
+        "beacon_id": to_hex(&signed.beacon_id()?),
+        "airnode": to_hex(&signed.airnode),
+        "template_id": to_hex(&signed.template_id),
+        "timestamp": to_hex(&signed.timestamp),
+        "data": to_hex(&signed.data),
+        "signature": to_hex(&signed.signature),
+    });
+    serde_json::to_string_pretty(&json).map_err(|e| CliError::InvalidArgument(e.to_string()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn parse_hex(s: &str) -> Result<Bytes, CliError> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+        .map_err(|e| CliError::InvalidArgument(format!("invalid hex {}: {}", s, e)))
+}
+
+fn parse_bytes32(s: &str) -> Result<Bytes32, CliError> {
+    Bytes32::try_from(parse_hex(s)?)
+        .map_err(|_| CliError::InvalidArgument(format!("{} is not 32 bytes", s)))
+}
+
+/// Names are `bytes32` strings, i.e. the UTF-8 bytes right padded with zeros
+fn parse_name(s: &str) -> Result<Bytes32, CliError> {
+    if s.starts_with("0x") && s.len() == 66 {
+        return parse_bytes32(s);
+    }
+    api3_common::ensure!(
+        s.len() < 32,
+        CliError::InvalidArgument(format!("{} is longer than 31 bytes", s))
+    )?;
+    let mut name = [0u8; 32];
+    name[..s.len()].copy_from_slice(s.as_bytes());
+    Ok(name)
+}
+
+/// Parses a decimal `int256`, negative values are kept in two's complement
+fn parse_int(s: &str) -> Result<Int, CliError> {
+    let invalid = || CliError::InvalidArgument(format!("{} is not a decimal int256", s));
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let magnitude = Int::from_dec_str(digits).map_err(|_| invalid())?;
+    let min = Int::one() << 255;
+    match negative {
+        false if magnitude < min => Ok(magnitude),
+        true if magnitude <= min => Ok(magnitude.overflowing_neg().0),
+        _ => Err(invalid()),
+    }
+}
+
+fn format_int(value: Int) -> String {
+    if value.bit(255) {
+        format!("-{}", value.overflowing_neg().0)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE_ID: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+    fn cli(args: &[&str]) -> Result<String, CliError> {
+        let cli = Cli::try_parse_from([&["api3-cli"], args].concat())
+            .map_err(|e| CliError::InvalidArgument(e.to_string()))?;
+        run(cli.command)
+    }
+
+    #[test]
+    fn derives_ids() {
+        assert_eq!(
+            cli(&[
+                "derive-beacon-id",
+                "--airnode",
+                "0x1d73899cc9fc3ad06a2c7f5bf26c8a4a76b42de905cb9b6ae96390355441a0ca",
+                "--template-id",
+                TEMPLATE_ID,
+            ])
+            .unwrap(),
+            "0xad1b5c75a8b8e0d7dbc56c1e28aee9fabe285ad8fb61a256ddabd4523bfb284a"
+        );
+        let beacon_ids = [[1u8; 32], [2u8; 32]];
+        assert_eq!(
+            cli(&[
+                "derive-dapi-id",
+                &to_hex(&beacon_ids[0]),
+                &to_hex(&beacon_ids[1])
+            ])
+            .unwrap(),
+            to_hex(&derive_dapi_id(&beacon_ids))
+        );
+        assert!(cli(&["derive-dapi-id", &to_hex(&beacon_ids[0])]).is_err());
+        assert!(matches!(
+            cli(&[
+                "derive-beacon-id",
+                "--airnode",
+                "0x00",
+                "--template-id",
+                TEMPLATE_ID
+            ]),
+            Err(CliError::Api3(api3_common::Error::AirnodeIdZero))
+        ));
+    }
+
+    #[test]
+    fn hashes_names() {
+        let mut name = [0u8; 32];
+        name[..7].copy_from_slice(b"ETH/USD");
+        let name_hash = to_hex(&keccak_packed(&[Token::FixedBytes(name.to_vec())]));
+        assert_eq!(cli(&["name-hash", "ETH/USD"]).unwrap(), name_hash);
+        assert_eq!(cli(&["name-hash", &to_hex(&name)]).unwrap(), name_hash);
+        assert!(cli(&["name-hash", &"a".repeat(32)]).is_err());
+    }
+
+    #[test]
+    fn derives_roles_from_the_manager() {
+        let manager = [7u8; 20];
+        let root_role = RoleDeriver::derive_root_role(&manager);
+        let admin_role = RoleDeriver::derive_role(root_role, String::from("admin role"));
+        let pauser_role = RoleDeriver::derive_role(admin_role, String::from("Pauser"));
+
+        let manager = to_hex(&manager);
+        assert_eq!(
+            cli(&["derive-role", "--manager", &manager]).unwrap(),
+            to_hex(&root_role)
+        );
+        assert_eq!(
+            cli(&["derive-role", "--manager", &manager, "admin role", "Pauser"]).unwrap(),
+            to_hex(&pauser_role)
+        );
+    }
+
+    #[test]
+    fn encodes_and_decodes_data() {
+        let minus_one = cli(&["encode-data", "-1"]).unwrap();
+        assert_eq!(minus_one, to_hex(&[0xff; 32]));
+        assert_eq!(cli(&["decode-data", &minus_one]).unwrap(), "-1");
+
+        let value = "123456789012345678901234567890";
+        let data = cli(&["encode-data", value]).unwrap();
+        assert_eq!(cli(&["decode-data", &data]).unwrap(), value);
+
+        let min = "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+        let data = cli(&["encode-data", min]).unwrap();
+        assert_eq!(cli(&["decode-data", &data]).unwrap(), min);
+        assert!(cli(&["encode-data", &min[1..]]).is_err());
+        assert!(matches!(
+            cli(&["decode-data", "0x01"]),
+            Err(CliError::Api3(api3_common::Error::InvalidDataLength))
+        ));
+    }
+
+    /// Writes the secret key to a file for `--secret-key-file`
+    fn secret_key_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("api3-cli-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_the_secret_key_from_a_file_or_the_environment() {
+        let sign = |args: &[&str]| {
+            cli(&[
+                &["sign", "--scheme", "ed25519"],
+                args,
+                &[
+                    "--template-id",
+                    TEMPLATE_ID,
+                    "--timestamp",
+                    "1650000000",
+                    "--value",
+                    "1",
+                ],
+            ]
+            .concat())
+        };
+        let secret_key = to_hex(&[3u8; 32]);
+        let file = secret_key_file("env", &format!("{}\n", secret_key));
+        let from_file = sign(&["--secret-key-file", file.to_str().unwrap()]).unwrap();
+
+        // the key is not accepted as an argument
+        assert!(sign(&["--secret-key", &secret_key]).is_err());
+
+        std::env::set_var(SECRET_KEY_ENV, &secret_key);
+        assert_eq!(sign(&[]).unwrap(), from_file);
+        std::env::set_var(SECRET_KEY_ENV, "0xzz");
+        assert!(matches!(
+            sign(&[]),
+            Err(CliError::Api3(api3_common::Error::InvalidSecretKey))
+        ));
+        std::env::remove_var(SECRET_KEY_ENV);
+        assert!(matches!(sign(&[]), Err(CliError::InvalidArgument(_))));
+
+        let missing = file.with_extension("missing");
+        assert!(sign(&["--secret-key-file", missing.to_str().unwrap()]).is_err());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn signs_and_verifies() {
+        for (scheme, secret_key) in [("ed25519", [1u8; 32]), ("secp256k1", [2u8; 32])] {
+            let file = secret_key_file(scheme, &to_hex(&secret_key));
+            let signed = cli(&[
+                "sign",
+                "--scheme",
+                scheme,
+                "--secret-key-file",
+                file.to_str().unwrap(),
+                "--template-id",
+                TEMPLATE_ID,
+                "--timestamp",
+                "1650000000",
+                "--value",
+                "-5",
+            ])
+            .unwrap();
+            let signed: serde_json::Value = serde_json::from_str(&signed).unwrap();
+            let field = |k: &str| signed[k].as_str().unwrap().to_string();
+            assert_eq!(
+                field("beacon_id"),
+                cli(&[
+                    "derive-beacon-id",
+                    "--airnode",
+                    &field("airnode"),
+                    "--template-id",
+                    TEMPLATE_ID
+                ])
+                .unwrap()
+            );
+            assert_eq!(cli(&["decode-data", &field("data")]).unwrap(), "-5");
+
+            let verify = |data: &str| {
+                cli(&[
+                    "verify",
+                    "--scheme",
+                    scheme,
+                    "--airnode",
+                    &field("airnode"),
+                    "--template-id",
+                    TEMPLATE_ID,
+                    "--timestamp",
+                    "1650000000",
+                    "--data",
+                    data,
+                    "--signature",
+                    &field("signature"),
+                ])
+            };
+            assert_eq!(verify(&field("data")).unwrap(), "valid");
+            assert!(matches!(
+                verify(&cli(&["encode-data", "5"]).unwrap()),
+                Err(CliError::Api3(api3_common::Error::InvalidSignature))
+            ));
+            std::fs::remove_file(file).unwrap();
+        }
+    }
+}
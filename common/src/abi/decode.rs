@@ -1,6 +1,7 @@
 //! ABI decoder.
 
 use crate::abi::{ParamType, Token, Word};
+use crate::prelude::*;
 use crate::Error;
 
 #[derive(Debug)]
@@ -14,12 +15,8 @@ fn as_usize(slice: &Word) -> Result<usize, Error> {
         return Err(Error::InvalidData);
     }
 
-    let result = ((slice[28] as usize) << 24)
-        + ((slice[29] as usize) << 16)
-        + ((slice[30] as usize) << 8)
-        + (slice[31] as usize);
-
-    Ok(result)
+    let result = u32::from_be_bytes([slice[28], slice[29], slice[30], slice[31]]);
+    usize::try_from(result).map_err(|_| Error::InvalidData)
 }
 
 /// Decodes ABI compliant vector of bytes into vector of tokens described by types param.
@@ -48,27 +45,23 @@ pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, Error> {
 }
 
 fn peek(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
-    if offset + len > data.len() {
-        Err(Error::InvalidData)
-    } else {
-        Ok(&data[offset..(offset + len)])
-    }
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(Error::InvalidData)
 }
 
 fn peek_32_bytes(data: &[u8], offset: usize) -> Result<Word, Error> {
-    peek(data, offset, 32).map(|x| {
-        let mut out: Word = [0u8; 32];
-        out.copy_from_slice(&x[0..32]);
-        out
-    })
+    peek(data, offset, 32).and_then(|x| Word::try_from(x).map_err(|_| Error::InvalidData))
 }
 
 fn take_bytes(data: &[u8], offset: usize, len: usize) -> Result<Vec<u8>, Error> {
-    if offset + len > data.len() {
-        Err(Error::InvalidData)
-    } else {
-        Ok((data[offset..(offset + len)]).to_vec())
-    }
+    peek(data, offset, len).map(<[u8]>::to_vec)
+}
+
+/// The offset of the next head, 32 bytes after `offset`
+fn next_offset(offset: usize) -> Result<usize, Error> {
+    offset.checked_add(32).ok_or(Error::InvalidData)
 }
 
 fn decode_param(param: &ParamType, data: &[u8], offset: usize) -> Result<DecodeResult, Error> {
@@ -79,7 +72,7 @@ fn decode_param(param: &ParamType, data: &[u8], offset: usize) -> Result<DecodeR
             address.copy_from_slice(&slice[12..]);
             let result = DecodeResult {
                 token: Token::Address(address),
-                new_offset: offset + 32,
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
@@ -87,7 +80,7 @@ fn decode_param(param: &ParamType, data: &[u8], offset: usize) -> Result<DecodeR
             let slice = peek_32_bytes(data, offset)?;
             let result = DecodeResult {
                 token: Token::Uint(slice.into()),
-                new_offset: offset + 32,
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
@@ -95,7 +88,7 @@ fn decode_param(param: &ParamType, data: &[u8], offset: usize) -> Result<DecodeR
             let slice = peek_32_bytes(data, offset)?;
             let result = DecodeResult {
                 token: Token::Int(slice.into()),
-                new_offset: offset + 32,
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
@@ -105,31 +98,31 @@ fn decode_param(param: &ParamType, data: &[u8], offset: usize) -> Result<DecodeR
             let bytes = take_bytes(data, offset, len)?;
             let result = DecodeResult {
                 token: Token::FixedBytes(bytes),
-                new_offset: offset + 32,
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
         ParamType::Bytes => {
             let dynamic_offset = as_usize(&peek_32_bytes(data, offset)?)?;
             let len = as_usize(&peek_32_bytes(data, dynamic_offset)?)?;
-            let bytes = take_bytes(data, dynamic_offset + 32, len)?;
+            let bytes = take_bytes(data, next_offset(dynamic_offset)?, len)?;
             let result = DecodeResult {
                 token: Token::Bytes(bytes),
-                new_offset: offset + 32,
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
         ParamType::String => {
             let dynamic_offset = as_usize(&peek_32_bytes(data, offset)?)?;
             let len = as_usize(&peek_32_bytes(data, dynamic_offset)?)?;
-            let bytes = take_bytes(data, dynamic_offset + 32, len)?;
+            let bytes = take_bytes(data, next_offset(dynamic_offset)?, len)?;
             let result = DecodeResult {
                 // NOTE: We're decoding strings using lossy UTF-8 decoding to
                 // prevent invalid strings written into contracts by either users or
                 // Solidity bugs from causing graph-node to fail decoding event
                 // data.
-                token: Token::String(String::from_utf8_lossy(&*bytes).into()),
-                new_offset: offset + 32,
+                token: Token::String(String::from_utf8_lossy(&bytes).into()),
+                new_offset: next_offset(offset)?,
             };
             Ok(result)
         }
@@ -220,7 +213,7 @@ mod tests {
             )
             .unwrap(),
             &[
-                Token::Address(hex!("8497afefdc5ac170a664a231f6efb25526ef813f").into()),
+                Token::Address(hex!("8497afefdc5ac170a664a231f6efb25526ef813f")),
                 Token::FixedBytes([0u8; 32].to_vec()),
                 Token::FixedBytes([0u8; 4].to_vec()),
                 Token::String("0x0000001F".into()),
@@ -1,6 +1,7 @@
 //! ABI encoder.
 
 use crate::abi::types::{Token, Word};
+use crate::prelude::*;
 use crate::Bytes;
 
 /// Converts a u32 to a right aligned array of 32 bytes.
@@ -17,25 +18,14 @@ fn pad_bytes(bytes: &[u8]) -> Vec<Word> {
 }
 
 fn pad_fixed_bytes(bytes: &[u8]) -> Vec<Word> {
-    let len = (bytes.len() + 31) / 32;
-    let mut result = Vec::with_capacity(len);
-    for i in 0..len {
-        let mut padded = [0u8; 32];
-
-        let to_copy = match i == len - 1 {
-            false => 32,
-            true => match bytes.len() % 32 {
-                0 => 32,
-                x => x,
-            },
-        };
-
-        let offset = 32 * i;
-        padded[..to_copy].copy_from_slice(&bytes[offset..offset + to_copy]);
-        result.push(padded);
-    }
-
-    result
+    bytes
+        .chunks(32)
+        .map(|chunk| {
+            let mut padded = [0u8; 32];
+            padded.iter_mut().zip(chunk).for_each(|(p, b)| *p = *b);
+            padded
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -47,7 +37,7 @@ enum Mediate {
 impl Mediate {
     fn head_len(&self) -> u32 {
         match *self {
-            Mediate::Raw(ref raw) => 32 * raw.len() as u32,
+            Mediate::Raw(ref raw) => (raw.len() as u32).saturating_mul(32),
             Mediate::Prefixed(_) => 32,
         }
     }
@@ -55,7 +45,7 @@ impl Mediate {
     fn tail_len(&self) -> u32 {
         match *self {
             Mediate::Raw(_) => 0,
-            Mediate::Prefixed(ref pre) => pre.len() as u32 * 32,
+            Mediate::Prefixed(ref pre) => (pre.len() as u32).saturating_mul(32),
         }
     }
 
@@ -75,18 +65,20 @@ impl Mediate {
 }
 
 fn encode_head_tail(mediates: &[Mediate]) -> Vec<Word> {
-    let heads_len = mediates.iter().fold(0, |acc, m| acc + m.head_len());
+    let heads_len = mediates
+        .iter()
+        .fold(0u32, |acc, m| acc.saturating_add(m.head_len()));
 
     let (mut result, len) = mediates.iter().fold(
         (Vec::with_capacity(heads_len as usize), heads_len),
         |(mut acc, offset), m| {
             acc.extend(m.head(offset));
-            (acc, offset + m.tail_len())
+            (acc, offset.saturating_add(m.tail_len()))
         },
     );
 
     let tails = mediates.iter().fold(
-        Vec::with_capacity((len - heads_len) as usize),
+        Vec::with_capacity(len.saturating_sub(heads_len) as usize),
         |mut acc, m| {
             acc.extend(m.tail());
             acc
@@ -130,7 +122,7 @@ mod tests {
 
     #[test]
     fn encode_address() {
-        let address = Token::Address([0x11u8; 20].into());
+        let address = Token::Address([0x11u8; 20]);
         let encoded = encode(&[address]);
         let expected = hex!("0000000000000000000000001111111111111111111111111111111111111111");
         assert_eq!(encoded, expected);
@@ -138,8 +130,8 @@ mod tests {
 
     #[test]
     fn encode_two_addresses() {
-        let address1 = Token::Address([0x11u8; 20].into());
-        let address2 = Token::Address([0x22u8; 20].into());
+        let address1 = Token::Address([0x11u8; 20]);
+        let address2 = Token::Address([0x22u8; 20]);
         let encoded = encode(&[address1, address2]);
         let expected = hex!(
             "
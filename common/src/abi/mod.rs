@@ -2,6 +2,7 @@ mod decode;
 mod encode;
 mod types;
 
+use crate::prelude::*;
 use tiny_keccak::{Hasher, Keccak};
 
 pub use crate::abi::decode::*;
@@ -93,7 +94,7 @@ mod tests {
 
     #[test]
     fn keccak_works() {
-        let bytes = keccak256(&vec![1, 2, 3]);
+        let bytes = keccak256(&[1, 2, 3]);
         assert_eq!(
             hex::encode(bytes),
             "f1885eda54b7a053318cd41e2093220dab15d65381b1157a3633a83bfd5c9239"
@@ -1,9 +1,11 @@
 #![allow(clippy::assign_op_pattern)]
 #![allow(clippy::ptr_offset_with_cast)]
+#![allow(clippy::manual_div_ceil)]
 
+use crate::prelude::*;
 use crate::{Bytes, Bytes32};
+use borsh::maybestd::io;
 use borsh::{BorshDeserialize, BorshSerialize};
-use std::io;
 use uint::construct_uint;
 
 pub type Address = [u8; 20];
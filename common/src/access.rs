@@ -1,10 +1,12 @@
 use crate::abi::Token;
-use crate::{ensure, keccak_packed, Bytes32, Error, Zero};
+use crate::prelude::*;
+use crate::{ensure, keccak_packed, Bytes32, Error, Timestamp, TimestampChecker, Zero};
 
 /// Roles that are known at dev time.
 pub enum StaticRole {
     UnlimitedReaderRole,
     NameSetterRole,
+    PauserRole,
 }
 
 pub trait AccessControlRegistryAdminnedWithManager {
@@ -26,6 +28,7 @@ pub trait AccessControlRegistry: AccessControlRegistryAdminnedWithManager {
     const DEFAULT_ADMIN_ROLE: Bytes32 = [0; 32];
     const NAME_SETTER_ROLE_DESCRIPTION: &'static str = "Name setter";
     const UNLIMITED_READER_ROLE_DESCRIPTION: &'static str = "Unlimited reader";
+    const PAUSER_ROLE_DESCRIPTION: &'static str = "Pauser";
 
     /// Find the role by its name. Not in the original solidity contract
     /// Just for making it work in Rust
@@ -33,11 +36,15 @@ pub trait AccessControlRegistry: AccessControlRegistryAdminnedWithManager {
         match role {
             StaticRole::UnlimitedReaderRole => self.derive_role(
                 self.derive_admin_role(self.manager()),
-                Self::UNLIMITED_READER_ROLE_DESCRIPTION.parse().unwrap(),
+                Self::UNLIMITED_READER_ROLE_DESCRIPTION.to_string(),
             ),
             StaticRole::NameSetterRole => self.derive_role(
                 self.derive_admin_role(self.manager()),
-                Self::NAME_SETTER_ROLE_DESCRIPTION.parse().unwrap(),
+                Self::NAME_SETTER_ROLE_DESCRIPTION.to_string(),
+            ),
+            StaticRole::PauserRole => self.derive_role(
+                self.derive_admin_role(self.manager()),
+                Self::PAUSER_ROLE_DESCRIPTION.to_string(),
             ),
         }
     }
@@ -79,6 +86,21 @@ pub trait AccessControlRegistry: AccessControlRegistryAdminnedWithManager {
     /// `role` Role to be renounced
     /// `account` Account to renounce the role
     fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error>;
+    /// Moves the grant of `from` to `who` over to `to`, see `migrate_role_members`.
+    /// Registries with expiring grants override it with
+    /// `migrate_role_grant_with_expiration` so that the grant keeps its time limit.
+    /// `from` The role to revoke
+    /// `to` The role to grant
+    /// `who` The address to move the grant of
+    fn migrate_role_grant(
+        &mut self,
+        from: &Bytes32,
+        to: &Bytes32,
+        who: &Self::Address,
+    ) -> Result<(), Error> {
+        self.revoke_role(from, who)?;
+        self.grant_role(to, who)
+    }
     /// Initializes the manager by initializing its root role and
     /// granting it to them
     /// Anyone can initialize a manager. An uninitialized manager
@@ -153,6 +175,181 @@ pub trait AccessControlRegistry: AccessControlRegistryAdminnedWithManager {
     fn derive_role(&self, admin_role: Bytes32, description: String) -> Bytes32 {
         RoleDeriver::derive_role(admin_role, description)
     }
+    /// Moves the members of a role derived from the admin role of
+    /// `previous_manager` to the role with the same description derived from
+    /// the admin role of the current manager. Roles derived from the manager
+    /// change when the manager is transferred, this re-creates them under the
+    /// new manager. Only the current manager can migrate roles.
+    /// Accounts that are not members of the previous role are skipped.
+    /// The admin role of the previous manager is migrated to the admin role of
+    /// the current manager, any other admin of the role is kept as is.
+    /// `previous_manager` The manager the role was derived from
+    /// `description` Human-readable description of the role
+    /// `members` Accounts to migrate
+    /// `msg_sender` The message sender address
+    fn migrate_role_members(
+        &mut self,
+        previous_manager: &Self::Address,
+        description: String,
+        members: &[Self::Address],
+        msg_sender: &Self::Address,
+    ) -> Result<Bytes32, Error> {
+        ensure!(self.manager() == msg_sender, Error::NotAuthorized)?;
+        ensure!(!description.is_empty(), Error::RoleDescriptionEmpty)?;
+        let previous_role = self.derive_role(
+            self.derive_admin_role(previous_manager),
+            description.clone(),
+        );
+        let admin_role = self.derive_admin_role(self.manager());
+        let role = self.derive_role(admin_role, description);
+        ensure!(previous_role != role, Error::SameManager)?;
+
+        match self.get_role_admin(&previous_role) {
+            Some(r) if r == self.derive_admin_role(previous_manager) => {
+                self.set_role_admin(&role, admin_role)?
+            }
+            Some(r) if r != Self::DEFAULT_ADMIN_ROLE => self.set_role_admin(&role, r)?,
+            _ => {}
+        }
+        for member in members {
+            if self.has_role(&previous_role, member) {
+                self.migrate_role_grant(&previous_role, &role, member)?;
+            }
+        }
+        Ok(role)
+    }
+}
+
+/// Enumerable role membership, same as `AccessControlEnumerable` in openzeppelin.
+/// Lets anyone audit who holds a role, e.g. `StaticRole::UnlimitedReaderRole`.
+pub trait AccessControlRegistryEnumerable: AccessControlRegistry {
+    /// Returns the number of accounts that have `role`
+    /// `role` The role to check
+    fn get_role_member_count(&self, role: &Bytes32) -> usize;
+    /// Returns one of the accounts that have `role`, `None` if `index` is not
+    /// lower than `get_role_member_count`. Members are not stored in any
+    /// particular order and the order may change when roles are granted or revoked.
+    /// `role` The role to check
+    /// `index` Index of the member
+    fn get_role_member(&self, role: &Bytes32, index: usize) -> Option<Self::Address>;
+    /// Returns the roles `account` has
+    /// `account` The address to check
+    fn roles_of(&self, account: &Self::Address) -> Vec<Bytes32>;
+}
+
+/// Role grants that expire automatically, e.g. for temporary name setters or
+/// auditors. Implementations are expected to return `false` from `has_role`
+/// once `role_is_expired` is `true`, and to clear the expiration in `grant_role`
+/// and `revoke_role` so that a plain grant is permanent.
+pub trait AccessControlRegistryWithExpiration: AccessControlRegistry + TimestampChecker {
+    /// Returns the timestamp the grant of `role` to `who` expires at, `None`
+    /// if the grant does not expire
+    /// `role` The role to check
+    /// `who` The address to check
+    fn role_expiration(&self, role: &Bytes32, who: &Self::Address) -> Option<Timestamp>;
+    /// Sets the expiration of the grant of `role` to `who`, `None` removes it
+    /// `role` The role granted
+    /// `who` The address granted the role
+    /// `expiration_timestamp` Timestamp the grant expires at
+    fn set_role_expiration(
+        &mut self,
+        role: &Bytes32,
+        who: &Self::Address,
+        expiration_timestamp: Option<Timestamp>,
+    );
+    /// Grants `role` to `who` until `expiration_timestamp`
+    /// `role` The role to grant
+    /// `who` The address to grant role
+    /// `expiration_timestamp` Timestamp the grant expires at
+    fn grant_role_until(
+        &mut self,
+        role: &Bytes32,
+        who: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) -> Result<(), Error> {
+        ensure!(
+            expiration_timestamp > self.current_timestamp(),
+            Error::InvalidTimestamp
+        )?;
+        self.grant_role(role, who)?;
+        self.set_role_expiration(role, who, Some(expiration_timestamp));
+        Ok(())
+    }
+    /// Returns if the grant of `role` to `who` has expired
+    /// `role` The role to check
+    /// `who` The address to check
+    fn role_is_expired(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        matches!(
+            self.role_expiration(role, who),
+            Some(e) if e <= self.current_timestamp()
+        )
+    }
+}
+
+/// `AccessControlRegistry::migrate_role_grant` of registries with expiring
+/// grants. A grant that expires is granted again with `grant_role_until`, so
+/// moving it to the role of a new manager does not make it permanent.
+///
+/// # Arguments
+///
+/// * `registry` The access control registry
+/// * `from` The role to revoke
+/// * `to` The role to grant
+/// * `who` The address to move the grant of
+pub fn migrate_role_grant_with_expiration<R: AccessControlRegistryWithExpiration + ?Sized>(
+    registry: &mut R,
+    from: &Bytes32,
+    to: &Bytes32,
+    who: &R::Address,
+) -> Result<(), Error> {
+    let expiration = registry.role_expiration(from, who);
+    registry.revoke_role(from, who)?;
+    match expiration {
+        Some(expiration_timestamp) => registry.grant_role_until(to, who, expiration_timestamp),
+        None => registry.grant_role(to, who),
+    }
+}
+
+/// Proposes `new_manager` as the next manager. The transfer only takes effect
+/// once the proposed account calls `accept_manager`, so the manager cannot be
+/// handed to an account nobody controls. Proposing again replaces the pending
+/// manager. Returns the manager to be stored as pending.
+///
+/// # Arguments
+///
+/// * `manager` The current manager
+/// * `new_manager` The proposed manager
+/// * `msg_sender` Address of who sent the transaction
+pub fn propose_manager<Address: AsRef<[u8]> + Zero + PartialEq>(
+    manager: &Address,
+    new_manager: Address,
+    msg_sender: &Address,
+) -> Result<Address, Error> {
+    ensure!(manager == msg_sender, Error::NotAuthorized)?;
+    ensure!(
+        !new_manager.is_zero() && new_manager != *manager,
+        Error::InvalidAddress
+    )?;
+    Ok(new_manager)
+}
+
+/// Accepts a pending manager transfer. Only the pending manager can accept.
+/// Returns the new manager. The caller is expected to store it, clear the
+/// pending manager and initialize the new manager in its registry, after which
+/// roles derived from the previous manager can be moved over with
+/// `AccessControlRegistry::migrate_role_members`.
+///
+/// # Arguments
+///
+/// * `pending_manager` The manager proposed with `propose_manager`, if any
+/// * `msg_sender` Address of who sent the transaction
+pub fn accept_manager<Address: PartialEq>(
+    pending_manager: Option<Address>,
+    msg_sender: &Address,
+) -> Result<Address, Error> {
+    let pending_manager = pending_manager.ok_or(Error::PendingManagerNotFound)?;
+    ensure!(pending_manager == *msg_sender, Error::NotAuthorized)?;
+    Ok(pending_manager)
 }
 
 /// Contract that implements the AccessControlRegistry role derivation logic
@@ -195,4 +392,232 @@ impl RoleDeriver {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[derive(Default)]
+    struct MapRegistry {
+        manager: Bytes32,
+        members: HashSet<(Bytes32, Bytes32)>,
+        admins: HashMap<Bytes32, Bytes32>,
+        expirations: HashMap<(Bytes32, Bytes32), Timestamp>,
+        now: Timestamp,
+    }
+
+    impl AccessControlRegistryAdminnedWithManager for MapRegistry {
+        type Address = Bytes32;
+        fn manager(&self) -> &Self::Address {
+            &self.manager
+        }
+        fn admin_role_description(&self) -> String {
+            String::from("admin")
+        }
+        fn admin_role_description_hash(&self) -> Bytes32 {
+            keccak_packed(&[Token::String(self.admin_role_description())])
+        }
+        fn admin_role(&self) -> Bytes32 {
+            self.derive_admin_role(&self.manager)
+        }
+    }
+
+    impl AccessControlRegistry for MapRegistry {
+        fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+            self.members.contains(&(*role, *who)) && !self.role_is_expired(role, who)
+        }
+        fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+            self.members.insert((*role, *who));
+            self.expirations.remove(&(*role, *who));
+            Ok(())
+        }
+        fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+            self.admins.get(role).copied()
+        }
+        fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+            self.admins.insert(*role, role_admin);
+            Ok(())
+        }
+        fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+            self.revoke_role(role, account)
+        }
+        fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+            self.members.remove(&(*role, *account));
+            self.expirations.remove(&(*role, *account));
+            Ok(())
+        }
+        fn migrate_role_grant(
+            &mut self,
+            from: &Bytes32,
+            to: &Bytes32,
+            who: &Self::Address,
+        ) -> Result<(), Error> {
+            migrate_role_grant_with_expiration(self, from, to, who)
+        }
+    }
+
+    impl TimestampChecker for MapRegistry {
+        fn current_timestamp(&self) -> Timestamp {
+            self.now
+        }
+    }
+
+    impl AccessControlRegistryWithExpiration for MapRegistry {
+        fn role_expiration(&self, role: &Bytes32, who: &Self::Address) -> Option<Timestamp> {
+            self.expirations.get(&(*role, *who)).copied()
+        }
+        fn set_role_expiration(
+            &mut self,
+            role: &Bytes32,
+            who: &Self::Address,
+            expiration_timestamp: Option<Timestamp>,
+        ) {
+            match expiration_timestamp {
+                Some(e) => self.expirations.insert((*role, *who), e),
+                None => self.expirations.remove(&(*role, *who)),
+            };
+        }
+    }
+
+    const OLD: Bytes32 = [1u8; 32];
+    const NEW: Bytes32 = [2u8; 32];
+    const MEMBER: Bytes32 = [3u8; 32];
+
+    #[test]
+    fn manager_transfer_works() {
+        assert!(matches!(
+            propose_manager(&OLD, NEW, &NEW),
+            Err(Error::NotAuthorized)
+        ));
+        assert!(matches!(
+            propose_manager(&OLD, [0u8; 32], &OLD),
+            Err(Error::InvalidAddress)
+        ));
+        let pending = propose_manager(&OLD, NEW, &OLD).unwrap();
+        assert!(matches!(
+            accept_manager(Some(pending), &OLD),
+            Err(Error::NotAuthorized)
+        ));
+        assert!(matches!(
+            accept_manager::<Bytes32>(None, &NEW),
+            Err(Error::PendingManagerNotFound)
+        ));
+        assert_eq!(accept_manager(Some(pending), &NEW).unwrap(), NEW);
+    }
+
+    #[test]
+    fn migrate_role_members_works() {
+        let mut registry = MapRegistry {
+            manager: OLD,
+            ..Default::default()
+        };
+        let previous_role = registry.find_static_role(StaticRole::PauserRole);
+        registry.grant_role(&previous_role, &MEMBER).unwrap();
+
+        registry.manager = NEW;
+        let description = String::from(MapRegistry::PAUSER_ROLE_DESCRIPTION);
+        assert!(matches!(
+            registry.migrate_role_members(&OLD, description.clone(), &[MEMBER], &OLD),
+            Err(Error::NotAuthorized)
+        ));
+        assert!(matches!(
+            registry.migrate_role_members(&NEW, description.clone(), &[MEMBER], &NEW),
+            Err(Error::SameManager)
+        ));
+        let role = registry
+            .migrate_role_members(&OLD, description, &[MEMBER, OLD], &NEW)
+            .unwrap();
+        assert_eq!(role, registry.find_static_role(StaticRole::PauserRole));
+        assert!(registry.has_role(&role, &MEMBER));
+        assert!(!registry.has_role(&previous_role, &MEMBER));
+        assert!(!registry.has_role(&role, &OLD));
+    }
+
+    #[test]
+    fn migrate_role_members_keeps_expirations() {
+        let mut registry = MapRegistry {
+            manager: OLD,
+            now: Timestamp::from_secs(100),
+            ..Default::default()
+        };
+        let other = [4u8; 32];
+        let previous_role = registry.find_static_role(StaticRole::PauserRole);
+        registry
+            .grant_role_until(&previous_role, &MEMBER, Timestamp::from_secs(200))
+            .unwrap();
+        registry.grant_role(&previous_role, &other).unwrap();
+
+        registry.manager = NEW;
+        let description = String::from(MapRegistry::PAUSER_ROLE_DESCRIPTION);
+        let role = registry
+            .migrate_role_members(&OLD, description, &[MEMBER, other], &NEW)
+            .unwrap();
+        assert!(registry.has_role(&role, &MEMBER));
+        assert_eq!(
+            registry.role_expiration(&role, &MEMBER),
+            Some(Timestamp::from_secs(200))
+        );
+        assert_eq!(registry.role_expiration(&previous_role, &MEMBER), None);
+        assert_eq!(registry.role_expiration(&role, &other), None);
+
+        registry.now = Timestamp::from_secs(200);
+        assert!(!registry.has_role(&role, &MEMBER));
+        assert!(registry.has_role(&role, &other));
+    }
+
+    #[test]
+    fn migrate_role_members_migrates_the_admin() {
+        let mut registry = MapRegistry {
+            manager: OLD,
+            ..Default::default()
+        };
+        let custom_admin = [4u8; 32];
+        let adminned = registry.derive_role(registry.admin_role(), String::from("adminned"));
+        let custom = registry.derive_role(registry.admin_role(), String::from("custom"));
+        registry
+            .set_role_admin(&adminned, registry.admin_role())
+            .unwrap();
+        registry.set_role_admin(&custom, custom_admin).unwrap();
+
+        registry.manager = NEW;
+        let adminned = registry
+            .migrate_role_members(&OLD, String::from("adminned"), &[], &NEW)
+            .unwrap();
+        assert_eq!(
+            registry.get_role_admin(&adminned),
+            Some(registry.admin_role())
+        );
+        let custom = registry
+            .migrate_role_members(&OLD, String::from("custom"), &[], &NEW)
+            .unwrap();
+        assert_eq!(registry.get_role_admin(&custom), Some(custom_admin));
+    }
+
+    #[test]
+    fn grant_role_until_expires() {
+        let role = [4u8; 32];
+        let mut registry = MapRegistry {
+            now: Timestamp::from_secs(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            registry.grant_role_until(&role, &MEMBER, Timestamp::from_secs(100)),
+            Err(Error::InvalidTimestamp)
+        ));
+        registry
+            .grant_role_until(&role, &MEMBER, Timestamp::from_secs(200))
+            .unwrap();
+        assert!(registry.has_role(&role, &MEMBER));
+        assert_eq!(
+            registry.role_expiration(&role, &MEMBER),
+            Some(Timestamp::from_secs(200))
+        );
+
+        registry.now = Timestamp::from_secs(200);
+        assert!(!registry.has_role(&role, &MEMBER));
+
+        // a plain grant is permanent
+        registry.grant_role(&role, &MEMBER).unwrap();
+        assert!(registry.has_role(&role, &MEMBER));
+        assert_eq!(registry.role_expiration(&role, &MEMBER), None);
+    }
+}
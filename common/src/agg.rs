@@ -1,21 +1,53 @@
-use crate::abi::Int;
-use crate::DataPoint;
-use std::ops::Div;
+use crate::abi::{Int, U256};
+use crate::{DataPoint, Error, Timestamp};
 
 /// The Manager for handling multiple datapoints
 pub struct Aggregator;
 
 impl Aggregator {
-    pub fn agg(datapoints: &[DataPoint]) -> DataPoint {
-        let value = Int::from(0);
-        let timestamp = 0u32;
+    /// Averages the values and timestamps of the data points. Values are
+    /// summed as unsigned integers, like `median` compares them.
+    /// Fails with `InvalidDataLength` if there are no data points and with
+    /// `InvalidData` if the sum of the values overflows.
+    pub fn agg(datapoints: &[DataPoint]) -> Result<DataPoint, Error> {
+        let l = datapoints.len();
+        if l == 0 {
+            return Err(Error::InvalidDataLength);
+        }
+        let mut value = Int::from(0);
+        let mut timestamp = U256::from(0);
         for d in datapoints {
-            value.checked_add(d.value).expect("value overflow");
-            timestamp
-                .checked_add(d.timestamp)
-                .expect("timestamp overflow");
+            value = value.checked_add(d.value).ok_or(Error::InvalidData)?;
+            timestamp = timestamp
+                .checked_add(U256::from(d.timestamp.as_secs()))
+                .ok_or(Error::InvalidData)?;
         }
-        let l = datapoints.len();
-        DataPoint::new(value.div(l), timestamp / l as u32)
+        let l = U256::from(l);
+        let average = |sum: U256| sum.checked_div(l).ok_or(Error::InvalidDataLength);
+        Ok(DataPoint::new(
+            average(value)?,
+            Timestamp::from_secs_u256(average(timestamp)?)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_data_points() {
+        let datapoints = [
+            DataPoint::new(Int::from(10), Timestamp::from_secs(100)),
+            DataPoint::new(Int::from(20), Timestamp::from_secs(200)),
+        ];
+        assert_eq!(
+            Aggregator::agg(&datapoints).unwrap(),
+            DataPoint::new(Int::from(15), Timestamp::from_secs(150))
+        );
+        assert!(matches!(
+            Aggregator::agg(&[]),
+            Err(Error::InvalidDataLength)
+        ));
     }
 }
@@ -1,10 +1,14 @@
 use crate::abi::{decode, encode, encode_packed, keccak256, Int, ParamType, Token, Uint, U256};
 use crate::access::AccessControlRegistry;
+use crate::prelude::*;
 use crate::whitelist::Whitelist;
-use crate::{ensure, keccak_packed, median, Bytes, Bytes32, DataPoint, Error, StaticRole, Zero};
+use crate::{
+    ensure, ensure_data_feed_not_paused, keccak_packed, median, Bytes, Bytes32, DapiUpdateError,
+    DataPoint, Error, FeedStatus, StaticRole, Timestamp, Zero,
+};
 
-const ONE_HOUR_IN_SECONDS: u32 = 3600;
-const FIFTEEN_MINUTES_IN_SECONDS: u32 = 900;
+const ONE_HOUR_IN_SECONDS: u64 = 3600;
+const FIFTEEN_MINUTES_IN_SECONDS: u64 = 900;
 
 /// Generic storage trait. Used for the common processing logic so that each chain could
 /// have their own implementation.
@@ -28,7 +32,7 @@ pub trait SignatureManger {
 
 /// Public trait that handles timestamp fetching across different chains
 pub trait TimestampChecker {
-    fn current_timestamp(&self) -> u32;
+    fn current_timestamp(&self) -> Timestamp;
 
     /// Returns if the timestamp used in the signature is valid
     /// Returns `false` if the timestamp is not at most 1 hour old to
@@ -40,13 +44,13 @@ pub trait TimestampChecker {
     /// # Arguments
     ///
     /// * `timestamp` Timestamp used in the signature
-    fn is_valid(&self, timestamp: u32) -> bool {
+    fn is_valid(&self, timestamp: Timestamp) -> bool {
         let c = self.current_timestamp();
+        // A timestamp too far in the future to add an hour to is not valid either
         timestamp
-            .checked_add(ONE_HOUR_IN_SECONDS)
-            .expect("Invalid timestamp")
-            > c
-            && timestamp < c + FIFTEEN_MINUTES_IN_SECONDS
+            .checked_add_secs(ONE_HOUR_IN_SECONDS)
+            .is_some_and(|t| t > c)
+            && timestamp < c.saturating_add_secs(FIFTEEN_MINUTES_IN_SECONDS)
     }
 }
 
@@ -58,23 +62,27 @@ pub trait TimestampChecker {
 /// * `datapoint_id` Data point ID
 /// * `msg_sender` Address of who sent the transaction
 /// * `datapoint_storage` Data point storage that links `datapoint_id` to `Datapoint`
+/// * `status_storage` Circuit breaker status storage that links `datapoint_id` to `FeedStatus`
 /// * `access` The access control registry used
 /// * `whitelist` The whitelist implementation used
 pub fn read_with_data_point_id<
     D: Storage<DataPoint>,
+    F: Storage<FeedStatus>,
     A: AccessControlRegistry,
     W: Whitelist<Address = A::Address>,
 >(
     datapoint_id: &Bytes32,
     msg_sender: &A::Address,
     datapoint_storage: &D,
+    status_storage: &F,
     access: &A,
     whitelist: &W,
-) -> Result<(Int, u32), Error> {
+) -> Result<(Int, Timestamp), Error> {
     ensure!(
         reader_can_read_data_point(datapoint_id, msg_sender, access, whitelist),
         Error::AccessDenied
     )?;
+    ensure_data_feed_not_paused(status_storage, datapoint_id)?;
     let data_point = datapoint_storage
         .get(datapoint_id)
         .ok_or(Error::BeaconDataNotFound)?;
@@ -92,11 +100,14 @@ pub fn read_with_data_point_id<
 /// * `msg_sender` Address of who sent the transaction
 /// * `datapoint_storage` Data point storage that links `datapoint_id` to `Datapoint`
 /// * `name_storage` Name to Datapoint Id storage used
+/// * `status_storage` Circuit breaker status storage that links `datapoint_id` to `FeedStatus`
 /// * `access` The access control registry used
 /// * `whitelist` The whitelist implementation used
+#[allow(clippy::too_many_arguments)]
 pub fn read_with_name<
     D: Storage<DataPoint>,
     H: Storage<Bytes32>,
+    F: Storage<FeedStatus>,
     A: AccessControlRegistry,
     W: Whitelist<Address = A::Address>,
 >(
@@ -104,9 +115,10 @@ pub fn read_with_name<
     msg_sender: &A::Address,
     datapoint_storage: &D,
     name_storage: &H,
+    status_storage: &F,
     access: &A,
     whitelist: &W,
-) -> Result<(Int, u32), Error> {
+) -> Result<(Int, Timestamp), Error> {
     let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
     ensure!(
         reader_can_read_data_point(&name_hash, msg_sender, access, whitelist),
@@ -115,6 +127,7 @@ pub fn read_with_name<
     let key = name_storage
         .get(&name_hash)
         .ok_or(Error::NameHashNotFound)?;
+    ensure_data_feed_not_paused(status_storage, &key)?;
     let data_point = datapoint_storage
         .get(&key)
         .ok_or(Error::BeaconDataNotFound)?;
@@ -142,7 +155,7 @@ pub fn reader_can_read_data_point<A: AccessControlRegistry, W: Whitelist<Address
 }
 
 /// Updates the dAPI that is specified by the beacon IDs.
-/// Returns the dAPI ID.
+/// Returns the dAPI ID, fails with the index of the Beacon that has no data.
 ///
 /// # Arguments
 ///
@@ -150,28 +163,31 @@ pub fn reader_can_read_data_point<A: AccessControlRegistry, W: Whitelist<Address
 pub fn update_dapi_with_beacons<D: Storage<DataPoint>>(
     d: &mut D,
     beacon_ids: &[Bytes32],
-) -> Result<Bytes32, Error> {
+) -> Result<Bytes32, DapiUpdateError> {
     let beacon_count = beacon_ids.len();
     ensure!(beacon_count > 1, Error::LessThanTwoBeacons)?;
 
     let mut values = Vec::with_capacity(beacon_count);
     let mut accumulated_timestamp = U256::from(0);
 
-    for beacon_id in beacon_ids {
-        let data_point = d.get(beacon_id).ok_or(Error::BeaconDataNotFound)?;
+    for (ind, beacon_id) in beacon_ids.iter().enumerate() {
+        let data_point = d
+            .get(beacon_id)
+            .ok_or(Error::BeaconDataNotFound)
+            .map_err(DapiUpdateError::at(ind))?;
         values.push(data_point.value);
-        accumulated_timestamp += U256::from(data_point.timestamp);
+        accumulated_timestamp = add_timestamp(accumulated_timestamp, data_point.timestamp)?;
     }
 
     let dapi_id = derive_dapi_id(beacon_ids);
     let dapi_datapoint = d.get(&dapi_id).ok_or(Error::BeaconDataNotFound)?;
 
-    let updated_timestamp = (accumulated_timestamp / beacon_count).as_u32();
+    let updated_timestamp = average_timestamp(accumulated_timestamp, beacon_count)?;
     ensure!(
         updated_timestamp >= dapi_datapoint.timestamp,
         Error::UpdatedValueOutdated
     )?;
-    let updated_value = median(&values);
+    let updated_value = median(&values)?;
     let datapoint = DataPoint::new(updated_value, updated_timestamp);
 
     d.store(dapi_id, datapoint);
@@ -181,7 +197,8 @@ pub fn update_dapi_with_beacons<D: Storage<DataPoint>>(
 /// Updates a dAPI using data signed by the respective Airnodes
 /// without requiring a request or subscription. The beacons for which the
 /// signature is omitted will be read from the storage.
-/// Returns the dAPI ID.
+/// Returns the dAPI ID. Failures of a Beacon, e.g. an invalid signature or
+/// timestamp, carry the index of the Beacon.
 ///
 /// # Arguments
 ///
@@ -205,11 +222,11 @@ pub fn update_dapi_with_signed_data<
     timestamps: Vec<[u8; 32]>,
     data: Vec<Bytes>,
     signatures: Vec<Bytes>,
-) -> Result<Bytes32, Error> {
+) -> Result<Bytes32, DapiUpdateError> {
     let beacon_count = template_ids.len();
 
     ensure!(
-        beacon_count == template_ids.len()
+        beacon_count == airnodes.len()
             && beacon_count == timestamps.len()
             && beacon_count == data.len()
             && beacon_count == signatures.len(),
@@ -222,43 +239,48 @@ pub fn update_dapi_with_signed_data<
     let mut values = Vec::with_capacity(beacon_count);
     let mut accumulated_timestamp = U256::from(0);
 
-    for ind in 0..beacon_count {
-        if !signatures[ind].is_empty() {
-            let timestamp = U256::from_big_endian(&timestamps[ind]);
-            let timestamp_u32 = timestamp.as_u32();
-            ensure!(
-                timestamp_checker.is_valid(timestamp_u32),
-                Error::InvalidTimestamp
-            )?;
+    let beacons = airnodes
+        .into_iter()
+        .zip(template_ids)
+        .zip(timestamps)
+        .zip(data)
+        .zip(signatures);
+    for (ind, ((((airnode, template_id), timestamp), data), signature)) in beacons.enumerate() {
+        let at = DapiUpdateError::at(ind);
+        let beacon_id = derive_beacon_id(airnode.clone(), template_id).map_err(&at)?;
+        if !signature.is_empty() {
+            let timestamp = U256::from_big_endian(&timestamp);
+            let valid =
+                timestamp_checker.is_valid(Timestamp::from_secs_u256(timestamp).map_err(&at)?);
+            ensure!(valid, Error::InvalidTimestamp).map_err(&at)?;
 
             let message = keccak_packed(&[
-                Token::FixedBytes(template_ids[ind].clone().to_vec()),
+                Token::FixedBytes(template_id.to_vec()),
                 Token::Uint(timestamp),
-                Token::Bytes(data[ind].clone()),
+                Token::Bytes(data.clone()),
             ]);
             ensure!(
-                S::verify(&airnodes[ind], &message, &signatures[ind]),
+                S::verify(&airnode, &message, &signature),
                 Error::InvalidSignature
-            )?;
-
-            values.push(decode_fulfillment_data(&data[ind])?);
+            )
+            .map_err(&at)?;
 
-            // Timestamp validity is already checked, which means it will
-            // be small enough to be typecast into `uint32`
-            accumulated_timestamp += timestamp;
-            beacon_ids.push(derive_beacon_id(airnodes[ind].clone(), template_ids[ind]));
+            values.push(decode_fulfillment_data(&data).map_err(&at)?);
+            accumulated_timestamp = accumulated_timestamp
+                .checked_add(timestamp)
+                .ok_or(Error::InvalidTimestamp)?;
         } else {
-            let beacon_id = derive_beacon_id(airnodes[ind].clone(), template_ids[ind]);
             let data_point = datapoint_storage
                 .get(&beacon_id)
-                .ok_or(Error::BeaconDataNotFound)?;
+                .ok_or(Error::BeaconDataNotFound)
+                .map_err(&at)?;
             values.push(data_point.value);
-            accumulated_timestamp += U256::from(data_point.timestamp);
-            beacon_ids.push(beacon_id);
+            accumulated_timestamp = add_timestamp(accumulated_timestamp, data_point.timestamp)?;
         }
+        beacon_ids.push(beacon_id);
     }
     let dapi_id = derive_dapi_id(&beacon_ids);
-    let updated_timestamp = (accumulated_timestamp / beacon_count).as_u32();
+    let updated_timestamp = average_timestamp(accumulated_timestamp, beacon_count)?;
     let dapi_datapoint = datapoint_storage
         .get(&dapi_id)
         .ok_or(Error::BeaconDataNotFound)?;
@@ -266,7 +288,7 @@ pub fn update_dapi_with_signed_data<
         updated_timestamp >= dapi_datapoint.timestamp,
         Error::UpdatedValueOutdated
     )?;
-    let updated_value = median(&values);
+    let updated_value = median(&values)?;
     let datapoint = DataPoint::new(updated_value, updated_timestamp);
     datapoint_storage.store(dapi_id, datapoint);
     Ok(dapi_id)
@@ -312,14 +334,14 @@ pub fn set_name<D: Storage<Bytes32>, A: AccessControlRegistry>(
 ///
 /// * `airnode` Airnode address
 /// * `template_id` Template ID
-pub fn derive_beacon_id(airnode: Bytes, template_id: Bytes32) -> Bytes32 {
-    ensure!(not_zero(&airnode), Error::AirnodeIdZero).unwrap();
-    ensure!(not_zero(&template_id), Error::TemplateIdZero).unwrap();
+pub fn derive_beacon_id(airnode: Bytes, template_id: Bytes32) -> Result<Bytes32, Error> {
+    ensure!(not_zero(&airnode), Error::AirnodeIdZero)?;
+    ensure!(not_zero(&template_id), Error::TemplateIdZero)?;
     let (encoded, _) = encode_packed(&[
         Token::Bytes(airnode),
         Token::FixedBytes(template_id.to_vec()),
     ]);
-    keccak256(&encoded)
+    Ok(keccak256(&encoded))
 }
 
 /// Derives the dAPI ID from the beacon IDs
@@ -348,12 +370,10 @@ pub fn decode_fulfillment_data(data: &Bytes) -> Result<Int, Error> {
     ensure!(data.len() == 32, Error::InvalidDataLength)?;
 
     let tokens = decode(&[ParamType::Int(0)], data)?;
-    ensure!(tokens.len() == 1, Error::InvalidDataLength)?;
-
-    if let Token::Int(i) = tokens[0] {
-        Ok(i)
-    } else {
-        Err(Error::InvalidDataType)
+    match tokens.as_slice() {
+        [Token::Int(i)] => Ok(*i),
+        [_] => Err(Error::InvalidDataType),
+        _ => Err(Error::InvalidDataLength),
     }
 }
 
@@ -375,34 +395,52 @@ pub fn process_beacon_update<D: Storage<DataPoint>>(
     let updated_beacon_value = decode_fulfillment_data(&data)?;
 
     let beacon = storage.get(&beacon_id).ok_or(Error::BeaconDataNotFound)?;
+    let timestamp = Timestamp::from_secs_u256(timestamp)?;
     ensure!(
-        timestamp.as_u32() > beacon.timestamp,
+        timestamp > beacon.timestamp,
         Error::FulfillmentOlderThanBeacon
     )?;
 
-    // Timestamp validity is already checked by `onlyValidTimestamp`, which
-    // means it will be small enough to be typecast into `uint32`
-
-    let datapoint = DataPoint::new(updated_beacon_value, timestamp.as_u32());
+    let datapoint = DataPoint::new(updated_beacon_value, timestamp);
     storage.store(beacon_id, datapoint);
 
     Ok(())
 }
 
 fn not_zero(bytes: &[u8]) -> bool {
-    let mut count = 0;
-    for i in bytes {
-        if *i == 0u8 {
-            count += 1;
-        }
-    }
-    count != bytes.len()
+    bytes.iter().any(|b| *b != 0)
+}
+
+/// Adds the timestamp of a Beacon to the sum the dAPI timestamp averages
+fn add_timestamp(sum: U256, timestamp: Timestamp) -> Result<U256, Error> {
+    sum.checked_add(U256::from(timestamp.as_secs()))
+        .ok_or(Error::InvalidTimestamp)
+}
+
+/// The timestamp of a dAPI, the average of the timestamps of its Beacons
+fn average_timestamp(sum: U256, beacon_count: usize) -> Result<Timestamp, Error> {
+    let average = sum
+        .checked_div(U256::from(beacon_count))
+        .ok_or(Error::LessThanTwoBeacons)?;
+    Timestamp::from_secs_u256(average)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::beacon::not_zero;
-    use crate::derive_beacon_id;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+    use crate::{
+        derive_beacon_id, derive_dapi_id, update_dapi_with_beacons, update_dapi_with_signed_data,
+        DapiUpdateError, DataPoint, Error, ErrorPayload, SignatureManger, Storage, Timestamp,
+    };
+
+    struct AcceptAll;
+
+    impl SignatureManger for AcceptAll {
+        fn verify(_key: &[u8], _message: &[u8], _signature: &[u8]) -> bool {
+            true
+        }
+    }
 
     #[test]
     fn not_zero_works() {
@@ -422,10 +460,65 @@ mod tests {
                 .unwrap();
         let mut template_id = [0; 32];
         template_id.copy_from_slice(&raw_template_id);
-        let beacon_id = derive_beacon_id(airnode, template_id);
+        let beacon_id = derive_beacon_id(airnode, template_id).unwrap();
         assert_eq!(
             hex::encode(beacon_id),
             "ad1b5c75a8b8e0d7dbc56c1e28aee9fabe285ad8fb61a256ddabd4523bfb284a"
         );
     }
+
+    #[test]
+    fn dapi_update_reports_the_failing_beacon() {
+        let beacon_ids = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        let mut storage = InMemoryStorage::default();
+        storage.store(beacon_ids[0], DataPoint::default());
+        storage.store(derive_dapi_id(&beacon_ids), DataPoint::default());
+
+        let error = update_dapi_with_beacons(&mut storage, &beacon_ids).unwrap_err();
+        assert!(matches!(
+            error,
+            DapiUpdateError {
+                error: Error::BeaconDataNotFound,
+                beacon_index: Some(1)
+            }
+        ));
+        assert_eq!(ErrorPayload::from(&error).details["beacon_index"], "1");
+
+        storage.store(beacon_ids[1], DataPoint::default());
+        storage.store(
+            beacon_ids[2],
+            DataPoint::new(1.into(), Timestamp::from_secs(3)),
+        );
+        assert!(update_dapi_with_beacons(&mut storage, &beacon_ids).is_ok());
+        assert!(matches!(
+            update_dapi_with_beacons(&mut storage, &beacon_ids[..1]),
+            Err(DapiUpdateError {
+                error: Error::LessThanTwoBeacons,
+                beacon_index: None
+            })
+        ));
+    }
+
+    #[test]
+    fn dapi_signed_update_rejects_fewer_airnodes() {
+        let mut storage = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(10));
+        let error = update_dapi_with_signed_data::<_, AcceptAll, _>(
+            &mut storage,
+            &clock,
+            vec![vec![1; 32]],
+            vec![[1; 32], [2; 32]],
+            vec![[0; 32], [0; 32]],
+            vec![vec![], vec![]],
+            vec![vec![], vec![]],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            DapiUpdateError {
+                error: Error::ParameterLengthMismatch,
+                beacon_index: None
+            }
+        ));
+    }
 }
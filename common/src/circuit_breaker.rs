@@ -0,0 +1,501 @@
+use crate::abi::{Int, Uint, U256};
+use crate::beacon::Storage;
+use crate::prelude::*;
+use crate::{
+    decode_fulfillment_data, derive_beacon_id, ensure, update_dapi_with_signed_data,
+    AccessControlRegistry, Bytes, Bytes32, DapiUpdateError, DataPoint, Error, SignatureManger,
+    Timestamp, TimestampChecker,
+};
+
+/// The value that represents 100% when expressing deviations, same as
+/// `HUNDRED_PERCENT` in the solidity contracts
+pub const HUNDRED_PERCENT: u32 = 100_000_000;
+
+/// The circuit breaker status of a data feed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FeedStatus {
+    /// Updates are applied as they arrive
+    #[default]
+    Active,
+    /// An update deviated more than the threshold from the stored value. The
+    /// update is held here until it is confirmed by a second update or released
+    /// by an admin.
+    Paused(DataPoint),
+}
+
+/// Same as `process_beacon_update`, but an update that moves the Beacon by more
+/// than `deviation_threshold` from its current value is not stored. Instead it is
+/// held in the pending slot of the feed and the feed is marked as `Paused`.
+/// A subsequent update that is within `deviation_threshold` of the pending value
+/// confirms it and resumes the feed, otherwise it replaces the pending value.
+/// Returns the status of the feed after the update.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `beacon_id` to `Datapoint`
+/// * `status_storage` The storage between `beacon_id` to `FeedStatus`
+/// * `deviation_threshold` Maximum allowed deviation, `HUNDRED_PERCENT` is 100%. Zero disables the breaker
+/// * `beacon_id` The Beacon ID
+/// * `timestamp` Timestamp used in the signature
+/// * `data` Fulfillment data (an `int256` encoded in contract ABI)
+pub fn process_beacon_update_with_circuit_breaker<D: Storage<DataPoint>, F: Storage<FeedStatus>>(
+    storage: &mut D,
+    status_storage: &mut F,
+    deviation_threshold: u32,
+    beacon_id: Bytes32,
+    timestamp: Uint,
+    data: Bytes,
+) -> Result<FeedStatus, Error> {
+    let updated_beacon_value = decode_fulfillment_data(&data)?;
+
+    let beacon = storage.get(&beacon_id).ok_or(Error::BeaconDataNotFound)?;
+    let timestamp = Timestamp::from_secs_u256(timestamp)?;
+    ensure!(
+        timestamp > beacon.timestamp,
+        Error::FulfillmentOlderThanBeacon
+    )?;
+    let datapoint = DataPoint::new(updated_beacon_value, timestamp);
+
+    let previous_status = status_storage.get(&beacon_id).unwrap_or_default();
+    let tripped = match &previous_status {
+        FeedStatus::Paused(pending) => {
+            ensure!(
+                datapoint.timestamp > pending.timestamp,
+                Error::FulfillmentOlderThanBeacon
+            )?;
+            exceeds_threshold(&pending.value, &datapoint.value, deviation_threshold)
+        }
+        // A Beacon that has never been updated has nothing to deviate from
+        FeedStatus::Active => {
+            !beacon.timestamp.is_zero()
+                && exceeds_threshold(&beacon.value, &datapoint.value, deviation_threshold)
+        }
+    };
+
+    let status = if tripped {
+        FeedStatus::Paused(datapoint)
+    } else {
+        storage.store(beacon_id, datapoint);
+        FeedStatus::Active
+    };
+    if status != previous_status {
+        status_storage.store(beacon_id, status.clone());
+    }
+    Ok(status)
+}
+
+/// Same as `update_dapi_with_signed_data`, but a dAPI is not updated with a Beacon
+/// the circuit breaker has paused, or with signed data that would pause its Beacon.
+/// A dAPI does not hold updates the way a Beacon does, so these are rejected with
+/// `Error::DataFeedPaused` and `Error::DeviationExceedsThreshold`. Such data
+/// should be relayed with `update_beacon_with_signed_data` first. Both carry the
+/// index of the Beacon.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `data_point_id` to `Datapoint`
+/// * `status_storage` The storage between `beacon_id` to `FeedStatus`
+/// * `deviation_threshold` Maximum allowed deviation, `HUNDRED_PERCENT` is 100%. Zero disables the breaker
+/// * `timestamp_checker` The timestamp checker/validator to use
+/// * `airnodes` Airnode addresses
+/// * `template_ids` Template IDs
+/// * `timestamps` Timestamps used in the signatures
+/// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+/// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode address per Beacon
+#[allow(clippy::too_many_arguments)]
+pub fn update_dapi_with_signed_data_with_circuit_breaker<
+    D: Storage<DataPoint>,
+    F: Storage<FeedStatus>,
+    S: SignatureManger,
+    T: TimestampChecker,
+>(
+    storage: &mut D,
+    status_storage: &F,
+    deviation_threshold: u32,
+    timestamp_checker: &T,
+    airnodes: Vec<Bytes>,
+    template_ids: Vec<Bytes32>,
+    timestamps: Vec<Bytes32>,
+    data: Vec<Bytes>,
+    signatures: Vec<Bytes>,
+) -> Result<Bytes32, DapiUpdateError> {
+    ensure!(
+        airnodes.len() == template_ids.len()
+            && template_ids.len() == data.len()
+            && template_ids.len() == signatures.len(),
+        Error::ParameterLengthMismatch
+    )?;
+    let beacons = airnodes
+        .iter()
+        .zip(&template_ids)
+        .zip(&data)
+        .zip(&signatures);
+    for (ind, (((airnode, template_id), data), signature)) in beacons.enumerate() {
+        let at = DapiUpdateError::at(ind);
+        let beacon_id = derive_beacon_id(airnode.clone(), *template_id).map_err(&at)?;
+        ensure_data_feed_not_paused(status_storage, &beacon_id).map_err(&at)?;
+        if signature.is_empty() {
+            continue;
+        }
+        let beacon = match storage.get(&beacon_id) {
+            Some(beacon) if !beacon.timestamp.is_zero() => beacon,
+            // A Beacon that has never been updated has nothing to deviate from
+            _ => continue,
+        };
+        let updated_value = decode_fulfillment_data(data).map_err(&at)?;
+        ensure!(
+            !exceeds_threshold(&beacon.value, &updated_value, deviation_threshold),
+            Error::DeviationExceedsThreshold
+        )
+        .map_err(&at)?;
+    }
+    update_dapi_with_signed_data::<D, S, T>(
+        storage,
+        timestamp_checker,
+        airnodes,
+        template_ids,
+        timestamps,
+        data,
+        signatures,
+    )
+}
+
+/// Releases a paused data feed by storing its pending update and marking it
+/// as `Active` again. Only the manager or accounts with the admin role can
+/// release a feed.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `data_feed_id` to `Datapoint`
+/// * `status_storage` The storage between `data_feed_id` to `FeedStatus`
+/// * `data_feed_id` The data feed ID
+/// * `msg_sender` Address of who sent the transaction
+/// * `access` The access control registry used
+pub fn release_data_feed<
+    D: Storage<DataPoint>,
+    F: Storage<FeedStatus>,
+    A: AccessControlRegistry,
+>(
+    storage: &mut D,
+    status_storage: &mut F,
+    data_feed_id: Bytes32,
+    msg_sender: &A::Address,
+    access: &A,
+) -> Result<(), Error> {
+    ensure!(
+        access.manager() == msg_sender || access.has_role(&access.admin_role(), msg_sender),
+        Error::AccessDenied
+    )?;
+    match status_storage.get(&data_feed_id).unwrap_or_default() {
+        FeedStatus::Paused(pending) => {
+            storage.store(data_feed_id, pending);
+            status_storage.store(data_feed_id, FeedStatus::Active);
+            Ok(())
+        }
+        FeedStatus::Active => Err(Error::DataFeedNotPaused),
+    }
+}
+
+/// Returns `Error::DataFeedPaused` if the data feed is paused by the circuit breaker
+///
+/// # Arguments
+///
+/// * `status_storage` The storage between `data_feed_id` to `FeedStatus`
+/// * `data_feed_id` The data feed ID
+pub fn ensure_data_feed_not_paused<F: Storage<FeedStatus>>(
+    status_storage: &F,
+    data_feed_id: &Bytes32,
+) -> Result<(), Error> {
+    match status_storage.get(data_feed_id) {
+        Some(FeedStatus::Paused(_)) => Err(Error::DataFeedPaused),
+        _ => Ok(()),
+    }
+}
+
+fn exceeds_threshold(initial_value: &Int, updated_value: &Int, deviation_threshold: u32) -> bool {
+    deviation_threshold != 0
+        && calculate_update_in_percentage(initial_value, updated_value)
+            > U256::from(deviation_threshold)
+}
+
+/// Calculates the deviation of `updated_value` from `initial_value` where both
+/// are `int256` in two's complement. Same as `calculateUpdateInPercentage` in the
+/// solidity contracts.
+pub fn calculate_update_in_percentage(initial_value: &Int, updated_value: &Int) -> U256 {
+    let absolute_initial_value = abs(initial_value);
+    let absolute_updated_value = abs(updated_value);
+    let absolute_delta = if is_negative(initial_value) == is_negative(updated_value) {
+        if absolute_initial_value > absolute_updated_value {
+            absolute_initial_value.saturating_sub(absolute_updated_value)
+        } else {
+            absolute_updated_value.saturating_sub(absolute_initial_value)
+        }
+    } else {
+        absolute_initial_value.saturating_add(absolute_updated_value)
+    };
+    // a zero initial value deviates as if it was one
+    absolute_delta
+        .saturating_mul(U256::from(HUNDRED_PERCENT))
+        .checked_div(absolute_initial_value.max(U256::from(1u8)))
+        .unwrap_or(U256::MAX)
+}
+
+fn is_negative(value: &Int) -> bool {
+    value.bit(255)
+}
+
+fn abs(value: &Int) -> U256 {
+    if is_negative(value) {
+        (!*value).overflowing_add(U256::from(1u8)).0
+    } else {
+        *value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::{encode, Token};
+    use crate::derive_dapi_id;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+
+    /// Accepts any signature, the signatures are covered by the `beacon` tests
+    struct AnySignature;
+    impl SignatureManger for AnySignature {
+        fn verify(_key: &[u8], _message: &[u8], _signature: &[u8]) -> bool {
+            true
+        }
+    }
+
+    fn storage_with_default(key: Bytes32) -> InMemoryStorage<DataPoint> {
+        let mut s = InMemoryStorage::default();
+        s.store(key, DataPoint::default());
+        s
+    }
+
+    fn int(v: i128) -> Int {
+        if v < 0 {
+            (!Int::from(-v)).overflowing_add(Int::from(1)).0
+        } else {
+            Int::from(v)
+        }
+    }
+
+    fn data(v: i128) -> Bytes {
+        encode(&[Token::Int(int(v))])
+    }
+
+    const BEACON_ID: Bytes32 = [1u8; 32];
+    // 10%
+    const THRESHOLD: u32 = HUNDRED_PERCENT / 10;
+
+    #[test]
+    fn calculate_update_in_percentage_works() {
+        assert_eq!(
+            calculate_update_in_percentage(&int(100), &int(110)),
+            U256::from(HUNDRED_PERCENT / 10)
+        );
+        assert_eq!(
+            calculate_update_in_percentage(&int(-100), &int(-90)),
+            U256::from(HUNDRED_PERCENT / 10)
+        );
+        assert_eq!(
+            calculate_update_in_percentage(&int(-100), &int(100)),
+            U256::from(HUNDRED_PERCENT) * 2
+        );
+        assert_eq!(
+            calculate_update_in_percentage(&int(0), &int(1)),
+            U256::from(HUNDRED_PERCENT)
+        );
+    }
+
+    #[test]
+    fn first_update_is_never_paused() {
+        let mut storage = storage_with_default(BEACON_ID);
+        let mut status = InMemoryStorage::default();
+        let r = process_beacon_update_with_circuit_breaker(
+            &mut storage,
+            &mut status,
+            THRESHOLD,
+            BEACON_ID,
+            Uint::from(1),
+            data(100),
+        )
+        .unwrap();
+        assert_eq!(r, FeedStatus::Active);
+        assert_eq!(storage.get(&BEACON_ID).unwrap().value, int(100));
+    }
+
+    #[test]
+    fn extreme_move_pauses_until_confirmed() {
+        let mut storage = storage_with_default(BEACON_ID);
+        let mut status = InMemoryStorage::default();
+        storage.store(BEACON_ID, DataPoint::new(int(100), Timestamp::from_secs(1)));
+
+        let r = process_beacon_update_with_circuit_breaker(
+            &mut storage,
+            &mut status,
+            THRESHOLD,
+            BEACON_ID,
+            Uint::from(2),
+            data(200),
+        )
+        .unwrap();
+        assert_eq!(
+            r,
+            FeedStatus::Paused(DataPoint::new(int(200), Timestamp::from_secs(2)))
+        );
+        assert_eq!(storage.get(&BEACON_ID).unwrap().value, int(100));
+        assert!(matches!(
+            ensure_data_feed_not_paused(&status, &BEACON_ID),
+            Err(Error::DataFeedPaused)
+        ));
+
+        // deviates from the pending value as well, replaces it
+        let r = process_beacon_update_with_circuit_breaker(
+            &mut storage,
+            &mut status,
+            THRESHOLD,
+            BEACON_ID,
+            Uint::from(3),
+            data(300),
+        )
+        .unwrap();
+        assert_eq!(
+            r,
+            FeedStatus::Paused(DataPoint::new(int(300), Timestamp::from_secs(3)))
+        );
+
+        // confirms the pending value
+        let r = process_beacon_update_with_circuit_breaker(
+            &mut storage,
+            &mut status,
+            THRESHOLD,
+            BEACON_ID,
+            Uint::from(4),
+            data(301),
+        )
+        .unwrap();
+        assert_eq!(r, FeedStatus::Active);
+        assert_eq!(
+            storage.get(&BEACON_ID).unwrap(),
+            DataPoint::new(int(301), Timestamp::from_secs(4))
+        );
+        assert!(ensure_data_feed_not_paused(&status, &BEACON_ID).is_ok());
+    }
+
+    #[test]
+    fn zero_threshold_disables_breaker() {
+        let mut storage = storage_with_default(BEACON_ID);
+        let mut status = InMemoryStorage::default();
+        storage.store(BEACON_ID, DataPoint::new(int(100), Timestamp::from_secs(1)));
+        let r = process_beacon_update_with_circuit_breaker(
+            &mut storage,
+            &mut status,
+            0,
+            BEACON_ID,
+            Uint::from(2),
+            data(-100),
+        )
+        .unwrap();
+        assert_eq!(r, FeedStatus::Active);
+        assert_eq!(storage.get(&BEACON_ID).unwrap().value, int(-100));
+    }
+
+    #[test]
+    fn release_data_feed_works() {
+        let access = crate::dummy::DummyAccess::<Bytes32>::default();
+        let mut storage = storage_with_default(BEACON_ID);
+        let mut status = InMemoryStorage::default();
+        assert!(matches!(
+            release_data_feed(&mut storage, &mut status, BEACON_ID, &[0u8; 32], &access),
+            Err(Error::DataFeedNotPaused)
+        ));
+
+        status.store(
+            BEACON_ID,
+            FeedStatus::Paused(DataPoint::new(int(5), Timestamp::from_secs(10))),
+        );
+        release_data_feed(&mut storage, &mut status, BEACON_ID, &[0u8; 32], &access).unwrap();
+        assert_eq!(
+            storage.get(&BEACON_ID).unwrap(),
+            DataPoint::new(int(5), Timestamp::from_secs(10))
+        );
+        assert_eq!(status.get(&BEACON_ID).unwrap(), FeedStatus::Active);
+    }
+
+    fn update_dapi(
+        storage: &mut InMemoryStorage<DataPoint>,
+        status: &InMemoryStorage<FeedStatus>,
+        signed: [bool; 2],
+        value: i128,
+    ) -> Result<Bytes32, DapiUpdateError> {
+        update_dapi_with_signed_data_with_circuit_breaker::<_, _, AnySignature, _>(
+            storage,
+            status,
+            THRESHOLD,
+            &FixedClock::new(Timestamp::from_secs(10)),
+            vec![vec![1u8; 32], vec![2u8; 32]],
+            vec![[1u8; 32], [1u8; 32]],
+            vec![Uint::from(10).into(); 2],
+            vec![data(value); 2],
+            signed
+                .iter()
+                .map(|s| if *s { vec![1] } else { vec![] })
+                .collect(),
+        )
+    }
+
+    fn dapi_storage() -> (InMemoryStorage<DataPoint>, [Bytes32; 2]) {
+        let beacon_ids = [
+            derive_beacon_id(vec![1u8; 32], [1u8; 32]).unwrap(),
+            derive_beacon_id(vec![2u8; 32], [1u8; 32]).unwrap(),
+        ];
+        let mut storage = InMemoryStorage::default();
+        for beacon_id in beacon_ids {
+            storage.store(beacon_id, DataPoint::new(int(100), Timestamp::from_secs(1)));
+        }
+        storage.store(derive_dapi_id(&beacon_ids), DataPoint::default());
+        (storage, beacon_ids)
+    }
+
+    #[test]
+    fn dapi_update_rejects_paused_beacons() {
+        let (mut storage, beacon_ids) = dapi_storage();
+        let mut status = InMemoryStorage::default();
+        status.store(
+            beacon_ids[1],
+            FeedStatus::Paused(DataPoint::new(int(200), Timestamp::from_secs(2))),
+        );
+        for signed in [[true, true], [true, false]] {
+            assert!(matches!(
+                update_dapi(&mut storage, &status, signed, 105),
+                Err(DapiUpdateError {
+                    error: Error::DataFeedPaused,
+                    beacon_index: Some(1)
+                })
+            ));
+        }
+        assert_eq!(
+            storage.get(&derive_dapi_id(&beacon_ids)).unwrap(),
+            DataPoint::default()
+        );
+    }
+
+    #[test]
+    fn dapi_update_rejects_deviating_signed_data() {
+        let (mut storage, beacon_ids) = dapi_storage();
+        let status = InMemoryStorage::default();
+        assert!(matches!(
+            update_dapi(&mut storage, &status, [true, true], 200),
+            Err(DapiUpdateError {
+                error: Error::DeviationExceedsThreshold,
+                beacon_index: Some(0)
+            })
+        ));
+
+        let dapi_id = update_dapi(&mut storage, &status, [true, true], 105).unwrap();
+        assert_eq!(dapi_id, derive_dapi_id(&beacon_ids));
+        assert_eq!(storage.get(&dapi_id).unwrap().value, int(105));
+    }
+}
@@ -1,11 +1,55 @@
 //! A collection of default implementations
 
 use crate::abi::U256;
+use crate::prelude::*;
 use crate::{
-    AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32, Error, Whitelist,
-    WhitelistRoles, WhitelistRolesWithManager, WhitelistWithManager, Zero,
+    AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32, Error, Storage,
+    Timestamp, TimestampChecker, Whitelist, WhitelistRoles, WhitelistRolesWithManager,
+    WhitelistWithManager, Zero,
 };
 
+/// `Storage` kept in memory, for tests and off-chain use
+#[derive(Clone, Debug)]
+pub struct InMemoryStorage<T> {
+    map: BTreeMap<Bytes32, T>,
+}
+
+impl<T> Default for InMemoryStorage<T> {
+    fn default() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> Storage<T> for InMemoryStorage<T> {
+    fn get(&self, key: &Bytes32) -> Option<T> {
+        self.map.get(key).cloned()
+    }
+
+    fn store(&mut self, key: Bytes32, t: T) {
+        self.map.insert(key, t);
+    }
+}
+
+/// A clock that always returns the same timestamp
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixedClock {
+    current_timestamp: Timestamp,
+}
+
+impl FixedClock {
+    pub fn new(current_timestamp: Timestamp) -> Self {
+        Self { current_timestamp }
+    }
+}
+
+impl TimestampChecker for FixedClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
 pub struct DummyWhitelist<Address: AsRef<[u8]> + Zero + Default + PartialEq> {
     manager: Address,
 }
@@ -30,7 +74,7 @@ where
         &mut self,
         _service_id: &Bytes32,
         _user: &Self::Address,
-        _expiration_timestamp: u64,
+        _expiration_timestamp: Timestamp,
     ) {
     }
 
@@ -38,7 +82,7 @@ where
         &mut self,
         _service_id: &Bytes32,
         _user: &Self::Address,
-        _expiration_timestamp: u64,
+        _expiration_timestamp: Timestamp,
     ) {
     }
 
@@ -47,8 +91,8 @@ where
         _service_id: &Bytes32,
         _user: &Self::Address,
         _status: bool,
-    ) -> U256 {
-        U256::from(0u8)
+    ) -> Result<U256, Error> {
+        Ok(U256::from(0u8))
     }
 
     fn revoke_indefinite_whitelist_status(
@@ -56,8 +100,8 @@ where
         _service_id: &Bytes32,
         _user: &Self::Address,
         _setter: &Self::Address,
-    ) -> (bool, U256) {
-        (true, U256::from(0u8))
+    ) -> Result<(bool, U256), Error> {
+        Ok((true, U256::from(0u8)))
     }
 }
 
@@ -117,7 +161,7 @@ where
         &mut self,
         _service_id: &Bytes32,
         _user: &<Self as Whitelist>::Address,
-        _expiration_timestamp: u64,
+        _expiration_timestamp: Timestamp,
     ) {
     }
 
@@ -125,7 +169,7 @@ where
         &mut self,
         _service_id: &Bytes32,
         _user: &<Self as Whitelist>::Address,
-        _expiration_timestamp: u64,
+        _expiration_timestamp: Timestamp,
     ) {
     }
 
@@ -134,8 +178,8 @@ where
         _service_id: &Bytes32,
         _user: &<Self as Whitelist>::Address,
         _status: bool,
-    ) -> U256 {
-        U256::from(0u8)
+    ) -> Result<U256, Error> {
+        Ok(U256::from(0u8))
     }
 
     fn revoke_indefinite_whitelist_status(
@@ -143,8 +187,8 @@ where
         _service_id: &Bytes32,
         _user: &<Self as Whitelist>::Address,
         _setter: &<Self as Whitelist>::Address,
-    ) -> (bool, U256) {
-        (false, U256::from(0u8))
+    ) -> Result<(bool, U256), Error> {
+        Ok((false, U256::from(0u8)))
     }
 }
 
@@ -1,94 +1,428 @@
-use std::fmt::Debug;
-use thiserror::Error;
+use crate::prelude::*;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
-#[derive(Error, Debug)]
+#[derive(Clone, Debug, Display)]
 pub enum Error {
-    #[error("")]
+    #[display(fmt = "Cannot deserialize data point")]
     CannotDeserializeDataPoint,
-    #[error("")]
+    #[display(fmt = "Invalid data")]
     InvalidData,
-    #[error("Data length not correct")]
+    #[display(fmt = "Data length not correct")]
     InvalidDataLength,
-    #[error("Invalid data type")]
+    #[display(fmt = "Invalid data type")]
     InvalidDataType,
-    #[error("Beacon data not found")]
+    #[display(fmt = "Beacon data not found")]
     BeaconDataNotFound,
-    #[error("Fulfillment older than Beacon")]
+    #[display(fmt = "Fulfillment older than Beacon")]
     FulfillmentOlderThanBeacon,
-    #[error("Invalid name: {0}")]
+    #[display(fmt = "Invalid name: {}", _0)]
     InvalidName(String),
-    #[error("Parameter length mismatch")]
+    #[display(fmt = "Parameter length mismatch")]
     ParameterLengthMismatch,
-    #[error("Specified less than two Beacons")]
+    #[display(fmt = "Specified less than two Beacons")]
     LessThanTwoBeacons,
-    #[error("Timestamp not valid")]
+    #[display(fmt = "Timestamp not valid")]
     InvalidTimestamp,
-    #[error("Signature mismatch")]
+    #[display(fmt = "Signature mismatch")]
     InvalidSignature,
-    #[error("Updated value outdated")]
+    #[display(fmt = "Updated value outdated")]
     UpdatedValueOutdated,
-    #[error("Does not extend expiration")]
+    #[display(fmt = "Does not extend expiration")]
     DoesNotExtendExpiration,
-    #[error("Access Denied")]
+    #[display(fmt = "Access Denied")]
     AccessDenied,
-    #[error("NameHash Not Found")]
+    #[display(fmt = "NameHash Not Found")]
     NameHashNotFound,
-    #[error("Role description Empty")]
+    #[display(fmt = "Role description Empty")]
     RoleDescriptionEmpty,
-    #[error("Service ID zero")]
+    #[display(fmt = "Service ID zero")]
     ServiceIdZero,
-    #[error("User address zero")]
+    #[display(fmt = "User address zero")]
     UserAddressZero,
-    #[error("Invalid Address")]
+    #[display(fmt = "Invalid Address")]
     InvalidAddress,
-    #[error("Only Renounce roles for self")]
+    #[display(fmt = "Only Renounce roles for self")]
     OnlyRenounceRolesForSelf,
-    #[error("Not authorized to perform this action")]
+    #[display(fmt = "Not authorized to perform this action")]
     NotAuthorized,
-    #[error("Role admin not found")]
+    #[display(fmt = "Role admin not found")]
     RoleAdminNotFound,
-    #[error("Contract already initialized")]
+    #[display(fmt = "Contract already initialized")]
     AlreadyInitialized,
-    #[error("Cannot set indefinite status")]
+    #[display(fmt = "Cannot set indefinite status")]
     CannotSetIndefiniteStatus,
-    #[error("Template id cannot be zero")]
+    #[display(fmt = "Template id cannot be zero")]
     TemplateIdZero,
-    #[error("Airnode id cannot be zero")]
+    #[display(fmt = "Airnode id cannot be zero")]
     AirnodeIdZero,
-    #[error("Setter can set indefinite status")]
+    #[display(fmt = "Setter can set indefinite status")]
     SetterCanSetIndefiniteStatus,
+    #[display(fmt = "Data feed paused")]
+    DataFeedPaused,
+    #[display(fmt = "Data feed not paused")]
+    DataFeedNotPaused,
+    #[display(fmt = "Updates paused")]
+    UpdatesPaused,
+    #[display(fmt = "Reads paused")]
+    ReadsPaused,
+    #[display(fmt = "Invalid pause state")]
+    InvalidPauseState,
+    #[display(fmt = "Pending manager not found")]
+    PendingManagerNotFound,
+    #[display(fmt = "Subscription not configured")]
+    SubscriptionNotConfigured,
+    #[display(fmt = "Invalid subscription periods")]
+    InvalidSubscriptionPeriods,
+    #[display(fmt = "Insufficient payment")]
+    InsufficientPayment,
+    #[display(fmt = "Insufficient balance")]
+    InsufficientBalance,
+    #[display(fmt = "Permit expired")]
+    PermitExpired,
+    #[display(fmt = "Invalid permit nonce")]
+    InvalidPermitNonce,
+    #[display(fmt = "Read quota exceeded")]
+    ReadQuotaExceeded,
+    #[display(fmt = "Invalid reader tier")]
+    InvalidReaderTier,
+    #[display(fmt = "Previous manager not found")]
+    PreviousManagerNotFound,
+    #[display(fmt = "Storage opened read only")]
+    ReadOnlyStorage,
+    #[display(fmt = "Contract not initialized")]
+    NotInitialized,
+    #[display(fmt = "Invalid secret key")]
+    InvalidSecretKey,
+    #[display(fmt = "Deviation exceeds threshold")]
+    DeviationExceedsThreshold,
+    #[display(fmt = "Transfer failed")]
+    TransferFailed,
+    #[display(fmt = "Value typecasting error")]
+    ValueTypecasting,
+    #[display(fmt = "Invalid indefinite whitelist count")]
+    InvalidIndefiniteWhitelistCount,
+    #[display(fmt = "Usage epoch not closed")]
+    UsageEpochNotClosed,
+    #[display(fmt = "Previous manager is the current manager")]
+    SameManager,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Error codes are namespaced so that the codes of `Error` and of the chain
+/// specific errors, e.g. the `ERROR_*` constants of the Solana program, never
+/// collide. A code is `namespace base + index in the namespace`.
+pub const ERROR_NAMESPACE_SIZE: u32 = 1000;
+
+/// The first error code, same as the `ERROR_CODE_OFFSET` of anchor, which
+/// uses the codes below for its own errors, so that Solana programs can return
+/// every namespace as a custom program error
+pub const ERROR_CODE_OFFSET: u32 = 6000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorNamespace {
+    /// `Error`, shared by all chains
+    Common = 0,
+    /// Errors specific to the Solana program
+    Solana = 1,
+}
+
+impl ErrorNamespace {
+    /// The first code of the namespace
+    // the namespaces are few, their codes are far from `u32::MAX`
+    #[allow(clippy::arithmetic_side_effects)]
+    pub const fn base(self) -> u32 {
+        ERROR_CODE_OFFSET + self as u32 * ERROR_NAMESPACE_SIZE
+    }
+
+    /// Splits `code` into its namespace and its index in the namespace,
+    /// `None` if the namespace is unknown
+    pub fn split(code: u32) -> Option<(Self, u32)> {
+        let code = code.checked_sub(ERROR_CODE_OFFSET)?;
+        let namespace = match code / ERROR_NAMESPACE_SIZE {
+            0 => ErrorNamespace::Common,
+            1 => ErrorNamespace::Solana,
+            _ => return None,
+        };
+        Some((namespace, code % ERROR_NAMESPACE_SIZE))
+    }
+}
+
+/// The code table of `Error`, the index of each variant in the
+/// `ErrorNamespace::Common` namespace. Codes are stable: never renumber a
+/// variant or reuse an index, append new variants with the next free index
+/// instead. Indexes 7 and 8 were never assigned and stay reserved.
+macro_rules! error_codes {
+    ($($variant:ident => $index:literal,)*) => {
+        impl Error {
+            /// The stable code of the error, in the `ErrorNamespace::Common` namespace
+            // the indexes are below `ERROR_NAMESPACE_SIZE`
+            #[allow(clippy::arithmetic_side_effects)]
+            pub fn code(&self) -> u32 {
+                let index = match self {
+                    Error::InvalidName(_) => 6,
+                    $(Error::$variant => $index,)*
+                };
+                ErrorNamespace::Common.base() + index
+            }
+
+            /// The name of the error variant, e.g. `AccessDenied`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    Error::InvalidName(_) => "InvalidName",
+                    $(Error::$variant => stringify!($variant),)*
+                }
+            }
+
+            /// Returns the error with `code`, `None` if no error has the code.
+            /// The details carried by some errors, e.g. the name of `InvalidName`,
+            /// are not part of the code and come back empty.
+            pub fn from_code(code: u32) -> Option<Self> {
+                match ErrorNamespace::split(code)? {
+                    (ErrorNamespace::Common, 6) => Some(Error::InvalidName(String::new())),
+                    $((ErrorNamespace::Common, $index) => Some(Error::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    CannotDeserializeDataPoint => 0,
+    InvalidData => 1,
+    InvalidDataLength => 2,
+    InvalidDataType => 3,
+    BeaconDataNotFound => 4,
+    FulfillmentOlderThanBeacon => 5,
+    ParameterLengthMismatch => 9,
+    LessThanTwoBeacons => 10,
+    InvalidTimestamp => 11,
+    InvalidSignature => 12,
+    UpdatedValueOutdated => 13,
+    AccessDenied => 14,
+    NameHashNotFound => 15,
+    RoleDescriptionEmpty => 16,
+    DoesNotExtendExpiration => 17,
+    ServiceIdZero => 18,
+    UserAddressZero => 19,
+    InvalidAddress => 20,
+    OnlyRenounceRolesForSelf => 21,
+    NotAuthorized => 22,
+    RoleAdminNotFound => 23,
+    AlreadyInitialized => 24,
+    CannotSetIndefiniteStatus => 25,
+    TemplateIdZero => 26,
+    AirnodeIdZero => 27,
+    SetterCanSetIndefiniteStatus => 28,
+    DataFeedPaused => 29,
+    DataFeedNotPaused => 30,
+    UpdatesPaused => 31,
+    ReadsPaused => 32,
+    InvalidPauseState => 33,
+    PendingManagerNotFound => 34,
+    SubscriptionNotConfigured => 35,
+    InvalidSubscriptionPeriods => 36,
+    InsufficientPayment => 37,
+    InsufficientBalance => 38,
+    PermitExpired => 39,
+    InvalidPermitNonce => 40,
+    ReadQuotaExceeded => 41,
+    InvalidReaderTier => 42,
+    PreviousManagerNotFound => 43,
+    ReadOnlyStorage => 44,
+    NotInitialized => 45,
+    InvalidSecretKey => 46,
+    DeviationExceedsThreshold => 47,
+    TransferFailed => 48,
+    ValueTypecasting => 49,
+    InvalidIndefiniteWhitelistCount => 50,
+    UsageEpochNotClosed => 51,
+    SameManager => 52,
 }
 
 impl From<Error> for u32 {
     fn from(e: Error) -> Self {
+        e.code()
+    }
+}
+
+/// Failure of a dAPI update, with the index of the Beacon it failed at in the
+/// Beacons of the dAPI, `None` if it failed at the dAPI itself, e.g. with
+/// `LessThanTwoBeacons`
+#[derive(Debug, Display)]
+#[display(fmt = "{}", error)]
+pub struct DapiUpdateError {
+    /// The error
+    pub error: Error,
+    /// Index of the failing Beacon
+    pub beacon_index: Option<usize>,
+}
+
+impl DapiUpdateError {
+    /// Returns a function that attributes an error to the Beacon at `index`
+    pub fn at(index: usize) -> impl Fn(Error) -> Self {
+        move |error| Self {
+            error,
+            beacon_index: Some(index),
+        }
+    }
+}
+
+impl From<Error> for DapiUpdateError {
+    fn from(error: Error) -> Self {
+        Self {
+            error,
+            beacon_index: None,
+        }
+    }
+}
+
+impl From<DapiUpdateError> for Error {
+    fn from(e: DapiUpdateError) -> Self {
+        e.error
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DapiUpdateError {}
+
+/// Machine readable failure of a contract call: the `Error` plus the context
+/// it happened in, such as the offending beacon ID. Chains that fail calls
+/// with a message, e.g. NEAR, fail with the payload as JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorPayload {
+    /// Stable code of the error, see `Error::code`
+    pub code: u32,
+    /// Name of the error, see `Error::name`
+    pub name: String,
+    /// Human readable message of the error
+    pub message: String,
+    /// Context of the failure, e.g. `beacon_id`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+}
+
+impl ErrorPayload {
+    /// Adds `key` to the context of the failure
+    pub fn with_detail(mut self, key: &str, value: impl ToString) -> Self {
+        self.details.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Adds `key` to the context of the failure, hex encoding the ID
+    pub fn with_id(self, key: &str, id: &[u8]) -> Self {
+        self.with_detail(key, format!("0x{}", hex::encode(id)))
+    }
+
+    /// The error the payload was built from, `None` if the code is unknown,
+    /// e.g. sent by a newer contract
+    pub fn error(&self) -> Option<Error> {
+        Error::from_code(self.code)
+    }
+
+    /// The payload as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Finds and parses the payload in the failure message of a call. Chains
+    /// and clients wrap the message, e.g. NEAR reports
+    /// `Smart contract panicked: {...}`, so the text around the payload is skipped.
+    /// Returns `None` if the message carries no payload.
+    pub fn parse(message: &str) -> Option<Self> {
+        let start = message.find('{')?;
+        let end = message.rfind('}')?;
+        if end < start {
+            return None;
+        }
+        serde_json::from_str(&message[start..=end]).ok()
+    }
+}
+
+impl From<&Error> for ErrorPayload {
+    fn from(e: &Error) -> Self {
+        let payload = Self {
+            code: e.code(),
+            name: e.name().to_string(),
+            message: e.to_string(),
+            details: BTreeMap::new(),
+        };
         match e {
-            Error::CannotDeserializeDataPoint => 0,
-            Error::InvalidData => 1,
-            Error::InvalidDataLength => 2,
-            Error::InvalidDataType => 3,
-            Error::BeaconDataNotFound => 4,
-            Error::FulfillmentOlderThanBeacon => 5,
-            Error::InvalidName(_) => 6,
-            Error::ParameterLengthMismatch => 9,
-            Error::LessThanTwoBeacons => 10,
-            Error::InvalidTimestamp => 11,
-            Error::InvalidSignature => 12,
-            Error::UpdatedValueOutdated => 13,
-            Error::AccessDenied => 14,
-            Error::NameHashNotFound => 15,
-            Error::RoleDescriptionEmpty => 16,
-            Error::DoesNotExtendExpiration => 17,
-            Error::ServiceIdZero => 18,
-            Error::UserAddressZero => 19,
-            Error::InvalidAddress => 20,
-            Error::OnlyRenounceRolesForSelf => 21,
-            Error::NotAuthorized => 22,
-            Error::RoleAdminNotFound => 23,
-            Error::AlreadyInitialized => 24,
-            Error::CannotSetIndefiniteStatus => 25,
-            Error::TemplateIdZero => 26,
-            Error::AirnodeIdZero => 27,
-            Error::SetterCanSetIndefiniteStatus => 28,
+            Error::InvalidName(name) => payload.with_detail("name", name),
+            _ => payload,
+        }
+    }
+}
+
+impl From<&DapiUpdateError> for ErrorPayload {
+    fn from(e: &DapiUpdateError) -> Self {
+        let payload = Self::from(&e.error);
+        match e.beacon_index {
+            Some(index) => payload.with_detail("beacon_index", index),
+            None => payload,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        let mut count = 0;
+        let base = ErrorNamespace::Common.base();
+        for code in 0..base + 2 * ERROR_NAMESPACE_SIZE {
+            if let Some(e) = Error::from_code(code) {
+                assert_eq!(e.code(), code);
+                assert!(!e.to_string().is_empty());
+                count += 1;
+            }
+        }
+        assert_eq!(count, 51);
+        assert!(Error::from_code(base + 7).is_none());
+        assert!(Error::from_code(22).is_none());
+        assert_eq!(Error::NotAuthorized.code(), 6022);
+        assert!(matches!(
+            Error::from_code(u32::from(Error::InvalidName(String::from("a")))),
+            Some(Error::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn namespaces_do_not_overlap() {
+        assert_eq!(
+            ErrorNamespace::split(ErrorNamespace::Solana.base() + 10),
+            Some((ErrorNamespace::Solana, 10))
+        );
+        assert_eq!(
+            ErrorNamespace::split(Error::NotAuthorized.code()),
+            Some((ErrorNamespace::Common, 22))
+        );
+        assert_eq!(
+            ErrorNamespace::split(ERROR_CODE_OFFSET + 2 * ERROR_NAMESPACE_SIZE),
+            None
+        );
+        // anchor's own errors
+        assert_eq!(ErrorNamespace::split(ERROR_CODE_OFFSET - 1), None);
+        assert_eq!(ErrorNamespace::split(2000), None);
+    }
+
+    #[test]
+    fn payload_survives_wrapping() {
+        let payload =
+            ErrorPayload::from(&Error::BeaconDataNotFound).with_id("beacon_id", &[1u8; 2]);
+        let message = format!("Smart contract panicked: {}", payload.to_json());
+
+        let parsed = ErrorPayload::parse(&message).unwrap();
+        assert_eq!(parsed, payload);
+        assert_eq!(parsed.name, "BeaconDataNotFound");
+        assert_eq!(parsed.details["beacon_id"], "0x0101");
+        assert!(matches!(parsed.error(), Some(Error::BeaconDataNotFound)));
+        assert!(ErrorPayload::parse("Smart contract panicked: AccessDenied").is_none());
+    }
+}
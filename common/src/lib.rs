@@ -1,23 +1,70 @@
+// The library runs inside contracts, where a panic aborts the call without an
+// error code. Fallible functions return `Result<_, Error>` instead.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::indexing_slicing,
+        clippy::arithmetic_side_effects
+    )
+)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod abi;
 mod access;
 mod agg;
 mod beacon;
+mod circuit_breaker;
 mod datapoint;
 #[cfg(feature = "dummy")]
 pub mod dummy;
 mod error;
+mod merkle;
+mod metering;
+mod pause;
+mod permit;
+#[cfg(feature = "signer")]
+pub mod signer;
+mod subscription;
+mod timestamp;
 pub mod util;
 mod whitelist;
 
+/// The `alloc` types used across the crate, so that modules build the same
+/// with and without `std`
+mod prelude {
+    pub use alloc::collections::BTreeMap;
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
+
 pub use access::*;
 pub use agg::Aggregator;
 pub use beacon::*;
+pub use circuit_breaker::*;
 pub use datapoint::DataPoint;
-pub use error::Error;
+pub use error::{
+    DapiUpdateError, Error, ErrorNamespace, ErrorPayload, ERROR_CODE_OFFSET, ERROR_NAMESPACE_SIZE,
+};
+pub use merkle::*;
+pub use metering::*;
+pub use pause::*;
+pub use permit::*;
+pub use subscription::*;
+pub use timestamp::Timestamp;
 pub use util::*;
 pub use whitelist::*;
 
-pub type Bytes = Vec<u8>;
+pub type Bytes = alloc::vec::Vec<u8>;
 pub type Bytes32 = [u8; 32];
 pub const BYTES32_ZERO: Bytes32 = [0u8; 32];
 
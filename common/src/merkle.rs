@@ -0,0 +1,193 @@
+use crate::abi::{keccak256, Token};
+use crate::prelude::*;
+use crate::{ensure, keccak_packed, Bytes32, Error, Storage, Zero, BYTES32_ZERO};
+
+/// Leaf of `user` in a whitelist Merkle tree.
+/// The user is hashed twice so that a leaf can never be mistaken for an
+/// inner node, which is the hash of 64 bytes.
+///
+/// # Arguments
+///
+/// * `user` User address
+pub fn merkle_leaf(user: &[u8]) -> Bytes32 {
+    keccak256(&keccak_packed(&[Token::FixedBytes(user.to_vec())]))
+}
+
+/// Hashes a pair of nodes in sorted order, so that proofs do not need to
+/// record whether a sibling is on the left or on the right
+fn hash_sorted_pair(a: &Bytes32, b: &Bytes32) -> Bytes32 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    keccak_packed(&[
+        Token::FixedBytes(first.to_vec()),
+        Token::FixedBytes(second.to_vec()),
+    ])
+}
+
+/// Checks `proof` proves `leaf` is in the tree with `root`
+///
+/// # Arguments
+///
+/// * `root` The Merkle root
+/// * `leaf` The leaf, see `merkle_leaf`
+/// * `proof` The sibling hashes from the leaf up to the root
+pub fn verify_merkle_proof(root: &Bytes32, leaf: &Bytes32, proof: &[Bytes32]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(*leaf, |node, sibling| hash_sorted_pair(&node, sibling));
+    computed == *root
+}
+
+/// Publishes the Merkle root of the readers whitelisted for the service.
+/// A zero root disables Merkle proof whitelisting for the service.
+///
+/// # Arguments
+///
+/// * `storage` The storage between service IDs and Merkle roots
+/// * `manager` The manager
+/// * `msg_sender` Address of who sent the transaction
+/// * `service_id` Service ID
+/// * `root` The Merkle root, see `MerkleTree`
+pub fn set_whitelist_merkle_root<S: Storage<Bytes32>, Address: PartialEq>(
+    storage: &mut S,
+    manager: &Address,
+    msg_sender: &Address,
+    service_id: &Bytes32,
+    root: Bytes32,
+) -> Result<(), Error> {
+    ensure!(manager == msg_sender, Error::NotAuthorized)?;
+    ensure!(!service_id.is_zero(), Error::ServiceIdZero)?;
+    storage.store(*service_id, root);
+    Ok(())
+}
+
+/// Returns if `proof` proves the user is in the Merkle tree published for the service
+///
+/// # Arguments
+///
+/// * `storage` The storage between service IDs and Merkle roots
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `proof` The sibling hashes from the leaf of the user up to the root
+pub fn user_is_whitelisted_with_proof<S: Storage<Bytes32>>(
+    storage: &S,
+    service_id: &Bytes32,
+    user: &[u8],
+    proof: &[Bytes32],
+) -> bool {
+    match storage.get(service_id) {
+        Some(root) if !root.is_zero() => verify_merkle_proof(&root, &merkle_leaf(user), proof),
+        _ => false,
+    }
+}
+
+/// Off-chain builder of whitelist Merkle trees. The manager publishes `root`
+/// with `set_whitelist_merkle_root` and hands each reader its `proof`.
+/// When a layer has an odd number of nodes the last one is carried up as is.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    layers: Vec<Vec<Bytes32>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree of the leaves, see `merkle_leaf`
+    pub fn new(leaves: Vec<Bytes32>) -> Self {
+        let mut layers = vec![leaves];
+        while let Some(layer) = layers.last().filter(|l| l.len() > 1) {
+            let next = layer
+                .chunks(2)
+                .filter_map(|pair| pair.iter().copied().reduce(|a, b| hash_sorted_pair(&a, &b)))
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Builds the tree of the whitelisted users
+    pub fn from_users<U: AsRef<[u8]>>(users: &[U]) -> Self {
+        Self::new(users.iter().map(|u| merkle_leaf(u.as_ref())).collect())
+    }
+
+    /// The root of the tree, zero if there are no leaves
+    pub fn root(&self) -> Bytes32 {
+        self.layers
+            .last()
+            .and_then(|l| l.first())
+            .copied()
+            .unwrap_or(BYTES32_ZERO)
+    }
+
+    /// Returns the proof of `leaf`, `None` if the leaf is not in the tree
+    pub fn proof(&self, leaf: &Bytes32) -> Option<Vec<Bytes32>> {
+        let mut index = self.layers.first()?.iter().position(|l| l == leaf)?;
+        // the root layer has no siblings
+        let (_, layers) = self.layers.split_last()?;
+        let mut proof = Vec::new();
+        for layer in layers {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    /// Returns the proof of `user`, `None` if the user is not in the tree
+    pub fn user_proof(&self, user: &[u8]) -> Option<Vec<Bytes32>> {
+        self.proof(&merkle_leaf(user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::InMemoryStorage;
+
+    const SERVICE_ID: Bytes32 = [1u8; 32];
+
+    #[test]
+    fn proofs_verify_for_every_user() {
+        let users: Vec<Vec<u8>> = (0u8..7).map(|i| vec![i; 20]).collect();
+        let tree = MerkleTree::from_users(&users);
+        let root = tree.root();
+        for user in &users {
+            let proof = tree.user_proof(user).unwrap();
+            assert!(verify_merkle_proof(&root, &merkle_leaf(user), &proof));
+        }
+        assert!(tree.user_proof(&[9u8; 20]).is_none());
+
+        let proof = tree.user_proof(&users[0]).unwrap();
+        assert!(!verify_merkle_proof(&root, &merkle_leaf(&users[1]), &proof));
+    }
+
+    #[test]
+    fn published_root_whitelists_users() {
+        let users = [b"alice.near".to_vec(), b"bob.near".to_vec()];
+        let tree = MerkleTree::from_users(&users);
+        let proof = tree.user_proof(&users[1]).unwrap();
+
+        let mut storage = InMemoryStorage::default();
+        assert!(!user_is_whitelisted_with_proof(
+            &storage,
+            &SERVICE_ID,
+            &users[1],
+            &proof
+        ));
+        assert!(matches!(
+            set_whitelist_merkle_root(&mut storage, &1u8, &2u8, &SERVICE_ID, tree.root()),
+            Err(Error::NotAuthorized)
+        ));
+        set_whitelist_merkle_root(&mut storage, &1u8, &1u8, &SERVICE_ID, tree.root()).unwrap();
+        assert!(user_is_whitelisted_with_proof(
+            &storage,
+            &SERVICE_ID,
+            &users[1],
+            &proof
+        ));
+        assert!(!user_is_whitelisted_with_proof(
+            &storage,
+            &SERVICE_ID,
+            b"carol.near",
+            &proof
+        ));
+    }
+}
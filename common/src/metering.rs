@@ -0,0 +1,244 @@
+use crate::abi::{Token, U256};
+use crate::{
+    ensure, keccak_packed, Bytes32, Error, Storage, Timestamp, TimestampChecker, WhitelistStatus,
+};
+
+/// How a reader is allowed to read a data feed. Usage quotas are configured
+/// per tier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReaderTier {
+    /// Has the unlimited reader role
+    UnlimitedReader,
+    /// Indefinitely whitelisted by at least one account
+    IndefinitelyWhitelisted,
+    /// Temporarily whitelisted, e.g. through a subscription
+    TemporarilyWhitelisted,
+    /// Whitelisted with a Merkle proof
+    MerkleWhitelisted,
+}
+
+impl From<ReaderTier> for u8 {
+    fn from(t: ReaderTier) -> Self {
+        match t {
+            ReaderTier::UnlimitedReader => 0,
+            ReaderTier::IndefinitelyWhitelisted => 1,
+            ReaderTier::TemporarilyWhitelisted => 2,
+            ReaderTier::MerkleWhitelisted => 3,
+        }
+    }
+}
+
+impl TryFrom<u8> for ReaderTier {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(ReaderTier::UnlimitedReader),
+            1 => Ok(ReaderTier::IndefinitelyWhitelisted),
+            2 => Ok(ReaderTier::TemporarilyWhitelisted),
+            3 => Ok(ReaderTier::MerkleWhitelisted),
+            _ => Err(Error::InvalidReaderTier),
+        }
+    }
+}
+
+/// Returns the tier the whitelist status puts the user in at `now`, `None` if
+/// the status does not whitelist the user. Indefinite whitelisting takes
+/// precedence over temporary whitelisting.
+///
+/// # Arguments
+///
+/// * `status` The whitelist status of the user
+/// * `now` The current timestamp
+pub fn whitelist_tier(status: &WhitelistStatus, now: Timestamp) -> Option<ReaderTier> {
+    if !status.indefinite_whitelist_count.is_zero() {
+        Some(ReaderTier::IndefinitelyWhitelisted)
+    } else if status.expiration_timestamp > now {
+        Some(ReaderTier::TemporarilyWhitelisted)
+    } else {
+        None
+    }
+}
+
+/// Returns the usage epoch `now` falls in
+///
+/// # Arguments
+///
+/// * `now` The current timestamp
+/// * `epoch_length` Length of an epoch in seconds, every timestamp is in epoch zero if it is zero
+pub fn usage_epoch(now: Timestamp, epoch_length: u64) -> u64 {
+    now.as_secs().checked_div(epoch_length).unwrap_or(0)
+}
+
+/// Storage key of the read count of `reader` for the data point in `epoch`
+///
+/// # Arguments
+///
+/// * `data_point_id` Data point ID
+/// * `reader` Reader address
+/// * `epoch` Usage epoch, see `usage_epoch`
+pub fn read_count_key(data_point_id: &Bytes32, reader: &[u8], epoch: u64) -> Bytes32 {
+    keccak_packed(&[
+        Token::FixedBytes(data_point_id.to_vec()),
+        Token::FixedBytes(reader.to_vec()),
+        Token::Uint(U256::from(epoch)),
+    ])
+}
+
+/// Counts a read of the data point by `reader` in the current epoch and
+/// checks it is within `quota`. Metering is disabled while `epoch_length`
+/// is zero, in which case nothing is counted.
+/// Returns the read count of the epoch including this read.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `read_count_key` and read counts
+/// * `clock` The clock used to find the current epoch
+/// * `epoch_length` Length of an epoch in seconds
+/// * `quota` Maximum reads per epoch of the tier of the reader, `None` if unlimited
+/// * `data_point_id` Data point ID
+/// * `reader` Reader address
+pub fn record_read<S: Storage<u64>, C: TimestampChecker>(
+    storage: &mut S,
+    clock: &C,
+    epoch_length: u64,
+    quota: Option<u64>,
+    data_point_id: &Bytes32,
+    reader: &[u8],
+) -> Result<u64, Error> {
+    if epoch_length == 0 {
+        return Ok(0);
+    }
+    let epoch = usage_epoch(clock.current_timestamp(), epoch_length);
+    let key = read_count_key(data_point_id, reader, epoch);
+    let count = storage
+        .get(&key)
+        .unwrap_or_default()
+        .checked_add(1)
+        .ok_or(Error::ReadQuotaExceeded)?;
+    if let Some(quota) = quota {
+        ensure!(count <= quota, Error::ReadQuotaExceeded)?;
+    }
+    storage.store(key, count);
+    Ok(count)
+}
+
+/// Checks that `msg_sender` can prune the read counts of `epoch`. Only the
+/// manager can prune, and only the counts of closed epochs so that the
+/// current epoch keeps being metered. Every epoch is closed while metering
+/// is disabled. The counts are removed by the caller, as `Storage` has no removal.
+///
+/// # Arguments
+///
+/// * `manager` The manager address
+/// * `msg_sender` The caller address
+/// * `clock` The clock used to find the current epoch
+/// * `epoch_length` Length of an epoch in seconds
+/// * `epoch` Usage epoch of the counts to prune
+pub fn ensure_can_prune_read_counts<Address: PartialEq, C: TimestampChecker>(
+    manager: &Address,
+    msg_sender: &Address,
+    clock: &C,
+    epoch_length: u64,
+    epoch: u64,
+) -> Result<(), Error> {
+    ensure!(manager == msg_sender, Error::NotAuthorized)?;
+    if epoch_length == 0 {
+        return Ok(());
+    }
+    ensure!(
+        epoch < usage_epoch(clock.current_timestamp(), epoch_length),
+        Error::UsageEpochNotClosed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+
+    const DATA_POINT_ID: Bytes32 = [1u8; 32];
+    const READER: [u8; 4] = [2u8; 4];
+
+    #[test]
+    fn reads_are_counted_per_epoch() {
+        let mut storage = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        for expected in 1..=2 {
+            let count =
+                record_read(&mut storage, &clock, 100, Some(2), &DATA_POINT_ID, &READER).unwrap();
+            assert_eq!(count, expected);
+        }
+        assert!(matches!(
+            record_read(&mut storage, &clock, 100, Some(2), &DATA_POINT_ID, &READER),
+            Err(Error::ReadQuotaExceeded)
+        ));
+        assert_eq!(
+            storage.get(&read_count_key(&DATA_POINT_ID, &READER, 10)),
+            Some(2)
+        );
+
+        // a new epoch starts from zero
+        let clock = FixedClock::new(Timestamp::from_secs(1100));
+        let count =
+            record_read(&mut storage, &clock, 100, Some(2), &DATA_POINT_ID, &READER).unwrap();
+        assert_eq!(count, 1);
+
+        // disabled metering does not count
+        let count = record_read(&mut storage, &clock, 0, Some(0), &DATA_POINT_ID, &READER).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn closed_epochs_stay_readable_until_pruned() {
+        let mut storage = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        record_read(&mut storage, &clock, 100, None, &DATA_POINT_ID, &READER).unwrap();
+
+        let clock = FixedClock::new(Timestamp::from_secs(1100));
+        record_read(&mut storage, &clock, 100, None, &DATA_POINT_ID, &READER).unwrap();
+        assert_eq!(
+            storage.get(&read_count_key(&DATA_POINT_ID, &READER, 10)),
+            Some(1)
+        );
+        assert_eq!(
+            storage.get(&read_count_key(&DATA_POINT_ID, &READER, 11)),
+            Some(1)
+        );
+
+        let (manager, other) = (1u8, 2u8);
+        assert!(ensure_can_prune_read_counts(&manager, &manager, &clock, 100, 10).is_ok());
+        assert!(matches!(
+            ensure_can_prune_read_counts(&manager, &other, &clock, 100, 10),
+            Err(Error::NotAuthorized)
+        ));
+        assert!(matches!(
+            ensure_can_prune_read_counts(&manager, &manager, &clock, 100, 11),
+            Err(Error::UsageEpochNotClosed)
+        ));
+        assert!(ensure_can_prune_read_counts(&manager, &manager, &clock, 0, 11).is_ok());
+    }
+
+    #[test]
+    fn indefinite_whitelist_takes_precedence() {
+        let now = Timestamp::from_secs(1000);
+        let mut status = WhitelistStatus {
+            expiration_timestamp: Timestamp::from_secs(2000),
+            indefinite_whitelist_count: U256::from(1u8),
+        };
+        assert_eq!(
+            whitelist_tier(&status, now),
+            Some(ReaderTier::IndefinitelyWhitelisted)
+        );
+        status.indefinite_whitelist_count = U256::zero();
+        assert_eq!(
+            whitelist_tier(&status, now),
+            Some(ReaderTier::TemporarilyWhitelisted)
+        );
+        assert_eq!(whitelist_tier(&status, Timestamp::from_secs(2000)), None);
+        assert!(matches!(
+            ReaderTier::try_from(3u8),
+            Ok(ReaderTier::MerkleWhitelisted)
+        ));
+    }
+}
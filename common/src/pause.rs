@@ -0,0 +1,124 @@
+use crate::{ensure, AccessControlRegistry, Error, StaticRole};
+
+/// The pause state of the whole contract or of a single data feed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PauseState {
+    /// Updates and reads are allowed
+    #[default]
+    Unpaused,
+    /// Updates are blocked, reads are still allowed
+    UpdatesPaused,
+    /// Emergency stop, both updates and reads are blocked
+    UpdatesAndReadsPaused,
+}
+
+impl PauseState {
+    /// Returns if updates are blocked in this state
+    pub fn blocks_updates(&self) -> bool {
+        *self != PauseState::Unpaused
+    }
+
+    /// Returns if reads are blocked in this state
+    pub fn blocks_reads(&self) -> bool {
+        *self == PauseState::UpdatesAndReadsPaused
+    }
+}
+
+impl From<PauseState> for u8 {
+    fn from(s: PauseState) -> Self {
+        match s {
+            PauseState::Unpaused => 0,
+            PauseState::UpdatesPaused => 1,
+            PauseState::UpdatesAndReadsPaused => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for PauseState {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(PauseState::Unpaused),
+            1 => Ok(PauseState::UpdatesPaused),
+            2 => Ok(PauseState::UpdatesAndReadsPaused),
+            _ => Err(Error::InvalidPauseState),
+        }
+    }
+}
+
+/// Checks if the account can change the pause states, i.e. it is the
+/// manager or has the pauser role
+///
+/// # Arguments
+///
+/// * `access` The access control registry used
+/// * `msg_sender` Address of who sent the transaction
+pub fn ensure_can_pause<A: AccessControlRegistry>(
+    access: &A,
+    msg_sender: &A::Address,
+) -> Result<(), Error> {
+    let role = access.find_static_role(StaticRole::PauserRole);
+    ensure!(
+        access.manager() == msg_sender || access.has_role(&role, msg_sender),
+        Error::AccessDenied
+    )
+}
+
+/// Checks if the data feed can be updated
+///
+/// # Arguments
+///
+/// * `global` The pause state of the contract
+/// * `data_feed` The pause state of the data feed to update
+pub fn ensure_updates_allowed(global: PauseState, data_feed: PauseState) -> Result<(), Error> {
+    ensure!(
+        !global.blocks_updates() && !data_feed.blocks_updates(),
+        Error::UpdatesPaused
+    )
+}
+
+/// Checks if the data feed can be read
+///
+/// # Arguments
+///
+/// * `global` The pause state of the contract
+/// * `data_feed` The pause state of the data feed to read
+pub fn ensure_reads_allowed(global: PauseState, data_feed: PauseState) -> Result<(), Error> {
+    ensure!(
+        !global.blocks_reads() && !data_feed.blocks_reads(),
+        Error::ReadsPaused
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_state_blocks() {
+        use PauseState::*;
+        assert!(ensure_updates_allowed(Unpaused, Unpaused).is_ok());
+        assert!(ensure_reads_allowed(Unpaused, Unpaused).is_ok());
+
+        assert!(ensure_updates_allowed(UpdatesPaused, Unpaused).is_err());
+        assert!(ensure_updates_allowed(Unpaused, UpdatesPaused).is_err());
+        assert!(ensure_reads_allowed(UpdatesPaused, UpdatesPaused).is_ok());
+
+        assert!(ensure_updates_allowed(Unpaused, UpdatesAndReadsPaused).is_err());
+        assert!(ensure_reads_allowed(Unpaused, UpdatesAndReadsPaused).is_err());
+        assert!(ensure_reads_allowed(UpdatesAndReadsPaused, Unpaused).is_err());
+    }
+
+    #[test]
+    fn pause_state_u8_round_trip() {
+        for s in [
+            PauseState::Unpaused,
+            PauseState::UpdatesPaused,
+            PauseState::UpdatesAndReadsPaused,
+        ] {
+            assert_eq!(PauseState::try_from(u8::from(s)).unwrap(), s);
+        }
+        assert!(PauseState::try_from(3).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use crate::abi::{keccak256, Token, U256};
+use crate::prelude::*;
+use crate::{
+    ensure, keccak_packed, Bytes32, Error, SignatureManger, Storage, Timestamp, TimestampChecker,
+    WhitelistRolesWithManager, Zero,
+};
+
+/// Name of the whitelist permit domain, see `permit_domain_separator`
+const WHITELIST_PERMIT_DOMAIN: &str = "api3 whitelist permit";
+
+/// Permit to set the whitelist expiration of `user` for the service, signed
+/// off-chain by an account with the whitelist expiration setter role so that
+/// anyone can submit it. The permit is only valid for the contract and the
+/// chain it names.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhitelistPermit {
+    /// Chain the permit is valid on, the contract of each chain documents its value
+    pub chain_id: String,
+    /// Account or program ID of the contract the permit is valid for
+    pub contract: Vec<u8>,
+    /// Service ID
+    pub service_id: Bytes32,
+    /// User address
+    pub user: Vec<u8>,
+    /// Timestamp at which the temporary whitelist will expire
+    pub expiration_timestamp: Timestamp,
+    /// Nonce of the setter, see `permit_nonce_key`
+    pub nonce: u64,
+    /// The permit cannot be used after this timestamp
+    pub deadline: Timestamp,
+}
+
+impl WhitelistPermit {
+    /// The message the setter signs
+    pub fn message(&self) -> Bytes32 {
+        keccak_packed(&[
+            Token::FixedBytes(permit_domain_separator(&self.chain_id, &self.contract).to_vec()),
+            Token::FixedBytes(self.service_id.to_vec()),
+            Token::FixedBytes(self.user.clone()),
+            Token::Uint(U256::from(self.expiration_timestamp.as_secs())),
+            Token::Uint(U256::from(self.nonce)),
+            Token::Uint(U256::from(self.deadline.as_secs())),
+        ])
+    }
+}
+
+/// Separates the permits of different contracts and chains, so that a permit
+/// cannot be replayed on another deployment where the setter has the same role
+///
+/// # Arguments
+///
+/// * `chain_id` Chain the permit is valid on
+/// * `contract` Account or program ID of the contract
+pub fn permit_domain_separator(chain_id: &str, contract: &[u8]) -> Bytes32 {
+    keccak_packed(&[
+        Token::FixedBytes(keccak256(WHITELIST_PERMIT_DOMAIN.as_bytes()).to_vec()),
+        Token::FixedBytes(keccak256(chain_id.as_bytes()).to_vec()),
+        Token::FixedBytes(keccak256(contract).to_vec()),
+    ])
+}
+
+/// Storage key of the next permit nonce of `setter`
+///
+/// # Arguments
+///
+/// * `setter` Address of the permit signer
+pub fn permit_nonce_key(setter: &[u8]) -> Bytes32 {
+    keccak_packed(&[
+        Token::String(String::from("whitelist permit nonce")),
+        Token::FixedBytes(setter.to_vec()),
+    ])
+}
+
+/// Signature scheme of whitelist permits. The setter of a permit is the
+/// account of the key that signed it, so that the account checked for the
+/// role cannot be named apart from the key.
+pub trait PermitSigner: SignatureManger {
+    /// Account address of the chain
+    type Address;
+
+    /// Returns the account of the public key of a setter
+    ///
+    /// # Arguments
+    ///
+    /// * `key` Public key of the permit signer
+    fn address_of(key: &[u8]) -> Self::Address;
+}
+
+/// Checks the permit is signed with `setter_key`, whose account must have the
+/// whitelist expiration setter role or be the manager, and consumes its nonce
+/// so that it cannot be replayed. Applying the expiration is up to the caller.
+/// Returns the account of the setter.
+///
+/// # Arguments
+///
+/// * `roles` The whitelist roles used to check the setter
+/// * `nonces` The storage between `permit_nonce_key` and the next nonce
+/// * `clock` The clock used to check the deadline
+/// * `permit` The permit
+/// * `setter_key` Public key of the permit signer
+/// * `signature` Signature of `permit.message()`
+pub fn use_whitelist_permit<R, N, S, C>(
+    roles: &R,
+    nonces: &mut N,
+    clock: &C,
+    permit: &WhitelistPermit,
+    setter_key: &[u8],
+    signature: &[u8],
+) -> Result<R::Address, Error>
+where
+    R: WhitelistRolesWithManager,
+    N: Storage<u64>,
+    S: PermitSigner<Address = R::Address>,
+    C: TimestampChecker,
+{
+    let setter = S::address_of(setter_key);
+    ensure!(
+        clock.current_timestamp() <= permit.deadline,
+        Error::PermitExpired
+    )?;
+    ensure!(!permit.service_id.is_zero(), Error::ServiceIdZero)?;
+    ensure!(!permit.user.is_empty(), Error::UserAddressZero)?;
+    ensure!(
+        roles.has_whitelist_expiration_setter_role_or_is_manager(&setter),
+        Error::NotAuthorized
+    )?;
+
+    let key = permit_nonce_key(setter.as_ref());
+    let nonce = nonces.get(&key).unwrap_or_default();
+    ensure!(permit.nonce == nonce, Error::InvalidPermitNonce)?;
+    ensure!(
+        S::verify(setter_key, &permit.message(), signature),
+        Error::InvalidSignature
+    )?;
+    nonces.store(key, nonce.checked_add(1).ok_or(Error::InvalidPermitNonce)?);
+    Ok(setter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{DummyWhitelist, FixedClock, InMemoryStorage};
+    use crate::{AccessControlRegistryAdminnedWithManager, WhitelistRoles};
+
+    /// Accepts the message itself as the signature, the key is the address
+    struct EchoSignature;
+    impl SignatureManger for EchoSignature {
+        fn verify(_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+            message == signature
+        }
+    }
+    impl PermitSigner for EchoSignature {
+        type Address = Bytes32;
+        fn address_of(key: &[u8]) -> Bytes32 {
+            Bytes32::try_from(key).unwrap_or_default()
+        }
+    }
+
+    /// Only the manager can set whitelist expirations
+    struct ManagerOnly {
+        manager: Bytes32,
+    }
+    impl AccessControlRegistryAdminnedWithManager for ManagerOnly {
+        type Address = Bytes32;
+        fn manager(&self) -> &Bytes32 {
+            &self.manager
+        }
+        fn admin_role_description(&self) -> String {
+            String::from("admin")
+        }
+        fn admin_role_description_hash(&self) -> Bytes32 {
+            Bytes32::default()
+        }
+        fn admin_role(&self) -> Bytes32 {
+            Bytes32::default()
+        }
+    }
+    impl WhitelistRoles for ManagerOnly {}
+    impl WhitelistRolesWithManager for ManagerOnly {
+        fn has_whitelist_expiration_extender_role_or_is_manager(&self, account: &Bytes32) -> bool {
+            *account == self.manager
+        }
+        fn has_indefinite_whitelister_role_or_is_manager(&self, account: &Bytes32) -> bool {
+            *account == self.manager
+        }
+        fn has_whitelist_expiration_setter_role_or_is_manager(&self, account: &Bytes32) -> bool {
+            *account == self.manager
+        }
+    }
+
+    #[test]
+    fn permit_can_only_be_used_once() {
+        let roles = DummyWhitelist::<Bytes32>::default();
+        let mut nonces = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        let setter = [3u8; 32];
+        let mut permit = WhitelistPermit {
+            chain_id: String::from("testnet"),
+            contract: b"dapi.testnet".to_vec(),
+            service_id: [1u8; 32],
+            user: vec![2u8; 4],
+            expiration_timestamp: Timestamp::from_secs(5000),
+            nonce: 0,
+            deadline: Timestamp::from_secs(2000),
+        };
+
+        use_whitelist_permit::<_, _, EchoSignature, _>(
+            &roles,
+            &mut nonces,
+            &clock,
+            &permit,
+            &setter,
+            &permit.message(),
+        )
+        .unwrap();
+        assert!(matches!(
+            use_whitelist_permit::<_, _, EchoSignature, _>(
+                &roles,
+                &mut nonces,
+                &clock,
+                &permit,
+                &setter,
+                &permit.message(),
+            ),
+            Err(Error::InvalidPermitNonce)
+        ));
+
+        permit.nonce = 1;
+        assert!(matches!(
+            use_whitelist_permit::<_, _, EchoSignature, _>(
+                &roles,
+                &mut nonces,
+                &clock,
+                &permit,
+                &setter,
+                &[0u8; 32],
+            ),
+            Err(Error::InvalidSignature)
+        ));
+
+        let late = FixedClock::new(Timestamp::from_secs(2001));
+        assert!(matches!(
+            use_whitelist_permit::<_, _, EchoSignature, _>(
+                &roles,
+                &mut nonces,
+                &late,
+                &permit,
+                &setter,
+                &permit.message(),
+            ),
+            Err(Error::PermitExpired)
+        ));
+    }
+
+    #[test]
+    fn permit_is_bound_to_its_contract_and_chain() {
+        let roles = DummyWhitelist::<Bytes32>::default();
+        let mut nonces = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        let setter = [3u8; 32];
+        let permit = WhitelistPermit {
+            chain_id: String::from("testnet"),
+            contract: b"dapi.testnet".to_vec(),
+            service_id: [1u8; 32],
+            user: vec![2u8; 4],
+            expiration_timestamp: Timestamp::from_secs(5000),
+            nonce: 0,
+            deadline: Timestamp::from_secs(2000),
+        };
+        let signature = permit.message();
+
+        let other_contract = WhitelistPermit {
+            contract: b"other.testnet".to_vec(),
+            ..permit.clone()
+        };
+        let other_chain = WhitelistPermit {
+            chain_id: String::from("near"),
+            ..permit.clone()
+        };
+        for replayed in [other_contract, other_chain] {
+            assert!(matches!(
+                use_whitelist_permit::<_, _, EchoSignature, _>(
+                    &roles,
+                    &mut nonces,
+                    &clock,
+                    &replayed,
+                    &setter,
+                    &signature,
+                ),
+                Err(Error::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn permit_setter_is_the_account_of_the_key() {
+        let setter = [3u8; 32];
+        let roles = ManagerOnly { manager: setter };
+        let mut nonces = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        let permit = WhitelistPermit {
+            chain_id: String::from("testnet"),
+            contract: b"dapi.testnet".to_vec(),
+            service_id: [1u8; 32],
+            user: vec![2u8; 4],
+            expiration_timestamp: Timestamp::from_secs(5000),
+            nonce: 0,
+            deadline: Timestamp::from_secs(2000),
+        };
+
+        // signing with another key does not act for the role holder
+        assert!(matches!(
+            use_whitelist_permit::<_, _, EchoSignature, _>(
+                &roles,
+                &mut nonces,
+                &clock,
+                &permit,
+                &[4u8; 32],
+                &permit.message(),
+            ),
+            Err(Error::NotAuthorized)
+        ));
+        let used_by = use_whitelist_permit::<_, _, EchoSignature, _>(
+            &roles,
+            &mut nonces,
+            &clock,
+            &permit,
+            &setter,
+            &permit.message(),
+        )
+        .unwrap();
+        assert_eq!(used_by, setter);
+        assert_eq!(nonces.get(&permit_nonce_key(&setter)), Some(1));
+    }
+}
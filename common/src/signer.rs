@@ -0,0 +1,334 @@
+//! Produces the signed data of Airnodes off-chain, e.g. for mock Airnodes,
+//! relayers and tests. The output is what `update_dapi_with_signed_data` takes.
+
+use crate::abi::{encode, keccak256, Int, Token, Uint};
+use crate::prelude::*;
+use crate::{derive_beacon_id, keccak_packed, Bytes, Bytes32, Error, SignatureManger, Timestamp};
+
+/// The key an Airnode signs its data with
+pub trait AirnodeSigner {
+    /// The Airnode as the contracts know it, which derives the beacon IDs
+    fn airnode(&self) -> Bytes;
+
+    /// Signs the message of the signed data, see `SignedData::message`
+    fn sign(&self, message: &Bytes32) -> Bytes;
+}
+
+/// Signs with an ed25519 key, as Airnodes do for NEAR, Solana and the other
+/// ed25519 chains. The Airnode is the 32 byte public key, the message is
+/// signed as is.
+pub struct Ed25519Signer {
+    keypair: ed25519_dalek::Keypair,
+}
+
+impl Ed25519Signer {
+    /// The signer with the 32 byte secret key
+    pub fn from_secret_key(secret_key: &[u8]) -> Result<Self, Error> {
+        let secret = ed25519_dalek::SecretKey::from_bytes(secret_key)
+            .map_err(|_| Error::InvalidSecretKey)?;
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        Ok(Self {
+            keypair: ed25519_dalek::Keypair { secret, public },
+        })
+    }
+}
+
+impl AirnodeSigner for Ed25519Signer {
+    fn airnode(&self) -> Bytes {
+        self.keypair.public.to_bytes().to_vec()
+    }
+
+    fn sign(&self, message: &Bytes32) -> Bytes {
+        use ed25519_dalek::Signer;
+        self.keypair.sign(message).to_bytes().to_vec()
+    }
+}
+
+/// Signs with a secp256k1 key, as Airnodes do for EVM chains. The Airnode is
+/// the 20 byte address of the key, the message is signed as an Ethereum
+/// signed message and the signature is `r`, `s` and `v` (27 or 28).
+pub struct Secp256k1Signer {
+    secret_key: libsecp256k1::SecretKey,
+}
+
+impl Secp256k1Signer {
+    /// The signer with the 32 byte secret key
+    pub fn from_secret_key(secret_key: &[u8]) -> Result<Self, Error> {
+        let secret_key = libsecp256k1::SecretKey::parse_slice(secret_key)
+            .map_err(|_| Error::InvalidSecretKey)?;
+        Ok(Self { secret_key })
+    }
+}
+
+impl AirnodeSigner for Secp256k1Signer {
+    fn airnode(&self) -> Bytes {
+        let public_key = libsecp256k1::PublicKey::from_secret_key(&self.secret_key).serialize();
+        keccak256(&public_key[1..])[12..].to_vec()
+    }
+
+    fn sign(&self, message: &Bytes32) -> Bytes {
+        let message = libsecp256k1::Message::parse(&eth_signed_message_hash(message));
+        let (signature, recovery_id) = libsecp256k1::sign(&message, &self.secret_key);
+        let mut signature = signature.serialize().to_vec();
+        signature.push(recovery_id.serialize().saturating_add(27));
+        signature
+    }
+}
+
+/// Verifies the signatures of `Ed25519Signer`, the key is the public key
+pub struct Ed25519Verifier;
+
+impl SignatureManger for Ed25519Verifier {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        use ed25519_dalek::Verifier;
+        match (
+            ed25519_dalek::PublicKey::from_bytes(key),
+            ed25519_dalek::Signature::try_from(signature),
+        ) {
+            (Ok(key), Ok(signature)) => key.verify(message, &signature).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+/// Verifies the signatures of `Secp256k1Signer` the way `ecrecover` does,
+/// the key is the address of the signer
+pub struct Secp256k1Verifier;
+
+impl SignatureManger for Secp256k1Verifier {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        if message.len() != 32 || signature.len() != 65 {
+            return false;
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(message);
+        let message = libsecp256k1::Message::parse(&eth_signed_message_hash(&hash));
+        let Some((v, signature)) = signature.split_last() else {
+            return false;
+        };
+        let recovered = libsecp256k1::Signature::parse_standard_slice(signature).and_then(|s| {
+            let recovery_id = libsecp256k1::RecoveryId::parse_rpc(*v)?;
+            libsecp256k1::recover(&message, &s, &recovery_id)
+        });
+        match recovered {
+            Ok(public_key) => keccak256(&public_key.serialize()[1..])[12..] == *key,
+            Err(_) => false,
+        }
+    }
+}
+
+/// The hash EVM signers sign for `message`, i.e. `ECDSA.toEthSignedMessageHash`
+pub fn eth_signed_message_hash(message: &Bytes32) -> Bytes32 {
+    let mut prefixed = b"\x19Ethereum Signed Message:\n32".to_vec();
+    prefixed.extend_from_slice(message);
+    keccak256(&prefixed)
+}
+
+/// The signed data of a Beacon, one entry of the arguments of `update_dapi_with_signed_data`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedData {
+    /// Airnode, see `AirnodeSigner::airnode`
+    pub airnode: Bytes,
+    /// Template ID
+    pub template_id: Bytes32,
+    /// Timestamp in seconds, a big endian `uint256`
+    pub timestamp: Bytes32,
+    /// The value encoded in contract ABI as an `int256`
+    pub data: Bytes,
+    /// Empty if the Beacon is read from storage instead
+    pub signature: Bytes,
+}
+
+impl SignedData {
+    /// Signs `value` at `timestamp` for the Beacon of the signer and `template_id`
+    pub fn sign<S: AirnodeSigner>(
+        signer: &S,
+        template_id: Bytes32,
+        timestamp: Timestamp,
+        value: Int,
+    ) -> Self {
+        let mut signed = Self::unsigned(signer.airnode(), template_id);
+        Uint::from(timestamp.as_secs()).to_big_endian(&mut signed.timestamp);
+        signed.data = encode(&[Token::Int(value)]);
+        signed.signature = signer.sign(&signed.message());
+        signed
+    }
+
+    /// The Beacon without data, `update_dapi_with_signed_data` reads its
+    /// value from storage
+    pub fn unsigned(airnode: Bytes, template_id: Bytes32) -> Self {
+        Self {
+            airnode,
+            template_id,
+            timestamp: [0u8; 32],
+            data: vec![],
+            signature: vec![],
+        }
+    }
+
+    /// The message the Airnode signs
+    pub fn message(&self) -> Bytes32 {
+        keccak_packed(&[
+            Token::FixedBytes(self.template_id.to_vec()),
+            Token::Uint(Uint::from_big_endian(&self.timestamp)),
+            Token::Bytes(self.data.clone()),
+        ])
+    }
+
+    /// The ID of the Beacon the data is for
+    pub fn beacon_id(&self) -> Result<Bytes32, Error> {
+        derive_beacon_id(self.airnode.clone(), self.template_id)
+    }
+}
+
+/// The signed data of the Beacons of a dAPI, split into the arguments of
+/// `update_dapi_with_signed_data`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignedDataBatch {
+    pub airnodes: Vec<Bytes>,
+    pub template_ids: Vec<Bytes32>,
+    pub timestamps: Vec<Bytes32>,
+    pub data: Vec<Bytes>,
+    pub signatures: Vec<Bytes>,
+}
+
+impl FromIterator<SignedData> for SignedDataBatch {
+    fn from_iter<I: IntoIterator<Item = SignedData>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::default(), |mut batch, s| {
+            batch.airnodes.push(s.airnode);
+            batch.template_ids.push(s.template_id);
+            batch.timestamps.push(s.timestamp);
+            batch.data.push(s.data);
+            batch.signatures.push(s.signature);
+            batch
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+    use crate::{
+        decode_fulfillment_data, derive_dapi_id, update_dapi_with_signed_data, DapiUpdateError,
+        DataPoint, Storage,
+    };
+
+    const TEMPLATE_ID: Bytes32 = [1u8; 32];
+    const NOW: u64 = 1_650_000_000;
+
+    fn update<S: SignatureManger>(
+        storage: &mut InMemoryStorage<DataPoint>,
+        batch: SignedDataBatch,
+    ) -> Result<Bytes32, DapiUpdateError> {
+        // chains read unset data points as the default one, the memory storage does not
+        let beacon_ids = batch
+            .airnodes
+            .iter()
+            .zip(&batch.template_ids)
+            .map(|(a, t)| derive_beacon_id(a.clone(), *t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let dapi_id = derive_dapi_id(&beacon_ids);
+        if storage.get(&dapi_id).is_none() {
+            storage.store(dapi_id, DataPoint::default());
+        }
+        update_dapi_with_signed_data::<_, S, _>(
+            storage,
+            &FixedClock::new(Timestamp::from_secs(NOW)),
+            batch.airnodes,
+            batch.template_ids,
+            batch.timestamps,
+            batch.data,
+            batch.signatures,
+        )
+    }
+
+    #[test]
+    fn ed25519_signed_data_updates_dapi() {
+        let signers = (1..=3u8)
+            .map(|seed| Ed25519Signer::from_secret_key(&[seed; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let batch = signers
+            .iter()
+            .zip([100u64, 200, 400])
+            .map(|(s, v)| {
+                SignedData::sign(s, TEMPLATE_ID, Timestamp::from_secs(NOW - 10), Int::from(v))
+            })
+            .collect::<SignedDataBatch>();
+        assert_eq!(batch.airnodes[0].len(), 32);
+        assert_eq!(batch.signatures[0].len(), 64);
+
+        let mut storage = InMemoryStorage::default();
+        let dapi_id = update::<Ed25519Verifier>(&mut storage, batch.clone()).unwrap();
+        assert_eq!(
+            storage.get(&dapi_id),
+            Some(DataPoint::new(
+                Int::from(200),
+                Timestamp::from_secs(NOW - 10)
+            ))
+        );
+
+        // the data does not match the signature once it is changed
+        let mut tampered = batch;
+        tampered.data[1] = encode(&[Token::Int(Int::from(201))]);
+        assert!(matches!(
+            update::<Ed25519Verifier>(&mut storage, tampered),
+            Err(DapiUpdateError {
+                error: Error::InvalidSignature,
+                beacon_index: Some(1)
+            })
+        ));
+    }
+
+    #[test]
+    fn secp256k1_signed_data_updates_dapi() {
+        let mut secret_key = [0u8; 32];
+        secret_key[31] = 1;
+        let signer = Secp256k1Signer::from_secret_key(&secret_key).unwrap();
+        assert_eq!(
+            hex::encode(signer.airnode()),
+            "7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+
+        // negative values are encoded as two's complement `int256`
+        let minus_one = Int::MAX;
+        let signed = SignedData::sign(
+            &signer,
+            TEMPLATE_ID,
+            Timestamp::from_secs(NOW - 10),
+            minus_one,
+        );
+        assert_eq!(signed.data, vec![0xff; 32]);
+        assert_eq!(decode_fulfillment_data(&signed.data).unwrap(), minus_one);
+        assert_eq!(signed.signature.len(), 65);
+        assert!(matches!(signed.signature[64], 27 | 28));
+
+        // the Beacon of the other Airnode is read from storage
+        let other = Secp256k1Signer::from_secret_key(&[2u8; 32]).unwrap();
+        let other_beacon = SignedData::unsigned(other.airnode(), TEMPLATE_ID);
+        let mut storage = InMemoryStorage::default();
+        storage.store(
+            other_beacon.beacon_id().unwrap(),
+            DataPoint::new(Int::from(1), Timestamp::from_secs(NOW - 20)),
+        );
+        let dapi_id =
+            update::<Secp256k1Verifier>(&mut storage, [signed, other_beacon].into_iter().collect())
+                .unwrap();
+        assert_eq!(
+            storage.get(&dapi_id).map(|d| d.timestamp),
+            Some(Timestamp::from_secs(NOW - 15))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_secret_keys() {
+        assert!(matches!(
+            Ed25519Signer::from_secret_key(&[1u8; 31]),
+            Err(Error::InvalidSecretKey)
+        ));
+        assert!(matches!(
+            Secp256k1Signer::from_secret_key(&[0u8; 32]),
+            Err(Error::InvalidSecretKey)
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    ensure, whitelist_status_key, Bytes32, Error, Storage, Timestamp, TimestampChecker,
+    WhitelistStatus, Zero,
+};
+
+/// The price readers pay to whitelist themselves for a data feed, in the
+/// smallest unit of the native token of the chain (yoctoNEAR, lamports...)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SubscriptionPrice {
+    /// Amount charged per period
+    pub amount_per_period: u128,
+    /// Length of a period in seconds
+    pub period: u64,
+}
+
+impl SubscriptionPrice {
+    pub fn new(amount_per_period: u128, period: u64) -> Self {
+        Self {
+            amount_per_period,
+            period,
+        }
+    }
+
+    /// Subscriptions are disabled until a non-zero period is configured
+    pub fn is_configured(&self) -> bool {
+        self.period != 0
+    }
+
+    /// Returns the amount to pay for `periods` periods
+    ///
+    /// # Arguments
+    ///
+    /// * `periods` Number of periods to subscribe for
+    pub fn cost(&self, periods: u64) -> Result<u128, Error> {
+        ensure!(self.is_configured(), Error::SubscriptionNotConfigured)?;
+        ensure!(periods != 0, Error::InvalidSubscriptionPeriods)?;
+        self.amount_per_period
+            .checked_mul(u128::from(periods))
+            .ok_or(Error::InvalidSubscriptionPeriods)
+    }
+}
+
+/// Extends the temporary whitelist of `reader` for the data feed by `periods`
+/// periods of `price`. The extension starts from the current expiration if the
+/// reader is still whitelisted, otherwise from now. Checking that the reader
+/// paid `price.cost(periods)` is up to the chain.
+/// Returns the new expiration.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `clock` The clock used to find the start of the extension
+/// * `price` The subscription price
+/// * `data_feed_id` The data feed ID
+/// * `reader` The reader address
+/// * `periods` Number of periods to subscribe for
+pub fn extend_subscription<W: Storage<WhitelistStatus>, C: TimestampChecker>(
+    storage: &mut W,
+    clock: &C,
+    price: &SubscriptionPrice,
+    data_feed_id: &Bytes32,
+    reader: &[u8],
+    periods: u64,
+) -> Result<Timestamp, Error> {
+    price.cost(periods)?;
+    ensure!(!data_feed_id.is_zero(), Error::ServiceIdZero)?;
+    ensure!(!reader.is_empty(), Error::UserAddressZero)?;
+
+    let key = whitelist_status_key(data_feed_id, reader);
+    let mut status = storage.get(&key).unwrap_or_default();
+    let start = status.expiration_timestamp.max(clock.current_timestamp());
+    let expiration = price
+        .period
+        .checked_mul(periods)
+        .and_then(|secs| start.checked_add_secs(secs))
+        .ok_or(Error::InvalidSubscriptionPeriods)?;
+    status.expiration_timestamp = expiration;
+    storage.store(key, status);
+    Ok(expiration)
+}
+
+/// Checks the manager can withdraw `amount` of the subscription payments.
+/// Returns the balance left after the withdrawal.
+///
+/// # Arguments
+///
+/// * `manager` The manager
+/// * `msg_sender` Address of who sent the transaction
+/// * `balance` The subscription payments that were not withdrawn yet
+/// * `amount` The amount to withdraw
+pub fn withdraw_subscription_balance<Address: PartialEq>(
+    manager: &Address,
+    msg_sender: &Address,
+    balance: u128,
+    amount: u128,
+) -> Result<u128, Error> {
+    ensure!(manager == msg_sender, Error::NotAuthorized)?;
+    balance
+        .checked_sub(amount)
+        .ok_or(Error::InsufficientBalance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+    use crate::user_is_whitelisted;
+
+    const DATA_FEED_ID: Bytes32 = [1u8; 32];
+    const READER: [u8; 4] = [2u8; 4];
+
+    #[test]
+    fn subscription_extends_expiration() {
+        let price = SubscriptionPrice::new(10, 100);
+        assert_eq!(price.cost(3).unwrap(), 30);
+
+        let mut storage = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        let expiration =
+            extend_subscription(&mut storage, &clock, &price, &DATA_FEED_ID, &READER, 2).unwrap();
+        assert_eq!(expiration, Timestamp::from_secs(1200));
+        assert!(user_is_whitelisted(
+            &storage,
+            &clock,
+            &DATA_FEED_ID,
+            &READER
+        ));
+
+        // still active, extends from the current expiration
+        let expiration =
+            extend_subscription(&mut storage, &clock, &price, &DATA_FEED_ID, &READER, 1).unwrap();
+        assert_eq!(expiration, Timestamp::from_secs(1300));
+
+        // expired, extends from now
+        let clock = FixedClock::new(Timestamp::from_secs(5000));
+        let expiration =
+            extend_subscription(&mut storage, &clock, &price, &DATA_FEED_ID, &READER, 1).unwrap();
+        assert_eq!(expiration, Timestamp::from_secs(5100));
+    }
+
+    #[test]
+    fn subscription_requires_configuration() {
+        let mut storage = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(1000));
+        assert!(matches!(
+            extend_subscription(
+                &mut storage,
+                &clock,
+                &SubscriptionPrice::default(),
+                &DATA_FEED_ID,
+                &READER,
+                1
+            ),
+            Err(Error::SubscriptionNotConfigured)
+        ));
+        assert!(matches!(
+            SubscriptionPrice::new(10, 100).cost(0),
+            Err(Error::InvalidSubscriptionPeriods)
+        ));
+    }
+
+    #[test]
+    fn withdraw_checks_manager_and_balance() {
+        assert!(matches!(
+            withdraw_subscription_balance(&1u8, &2u8, 100, 10),
+            Err(Error::NotAuthorized)
+        ));
+        assert!(matches!(
+            withdraw_subscription_balance(&1u8, &1u8, 100, 101),
+            Err(Error::InsufficientBalance)
+        ));
+        assert_eq!(
+            withdraw_subscription_balance(&1u8, &1u8, 100, 60).unwrap(),
+            40
+        );
+    }
+}
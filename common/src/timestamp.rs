@@ -0,0 +1,95 @@
+use crate::abi::U256;
+use crate::Error;
+use core::fmt;
+
+const NANOSECONDS_IN_SECOND: u64 = 1_000_000_000;
+const MILLISECONDS_IN_SECOND: u64 = 1_000;
+
+/// A point in time as seconds since the unix epoch.
+///
+/// Chains report the current time in different units, e.g. NEAR uses nanoseconds
+/// while Solana uses seconds. There is deliberately no `From<u64>` so that every
+/// conversion has to name its unit and a timestamp in the wrong unit cannot be
+/// compared with a `Timestamp` by accident.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub const ZERO: Timestamp = Timestamp(0);
+
+    /// Creates a timestamp from seconds since the unix epoch
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    /// Creates a timestamp from milliseconds since the unix epoch, rounded down
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(millis / MILLISECONDS_IN_SECOND)
+    }
+
+    /// Creates a timestamp from nanoseconds since the unix epoch, rounded down
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos / NANOSECONDS_IN_SECOND)
+    }
+
+    /// Creates a timestamp from seconds encoded as `uint256`, as in the signed
+    /// data of Airnodes. Fails with `InvalidTimestamp` if it does not fit the
+    /// `u32` width used in `DataPoint`.
+    pub fn from_secs_u256(secs: U256) -> Result<Self, Error> {
+        u32::try_from(secs)
+            .map(|s| Self(u64::from(s)))
+            .map_err(|_| Error::InvalidTimestamp)
+    }
+
+    /// Returns the seconds since the unix epoch
+    pub const fn as_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the seconds since the unix epoch as `u32`, the width used in
+    /// `DataPoint`. Saturates at `u32::MAX`.
+    pub fn as_secs_u32(&self) -> u32 {
+        u32::try_from(self.0).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the timestamp `secs` seconds later, `None` on overflow
+    pub fn checked_add_secs(&self, secs: u64) -> Option<Self> {
+        self.0.checked_add(secs).map(Self)
+    }
+
+    /// Returns the timestamp `secs` seconds later, saturating at the maximum
+    pub fn saturating_add_secs(&self, secs: u64) -> Self {
+        Self(self.0.saturating_add(secs))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round_down_to_seconds() {
+        assert_eq!(
+            Timestamp::from_nanos(1_999_999_999),
+            Timestamp::from_secs(1)
+        );
+        assert_eq!(Timestamp::from_millis(2_500), Timestamp::from_secs(2));
+        assert_eq!(Timestamp::from_secs(u64::MAX).as_secs_u32(), u32::MAX);
+        assert_eq!(Timestamp::from_secs(u64::MAX).checked_add_secs(1), None);
+        assert_eq!(
+            Timestamp::from_secs_u256(U256::from(7u8)).ok(),
+            Some(Timestamp::from_secs(7))
+        );
+        assert!(Timestamp::from_secs_u256(U256::from(u64::MAX)).is_err());
+    }
+}
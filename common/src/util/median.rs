@@ -1,34 +1,36 @@
 use super::sort;
 use crate::abi::U256;
+use crate::Error;
 
-/// get the median from an array of U256
-pub fn median(array: &[U256]) -> U256 {
-    let len = array.len();
+/// get the median from an array of U256, fails with `InvalidDataLength` if the array is empty
+pub fn median(array: &[U256]) -> Result<U256, Error> {
     let array = sort(array);
-    let mid: usize = len / 2;
-    if len % 2 == 1 {
-        array[mid]
-    } else {
-        (array[mid - 1] + array[mid]) / 2
+    let mid: usize = array.len() / 2;
+    let upper = array.get(mid).ok_or(Error::InvalidDataLength)?;
+    match mid.checked_sub(1).and_then(|i| array.get(i)) {
+        Some(lower) if array.len() % 2 != 1 => Ok(average(lower, upper)),
+        _ => Ok(*upper),
     }
 }
 
+/// Average of two values, rounded down, that cannot overflow
+fn average(a: &U256, b: &U256) -> U256 {
+    let two = U256::from(2u8);
+    let ((half_a, rem_a), (half_b, rem_b)) = (a.div_mod(two), b.div_mod(two));
+    // both are odd, their halves lost a half each
+    let carry = U256::from(u8::from(!rem_a.is_zero() && !rem_b.is_zero()));
+    half_a.saturating_add(half_b).saturating_add(carry)
+}
+
 /// TODO: find a way to unify this with the non-wrapped one
-pub fn median_wrapped_u256(array: &[U256]) -> U256 {
-    let len = array.len();
-    let array = sort(array);
-    let mid: usize = len / 2;
-    if len % 2 == 1 {
-        array[mid]
-    } else {
-        (array[mid - 1] + array[mid]) / 2
-    }
+pub fn median_wrapped_u256(array: &[U256]) -> Result<U256, Error> {
+    median(array)
 }
 
 #[test]
 fn ideal_median() {
     let numbers = vec![U256::from(1_i128), U256::from(2_i128), U256::from(3_i128)];
-    let result = median(&numbers);
+    let result = median(&numbers).unwrap();
     assert_eq!(result, U256::from(2_i128));
 }
 
@@ -40,6 +42,13 @@ fn even_length() {
         U256::from(5_u128),
         U256::from(9_u128),
     ];
-    let result = median(&numbers);
+    let result = median(&numbers).unwrap();
     assert_eq!(result, U256::from(4_i128));
 }
+
+#[test]
+fn no_overflow_or_empty_panic() {
+    let numbers = vec![U256::MAX, U256::MAX - 2];
+    assert_eq!(median(&numbers).unwrap(), U256::MAX - 1);
+    assert!(median(&[]).is_err());
+}
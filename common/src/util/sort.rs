@@ -1,4 +1,5 @@
-use std::cmp::Ord;
+use crate::prelude::*;
+use core::cmp::Ord;
 
 /// sort an array of U256
 pub fn sort<T>(array: &[T]) -> Vec<T>
@@ -1,8 +1,9 @@
 #![allow(unused)]
 use crate::abi::{Token, U256};
+use crate::prelude::*;
 use crate::Error;
 use crate::{ensure, keccak_packed, AccessControlRegistryAdminnedWithManager, RoleDeriver};
-use crate::{Bytes32, Zero};
+use crate::{Bytes32, Storage, Timestamp, TimestampChecker, Zero};
 
 /// Trait that implements temporary and permanent whitelists for
 /// multiple services identified with a hash
@@ -41,7 +42,7 @@ pub trait Whitelist {
         &mut self,
         service_id: &Bytes32,
         user: &Self::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     );
 
     /// Sets the expiration of the temporary whitelist of `user` to be
@@ -57,7 +58,7 @@ pub trait Whitelist {
         &mut self,
         service_id: &Bytes32,
         user: &Self::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     );
 
     /// Sets the indefinite whitelist status of `user` to be able to
@@ -74,7 +75,7 @@ pub trait Whitelist {
         service_id: &Bytes32,
         user: &Self::Address,
         status: bool,
-    ) -> U256;
+    ) -> Result<U256, Error>;
 
     /// Revokes the indefinite whitelist status granted to the user for
     /// the service by a specific account
@@ -89,7 +90,7 @@ pub trait Whitelist {
         service_id: &Bytes32,
         user: &Self::Address,
         setter: &Self::Address,
-    ) -> (bool, U256);
+    ) -> Result<(bool, U256), Error>;
 }
 
 pub trait WhitelistRoles {
@@ -171,7 +172,7 @@ pub trait WhitelistWithManager: Whitelist + WhitelistRolesWithManager {
         &mut self,
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     );
 
     /// Sets the expiration of the temporary whitelist of `user` to be
@@ -187,7 +188,7 @@ pub trait WhitelistWithManager: Whitelist + WhitelistRolesWithManager {
         &mut self,
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     );
 
     /// Sets the indefinite whitelist status of `user` to be able to
@@ -203,7 +204,7 @@ pub trait WhitelistWithManager: Whitelist + WhitelistRolesWithManager {
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
         status: bool,
-    ) -> U256;
+    ) -> Result<U256, Error>;
 
     /// Revokes the indefinite whitelist status granted to the user for
     /// the service by a specific account
@@ -218,5 +219,377 @@ pub trait WhitelistWithManager: Whitelist + WhitelistRolesWithManager {
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
         setter: &<Self as Whitelist>::Address,
-    ) -> (bool, U256);
+    ) -> Result<(bool, U256), Error>;
+}
+
+/// Whitelist whose entries can be listed, so that anyone can audit who can
+/// use a service and reclaim the storage of entries that no longer whitelist anyone
+pub trait WhitelistEnumerable: Whitelist {
+    /// Returns the number of users with a whitelist status for the service
+    /// `service_id` Service ID
+    fn whitelisted_user_count(&self, service_id: &Bytes32) -> usize;
+    /// Returns up to `limit` users with a whitelist status for the service,
+    /// starting at `from_index`. Includes the users whose temporary whitelist
+    /// expired but were not swept yet. Sweeping changes the order of the users.
+    /// `service_id` Service ID
+    /// `from_index` Index of the first user to return
+    /// `limit` Maximum number of users to return
+    fn whitelisted_users_of(
+        &self,
+        service_id: &Bytes32,
+        from_index: usize,
+        limit: usize,
+    ) -> Vec<Self::Address>;
+    /// Returns the accounts that indefinitely whitelisted the user for the service
+    /// `service_id` Service ID
+    /// `user` User address
+    fn indefinite_whitelist_setters_of(
+        &self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+    ) -> Vec<Self::Address>;
+    /// Checks up to `limit` users of the service starting at `from_index` and
+    /// removes the whitelist status of the ones whose temporary whitelist
+    /// expired and who are not indefinitely whitelisted, see
+    /// `WhitelistStatus::is_active`, along with their revoked indefinite
+    /// whitelist statuses. Returns the number of removed users.
+    /// `service_id` Service ID
+    /// `from_index` Index of the first user to check
+    /// `limit` Maximum number of users to check
+    fn sweep_expired(&mut self, service_id: &Bytes32, from_index: usize, limit: usize) -> usize;
+}
+
+/// The whitelist status of a user for a service, same as `WhitelistStatus`
+/// in the solidity contract
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WhitelistStatus {
+    /// Timestamp the temporary whitelist expires at
+    pub expiration_timestamp: Timestamp,
+    /// Number of accounts that have indefinitely whitelisted the user, originally uint192
+    pub indefinite_whitelist_count: U256,
+}
+
+impl WhitelistStatus {
+    /// Returns if the status whitelists the user at `now`
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        !self.indefinite_whitelist_count.is_zero() || self.expiration_timestamp > now
+    }
+}
+
+/// Storage key of the whitelist status of `user` for the service
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User address
+pub fn whitelist_status_key(service_id: &Bytes32, user: &[u8]) -> Bytes32 {
+    keccak_packed(&[
+        Token::FixedBytes(service_id.to_vec()),
+        Token::FixedBytes(user.to_vec()),
+    ])
+}
+
+/// Storage key of the indefinite whitelist status of `user` for the service
+/// set by `setter`
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `setter` Setter of the indefinite whitelist status
+pub fn indefinite_whitelist_status_key(
+    service_id: &Bytes32,
+    user: &[u8],
+    setter: &[u8],
+) -> Bytes32 {
+    keccak_packed(&[
+        Token::FixedBytes(service_id.to_vec()),
+        Token::FixedBytes(user.to_vec()),
+        Token::FixedBytes(setter.to_vec()),
+    ])
+}
+
+/// Returns if the user is whitelisted to use the service, i.e. it is
+/// indefinitely whitelisted by at least one account or its temporary
+/// whitelist has not expired
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `clock` The clock used to check the expiration
+/// * `service_id` Service ID
+/// * `user` User address
+pub fn user_is_whitelisted<W: Storage<WhitelistStatus>, C: TimestampChecker>(
+    storage: &W,
+    clock: &C,
+    service_id: &Bytes32,
+    user: &[u8],
+) -> bool {
+    storage
+        .get(&whitelist_status_key(service_id, user))
+        .map(|status| status.is_active(clock.current_timestamp()))
+        .unwrap_or(false)
+}
+
+/// Extends the expiration of the temporary whitelist of the user for the service
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `expiration_timestamp` Timestamp at which the temporary whitelist will expire
+pub fn extend_whitelist_expiration<W: Storage<WhitelistStatus>>(
+    storage: &mut W,
+    service_id: &Bytes32,
+    user: &[u8],
+    expiration_timestamp: Timestamp,
+) -> Result<(), Error> {
+    let key = whitelist_status_key(service_id, user);
+    let mut status = storage.get(&key).unwrap_or_default();
+    ensure!(
+        expiration_timestamp > status.expiration_timestamp,
+        Error::DoesNotExtendExpiration
+    )?;
+    status.expiration_timestamp = expiration_timestamp;
+    storage.store(key, status);
+    Ok(())
+}
+
+/// Sets the expiration of the temporary whitelist of the user for the
+/// service. Unlike `extend_whitelist_expiration`, this can hasten expiration.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `expiration_timestamp` Timestamp at which the temporary whitelist will expire
+pub fn set_whitelist_expiration<W: Storage<WhitelistStatus>>(
+    storage: &mut W,
+    service_id: &Bytes32,
+    user: &[u8],
+    expiration_timestamp: Timestamp,
+) {
+    let key = whitelist_status_key(service_id, user);
+    let mut status = storage.get(&key).unwrap_or_default();
+    status.expiration_timestamp = expiration_timestamp;
+    storage.store(key, status);
+}
+
+/// Sets the indefinite whitelist status of the user for the service set by
+/// `setter`. Returns the indefinite whitelist count of the user after the change,
+/// fails with `InvalidIndefiniteWhitelistCount` if the count is out of sync with
+/// the statuses, e.g. zero while a status is set.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `indefinite_storage` The storage between `indefinite_whitelist_status_key` to the status
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `setter` Setter of the indefinite whitelist status
+/// * `status` Indefinite whitelist status
+pub fn set_indefinite_whitelist_status<W: Storage<WhitelistStatus>, I: Storage<bool>>(
+    storage: &mut W,
+    indefinite_storage: &mut I,
+    service_id: &Bytes32,
+    user: &[u8],
+    setter: &[u8],
+    status: bool,
+) -> Result<U256, Error> {
+    let key = whitelist_status_key(service_id, user);
+    let indefinite_key = indefinite_whitelist_status_key(service_id, user, setter);
+    let mut whitelist_status = storage.get(&key).unwrap_or_default();
+    let indefinite_status = indefinite_storage.get(&indefinite_key).unwrap_or(false);
+
+    if status == indefinite_status {
+        return Ok(whitelist_status.indefinite_whitelist_count);
+    }
+    let count = whitelist_status.indefinite_whitelist_count;
+    let count = if status {
+        count.checked_add(U256::from(1u8))
+    } else {
+        count.checked_sub(U256::from(1u8))
+    }
+    .ok_or(Error::InvalidIndefiniteWhitelistCount)?;
+    indefinite_storage.store(indefinite_key, status);
+    whitelist_status.indefinite_whitelist_count = count;
+    storage.store(key, whitelist_status);
+    Ok(count)
+}
+
+/// Revokes the indefinite whitelist status granted to the user for the
+/// service by `setter`. Returns if the status was revoked and the indefinite
+/// whitelist count of the user after the change.
+///
+/// # Arguments
+///
+/// * `storage` The storage between `whitelist_status_key` to `WhitelistStatus`
+/// * `indefinite_storage` The storage between `indefinite_whitelist_status_key` to the status
+/// * `service_id` Service ID
+/// * `user` User address
+/// * `setter` Setter of the indefinite whitelist status
+pub fn revoke_indefinite_whitelist_status<W: Storage<WhitelistStatus>, I: Storage<bool>>(
+    storage: &mut W,
+    indefinite_storage: &mut I,
+    service_id: &Bytes32,
+    user: &[u8],
+    setter: &[u8],
+) -> Result<(bool, U256), Error> {
+    let indefinite_key = indefinite_whitelist_status_key(service_id, user, setter);
+    if !indefinite_storage.get(&indefinite_key).unwrap_or(false) {
+        let count = storage
+            .get(&whitelist_status_key(service_id, user))
+            .unwrap_or_default()
+            .indefinite_whitelist_count;
+        return Ok((false, count));
+    }
+    let count = set_indefinite_whitelist_status(
+        storage,
+        indefinite_storage,
+        service_id,
+        user,
+        setter,
+        false,
+    )?;
+    Ok((true, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{FixedClock, InMemoryStorage};
+
+    const SERVICE_ID: Bytes32 = [1u8; 32];
+    const USER: [u8; 4] = [2u8; 4];
+    const SETTER: [u8; 4] = [3u8; 4];
+
+    #[test]
+    fn temporary_whitelist_expires() {
+        let mut storage = InMemoryStorage::default();
+        assert!(!user_is_whitelisted(
+            &storage,
+            &FixedClock::new(Timestamp::from_secs(10)),
+            &SERVICE_ID,
+            &USER
+        ));
+
+        extend_whitelist_expiration(&mut storage, &SERVICE_ID, &USER, Timestamp::from_secs(20))
+            .unwrap();
+        assert!(user_is_whitelisted(
+            &storage,
+            &FixedClock::new(Timestamp::from_secs(10)),
+            &SERVICE_ID,
+            &USER
+        ));
+        assert!(!user_is_whitelisted(
+            &storage,
+            &FixedClock::new(Timestamp::from_secs(20)),
+            &SERVICE_ID,
+            &USER
+        ));
+        assert!(matches!(
+            extend_whitelist_expiration(&mut storage, &SERVICE_ID, &USER, Timestamp::from_secs(20)),
+            Err(Error::DoesNotExtendExpiration)
+        ));
+
+        set_whitelist_expiration(&mut storage, &SERVICE_ID, &USER, Timestamp::from_secs(5));
+        assert!(!user_is_whitelisted(
+            &storage,
+            &FixedClock::new(Timestamp::from_secs(10)),
+            &SERVICE_ID,
+            &USER
+        ));
+    }
+
+    #[test]
+    fn indefinite_whitelist_counts_setters() {
+        let mut storage = InMemoryStorage::default();
+        let mut indefinite = InMemoryStorage::default();
+        let clock = FixedClock::new(Timestamp::from_secs(10));
+
+        let count = set_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &SETTER,
+            true,
+        )
+        .unwrap();
+        assert_eq!(count, U256::from(1u8));
+        // setting the same status again does not change the count
+        let count = set_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &SETTER,
+            true,
+        )
+        .unwrap();
+        assert_eq!(count, U256::from(1u8));
+        let count = set_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &USER,
+            true,
+        )
+        .unwrap();
+        assert_eq!(count, U256::from(2u8));
+        assert!(user_is_whitelisted(&storage, &clock, &SERVICE_ID, &USER));
+
+        let r = revoke_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &SETTER,
+        )
+        .unwrap();
+        assert_eq!(r, (true, U256::from(1u8)));
+        let r = revoke_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &SETTER,
+        )
+        .unwrap();
+        assert_eq!(r, (false, U256::from(1u8)));
+
+        set_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &USER,
+            false,
+        )
+        .unwrap();
+        assert!(!user_is_whitelisted(&storage, &clock, &SERVICE_ID, &USER));
+    }
+
+    #[test]
+    fn indefinite_whitelist_count_out_of_sync_fails() {
+        let mut storage = InMemoryStorage::default();
+        let mut indefinite = InMemoryStorage::default();
+
+        // a status stored without its count, e.g. after a partial migration
+        indefinite.store(
+            indefinite_whitelist_status_key(&SERVICE_ID, &USER, &SETTER),
+            true,
+        );
+        let r = revoke_indefinite_whitelist_status(
+            &mut storage,
+            &mut indefinite,
+            &SERVICE_ID,
+            &USER,
+            &SETTER,
+        );
+        assert!(matches!(r, Err(Error::InvalidIndefiniteWhitelistCount)));
+    }
 }
@@ -0,0 +1,49 @@
+use crate::types::IcDataPoint;
+use api3_common::Bytes32;
+use ic_certified_map::{fork, fork_hash, labeled, labeled_hash, AsHashTree, Hash, RbTree};
+use serde::Serialize;
+
+const DATA_POINTS_LABEL: &[u8] = b"data_points";
+const NAMES_LABEL: &[u8] = b"names";
+
+/// The certified copy of the data points and the names, kept on the heap
+/// and rebuilt from stable memory after upgrades. Its root hash is the
+/// certified data of the canister.
+#[derive(Default)]
+pub(crate) struct CertifiedFeeds {
+    data_points: RbTree<Bytes32, Vec<u8>>,
+    names: RbTree<Bytes32, Vec<u8>>,
+}
+
+impl CertifiedFeeds {
+    pub fn insert_data_point(&mut self, data_point_id: Bytes32, data_point: &IcDataPoint) {
+        self.data_points.insert(data_point_id, data_point.to_leaf());
+    }
+
+    pub fn insert_name(&mut self, name_hash: Bytes32, data_point_id: Bytes32) {
+        self.names.insert(name_hash, data_point_id.to_vec());
+    }
+
+    pub fn root_hash(&self) -> Hash {
+        fork_hash(
+            &labeled_hash(DATA_POINTS_LABEL, &self.data_points.root_hash()),
+            &labeled_hash(NAMES_LABEL, &self.names.root_hash()),
+        )
+    }
+
+    /// The CBOR encoded hash tree that proves the data point the name hash
+    /// points to, or their absence
+    pub fn witness(&self, name_hash: &Bytes32, data_point_id: &Bytes32) -> Vec<u8> {
+        let tree = fork(
+            labeled(DATA_POINTS_LABEL, self.data_points.witness(data_point_id)),
+            labeled(NAMES_LABEL, self.names.witness(name_hash)),
+        );
+        let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+        serializer
+            .self_describe()
+            .expect("CBOR tag is written to a vector");
+        tree.serialize(&mut serializer)
+            .expect("hash tree is written to a vector");
+        serializer.into_inner()
+    }
+}
@@ -0,0 +1,740 @@
+mod certification;
+mod types;
+mod utils;
+mod whitelist;
+
+pub use crate::types::{CertifiedRead, IcDataPoint, IcWhitelistStatus};
+
+use crate::types::Account;
+use crate::utils::{
+    config, ic_ensure, ic_fail, ic_unwrap, ic_unwrap_dapi, recertify, update_config, witness,
+    DatapointStorage, FeedStatusStorage, IcAccessControlRegistry, IcClock,
+    IndefiniteWhitelistStatusStorage, NameHashStorage, SignatureVerify, WhitelistStatusStorage,
+};
+use crate::whitelist::IcWhitelist;
+use api3_common::abi::{Token, Uint};
+use api3_common::{
+    indefinite_whitelist_status_key, keccak_packed, process_beacon_update_with_circuit_breaker,
+    update_dapi_with_signed_data_with_circuit_breaker, whitelist_status_key, AccessControlRegistry,
+    Bytes32, DataPoint, Error, ErrorPayload, FeedStatus, PauseState, SignatureManger, StaticRole,
+    Storage, Timestamp, Whitelist, WhitelistRolesWithManager, Zero,
+};
+use candid::Principal;
+use ic_cdk::{init, post_upgrade, query, update};
+
+/// The installer is the manager and gets the default admin role
+#[init]
+fn init() {
+    let manager = ic_cdk::caller();
+    update_config(|c| c.manager = Some(manager));
+    let manager = Account(manager);
+    ic_unwrap(
+        IcAccessControlRegistry::new(manager)
+            .grant_role(&IcAccessControlRegistry::DEFAULT_ADMIN_ROLE, &manager),
+    );
+    recertify();
+}
+
+/// Everything is in stable memory, only the certified tree on the heap is rebuilt
+#[post_upgrade]
+fn post_upgrade() {
+    recertify();
+}
+
+// ================== Access Control ====================
+/// Returns the unlimited reader role and the name setter role
+#[query]
+fn roles() -> (Bytes32, Bytes32) {
+    let access = access();
+    (
+        access.find_static_role(StaticRole::UnlimitedReaderRole),
+        access.find_static_role(StaticRole::NameSetterRole),
+    )
+}
+
+/// Renounce `role` to `who`, the caller must be `who`
+#[update]
+fn renounce_role(role: Bytes32, who: Principal) {
+    let mut access = access().with_sender(msg_sender());
+    ic_unwrap(access.renounce_role(&role, &Account(who)));
+}
+
+/// Revoke `role` to `who`
+#[update]
+fn revoke_role(role: Bytes32, who: Principal) {
+    let mut access = access();
+    let role_admin = access
+        .get_role_admin(&role)
+        .unwrap_or(IcAccessControlRegistry::DEFAULT_ADMIN_ROLE);
+    ic_ensure(
+        access.only_role(&role_admin, &msg_sender()).is_ok(),
+        Error::NotAuthorized,
+    );
+    ic_unwrap(access.revoke_role(&role, &Account(who)));
+}
+
+/// Grants `role` to `who`
+#[update]
+fn grant_role(role: Bytes32, who: Principal) {
+    let mut access = access();
+    ic_ensure(
+        access
+            .only_role(&IcAccessControlRegistry::DEFAULT_ADMIN_ROLE, &msg_sender())
+            .is_ok(),
+        Error::NotAuthorized,
+    );
+    ic_unwrap(access.grant_role(&role, &Account(who)));
+}
+
+/// Checks if `who` has `role`
+#[query]
+fn has_role(role: Bytes32, who: Principal) -> bool {
+    access().has_role(&role, &Account(who))
+}
+
+// ================== Manager Transfer ====================
+/// Returns the manager of the canister
+#[query]
+fn manager() -> Principal {
+    manager_principal()
+}
+
+/// Returns the manager proposed with `propose_manager` that has not
+/// accepted yet
+#[query]
+fn pending_manager() -> Option<Principal> {
+    config().pending_manager
+}
+
+/// Returns the manager before the last transfer
+#[query]
+fn previous_manager() -> Option<Principal> {
+    config().previous_manager
+}
+
+/// Proposes `new_manager` as the manager of the canister. Only the
+/// manager can propose and the transfer is completed once `new_manager`
+/// calls `accept_manager`.
+///
+/// # Arguments
+///
+/// * `new_manager` The principal proposed as the new manager
+#[update]
+fn propose_manager(new_manager: Principal) {
+    let new_manager = ic_unwrap(api3_common::propose_manager(
+        &Account(manager_principal()),
+        Account(new_manager),
+        &msg_sender(),
+    ));
+    update_config(|c| c.pending_manager = Some(new_manager.0));
+}
+
+/// Called by the pending manager to complete the transfer. The default
+/// admin role is moved from the previous manager to the new one.
+#[update]
+fn accept_manager() {
+    let new_manager = ic_unwrap(api3_common::accept_manager(
+        config().pending_manager.map(Account),
+        &msg_sender(),
+    ));
+    let previous_manager = Account(manager_principal());
+    update_config(|c| {
+        c.manager = Some(new_manager.0);
+        c.previous_manager = Some(previous_manager.0);
+        c.pending_manager = None;
+    });
+
+    let mut access = IcAccessControlRegistry::new(new_manager);
+    ic_unwrap(access.initialize_manager(&new_manager));
+    ic_unwrap(access.grant_role(&IcAccessControlRegistry::DEFAULT_ADMIN_ROLE, &new_manager));
+    ic_unwrap(access.revoke_role(
+        &IcAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+        &previous_manager,
+    ));
+}
+
+/// Moves `members` of the role with `description` derived from the previous
+/// manager to the same role derived from the current manager. Only the
+/// manager can migrate roles. Returns the migrated role.
+///
+/// # Arguments
+///
+/// * `description` Description of the role, e.g. `Pauser`
+/// * `members` Principals to migrate, non-members of the previous role are skipped
+#[update]
+fn migrate_role_members(description: String, members: Vec<Principal>) -> Bytes32 {
+    let previous_manager = match config().previous_manager {
+        Some(m) => Account(m),
+        None => ic_fail(Error::PreviousManagerNotFound),
+    };
+    let members = members.into_iter().map(Account).collect::<Vec<_>>();
+    ic_unwrap(access().migrate_role_members(
+        &previous_manager,
+        description,
+        &members,
+        &msg_sender(),
+    ))
+}
+
+// ================== Pause ====================
+/// Returns the pauser role
+#[query]
+fn pauser_role() -> Bytes32 {
+    access().find_static_role(StaticRole::PauserRole)
+}
+
+/// Returns the pause state of the canister. `0` is unpaused, `1` blocks
+/// updates and `2` blocks both updates and reads
+#[query]
+fn pause_state() -> u8 {
+    config().pause_state
+}
+
+/// Returns the pause state of the data feed
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed ID
+#[query]
+fn data_feed_pause_state(data_feed_id: Bytes32) -> u8 {
+    utils::data_feed_pause_state(&data_feed_id)
+}
+
+/// Sets the pause state of the canister if the caller is the manager or
+/// has the pauser role
+///
+/// # Arguments
+///
+/// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+#[update]
+fn set_pause_state(state: u8) {
+    let state = ic_unwrap(PauseState::try_from(state));
+    ensure_can_pause();
+    update_config(|c| c.pause_state = u8::from(state));
+}
+
+/// Sets the pause state of the data feed if the caller is the manager or
+/// has the pauser role
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed ID
+/// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+#[update]
+fn set_data_feed_pause_state(data_feed_id: Bytes32, state: u8) {
+    let state = ic_unwrap(PauseState::try_from(state));
+    ensure_can_pause();
+    utils::set_data_feed_pause_state(data_feed_id, u8::from(state));
+}
+
+// ================== Datapoint ====================
+/// Updates a Beacon using data signed by the respective Airnode,
+/// without requiring a request or subscription
+///
+/// # Arguments
+///
+/// * `airnode` Airnode ed25519 public key
+/// * `template_id` Template ID
+/// * `timestamp` Timestamp used in the signature
+/// * `data` Response data (an `int256` encoded in contract ABI)
+/// * `signature` Template ID, a timestamp and the response data signed by the Airnode key
+#[update]
+fn update_beacon_with_signed_data(
+    airnode: Bytes32,
+    template_id: Bytes32,
+    timestamp: Bytes32,
+    data: Vec<u8>,
+    signature: Vec<u8>,
+) {
+    let beacon_id = ic_unwrap(api3_common::derive_beacon_id(airnode.to_vec(), template_id));
+    ensure_updates_allowed(&beacon_id);
+
+    let message = keccak_packed(&[
+        Token::FixedBytes(template_id.to_vec()),
+        Token::Uint(Uint::from_big_endian(&timestamp)),
+        Token::Bytes(data.clone()),
+    ]);
+    if !SignatureVerify::verify(&airnode, &message, &signature) {
+        ic_cdk::trap(
+            &ErrorPayload::from(&Error::InvalidSignature)
+                .with_id("beacon_id", &beacon_id)
+                .to_json(),
+        );
+    }
+
+    ic_unwrap(process_beacon_update_with_circuit_breaker(
+        &mut DatapointStorage,
+        &mut FeedStatusStorage,
+        config().circuit_breaker_threshold,
+        beacon_id,
+        Uint::from_big_endian(&timestamp),
+        data,
+    ));
+}
+
+/// Updates the dAPI that is specified by the beacon IDs
+///
+/// # Arguments
+///
+/// * `beacon_ids` Beacon IDs
+#[update]
+fn update_dapi_with_beacons(beacon_ids: Vec<Bytes32>) -> Bytes32 {
+    ensure_dapi_updates_allowed(&beacon_ids);
+    ic_unwrap_dapi(api3_common::update_dapi_with_beacons(
+        &mut DatapointStorage,
+        &beacon_ids,
+    ))
+}
+
+/// Updates a dAPI using data signed by the respective Airnodes
+/// without requiring a request or subscription. The beacons for which the
+/// signature is omitted will be read from the storage. Fails if a Beacon is
+/// paused by the circuit breaker or its signed data exceeds the threshold.
+///
+/// # Arguments
+///
+/// * `airnodes` Airnode ed25519 public keys
+/// * `template_ids` Template IDs
+/// * `timestamps` Timestamps used in the signatures
+/// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+/// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode key per Beacon, empty to read the Beacon from storage
+#[update]
+fn update_dapi_with_signed_data(
+    airnodes: Vec<Bytes32>,
+    template_ids: Vec<Bytes32>,
+    timestamps: Vec<Bytes32>,
+    data: Vec<Vec<u8>>,
+    signatures: Vec<Vec<u8>>,
+) -> Bytes32 {
+    ic_ensure(
+        airnodes.len() == template_ids.len(),
+        Error::ParameterLengthMismatch,
+    );
+    let airnodes = airnodes.iter().map(|a| a.to_vec()).collect::<Vec<_>>();
+    let beacon_ids = airnodes
+        .iter()
+        .zip(template_ids.iter())
+        .map(|(airnode, template_id)| {
+            ic_unwrap(api3_common::derive_beacon_id(airnode.clone(), *template_id))
+        })
+        .collect::<Vec<_>>();
+    ensure_dapi_updates_allowed(&beacon_ids);
+
+    ic_unwrap_dapi(update_dapi_with_signed_data_with_circuit_breaker::<
+        _,
+        _,
+        SignatureVerify,
+        _,
+    >(
+        &mut DatapointStorage,
+        &FeedStatusStorage,
+        config().circuit_breaker_threshold,
+        &IcClock::now(),
+        airnodes,
+        template_ids,
+        timestamps,
+        data,
+        signatures,
+    ))
+}
+
+// ================== Circuit Breaker ====================
+/// Sets the maximum deviation a Beacon update can have from the current value
+/// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+///
+/// # Arguments
+///
+/// * `threshold` The deviation threshold
+#[update]
+fn set_circuit_breaker_threshold(threshold: u32) {
+    ic_ensure(
+        access()
+            .only_role(&IcAccessControlRegistry::DEFAULT_ADMIN_ROLE, &msg_sender())
+            .is_ok(),
+        Error::NotAuthorized,
+    );
+    update_config(|c| c.circuit_breaker_threshold = threshold);
+}
+
+/// Returns the circuit breaker deviation threshold
+#[query]
+fn circuit_breaker_threshold() -> u32 {
+    config().circuit_breaker_threshold
+}
+
+/// Returns the pending update of the data feed if it is paused by the circuit breaker
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed ID
+#[query]
+fn data_feed_pending_update(data_feed_id: Bytes32) -> Option<IcDataPoint> {
+    match FeedStatusStorage.get(&data_feed_id) {
+        Some(FeedStatus::Paused(d)) => Some(d.into()),
+        _ => None,
+    }
+}
+
+/// Releases the data feed paused by the circuit breaker, the pending
+/// update is stored as the data feed value
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed ID
+#[update]
+fn release_data_feed(data_feed_id: Bytes32) {
+    ic_unwrap(api3_common::release_data_feed(
+        &mut DatapointStorage,
+        &mut FeedStatusStorage,
+        data_feed_id,
+        &msg_sender(),
+        &access(),
+    ));
+}
+
+/// Sets the data point ID the name points to.
+/// While a data point ID refers to a specific Beacon or dAPI, names
+/// provide a more abstract interface for convenience. This means a name
+/// that was pointing at a Beacon can be pointed to a dAPI, then another
+/// dAPI, etc.
+///
+/// # Arguments
+///
+/// * `name` Human-readable name
+/// * `datapoint_id` Data point ID the name will point to
+#[update]
+fn set_name(name: Bytes32, datapoint_id: Bytes32) {
+    ic_unwrap(api3_common::set_name(
+        name,
+        datapoint_id,
+        &msg_sender(),
+        &access(),
+        &mut NameHashStorage,
+    ));
+}
+
+/// Returns the data point ID the name is set to
+/// `name` Name
+#[query]
+fn name_to_data_point_id(name: Bytes32) -> Option<Bytes32> {
+    NameHashStorage.get(&name_hash(&name))
+}
+
+/// Derives the beacon set ID from the beacon IDs
+/// Notice that `encode()` is used over `encode_packed()`
+/// Returns the derived dapi id
+///
+/// # Arguments
+///
+/// * `beacon_ids` Beacon IDs
+#[query]
+fn derive_beacon_set_id(beacon_ids: Vec<Bytes32>) -> Bytes32 {
+    api3_common::derive_dapi_id(&beacon_ids)
+}
+
+/// Derives the beacon id based on the `airnode` and `templated_id`
+/// Returns the beacon id
+///
+/// # Arguments
+///
+/// * `airnode` Airnode ed25519 public key
+/// * `template_id` Template ID
+#[query]
+fn derive_beacon_id(airnode: Bytes32, template_id: Bytes32) -> Bytes32 {
+    ic_unwrap(api3_common::derive_beacon_id(airnode.to_vec(), template_id))
+}
+
+/// Reads the data point with ID, the caller is the reader
+///
+/// # Arguments
+///
+/// * `data_point_id` Data point ID
+#[query]
+fn read_with_data_point_id(data_point_id: Bytes32) -> (Bytes32, u32) {
+    ensure_reads_allowed(&data_point_id);
+    let reader = msg_sender();
+    let access = access();
+    let whitelist = IcWhitelist::new(&access, reader);
+    let (value, timestamp) = ic_unwrap(api3_common::read_with_data_point_id(
+        &data_point_id,
+        &reader,
+        &DatapointStorage,
+        &FeedStatusStorage,
+        &access,
+        &whitelist,
+    ));
+    let data_point = IcDataPoint::from(DataPoint::new(value, timestamp));
+    (data_point.value, data_point.timestamp)
+}
+
+/// Reads the data point with name, the caller is the reader.
+/// The read data point may belong to a Beacon or dAPI. The reader
+/// must be whitelisted for the hash of the data point name.
+///
+/// The result is certified, see `CertifiedRead` for verifying it without
+/// trusting the replica that answered the query.
+///
+/// # Arguments
+///
+/// * `name` Data point name
+#[query]
+fn read_with_name(name: Bytes32) -> CertifiedRead {
+    let name_hash = name_hash(&name);
+    let data_point_id = NameHashStorage.get(&name_hash).unwrap_or_default();
+    ensure_reads_allowed(&data_point_id);
+    let reader = msg_sender();
+    let access = access();
+    let whitelist = IcWhitelist::new(&access, reader);
+    let (value, timestamp) = ic_unwrap(api3_common::read_with_name(
+        name,
+        &reader,
+        &DatapointStorage,
+        &NameHashStorage,
+        &FeedStatusStorage,
+        &access,
+        &whitelist,
+    ));
+    let data_point = IcDataPoint::from(DataPoint::new(value, timestamp));
+    CertifiedRead {
+        value: data_point.value,
+        timestamp: data_point.timestamp,
+        certificate: ic_cdk::api::data_certificate().unwrap_or_default(),
+        witness: witness(&name_hash, &data_point_id),
+    }
+}
+
+/// Returns if a reader can read the data point
+///
+/// # Arguments
+///
+/// * `data_point_id` Data point ID (or data point name hash)
+/// * `reader` Reader principal
+#[query]
+fn reader_can_read_data_point(data_point_id: Bytes32, reader: Principal) -> bool {
+    let reader = Account(reader);
+    let access = access();
+    let whitelist = IcWhitelist::new(&access, reader);
+    api3_common::reader_can_read_data_point(&data_point_id, &reader, &access, &whitelist)
+}
+
+// ================== Whitelist ====================
+/// Returns if `setter` has indefinitely whitelisted the reader for the data feed
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed id
+/// * `reader` Reader principal
+/// * `setter` Setter principal
+#[query]
+fn data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+    data_feed_id: Bytes32,
+    reader: Principal,
+    setter: Principal,
+) -> bool {
+    let key = indefinite_whitelist_status_key(&data_feed_id, reader.as_slice(), setter.as_slice());
+    IndefiniteWhitelistStatusStorage.get(&key).unwrap_or(false)
+}
+
+/// Returns the detailed whitelist status of the reader for the data feed,
+/// the expiration timestamp and the indefinite whitelist count
+///
+/// # Arguments
+///
+/// * `data_feed_id` The data feed id
+/// * `reader` Reader principal
+#[query]
+fn data_feed_id_to_whitelist_status(
+    data_feed_id: Bytes32,
+    reader: Principal,
+) -> Option<IcWhitelistStatus> {
+    let key = whitelist_status_key(&data_feed_id, reader.as_slice());
+    WhitelistStatusStorage
+        .get(&key)
+        .map(IcWhitelistStatus::from)
+}
+
+/// Extends the expiration of the temporary whitelist of `user` to
+/// be able to use the service with `service_id` if the caller has the
+/// whitelist expiration extender role
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User principal
+/// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+#[update]
+fn extend_whitelist_expiration(service_id: Bytes32, user: Principal, expiration_timestamp: u64) {
+    let user = Account(user);
+    let sender = msg_sender();
+    let access = access();
+    let mut whitelist = IcWhitelist::new(&access, sender);
+    ic_ensure(
+        whitelist.has_whitelist_expiration_extender_role_or_is_manager(&sender),
+        Error::AccessDenied,
+    );
+    ensure_whitelist_ids(&service_id, &user);
+    ic_unwrap(whitelist.try_extend_whitelist_expiration(
+        &service_id,
+        &user,
+        Timestamp::from_secs(expiration_timestamp),
+    ));
+}
+
+/// Sets the expiration of the temporary whitelist of `user` to be
+/// able to use the service with `service_id` if the caller has the
+/// whitelist expiration setter role
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User principal
+/// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+#[update]
+fn set_whitelist_expiration(service_id: Bytes32, user: Principal, expiration_timestamp: u64) {
+    let user = Account(user);
+    let sender = msg_sender();
+    let access = access();
+    let mut whitelist = IcWhitelist::new(&access, sender);
+    ic_ensure(
+        whitelist.has_whitelist_expiration_setter_role_or_is_manager(&sender),
+        Error::AccessDenied,
+    );
+    ensure_whitelist_ids(&service_id, &user);
+    whitelist.set_whitelist_expiration(
+        &service_id,
+        &user,
+        Timestamp::from_secs(expiration_timestamp),
+    );
+}
+
+/// Sets the indefinite whitelist status of `user` to be able to
+/// use the service with `service_id` if the caller has the indefinite whitelister role.
+/// Returns the indefinite whitelist count of the user.
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User principal
+/// * `status` Indefinite whitelist status
+#[update]
+fn set_indefinite_whitelist_status(service_id: Bytes32, user: Principal, status: bool) -> Bytes32 {
+    let user = Account(user);
+    let sender = msg_sender();
+    let access = access();
+    let mut whitelist = IcWhitelist::new(&access, sender);
+    ic_ensure(
+        whitelist.has_indefinite_whitelister_role_or_is_manager(&sender),
+        Error::AccessDenied,
+    );
+    ensure_whitelist_ids(&service_id, &user);
+    let count = ic_unwrap(whitelist.set_indefinite_whitelist_status(&service_id, &user, status));
+    Bytes32::from(&count)
+}
+
+/// Revokes the indefinite whitelist status granted to the user for
+/// the service by a specific principal. Anyone can revoke the status set by
+/// a principal that no longer has the indefinite whitelister role.
+/// Returns if the status was revoked and the indefinite whitelist count of the user.
+///
+/// # Arguments
+///
+/// * `service_id` Service ID
+/// * `user` User principal
+/// * `setter` Setter principal
+#[update]
+fn revoke_indefinite_whitelist_status(
+    service_id: Bytes32,
+    user: Principal,
+    setter: Principal,
+) -> (bool, Bytes32) {
+    let setter = Account(setter);
+    let access = access();
+    let mut whitelist = IcWhitelist::new(&access, setter);
+    ic_ensure(
+        !whitelist.has_indefinite_whitelister_role_or_is_manager(&setter),
+        Error::SetterCanSetIndefiniteStatus,
+    );
+    let (revoked, count) = ic_unwrap(whitelist.revoke_indefinite_whitelist_status(
+        &service_id,
+        &Account(user),
+        &setter,
+    ));
+    (revoked, Bytes32::from(&count))
+}
+
+/// Returns the whitelist expiration extender role
+#[query]
+fn whitelist_expiration_extender_role() -> Bytes32 {
+    let access = access();
+    IcWhitelist::new(&access, Account::default()).whitelist_expiration_extender_role()
+}
+
+/// Returns the whitelist expiration setter role
+#[query]
+fn whitelist_expiration_setter_role() -> Bytes32 {
+    let access = access();
+    IcWhitelist::new(&access, Account::default()).whitelist_expiration_setter_role()
+}
+
+/// Returns the indefinite whitelister role
+#[query]
+fn indefinite_whitelister_role() -> Bytes32 {
+    let access = access();
+    IcWhitelist::new(&access, Account::default()).indefinite_whitelister_role()
+}
+
+// ================== Helpers ====================
+fn msg_sender() -> Account {
+    Account(ic_cdk::caller())
+}
+
+fn manager_principal() -> Principal {
+    match config().manager {
+        Some(m) => m,
+        None => ic_fail(Error::NotInitialized),
+    }
+}
+
+fn access() -> IcAccessControlRegistry {
+    IcAccessControlRegistry::new(Account(manager_principal()))
+}
+
+fn name_hash(name: &Bytes32) -> Bytes32 {
+    keccak_packed(&[Token::FixedBytes(name.to_vec())])
+}
+
+fn ensure_whitelist_ids(service_id: &Bytes32, user: &Account) {
+    ic_ensure(!service_id.is_zero(), Error::ServiceIdZero);
+    ic_ensure(!user.is_zero(), Error::UserAddressZero);
+}
+
+fn ensure_can_pause() {
+    ic_unwrap(api3_common::ensure_can_pause(&access(), &msg_sender()));
+}
+
+fn pause_states(data_feed_id: &Bytes32) -> (PauseState, PauseState) {
+    (
+        ic_unwrap(PauseState::try_from(config().pause_state)),
+        ic_unwrap(PauseState::try_from(utils::data_feed_pause_state(
+            data_feed_id,
+        ))),
+    )
+}
+
+fn ensure_updates_allowed(data_feed_id: &Bytes32) {
+    let (global, data_feed) = pause_states(data_feed_id);
+    ic_unwrap(api3_common::ensure_updates_allowed(global, data_feed));
+}
+
+/// Checks updates are allowed for the dAPI and each of its Beacons
+fn ensure_dapi_updates_allowed(beacon_ids: &[Bytes32]) {
+    ensure_updates_allowed(&api3_common::derive_dapi_id(beacon_ids));
+    beacon_ids.iter().for_each(ensure_updates_allowed);
+}
+
+fn ensure_reads_allowed(data_feed_id: &Bytes32) {
+    let (global, data_feed) = pause_states(data_feed_id);
+    ic_unwrap(api3_common::ensure_reads_allowed(global, data_feed));
+}
+
+ic_cdk::export_candid!();
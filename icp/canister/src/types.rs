@@ -0,0 +1,231 @@
+use api3_common::abi::{Int, U256};
+use api3_common::{Bytes32, DataPoint, FeedStatus, Timestamp, WhitelistStatus, Zero};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::Storable;
+use std::borrow::Cow;
+
+/// The principal as the address type of `api3_common`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Account(pub Principal);
+
+impl Default for Account {
+    fn default() -> Self {
+        Self(Principal::management_canister())
+    }
+}
+
+impl Zero for Account {
+    /// The management canister is the principal with no bytes
+    fn is_zero(&self) -> bool {
+        self.0.as_slice().is_empty()
+    }
+}
+
+impl AsRef<[u8]> for Account {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct IcDataPoint {
+    pub value: Bytes32,
+    pub timestamp: u32,
+}
+
+impl IcDataPoint {
+    const SIZE: usize = 36;
+
+    /// The value followed by the big endian timestamp, also the leaf of the
+    /// data point in the certified tree
+    pub fn to_leaf(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.value);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes
+    }
+
+    fn from_leaf(bytes: &[u8]) -> Self {
+        let mut value = [0u8; 32];
+        value.copy_from_slice(&bytes[..32]);
+        let mut timestamp = [0u8; 4];
+        timestamp.copy_from_slice(&bytes[32..Self::SIZE]);
+        Self {
+            value,
+            timestamp: u32::from_be_bytes(timestamp),
+        }
+    }
+}
+
+impl Storable for IcDataPoint {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.to_leaf())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self::from_leaf(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::SIZE as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl From<IcDataPoint> for DataPoint {
+    fn from(t: IcDataPoint) -> Self {
+        DataPoint::new(
+            Int::from_big_endian(&t.value),
+            Timestamp::from_secs(u64::from(t.timestamp)),
+        )
+    }
+}
+
+impl From<DataPoint> for IcDataPoint {
+    fn from(t: DataPoint) -> Self {
+        let mut value = [0u8; 32];
+        t.value.to_big_endian(&mut value);
+        IcDataPoint {
+            value,
+            timestamp: t.timestamp.as_secs_u32(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum IcFeedStatus {
+    Active,
+    Paused(IcDataPoint),
+}
+
+impl Storable for IcFeedStatus {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            IcFeedStatus::Active => Cow::Owned(vec![0]),
+            IcFeedStatus::Paused(d) => {
+                let mut bytes = vec![1];
+                bytes.extend(d.to_leaf());
+                Cow::Owned(bytes)
+            }
+        }
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        match bytes.first() {
+            Some(1) => IcFeedStatus::Paused(IcDataPoint::from_leaf(&bytes[1..])),
+            _ => IcFeedStatus::Active,
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 1 + IcDataPoint::SIZE as u32,
+        is_fixed_size: false,
+    };
+}
+
+impl From<IcFeedStatus> for FeedStatus {
+    fn from(t: IcFeedStatus) -> Self {
+        match t {
+            IcFeedStatus::Active => FeedStatus::Active,
+            IcFeedStatus::Paused(d) => FeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+impl From<FeedStatus> for IcFeedStatus {
+    fn from(t: FeedStatus) -> Self {
+        match t {
+            FeedStatus::Active => IcFeedStatus::Active,
+            FeedStatus::Paused(d) => IcFeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct IcWhitelistStatus {
+    /// in seconds
+    pub expiration_timestamp: u64,
+    /// originally uint192, stored big endian
+    pub indefinite_whitelist_count: Bytes32,
+}
+
+impl Storable for IcWhitelistStatus {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = self.expiration_timestamp.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.indefinite_whitelist_count);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut expiration_timestamp = [0u8; 8];
+        expiration_timestamp.copy_from_slice(&bytes[..8]);
+        let mut indefinite_whitelist_count = [0u8; 32];
+        indefinite_whitelist_count.copy_from_slice(&bytes[8..40]);
+        Self {
+            expiration_timestamp: u64::from_be_bytes(expiration_timestamp),
+            indefinite_whitelist_count,
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 40,
+        is_fixed_size: true,
+    };
+}
+
+impl From<IcWhitelistStatus> for WhitelistStatus {
+    fn from(s: IcWhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: Timestamp::from_secs(s.expiration_timestamp),
+            indefinite_whitelist_count: U256::from_big_endian(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+impl From<WhitelistStatus> for IcWhitelistStatus {
+    fn from(s: WhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: s.expiration_timestamp.as_secs(),
+            indefinite_whitelist_count: Bytes32::from(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+/// The settings of the canister that are not kept per data feed
+#[derive(CandidType, Deserialize, Clone, Default)]
+pub struct Config {
+    pub manager: Option<Principal>,
+    pub pending_manager: Option<Principal>,
+    pub previous_manager: Option<Principal>,
+    pub pause_state: u8,
+    pub circuit_breaker_threshold: u32,
+}
+
+impl Storable for Config {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("config encodes"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("config decodes")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// The data point read by `read_with_name` with the proof that the subnet
+/// certified it.
+///
+/// `witness` is the CBOR encoded hash tree with the paths
+/// `["names", name_hash]` to the data point ID and `["data_points", data_point_id]`
+/// to the `value` followed by the big endian `timestamp`. Its root hash is the
+/// certified data of the canister in `certificate`.
+#[derive(CandidType, Deserialize, Clone, PartialEq, Debug)]
+pub struct CertifiedRead {
+    pub value: Bytes32,
+    pub timestamp: u32,
+    /// Empty when called as an update, certificates are only available in queries
+    pub certificate: Vec<u8>,
+    pub witness: Vec<u8>,
+}
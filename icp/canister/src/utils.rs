@@ -0,0 +1,352 @@
+use crate::certification::CertifiedFeeds;
+use crate::types::{Account, Config, IcDataPoint, IcFeedStatus, IcWhitelistStatus};
+use api3_common::abi::Token;
+use api3_common::{
+    keccak_packed, AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32,
+    DapiUpdateError, DataPoint, Error, ErrorPayload, FeedStatus, RoleDeriver, SignatureManger,
+    Storage, Timestamp, TimestampChecker, WhitelistStatus,
+};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell};
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const CONFIG_MEMORY: MemoryId = MemoryId::new(0);
+const DATA_POINTS_MEMORY: MemoryId = MemoryId::new(1);
+const NAME_HASHES_MEMORY: MemoryId = MemoryId::new(2);
+const FEED_STATUS_MEMORY: MemoryId = MemoryId::new(3);
+const WHITELIST_STATUS_MEMORY: MemoryId = MemoryId::new(4);
+const INDEFINITE_WHITELIST_STATUS_MEMORY: MemoryId = MemoryId::new(5);
+const ROLE_MEMBERSHIP_MEMORY: MemoryId = MemoryId::new(6);
+const ROLE_ADMIN_MEMORY: MemoryId = MemoryId::new(7);
+const DATA_FEED_PAUSE_STATE_MEMORY: MemoryId = MemoryId::new(8);
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+
+    static CONFIG: RefCell<StableCell<Config, Memory>> = RefCell::new(
+        StableCell::init(memory(CONFIG_MEMORY), Config::default())
+            .expect("config memory is initialized")
+    );
+    static DATA_POINTS: RefCell<StableBTreeMap<Bytes32, IcDataPoint, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(DATA_POINTS_MEMORY)));
+    static NAME_HASHES: RefCell<StableBTreeMap<Bytes32, Bytes32, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(NAME_HASHES_MEMORY)));
+    static FEED_STATUS: RefCell<StableBTreeMap<Bytes32, IcFeedStatus, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(FEED_STATUS_MEMORY)));
+    static WHITELIST_STATUS: RefCell<StableBTreeMap<Bytes32, IcWhitelistStatus, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(WHITELIST_STATUS_MEMORY)));
+    static INDEFINITE_WHITELIST_STATUS: RefCell<StableBTreeMap<Bytes32, bool, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(INDEFINITE_WHITELIST_STATUS_MEMORY)));
+    static ROLE_MEMBERSHIP: RefCell<StableBTreeMap<Bytes32, bool, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(ROLE_MEMBERSHIP_MEMORY)));
+    static ROLE_ADMIN: RefCell<StableBTreeMap<Bytes32, Bytes32, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(ROLE_ADMIN_MEMORY)));
+    static DATA_FEED_PAUSE_STATE: RefCell<StableBTreeMap<Bytes32, u8, Memory>> =
+        RefCell::new(StableBTreeMap::init(memory(DATA_FEED_PAUSE_STATE_MEMORY)));
+
+    static CERTIFIED_FEEDS: RefCell<CertifiedFeeds> = RefCell::new(CertifiedFeeds::default());
+}
+
+fn memory(id: MemoryId) -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(id))
+}
+
+/// Traps with the `ErrorPayload` of the error as JSON, so that clients can
+/// read the error back from the reject message with `ErrorPayload::parse`
+pub(crate) fn ic_fail(e: Error) -> ! {
+    ic_cdk::trap(&ErrorPayload::from(&e).to_json())
+}
+
+pub(crate) fn ic_unwrap<T>(r: Result<T, Error>) -> T {
+    match r {
+        Ok(t) => t,
+        Err(e) => ic_fail(e),
+    }
+}
+
+/// Same as `ic_unwrap` for dAPI updates, the payload has the `beacon_index`
+/// of the Beacon that failed the update
+pub(crate) fn ic_unwrap_dapi(r: Result<Bytes32, DapiUpdateError>) -> Bytes32 {
+    match r {
+        Ok(t) => t,
+        Err(e) => ic_cdk::trap(&ErrorPayload::from(&e).to_json()),
+    }
+}
+
+pub(crate) fn ic_ensure(condition: bool, e: Error) {
+    if !condition {
+        ic_fail(e)
+    }
+}
+
+pub(crate) fn config() -> Config {
+    CONFIG.with(|c| c.borrow().get().clone())
+}
+
+pub(crate) fn update_config(f: impl FnOnce(&mut Config)) {
+    CONFIG.with(|c| {
+        let mut cell = c.borrow_mut();
+        let mut config = cell.get().clone();
+        f(&mut config);
+        cell.set(config)
+            .expect("config is written to stable memory");
+    })
+}
+
+pub(crate) fn data_feed_pause_state(data_feed_id: &Bytes32) -> u8 {
+    DATA_FEED_PAUSE_STATE.with(|m| m.borrow().get(data_feed_id).unwrap_or_default())
+}
+
+pub(crate) fn set_data_feed_pause_state(data_feed_id: Bytes32, state: u8) {
+    DATA_FEED_PAUSE_STATE.with(|m| m.borrow_mut().insert(data_feed_id, state));
+}
+
+/// Sets the certified data of the canister to the root hash of the
+/// certified data points and names
+fn certify() {
+    CERTIFIED_FEEDS.with(|c| ic_cdk::api::set_certified_data(&c.borrow().root_hash()));
+}
+
+/// Rebuilds the certified data points and names from stable memory, the heap
+/// is cleared on upgrades
+pub(crate) fn recertify() {
+    CERTIFIED_FEEDS.with(|c| {
+        let mut feeds = CertifiedFeeds::default();
+        DATA_POINTS.with(|m| {
+            for (id, data_point) in m.borrow().iter() {
+                feeds.insert_data_point(id, &data_point);
+            }
+        });
+        NAME_HASHES.with(|m| {
+            for (name_hash, data_point_id) in m.borrow().iter() {
+                feeds.insert_name(name_hash, data_point_id);
+            }
+        });
+        *c.borrow_mut() = feeds;
+    });
+    certify();
+}
+
+/// The witness of the data point the name hash points to, see `CertifiedRead`
+pub(crate) fn witness(name_hash: &Bytes32, data_point_id: &Bytes32) -> Vec<u8> {
+    CERTIFIED_FEEDS.with(|c| c.borrow().witness(name_hash, data_point_id))
+}
+
+/// The utility struct for handling ICP stable memory so that
+/// we can use the code in `api3_common` for all the processing.
+/// Stored data points are certified.
+pub(crate) struct DatapointStorage;
+
+impl Storage<DataPoint> for DatapointStorage {
+    fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        Some(
+            DATA_POINTS
+                .with(|m| m.borrow().get(k))
+                .map(IcDataPoint::into)
+                .unwrap_or_default(),
+        )
+    }
+
+    fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
+        let datapoint = IcDataPoint::from(datapoint);
+        DATA_POINTS.with(|m| m.borrow_mut().insert(k, datapoint));
+        CERTIFIED_FEEDS.with(|c| c.borrow_mut().insert_data_point(k, &datapoint));
+        certify();
+    }
+}
+
+/// The utility struct for handling ICP stable memory so that
+/// we can use the code in `api3_common` for all the processing.
+/// Stored names are certified.
+pub(crate) struct NameHashStorage;
+
+impl Storage<Bytes32> for NameHashStorage {
+    fn get(&self, k: &Bytes32) -> Option<Bytes32> {
+        NAME_HASHES.with(|m| m.borrow().get(k))
+    }
+
+    fn store(&mut self, k: Bytes32, data: Bytes32) {
+        NAME_HASHES.with(|m| m.borrow_mut().insert(k, data));
+        CERTIFIED_FEEDS.with(|c| c.borrow_mut().insert_name(k, data));
+        certify();
+    }
+}
+
+/// The utility struct for handling ICP stable memory so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusStorage;
+
+impl Storage<FeedStatus> for FeedStatusStorage {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        FEED_STATUS
+            .with(|m| m.borrow().get(k))
+            .map(FeedStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        FEED_STATUS.with(|m| m.borrow_mut().insert(k, IcFeedStatus::from(status)));
+    }
+}
+
+/// The utility struct for handling ICP stable memory so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct WhitelistStatusStorage;
+
+impl Storage<WhitelistStatus> for WhitelistStatusStorage {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        WHITELIST_STATUS
+            .with(|m| m.borrow().get(k))
+            .map(WhitelistStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        WHITELIST_STATUS.with(|m| m.borrow_mut().insert(k, IcWhitelistStatus::from(status)));
+    }
+}
+
+/// The utility struct for handling ICP stable memory so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct IndefiniteWhitelistStatusStorage;
+
+impl Storage<bool> for IndefiniteWhitelistStatusStorage {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        INDEFINITE_WHITELIST_STATUS.with(|m| m.borrow().get(k))
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        INDEFINITE_WHITELIST_STATUS.with(|m| m.borrow_mut().insert(k, status));
+    }
+}
+
+/// Signature verification for ICP so that we can use `api3_common` package
+/// for the functions. `key` is the 32 byte ed25519 public key of the Airnode.
+pub(crate) struct SignatureVerify;
+
+impl SignatureManger for SignatureVerify {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let (key, signature) = match (<[u8; 32]>::try_from(key), <[u8; 64]>::try_from(signature)) {
+            (Ok(k), Ok(s)) => (k, s),
+            _ => return false,
+        };
+        match ed25519_dalek::VerifyingKey::from_bytes(&key) {
+            Ok(key) => key
+                .verify_strict(message, &ed25519_dalek::Signature::from_bytes(&signature))
+                .is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+pub(crate) struct IcClock {
+    current_timestamp: Timestamp,
+}
+
+impl IcClock {
+    pub fn new(current_timestamp: Timestamp) -> Self {
+        Self { current_timestamp }
+    }
+
+    /// The clock of the replica, ICP reports the time in nanoseconds
+    pub fn now() -> Self {
+        Self::new(Timestamp::from_nanos(ic_cdk::api::time()))
+    }
+}
+
+impl TimestampChecker for IcClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
+pub(crate) struct IcAccessControlRegistry {
+    manager: Account,
+    admin_role_description: String,
+    sender: Option<Account>,
+}
+
+impl IcAccessControlRegistry {
+    pub const ADMIN_ROLE_DESCRIPTION: &'static str = "admin role";
+
+    pub fn new(manager: Account) -> Self {
+        Self {
+            manager,
+            admin_role_description: String::from(Self::ADMIN_ROLE_DESCRIPTION),
+            sender: None,
+        }
+    }
+
+    /// The registry for calls by `sender`, who can then renounce roles
+    pub fn with_sender(mut self, sender: Account) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    fn membership_key(role: &Bytes32, who: &Account) -> Bytes32 {
+        keccak_packed(&[
+            Token::FixedBytes(role.to_vec()),
+            Token::FixedBytes(who.as_ref().to_vec()),
+        ])
+    }
+}
+
+impl AccessControlRegistryAdminnedWithManager for IcAccessControlRegistry {
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        &self.manager
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.admin_role_description.clone()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        keccak_packed(&[Token::String(self.admin_role_description())])
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        RoleDeriver::derive_role(
+            RoleDeriver::derive_root_role(self.manager.as_ref()),
+            self.admin_role_description(),
+        )
+    }
+}
+
+impl AccessControlRegistry for IcAccessControlRegistry {
+    fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        let key = Self::membership_key(role, who);
+        ROLE_MEMBERSHIP.with(|m| m.borrow().get(&key).unwrap_or(false))
+    }
+
+    fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+        let key = Self::membership_key(role, who);
+        ROLE_MEMBERSHIP.with(|m| m.borrow_mut().insert(key, true));
+        Ok(())
+    }
+
+    fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+        if *role == Self::DEFAULT_ADMIN_ROLE {
+            return Some(Self::DEFAULT_ADMIN_ROLE);
+        }
+        ROLE_ADMIN.with(|m| m.borrow().get(role))
+    }
+
+    fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+        ROLE_ADMIN.with(|m| m.borrow_mut().insert(*role, role_admin));
+        Ok(())
+    }
+
+    fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        api3_common::ensure!(self.sender.as_ref() == Some(account), Error::NotAuthorized)?;
+        self.revoke_role(role, account)
+    }
+
+    fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        let key = Self::membership_key(role, account);
+        ROLE_MEMBERSHIP.with(|m| m.borrow_mut().remove(&key));
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+//! Runs the canister in PocketIC. Build the wasm first with
+//! `cargo build --target wasm32-unknown-unknown --release` and point
+//! `POCKET_IC_BIN` to the PocketIC server binary.
+
+use api3_common::abi::{encode, Int, Token, Uint};
+use api3_common::{keccak_packed, Bytes32, Error, ErrorPayload};
+use candid::utils::{ArgumentDecoder, ArgumentEncoder};
+use candid::{decode_args, encode_args, Principal};
+use dapi_server_icp::CertifiedRead;
+use ed25519_dalek::{Signer, SigningKey};
+use ic_agent::Agent;
+use ic_certification::{Certificate, HashTree, LookupResult};
+use pocket_ic::{PocketIc, PocketIcBuilder, WasmResult};
+use std::time::{Duration, SystemTime};
+use ic_certification::hash_tree::{fork, label, leaf};
+
+const TEMPLATE_ID: Bytes32 = [1u8; 32];
+const NOW: u64 = 1_650_000_000;
+
+fn wasm() -> Vec<u8> {
+    let path = std::env::var("DAPI_SERVER_WASM").unwrap_or_else(|_| {
+        format!(
+            "{}/target/wasm32-unknown-unknown/release/dapi_server_icp.wasm",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    });
+    std::fs::read(&path).unwrap_or_else(|_| panic!("canister wasm not found at {}", path))
+}
+
+fn principal(id: u8) -> Principal {
+    Principal::from_slice(&[id; 29])
+}
+
+struct DapiServer {
+    pic: PocketIc,
+    canister_id: Principal,
+    manager: Principal,
+}
+
+impl DapiServer {
+    /// Installs the canister on an application subnet, certificates are then
+    /// signed by the subnet with a delegation from the NNS root key
+    fn install() -> Self {
+        let pic = PocketIcBuilder::new()
+            .with_nns_subnet()
+            .with_application_subnet()
+            .build();
+        pic.set_time(SystemTime::UNIX_EPOCH + Duration::from_secs(NOW));
+        let manager = principal(1);
+        let subnet_id = pic.topology().get_app_subnets()[0];
+        let canister_id = pic.create_canister_on_subnet(Some(manager), None, subnet_id);
+        pic.add_cycles(canister_id, 2_000_000_000_000);
+        pic.install_canister(canister_id, wasm(), encode_args(()).unwrap(), Some(manager));
+        Self {
+            pic,
+            canister_id,
+            manager,
+        }
+    }
+
+    fn update<A: ArgumentEncoder, R: for<'a> ArgumentDecoder<'a>>(
+        &self,
+        sender: Principal,
+        method: &str,
+        args: A,
+    ) -> Result<R, ErrorPayload> {
+        let result =
+            self.pic
+                .update_call(self.canister_id, sender, method, encode_args(args).unwrap());
+        Self::decode(result)
+    }
+
+    fn query<A: ArgumentEncoder, R: for<'a> ArgumentDecoder<'a>>(
+        &self,
+        sender: Principal,
+        method: &str,
+        args: A,
+    ) -> Result<R, ErrorPayload> {
+        let result =
+            self.pic
+                .query_call(self.canister_id, sender, method, encode_args(args).unwrap());
+        Self::decode(result)
+    }
+
+    /// Traps carry the `ErrorPayload` in the reject message
+    fn decode<R: for<'a> ArgumentDecoder<'a>>(
+        result: Result<WasmResult, pocket_ic::UserError>,
+    ) -> Result<R, ErrorPayload> {
+        let message = match result {
+            Ok(WasmResult::Reply(bytes)) => return Ok(decode_args(&bytes).unwrap()),
+            Ok(WasmResult::Reject(message)) => message,
+            Err(e) => e.description,
+        };
+        Err(ErrorPayload::parse(&message).unwrap_or_else(|| panic!("no payload in {}", message)))
+    }
+}
+
+fn error(e: Error) -> ErrorPayload {
+    ErrorPayload::from(&e)
+}
+
+fn airnode_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+/// Signs `value` at `timestamp` the way Airnode does, returns the
+/// timestamp, data and signature arguments of the update methods
+fn sign(key: &SigningKey, timestamp: u64, value: u128) -> (Bytes32, Vec<u8>, Vec<u8>) {
+    let mut ts = [0u8; 32];
+    Uint::from(timestamp).to_big_endian(&mut ts);
+    let data = encode(&[Token::Int(Int::from(value))]);
+    let message = keccak_packed(&[
+        Token::FixedBytes(TEMPLATE_ID.to_vec()),
+        Token::Uint(Uint::from(timestamp)),
+        Token::Bytes(data.clone()),
+    ]);
+    (ts, data, key.sign(&message).to_bytes().to_vec())
+}
+
+fn value(v: u128) -> Bytes32 {
+    let mut value = [0u8; 32];
+    Int::from(v).to_big_endian(&mut value);
+    value
+}
+
+/// Checks that `read` is the data point the certificate vouches for. The BLS
+/// signature of the certificate and its delegation are verified against the
+/// root key of the PocketIC instance.
+fn verify_certified(
+    server: &DapiServer,
+    read: &CertifiedRead,
+    name_hash: &Bytes32,
+    data_point_id: &Bytes32,
+) -> Result<(), String> {
+    let certificate: Certificate =
+        serde_cbor::from_slice(&read.certificate).map_err(|e| e.to_string())?;
+    let witness: HashTree = serde_cbor::from_slice(&read.witness).map_err(|e| e.to_string())?;
+
+    let agent = Agent::builder()
+        .with_url("http://localhost")
+        .build()
+        .map_err(|e| e.to_string())?;
+    agent.set_root_key(server.pic.root_key().ok_or("no NNS subnet")?);
+    agent
+        .verify(&certificate, server.canister_id)
+        .map_err(|e| e.to_string())?;
+
+    let certified_data = match certificate.tree.lookup_path([
+        b"canister".as_slice(),
+        server.canister_id.as_slice(),
+        b"certified_data".as_slice(),
+    ]) {
+        LookupResult::Found(data) => data.to_vec(),
+        _ => return Err("certified data is missing".to_string()),
+    };
+    if certified_data != witness.digest() {
+        return Err("witness does not match the certified data".to_string());
+    }
+
+    let mut data_point = read.value.to_vec();
+    data_point.extend_from_slice(&read.timestamp.to_be_bytes());
+    if !matches!(
+        witness.lookup_path([b"names".as_slice(), name_hash.as_slice()]),
+        LookupResult::Found(id) if id == data_point_id.as_slice()
+    ) {
+        return Err("name is not certified".to_string());
+    }
+    if !matches!(
+        witness.lookup_path([b"data_points".as_slice(), data_point_id.as_slice()]),
+        LookupResult::Found(l) if l == data_point.as_slice()
+    ) {
+        return Err("data point is not certified".to_string());
+    }
+    Ok(())
+}
+
+fn assert_certified(
+    server: &DapiServer,
+    read: &CertifiedRead,
+    name_hash: &Bytes32,
+    data_point_id: &Bytes32,
+) {
+    verify_certified(server, read, name_hash, data_point_id).unwrap();
+}
+
+#[test]
+fn installer_is_manager() {
+    let server = DapiServer::install();
+    let (manager,): (Principal,) = server.query(server.manager, "manager", ()).unwrap();
+    assert_eq!(manager, server.manager);
+    let (is_admin,): (bool,) = server
+        .query(server.manager, "has_role", ([0u8; 32], server.manager))
+        .unwrap();
+    assert!(is_admin);
+}
+
+#[test]
+fn updates_beacon_with_signed_data() {
+    let server = DapiServer::install();
+    let key = airnode_key(1);
+    let airnode = key.verifying_key().to_bytes();
+    let (beacon_id,): (Bytes32,) = server
+        .query(server.manager, "derive_beacon_id", (airnode, TEMPLATE_ID))
+        .unwrap();
+
+    let (ts, data, signature) = sign(&key, NOW - 10, 123);
+    let (): () = server
+        .update(
+            principal(2),
+            "update_beacon_with_signed_data",
+            (airnode, TEMPLATE_ID, ts, data, signature),
+        )
+        .unwrap();
+
+    let (ts, data, signature) = sign(&airnode_key(2), NOW - 5, 456);
+    let r: Result<(), _> = server.update(
+        principal(2),
+        "update_beacon_with_signed_data",
+        (airnode, TEMPLATE_ID, ts, data, signature),
+    );
+    assert_eq!(
+        r,
+        Err(error(Error::InvalidSignature).with_id("beacon_id", &beacon_id))
+    );
+
+    // the manager is not whitelisted, but can grant itself the unlimited reader role
+    let r: Result<(Bytes32, u32), _> =
+        server.query(server.manager, "read_with_data_point_id", (beacon_id,));
+    assert_eq!(r, Err(error(Error::AccessDenied)));
+    let (unlimited_reader, _): (Bytes32, Bytes32) =
+        server.query(server.manager, "roles", ()).unwrap();
+    let (): () = server
+        .update(
+            server.manager,
+            "grant_role",
+            (unlimited_reader, server.manager),
+        )
+        .unwrap();
+    let read: (Bytes32, u32) = server
+        .query(server.manager, "read_with_data_point_id", (beacon_id,))
+        .unwrap();
+    assert_eq!(read, (value(123), (NOW - 10) as u32));
+}
+
+/// Updates a dAPI, names it and whitelists `reader` to read it with the name.
+/// Returns the name, its hash and the dAPI ID.
+fn named_dapi(server: &DapiServer, reader: Principal) -> (Bytes32, Bytes32, Bytes32) {
+    let keys = [airnode_key(1), airnode_key(2), airnode_key(3)];
+    let mut airnodes = vec![];
+    let mut timestamps = vec![];
+    let mut data = vec![];
+    let mut signatures = vec![];
+    for (key, v) in keys.iter().zip([100u128, 200, 400]) {
+        let (ts, d, signature) = sign(key, NOW - 10, v);
+        airnodes.push(key.verifying_key().to_bytes());
+        timestamps.push(ts);
+        data.push(d);
+        signatures.push(signature);
+    }
+    let (dapi_id,): (Bytes32,) = server
+        .update(
+            reader,
+            "update_dapi_with_signed_data",
+            (
+                airnodes,
+                vec![TEMPLATE_ID; keys.len()],
+                timestamps,
+                data,
+                signatures,
+            ),
+        )
+        .unwrap();
+
+    let name = [7u8; 32];
+    let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+    let (_, name_setter): (Bytes32, Bytes32) = server.query(server.manager, "roles", ()).unwrap();
+    let (): () = server
+        .update(server.manager, "grant_role", (name_setter, server.manager))
+        .unwrap();
+    let (): () = server
+        .update(server.manager, "set_name", (name, dapi_id))
+        .unwrap();
+    let (): () = server
+        .update(
+            server.manager,
+            "set_whitelist_expiration",
+            (name_hash, reader, NOW + 60),
+        )
+        .unwrap();
+
+    (name, name_hash, dapi_id)
+}
+
+#[test]
+fn reads_with_name_are_certified() {
+    let server = DapiServer::install();
+    let reader = principal(2);
+    let (name, name_hash, dapi_id) = named_dapi(&server, reader);
+
+    let (read,): (CertifiedRead,) = server.query(reader, "read_with_name", (name,)).unwrap();
+    assert_eq!(
+        (read.value, read.timestamp),
+        (value(200), (NOW - 10) as u32)
+    );
+    assert_certified(&server, &read, &name_hash, &dapi_id);
+
+    // the certified tree is rebuilt from stable memory after upgrades
+    server
+        .pic
+        .upgrade_canister(
+            server.canister_id,
+            wasm(),
+            encode_args(()).unwrap(),
+            Some(server.manager),
+        )
+        .unwrap();
+    let (upgraded,): (CertifiedRead,) = server.query(reader, "read_with_name", (name,)).unwrap();
+    assert_eq!(upgraded.witness, read.witness);
+    assert_certified(&server, &upgraded, &name_hash, &dapi_id);
+}
+
+#[test]
+fn tampered_certified_reads_are_rejected() {
+    let server = DapiServer::install();
+    let reader = principal(2);
+    let (name, name_hash, dapi_id) = named_dapi(&server, reader);
+    let (read,): (CertifiedRead,) = server.query(reader, "read_with_name", (name,)).unwrap();
+    assert_certified(&server, &read, &name_hash, &dapi_id);
+
+    // a value the witness does not vouch for
+    let mut tampered = read.clone();
+    tampered.value = value(201);
+    assert!(verify_certified(&server, &tampered, &name_hash, &dapi_id).is_err());
+
+    // a witness of another tree does not match the certified data
+    let mut tampered = read.clone();
+    let mut forged_leaf = value(201).to_vec();
+    forged_leaf.extend_from_slice(&read.timestamp.to_be_bytes());
+    let forged = fork(
+        label("data_points", label(dapi_id.to_vec(), leaf(forged_leaf))),
+        label("names", label(name_hash.to_vec(), leaf(dapi_id.to_vec()))),
+    );
+    tampered.value = value(201);
+    tampered.witness = serde_cbor::to_vec(&forged).unwrap();
+    assert!(verify_certified(&server, &tampered, &name_hash, &dapi_id).is_err());
+
+    // a certificate with a flipped bit in its BLS signature
+    let certificate: Certificate = serde_cbor::from_slice(&read.certificate).unwrap();
+    let at = read
+        .certificate
+        .windows(certificate.signature.len())
+        .position(|w| w == certificate.signature.as_slice())
+        .unwrap();
+    let mut tampered = read.clone();
+    tampered.certificate[at] ^= 1;
+    assert!(verify_certified(&server, &tampered, &name_hash, &dapi_id).is_err());
+
+    // a certificate of another PocketIC instance is signed with another root key
+    let other = DapiServer::install();
+    let (other_name, other_name_hash, other_dapi_id) = named_dapi(&other, reader);
+    let (other_read,): (CertifiedRead,) = other
+        .query(reader, "read_with_name", (other_name,))
+        .unwrap();
+    assert_certified(&other, &other_read, &other_name_hash, &other_dapi_id);
+    assert!(verify_certified(&server, &other_read, &other_name_hash, &other_dapi_id).is_err());
+}
+
+#[test]
+fn pause_blocks_updates_and_reads() {
+    let server = DapiServer::install();
+    let beacon_ids = vec![[1u8; 32], [2u8; 32]];
+    let (dapi_id,): (Bytes32,) = server
+        .query(
+            server.manager,
+            "derive_beacon_set_id",
+            (beacon_ids.clone(),),
+        )
+        .unwrap();
+
+    let r: Result<(), _> = server.update(principal(2), "set_pause_state", (1u8,));
+    assert_eq!(r, Err(error(Error::AccessDenied)));
+    let r: Result<(), _> = server.update(server.manager, "set_pause_state", (3u8,));
+    assert_eq!(r, Err(error(Error::InvalidPauseState)));
+
+    let (): () = server
+        .update(server.manager, "set_data_feed_pause_state", (dapi_id, 2u8))
+        .unwrap();
+    let r: Result<(Bytes32,), _> = server.update(
+        principal(2),
+        "update_dapi_with_beacons",
+        (beacon_ids.clone(),),
+    );
+    assert_eq!(r, Err(error(Error::UpdatesPaused)));
+    let r: Result<(Bytes32, u32), _> =
+        server.query(server.manager, "read_with_data_point_id", (dapi_id,));
+    assert_eq!(r, Err(error(Error::ReadsPaused)));
+
+    let (): () = server
+        .update(server.manager, "set_data_feed_pause_state", (dapi_id, 0u8))
+        .unwrap();
+    let (updated,): (Bytes32,) = server
+        .update(principal(2), "update_dapi_with_beacons", (beacon_ids,))
+        .unwrap();
+    assert_eq!(updated, dapi_id);
+}
+
+#[test]
+fn manager_transfer_moves_admin_role() {
+    let server = DapiServer::install();
+    let (bob, pauser) = (principal(2), principal(3));
+    let (pauser_role,): (Bytes32,) = server.query(server.manager, "pauser_role", ()).unwrap();
+    let (): () = server
+        .update(server.manager, "grant_role", (pauser_role, pauser))
+        .unwrap();
+
+    let (): () = server
+        .update(server.manager, "propose_manager", (bob,))
+        .unwrap();
+    let r: Result<(), _> = server.update(server.manager, "accept_manager", ());
+    assert_eq!(r, Err(error(Error::NotAuthorized)));
+    let (): () = server.update(bob, "accept_manager", ()).unwrap();
+
+    let (manager,): (Principal,) = server.query(bob, "manager", ()).unwrap();
+    assert_eq!(manager, bob);
+    let (is_admin,): (bool,) = server
+        .query(bob, "has_role", ([0u8; 32], server.manager))
+        .unwrap();
+    assert!(!is_admin);
+
+    // the roles are derived from the new manager, members are migrated by it
+    let r: Result<(Bytes32,), _> = server.update(
+        server.manager,
+        "migrate_role_members",
+        ("Pauser".to_string(), vec![pauser]),
+    );
+    assert_eq!(r, Err(error(Error::NotAuthorized)));
+    let (role,): (Bytes32,) = server
+        .update(
+            bob,
+            "migrate_role_members",
+            ("Pauser".to_string(), vec![pauser]),
+        )
+        .unwrap();
+    let (new_pauser_role,): (Bytes32,) = server.query(bob, "pauser_role", ()).unwrap();
+    assert_eq!(role, new_pauser_role);
+    assert_ne!(role, pauser_role);
+    let (is_pauser,): (bool,) = server.query(bob, "has_role", (role, pauser)).unwrap();
+    assert!(is_pauser);
+}
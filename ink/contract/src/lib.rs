@@ -0,0 +1,2277 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod types;
+mod utils;
+mod whitelist;
+
+pub use crate::utils::ContractError;
+
+#[ink::contract]
+mod dapi_server {
+    use crate::types::{Address, InkDataPoint, InkFeedStatus, InkWhitelistStatus};
+    use crate::utils::{
+        derive_beacon_ids, msg_sender, value_to_bytes32, Bytes32Map, ContractError, DatapointMap,
+        FeedStatusMap, InkAccessControlRegistry, InkClock, RoleMembership, SignatureVerify, U64Map,
+    };
+    use crate::whitelist::{InkWhitelist, WhitelistIndex};
+    use api3_common::abi::{Token, Uint};
+    use api3_common::{
+        indefinite_whitelist_status_key, keccak_packed, process_beacon_update_with_circuit_breaker,
+        update_dapi_with_signed_data_with_circuit_breaker, whitelist_status_key,
+        AccessControlRegistry, AccessControlRegistryEnumerable,
+        AccessControlRegistryWithExpiration, Bytes32, Error, FeedStatus, PauseState, ReaderTier,
+        SignatureManger, StaticRole, SubscriptionPrice, TimestampChecker, Whitelist,
+        WhitelistEnumerable, WhitelistPermit, WhitelistRolesWithManager, Zero,
+    };
+    use ink::prelude::string::String;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    pub type Result<T> = core::result::Result<T, ContractError>;
+
+    #[ink(storage)]
+    pub struct DapiServer {
+        /// Data point related storage
+        data_points: Mapping<Bytes32, InkDataPoint>,
+        name_hash_to_data_point_id: Mapping<Bytes32, Bytes32>,
+
+        /// Circuit breaker related storage
+        data_feed_status: Mapping<Bytes32, InkFeedStatus>,
+        circuit_breaker_threshold: u32,
+
+        /// Pause related storage
+        pause_state: u8,
+        data_feed_pause_state: Mapping<Bytes32, u8>,
+
+        /// Access control related storage
+        manager: AccountId,
+        pending_manager: Option<AccountId>,
+        previous_manager: Option<AccountId>,
+        admin_role_description: String,
+        role_membership: RoleMembership,
+        role_admin: Mapping<Bytes32, Bytes32>,
+
+        /// Whitelist related storage
+        service_id_to_user_to_whitelist_status: Mapping<Bytes32, InkWhitelistStatus>,
+        service_id_to_user_to_setter_to_indefinite_whitelist_status: Mapping<Bytes32, bool>,
+        whitelist_index: WhitelistIndex,
+        service_id_to_whitelist_merkle_root: Mapping<Bytes32, Bytes32>,
+        whitelist_permit_nonces: Mapping<Bytes32, u64>,
+        chain_id: String,
+
+        /// Usage metering related storage
+        usage_epoch_length: u64,
+        usage_quotas: Mapping<u8, u64>,
+        read_counts: Mapping<Bytes32, u64>,
+
+        /// Subscription related storage
+        subscription_amount_per_period: Balance,
+        subscription_period: u64,
+        subscription_balance: Balance,
+    }
+
+    impl DapiServer {
+        /// Deploys the contract with the caller as the manager
+        ///
+        /// # Arguments
+        ///
+        /// * `chain_id` Name of the chain the whitelist permits of the contract
+        ///   are valid on, e.g. the genesis hash, see `whitelist_permit_domain_separator`
+        #[ink(constructor)]
+        pub fn new(chain_id: String) -> Self {
+            let mut s = Self {
+                data_points: Mapping::default(),
+                name_hash_to_data_point_id: Mapping::default(),
+                data_feed_status: Mapping::default(),
+                circuit_breaker_threshold: 0,
+                pause_state: PauseState::Unpaused.into(),
+                data_feed_pause_state: Mapping::default(),
+                manager: Self::env().caller(),
+                pending_manager: None,
+                previous_manager: None,
+                admin_role_description: String::from("admin role"),
+                role_membership: RoleMembership::default(),
+                role_admin: Mapping::default(),
+                service_id_to_user_to_whitelist_status: Mapping::default(),
+                service_id_to_user_to_setter_to_indefinite_whitelist_status: Mapping::default(),
+                whitelist_index: WhitelistIndex::default(),
+                service_id_to_whitelist_merkle_root: Mapping::default(),
+                whitelist_permit_nonces: Mapping::default(),
+                chain_id,
+                usage_epoch_length: 0,
+                usage_quotas: Mapping::default(),
+                read_counts: Mapping::default(),
+                subscription_amount_per_period: 0,
+                subscription_period: 0,
+                subscription_balance: 0,
+            };
+            let manager = Address(s.manager);
+            let mut access = InkAccessControlRegistry::requires_write(
+                manager,
+                s.admin_role_description.clone(),
+                &mut s.role_membership,
+                &mut s.role_admin,
+            );
+            // `grant_role` on ink! storage cannot fail
+            let _ = access.grant_role(&Self::DEFAULT_ADMIN_ROLE, &manager);
+            s
+        }
+
+        const DEFAULT_ADMIN_ROLE: Bytes32 = [0; 32];
+
+        // ================== Access Control ====================
+        /// Returns the unlimited reader role and the name setter role
+        #[ink(message)]
+        pub fn roles(&self) -> (Bytes32, Bytes32) {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            (
+                access.find_static_role(StaticRole::UnlimitedReaderRole),
+                access.find_static_role(StaticRole::NameSetterRole),
+            )
+        }
+
+        /// Renounce `role` to `who`
+        #[ink(message)]
+        pub fn renounce_role(&mut self, role: Bytes32, who: AccountId) -> Result<()> {
+            let mut access = InkAccessControlRegistry::requires_write(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            Ok(access.renounce_role(&role, &Address(who))?)
+        }
+
+        /// Revoke `role` to `who`
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: Bytes32, who: AccountId) -> Result<()> {
+            let mut access = InkAccessControlRegistry::requires_write(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            let role_admin = access
+                .get_role_admin(&role)
+                .unwrap_or(Self::DEFAULT_ADMIN_ROLE);
+            access
+                .only_role(&role_admin, &msg_sender())
+                .map_err(|_| Error::NotAuthorized)?;
+            Ok(access.revoke_role(&role, &Address(who))?)
+        }
+
+        /// Grants `role` to `who`
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Bytes32, who: AccountId) -> Result<()> {
+            let mut access = InkAccessControlRegistry::requires_write(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            access
+                .only_role(&Self::DEFAULT_ADMIN_ROLE, &msg_sender())
+                .map_err(|_| Error::NotAuthorized)?;
+            Ok(access.grant_role(&role, &Address(who))?)
+        }
+
+        /// Checks if `who` has `role`
+        #[ink(message)]
+        pub fn has_role(&self, role: Bytes32, who: AccountId) -> bool {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access.has_role(&role, &Address(who))
+        }
+
+        /// Grants `role` to `who` until `expiration_timestamp` (in seconds), after
+        /// which `who` loses the role automatically
+        #[ink(message)]
+        pub fn grant_role_until(
+            &mut self,
+            role: Bytes32,
+            who: AccountId,
+            expiration_timestamp: u64,
+        ) -> Result<()> {
+            let mut access = InkAccessControlRegistry::requires_write(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            access
+                .only_role(&Self::DEFAULT_ADMIN_ROLE, &msg_sender())
+                .map_err(|_| Error::NotAuthorized)?;
+            Ok(access.grant_role_until(
+                &role,
+                &Address(who),
+                api3_common::Timestamp::from_secs(expiration_timestamp),
+            )?)
+        }
+
+        /// Returns the timestamp (in seconds) the grant of `role` to `who`
+        /// expires at, `None` if the grant does not expire
+        #[ink(message)]
+        pub fn role_expiration(&self, role: Bytes32, who: AccountId) -> Option<u64> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access
+                .role_expiration(&role, &Address(who))
+                .map(|t| t.as_secs())
+        }
+
+        /// Returns the number of accounts that have `role`
+        #[ink(message)]
+        pub fn get_role_member_count(&self, role: Bytes32) -> u64 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access.get_role_member_count(&role) as u64
+        }
+
+        /// Returns the member of `role` at `index`, members are not sorted and
+        /// their order may change when `role` is granted or revoked
+        #[ink(message)]
+        pub fn get_role_member(&self, role: Bytes32, index: u64) -> Option<AccountId> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access
+                .get_role_member(&role, usize::try_from(index).unwrap_or(usize::MAX))
+                .map(|a| a.0)
+        }
+
+        /// Returns the roles `account` has
+        #[ink(message)]
+        pub fn roles_of(&self, account: AccountId) -> Vec<Bytes32> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access.roles_of(&Address(account))
+        }
+
+        // ================== Manager Transfer ====================
+        /// Returns the manager of the contract
+        #[ink(message)]
+        pub fn manager(&self) -> AccountId {
+            self.manager
+        }
+
+        /// Returns the manager proposed with `propose_manager` that has not
+        /// accepted yet
+        #[ink(message)]
+        pub fn pending_manager(&self) -> Option<AccountId> {
+            self.pending_manager
+        }
+
+        /// Returns the manager before the last transfer, roles derived from it can
+        /// be moved to the current manager with `migrate_role_members`
+        #[ink(message)]
+        pub fn previous_manager(&self) -> Option<AccountId> {
+            self.previous_manager
+        }
+
+        /// Proposes `new_manager` as the manager of the contract. Only the
+        /// manager can propose and the transfer is completed once `new_manager`
+        /// calls `accept_manager`.
+        ///
+        /// # Arguments
+        ///
+        /// * `new_manager` The account proposed as the new manager
+        #[ink(message)]
+        pub fn propose_manager(&mut self, new_manager: AccountId) -> Result<()> {
+            let new_manager = api3_common::propose_manager(
+                &Address(self.manager),
+                Address(new_manager),
+                &msg_sender(),
+            )?;
+            self.pending_manager = Some(new_manager.0);
+            Ok(())
+        }
+
+        /// Called by the pending manager to complete the transfer. The default
+        /// admin role is moved from the previous manager to the new one.
+        #[ink(message)]
+        pub fn accept_manager(&mut self) -> Result<()> {
+            let new_manager =
+                api3_common::accept_manager(self.pending_manager.map(Address), &msg_sender())?;
+            let previous_manager = Address(core::mem::replace(&mut self.manager, new_manager.0));
+            self.pending_manager = None;
+
+            let mut access = InkAccessControlRegistry::requires_write(
+                new_manager,
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            access.initialize_manager(&new_manager)?;
+            access.grant_role(&Self::DEFAULT_ADMIN_ROLE, &new_manager)?;
+            access.revoke_role(&Self::DEFAULT_ADMIN_ROLE, &previous_manager)?;
+            self.previous_manager = Some(previous_manager.0);
+            Ok(())
+        }
+
+        /// Moves `members` of the role with `description` derived from the previous
+        /// manager to the same role derived from the current manager. Only the
+        /// manager can migrate roles. Returns the migrated role.
+        ///
+        /// # Arguments
+        ///
+        /// * `description` Description of the role, e.g. `Pauser`
+        /// * `members` Accounts to migrate, non-members of the previous role are skipped
+        #[ink(message)]
+        pub fn migrate_role_members(
+            &mut self,
+            description: String,
+            members: Vec<AccountId>,
+        ) -> Result<Bytes32> {
+            let previous_manager = self
+                .previous_manager
+                .map(Address)
+                .ok_or(Error::PreviousManagerNotFound)?;
+            let mut access = InkAccessControlRegistry::requires_write(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &mut self.role_membership,
+                &mut self.role_admin,
+            );
+            let members = members.into_iter().map(Address).collect::<Vec<_>>();
+            Ok(access.migrate_role_members(
+                &previous_manager,
+                description,
+                &members,
+                &msg_sender(),
+            )?)
+        }
+
+        // ================== Pause ====================
+        /// Returns the pauser role
+        #[ink(message)]
+        pub fn pauser_role(&self) -> Bytes32 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access.find_static_role(StaticRole::PauserRole)
+        }
+
+        /// Returns the pause state of the contract. `0` is unpaused, `1` blocks
+        /// updates and `2` blocks both updates and reads
+        #[ink(message)]
+        pub fn pause_state(&self) -> u8 {
+            self.pause_state
+        }
+
+        /// Returns the pause state of the data feed
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed ID
+        #[ink(message)]
+        pub fn data_feed_pause_state(&self, data_feed_id: Bytes32) -> u8 {
+            self.data_feed_pause_state
+                .get(data_feed_id)
+                .unwrap_or_else(|| PauseState::Unpaused.into())
+        }
+
+        /// Sets the pause state of the contract if the sender is the manager or
+        /// has the pauser role
+        ///
+        /// # Arguments
+        ///
+        /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+        #[ink(message)]
+        pub fn set_pause_state(&mut self, state: u8) -> Result<()> {
+            let state = PauseState::try_from(state)?;
+            self.ensure_can_pause()?;
+            self.pause_state = state.into();
+            Ok(())
+        }
+
+        /// Sets the pause state of the data feed if the sender is the manager or
+        /// has the pauser role
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed ID
+        /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+        #[ink(message)]
+        pub fn set_data_feed_pause_state(
+            &mut self,
+            data_feed_id: Bytes32,
+            state: u8,
+        ) -> Result<()> {
+            let state = PauseState::try_from(state)?;
+            self.ensure_can_pause()?;
+            self.data_feed_pause_state
+                .insert(data_feed_id, &u8::from(state));
+            Ok(())
+        }
+
+        // ================== Datapoint ====================
+        /// Updates a Beacon using data signed by the respective Airnode,
+        /// without requiring a request or subscription
+        ///
+        /// # Arguments
+        ///
+        /// * `airnode` Airnode address, the 20 byte EVM address of its key
+        /// * `template_id` Template ID
+        /// * `timestamp` Timestamp used in the signature
+        /// * `data` Response data (an `int256` encoded in contract ABI)
+        /// * `signature` Template ID, a timestamp and the response data signed by the Airnode address
+        #[ink(message)]
+        pub fn update_beacon_with_signed_data(
+            &mut self,
+            airnode: Vec<u8>,
+            template_id: Bytes32,
+            timestamp: Bytes32,
+            data: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> Result<()> {
+            let beacon_id = api3_common::derive_beacon_id(airnode.clone(), template_id)?;
+            self.ensure_updates_allowed(&beacon_id)?;
+
+            let message = keccak_packed(&[
+                Token::FixedBytes(template_id.to_vec()),
+                Token::Uint(Uint::from_big_endian(&timestamp)),
+                Token::Bytes(data.clone()),
+            ]);
+            if !SignatureVerify::verify(&airnode, &message, &signature) {
+                return Err(Error::InvalidSignature.into());
+            }
+
+            let mut storage = DatapointMap::requires_write(&mut self.data_points);
+            let mut status_storage = FeedStatusMap::requires_write(&mut self.data_feed_status);
+            process_beacon_update_with_circuit_breaker(
+                &mut storage,
+                &mut status_storage,
+                self.circuit_breaker_threshold,
+                beacon_id,
+                Uint::from_big_endian(&timestamp),
+                data,
+            )?;
+            Ok(())
+        }
+
+        /// Updates the dAPI that is specified by the beacon IDs
+        ///
+        /// # Arguments
+        ///
+        /// * `beacon_ids` Beacon IDs
+        #[ink(message)]
+        pub fn update_dapi_with_beacons(&mut self, beacon_ids: Vec<Bytes32>) -> Result<Bytes32> {
+            let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+            self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids)?;
+            let mut storage = DatapointMap::requires_write(&mut self.data_points);
+            Ok(api3_common::update_dapi_with_beacons(
+                &mut storage,
+                &beacon_ids,
+            )?)
+        }
+
+        /// Updates a dAPI using data signed by the respective Airnodes
+        /// without requiring a request or subscription. The beacons for which the
+        /// signature is omitted will be read from the storage. Fails if a Beacon is
+        /// paused by the circuit breaker or its signed data exceeds the threshold.
+        ///
+        /// # Arguments
+        ///
+        /// * `airnodes` Airnode addresses
+        /// * `template_ids` Template IDs
+        /// * `timestamps` Timestamps used in the signatures
+        /// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+        /// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode address per Beacon
+        #[ink(message)]
+        pub fn update_dapi_with_signed_data(
+            &mut self,
+            airnodes: Vec<Vec<u8>>,
+            template_ids: Vec<Bytes32>,
+            timestamps: Vec<Bytes32>,
+            data: Vec<Vec<u8>>,
+            signatures: Vec<Vec<u8>>,
+        ) -> Result<Bytes32> {
+            let beacon_ids = derive_beacon_ids(&airnodes, &template_ids)?;
+            let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+            self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids)?;
+
+            let mut storage = DatapointMap::requires_write(&mut self.data_points);
+            let status_storage = FeedStatusMap::read_only(&self.data_feed_status);
+            Ok(update_dapi_with_signed_data_with_circuit_breaker::<
+                _,
+                _,
+                SignatureVerify,
+                _,
+            >(
+                &mut storage,
+                &status_storage,
+                self.circuit_breaker_threshold,
+                &InkClock::now(),
+                airnodes,
+                template_ids,
+                timestamps,
+                data,
+                signatures,
+            )?)
+        }
+
+        // ================== Circuit Breaker ====================
+        /// Sets the maximum deviation a Beacon update can have from the current value
+        /// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+        ///
+        /// # Arguments
+        ///
+        /// * `threshold` The deviation threshold
+        #[ink(message)]
+        pub fn set_circuit_breaker_threshold(&mut self, threshold: u32) -> Result<()> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access
+                .only_role(&Self::DEFAULT_ADMIN_ROLE, &msg_sender())
+                .map_err(|_| Error::NotAuthorized)?;
+            self.circuit_breaker_threshold = threshold;
+            Ok(())
+        }
+
+        /// Returns the circuit breaker deviation threshold
+        #[ink(message)]
+        pub fn circuit_breaker_threshold(&self) -> u32 {
+            self.circuit_breaker_threshold
+        }
+
+        /// Returns the pending update of the data feed if it is paused by the circuit breaker
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed ID
+        #[ink(message)]
+        pub fn data_feed_pending_update(&self, data_feed_id: Bytes32) -> Option<(Bytes32, u32)> {
+            match self
+                .data_feed_status
+                .get(data_feed_id)
+                .map(FeedStatus::from)
+            {
+                Some(FeedStatus::Paused(d)) => {
+                    Some((value_to_bytes32(d.value), d.timestamp.as_secs_u32()))
+                }
+                _ => None,
+            }
+        }
+
+        /// Releases the data feed paused by the circuit breaker, the pending
+        /// update is stored as the data feed value
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed ID
+        #[ink(message)]
+        pub fn release_data_feed(&mut self, data_feed_id: Bytes32) -> Result<()> {
+            let mut storage = DatapointMap::requires_write(&mut self.data_points);
+            let mut status_storage = FeedStatusMap::requires_write(&mut self.data_feed_status);
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            Ok(api3_common::release_data_feed(
+                &mut storage,
+                &mut status_storage,
+                data_feed_id,
+                &msg_sender(),
+                &access,
+            )?)
+        }
+
+        /// Sets the data point ID the name points to.
+        /// While a data point ID refers to a specific Beacon or dAPI, names
+        /// provide a more abstract interface for convenience. This means a name
+        /// that was pointing at a Beacon can be pointed to a dAPI, then another
+        /// dAPI, etc.
+        ///
+        /// # Arguments
+        ///
+        /// * `name` Human-readable name
+        /// * `datapoint_id` Data point ID the name will point to
+        #[ink(message)]
+        pub fn set_name(&mut self, name: Bytes32, datapoint_id: Bytes32) -> Result<()> {
+            let mut storage = Bytes32Map::requires_write(&mut self.name_hash_to_data_point_id);
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            Ok(api3_common::set_name(
+                name,
+                datapoint_id,
+                &msg_sender(),
+                &access,
+                &mut storage,
+            )?)
+        }
+
+        /// Returns the data point ID the name is set to
+        /// `name` Name
+        #[ink(message)]
+        pub fn name_to_data_point_id(&self, name: Bytes32) -> Option<Bytes32> {
+            self.name_hash_to_data_point_id
+                .get(keccak_packed(&[Token::FixedBytes(name.to_vec())]))
+        }
+
+        /// Derives the beacon set ID from the beacon IDs
+        /// Notice that `encode()` is used over `encode_packed()`
+        /// Returns the derived dapi id
+        ///
+        /// # Arguments
+        ///
+        /// * `beacon_ids` Beacon IDs
+        #[ink(message)]
+        pub fn derive_beacon_set_id(&self, beacon_ids: Vec<Bytes32>) -> Bytes32 {
+            api3_common::derive_dapi_id(&beacon_ids)
+        }
+
+        /// Derives the beacon id based on the `airnode` and `templated_id`
+        /// Returns the beacon id
+        ///
+        /// # Arguments
+        ///
+        /// * `airnode` Airnode address
+        /// * `template_id` Template ID
+        #[ink(message)]
+        pub fn derive_beacon_id(&self, airnode: Vec<u8>, template_id: Bytes32) -> Result<Bytes32> {
+            Ok(api3_common::derive_beacon_id(airnode, template_id)?)
+        }
+
+        /// Reads the data point with ID
+        /// Reads take `&mut self` as they count the read while usage metering
+        /// is enabled, see `set_usage_epoch_length`.
+        ///
+        /// # Arguments
+        ///
+        /// * `data_point_id` Data point ID
+        /// * `proof` Optional Merkle proof of the sender in the whitelist tree of the data point
+        #[ink(message)]
+        pub fn read_with_data_point_id(
+            &mut self,
+            data_point_id: Bytes32,
+            proof: Option<Vec<Bytes32>>,
+        ) -> Result<(Bytes32, u32)> {
+            self.ensure_reads_allowed(&data_point_id)?;
+            let storage = DatapointMap::read_only(&self.data_points);
+            let status_storage = FeedStatusMap::read_only(&self.data_feed_status);
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            )
+            .with_merkle_proof(
+                &data_point_id,
+                self.service_id_to_whitelist_merkle_root.get(data_point_id),
+                proof.clone(),
+            );
+            let (value, timestamp) = api3_common::read_with_data_point_id(
+                &data_point_id,
+                &msg_sender(),
+                &storage,
+                &status_storage,
+                &access,
+                &whitelist,
+            )?;
+            self.meter_read(&data_point_id, &data_point_id, proof)?;
+            Ok((value_to_bytes32(value), timestamp.as_secs_u32()))
+        }
+
+        /// Reads the data point with name
+        /// The read data point may belong to a Beacon or dAPI. The reader
+        /// must be whitelisted for the hash of the data point name.
+        ///
+        /// # Arguments
+        ///
+        /// * `name` Data point name
+        /// * `proof` Optional Merkle proof of the sender in the whitelist tree of the name hash
+        #[ink(message)]
+        pub fn read_with_name(
+            &mut self,
+            name: Bytes32,
+            proof: Option<Vec<Bytes32>>,
+        ) -> Result<(Bytes32, u32)> {
+            let data_point_id = self.name_to_data_point_id(name).unwrap_or_default();
+            self.ensure_reads_allowed(&data_point_id)?;
+            let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+            let dp_s = DatapointMap::read_only(&self.data_points);
+            let nh_s = Bytes32Map::read_only(&self.name_hash_to_data_point_id);
+            let fs_s = FeedStatusMap::read_only(&self.data_feed_status);
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            )
+            .with_merkle_proof(
+                &name_hash,
+                self.service_id_to_whitelist_merkle_root.get(name_hash),
+                proof.clone(),
+            );
+            let (value, timestamp) = api3_common::read_with_name(
+                name,
+                &msg_sender(),
+                &dp_s,
+                &nh_s,
+                &fs_s,
+                &access,
+                &whitelist,
+            )?;
+            self.meter_read(&name_hash, &data_point_id, proof)?;
+            Ok((value_to_bytes32(value), timestamp.as_secs_u32()))
+        }
+
+        /// Returns if a reader can read the data point
+        ///
+        /// # Arguments
+        ///
+        /// * `data_point_id` Data point ID (or data point name hash)
+        /// * `reader` Reader address
+        /// * `proof` Optional Merkle proof of the reader in the whitelist tree of the data point
+        #[ink(message)]
+        pub fn reader_can_read_data_point(
+            &self,
+            data_point_id: Bytes32,
+            reader: AccountId,
+            proof: Option<Vec<Bytes32>>,
+        ) -> bool {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            )
+            .with_merkle_proof(
+                &data_point_id,
+                self.service_id_to_whitelist_merkle_root.get(data_point_id),
+                proof,
+            );
+            api3_common::reader_can_read_data_point(
+                &data_point_id,
+                &Address(reader),
+                &access,
+                &whitelist,
+            )
+        }
+
+        // ================== Whitelist ====================
+        /// Returns if `setter` has indefinitely whitelisted the reader for the data feed
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed id
+        /// * `reader` Reader address
+        /// * `setter` Setter address
+        #[ink(message)]
+        pub fn data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+            &self,
+            data_feed_id: Bytes32,
+            reader: AccountId,
+            setter: AccountId,
+        ) -> bool {
+            self.service_id_to_user_to_setter_to_indefinite_whitelist_status
+                .get(indefinite_whitelist_status_key(
+                    &data_feed_id,
+                    reader.as_ref(),
+                    setter.as_ref(),
+                ))
+                .unwrap_or(false)
+        }
+
+        /// Returns the detailed whitelist status of the reader for the data feed,
+        /// the expiration timestamp and the indefinite whitelist count
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed id
+        /// * `reader` Reader address
+        #[ink(message)]
+        pub fn data_feed_id_to_whitelist_status(
+            &self,
+            data_feed_id: Bytes32,
+            reader: AccountId,
+        ) -> Option<(u64, Bytes32)> {
+            self.service_id_to_user_to_whitelist_status
+                .get(whitelist_status_key(&data_feed_id, reader.as_ref()))
+                .map(|s| (s.expiration_timestamp, s.indefinite_whitelist_count))
+        }
+
+        /// Extends the expiration of the temporary whitelist of `user` to
+        /// be able to use the service with `service_id` if the sender has the
+        /// whitelist expiration extender role
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+        #[ink(message)]
+        pub fn extend_whitelist_expiration(
+            &mut self,
+            service_id: Bytes32,
+            user: AccountId,
+            expiration_timestamp: u64,
+        ) -> Result<()> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            let user = Address(user);
+            api3_common::ensure!(
+                whitelist.has_whitelist_expiration_extender_role_or_is_manager(&msg_sender()),
+                Error::AccessDenied
+            )?;
+            Self::ensure_whitelist_ids(&service_id, &user)?;
+            Ok(whitelist.try_extend_whitelist_expiration(
+                &service_id,
+                &user,
+                api3_common::Timestamp::from_secs(expiration_timestamp),
+            )?)
+        }
+
+        /// Sets the expiration of the temporary whitelist of `user` to be
+        /// able to use the service with `service_id` if the sender has the
+        /// whitelist expiration setter role
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+        #[ink(message)]
+        pub fn set_whitelist_expiration(
+            &mut self,
+            service_id: Bytes32,
+            user: AccountId,
+            expiration_timestamp: u64,
+        ) -> Result<()> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            let user = Address(user);
+            api3_common::ensure!(
+                whitelist.has_whitelist_expiration_setter_role_or_is_manager(&msg_sender()),
+                Error::AccessDenied
+            )?;
+            Self::ensure_whitelist_ids(&service_id, &user)?;
+            whitelist.set_whitelist_expiration(
+                &service_id,
+                &user,
+                api3_common::Timestamp::from_secs(expiration_timestamp),
+            );
+            Ok(())
+        }
+
+        /// Sets the expiration of the temporary whitelist of `user` with a permit
+        /// signed off-chain by an account with the whitelist expiration setter role.
+        /// Anyone can submit the permit. The setter signs the blake2 256 hash of
+        /// `WhitelistPermit::message` with its ECDSA key and is the account of
+        /// `setter_key`, i.e. the blake2 256 hash of the compressed public key.
+        /// Each permit nonce of a setter can only be used once. The permit names
+        /// this contract and the chain, see `whitelist_permit_domain_separator`.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+        /// * `nonce` The next permit nonce of the setter, see `whitelist_permit_nonce`
+        /// * `deadline` Timestamp (in seconds) after which the permit cannot be used
+        /// * `setter_key` The 33 byte compressed ECDSA public key of the setter
+        /// * `signature` The 65 byte recoverable signature of the setter
+        #[ink(message)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_whitelist_expiration_with_permit(
+            &mut self,
+            service_id: Bytes32,
+            user: AccountId,
+            expiration_timestamp: u64,
+            nonce: u64,
+            deadline: u64,
+            setter_key: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> Result<()> {
+            let user = Address(user);
+            Self::ensure_whitelist_ids(&service_id, &user)?;
+            let (chain_id, contract) = self.permit_domain();
+            let permit = WhitelistPermit {
+                chain_id,
+                contract,
+                service_id,
+                user: user.as_ref().to_vec(),
+                expiration_timestamp: api3_common::Timestamp::from_secs(expiration_timestamp),
+                nonce,
+                deadline: api3_common::Timestamp::from_secs(deadline),
+            };
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            Ok(whitelist.set_whitelist_expiration_with_permit(
+                &mut U64Map::requires_write(&mut self.whitelist_permit_nonces),
+                &permit,
+                &user,
+                &setter_key,
+                &signature,
+            )?)
+        }
+
+        /// Returns the domain separator of the whitelist permits of this contract,
+        /// see `api3_common::permit_domain_separator`. The chain ID is the one
+        /// the contract was deployed with.
+        #[ink(message)]
+        pub fn whitelist_permit_domain_separator(&self) -> Bytes32 {
+            let (chain_id, contract) = self.permit_domain();
+            api3_common::permit_domain_separator(&chain_id, &contract)
+        }
+
+        /// Returns the nonce the next permit of `setter` must use
+        ///
+        /// # Arguments
+        ///
+        /// * `setter` The setter account
+        #[ink(message)]
+        pub fn whitelist_permit_nonce(&self, setter: AccountId) -> u64 {
+            self.whitelist_permit_nonces
+                .get(api3_common::permit_nonce_key(setter.as_ref()))
+                .unwrap_or_default()
+        }
+
+        /// Sets the indefinite whitelist status of `user` to be able to
+        /// use the service with `service_id` if the sender has the indefinite whitelister role.
+        /// Returns the indefinite whitelist count of the user.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        /// * `status` Indefinite whitelist status
+        #[ink(message)]
+        pub fn set_indefinite_whitelist_status(
+            &mut self,
+            service_id: Bytes32,
+            user: AccountId,
+            status: bool,
+        ) -> Result<Bytes32> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            let user = Address(user);
+            api3_common::ensure!(
+                whitelist.has_indefinite_whitelister_role_or_is_manager(&msg_sender()),
+                Error::AccessDenied
+            )?;
+            Self::ensure_whitelist_ids(&service_id, &user)?;
+            let count = whitelist.set_indefinite_whitelist_status(&service_id, &user, status)?;
+            Ok(Bytes32::from(count))
+        }
+
+        /// Revokes the indefinite whitelist status granted to the user for
+        /// the service by a specific account. Anyone can revoke the status set by
+        /// an account that no longer has the indefinite whitelister role.
+        /// Returns if the status was revoked and the indefinite whitelist count of the user.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        /// * `setter` Setter address
+        #[ink(message)]
+        pub fn revoke_indefinite_whitelist_status(
+            &mut self,
+            service_id: Bytes32,
+            user: AccountId,
+            setter: AccountId,
+        ) -> Result<(bool, Bytes32)> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            let setter = Address(setter);
+            api3_common::ensure!(
+                !whitelist.has_indefinite_whitelister_role_or_is_manager(&setter),
+                Error::SetterCanSetIndefiniteStatus
+            )?;
+            let (revoked, count) = whitelist.revoke_indefinite_whitelist_status(
+                &service_id,
+                &Address(user),
+                &setter,
+            )?;
+            Ok((revoked, Bytes32::from(count)))
+        }
+
+        /// Returns the whitelist expiration extender role
+        #[ink(message)]
+        pub fn whitelist_expiration_extender_role(&self) -> Bytes32 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist.whitelist_expiration_extender_role()
+        }
+
+        /// Returns the whitelist expiration setter role
+        #[ink(message)]
+        pub fn whitelist_expiration_setter_role(&self) -> Bytes32 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist.whitelist_expiration_setter_role()
+        }
+
+        /// Returns the indefinite whitelister role
+        #[ink(message)]
+        pub fn indefinite_whitelister_role(&self) -> Bytes32 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist.indefinite_whitelister_role()
+        }
+
+        /// Publishes the Merkle root of the readers whitelisted for the service.
+        /// Readers then pass their proof, built with `api3_common::MerkleTree`,
+        /// to the read messages. A zero root disables Merkle proof whitelisting.
+        /// Only the manager can publish roots.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `root` The Merkle root of the whitelisted readers
+        #[ink(message)]
+        pub fn set_whitelist_merkle_root(
+            &mut self,
+            service_id: Bytes32,
+            root: Bytes32,
+        ) -> Result<()> {
+            let mut storage =
+                Bytes32Map::requires_write(&mut self.service_id_to_whitelist_merkle_root);
+            Ok(api3_common::set_whitelist_merkle_root(
+                &mut storage,
+                &Address(self.manager),
+                &msg_sender(),
+                &service_id,
+                root,
+            )?)
+        }
+
+        /// Returns the Merkle root of the readers whitelisted for the service
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        #[ink(message)]
+        pub fn whitelist_merkle_root(&self, service_id: Bytes32) -> Option<Bytes32> {
+            self.service_id_to_whitelist_merkle_root.get(service_id)
+        }
+
+        /// Returns the number of users with a whitelist status for the service
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        #[ink(message)]
+        pub fn whitelisted_user_count(&self, service_id: Bytes32) -> u64 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist.whitelisted_user_count(&service_id) as u64
+        }
+
+        /// Returns up to `limit` users with a whitelist status for the service,
+        /// starting at `from_index`. Includes the users whose temporary whitelist
+        /// expired but were not swept yet. Sweeping changes the order of the users.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `from_index` Index of the first user to return
+        /// * `limit` Maximum number of users to return
+        #[ink(message)]
+        pub fn whitelisted_users_of(
+            &self,
+            service_id: Bytes32,
+            from_index: u64,
+            limit: u64,
+        ) -> Vec<AccountId> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist
+                .whitelisted_users_of(
+                    &service_id,
+                    usize::try_from(from_index).unwrap_or(usize::MAX),
+                    usize::try_from(limit).unwrap_or(usize::MAX),
+                )
+                .into_iter()
+                .map(|a| a.0)
+                .collect()
+        }
+
+        /// Returns the accounts that indefinitely whitelisted `user` for the service
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `user` User address
+        #[ink(message)]
+        pub fn indefinite_whitelist_setters_of(
+            &self,
+            service_id: Bytes32,
+            user: AccountId,
+        ) -> Vec<AccountId> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let whitelist = InkWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            );
+            whitelist
+                .indefinite_whitelist_setters_of(&service_id, &Address(user))
+                .into_iter()
+                .map(|a| a.0)
+                .collect()
+        }
+
+        /// Checks up to `limit` users of the service starting at `from_index` and
+        /// removes the whitelist status of the ones whose temporary whitelist
+        /// expired and who are not indefinitely whitelisted, along with their
+        /// revoked indefinite whitelist statuses, releasing their storage.
+        /// Anyone can call this. Returns the number of removed users.
+        ///
+        /// # Arguments
+        ///
+        /// * `service_id` Service ID
+        /// * `from_index` Index of the first user to check, see `whitelisted_users_of`
+        /// * `limit` Maximum number of users to check
+        #[ink(message)]
+        pub fn sweep_expired(&mut self, service_id: Bytes32, from_index: u64, limit: u64) -> u64 {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            whitelist.sweep_expired(
+                &service_id,
+                usize::try_from(from_index).unwrap_or(usize::MAX),
+                usize::try_from(limit).unwrap_or(usize::MAX),
+            ) as u64
+        }
+
+        // ================== Usage Metering ====================
+        /// Sets the length of the epochs reads are counted in. A zero length
+        /// disables usage metering. The read counts of closed epochs are kept for
+        /// invoicing until the manager prunes them with `prune_read_counts`.
+        ///
+        /// # Arguments
+        ///
+        /// * `epoch_length` Length of an epoch in seconds
+        #[ink(message)]
+        pub fn set_usage_epoch_length(&mut self, epoch_length: u64) -> Result<()> {
+            self.ensure_is_admin()?;
+            self.usage_epoch_length = epoch_length;
+            Ok(())
+        }
+
+        /// Returns the length of the usage epochs in seconds, zero if metering is disabled
+        #[ink(message)]
+        pub fn usage_epoch_length(&self) -> u64 {
+            self.usage_epoch_length
+        }
+
+        /// Returns the current usage epoch, `None` if metering is disabled
+        #[ink(message)]
+        pub fn current_usage_epoch(&self) -> Option<u64> {
+            if self.usage_epoch_length == 0 {
+                return None;
+            }
+            let now = InkClock::now().current_timestamp();
+            Some(api3_common::usage_epoch(now, self.usage_epoch_length))
+        }
+
+        /// Sets the maximum number of reads per data point and epoch of the readers
+        /// in the tier. Tiers without a quota are unlimited.
+        ///
+        /// # Arguments
+        ///
+        /// * `tier` The reader tier, see `api3_common::ReaderTier`
+        /// * `quota` Maximum reads per epoch, `None` to remove the quota
+        #[ink(message)]
+        pub fn set_usage_quota(&mut self, tier: u8, quota: Option<u64>) -> Result<()> {
+            self.ensure_is_admin()?;
+            ReaderTier::try_from(tier)?;
+            match quota {
+                Some(q) => {
+                    self.usage_quotas.insert(tier, &q);
+                }
+                None => self.usage_quotas.remove(tier),
+            }
+            Ok(())
+        }
+
+        /// Returns the quota of the reader tier, `None` if unlimited
+        ///
+        /// # Arguments
+        ///
+        /// * `tier` The reader tier, see `api3_common::ReaderTier`
+        #[ink(message)]
+        pub fn usage_quota(&self, tier: u8) -> Option<u64> {
+            self.usage_quotas.get(tier)
+        }
+
+        /// Returns how many times the reader read the data point in the epoch
+        ///
+        /// # Arguments
+        ///
+        /// * `data_point_id` Data point ID
+        /// * `reader` Reader address
+        /// * `epoch` Usage epoch, see `current_usage_epoch`
+        #[ink(message)]
+        pub fn read_count(&self, data_point_id: Bytes32, reader: AccountId, epoch: u64) -> u64 {
+            let key = api3_common::read_count_key(&data_point_id, reader.as_ref(), epoch);
+            self.read_counts.get(key).unwrap_or_default()
+        }
+
+        /// Removes the read counts of the readers of the data point in a closed
+        /// epoch, e.g. once the epoch is invoiced. Only the manager can prune.
+        ///
+        /// # Arguments
+        ///
+        /// * `data_point_id` Data point ID
+        /// * `readers` Reader addresses
+        /// * `epoch` Usage epoch, before `current_usage_epoch`
+        #[ink(message)]
+        pub fn prune_read_counts(
+            &mut self,
+            data_point_id: Bytes32,
+            readers: Vec<AccountId>,
+            epoch: u64,
+        ) -> Result<()> {
+            api3_common::ensure_can_prune_read_counts(
+                &Address(self.manager),
+                &msg_sender(),
+                &InkClock::now(),
+                self.usage_epoch_length,
+                epoch,
+            )?;
+            for reader in readers {
+                let key = api3_common::read_count_key(&data_point_id, reader.as_ref(), epoch);
+                self.read_counts.remove(key);
+            }
+            Ok(())
+        }
+
+        // ================== Subscription ====================
+        /// Sets the price readers pay to whitelist themselves with `subscribe`.
+        /// A zero `period` disables subscriptions.
+        ///
+        /// # Arguments
+        ///
+        /// * `amount_per_period` Amount charged per period
+        /// * `period` Length of a period in seconds
+        #[ink(message)]
+        pub fn set_subscription_price(
+            &mut self,
+            amount_per_period: Balance,
+            period: u64,
+        ) -> Result<()> {
+            self.ensure_is_admin()?;
+            self.subscription_amount_per_period = amount_per_period;
+            self.subscription_period = period;
+            Ok(())
+        }
+
+        /// Returns the subscription amount per period and the period in seconds
+        #[ink(message)]
+        pub fn subscription_price(&self) -> (Balance, u64) {
+            (
+                self.subscription_amount_per_period,
+                self.subscription_period,
+            )
+        }
+
+        /// Returns the subscription payments that were not withdrawn by the manager yet
+        #[ink(message)]
+        pub fn subscription_balance(&self) -> Balance {
+            self.subscription_balance
+        }
+
+        /// Whitelists the caller to read the data feed for `periods` more periods.
+        /// The transferred value must cover the price of the periods, the excess is
+        /// refunded. Returns the new expiration timestamp in seconds.
+        ///
+        /// # Arguments
+        ///
+        /// * `data_feed_id` The data feed ID
+        /// * `periods` Number of periods to subscribe for
+        #[ink(message, payable)]
+        pub fn subscribe(&mut self, data_feed_id: Bytes32, periods: u64) -> Result<u64> {
+            let price = SubscriptionPrice::new(
+                self.subscription_amount_per_period,
+                self.subscription_period,
+            );
+            let cost = price.cost(periods)?;
+            let payment = self.env().transferred_value();
+            api3_common::ensure!(payment >= cost, Error::InsufficientPayment)?;
+
+            let reader = msg_sender();
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let mut whitelist = InkWhitelist::requires_write(
+                &access,
+                &mut self.service_id_to_user_to_whitelist_status,
+                &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &mut self.whitelist_index,
+            );
+            let expiration = whitelist.subscribe(&price, &data_feed_id, &reader, periods)?;
+
+            self.subscription_balance += cost;
+            if payment > cost {
+                self.env()
+                    .transfer(reader.0, payment - cost)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            Ok(expiration.as_secs())
+        }
+
+        /// Transfers `amount` of the subscription payments to the manager. Only
+        /// the manager can withdraw.
+        ///
+        /// # Arguments
+        ///
+        /// * `amount` Amount to withdraw
+        #[ink(message)]
+        pub fn withdraw_subscription_balance(&mut self, amount: Balance) -> Result<()> {
+            self.subscription_balance = api3_common::withdraw_subscription_balance(
+                &Address(self.manager),
+                &msg_sender(),
+                self.subscription_balance,
+                amount,
+            )?;
+            self.env()
+                .transfer(self.manager, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            Ok(())
+        }
+
+        fn ensure_is_admin(&self) -> Result<()> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            access
+                .only_role(&Self::DEFAULT_ADMIN_ROLE, &msg_sender())
+                .map_err(|_| Error::NotAuthorized)?;
+            Ok(())
+        }
+
+        /// Counts a read of the data point by the caller against the quota of
+        /// the tier the caller reads the service with. Only the read count is
+        /// written and only while metering is enabled.
+        fn meter_read(
+            &mut self,
+            service_id: &Bytes32,
+            data_point_id: &Bytes32,
+            proof: Option<Vec<Bytes32>>,
+        ) -> Result<()> {
+            if self.usage_epoch_length == 0 {
+                return Ok(());
+            }
+            let reader = msg_sender();
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            let tier = if access.has_role(
+                &access.find_static_role(StaticRole::UnlimitedReaderRole),
+                &reader,
+            ) {
+                Some(ReaderTier::UnlimitedReader)
+            } else {
+                InkWhitelist::read_only(
+                    &access,
+                    &self.service_id_to_user_to_whitelist_status,
+                    &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                    &self.whitelist_index,
+                )
+                .with_merkle_proof(
+                    service_id,
+                    self.service_id_to_whitelist_merkle_root.get(service_id),
+                    proof,
+                )
+                .reader_tier(service_id, &reader)
+            };
+            let quota = tier.and_then(|t| self.usage_quotas.get(u8::from(t)));
+            api3_common::record_read(
+                &mut U64Map::requires_write(&mut self.read_counts),
+                &InkClock::now(),
+                self.usage_epoch_length,
+                quota,
+                data_point_id,
+                reader.as_ref(),
+            )?;
+            Ok(())
+        }
+
+        /// The chain and the contract whitelist permits are valid for
+        fn permit_domain(&self) -> (String, Vec<u8>) {
+            let contract = self.env().account_id();
+            (
+                self.chain_id.clone(),
+                AsRef::<[u8]>::as_ref(&contract).to_vec(),
+            )
+        }
+
+        fn ensure_whitelist_ids(service_id: &Bytes32, user: &Address) -> Result<()> {
+            api3_common::ensure!(!service_id.is_zero(), Error::ServiceIdZero)?;
+            api3_common::ensure!(!user.is_zero(), Error::UserAddressZero)?;
+            Ok(())
+        }
+
+        fn ensure_can_pause(&self) -> Result<()> {
+            let access = InkAccessControlRegistry::read_only(
+                Address(self.manager),
+                self.admin_role_description.clone(),
+                &self.role_membership,
+                &self.role_admin,
+            );
+            Ok(api3_common::ensure_can_pause(&access, &msg_sender())?)
+        }
+
+        fn pause_states(&self, data_feed_id: &Bytes32) -> Result<(PauseState, PauseState)> {
+            let global = PauseState::try_from(self.pause_state)?;
+            let data_feed = PauseState::try_from(self.data_feed_pause_state(*data_feed_id))?;
+            Ok((global, data_feed))
+        }
+
+        fn ensure_updates_allowed(&self, data_feed_id: &Bytes32) -> Result<()> {
+            let (global, data_feed) = self.pause_states(data_feed_id)?;
+            Ok(api3_common::ensure_updates_allowed(global, data_feed)?)
+        }
+
+        /// Checks updates are allowed for the dAPI and each of its Beacons
+        fn ensure_dapi_updates_allowed(
+            &self,
+            dapi_id: &Bytes32,
+            beacon_ids: &[Bytes32],
+        ) -> Result<()> {
+            self.ensure_updates_allowed(dapi_id)?;
+            for beacon_id in beacon_ids {
+                self.ensure_updates_allowed(beacon_id)?;
+            }
+            Ok(())
+        }
+
+        fn ensure_reads_allowed(&self, data_feed_id: &Bytes32) -> Result<()> {
+            let (global, data_feed) = self.pause_states(data_feed_id)?;
+            Ok(api3_common::ensure_reads_allowed(global, data_feed)?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::utils::permit_setter;
+        use api3_common::abi::{encode, keccak256, Int};
+        use api3_common::MerkleTree;
+        use ink::env::hash::{Blake2x256, HashOutput};
+        use ink::env::test::{
+            callee, default_accounts, get_account_balance, set_account_balance,
+            set_block_timestamp, set_caller, set_value_transferred,
+        };
+        use ink::env::DefaultEnvironment;
+
+        const TEMPLATE_ID: Bytes32 = [1u8; 32];
+        const NOW: u64 = 1_650_000_000;
+
+        fn airnode_key(seed: u8) -> libsecp256k1::SecretKey {
+            libsecp256k1::SecretKey::parse(&[seed; 32]).unwrap()
+        }
+
+        fn airnode_address(key: &libsecp256k1::SecretKey) -> Vec<u8> {
+            let public_key = libsecp256k1::PublicKey::from_secret_key(key).serialize();
+            keccak256(&public_key[1..])[12..].to_vec()
+        }
+
+        /// Signs `value` at `timestamp` the way Airnode does, returns the
+        /// timestamp, data and signature arguments of the update messages
+        fn sign(
+            key: &libsecp256k1::SecretKey,
+            timestamp: u64,
+            value: u128,
+        ) -> (Bytes32, Vec<u8>, Vec<u8>) {
+            let mut ts = [0u8; 32];
+            Uint::from(timestamp).to_big_endian(&mut ts);
+            let data = encode(&[Token::Int(Int::from(value))]);
+            let message = keccak_packed(&[
+                Token::FixedBytes(TEMPLATE_ID.to_vec()),
+                Token::Uint(Uint::from(timestamp)),
+                Token::Bytes(data.clone()),
+            ]);
+            let mut prefixed = b"\x19Ethereum Signed Message:\n32".to_vec();
+            prefixed.extend_from_slice(&message);
+            let (signature, recovery_id) =
+                libsecp256k1::sign(&libsecp256k1::Message::parse(&keccak256(&prefixed)), key);
+            let mut signature = signature.serialize().to_vec();
+            signature.push(recovery_id.serialize() + 27);
+            (ts, data, signature)
+        }
+
+        fn value(v: u128) -> Bytes32 {
+            value_to_bytes32(Int::from(v))
+        }
+
+        /// Signs the permit the way a Substrate ECDSA account does, returns the
+        /// setter account, its compressed public key and the signature
+        fn sign_permit(
+            key: &libsecp256k1::SecretKey,
+            permit: &WhitelistPermit,
+        ) -> (AccountId, Vec<u8>, Vec<u8>) {
+            let setter_key = libsecp256k1::PublicKey::from_secret_key(key)
+                .serialize_compressed()
+                .to_vec();
+            let mut hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(&permit.message(), &mut hash);
+            let (signature, recovery_id) =
+                libsecp256k1::sign(&libsecp256k1::Message::parse(&hash), key);
+            let mut signature = signature.serialize().to_vec();
+            signature.push(recovery_id.serialize());
+            (permit_setter(&setter_key).0, setter_key, signature)
+        }
+
+        fn deploy() -> DapiServer {
+            let accounts = default_accounts::<DefaultEnvironment>();
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            set_block_timestamp::<DefaultEnvironment>(NOW * 1000);
+            DapiServer::new(String::from("ink-test"))
+        }
+
+        #[ink::test]
+        fn updates_beacon_with_signed_data() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let key = airnode_key(1);
+            let airnode = airnode_address(&key);
+            let beacon_id = contract
+                .derive_beacon_id(airnode.clone(), TEMPLATE_ID)
+                .unwrap();
+
+            let (ts, data, signature) = sign(&key, NOW - 10, 123);
+            contract
+                .update_beacon_with_signed_data(airnode.clone(), TEMPLATE_ID, ts, data, signature)
+                .unwrap();
+
+            // a signature of another Airnode is rejected
+            let (ts, data, signature) = sign(&airnode_key(2), NOW - 5, 456);
+            assert_eq!(
+                contract.update_beacon_with_signed_data(
+                    airnode.clone(),
+                    TEMPLATE_ID,
+                    ts,
+                    data,
+                    signature
+                ),
+                Err(ContractError(Error::InvalidSignature.code()))
+            );
+
+            // the manager is not whitelisted, but can grant itself the unlimited reader role
+            assert_eq!(
+                contract.read_with_data_point_id(beacon_id, None),
+                Err(ContractError(Error::AccessDenied.code()))
+            );
+            let (unlimited_reader, _) = contract.roles();
+            contract
+                .grant_role(unlimited_reader, accounts.alice)
+                .unwrap();
+            assert_eq!(
+                contract.read_with_data_point_id(beacon_id, None),
+                Ok((value(123), (NOW - 10) as u32))
+            );
+        }
+
+        #[ink::test]
+        fn updates_dapi_with_signed_data() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let keys = [airnode_key(1), airnode_key(2), airnode_key(3)];
+            let airnodes = keys.iter().map(airnode_address).collect::<Vec<_>>();
+            let (timestamps, data, signatures) = keys
+                .iter()
+                .zip([100u128, 200, 400])
+                .map(|(k, v)| sign(k, NOW - 10, v))
+                .fold(
+                    (Vec::new(), Vec::new(), Vec::new()),
+                    |(mut t, mut d, mut s), (ts, data, sig)| {
+                        t.push(ts);
+                        d.push(data);
+                        s.push(sig);
+                        (t, d, s)
+                    },
+                );
+
+            let dapi_id = contract
+                .update_dapi_with_signed_data(
+                    airnodes,
+                    vec![TEMPLATE_ID; 3],
+                    timestamps,
+                    data,
+                    signatures,
+                )
+                .unwrap();
+
+            contract
+                .set_whitelist_expiration(dapi_id, accounts.bob, NOW + 60)
+                .unwrap();
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.read_with_data_point_id(dapi_id, None),
+                Ok((value(200), (NOW - 10) as u32))
+            );
+
+            // the temporary whitelist expires
+            set_block_timestamp::<DefaultEnvironment>((NOW + 60) * 1000);
+            assert!(!contract.reader_can_read_data_point(dapi_id, accounts.bob, None));
+        }
+
+        #[ink::test]
+        fn names_and_whitelists() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let name = [7u8; 32];
+            let data_point_id = [8u8; 32];
+            let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+
+            assert_eq!(
+                contract.set_name(name, data_point_id),
+                Err(ContractError(Error::AccessDenied.code()))
+            );
+            let (_, name_setter) = contract.roles();
+            contract.grant_role(name_setter, accounts.alice).unwrap();
+            contract.set_name(name, data_point_id).unwrap();
+            assert_eq!(contract.name_to_data_point_id(name), Some(data_point_id));
+
+            // only the manager and indefinite whitelisters can whitelist indefinitely
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_indefinite_whitelist_status(name_hash, accounts.charlie, true),
+                Err(ContractError(Error::AccessDenied.code()))
+            );
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            contract
+                .set_indefinite_whitelist_status(name_hash, accounts.charlie, true)
+                .unwrap();
+            assert!(
+                contract.data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+                    name_hash,
+                    accounts.charlie,
+                    accounts.alice
+                )
+            );
+
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.read_with_name(name, None), Ok((value(0), 0)));
+
+            // the manager can always set indefinite whitelist statuses, so they cannot be revoked
+            assert_eq!(
+                contract.revoke_indefinite_whitelist_status(
+                    name_hash,
+                    accounts.charlie,
+                    accounts.alice
+                ),
+                Err(ContractError(Error::SetterCanSetIndefiniteStatus.code()))
+            );
+        }
+
+        #[ink::test]
+        fn pause_blocks_updates_and_reads() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let beacon_ids = vec![[1u8; 32], [2u8; 32]];
+            let dapi_id = contract.derive_beacon_set_id(beacon_ids.clone());
+
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(contract.set_pause_state(1).is_err());
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.set_data_feed_pause_state(dapi_id, 2).unwrap();
+            assert!(contract
+                .update_dapi_with_beacons(beacon_ids.clone())
+                .is_err());
+            assert!(contract.read_with_data_point_id(dapi_id, None).is_err());
+
+            contract.set_data_feed_pause_state(dapi_id, 0).unwrap();
+            assert_eq!(contract.update_dapi_with_beacons(beacon_ids), Ok(dapi_id));
+        }
+
+        #[ink::test]
+        fn paused_beacon_blocks_dapi_updates() {
+            let mut contract = deploy();
+            let beacon_ids = vec![[1u8; 32], [2u8; 32]];
+
+            contract
+                .set_data_feed_pause_state(beacon_ids[1], 1)
+                .unwrap();
+            assert_eq!(
+                contract.update_dapi_with_beacons(beacon_ids.clone()),
+                Err(ContractError(Error::UpdatesPaused.code()))
+            );
+
+            contract
+                .set_data_feed_pause_state(beacon_ids[1], 0)
+                .unwrap();
+            assert!(contract.update_dapi_with_beacons(beacon_ids).is_ok());
+        }
+
+        #[ink::test]
+        fn manager_transfer_moves_admin_role() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+
+            contract.propose_manager(accounts.bob).unwrap();
+            assert_eq!(contract.pending_manager(), Some(accounts.bob));
+            assert_eq!(
+                contract.accept_manager(),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.accept_manager().unwrap();
+            assert_eq!(contract.manager(), accounts.bob);
+            assert_eq!(contract.previous_manager(), Some(accounts.alice));
+            assert!(contract.has_role(DapiServer::DEFAULT_ADMIN_ROLE, accounts.bob));
+            assert!(!contract.has_role(DapiServer::DEFAULT_ADMIN_ROLE, accounts.alice));
+        }
+
+        #[ink::test]
+        fn migrated_roles_keep_their_expiration() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let pauser = contract.pauser_role();
+            contract
+                .grant_role_until(pauser, accounts.charlie, NOW + 10)
+                .unwrap();
+
+            contract.propose_manager(accounts.bob).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.accept_manager().unwrap();
+            contract
+                .migrate_role_members(String::from("Pauser"), vec![accounts.charlie])
+                .unwrap();
+            let pauser = contract.pauser_role();
+            assert!(contract.has_role(pauser, accounts.charlie));
+            assert_eq!(
+                contract.role_expiration(pauser, accounts.charlie),
+                Some(NOW + 10)
+            );
+
+            set_block_timestamp::<DefaultEnvironment>((NOW + 10) * 1000);
+            assert!(!contract.has_role(pauser, accounts.charlie));
+        }
+
+        #[ink::test]
+        fn subscription_whitelists_and_pays_manager() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let data_feed_id = [9u8; 32];
+
+            assert_eq!(
+                contract.subscribe(data_feed_id, 1),
+                Err(ContractError(Error::SubscriptionNotConfigured.code()))
+            );
+            contract.set_subscription_price(10, 100).unwrap();
+
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_subscription_price(0, 0),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+            set_value_transferred::<DefaultEnvironment>(19);
+            assert_eq!(
+                contract.subscribe(data_feed_id, 2),
+                Err(ContractError(Error::InsufficientPayment.code()))
+            );
+
+            // the excess is refunded
+            set_account_balance::<DefaultEnvironment>(callee::<DefaultEnvironment>(), 25);
+            let balance = get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            set_value_transferred::<DefaultEnvironment>(25);
+            assert_eq!(contract.subscribe(data_feed_id, 2), Ok(NOW + 200));
+            assert_eq!(
+                get_account_balance::<DefaultEnvironment>(accounts.bob),
+                Ok(balance + 5)
+            );
+            assert!(contract.reader_can_read_data_point(data_feed_id, accounts.bob, None));
+            assert_eq!(
+                contract.whitelisted_users_of(data_feed_id, 0, 10),
+                vec![accounts.bob]
+            );
+            assert_eq!(contract.subscription_balance(), 20);
+
+            assert_eq!(
+                contract.withdraw_subscription_balance(20),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.withdraw_subscription_balance(21),
+                Err(ContractError(Error::InsufficientBalance.code()))
+            );
+            let balance = get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            contract.withdraw_subscription_balance(20).unwrap();
+            assert_eq!(contract.subscription_balance(), 0);
+            assert_eq!(
+                get_account_balance::<DefaultEnvironment>(accounts.alice),
+                Ok(balance + 20)
+            );
+        }
+
+        #[ink::test]
+        fn permits_and_merkle_proofs_whitelist_readers() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let data_feed_id = [9u8; 32];
+
+            let contract_id = callee::<DefaultEnvironment>();
+            let mut permit = WhitelistPermit {
+                chain_id: String::from("ink-test"),
+                contract: AsRef::<[u8]>::as_ref(&contract_id).to_vec(),
+                service_id: data_feed_id,
+                user: AsRef::<[u8]>::as_ref(&accounts.charlie).to_vec(),
+                expiration_timestamp: api3_common::Timestamp::from_secs(NOW + 100),
+                nonce: 0,
+                deadline: api3_common::Timestamp::from_secs(NOW + 10),
+            };
+            assert_eq!(
+                contract.whitelist_permit_domain_separator(),
+                api3_common::permit_domain_separator(&permit.chain_id, &permit.contract)
+            );
+            let key = airnode_key(5);
+            let (setter, setter_key, signature) = sign_permit(&key, &permit);
+
+            // the setter needs the whitelist expiration setter role
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_whitelist_expiration_with_permit(
+                    data_feed_id,
+                    accounts.charlie,
+                    NOW + 100,
+                    0,
+                    NOW + 10,
+                    setter_key.clone(),
+                    signature.clone()
+                ),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            let role = contract.whitelist_expiration_setter_role();
+            contract.grant_role(role, setter).unwrap();
+
+            // anyone can submit the permit, but only for the signed user
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_whitelist_expiration_with_permit(
+                    data_feed_id,
+                    accounts.django,
+                    NOW + 100,
+                    0,
+                    NOW + 10,
+                    setter_key.clone(),
+                    signature.clone()
+                ),
+                Err(ContractError(Error::InvalidSignature.code()))
+            );
+            contract
+                .set_whitelist_expiration_with_permit(
+                    data_feed_id,
+                    accounts.charlie,
+                    NOW + 100,
+                    0,
+                    NOW + 10,
+                    setter_key.clone(),
+                    signature.clone(),
+                )
+                .unwrap();
+            assert_eq!(contract.whitelist_permit_nonce(setter), 1);
+            assert!(contract.reader_can_read_data_point(data_feed_id, accounts.charlie, None));
+            assert_eq!(
+                contract.set_whitelist_expiration_with_permit(
+                    data_feed_id,
+                    accounts.charlie,
+                    NOW + 100,
+                    0,
+                    NOW + 10,
+                    setter_key.clone(),
+                    signature
+                ),
+                Err(ContractError(Error::InvalidPermitNonce.code()))
+            );
+
+            // a permit signed for another chain is rejected
+            permit.chain_id = String::from("another-chain");
+            permit.nonce = 1;
+            let (_, _, signature) = sign_permit(&key, &permit);
+            assert_eq!(
+                contract.set_whitelist_expiration_with_permit(
+                    data_feed_id,
+                    accounts.charlie,
+                    NOW + 100,
+                    1,
+                    NOW + 10,
+                    setter_key,
+                    signature
+                ),
+                Err(ContractError(Error::InvalidSignature.code()))
+            );
+
+            // readers in the published Merkle tree read with their proof
+            let tree = MerkleTree::from_users(&[accounts.django, accounts.eve]);
+            assert_eq!(
+                contract.set_whitelist_merkle_root(data_feed_id, tree.root()),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            contract
+                .set_whitelist_merkle_root(data_feed_id, tree.root())
+                .unwrap();
+            let proof = tree.user_proof(accounts.eve.as_ref()).unwrap();
+            assert!(!contract.reader_can_read_data_point(data_feed_id, accounts.eve, None));
+            assert!(contract.reader_can_read_data_point(
+                data_feed_id,
+                accounts.eve,
+                Some(proof.clone())
+            ));
+            assert!(!contract.reader_can_read_data_point(
+                data_feed_id,
+                accounts.django,
+                Some(proof.clone())
+            ));
+            set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                contract.read_with_data_point_id(data_feed_id, Some(proof)),
+                Ok((value(0), 0))
+            );
+        }
+
+        #[ink::test]
+        fn expiring_roles_are_enumerable() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let (unlimited_reader, name_setter) = contract.roles();
+
+            assert_eq!(
+                contract.grant_role_until(unlimited_reader, accounts.bob, NOW),
+                Err(ContractError(Error::InvalidTimestamp.code()))
+            );
+            contract
+                .grant_role_until(unlimited_reader, accounts.bob, NOW + 10)
+                .unwrap();
+            contract.grant_role(name_setter, accounts.bob).unwrap();
+            assert_eq!(
+                contract.role_expiration(unlimited_reader, accounts.bob),
+                Some(NOW + 10)
+            );
+            assert_eq!(contract.role_expiration(name_setter, accounts.bob), None);
+            assert_eq!(contract.get_role_member_count(unlimited_reader), 1);
+            assert_eq!(
+                contract.get_role_member(unlimited_reader, 0),
+                Some(accounts.bob)
+            );
+            assert_eq!(contract.get_role_member(unlimited_reader, 1), None);
+            assert_eq!(
+                contract.roles_of(accounts.bob),
+                vec![unlimited_reader, name_setter]
+            );
+
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.grant_role_until(unlimited_reader, accounts.charlie, NOW + 10),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+
+            // the grant expires on its own
+            set_block_timestamp::<DefaultEnvironment>((NOW + 10) * 1000);
+            assert!(!contract.has_role(unlimited_reader, accounts.bob));
+            assert_eq!(contract.get_role_member_count(unlimited_reader), 0);
+            assert_eq!(contract.roles_of(accounts.bob), vec![name_setter]);
+
+            // granting again without an expiration is permanent
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.grant_role(unlimited_reader, accounts.bob).unwrap();
+            assert_eq!(
+                contract.role_expiration(unlimited_reader, accounts.bob),
+                None
+            );
+            assert!(contract.has_role(unlimited_reader, accounts.bob));
+        }
+
+        #[ink::test]
+        fn closed_epoch_read_counts_are_kept_until_pruned() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let data_feed_id = [9u8; 32];
+            let epoch = NOW / 100;
+
+            contract.set_usage_epoch_length(100).unwrap();
+            contract
+                .set_indefinite_whitelist_status(data_feed_id, accounts.django, true)
+                .unwrap();
+            set_caller::<DefaultEnvironment>(accounts.django);
+            contract
+                .read_with_data_point_id(data_feed_id, None)
+                .unwrap();
+
+            // the next epoch does not overwrite the count of the closed one
+            set_block_timestamp::<DefaultEnvironment>((NOW + 100) * 1000);
+            contract
+                .read_with_data_point_id(data_feed_id, None)
+                .unwrap();
+            assert_eq!(contract.read_count(data_feed_id, accounts.django, epoch), 1);
+            assert_eq!(
+                contract.read_count(data_feed_id, accounts.django, epoch + 1),
+                1
+            );
+
+            assert_eq!(
+                contract.prune_read_counts(data_feed_id, vec![accounts.django], epoch),
+                Err(ContractError(Error::NotAuthorized.code()))
+            );
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.prune_read_counts(data_feed_id, vec![accounts.django], epoch + 1),
+                Err(ContractError(Error::UsageEpochNotClosed.code()))
+            );
+            contract
+                .prune_read_counts(data_feed_id, vec![accounts.django], epoch)
+                .unwrap();
+            assert_eq!(contract.read_count(data_feed_id, accounts.django, epoch), 0);
+            assert_eq!(
+                contract.read_count(data_feed_id, accounts.django, epoch + 1),
+                1
+            );
+        }
+
+        #[ink::test]
+        fn reads_are_metered_and_expired_users_swept() {
+            let mut contract = deploy();
+            let accounts = default_accounts::<DefaultEnvironment>();
+            let data_feed_id = [9u8; 32];
+            let temporary = u8::from(ReaderTier::TemporarilyWhitelisted);
+
+            assert_eq!(
+                contract.set_usage_quota(9, Some(1)),
+                Err(ContractError(Error::InvalidReaderTier.code()))
+            );
+            contract.set_usage_epoch_length(100).unwrap();
+            contract.set_usage_quota(temporary, Some(1)).unwrap();
+            assert_eq!(contract.usage_quota(temporary), Some(1));
+            assert_eq!(contract.current_usage_epoch(), Some(NOW / 100));
+
+            contract
+                .set_whitelist_expiration(data_feed_id, accounts.bob, NOW + 50)
+                .unwrap();
+            contract
+                .set_whitelist_expiration(data_feed_id, accounts.charlie, NOW + 500)
+                .unwrap();
+            contract
+                .set_indefinite_whitelist_status(data_feed_id, accounts.django, true)
+                .unwrap();
+            assert_eq!(
+                contract.indefinite_whitelist_setters_of(data_feed_id, accounts.django),
+                vec![accounts.alice]
+            );
+
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            contract
+                .read_with_data_point_id(data_feed_id, None)
+                .unwrap();
+            assert_eq!(
+                contract.read_with_data_point_id(data_feed_id, None),
+                Err(ContractError(Error::ReadQuotaExceeded.code()))
+            );
+            assert_eq!(
+                contract.read_count(data_feed_id, accounts.bob, NOW / 100),
+                1
+            );
+            // indefinitely whitelisted readers have no quota
+            set_caller::<DefaultEnvironment>(accounts.django);
+            for _ in 0..2 {
+                contract
+                    .read_with_data_point_id(data_feed_id, None)
+                    .unwrap();
+            }
+
+            // only the expired user is swept
+            set_block_timestamp::<DefaultEnvironment>((NOW + 60) * 1000);
+            assert_eq!(contract.whitelisted_user_count(data_feed_id), 3);
+            assert_eq!(contract.sweep_expired(data_feed_id, 0, 10), 1);
+            assert_eq!(contract.whitelisted_user_count(data_feed_id), 2);
+            assert_eq!(
+                contract.whitelisted_users_of(data_feed_id, 0, 10),
+                vec![accounts.django, accounts.charlie]
+            );
+            assert_eq!(
+                contract.whitelisted_users_of(data_feed_id, 1, 10),
+                vec![accounts.charlie]
+            );
+            assert_eq!(
+                contract.data_feed_id_to_whitelist_status(data_feed_id, accounts.bob),
+                None
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use api3_common::abi::{Int, U256};
+use api3_common::{Bytes32, DataPoint, FeedStatus, Timestamp, WhitelistStatus, Zero, BYTES32_ZERO};
+use ink::primitives::AccountId;
+
+/// The ink! account as the address type of `api3_common`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Address(pub AccountId);
+
+impl Zero for Address {
+    fn is_zero(&self) -> bool {
+        AsRef::<[u8]>::as_ref(&self.0).iter().all(|b| *b == 0)
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+#[derive(scale::Encode, scale::Decode, Clone, Default)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+pub struct InkDataPoint {
+    pub value: Bytes32,
+    pub timestamp: u32,
+}
+
+impl From<InkDataPoint> for DataPoint {
+    fn from(t: InkDataPoint) -> Self {
+        DataPoint::new(
+            Int::from_big_endian(&t.value),
+            Timestamp::from_secs(u64::from(t.timestamp)),
+        )
+    }
+}
+
+impl From<DataPoint> for InkDataPoint {
+    fn from(t: DataPoint) -> Self {
+        let mut value = [0u8; 32];
+        t.value.to_big_endian(&mut value);
+        InkDataPoint {
+            value,
+            timestamp: t.timestamp.as_secs_u32(),
+        }
+    }
+}
+
+#[derive(scale::Encode, scale::Decode, Clone)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+pub enum InkFeedStatus {
+    Active,
+    Paused(InkDataPoint),
+}
+
+impl From<InkFeedStatus> for FeedStatus {
+    fn from(t: InkFeedStatus) -> Self {
+        match t {
+            InkFeedStatus::Active => FeedStatus::Active,
+            InkFeedStatus::Paused(d) => FeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+impl From<FeedStatus> for InkFeedStatus {
+    fn from(t: FeedStatus) -> Self {
+        match t {
+            FeedStatus::Active => InkFeedStatus::Active,
+            FeedStatus::Paused(d) => InkFeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+#[derive(scale::Encode, scale::Decode, Clone)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+pub struct InkWhitelistStatus {
+    /// in seconds
+    pub expiration_timestamp: u64,
+    /// originally uint192, stored big endian
+    pub indefinite_whitelist_count: Bytes32,
+}
+
+impl Default for InkWhitelistStatus {
+    fn default() -> Self {
+        Self {
+            expiration_timestamp: 0,
+            indefinite_whitelist_count: BYTES32_ZERO,
+        }
+    }
+}
+
+impl From<InkWhitelistStatus> for WhitelistStatus {
+    fn from(s: InkWhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: Timestamp::from_secs(s.expiration_timestamp),
+            indefinite_whitelist_count: U256::from_big_endian(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+impl From<WhitelistStatus> for InkWhitelistStatus {
+    fn from(s: WhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: s.expiration_timestamp.as_secs(),
+            indefinite_whitelist_count: Bytes32::from(&s.indefinite_whitelist_count),
+        }
+    }
+}
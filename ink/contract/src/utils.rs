@@ -0,0 +1,514 @@
+use crate::types::{Address, InkDataPoint, InkFeedStatus};
+use api3_common::abi::{keccak256, Token};
+use api3_common::{
+    keccak_packed, migrate_role_grant_with_expiration, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, AccessControlRegistryEnumerable,
+    AccessControlRegistryWithExpiration, Bytes32, DapiUpdateError, DataPoint, Error, ErrorPayload,
+    FeedStatus, PermitSigner, RoleDeriver, SignatureManger, Storage, Timestamp, TimestampChecker,
+};
+use ink::env::hash::Blake2x256;
+use ink::prelude::string::String;
+use ink::prelude::vec::Vec;
+use ink::primitives::AccountId;
+use ink::storage::traits::StorageKey;
+use ink::storage::Mapping;
+
+/// Read write privilege
+pub(crate) enum ReadWrite<'a, T> {
+    ReadOnly(&'a T),
+    Write(&'a mut T),
+}
+
+impl<'a, T> ReadWrite<'a, T> {
+    pub fn get(&self) -> &T {
+        match self {
+            ReadWrite::ReadOnly(m) => m,
+            ReadWrite::Write(m) => m,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        match self {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        }
+    }
+}
+
+/// The error of a failed message. It is the code of the `api3_common::Error`,
+/// which clients can map back with `Error::from_code`.
+#[derive(scale::Encode, scale::Decode, Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ContractError(pub u32);
+
+impl From<Error> for ContractError {
+    fn from(e: Error) -> Self {
+        ContractError(e.code())
+    }
+}
+
+impl From<DapiUpdateError> for ContractError {
+    fn from(e: DapiUpdateError) -> Self {
+        ContractError::from(e.error)
+    }
+}
+
+/// Fails the call where the common traits leave no room for an error, with the
+/// payload as the panic message. Messages return `ContractError` otherwise.
+pub(crate) fn ink_fail(error: Error) -> ! {
+    panic!("{}", ErrorPayload::from(&error).to_json())
+}
+
+/// Fails the call for writing to storage opened with `read_only`, which is a
+/// bug in the contract rather than in the request
+pub(crate) fn read_only_storage() -> ! {
+    ink_fail(Error::ReadOnlyStorage)
+}
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct DatapointMap<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, InkDataPoint, K>>,
+}
+
+impl<'a, K: StorageKey> DatapointMap<'a, K> {
+    pub fn requires_write(map: &'a mut Mapping<Bytes32, InkDataPoint, K>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a Mapping<Bytes32, InkDataPoint, K>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a, K: StorageKey> Storage<DataPoint> for DatapointMap<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        Some(self.map.get().get(k).unwrap_or_default().into())
+    }
+
+    fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
+        self.map.get_mut().insert(k, &InkDataPoint::from(datapoint));
+    }
+}
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct Bytes32Map<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, Bytes32, K>>,
+}
+
+impl<'a, K: StorageKey> Bytes32Map<'a, K> {
+    pub fn requires_write(map: &'a mut Mapping<Bytes32, Bytes32, K>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a Mapping<Bytes32, Bytes32, K>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a, K: StorageKey> Storage<Bytes32> for Bytes32Map<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<Bytes32> {
+        self.map.get().get(k)
+    }
+
+    fn store(&mut self, k: Bytes32, data: Bytes32) {
+        self.map.get_mut().insert(k, &data);
+    }
+}
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusMap<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, InkFeedStatus, K>>,
+}
+
+impl<'a, K: StorageKey> FeedStatusMap<'a, K> {
+    pub fn requires_write(map: &'a mut Mapping<Bytes32, InkFeedStatus, K>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a Mapping<Bytes32, InkFeedStatus, K>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a, K: StorageKey> Storage<FeedStatus> for FeedStatusMap<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        self.map.get().get(k).map(FeedStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        self.map.get_mut().insert(k, &InkFeedStatus::from(status));
+    }
+}
+
+/// Signature verification for ink! so that we can use `api3_common` package
+/// for the functions. Airnodes sign with their EVM key like on the Solidity
+/// contract: `key` is the 20 byte Airnode address and `signature` is the
+/// 65 byte recoverable signature of the `eth_sign` prefixed message.
+pub(crate) struct SignatureVerify;
+
+impl SignatureManger for SignatureVerify {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let signature = match <[u8; 65]>::try_from(signature) {
+            Ok(s) if matches!(s[64], 0 | 1 | 27 | 28) => s,
+            _ => return false,
+        };
+        let mut prefixed = b"\x19Ethereum Signed Message:\n32".to_vec();
+        prefixed.extend_from_slice(message);
+        let message_hash = keccak256(&prefixed);
+
+        let mut public_key = [0u8; 33];
+        if ink::env::ecdsa_recover(&signature, &message_hash, &mut public_key).is_err() {
+            return false;
+        }
+        let mut address = [0u8; 20];
+        ink::env::ecdsa_to_eth_address(&public_key, &mut address).is_ok() && key == address
+    }
+}
+
+pub(crate) struct InkClock {
+    current_timestamp: Timestamp,
+}
+
+impl InkClock {
+    pub fn new(current_timestamp: Timestamp) -> Self {
+        Self { current_timestamp }
+    }
+
+    /// The clock at the current block, ink! reports the block time in milliseconds
+    pub fn now() -> Self {
+        Self::new(Timestamp::from_millis(ink::env::block_timestamp::<
+            ink::env::DefaultEnvironment,
+        >()))
+    }
+}
+
+impl TimestampChecker for InkClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
+pub(crate) fn msg_sender() -> Address {
+    Address(ink::env::caller::<ink::env::DefaultEnvironment>())
+}
+
+/// Role membership storage. Memberships are looked up by `hash_membership`, the
+/// members of each role and the roles of each account are kept as well so that
+/// they can be enumerated. Grants made with `grant_role_until` have their
+/// expiration stored under the same hash.
+#[ink::storage_item]
+#[derive(Debug, Default)]
+pub(crate) struct RoleMembership {
+    membership: Mapping<Bytes32, bool>,
+    role_members: Mapping<Bytes32, Vec<AccountId>>,
+    account_roles: Mapping<AccountId, Vec<Bytes32>>,
+    expirations: Mapping<Bytes32, u64>,
+}
+
+impl RoleMembership {
+    fn contains(&self, hash: &Bytes32) -> bool {
+        self.membership.contains(hash)
+    }
+
+    fn insert(&mut self, hash: &Bytes32, role: &Bytes32, who: &Address) {
+        self.expirations.remove(hash);
+        if self.membership.insert(hash, &true).is_some() {
+            return;
+        }
+        let mut members = self.role_members.get(role).unwrap_or_default();
+        members.push(who.0);
+        self.role_members.insert(role, &members);
+        let mut roles = self.account_roles.get(who.0).unwrap_or_default();
+        roles.push(*role);
+        self.account_roles.insert(who.0, &roles);
+    }
+
+    fn remove(&mut self, hash: &Bytes32, role: &Bytes32, who: &Address) {
+        self.expirations.remove(hash);
+        if !self.membership.contains(hash) {
+            return;
+        }
+        self.membership.remove(hash);
+        let mut members = self.role_members.get(role).unwrap_or_default();
+        members.retain(|m| *m != who.0);
+        self.role_members.insert(role, &members);
+        let mut roles = self.account_roles.get(who.0).unwrap_or_default();
+        roles.retain(|r| r != role);
+        self.account_roles.insert(who.0, &roles);
+    }
+}
+
+pub(crate) struct InkAccessControlRegistry<'a, A: StorageKey> {
+    manager: Address,
+    admin_role_description: String,
+    role_membership: ReadWrite<'a, RoleMembership>,
+    role_admin: ReadWrite<'a, Mapping<Bytes32, Bytes32, A>>,
+}
+
+impl<'a, A: StorageKey> InkAccessControlRegistry<'a, A> {
+    pub fn requires_write(
+        manager: Address,
+        admin_role_description: String,
+        role_membership: &'a mut RoleMembership,
+        role_admin: &'a mut Mapping<Bytes32, Bytes32, A>,
+    ) -> Self {
+        Self {
+            manager,
+            admin_role_description,
+            role_membership: ReadWrite::Write(role_membership),
+            role_admin: ReadWrite::Write(role_admin),
+        }
+    }
+
+    pub fn read_only(
+        manager: Address,
+        admin_role_description: String,
+        role_membership: &'a RoleMembership,
+        role_admin: &'a Mapping<Bytes32, Bytes32, A>,
+    ) -> Self {
+        Self {
+            manager,
+            admin_role_description,
+            role_membership: ReadWrite::ReadOnly(role_membership),
+            role_admin: ReadWrite::ReadOnly(role_admin),
+        }
+    }
+
+    fn role_members(&self, role: &Bytes32) -> Vec<Address> {
+        self.role_membership
+            .get()
+            .role_members
+            .get(role)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Address)
+            .filter(|m| !self.role_is_expired(role, m))
+            .collect()
+    }
+
+    fn hash_membership(role: &Bytes32, who: &Address) -> Bytes32 {
+        keccak_packed(&[
+            Token::FixedBytes(role.to_vec()),
+            Token::FixedBytes(who.as_ref().to_vec()),
+        ])
+    }
+}
+
+impl<'a, A: StorageKey> AccessControlRegistryAdminnedWithManager
+    for InkAccessControlRegistry<'a, A>
+{
+    type Address = Address;
+
+    fn manager(&self) -> &Self::Address {
+        &self.manager
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.admin_role_description.clone()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        keccak_packed(&[Token::String(self.admin_role_description())])
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        RoleDeriver::derive_role(
+            RoleDeriver::derive_root_role(self.manager.as_ref()),
+            self.admin_role_description(),
+        )
+    }
+}
+
+impl<'a, A: StorageKey> AccessControlRegistry for InkAccessControlRegistry<'a, A> {
+    fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        let hash = Self::hash_membership(role, who);
+        self.role_membership.get().contains(&hash) && !self.role_is_expired(role, who)
+    }
+
+    fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+        let hash = Self::hash_membership(role, who);
+        self.role_membership.get_mut().insert(&hash, role, who);
+        Ok(())
+    }
+
+    fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+        if *role == Self::DEFAULT_ADMIN_ROLE {
+            return Some(Self::DEFAULT_ADMIN_ROLE);
+        }
+        self.role_admin.get().get(role)
+    }
+
+    fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+        self.role_admin.get_mut().insert(role, &role_admin);
+        Ok(())
+    }
+
+    fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        api3_common::ensure!(*account == msg_sender(), Error::NotAuthorized)?;
+        self.revoke_role(role, account)
+    }
+
+    fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        let hash = Self::hash_membership(role, account);
+        self.role_membership.get_mut().remove(&hash, role, account);
+        Ok(())
+    }
+
+    fn migrate_role_grant(
+        &mut self,
+        from: &Bytes32,
+        to: &Bytes32,
+        who: &Self::Address,
+    ) -> Result<(), Error> {
+        migrate_role_grant_with_expiration(self, from, to, who)
+    }
+}
+
+impl<'a, A: StorageKey> AccessControlRegistryEnumerable for InkAccessControlRegistry<'a, A> {
+    fn get_role_member_count(&self, role: &Bytes32) -> usize {
+        self.role_members(role).len()
+    }
+
+    fn get_role_member(&self, role: &Bytes32, index: usize) -> Option<Self::Address> {
+        self.role_members(role).get(index).copied()
+    }
+
+    fn roles_of(&self, account: &Self::Address) -> Vec<Bytes32> {
+        let mut roles = self
+            .role_membership
+            .get()
+            .account_roles
+            .get(account.0)
+            .unwrap_or_default();
+        roles.retain(|r| !self.role_is_expired(r, account));
+        roles
+    }
+}
+
+impl<'a, A: StorageKey> TimestampChecker for InkAccessControlRegistry<'a, A> {
+    fn current_timestamp(&self) -> Timestamp {
+        InkClock::now().current_timestamp()
+    }
+}
+
+impl<'a, A: StorageKey> AccessControlRegistryWithExpiration for InkAccessControlRegistry<'a, A> {
+    fn role_expiration(&self, role: &Bytes32, who: &Self::Address) -> Option<Timestamp> {
+        let hash = Self::hash_membership(role, who);
+        self.role_membership
+            .get()
+            .expirations
+            .get(hash)
+            .map(Timestamp::from_secs)
+    }
+
+    fn set_role_expiration(
+        &mut self,
+        role: &Bytes32,
+        who: &Self::Address,
+        expiration_timestamp: Option<Timestamp>,
+    ) {
+        let hash = Self::hash_membership(role, who);
+        let expirations = &mut self.role_membership.get_mut().expirations;
+        match expiration_timestamp {
+            Some(e) => {
+                expirations.insert(hash, &e.as_secs());
+            }
+            None => expirations.remove(hash),
+        }
+    }
+}
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct U64Map<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, u64, K>>,
+}
+
+impl<'a, K: StorageKey> U64Map<'a, K> {
+    pub fn requires_write(map: &'a mut Mapping<Bytes32, u64, K>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+}
+
+impl<'a, K: StorageKey> Storage<u64> for U64Map<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<u64> {
+        self.map.get().get(k)
+    }
+
+    fn store(&mut self, k: Bytes32, value: u64) {
+        self.map.get_mut().insert(k, &value);
+    }
+}
+
+/// Verifies whitelist permits. Setters sign with the ECDSA key of their
+/// account the way Substrate does: `key` is the 33 byte compressed public key
+/// and `signature` is the 65 byte recoverable signature of the blake2 256
+/// hash of the message. See `permit_setter` for the account of the key.
+pub(crate) struct PermitSignatureVerify;
+
+impl SignatureManger for PermitSignatureVerify {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let signature = match <[u8; 65]>::try_from(signature) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+        let mut message_hash = [0u8; 32];
+        ink::env::hash_bytes::<Blake2x256>(message, &mut message_hash);
+        let mut public_key = [0u8; 33];
+        ink::env::ecdsa_recover(&signature, &message_hash, &mut public_key).is_ok()
+            && key == public_key
+    }
+}
+
+impl PermitSigner for PermitSignatureVerify {
+    type Address = Address;
+
+    fn address_of(key: &[u8]) -> Address {
+        permit_setter(key)
+    }
+}
+
+/// The account of the ECDSA public key of a permit setter, the blake2 256
+/// hash of the compressed key like for any Substrate ECDSA account
+pub(crate) fn permit_setter(setter_key: &[u8]) -> Address {
+    let mut account = [0u8; 32];
+    ink::env::hash_bytes::<Blake2x256>(setter_key, &mut account);
+    Address(AccountId::from(account))
+}
+
+/// Encodes the data point value the way messages return it, as the big endian
+/// bytes of the `int256`
+pub(crate) fn value_to_bytes32(value: api3_common::abi::Int) -> Bytes32 {
+    let mut v = [0u8; 32];
+    value.to_big_endian(&mut v);
+    v
+}
+
+/// Derives the beacon IDs of the airnodes and template IDs
+pub(crate) fn derive_beacon_ids(
+    airnodes: &[Vec<u8>],
+    template_ids: &[Bytes32],
+) -> Result<Vec<Bytes32>, Error> {
+    airnodes
+        .iter()
+        .zip(template_ids.iter())
+        .map(|(airnode, template_id)| api3_common::derive_beacon_id(airnode.clone(), *template_id))
+        .collect()
+}
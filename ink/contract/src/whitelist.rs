@@ -0,0 +1,568 @@
+use crate::types::{Address, InkWhitelistStatus};
+use crate::utils::{ink_fail, msg_sender, InkClock, PermitSignatureVerify, ReadWrite};
+use api3_common::abi::U256;
+use api3_common::{
+    indefinite_whitelist_status_key, whitelist_status_key, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, Bytes32, Error, ReaderTier, Storage,
+    SubscriptionPrice, Timestamp, TimestampChecker, Whitelist, WhitelistEnumerable,
+    WhitelistPermit, WhitelistRoles, WhitelistRolesWithManager, WhitelistStatus, Zero,
+};
+use ink::prelude::string::String;
+use ink::prelude::vec::Vec;
+use ink::primitives::AccountId;
+use ink::storage::traits::StorageKey;
+use ink::storage::Mapping;
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct WhitelistStatusMap<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, InkWhitelistStatus, K>>,
+}
+
+impl<'a, K: StorageKey> WhitelistStatusMap<'a, K> {
+    fn remove(&mut self, k: &Bytes32) {
+        self.map.get_mut().remove(k);
+    }
+}
+
+impl<'a, K: StorageKey> Storage<WhitelistStatus> for WhitelistStatusMap<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        self.map.get().get(k).map(WhitelistStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        self.map
+            .get_mut()
+            .insert(k, &InkWhitelistStatus::from(status));
+    }
+}
+
+/// The utility struct for handling ink! storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct IndefiniteWhitelistStatusMap<'a, K: StorageKey> {
+    map: ReadWrite<'a, Mapping<Bytes32, bool, K>>,
+}
+
+impl<'a, K: StorageKey> IndefiniteWhitelistStatusMap<'a, K> {
+    fn remove(&mut self, k: &Bytes32) {
+        self.map.get_mut().remove(k);
+    }
+}
+
+impl<'a, K: StorageKey> Storage<bool> for IndefiniteWhitelistStatusMap<'a, K> {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        self.map.get().get(k)
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        self.map.get_mut().insert(k, &status);
+    }
+}
+
+/// Index of the whitelist storage so that it can be enumerated: the users
+/// with a whitelist status for each service, and the setters with an
+/// indefinite whitelist status of each user keyed by `whitelist_status_key`,
+/// including the revoked ones so that sweeping can remove them.
+/// The users of a service are stored by position, removing a user moves the
+/// last user of the service to its position.
+#[ink::storage_item]
+#[derive(Debug, Default)]
+pub(crate) struct WhitelistIndex {
+    service_id_to_user_count: Mapping<Bytes32, u32>,
+    service_id_to_users: Mapping<(Bytes32, u32), AccountId>,
+    user_to_position: Mapping<Bytes32, u32>,
+    user_to_indefinite_whitelist_setters: Mapping<Bytes32, Vec<AccountId>>,
+}
+
+impl WhitelistIndex {
+    fn user_count(&self, service_id: &Bytes32) -> u32 {
+        self.service_id_to_user_count
+            .get(service_id)
+            .unwrap_or_default()
+    }
+
+    fn user_at(&self, service_id: &Bytes32, position: u32) -> Option<Address> {
+        self.service_id_to_users
+            .get((*service_id, position))
+            .map(Address)
+    }
+
+    fn insert_user(&mut self, service_id: &Bytes32, user: &Address) {
+        let key = whitelist_status_key(service_id, user.as_ref());
+        if self.user_to_position.contains(key) {
+            return;
+        }
+        let count = self.user_count(service_id);
+        self.service_id_to_users
+            .insert((*service_id, count), &user.0);
+        self.user_to_position.insert(key, &count);
+        self.service_id_to_user_count
+            .insert(service_id, &(count + 1));
+    }
+
+    fn remove_user(&mut self, service_id: &Bytes32, user: &Address) {
+        let key = whitelist_status_key(service_id, user.as_ref());
+        let position = match self.user_to_position.take(key) {
+            Some(p) => p,
+            None => return,
+        };
+        let last = self.user_count(service_id) - 1;
+        let last_user = self.service_id_to_users.take((*service_id, last));
+        if let Some(last_user) = last_user.filter(|_| position != last) {
+            self.service_id_to_users
+                .insert((*service_id, position), &last_user);
+            self.user_to_position.insert(
+                whitelist_status_key(service_id, last_user.as_ref()),
+                &position,
+            );
+        }
+        if last == 0 {
+            self.service_id_to_user_count.remove(service_id);
+        } else {
+            self.service_id_to_user_count.insert(service_id, &last);
+        }
+    }
+
+    fn insert_setter(&mut self, key: &Bytes32, setter: &Address) {
+        let mut setters = self
+            .user_to_indefinite_whitelist_setters
+            .get(key)
+            .unwrap_or_default();
+        setters.push(setter.0);
+        self.user_to_indefinite_whitelist_setters
+            .insert(key, &setters);
+    }
+}
+
+pub(crate) struct InkWhitelist<'a, Access, S: StorageKey, I: StorageKey> {
+    access: &'a Access,
+    service_id_to_user_to_whitelist_status: WhitelistStatusMap<'a, S>,
+    service_id_to_user_to_setter_to_indefinite_whitelist_status:
+        IndefiniteWhitelistStatusMap<'a, I>,
+    index: ReadWrite<'a, WhitelistIndex>,
+    /// The service, its published Merkle root and the proof of the user being checked
+    merkle_proof: Option<(Bytes32, Bytes32, Vec<Bytes32>)>,
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> InkWhitelist<'a, Access, S, I>
+where
+    Access: AccessControlRegistry<Address = Address>,
+{
+    pub fn requires_write(
+        access: &'a Access,
+        service_id_to_user_to_whitelist_status: &'a mut Mapping<Bytes32, InkWhitelistStatus, S>,
+        service_id_to_user_to_setter_to_indefinite_whitelist_status: &'a mut Mapping<
+            Bytes32,
+            bool,
+            I,
+        >,
+        index: &'a mut WhitelistIndex,
+    ) -> Self {
+        Self {
+            access,
+            service_id_to_user_to_whitelist_status: WhitelistStatusMap {
+                map: ReadWrite::Write(service_id_to_user_to_whitelist_status),
+            },
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusMap {
+                    map: ReadWrite::Write(
+                        service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                    ),
+                },
+            index: ReadWrite::Write(index),
+            merkle_proof: None,
+        }
+    }
+
+    pub fn read_only(
+        access: &'a Access,
+        service_id_to_user_to_whitelist_status: &'a Mapping<Bytes32, InkWhitelistStatus, S>,
+        service_id_to_user_to_setter_to_indefinite_whitelist_status: &'a Mapping<Bytes32, bool, I>,
+        index: &'a WhitelistIndex,
+    ) -> Self {
+        Self {
+            access,
+            service_id_to_user_to_whitelist_status: WhitelistStatusMap {
+                map: ReadWrite::ReadOnly(service_id_to_user_to_whitelist_status),
+            },
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusMap {
+                    map: ReadWrite::ReadOnly(
+                        service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                    ),
+                },
+            index: ReadWrite::ReadOnly(index),
+            merkle_proof: None,
+        }
+    }
+
+    /// Also treats the user as whitelisted for the service if `proof` proves
+    /// it is in the Merkle tree with `root`, the root published for the service.
+    /// Without a proof or a root this is a no-op.
+    pub fn with_merkle_proof(
+        mut self,
+        service_id: &Bytes32,
+        root: Option<Bytes32>,
+        proof: Option<Vec<Bytes32>>,
+    ) -> Self {
+        self.merkle_proof = root.zip(proof).map(|(root, p)| (*service_id, root, p));
+        self
+    }
+
+    /// Returns if the Merkle proof passed to `with_merkle_proof` whitelists the user
+    fn user_is_whitelisted_with_proof(&self, service_id: &Bytes32, user: &Address) -> bool {
+        self.merkle_proof.as_ref().is_some_and(|(id, root, proof)| {
+            id == service_id
+                && !root.is_zero()
+                && api3_common::verify_merkle_proof(
+                    root,
+                    &api3_common::merkle_leaf(user.as_ref()),
+                    proof,
+                )
+        })
+    }
+
+    /// Runs `f` and adds `user` to the users of the service if `f` created
+    /// its whitelist status
+    fn indexing_user<T>(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Address,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let key = whitelist_status_key(service_id, user.as_ref());
+        let existed = self
+            .service_id_to_user_to_whitelist_status
+            .get(&key)
+            .is_some();
+        let r = f(self);
+        if !existed
+            && self
+                .service_id_to_user_to_whitelist_status
+                .get(&key)
+                .is_some()
+        {
+            self.index.get_mut().insert_user(service_id, user);
+        }
+        r
+    }
+
+    /// Same as `Whitelist::extend_whitelist_expiration`, but returns the error
+    /// so that messages can return it instead of failing the call
+    pub fn try_extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Address,
+        expiration_timestamp: Timestamp,
+    ) -> Result<(), Error> {
+        self.indexing_user(service_id, user, |w| {
+            api3_common::extend_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                service_id,
+                user.as_ref(),
+                expiration_timestamp,
+            )
+        })
+    }
+
+    /// Extends the whitelist of `reader` for the data feed by `periods`
+    /// periods of `price`, the payment is checked by the caller
+    pub fn subscribe(
+        &mut self,
+        price: &SubscriptionPrice,
+        data_feed_id: &Bytes32,
+        reader: &Address,
+        periods: u64,
+    ) -> Result<Timestamp, Error> {
+        self.indexing_user(data_feed_id, reader, |w| {
+            api3_common::extend_subscription(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &InkClock::now(),
+                price,
+                data_feed_id,
+                reader.as_ref(),
+                periods,
+            )
+        })
+    }
+
+    /// Sets the whitelist expiration of the permit for `user` if it is signed
+    /// with `setter_key`, see `api3_common::use_whitelist_permit`
+    pub fn set_whitelist_expiration_with_permit<N: Storage<u64>>(
+        &mut self,
+        permit_nonces: &mut N,
+        permit: &WhitelistPermit,
+        user: &Address,
+        setter_key: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        api3_common::use_whitelist_permit::<_, _, PermitSignatureVerify, _>(
+            &*self,
+            permit_nonces,
+            &InkClock::now(),
+            permit,
+            setter_key,
+            signature,
+        )?;
+        self.indexing_user(&permit.service_id, user, |w| {
+            api3_common::set_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &permit.service_id,
+                user.as_ref(),
+                permit.expiration_timestamp,
+            )
+        });
+        Ok(())
+    }
+
+    /// Returns the tier the whitelist puts the user in, `None` if the user
+    /// is not whitelisted. Does not consider the unlimited reader role.
+    pub fn reader_tier(&self, service_id: &Bytes32, user: &Address) -> Option<ReaderTier> {
+        let now = InkClock::now().current_timestamp();
+        self.service_id_to_user_to_whitelist_status
+            .get(&whitelist_status_key(service_id, user.as_ref()))
+            .and_then(|status| api3_common::whitelist_tier(&status, now))
+            .or_else(|| {
+                self.user_is_whitelisted_with_proof(service_id, user)
+                    .then_some(ReaderTier::MerkleWhitelisted)
+            })
+    }
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> Whitelist for InkWhitelist<'a, Access, S, I>
+where
+    Access: AccessControlRegistry<Address = Address>,
+{
+    type Address = Address;
+
+    fn user_is_whitelisted(&self, service_id: &Bytes32, user: &Address) -> bool {
+        api3_common::user_is_whitelisted(
+            &self.service_id_to_user_to_whitelist_status,
+            &InkClock::now(),
+            service_id,
+            user.as_ref(),
+        ) || self.user_is_whitelisted_with_proof(service_id, user)
+    }
+
+    fn extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        if let Err(e) = self.try_extend_whitelist_expiration(service_id, user, expiration_timestamp)
+        {
+            ink_fail(e)
+        }
+    }
+
+    fn set_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        self.indexing_user(service_id, user, |w| {
+            api3_common::set_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                service_id,
+                user.as_ref(),
+                expiration_timestamp,
+            )
+        })
+    }
+
+    fn set_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        status: bool,
+    ) -> Result<U256, Error> {
+        let setter = msg_sender();
+        let indefinite_key =
+            indefinite_whitelist_status_key(service_id, user.as_ref(), setter.as_ref());
+        let indexed = self
+            .service_id_to_user_to_setter_to_indefinite_whitelist_status
+            .get(&indefinite_key)
+            .is_some();
+        let count = self.indexing_user(service_id, user, |w| {
+            api3_common::set_indefinite_whitelist_status(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &mut w.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                service_id,
+                user.as_ref(),
+                setter.as_ref(),
+                status,
+            )
+        })?;
+        if !indexed {
+            let key = whitelist_status_key(service_id, user.as_ref());
+            self.index.get_mut().insert_setter(&key, &setter);
+        }
+        Ok(count)
+    }
+
+    fn revoke_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        setter: &Self::Address,
+    ) -> Result<(bool, U256), Error> {
+        api3_common::revoke_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            user.as_ref(),
+            setter.as_ref(),
+        )
+    }
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> WhitelistEnumerable
+    for InkWhitelist<'a, Access, S, I>
+where
+    Access: AccessControlRegistry<Address = Address>,
+{
+    fn whitelisted_user_count(&self, service_id: &Bytes32) -> usize {
+        self.index.get().user_count(service_id) as usize
+    }
+
+    fn whitelisted_users_of(
+        &self,
+        service_id: &Bytes32,
+        from_index: usize,
+        limit: usize,
+    ) -> Vec<Address> {
+        let index = self.index.get();
+        let count = index.user_count(service_id) as usize;
+        (from_index.min(count)..count)
+            .take(limit)
+            .filter_map(|i| index.user_at(service_id, i as u32))
+            .collect()
+    }
+
+    fn indefinite_whitelist_setters_of(
+        &self,
+        service_id: &Bytes32,
+        user: &Address,
+    ) -> Vec<Address> {
+        self.index
+            .get()
+            .user_to_indefinite_whitelist_setters
+            .get(whitelist_status_key(service_id, user.as_ref()))
+            .unwrap_or_default()
+            .into_iter()
+            .map(Address)
+            .filter(|setter| {
+                self.service_id_to_user_to_setter_to_indefinite_whitelist_status
+                    .get(&indefinite_whitelist_status_key(
+                        service_id,
+                        user.as_ref(),
+                        setter.as_ref(),
+                    ))
+                    == Some(true)
+            })
+            .collect()
+    }
+
+    fn sweep_expired(&mut self, service_id: &Bytes32, from_index: usize, limit: usize) -> usize {
+        let now = InkClock::now().current_timestamp();
+        let mut index = from_index;
+        let mut swept = 0;
+        for _ in 0..limit {
+            let user = match u32::try_from(index)
+                .ok()
+                .and_then(|i| self.index.get().user_at(service_id, i))
+            {
+                Some(user) => user,
+                None => break,
+            };
+            let key = whitelist_status_key(service_id, user.as_ref());
+            let active = self
+                .service_id_to_user_to_whitelist_status
+                .get(&key)
+                .is_some_and(|s| s.is_active(now));
+            if active {
+                index += 1;
+                continue;
+            }
+
+            // without an indefinite whitelist all the setters revoked theirs
+            let setters = self
+                .index
+                .get_mut()
+                .user_to_indefinite_whitelist_setters
+                .take(key)
+                .unwrap_or_default();
+            for setter in setters {
+                self.service_id_to_user_to_setter_to_indefinite_whitelist_status
+                    .remove(&indefinite_whitelist_status_key(
+                        service_id,
+                        user.as_ref(),
+                        setter.as_ref(),
+                    ));
+            }
+            self.service_id_to_user_to_whitelist_status.remove(&key);
+            // the last user takes the place of the removed one, check it next
+            self.index.get_mut().remove_user(service_id, &user);
+            swept += 1;
+        }
+        swept
+    }
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> WhitelistRoles for InkWhitelist<'a, Access, S, I> where
+    Access: AccessControlRegistry<Address = Address>
+{
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> AccessControlRegistryAdminnedWithManager
+    for InkWhitelist<'a, Access, S, I>
+where
+    Access: AccessControlRegistry<Address = Address>,
+{
+    type Address = Address;
+
+    fn manager(&self) -> &Self::Address {
+        self.access.manager()
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.access.admin_role_description()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        self.access.admin_role_description_hash()
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        self.access.admin_role()
+    }
+}
+
+impl<'a, Access, S: StorageKey, I: StorageKey> WhitelistRolesWithManager
+    for InkWhitelist<'a, Access, S, I>
+where
+    Access: AccessControlRegistry<Address = Address>,
+{
+    fn has_whitelist_expiration_extender_role_or_is_manager(&self, account: &Address) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_extender_role(), account)
+    }
+
+    fn has_indefinite_whitelister_role_or_is_manager(&self, account: &Address) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.indefinite_whitelister_role(), account)
+    }
+
+    fn has_whitelist_expiration_setter_role_or_is_manager(&self, account: &Address) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_setter_role(), account)
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    multiversx_sc_meta::cli_main::<dapi_server_multiversx::AbiProvider>();
+}
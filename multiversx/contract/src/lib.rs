@@ -0,0 +1,835 @@
+#![no_std]
+
+extern crate alloc;
+
+mod types;
+mod utils;
+mod whitelist;
+
+multiversx_sc::imports!();
+
+pub use crate::types::{MxDataPoint, MxWhitelistStatus};
+
+use crate::types::Account;
+use crate::utils::{
+    bytes32, mx_ensure, mx_fail, mx_unwrap, mx_unwrap_dapi, to_vec, verify_signed_data,
+    DatapointStorage, FeedStatusStorage, IndefiniteWhitelistStatusStorage, MxAccessControlRegistry,
+    MxClock, NameHashStorage, SignatureVerify, WhitelistStatusStorage,
+};
+use crate::whitelist::MxWhitelist;
+use alloc::string::String as AllocString;
+use alloc::vec::Vec as AllocVec;
+use api3_common::abi::{Int, Token, Uint};
+use api3_common::{
+    indefinite_whitelist_status_key, keccak_packed, process_beacon_update_with_circuit_breaker,
+    update_dapi_with_signed_data_with_circuit_breaker, whitelist_status_key, AccessControlRegistry,
+    Bytes32, Error, FeedStatus, PauseState, StaticRole, Timestamp, Whitelist,
+    WhitelistRolesWithManager, Zero,
+};
+
+/// The MultiversX port of the `DapiServer`. Endpoints fail with the
+/// `ErrorPayload` of the `api3_common::Error` as JSON.
+#[multiversx_sc::contract]
+pub trait DapiServer {
+    /// The deployer is the manager and gets the default admin role.
+    /// Upgrades keep the manager.
+    #[init]
+    fn init(&self) {
+        if !self.manager_address().is_empty() {
+            return;
+        }
+        let manager = self.blockchain().get_caller();
+        self.manager_address().set(&manager);
+        let manager = Account::new(&manager);
+        let mut access = MxAccessControlRegistry::<Self::Api>::new(manager);
+        mx_unwrap::<Self::Api, _>(access.grant_role(
+            &MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE,
+            &manager,
+        ));
+    }
+
+    // ================== Access Control ====================
+    /// Returns the unlimited reader role and the name setter role
+    #[view]
+    fn roles(&self) -> MultiValue2<Bytes32, Bytes32> {
+        let access = self.access();
+        (
+            access.find_static_role(StaticRole::UnlimitedReaderRole),
+            access.find_static_role(StaticRole::NameSetterRole),
+        )
+            .into()
+    }
+
+    /// Renounce `role` to `who`, the caller must be `who`
+    #[endpoint]
+    fn renounce_role(&self, role: Bytes32, who: ManagedAddress) {
+        let mut access = self.access().with_sender(self.msg_sender());
+        mx_unwrap::<Self::Api, _>(access.renounce_role(&role, &Account::new(&who)));
+    }
+
+    /// Revoke `role` to `who`
+    #[endpoint]
+    fn revoke_role(&self, role: Bytes32, who: ManagedAddress) {
+        let mut access = self.access();
+        let role_admin = access
+            .get_role_admin(&role)
+            .unwrap_or(MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE);
+        mx_ensure::<Self::Api>(
+            access.only_role(&role_admin, &self.msg_sender()).is_ok(),
+            Error::NotAuthorized,
+        );
+        mx_unwrap::<Self::Api, _>(access.revoke_role(&role, &Account::new(&who)));
+    }
+
+    /// Grants `role` to `who`
+    #[endpoint]
+    fn grant_role(&self, role: Bytes32, who: ManagedAddress) {
+        let mut access = self.access();
+        mx_ensure::<Self::Api>(
+            access
+                .only_role(
+                    &MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE,
+                    &self.msg_sender(),
+                )
+                .is_ok(),
+            Error::NotAuthorized,
+        );
+        mx_unwrap::<Self::Api, _>(access.grant_role(&role, &Account::new(&who)));
+    }
+
+    /// Checks if `who` has `role`
+    #[view]
+    fn has_role(&self, role: Bytes32, who: ManagedAddress) -> bool {
+        self.access().has_role(&role, &Account::new(&who))
+    }
+
+    // ================== Manager Transfer ====================
+    /// Returns the manager of the contract
+    #[view]
+    fn manager(&self) -> ManagedAddress {
+        self.manager_address().get()
+    }
+
+    /// Returns the manager proposed with `propose_manager` that has not
+    /// accepted yet
+    #[view]
+    fn pending_manager(&self) -> Option<ManagedAddress> {
+        let pending_manager = self.pending_manager_address();
+        (!pending_manager.is_empty()).then(|| pending_manager.get())
+    }
+
+    /// Returns the manager before the last transfer
+    #[view]
+    fn previous_manager(&self) -> Option<ManagedAddress> {
+        let previous_manager = self.previous_manager_address();
+        (!previous_manager.is_empty()).then(|| previous_manager.get())
+    }
+
+    /// Proposes `new_manager` as the manager of the contract. Only the
+    /// manager can propose and the transfer is completed once `new_manager`
+    /// calls `accept_manager`.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_manager` The account proposed as the new manager
+    #[endpoint]
+    fn propose_manager(&self, new_manager: ManagedAddress) {
+        let new_manager = mx_unwrap::<Self::Api, _>(api3_common::propose_manager(
+            &Account::new(&self.manager()),
+            Account::new(&new_manager),
+            &self.msg_sender(),
+        ));
+        self.pending_manager_address()
+            .set(new_manager.to_managed::<Self::Api>());
+    }
+
+    /// Called by the pending manager to complete the transfer. The default
+    /// admin role is moved from the previous manager to the new one.
+    #[endpoint]
+    fn accept_manager(&self) {
+        let new_manager = mx_unwrap::<Self::Api, _>(api3_common::accept_manager(
+            self.pending_manager().map(|m| Account::new(&m)),
+            &self.msg_sender(),
+        ));
+        let previous_manager = Account::new(&self.manager());
+        self.manager_address()
+            .set(new_manager.to_managed::<Self::Api>());
+        self.previous_manager_address()
+            .set(previous_manager.to_managed::<Self::Api>());
+        self.pending_manager_address().clear();
+
+        let mut access = MxAccessControlRegistry::<Self::Api>::new(new_manager);
+        mx_unwrap::<Self::Api, _>(access.initialize_manager(&new_manager));
+        mx_unwrap::<Self::Api, _>(access.grant_role(
+            &MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE,
+            &new_manager,
+        ));
+        mx_unwrap::<Self::Api, _>(access.revoke_role(
+            &MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE,
+            &previous_manager,
+        ));
+    }
+
+    /// Moves `members` of the role with `description` derived from the previous
+    /// manager to the same role derived from the current manager. Only the
+    /// manager can migrate roles. Returns the migrated role.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` Description of the role, e.g. `Pauser`
+    /// * `members` Accounts to migrate, non-members of the previous role are skipped
+    #[endpoint]
+    fn migrate_role_members(
+        &self,
+        description: ManagedBuffer,
+        members: ManagedVec<ManagedAddress>,
+    ) -> Bytes32 {
+        let previous_manager = match self.previous_manager() {
+            Some(m) => Account::new(&m),
+            None => mx_fail::<Self::Api>(Error::PreviousManagerNotFound),
+        };
+        let description = match AllocString::from_utf8(to_vec(&description)) {
+            core::result::Result::Ok(d) => d,
+            core::result::Result::Err(_) => mx_fail::<Self::Api>(Error::InvalidData),
+        };
+        let members = members
+            .iter()
+            .map(|m| Account::new(&m))
+            .collect::<AllocVec<_>>();
+        mx_unwrap::<Self::Api, _>(self.access().migrate_role_members(
+            &previous_manager,
+            description,
+            &members,
+            &self.msg_sender(),
+        ))
+    }
+
+    // ================== Pause ====================
+    /// Returns the pauser role
+    #[view]
+    fn pauser_role(&self) -> Bytes32 {
+        self.access().find_static_role(StaticRole::PauserRole)
+    }
+
+    /// Returns the pause state of the contract. `0` is unpaused, `1` blocks
+    /// updates and `2` blocks both updates and reads
+    #[view]
+    fn pause_state(&self) -> u8 {
+        self.pause_state_storage().get()
+    }
+
+    /// Returns the pause state of the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    #[view]
+    fn data_feed_pause_state(&self, data_feed_id: Bytes32) -> u8 {
+        self.data_feed_pause_state_storage(&data_feed_id).get()
+    }
+
+    /// Sets the pause state of the contract if the caller is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    #[endpoint]
+    fn set_pause_state(&self, state: u8) {
+        let state = mx_unwrap::<Self::Api, _>(PauseState::try_from(state));
+        self.ensure_can_pause();
+        self.pause_state_storage().set(u8::from(state));
+    }
+
+    /// Sets the pause state of the data feed if the caller is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    #[endpoint]
+    fn set_data_feed_pause_state(&self, data_feed_id: Bytes32, state: u8) {
+        let state = mx_unwrap::<Self::Api, _>(PauseState::try_from(state));
+        self.ensure_can_pause();
+        self.data_feed_pause_state_storage(&data_feed_id)
+            .set(u8::from(state));
+    }
+
+    // ================== Datapoint ====================
+    /// Updates a Beacon using data signed by the respective Airnode,
+    /// without requiring a request or subscription
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode ed25519 public key
+    /// * `template_id` Template ID
+    /// * `timestamp` Timestamp used in the signature
+    /// * `data` Response data (an `int256` encoded in contract ABI)
+    /// * `signature` Template ID, a timestamp and the response data signed by the Airnode key
+    #[endpoint]
+    fn update_beacon_with_signed_data(
+        &self,
+        airnode: Bytes32,
+        template_id: Bytes32,
+        timestamp: Bytes32,
+        data: ManagedBuffer,
+        signature: ManagedBuffer,
+    ) {
+        let data = to_vec(&data);
+        let beacon_id =
+            mx_unwrap::<Self::Api, _>(api3_common::derive_beacon_id(airnode.to_vec(), template_id));
+        self.ensure_updates_allowed(&beacon_id);
+        verify_signed_data::<Self::Api>(
+            &airnode,
+            &template_id,
+            &timestamp,
+            &data,
+            &to_vec(&signature),
+        );
+
+        mx_unwrap::<Self::Api, _>(process_beacon_update_with_circuit_breaker(
+            &mut DatapointStorage::<Self::Api>::new(),
+            &mut FeedStatusStorage::<Self::Api>::new(),
+            self.circuit_breaker_threshold(),
+            beacon_id,
+            Uint::from_big_endian(&timestamp),
+            data,
+        ));
+    }
+
+    /// Updates the dAPI that is specified by the beacon IDs
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    #[endpoint]
+    fn update_dapi_with_beacons(&self, beacon_ids: ManagedVec<ManagedByteArray<32>>) -> Bytes32 {
+        let beacon_ids = beacon_ids
+            .iter()
+            .map(|b| bytes32(&b))
+            .collect::<AllocVec<_>>();
+        self.ensure_dapi_updates_allowed(&beacon_ids);
+        mx_unwrap_dapi::<Self::Api>(api3_common::update_dapi_with_beacons(
+            &mut DatapointStorage::<Self::Api>::new(),
+            &beacon_ids,
+        ))
+    }
+
+    /// Updates a dAPI using data signed by the respective Airnodes
+    /// without requiring a request or subscription. The beacons for which the
+    /// signature is omitted will be read from the storage. Fails if a Beacon is
+    /// paused by the circuit breaker or its signed data exceeds the threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `airnodes` Airnode ed25519 public keys
+    /// * `template_ids` Template IDs
+    /// * `timestamps` Timestamps used in the signatures
+    /// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+    /// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode key per Beacon, empty to read the Beacon from storage
+    #[endpoint]
+    fn update_dapi_with_signed_data(
+        &self,
+        airnodes: ManagedVec<ManagedByteArray<32>>,
+        template_ids: ManagedVec<ManagedByteArray<32>>,
+        timestamps: ManagedVec<ManagedByteArray<32>>,
+        data: ManagedVec<ManagedBuffer>,
+        signatures: ManagedVec<ManagedBuffer>,
+    ) -> Bytes32 {
+        let airnodes = airnodes
+            .iter()
+            .map(|a| bytes32(&a).to_vec())
+            .collect::<AllocVec<_>>();
+        let template_ids = template_ids
+            .iter()
+            .map(|t| bytes32(&t))
+            .collect::<AllocVec<_>>();
+        let timestamps = timestamps
+            .iter()
+            .map(|t| bytes32(&t))
+            .collect::<AllocVec<_>>();
+        let data = data.iter().map(|d| to_vec(&d)).collect::<AllocVec<_>>();
+        let signatures = signatures
+            .iter()
+            .map(|s| to_vec(&s))
+            .collect::<AllocVec<_>>();
+        mx_ensure::<Self::Api>(
+            airnodes.len() == template_ids.len(),
+            Error::ParameterLengthMismatch,
+        );
+
+        let beacon_ids = airnodes
+            .iter()
+            .zip(template_ids.iter())
+            .map(|(airnode, template_id)| {
+                mx_unwrap::<Self::Api, _>(api3_common::derive_beacon_id(
+                    airnode.clone(),
+                    *template_id,
+                ))
+            })
+            .collect::<AllocVec<_>>();
+        self.ensure_dapi_updates_allowed(&beacon_ids);
+
+        mx_unwrap_dapi::<Self::Api>(update_dapi_with_signed_data_with_circuit_breaker::<
+            _,
+            _,
+            SignatureVerify<Self::Api>,
+            _,
+        >(
+            &mut DatapointStorage::<Self::Api>::new(),
+            &FeedStatusStorage::<Self::Api>::new(),
+            self.circuit_breaker_threshold(),
+            &MxClock::now::<Self::Api>(),
+            airnodes,
+            template_ids,
+            timestamps,
+            data,
+            signatures,
+        ))
+    }
+
+    // ================== Circuit Breaker ====================
+    /// Sets the maximum deviation a Beacon update can have from the current value
+    /// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` The deviation threshold
+    #[endpoint]
+    fn set_circuit_breaker_threshold(&self, threshold: u32) {
+        mx_ensure::<Self::Api>(
+            self.access()
+                .only_role(
+                    &MxAccessControlRegistry::<Self::Api>::DEFAULT_ADMIN_ROLE,
+                    &self.msg_sender(),
+                )
+                .is_ok(),
+            Error::NotAuthorized,
+        );
+        self.circuit_breaker_threshold_storage().set(threshold);
+    }
+
+    /// Returns the circuit breaker deviation threshold
+    #[view]
+    fn circuit_breaker_threshold(&self) -> u32 {
+        self.circuit_breaker_threshold_storage().get()
+    }
+
+    /// Returns the pending update of the data feed if it is paused by the circuit breaker
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    #[view]
+    fn data_feed_pending_update(&self, data_feed_id: Bytes32) -> Option<MxDataPoint> {
+        match api3_common::Storage::get(&FeedStatusStorage::<Self::Api>::new(), &data_feed_id) {
+            Some(FeedStatus::Paused(d)) => Some(d.into()),
+            _ => None,
+        }
+    }
+
+    /// Releases the data feed paused by the circuit breaker, the pending
+    /// update is stored as the data feed value
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    #[endpoint]
+    fn release_data_feed(&self, data_feed_id: Bytes32) {
+        mx_unwrap::<Self::Api, _>(api3_common::release_data_feed(
+            &mut DatapointStorage::<Self::Api>::new(),
+            &mut FeedStatusStorage::<Self::Api>::new(),
+            data_feed_id,
+            &self.msg_sender(),
+            &self.access(),
+        ));
+    }
+
+    /// Sets the data point ID the name points to.
+    /// While a data point ID refers to a specific Beacon or dAPI, names
+    /// provide a more abstract interface for convenience. This means a name
+    /// that was pointing at a Beacon can be pointed to a dAPI, then another
+    /// dAPI, etc.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` Human-readable name
+    /// * `datapoint_id` Data point ID the name will point to
+    #[endpoint]
+    fn set_name(&self, name: Bytes32, datapoint_id: Bytes32) {
+        mx_unwrap::<Self::Api, _>(api3_common::set_name(
+            name,
+            datapoint_id,
+            &self.msg_sender(),
+            &self.access(),
+            &mut NameHashStorage::<Self::Api>::new(),
+        ));
+    }
+
+    /// Returns the data point ID the name is set to
+    /// `name` Name
+    #[view]
+    fn name_to_data_point_id(&self, name: Bytes32) -> Option<Bytes32> {
+        let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+        api3_common::Storage::get(&NameHashStorage::<Self::Api>::new(), &name_hash)
+    }
+
+    /// Derives the beacon set ID from the beacon IDs
+    /// Notice that `encode()` is used over `encode_packed()`
+    /// Returns the derived dapi id
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    #[view]
+    fn derive_beacon_set_id(&self, beacon_ids: ManagedVec<ManagedByteArray<32>>) -> Bytes32 {
+        let beacon_ids = beacon_ids
+            .iter()
+            .map(|b| bytes32(&b))
+            .collect::<AllocVec<_>>();
+        api3_common::derive_dapi_id(&beacon_ids)
+    }
+
+    /// Derives the beacon id based on the `airnode` and `templated_id`
+    /// Returns the beacon id
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode ed25519 public key
+    /// * `template_id` Template ID
+    #[view]
+    fn derive_beacon_id(&self, airnode: Bytes32, template_id: Bytes32) -> Bytes32 {
+        mx_unwrap::<Self::Api, _>(api3_common::derive_beacon_id(airnode.to_vec(), template_id))
+    }
+
+    /// Reads the data point with ID, the caller is the reader
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID
+    #[endpoint]
+    fn read_with_data_point_id(&self, data_point_id: Bytes32) -> MultiValue2<Bytes32, u32> {
+        self.ensure_reads_allowed(&data_point_id);
+        let reader = self.msg_sender();
+        let access = self.access();
+        let whitelist = MxWhitelist::<Self::Api, _>::new(&access, reader);
+        let (value, timestamp) = mx_unwrap::<Self::Api, _>(api3_common::read_with_data_point_id(
+            &data_point_id,
+            &reader,
+            &DatapointStorage::<Self::Api>::new(),
+            &FeedStatusStorage::<Self::Api>::new(),
+            &access,
+            &whitelist,
+        ));
+        self.value_and_timestamp(value, timestamp)
+    }
+
+    /// Reads the data point with name, the caller is the reader
+    /// The read data point may belong to a Beacon or dAPI. The reader
+    /// must be whitelisted for the hash of the data point name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` Data point name
+    #[endpoint]
+    fn read_with_name(&self, name: Bytes32) -> MultiValue2<Bytes32, u32> {
+        let data_point_id = self.name_to_data_point_id(name).unwrap_or_default();
+        self.ensure_reads_allowed(&data_point_id);
+        let reader = self.msg_sender();
+        let access = self.access();
+        let whitelist = MxWhitelist::<Self::Api, _>::new(&access, reader);
+        let (value, timestamp) = mx_unwrap::<Self::Api, _>(api3_common::read_with_name(
+            name,
+            &reader,
+            &DatapointStorage::<Self::Api>::new(),
+            &NameHashStorage::<Self::Api>::new(),
+            &FeedStatusStorage::<Self::Api>::new(),
+            &access,
+            &whitelist,
+        ));
+        self.value_and_timestamp(value, timestamp)
+    }
+
+    /// Returns if a reader can read the data point
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID (or data point name hash)
+    /// * `reader` Reader address
+    #[view]
+    fn reader_can_read_data_point(&self, data_point_id: Bytes32, reader: ManagedAddress) -> bool {
+        let reader = Account::new(&reader);
+        let access = self.access();
+        let whitelist = MxWhitelist::<Self::Api, _>::new(&access, reader);
+        api3_common::reader_can_read_data_point(&data_point_id, &reader, &access, &whitelist)
+    }
+
+    // ================== Whitelist ====================
+    /// Returns if `setter` has indefinitely whitelisted the reader for the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed id
+    /// * `reader` Reader address
+    /// * `setter` Setter address
+    #[view]
+    fn data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+        &self,
+        data_feed_id: Bytes32,
+        reader: ManagedAddress,
+        setter: ManagedAddress,
+    ) -> bool {
+        let key = indefinite_whitelist_status_key(
+            &data_feed_id,
+            Account::new(&reader).as_ref(),
+            Account::new(&setter).as_ref(),
+        );
+        api3_common::Storage::get(&IndefiniteWhitelistStatusStorage::<Self::Api>::new(), &key)
+            .unwrap_or(false)
+    }
+
+    /// Returns the detailed whitelist status of the reader for the data feed,
+    /// the expiration timestamp and the indefinite whitelist count
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed id
+    /// * `reader` Reader address
+    #[view]
+    fn data_feed_id_to_whitelist_status(
+        &self,
+        data_feed_id: Bytes32,
+        reader: ManagedAddress,
+    ) -> Option<MxWhitelistStatus> {
+        let key = whitelist_status_key(&data_feed_id, Account::new(&reader).as_ref());
+        api3_common::Storage::get(&WhitelistStatusStorage::<Self::Api>::new(), &key)
+            .map(MxWhitelistStatus::from)
+    }
+
+    /// Extends the expiration of the temporary whitelist of `user` to
+    /// be able to use the service with `service_id` if the caller has the
+    /// whitelist expiration extender role
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    #[endpoint]
+    fn extend_whitelist_expiration(
+        &self,
+        service_id: Bytes32,
+        user: ManagedAddress,
+        expiration_timestamp: u64,
+    ) {
+        let user = Account::new(&user);
+        let sender = self.msg_sender();
+        let access = self.access();
+        let mut whitelist = MxWhitelist::<Self::Api, _>::new(&access, sender);
+        mx_ensure::<Self::Api>(
+            whitelist.has_whitelist_expiration_extender_role_or_is_manager(&sender),
+            Error::AccessDenied,
+        );
+        self.ensure_whitelist_ids(&service_id, &user);
+        mx_unwrap::<Self::Api, _>(whitelist.try_extend_whitelist_expiration(
+            &service_id,
+            &user,
+            Timestamp::from_secs(expiration_timestamp),
+        ));
+    }
+
+    /// Sets the expiration of the temporary whitelist of `user` to be
+    /// able to use the service with `service_id` if the caller has the
+    /// whitelist expiration setter role
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    #[endpoint]
+    fn set_whitelist_expiration(
+        &self,
+        service_id: Bytes32,
+        user: ManagedAddress,
+        expiration_timestamp: u64,
+    ) {
+        let user = Account::new(&user);
+        let sender = self.msg_sender();
+        let access = self.access();
+        let mut whitelist = MxWhitelist::<Self::Api, _>::new(&access, sender);
+        mx_ensure::<Self::Api>(
+            whitelist.has_whitelist_expiration_setter_role_or_is_manager(&sender),
+            Error::AccessDenied,
+        );
+        self.ensure_whitelist_ids(&service_id, &user);
+        whitelist.set_whitelist_expiration(
+            &service_id,
+            &user,
+            Timestamp::from_secs(expiration_timestamp),
+        );
+    }
+
+    /// Sets the indefinite whitelist status of `user` to be able to
+    /// use the service with `service_id` if the caller has the indefinite whitelister role.
+    /// Returns the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `status` Indefinite whitelist status
+    #[endpoint]
+    fn set_indefinite_whitelist_status(
+        &self,
+        service_id: Bytes32,
+        user: ManagedAddress,
+        status: bool,
+    ) -> Bytes32 {
+        let user = Account::new(&user);
+        let sender = self.msg_sender();
+        let access = self.access();
+        let mut whitelist = MxWhitelist::<Self::Api, _>::new(&access, sender);
+        mx_ensure::<Self::Api>(
+            whitelist.has_indefinite_whitelister_role_or_is_manager(&sender),
+            Error::AccessDenied,
+        );
+        self.ensure_whitelist_ids(&service_id, &user);
+        let count = mx_unwrap::<Self::Api, _>(whitelist.set_indefinite_whitelist_status(
+            &service_id,
+            &user,
+            status,
+        ));
+        Bytes32::from(&count)
+    }
+
+    /// Revokes the indefinite whitelist status granted to the user for
+    /// the service by a specific account. Anyone can revoke the status set by
+    /// an account that no longer has the indefinite whitelister role.
+    /// Returns if the status was revoked and the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `setter` Setter address
+    #[endpoint]
+    fn revoke_indefinite_whitelist_status(
+        &self,
+        service_id: Bytes32,
+        user: ManagedAddress,
+        setter: ManagedAddress,
+    ) -> MultiValue2<bool, Bytes32> {
+        let setter = Account::new(&setter);
+        let access = self.access();
+        let mut whitelist = MxWhitelist::<Self::Api, _>::new(&access, setter);
+        mx_ensure::<Self::Api>(
+            !whitelist.has_indefinite_whitelister_role_or_is_manager(&setter),
+            Error::SetterCanSetIndefiniteStatus,
+        );
+        let (revoked, count) =
+            mx_unwrap::<Self::Api, _>(whitelist.revoke_indefinite_whitelist_status(
+                &service_id,
+                &Account::new(&user),
+                &setter,
+            ));
+        (revoked, Bytes32::from(&count)).into()
+    }
+
+    /// Returns the whitelist expiration extender role
+    #[view]
+    fn whitelist_expiration_extender_role(&self) -> Bytes32 {
+        let access = self.access();
+        MxWhitelist::<Self::Api, _>::new(&access, Account::default())
+            .whitelist_expiration_extender_role()
+    }
+
+    /// Returns the whitelist expiration setter role
+    #[view]
+    fn whitelist_expiration_setter_role(&self) -> Bytes32 {
+        let access = self.access();
+        MxWhitelist::<Self::Api, _>::new(&access, Account::default())
+            .whitelist_expiration_setter_role()
+    }
+
+    /// Returns the indefinite whitelister role
+    #[view]
+    fn indefinite_whitelister_role(&self) -> Bytes32 {
+        let access = self.access();
+        MxWhitelist::<Self::Api, _>::new(&access, Account::default()).indefinite_whitelister_role()
+    }
+
+    // ================== Storage ====================
+    #[storage_mapper("manager")]
+    fn manager_address(&self) -> SingleValueMapper<ManagedAddress>;
+
+    #[storage_mapper("pendingManager")]
+    fn pending_manager_address(&self) -> SingleValueMapper<ManagedAddress>;
+
+    #[storage_mapper("previousManager")]
+    fn previous_manager_address(&self) -> SingleValueMapper<ManagedAddress>;
+
+    #[storage_mapper("pauseState")]
+    fn pause_state_storage(&self) -> SingleValueMapper<u8>;
+
+    #[storage_mapper("dataFeedPauseState")]
+    fn data_feed_pause_state_storage(&self, data_feed_id: &Bytes32) -> SingleValueMapper<u8>;
+
+    #[storage_mapper("circuitBreakerThreshold")]
+    fn circuit_breaker_threshold_storage(&self) -> SingleValueMapper<u32>;
+
+    // ================== Helpers ====================
+    fn msg_sender(&self) -> Account {
+        Account::new(&self.blockchain().get_caller())
+    }
+
+    fn access(&self) -> MxAccessControlRegistry<Self::Api> {
+        MxAccessControlRegistry::new(Account::new(&self.manager()))
+    }
+
+    fn value_and_timestamp(&self, value: Int, timestamp: Timestamp) -> MultiValue2<Bytes32, u32> {
+        let mut v = [0u8; 32];
+        value.to_big_endian(&mut v);
+        (v, timestamp.as_secs_u32()).into()
+    }
+
+    fn ensure_whitelist_ids(&self, service_id: &Bytes32, user: &Account) {
+        mx_ensure::<Self::Api>(!service_id.is_zero(), Error::ServiceIdZero);
+        mx_ensure::<Self::Api>(!user.is_zero(), Error::UserAddressZero);
+    }
+
+    fn ensure_can_pause(&self) {
+        mx_unwrap::<Self::Api, _>(api3_common::ensure_can_pause(
+            &self.access(),
+            &self.msg_sender(),
+        ));
+    }
+
+    fn pause_states(&self, data_feed_id: &Bytes32) -> (PauseState, PauseState) {
+        (
+            mx_unwrap::<Self::Api, _>(PauseState::try_from(self.pause_state())),
+            mx_unwrap::<Self::Api, _>(PauseState::try_from(
+                self.data_feed_pause_state(*data_feed_id),
+            )),
+        )
+    }
+
+    fn ensure_updates_allowed(&self, data_feed_id: &Bytes32) {
+        let (global, data_feed) = self.pause_states(data_feed_id);
+        mx_unwrap::<Self::Api, _>(api3_common::ensure_updates_allowed(global, data_feed));
+    }
+
+    /// Checks updates are allowed for the dAPI and each of its Beacons
+    fn ensure_dapi_updates_allowed(&self, beacon_ids: &[Bytes32]) {
+        self.ensure_updates_allowed(&api3_common::derive_dapi_id(beacon_ids));
+        for beacon_id in beacon_ids {
+            self.ensure_updates_allowed(beacon_id);
+        }
+    }
+
+    fn ensure_reads_allowed(&self, data_feed_id: &Bytes32) {
+        let (global, data_feed) = self.pause_states(data_feed_id);
+        mx_unwrap::<Self::Api, _>(api3_common::ensure_reads_allowed(global, data_feed));
+    }
+}
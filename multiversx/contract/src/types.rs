@@ -0,0 +1,112 @@
+use api3_common::abi::{Int, U256};
+use api3_common::{Bytes32, DataPoint, FeedStatus, Timestamp, WhitelistStatus, Zero};
+use multiversx_sc::api::ManagedTypeApi;
+use multiversx_sc::types::ManagedAddress;
+
+multiversx_sc::derive_imports!();
+
+/// The 32 bytes of a MultiversX address as the address type of `api3_common`
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Account(pub [u8; 32]);
+
+impl Account {
+    pub fn new<A: ManagedTypeApi>(address: &ManagedAddress<A>) -> Self {
+        Self(address.to_byte_array())
+    }
+
+    pub fn to_managed<A: ManagedTypeApi>(&self) -> ManagedAddress<A> {
+        ManagedAddress::new_from_bytes(&self.0)
+    }
+}
+
+impl Zero for Account {
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|b| *b == 0)
+    }
+}
+
+impl AsRef<[u8]> for Account {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+#[derive(
+    TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone, Default, PartialEq, Debug,
+)]
+pub struct MxDataPoint {
+    pub value: Bytes32,
+    pub timestamp: u32,
+}
+
+impl From<MxDataPoint> for DataPoint {
+    fn from(t: MxDataPoint) -> Self {
+        DataPoint::new(
+            Int::from_big_endian(&t.value),
+            Timestamp::from_secs(u64::from(t.timestamp)),
+        )
+    }
+}
+
+impl From<DataPoint> for MxDataPoint {
+    fn from(t: DataPoint) -> Self {
+        let mut value = [0u8; 32];
+        t.value.to_big_endian(&mut value);
+        MxDataPoint {
+            value,
+            timestamp: t.timestamp.as_secs_u32(),
+        }
+    }
+}
+
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone)]
+pub enum MxFeedStatus {
+    Active,
+    Paused(MxDataPoint),
+}
+
+impl From<MxFeedStatus> for FeedStatus {
+    fn from(t: MxFeedStatus) -> Self {
+        match t {
+            MxFeedStatus::Active => FeedStatus::Active,
+            MxFeedStatus::Paused(d) => FeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+impl From<FeedStatus> for MxFeedStatus {
+    fn from(t: FeedStatus) -> Self {
+        match t {
+            FeedStatus::Active => MxFeedStatus::Active,
+            FeedStatus::Paused(d) => MxFeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+#[derive(
+    TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone, Default, PartialEq, Debug,
+)]
+pub struct MxWhitelistStatus {
+    /// in seconds
+    pub expiration_timestamp: u64,
+    /// originally uint192, stored big endian
+    pub indefinite_whitelist_count: Bytes32,
+}
+
+impl From<MxWhitelistStatus> for WhitelistStatus {
+    fn from(s: MxWhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: Timestamp::from_secs(s.expiration_timestamp),
+            indefinite_whitelist_count: U256::from_big_endian(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+impl From<WhitelistStatus> for MxWhitelistStatus {
+    fn from(s: WhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: s.expiration_timestamp.as_secs(),
+            indefinite_whitelist_count: Bytes32::from(&s.indefinite_whitelist_count),
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use crate::types::{Account, MxDataPoint, MxFeedStatus, MxWhitelistStatus};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use api3_common::abi::Token;
+use api3_common::{
+    keccak_packed, AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32,
+    DapiUpdateError, DataPoint, Error, ErrorPayload, FeedStatus, RoleDeriver, SignatureManger,
+    Storage, Timestamp, TimestampChecker, WhitelistStatus,
+};
+use core::marker::PhantomData;
+use multiversx_sc::api::{ErrorApi, ErrorApiImpl, VMApi};
+use multiversx_sc::codec::{TopDecode, TopEncode};
+use multiversx_sc::contract_base::{BlockchainWrapper, CryptoWrapper};
+use multiversx_sc::storage::mappers::{SingleValueMapper, StorageMapper};
+use multiversx_sc::storage::StorageKey;
+use multiversx_sc::types::{ManagedBuffer, ManagedByteArray};
+
+pub(crate) const DATA_POINTS_KEY: &[u8] = b"dataPoints";
+pub(crate) const NAME_HASHES_KEY: &[u8] = b"nameHashToDataPointId";
+pub(crate) const FEED_STATUS_KEY: &[u8] = b"dataFeedStatus";
+pub(crate) const WHITELIST_STATUS_KEY: &[u8] = b"whitelistStatus";
+pub(crate) const INDEFINITE_WHITELIST_STATUS_KEY: &[u8] = b"indefiniteWhitelistStatus";
+pub(crate) const ROLE_MEMBERSHIP_KEY: &[u8] = b"roleMembership";
+pub(crate) const ROLE_ADMIN_KEY: &[u8] = b"roleAdmin";
+
+/// Fails the call with the `ErrorPayload` of the error as JSON, so that
+/// clients can read the error back with `ErrorPayload::parse`
+pub(crate) fn mx_fail<A: ErrorApi>(e: Error) -> ! {
+    A::error_api_impl().signal_error(ErrorPayload::from(&e).to_json().as_bytes())
+}
+
+/// Unwraps the result of `api3_common`, failing the call with the error
+pub(crate) fn mx_unwrap<A: ErrorApi, T>(r: Result<T, Error>) -> T {
+    match r {
+        Ok(t) => t,
+        Err(e) => mx_fail::<A>(e),
+    }
+}
+
+/// Same as `mx_unwrap` for dAPI updates, the payload has the `beacon_index`
+/// of the Beacon that failed the update
+pub(crate) fn mx_unwrap_dapi<A: ErrorApi>(r: Result<Bytes32, DapiUpdateError>) -> Bytes32 {
+    match r {
+        Ok(t) => t,
+        Err(e) => A::error_api_impl().signal_error(ErrorPayload::from(&e).to_json().as_bytes()),
+    }
+}
+
+/// Fails the call with `e` unless `condition` holds, the `ensure!` of endpoints
+pub(crate) fn mx_ensure<A: ErrorApi>(condition: bool, e: Error) {
+    if !condition {
+        mx_fail::<A>(e)
+    }
+}
+
+pub(crate) fn bytes32<A: VMApi>(b: &ManagedByteArray<A, 32>) -> Bytes32 {
+    b.to_byte_array()
+}
+
+pub(crate) fn to_vec<A: VMApi>(b: &ManagedBuffer<A>) -> Vec<u8> {
+    let mut bytes = vec![0u8; b.len()];
+    if b.load_slice(0, &mut bytes).is_err() {
+        mx_fail::<A>(Error::InvalidData)
+    }
+    bytes
+}
+
+/// The storage entry of `k` under `prefix`, the same as a `#[storage_mapper]`
+/// with a `k` argument
+fn mapper<A: VMApi, V: TopEncode + TopDecode>(
+    prefix: &[u8],
+    k: &Bytes32,
+) -> SingleValueMapper<A, V> {
+    let mut key = StorageKey::new(prefix);
+    key.append_item(k);
+    SingleValueMapper::new(key)
+}
+
+/// The utility struct for handling MultiversX storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct DatapointStorage<A: VMApi> {
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> DatapointStorage<A> {
+    pub fn new() -> Self {
+        Self { _api: PhantomData }
+    }
+}
+
+impl<A: VMApi> Storage<DataPoint> for DatapointStorage<A> {
+    fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        let m = mapper::<A, MxDataPoint>(DATA_POINTS_KEY, k);
+        if m.is_empty() {
+            Some(DataPoint::default())
+        } else {
+            Some(m.get().into())
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
+        mapper::<A, MxDataPoint>(DATA_POINTS_KEY, &k).set(MxDataPoint::from(datapoint));
+    }
+}
+
+/// The utility struct for handling MultiversX storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct NameHashStorage<A: VMApi> {
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> NameHashStorage<A> {
+    pub fn new() -> Self {
+        Self { _api: PhantomData }
+    }
+}
+
+impl<A: VMApi> Storage<Bytes32> for NameHashStorage<A> {
+    fn get(&self, k: &Bytes32) -> Option<Bytes32> {
+        let m = mapper::<A, Bytes32>(NAME_HASHES_KEY, k);
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.get())
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, data: Bytes32) {
+        mapper::<A, Bytes32>(NAME_HASHES_KEY, &k).set(data);
+    }
+}
+
+/// The utility struct for handling MultiversX storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusStorage<A: VMApi> {
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> FeedStatusStorage<A> {
+    pub fn new() -> Self {
+        Self { _api: PhantomData }
+    }
+}
+
+impl<A: VMApi> Storage<FeedStatus> for FeedStatusStorage<A> {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        let m = mapper::<A, MxFeedStatus>(FEED_STATUS_KEY, k);
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.get().into())
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        mapper::<A, MxFeedStatus>(FEED_STATUS_KEY, &k).set(MxFeedStatus::from(status));
+    }
+}
+
+/// The utility struct for handling MultiversX storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct WhitelistStatusStorage<A: VMApi> {
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> WhitelistStatusStorage<A> {
+    pub fn new() -> Self {
+        Self { _api: PhantomData }
+    }
+}
+
+impl<A: VMApi> Storage<WhitelistStatus> for WhitelistStatusStorage<A> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        let m = mapper::<A, MxWhitelistStatus>(WHITELIST_STATUS_KEY, k);
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.get().into())
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        mapper::<A, MxWhitelistStatus>(WHITELIST_STATUS_KEY, &k)
+            .set(MxWhitelistStatus::from(status));
+    }
+}
+
+/// The utility struct for handling MultiversX storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct IndefiniteWhitelistStatusStorage<A: VMApi> {
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> IndefiniteWhitelistStatusStorage<A> {
+    pub fn new() -> Self {
+        Self { _api: PhantomData }
+    }
+}
+
+impl<A: VMApi> Storage<bool> for IndefiniteWhitelistStatusStorage<A> {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        let m = mapper::<A, bool>(INDEFINITE_WHITELIST_STATUS_KEY, k);
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.get())
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        mapper::<A, bool>(INDEFINITE_WHITELIST_STATUS_KEY, &k).set(status);
+    }
+}
+
+/// Signature verification for MultiversX with the ed25519 verification of
+/// the VM. The VM fails the call on an invalid signature instead of returning
+/// `false`, so `verify` only returns once the signature is valid.
+pub(crate) struct SignatureVerify<A: VMApi> {
+    _phantom: PhantomData<A>,
+}
+
+impl<A: VMApi> SignatureManger for SignatureVerify<A> {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        if key.len() != 32 || signature.len() != 64 {
+            return false;
+        }
+        CryptoWrapper::<A>::new().verify_ed25519(
+            &ManagedBuffer::new_from_bytes(key),
+            &ManagedBuffer::new_from_bytes(message),
+            &ManagedBuffer::new_from_bytes(signature),
+        );
+        true
+    }
+}
+
+/// Verifies the signature of the Airnode over the template ID, the timestamp
+/// and the data with the VM, failing the call if it is not valid
+pub(crate) fn verify_signed_data<A: VMApi>(
+    airnode: &[u8],
+    template_id: &Bytes32,
+    timestamp: &Bytes32,
+    data: &[u8],
+    signature: &[u8],
+) {
+    let message = keccak_packed(&[
+        Token::FixedBytes(template_id.to_vec()),
+        Token::Uint(api3_common::abi::Uint::from_big_endian(timestamp)),
+        Token::Bytes(data.to_vec()),
+    ]);
+    mx_ensure::<A>(
+        SignatureVerify::<A>::verify(airnode, &message, signature),
+        Error::InvalidSignature,
+    );
+}
+
+pub(crate) struct MxClock {
+    current_timestamp: Timestamp,
+}
+
+impl MxClock {
+    pub fn new(current_timestamp: Timestamp) -> Self {
+        Self { current_timestamp }
+    }
+
+    /// The clock at the current block, MultiversX reports the block timestamp in seconds
+    pub fn now<A: VMApi>() -> Self {
+        Self::new(Timestamp::from_secs(
+            BlockchainWrapper::<A>::new().get_block_timestamp(),
+        ))
+    }
+}
+
+impl TimestampChecker for MxClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
+pub(crate) struct MxAccessControlRegistry<A: VMApi> {
+    manager: Account,
+    admin_role_description: String,
+    sender: Option<Account>,
+    _api: PhantomData<A>,
+}
+
+impl<A: VMApi> MxAccessControlRegistry<A> {
+    pub const ADMIN_ROLE_DESCRIPTION: &'static str = "admin role";
+
+    pub fn new(manager: Account) -> Self {
+        Self {
+            manager,
+            admin_role_description: String::from(Self::ADMIN_ROLE_DESCRIPTION),
+            sender: None,
+            _api: PhantomData,
+        }
+    }
+
+    /// The registry for calls by `sender`, who can then renounce roles
+    pub fn with_sender(mut self, sender: Account) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    fn membership(&self, role: &Bytes32, who: &Account) -> SingleValueMapper<A, bool> {
+        let key = keccak_packed(&[
+            Token::FixedBytes(role.to_vec()),
+            Token::FixedBytes(who.as_ref().to_vec()),
+        ]);
+        mapper(ROLE_MEMBERSHIP_KEY, &key)
+    }
+}
+
+impl<A: VMApi> AccessControlRegistryAdminnedWithManager for MxAccessControlRegistry<A> {
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        &self.manager
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.admin_role_description.clone()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        keccak_packed(&[Token::String(self.admin_role_description())])
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        RoleDeriver::derive_role(
+            RoleDeriver::derive_root_role(self.manager.as_ref()),
+            self.admin_role_description(),
+        )
+    }
+}
+
+impl<A: VMApi> AccessControlRegistry for MxAccessControlRegistry<A> {
+    fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        self.membership(role, who).get()
+    }
+
+    fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+        self.membership(role, who).set(true);
+        Ok(())
+    }
+
+    fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+        if *role == Self::DEFAULT_ADMIN_ROLE {
+            return Some(Self::DEFAULT_ADMIN_ROLE);
+        }
+        let m = mapper::<A, Bytes32>(ROLE_ADMIN_KEY, role);
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.get())
+        }
+    }
+
+    fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+        mapper::<A, Bytes32>(ROLE_ADMIN_KEY, role).set(role_admin);
+        Ok(())
+    }
+
+    fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        api3_common::ensure!(self.sender.as_ref() == Some(account), Error::NotAuthorized)?;
+        self.revoke_role(role, account)
+    }
+
+    fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        self.membership(role, account).clear();
+        Ok(())
+    }
+}
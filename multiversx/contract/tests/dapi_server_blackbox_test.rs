@@ -0,0 +1,449 @@
+use api3_common::abi::{encode, Int, Token, Uint};
+use api3_common::{keccak_packed, Bytes32, DapiUpdateError, Error, ErrorPayload};
+use dapi_server_multiversx::ProxyTrait as _;
+use ed25519_dalek::{Signer, SigningKey};
+use multiversx_sc::codec::multi_types::MultiValue2;
+use multiversx_sc::types::{Address, ManagedAddress, ManagedBuffer, ManagedByteArray, ManagedVec};
+use multiversx_sc_scenario::{api::StaticApi, scenario_model::*, *};
+
+const CODE_PATH: &str = "file:output/dapi-server-multiversx.wasm";
+const OWNER: &str = "address:owner";
+const BOB: &str = "address:bob";
+const CHARLIE: &str = "address:charlie";
+const DAPI_SERVER: &str = "sc:dapi-server";
+const TEMPLATE_ID: Bytes32 = [1u8; 32];
+const NOW: u64 = 1_650_000_000;
+
+type DapiServer = ContractInfo<dapi_server_multiversx::Proxy<StaticApi>>;
+
+fn address(expr: &str) -> Address {
+    AddressValue::from(expr).to_address()
+}
+
+fn user_error(e: Error) -> TxExpect {
+    TxExpect::user_error(format!("str:{}", ErrorPayload::from(&e).to_json()))
+}
+
+fn dapi_update_error(e: Error, beacon_index: usize) -> TxExpect {
+    let payload = ErrorPayload::from(&DapiUpdateError::at(beacon_index)(e));
+    TxExpect::user_error(format!("str:{}", payload.to_json()))
+}
+
+fn airnode_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+fn airnode(key: &SigningKey) -> Bytes32 {
+    key.verifying_key().to_bytes()
+}
+
+/// Signs `value` at `timestamp` the way Airnode does, returns the
+/// timestamp, data and signature arguments of the update endpoints
+fn sign(key: &SigningKey, timestamp: u64, value: u128) -> (Bytes32, Vec<u8>, Vec<u8>) {
+    let mut ts = [0u8; 32];
+    Uint::from(timestamp).to_big_endian(&mut ts);
+    let data = encode(&[Token::Int(Int::from(value))]);
+    let message = keccak_packed(&[
+        Token::FixedBytes(TEMPLATE_ID.to_vec()),
+        Token::Uint(Uint::from(timestamp)),
+        Token::Bytes(data.clone()),
+    ]);
+    (ts, data, key.sign(&message).to_bytes().to_vec())
+}
+
+fn value(v: u128) -> Bytes32 {
+    let mut value = [0u8; 32];
+    Int::from(v).to_big_endian(&mut value);
+    value
+}
+
+fn byte_arrays(items: &[Bytes32]) -> ManagedVec<StaticApi, ManagedByteArray<StaticApi, 32>> {
+    items.iter().map(ManagedByteArray::from).collect()
+}
+
+fn buffers(items: &[Vec<u8>]) -> ManagedVec<StaticApi, ManagedBuffer<StaticApi>> {
+    items
+        .iter()
+        .map(|i| ManagedBuffer::from(i.as_slice()))
+        .collect()
+}
+
+fn deploy() -> (ScenarioWorld, DapiServer) {
+    let mut world = ScenarioWorld::new();
+    world.set_current_dir_from_workspace("");
+    world.register_contract(CODE_PATH, dapi_server_multiversx::ContractBuilder);
+
+    let code = world.code_expression(CODE_PATH);
+    let mut contract = DapiServer::new(DAPI_SERVER);
+    world.set_state_step(
+        SetStateStep::new()
+            .put_account(OWNER, Account::new().nonce(1))
+            .put_account(BOB, Account::new().nonce(1))
+            .put_account(CHARLIE, Account::new().nonce(1))
+            .new_address(OWNER, 1, DAPI_SERVER)
+            .block_timestamp(NOW),
+    );
+    world.sc_deploy(
+        ScDeployStep::new()
+            .from(OWNER)
+            .code(code)
+            .call(contract.init()),
+    );
+    (world, contract)
+}
+
+#[test]
+fn deployer_is_manager() {
+    let (mut world, mut contract) = deploy();
+    let manager: Address = world.quick_query(contract.manager());
+    assert_eq!(manager, address(OWNER));
+    let is_admin: bool = world.quick_query(contract.has_role([0u8; 32], address(OWNER)));
+    assert!(is_admin);
+}
+
+#[test]
+fn updates_beacon_with_signed_data() {
+    let (mut world, mut contract) = deploy();
+    let key = airnode_key(1);
+    let beacon_id: Bytes32 =
+        world.quick_query(contract.derive_beacon_id(airnode(&key), TEMPLATE_ID));
+
+    let (ts, data, signature) = sign(&key, NOW - 10, 123);
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_beacon_with_signed_data(
+                airnode(&key),
+                TEMPLATE_ID,
+                ts,
+                ManagedBuffer::from(data.as_slice()),
+                ManagedBuffer::from(signature.as_slice()),
+            )),
+    );
+
+    // a signature of another Airnode fails in the host
+    let (ts, data, signature) = sign(&airnode_key(2), NOW - 5, 456);
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_beacon_with_signed_data(
+                airnode(&key),
+                TEMPLATE_ID,
+                ts,
+                ManagedBuffer::from(data.as_slice()),
+                ManagedBuffer::from(signature.as_slice()),
+            ))
+            .expect(TxExpect::err(10, "str:invalid signature")),
+    );
+
+    // the manager is not whitelisted, but can grant itself the unlimited reader role
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.read_with_data_point_id(beacon_id))
+            .expect(user_error(Error::AccessDenied)),
+    );
+    let roles: MultiValue2<Bytes32, Bytes32> = world.quick_query(contract.roles());
+    let (unlimited_reader, _) = roles.into_tuple();
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.grant_role(unlimited_reader, address(OWNER))),
+    );
+    let read: MultiValue2<Bytes32, u32> = world.sc_call_get_result(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.read_with_data_point_id(beacon_id)),
+    );
+    assert_eq!(read.into_tuple(), (value(123), (NOW - 10) as u32));
+}
+
+#[test]
+fn updates_dapi_with_signed_data() {
+    let (mut world, mut contract) = deploy();
+    let keys = [airnode_key(1), airnode_key(2), airnode_key(3)];
+    let signed = keys
+        .iter()
+        .zip([100u128, 200, 400])
+        .map(|(k, v)| sign(k, NOW - 10, v))
+        .collect::<Vec<_>>();
+
+    let airnodes = keys.iter().map(airnode).collect::<Vec<_>>();
+    let template_ids = vec![TEMPLATE_ID; keys.len()];
+    let timestamps = signed.iter().map(|s| s.0).collect::<Vec<_>>();
+    let data = signed.iter().map(|s| s.1.clone()).collect::<Vec<_>>();
+    let signatures = signed.iter().map(|s| s.2.clone()).collect::<Vec<_>>();
+    let dapi_id: Bytes32 = world.sc_call_get_result(ScCallStep::new().from(BOB).call(
+        contract.update_dapi_with_signed_data(
+            byte_arrays(&airnodes),
+            byte_arrays(&template_ids),
+            byte_arrays(&timestamps),
+            buffers(&data),
+            buffers(&signatures),
+        ),
+    ));
+
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_whitelist_expiration(dapi_id, address(BOB), NOW + 60)),
+    );
+    let read: MultiValue2<Bytes32, u32> = world.sc_call_get_result(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.read_with_data_point_id(dapi_id)),
+    );
+    assert_eq!(read.into_tuple(), (value(200), (NOW - 10) as u32));
+
+    // the temporary whitelist expires
+    world.set_state_step(SetStateStep::new().block_timestamp(NOW + 60));
+    world.sc_query(
+        ScQueryStep::new()
+            .call(contract.reader_can_read_data_point(dapi_id, address(BOB)))
+            .expect_value(false),
+    );
+}
+
+#[test]
+fn vm_verifies_dapi_signatures() {
+    let (mut world, mut contract) = deploy();
+    let keys = [airnode_key(1), airnode_key(2)];
+    let signed = keys
+        .iter()
+        .zip([100u128, 200])
+        .map(|(k, v)| sign(k, NOW - 10, v))
+        .collect::<Vec<_>>();
+    let airnodes = keys.iter().map(airnode).collect::<Vec<_>>();
+    let template_ids = vec![TEMPLATE_ID; keys.len()];
+    let timestamps = signed.iter().map(|s| s.0).collect::<Vec<_>>();
+    let data = signed.iter().map(|s| s.1.clone()).collect::<Vec<_>>();
+    let mut signatures = signed.iter().map(|s| s.2.clone()).collect::<Vec<_>>();
+
+    // a signature of another key fails the call in the VM
+    signatures[1] = sign(&airnode_key(3), NOW - 10, 200).2;
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_dapi_with_signed_data(
+                byte_arrays(&airnodes),
+                byte_arrays(&template_ids),
+                byte_arrays(&timestamps),
+                buffers(&data),
+                buffers(&signatures),
+            ))
+            .expect(TxExpect::err(10, "str:invalid signature")),
+    );
+
+    // a signature that is not 64 bytes is rejected before reaching the VM
+    signatures[1].truncate(63);
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_dapi_with_signed_data(
+                byte_arrays(&airnodes),
+                byte_arrays(&template_ids),
+                byte_arrays(&timestamps),
+                buffers(&data),
+                buffers(&signatures),
+            ))
+            .expect(dapi_update_error(Error::InvalidSignature, 1)),
+    );
+
+    // the VM verifies the rest when a Beacon is read from storage
+    signatures[1] = Vec::new();
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_dapi_with_signed_data(
+                byte_arrays(&airnodes),
+                byte_arrays(&template_ids),
+                byte_arrays(&timestamps),
+                buffers(&data),
+                buffers(&signatures),
+            )),
+    );
+}
+
+#[test]
+fn names_and_whitelists() {
+    let (mut world, mut contract) = deploy();
+    let name = [7u8; 32];
+    let data_point_id = [8u8; 32];
+    let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_name(name, data_point_id))
+            .expect(user_error(Error::AccessDenied)),
+    );
+    let roles: MultiValue2<Bytes32, Bytes32> = world.quick_query(contract.roles());
+    let (_, name_setter) = roles.into_tuple();
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.grant_role(name_setter, address(OWNER))),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_name(name, data_point_id)),
+    );
+    world.sc_query(
+        ScQueryStep::new()
+            .call(contract.name_to_data_point_id(name))
+            .expect_value(Some(data_point_id)),
+    );
+
+    // only the manager and indefinite whitelisters can whitelist indefinitely
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.set_indefinite_whitelist_status(name_hash, address(CHARLIE), true))
+            .expect(user_error(Error::AccessDenied)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_indefinite_whitelist_status(name_hash, address(CHARLIE), true)),
+    );
+    world.sc_query(
+        ScQueryStep::new()
+            .call(
+                contract.data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+                    name_hash,
+                    address(CHARLIE),
+                    address(OWNER),
+                ),
+            )
+            .expect_value(true),
+    );
+    let read: MultiValue2<Bytes32, u32> = world.sc_call_get_result(
+        ScCallStep::new()
+            .from(CHARLIE)
+            .call(contract.read_with_name(name)),
+    );
+    assert_eq!(read.into_tuple(), (value(0), 0));
+
+    // the manager can always set indefinite whitelist statuses, so they cannot be revoked
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.revoke_indefinite_whitelist_status(
+                name_hash,
+                address(CHARLIE),
+                address(OWNER),
+            ))
+            .expect(user_error(Error::SetterCanSetIndefiniteStatus)),
+    );
+}
+
+#[test]
+fn pause_blocks_updates_and_reads() {
+    let (mut world, mut contract) = deploy();
+    let beacon_ids = [[1u8; 32], [2u8; 32]];
+    let dapi_id: Bytes32 =
+        world.quick_query(contract.derive_beacon_set_id(byte_arrays(&beacon_ids)));
+
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.set_pause_state(1u8))
+            .expect(user_error(Error::AccessDenied)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_pause_state(3u8))
+            .expect(user_error(Error::InvalidPauseState)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_data_feed_pause_state(dapi_id, 2u8)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_dapi_with_beacons(byte_arrays(&beacon_ids)))
+            .expect(user_error(Error::UpdatesPaused)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.read_with_data_point_id(dapi_id))
+            .expect(user_error(Error::ReadsPaused)),
+    );
+
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.set_data_feed_pause_state(dapi_id, 0u8)),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.update_dapi_with_beacons(byte_arrays(&beacon_ids)))
+            .expect_value(dapi_id),
+    );
+}
+
+#[test]
+fn manager_transfer_moves_admin_role() {
+    let (mut world, mut contract) = deploy();
+    let pauser_role: Bytes32 = world.quick_query(contract.pauser_role());
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.grant_role(pauser_role, address(CHARLIE))),
+    );
+
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.propose_manager(address(BOB))),
+    );
+    world.sc_query(
+        ScQueryStep::new()
+            .call(contract.pending_manager())
+            .expect_value(Some(ManagedAddress::<StaticApi>::from(address(BOB)))),
+    );
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.accept_manager())
+            .expect(user_error(Error::NotAuthorized)),
+    );
+
+    world.sc_call(ScCallStep::new().from(BOB).call(contract.accept_manager()));
+    let manager: Address = world.quick_query(contract.manager());
+    assert_eq!(manager, address(BOB));
+    world.sc_query(
+        ScQueryStep::new()
+            .call(contract.has_role([0u8; 32], address(OWNER)))
+            .expect_value(false),
+    );
+
+    // the roles are derived from the new manager, members are migrated by it
+    let members: ManagedVec<StaticApi, ManagedAddress<StaticApi>> =
+        ManagedVec::from_single_item(ManagedAddress::from(address(CHARLIE)));
+    world.sc_call(
+        ScCallStep::new()
+            .from(OWNER)
+            .call(contract.migrate_role_members(ManagedBuffer::from("Pauser"), members.clone()))
+            .expect(user_error(Error::NotAuthorized)),
+    );
+    let role: Bytes32 = world.sc_call_get_result(
+        ScCallStep::new()
+            .from(BOB)
+            .call(contract.migrate_role_members(ManagedBuffer::from("Pauser"), members)),
+    );
+    let new_pauser_role: Bytes32 = world.quick_query(contract.pauser_role());
+    assert_eq!(role, new_pauser_role);
+    assert_ne!(role, pauser_role);
+    world.sc_query(
+        ScQueryStep::new()
+            .call(contract.has_role(role, address(CHARLIE)))
+            .expect_value(true),
+    );
+}
@@ -1,5 +1,9 @@
 const { toBuffer, bufferU64BE, encodeData } = require("./util");
 
+function proofArg(proof) {
+    return proof === undefined ? null : proof.map(p => [...p]);
+}
+
 class DapiServer {
     contract;
 
@@ -23,16 +27,16 @@ class DapiServer {
         return await this.contract.renounce_role( { args: { role: [...role], who} });
     }
 
-    async readDataFeedWithId(dataPointId) {
-        const data = await this.contract.read_with_data_point_id( { args: {data_point_id: [...dataPointId]} });
+    async readDataFeedWithId(dataPointId, proof) {
+        const data = await this.contract.read_with_data_point_id( { args: {data_point_id: [...dataPointId], proof: proofArg(proof)} });
         return {
             value: data[0],
             timestamp: data[1]
         };
     }
 
-    async readDataFeedWithDapiName(name) {
-        const data = await this.contract.read_with_name( { args: {name: [...name]} });
+    async readDataFeedWithDapiName(name, proof) {
+        const data = await this.contract.read_with_name( { args: {name: [...name], proof: proofArg(proof)} });
         return {
             value: data[0],
             timestamp: data[1]
@@ -118,11 +122,31 @@ class DapiServer {
         );
     }
 
-    async readerCanReadDataFeed(datapoint, reader) {
+    async readerCanReadDataFeed(datapoint, reader, proof) {
         return await this.contract.reader_can_read_data_point(
             {
                 data_point_id: [...datapoint],
-                reader
+                reader,
+                proof: proofArg(proof)
+            }
+        );
+    }
+
+    async setWhitelistMerkleRoot(serviceId, root) {
+        return await this.contract.set_whitelist_merkle_root(
+            {
+                args: {
+                    service_id: [...serviceId],
+                    root: [...root]
+                }
+            }
+        );
+    }
+
+    async whitelistMerkleRoot(serviceId) {
+        return await this.contract.whitelist_merkle_root(
+            {
+                service_id: [...serviceId]
             }
         );
     }
@@ -163,6 +187,34 @@ class DapiServer {
         );
     }
 
+    async setWhitelistExpirationWithPermit(serviceId, user, expirationTimestamp, nonce, deadline, setterKey, signature) {
+        return await this.contract.set_whitelist_expiration_with_permit(
+            {
+                args: {
+                    service_id: [...serviceId],
+                    user,
+                    expiration_timestamp: expirationTimestamp,
+                    nonce,
+                    deadline,
+                    setter_key: [...setterKey],
+                    signature: [...signature]
+                }
+            }
+        );
+    }
+
+    async whitelistPermitDomainSeparator() {
+        return await this.contract.whitelist_permit_domain_separator({});
+    }
+
+    async whitelistPermitNonce(setter) {
+        return await this.contract.whitelist_permit_nonce(
+            {
+                setter
+            }
+        );
+    }
+
     async revokeIndefiniteWhitelistStatus(serviceId, user, setter) {
         return await this.contract.revoke_indefinite_whitelist_status(
             {
@@ -205,6 +257,285 @@ class DapiServer {
             }
         );
     }
+
+    async setCircuitBreakerThreshold(threshold) {
+        return await this.contract.set_circuit_breaker_threshold(
+            {
+                args: {
+                    threshold
+                }
+            }
+        );
+    }
+
+    async circuitBreakerThreshold() {
+        return await this.contract.circuit_breaker_threshold({});
+    }
+
+    async dataFeedPendingUpdate(dataFeedId) {
+        return await this.contract.data_feed_pending_update(
+            {
+                data_feed_id: [...dataFeedId]
+            }
+        );
+    }
+
+    async releaseDataFeed(dataFeedId) {
+        return await this.contract.release_data_feed(
+            {
+                args: {
+                    data_feed_id: [...dataFeedId]
+                }
+            }
+        );
+    }
+
+    async pauserRole() {
+        return await this.contract.pauser_role({});
+    }
+
+    async pauseState() {
+        return await this.contract.pause_state({});
+    }
+
+    async dataFeedPauseState(dataFeedId) {
+        return await this.contract.data_feed_pause_state(
+            {
+                data_feed_id: [...dataFeedId]
+            }
+        );
+    }
+
+    async setPauseState(state) {
+        return await this.contract.set_pause_state(
+            {
+                args: {
+                    state
+                }
+            }
+        );
+    }
+
+    async setDataFeedPauseState(dataFeedId, state) {
+        return await this.contract.set_data_feed_pause_state(
+            {
+                args: {
+                    data_feed_id: [...dataFeedId],
+                    state
+                }
+            }
+        );
+    }
+
+    async manager() {
+        return await this.contract.manager({});
+    }
+
+    async pendingManager() {
+        return await this.contract.pending_manager({});
+    }
+
+    async previousManager() {
+        return await this.contract.previous_manager({});
+    }
+
+    async proposeManager(newManager) {
+        return await this.contract.propose_manager(
+            {
+                args: {
+                    new_manager: newManager
+                }
+            }
+        );
+    }
+
+    async acceptManager() {
+        return await this.contract.accept_manager({ args: {} });
+    }
+
+    async migrateRoleMembers(description, members) {
+        return await this.contract.migrate_role_members(
+            {
+                args: {
+                    description,
+                    members
+                }
+            }
+        );
+    }
+
+    async getRoleMemberCount(role) {
+        return await this.contract.get_role_member_count(
+            {
+                role: [...role]
+            }
+        );
+    }
+
+    async getRoleMember(role, index) {
+        return await this.contract.get_role_member(
+            {
+                role: [...role],
+                index
+            }
+        );
+    }
+
+    async rolesOf(account) {
+        return await this.contract.roles_of(
+            {
+                account
+            }
+        );
+    }
+
+    async grantRoleUntil(role, who, expirationTimestamp) {
+        return await this.contract.grant_role_until(
+            {
+                args: {
+                    role: [...role],
+                    who,
+                    expiration_timestamp: expirationTimestamp
+                }
+            }
+        );
+    }
+
+    async roleExpiration(role, who) {
+        return await this.contract.role_expiration(
+            {
+                role: [...role],
+                who
+            }
+        );
+    }
+
+    async setSubscriptionPrice(amountPerPeriod, period) {
+        return await this.contract.set_subscription_price(
+            {
+                args: {
+                    amount_per_period: amountPerPeriod,
+                    period
+                }
+            }
+        );
+    }
+
+    async subscriptionPrice() {
+        const price = await this.contract.subscription_price({});
+        return {
+            amountPerPeriod: price[0],
+            period: price[1]
+        };
+    }
+
+    async subscriptionBalance() {
+        return await this.contract.subscription_balance({});
+    }
+
+    async subscribe(dataFeedId, periods, amount) {
+        return await this.contract.subscribe(
+            {
+                args: {
+                    data_feed_id: [...dataFeedId],
+                    periods
+                },
+                amount
+            }
+        );
+    }
+
+    async withdrawSubscriptionBalance(amount) {
+        return await this.contract.withdraw_subscription_balance(
+            {
+                args: {
+                    amount
+                }
+            }
+        );
+    }
+
+    async whitelistedUserCount(serviceId) {
+        return await this.contract.whitelisted_user_count(
+            {
+                service_id: [...serviceId]
+            }
+        );
+    }
+
+    async whitelistedUsersOf(serviceId, fromIndex, limit) {
+        return await this.contract.whitelisted_users_of(
+            {
+                service_id: [...serviceId],
+                from_index: fromIndex,
+                limit
+            }
+        );
+    }
+
+    async indefiniteWhitelistSettersOf(serviceId, user) {
+        return await this.contract.indefinite_whitelist_setters_of(
+            {
+                service_id: [...serviceId],
+                user
+            }
+        );
+    }
+
+    async sweepExpired(serviceId, fromIndex, limit) {
+        return await this.contract.sweep_expired(
+            {
+                args: {
+                    service_id: [...serviceId],
+                    from_index: fromIndex,
+                    limit
+                }
+            }
+        );
+    }
+
+    async setUsageEpochLength(epochLength) {
+        return await this.contract.set_usage_epoch_length(
+            {
+                args: {
+                    epoch_length: epochLength
+                }
+            }
+        );
+    }
+
+    async usageEpochLength() {
+        return await this.contract.usage_epoch_length({});
+    }
+
+    async currentUsageEpoch() {
+        return await this.contract.current_usage_epoch({});
+    }
+
+    async setUsageQuota(tier, quota) {
+        return await this.contract.set_usage_quota(
+            {
+                args: {
+                    tier,
+                    quota
+                }
+            }
+        );
+    }
+
+    async usageQuota(tier) {
+        return await this.contract.usage_quota({ tier });
+    }
+
+    async readCount(dataPointId, reader, epoch) {
+        return await this.contract.read_count(
+            {
+                data_point_id: [...dataPointId],
+                reader,
+                epoch
+            }
+        );
+    }
 }
 
 module.exports = { DapiServer }
\ No newline at end of file
@@ -32,6 +32,17 @@ function keccak256Packed(types, data) {
     return Buffer.from(hex, "hex");
 }
 
+// Leaf of `user` in a whitelist Merkle tree, same as `api3_common::merkle_leaf`
+function merkleLeaf(user) {
+    return keccak256Packed(["bytes32"], [keccak256Packed(["bytes"], [Buffer.from(user)])]);
+}
+
+// Parent of two Merkle tree nodes, the pair is hashed in sorted order
+function merkleParent(a, b) {
+    const [first, second] = Buffer.compare(a, b) <= 0 ? [a, b] : [b, a];
+    return keccak256Packed(["bytes32", "bytes32"], [first, second]);
+}
+
 function currentTimestamp() {
     return Math.floor(Date.now() / 1000);
 }
@@ -42,6 +53,28 @@ async function encodeAndSignData(decodedData, requestHash, timestamp, signer) {
     return [data, signature.signature];
 }
 
+// Same as `api3_common::permit_domain_separator`
+function permitDomainSeparator(chainId, contract) {
+    return keccak256Packed(
+        ["bytes32", "bytes32", "bytes32"],
+        [
+            keccak256Packed(["bytes"], [Buffer.from("api3 whitelist permit")]),
+            keccak256Packed(["bytes"], [Buffer.from(chainId)]),
+            keccak256Packed(["bytes"], [Buffer.from(contract)]),
+        ]
+    );
+}
+
+// Signs a whitelist permit, same message as `api3_common::WhitelistPermit::message`
+async function signWhitelistPermit(chainId, contract, serviceId, user, expirationTimestamp, nonce, deadline, signer) {
+    const message = keccak256Packed(
+        ["bytes32", "bytes32", "bytes", "uint256", "uint256", "uint256"],
+        [permitDomainSeparator(chainId, contract), serviceId, Buffer.from(user), bufferU64BE(expirationTimestamp), bufferU64BE(nonce), bufferU64BE(deadline)]
+    );
+    const signature = await signer.sign(message);
+    return signature.signature;
+}
+
 function bufferU64BE(value) {
     const buffer = Buffer.alloc(32);
     buffer.writeBigUInt64BE(BigInt(value), 24);
@@ -78,5 +111,5 @@ function deriveDApiId(beaconIds) {
 module.exports = {
     keccak256Packed, currentTimestamp, encodeData, prepareMessage,
     generateRandomBytes32, toBuffer, bufferU64BE, encodeAndSignData,
-    deriveBeaconId, deriveDApiId, delay
+    deriveBeaconId, deriveDApiId, delay, merkleLeaf, merkleParent, permitDomainSeparator, signWhitelistPermit
 };
\ No newline at end of file
@@ -4,7 +4,7 @@ const {
   timestampNotValid, signatureNotValid
 } = require("./utils/updateBeaconWithSignedData");
 const { 
-  updatesBeaconSet, lessThanTwoBeacons
+  updatesBeaconSet, beaconUpdatesPaused, lessThanTwoBeacons
 
 } = require("./utils/updateBeaconSetWithBeacons");
 const { 
@@ -13,7 +13,7 @@ const {
 const {
   derivesBeaconId, templateIdZero, airnodeZero, derivesBeaconSetId,
 } = require("./utils/derive");
-const { generateRandomBytes32, toBuffer, currentTimestamp, deriveBeaconId, deriveDApiId, delay, encodeAndSignData, encodeData } = require("../src/util");
+const { generateRandomBytes32, toBuffer, currentTimestamp, deriveBeaconId, deriveDApiId, delay, encodeAndSignData, encodeData, merkleLeaf, merkleParent, permitDomainSeparator, signWhitelistPermit } = require("../src/util");
 const fs = require("fs");
 const ethers = require("ethers");
 const nearAPI = require("near-api-js");
@@ -83,8 +83,30 @@ describe('Token', function () {
         'whitelist_expiration_setter_role',
         'whitelist_expiration_extender_role',
         'indefinite_whitelister_role',
-        'read_with_data_point_id',
-        'read_with_name',
+        'circuit_breaker_threshold',
+        'data_feed_pending_update',
+        'pauser_role',
+        'pause_state',
+        'data_feed_pause_state',
+        'manager',
+        'pending_manager',
+        'previous_manager',
+        'get_role_member_count',
+        'get_role_member',
+        'roles_of',
+        'role_expiration',
+        'subscription_price',
+        'subscription_balance',
+        'whitelist_merkle_root',
+        'whitelist_permit_nonce',
+        'whitelist_permit_domain_separator',
+        'whitelisted_user_count',
+        'whitelisted_users_of',
+        'indefinite_whitelist_setters_of',
+        'usage_epoch_length',
+        'current_usage_epoch',
+        'usage_quota',
+        'read_count',
       ],
       changeMethods: [
         'initialize',
@@ -100,6 +122,23 @@ describe('Token', function () {
         'set_whitelist_expiration',
         'extend_whitelist_expiration',
         'revoke_indefinite_whitelist_status',
+        'set_circuit_breaker_threshold',
+        'release_data_feed',
+        'set_pause_state',
+        'set_data_feed_pause_state',
+        'propose_manager',
+        'accept_manager',
+        'migrate_role_members',
+        'grant_role_until',
+        'set_subscription_price',
+        'withdraw_subscription_balance',
+        'set_whitelist_merkle_root',
+        'set_whitelist_expiration_with_permit',
+        'sweep_expired',
+        'read_with_data_point_id',
+        'read_with_name',
+        'set_usage_epoch_length',
+        'set_usage_quota',
       ],
     });
     client = new DapiServer(contract);
@@ -115,7 +154,8 @@ describe('Token', function () {
         'data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status',
         'whitelist_expiration_setter_role',
         'whitelist_expiration_extender_role',
-        'indefinite_whitelister_role'
+        'indefinite_whitelister_role',
+        'whitelist_merkle_root'
       ],
       changeMethods: [
         'get_data_point',
@@ -124,7 +164,10 @@ describe('Token', function () {
         'set_indefinite_whitelist_status',
         'set_whitelist_expiration',
         'extend_whitelist_expiration',
-        'revoke_indefinite_whitelist_status'
+        'revoke_indefinite_whitelist_status',
+        'subscribe',
+        'set_whitelist_expiration_with_permit',
+        'sweep_expired'
       ],
     });
     userClient = new DapiServer(userContract);
@@ -140,6 +183,144 @@ describe('Token', function () {
     await client.grantRole([...unlimitedReaderRole], reader);
   });
 
+  describe('roleEnumeration', function () {
+    it('listsRoleMembers', async function () {
+      const unlimitedReaderRole = (await contract.roles())[0];
+      const count = await client.getRoleMemberCount(unlimitedReaderRole);
+      const members = [];
+      for (let i = 0; i < count; i++) {
+        members.push(await client.getRoleMember(unlimitedReaderRole, i));
+      }
+      expect(members).toContain(userAccount);
+      expect(await client.getRoleMember(unlimitedReaderRole, count)).toBeNull();
+
+      const roles = await client.rolesOf(userAccount);
+      expect(roles).toContainEqual([...unlimitedReaderRole]);
+    });
+
+    it('expiresTimeBoundRoles', async function () {
+      const role = new Array(32).fill(9);
+      const expiration = Math.floor(Date.now() / 1000) + 3;
+      await client.grantRoleUntil(role, userAccount, expiration);
+      expect(await client.hasRole(role, userAccount)).toBe(true);
+      expect(await client.roleExpiration(role, userAccount)).toBe(expiration);
+
+      await new Promise(r => setTimeout(r, 5000));
+      expect(await client.hasRole(role, userAccount)).toBe(false);
+    });
+  });
+
+  describe('subscription', function () {
+    it('whitelistsPayingReaders', async function () {
+      const dataFeedId = new Array(32).fill(8);
+      await client.setSubscriptionPrice('1000', 3600);
+      const price = await client.subscriptionPrice();
+      expect(price.amountPerPeriod).toBe('1000');
+      expect(price.period).toBe(3600);
+
+      await expect(userClient.subscribe(dataFeedId, 2, '1999')).rejects.toThrow();
+      const expiration = await userClient.subscribe(dataFeedId, 2, '2000');
+      expect(expiration).toBeGreaterThan(currentTimestamp() + 3600);
+      expect(await client.readerCanReadDataFeed(dataFeedId, userAccount)).toBe(true);
+      expect(await client.subscriptionBalance()).toBe('2000');
+
+      await expect(userClient.withdrawSubscriptionBalance('2000')).rejects.toThrow();
+      await client.withdrawSubscriptionBalance('2000');
+      expect(await client.subscriptionBalance()).toBe('0');
+    });
+  });
+
+  describe('merkleWhitelist', function () {
+    it('whitelistsReadersWithProof', async function () {
+      const serviceId = generateRandomBytes32();
+      const otherLeaf = merkleLeaf('other.testnet');
+      const root = merkleParent(merkleLeaf(userAccount), otherLeaf);
+
+      await expect(userClient.setWhitelistMerkleRoot(serviceId, root)).rejects.toThrow();
+      await client.setWhitelistMerkleRoot(serviceId, root);
+      expect(await client.whitelistMerkleRoot(serviceId)).toEqual([...root]);
+
+      expect(await client.readerCanReadDataFeed(serviceId, userAccount)).toBe(false);
+      expect(await client.readerCanReadDataFeed(serviceId, userAccount, [otherLeaf])).toBe(true);
+      expect(await client.readerCanReadDataFeed(serviceId, 'other.testnet', [otherLeaf])).toBe(false);
+    });
+  });
+
+  describe('whitelistPermit', function () {
+    it('setsExpirationOncePerPermit', async function () {
+      const setterKey = keyPair.getPublicKey().data;
+      const setter = Buffer.from(setterKey).toString('hex');
+      await client.grantRole(await client.whitelistExpirationSetterRole(), setter);
+
+      const serviceId = generateRandomBytes32();
+      const expiration = currentTimestamp() + 3600;
+      const deadline = currentTimestamp() + 600;
+      const nonce = await client.whitelistPermitNonce(setter);
+      // NEAR has no chain ID, the top-level account of the contract names the network
+      const chainId = contractAccount.split('.').pop();
+      expect(Buffer.from(await client.whitelistPermitDomainSeparator())).toEqual(permitDomainSeparator(chainId, contractAccount));
+      const signature = await signWhitelistPermit(chainId, contractAccount, serviceId, userAccount, expiration, nonce, deadline, keyPair);
+
+      await userClient.setWhitelistExpirationWithPermit(serviceId, userAccount, expiration, nonce, deadline, setterKey, signature);
+      expect(await client.readerCanReadDataFeed(serviceId, userAccount)).toBe(true);
+      expect(await client.whitelistPermitNonce(setter)).toBe(nonce + 1);
+
+      await expect(
+        userClient.setWhitelistExpirationWithPermit(serviceId, userAccount, expiration, nonce, deadline, setterKey, signature)
+      ).rejects.toThrow();
+
+      const otherContract = await signWhitelistPermit(chainId, 'other.testnet', serviceId, userAccount, expiration, nonce + 1, deadline, keyPair);
+      await expect(
+        userClient.setWhitelistExpirationWithPermit(serviceId, userAccount, expiration, nonce + 1, deadline, setterKey, otherContract)
+      ).rejects.toThrow();
+    });
+  });
+
+  describe('whitelistEnumeration', function () {
+    it('listsAndSweepsWhitelistedUsers', async function () {
+      const serviceId = generateRandomBytes32();
+      await client.setWhitelistExpiration(serviceId, userAccount, currentTimestamp() + 3);
+      await client.setIndefiniteWhitelistStatus(serviceId, 'other.testnet', true);
+      // a revoked indefinite whitelist leaves an inactive status behind
+      await client.setIndefiniteWhitelistStatus(serviceId, 'revoked.testnet', true);
+      await client.setIndefiniteWhitelistStatus(serviceId, 'revoked.testnet', false);
+
+      expect(await client.whitelistedUserCount(serviceId)).toBe(3);
+      expect(await client.whitelistedUsersOf(serviceId, 0, 2)).toEqual([userAccount, 'other.testnet']);
+      expect(await client.whitelistedUsersOf(serviceId, 2, 2)).toEqual(['revoked.testnet']);
+      expect(await client.indefiniteWhitelistSettersOf(serviceId, 'other.testnet')).toEqual([adminAccount]);
+      expect(await client.indefiniteWhitelistSettersOf(serviceId, 'revoked.testnet')).toEqual([]);
+
+      // only checks other.testnet, which stays whitelisted
+      expect(await userClient.sweepExpired(serviceId, 1, 1)).toBe(0);
+      await delay(5000);
+      expect(await userClient.sweepExpired(serviceId, 0, 10)).toBe(2);
+      expect(await client.whitelistedUsersOf(serviceId, 0, 10)).toEqual(['other.testnet']);
+      expect(await client.readerCanReadDataFeed(serviceId, 'other.testnet')).toBe(true);
+    });
+  });
+
+  describe('usageMetering', function () {
+    afterAll(async () => {
+      await client.setUsageEpochLength(0);
+    });
+
+    it('enforcesTierQuotas', async function () {
+      const dataPointId = generateRandomBytes32();
+      await client.setUsageEpochLength(3600);
+      await client.setUsageQuota(0, 1);
+      expect(await client.usageQuota(0)).toBe(1);
+
+      await userClient.readDataFeedWithId([...dataPointId]);
+      const epoch = await client.currentUsageEpoch();
+      expect(await client.readCount(dataPointId, userAccount, epoch)).toBe(1);
+      await expect(userClient.readDataFeedWithId([...dataPointId])).rejects.toThrow("ReadQuotaExceeded");
+
+      await client.setUsageQuota(0, null);
+      await userClient.readDataFeedWithId([...dataPointId]);
+    });
+  });
+
   describe('updateBeaconWithSignedData', function () {
     let roles;
 
@@ -219,6 +400,11 @@ describe('Token', function () {
       await updatesBeaconSet(client, beaconIds, 456, Math.floor(expectedTimestamp / beaconData.length), userClient);
     });
 
+    it('beaconUpdatesPaused', async function () {
+      const beaconIds = beaconSetTemplateIds.map(t => [...deriveBeaconId(keyPair.getPublicKey().data, t)]);
+      await beaconUpdatesPaused(client, beaconIds);
+    });
+
     it('lessThanTwoBeacons', async function () {
       await lessThanTwoBeacons(client);
     });
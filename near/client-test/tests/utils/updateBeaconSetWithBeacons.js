@@ -9,10 +9,16 @@ async function updatesBeaconSet(client, beaconIds, expectedValue, expectedTimest
     expect(beacon.value).toEqual([...encodeData(expectedValue)])
 }
 
+async function beaconUpdatesPaused(client, beaconIds) {
+  await client.setDataFeedPauseState(beaconIds[1], 1);
+  await expect(client.updateBeaconSetWithBeacons(beaconIds)).rejects.toThrow("UpdatesPaused");
+  await client.setDataFeedPauseState(beaconIds[1], 0);
+}
+
 async function lessThanTwoBeacons(client) {
   await expect(client.updateBeaconSetWithBeacons([])).rejects.toThrow("LessThanTwoBeacons")
 }
 
 module.exports = { 
-  updatesBeaconSet, lessThanTwoBeacons
+  updatesBeaconSet, beaconUpdatesPaused, lessThanTwoBeacons
 };
\ No newline at end of file
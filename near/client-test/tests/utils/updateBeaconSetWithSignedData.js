@@ -90,13 +90,16 @@ async function notAllSignaturesValid(client, signer, airnodeAddress, beaconSetTe
   const timestamp = currentTimestamp();
 
   const data = Buffer.alloc(21, 0);
-  await expect(client.updateBeaconSetWithSignedData(
+  const update = client.updateBeaconSetWithSignedData(
     [airnodeAddress, airnodeAddress],
     [beaconSetTemplateIds[0], beaconSetTemplateIds[1]],
     [0, timestamp],
     [[], [...data]],
     [[], [...Buffer.alloc(64)]]
-  )).rejects.toThrow("InvalidSignature") 
+  );
+  await expect(update).rejects.toThrow("InvalidSignature");
+  // the payload names the Beacon with the invalid signature
+  await expect(update).rejects.toThrow(/beacon_index\\?":\\?"1/);
 }
 
 async function lessThanTwoBeacons(client) {
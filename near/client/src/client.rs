@@ -0,0 +1,897 @@
+use crate::{BatchCall, ClientError, Transport};
+use api3_common::abi::U256;
+use api3_common::{
+    Bytes, Bytes32, PauseState, ReaderTier, SubscriptionPrice, Timestamp, WhitelistPermit,
+    WhitelistStatus,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// The value of a data feed as the contract returns it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataFeed {
+    pub value: i128,
+    pub timestamp: u32,
+}
+
+impl DataFeed {
+    fn decode(method: &str, (value, timestamp): (Bytes32, u32)) -> Result<Self, ClientError> {
+        let value = decode_value(&value).ok_or_else(|| {
+            ClientError::InvalidResult(format!(
+                "{}: 0x{} does not fit in i128",
+                method,
+                hex::encode(value)
+            ))
+        })?;
+        Ok(Self { value, timestamp })
+    }
+}
+
+/// Decodes a data feed value, an `int256` in two's complement, `None` if it
+/// does not fit in `i128`
+pub fn decode_value(value: &Bytes32) -> Option<i128> {
+    let (high, low) = value.split_at(16);
+    let low = i128::from_be_bytes(low.try_into().expect("split at half of 32 bytes"));
+    let sign = if low < 0 { 0xff } else { 0 };
+    high.iter().all(|b| *b == sign).then_some(low)
+}
+
+/// Typed client of the NEAR `DapiServer` contract. Builds the JSON arguments
+/// of the methods, `Bytes32` and `Bytes` as arrays of numbers, and decodes
+/// their results. Panics of the contract fail the calls with the
+/// `api3_common::Error` they carry.
+///
+/// The reads of data feeds are change calls, as the contract checks the
+/// whitelist of the sender and meters the reads while metering is enabled.
+pub struct DapiServer<T: Transport> {
+    transport: T,
+}
+
+impl<T: Transport> DapiServer<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    // ================== Access Control ====================
+    /// Returns the unlimited reader role and the name setter role
+    pub fn roles(&self) -> Result<(Bytes32, Bytes32), ClientError> {
+        self.view("roles", json!({}))
+    }
+
+    pub fn has_role(&self, role: &Bytes32, who: &str) -> Result<bool, ClientError> {
+        self.view("has_role", json!({ "role": role, "who": who }))
+    }
+
+    pub fn grant_role(&self, role: &Bytes32, who: &str) -> Result<(), ClientError> {
+        self.call("grant_role", json!({ "role": role, "who": who }))
+    }
+
+    /// Grants the role until `expiration_timestamp`, in seconds
+    pub fn grant_role_until(
+        &self,
+        role: &Bytes32,
+        who: &str,
+        expiration_timestamp: u64,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "grant_role_until",
+            json!({ "role": role, "who": who, "expiration_timestamp": expiration_timestamp }),
+        )
+    }
+
+    /// Returns when the role of `who` expires, `None` if it does not
+    pub fn role_expiration(&self, role: &Bytes32, who: &str) -> Result<Option<u64>, ClientError> {
+        self.view("role_expiration", json!({ "role": role, "who": who }))
+    }
+
+    pub fn revoke_role(&self, role: &Bytes32, who: &str) -> Result<(), ClientError> {
+        self.call("revoke_role", json!({ "role": role, "who": who }))
+    }
+
+    pub fn renounce_role(&self, role: &Bytes32, who: &str) -> Result<(), ClientError> {
+        self.call("renounce_role", json!({ "role": role, "who": who }))
+    }
+
+    pub fn get_role_member_count(&self, role: &Bytes32) -> Result<u64, ClientError> {
+        self.view("get_role_member_count", json!({ "role": role }))
+    }
+
+    pub fn get_role_member(
+        &self,
+        role: &Bytes32,
+        index: u64,
+    ) -> Result<Option<String>, ClientError> {
+        self.view("get_role_member", json!({ "role": role, "index": index }))
+    }
+
+    pub fn roles_of(&self, account: &str) -> Result<Vec<Bytes32>, ClientError> {
+        self.view("roles_of", json!({ "account": account }))
+    }
+
+    pub fn manager(&self) -> Result<String, ClientError> {
+        self.view("manager", json!({}))
+    }
+
+    pub fn pending_manager(&self) -> Result<Option<String>, ClientError> {
+        self.view("pending_manager", json!({}))
+    }
+
+    pub fn previous_manager(&self) -> Result<Option<String>, ClientError> {
+        self.view("previous_manager", json!({}))
+    }
+
+    pub fn propose_manager(&self, new_manager: &str) -> Result<(), ClientError> {
+        self.call("propose_manager", json!({ "new_manager": new_manager }))
+    }
+
+    pub fn accept_manager(&self) -> Result<(), ClientError> {
+        self.call("accept_manager", json!({}))
+    }
+
+    /// Grants the role of `description` of the previous manager to `members`,
+    /// returns the role
+    pub fn migrate_role_members(
+        &self,
+        description: &str,
+        members: &[String],
+    ) -> Result<Bytes32, ClientError> {
+        self.call(
+            "migrate_role_members",
+            json!({ "description": description, "members": members }),
+        )
+    }
+
+    // ================== Pause ====================
+    pub fn pauser_role(&self) -> Result<Bytes32, ClientError> {
+        self.view("pauser_role", json!({}))
+    }
+
+    pub fn pause_state(&self) -> Result<PauseState, ClientError> {
+        let state: u8 = self.view("pause_state", json!({}))?;
+        pause_state("pause_state", state)
+    }
+
+    pub fn data_feed_pause_state(&self, data_feed_id: &Bytes32) -> Result<PauseState, ClientError> {
+        let method = "data_feed_pause_state";
+        let state: u8 = self.view(method, json!({ "data_feed_id": data_feed_id }))?;
+        pause_state(method, state)
+    }
+
+    pub fn set_pause_state(&self, state: PauseState) -> Result<(), ClientError> {
+        self.call("set_pause_state", json!({ "state": u8::from(state) }))
+    }
+
+    pub fn set_data_feed_pause_state(
+        &self,
+        data_feed_id: &Bytes32,
+        state: PauseState,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "set_data_feed_pause_state",
+            json!({ "data_feed_id": data_feed_id, "state": u8::from(state) }),
+        )
+    }
+
+    // ================== Updates ====================
+    /// Updates the Beacon with data signed by its Airnode
+    pub fn update_beacon_with_signed_data(
+        &self,
+        airnode: &[u8],
+        template_id: &Bytes32,
+        timestamp: &Bytes32,
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<(), ClientError> {
+        let c = BatchCall::update_beacon_with_signed_data(
+            airnode,
+            template_id,
+            timestamp,
+            data,
+            signature,
+        );
+        self.call(&c.method, c.args)
+    }
+
+    /// Updates the dAPI of the Beacons, returns the dAPI ID
+    pub fn update_dapi_with_beacons(&self, beacon_ids: &[Bytes32]) -> Result<Bytes32, ClientError> {
+        self.call(
+            "update_dapi_with_beacons",
+            json!({ "beacon_ids": beacon_ids }),
+        )
+    }
+
+    /// Updates the dAPI with data signed by the Airnodes, the Beacons with an
+    /// empty signature are read from storage. Returns the dAPI ID.
+    pub fn update_dapi_with_signed_data(
+        &self,
+        airnodes: &[Bytes],
+        template_ids: &[Bytes32],
+        timestamps: &[Bytes32],
+        data: &[Bytes],
+        signatures: &[Bytes],
+    ) -> Result<Bytes32, ClientError> {
+        let c = BatchCall::update_dapi_with_signed_data(
+            airnodes,
+            template_ids,
+            timestamps,
+            data,
+            signatures,
+        );
+        self.call(&c.method, c.args)
+    }
+
+    /// Makes the calls in one transaction, in order. It fails as a whole
+    /// if one of them fails.
+    pub fn batch(&self, calls: &[BatchCall]) -> Result<(), ClientError> {
+        self.transport.call_batch(calls)
+    }
+
+    // ================== Circuit Breaker ====================
+    /// Sets the deviation threshold of the circuit breaker, `100_000_000` is
+    /// 100% and zero disables it
+    pub fn set_circuit_breaker_threshold(&self, threshold: u32) -> Result<(), ClientError> {
+        self.call(
+            "set_circuit_breaker_threshold",
+            json!({ "threshold": threshold }),
+        )
+    }
+
+    pub fn circuit_breaker_threshold(&self) -> Result<u32, ClientError> {
+        self.view("circuit_breaker_threshold", json!({}))
+    }
+
+    /// Returns the update the circuit breaker holds back, `None` if the data feed is not paused
+    pub fn data_feed_pending_update(
+        &self,
+        data_feed_id: &Bytes32,
+    ) -> Result<Option<DataFeed>, ClientError> {
+        let method = "data_feed_pending_update";
+        let pending: Option<(Bytes32, u32)> =
+            self.view(method, json!({ "data_feed_id": data_feed_id }))?;
+        pending.map(|p| DataFeed::decode(method, p)).transpose()
+    }
+
+    pub fn release_data_feed(&self, data_feed_id: &Bytes32) -> Result<(), ClientError> {
+        self.call("release_data_feed", json!({ "data_feed_id": data_feed_id }))
+    }
+
+    // ================== Names and IDs ====================
+    pub fn set_name(&self, name: &Bytes32, datapoint_id: &Bytes32) -> Result<(), ClientError> {
+        self.call(
+            "set_name",
+            json!({ "name": name, "datapoint_id": datapoint_id }),
+        )
+    }
+
+    pub fn name_to_data_point_id(&self, name: &Bytes32) -> Result<Option<Bytes32>, ClientError> {
+        self.view("name_to_data_point_id", json!({ "name": name }))
+    }
+
+    pub fn derive_beacon_id(
+        &self,
+        airnode: &[u8],
+        template_id: &Bytes32,
+    ) -> Result<Bytes32, ClientError> {
+        self.view(
+            "derive_beacon_id",
+            json!({ "airnode": airnode, "template_id": template_id }),
+        )
+    }
+
+    pub fn derive_beacon_set_id(&self, beacon_ids: &[Bytes32]) -> Result<Bytes32, ClientError> {
+        self.view("derive_beacon_set_id", json!({ "beacon_ids": beacon_ids }))
+    }
+
+    // ================== Reads ====================
+    /// Reads the data feed with ID, `proof` is the Merkle proof of the
+    /// account in the whitelist tree of the data feed
+    pub fn read_with_data_point_id(
+        &self,
+        data_point_id: &Bytes32,
+        proof: Option<&[Bytes32]>,
+    ) -> Result<DataFeed, ClientError> {
+        let method = "read_with_data_point_id";
+        let read = self.call(
+            method,
+            json!({ "data_point_id": data_point_id, "proof": proof }),
+        )?;
+        DataFeed::decode(method, read)
+    }
+
+    /// Reads the data feed the name points to, `proof` is the Merkle proof of
+    /// the account in the whitelist tree of the name hash
+    pub fn read_with_name(
+        &self,
+        name: &Bytes32,
+        proof: Option<&[Bytes32]>,
+    ) -> Result<DataFeed, ClientError> {
+        let method = "read_with_name";
+        let read = self.call(method, json!({ "name": name, "proof": proof }))?;
+        DataFeed::decode(method, read)
+    }
+
+    pub fn reader_can_read_data_point(
+        &self,
+        data_point_id: &Bytes32,
+        reader: &str,
+        proof: Option<&[Bytes32]>,
+    ) -> Result<bool, ClientError> {
+        self.view(
+            "reader_can_read_data_point",
+            json!({ "data_point_id": data_point_id, "reader": reader, "proof": proof }),
+        )
+    }
+
+    // ================== Whitelist ====================
+    pub fn whitelist_expiration_extender_role(&self) -> Result<Bytes32, ClientError> {
+        self.view("whitelist_expiration_extender_role", json!({}))
+    }
+
+    pub fn whitelist_expiration_setter_role(&self) -> Result<Bytes32, ClientError> {
+        self.view("whitelist_expiration_setter_role", json!({}))
+    }
+
+    pub fn indefinite_whitelister_role(&self) -> Result<Bytes32, ClientError> {
+        self.view("indefinite_whitelister_role", json!({}))
+    }
+
+    /// Returns the whitelist status of the reader, `None` if it has none
+    pub fn data_feed_id_to_whitelist_status(
+        &self,
+        data_feed_id: &Bytes32,
+        reader: &str,
+    ) -> Result<Option<WhitelistStatus>, ClientError> {
+        let status: Option<(u64, Bytes32)> = self.view(
+            "data_feed_id_to_whitelist_status",
+            json!({ "data_feed_id": data_feed_id, "reader": reader }),
+        )?;
+        Ok(status.map(|(expiration, count)| WhitelistStatus {
+            expiration_timestamp: Timestamp::from_secs(expiration),
+            indefinite_whitelist_count: U256::from_big_endian(&count),
+        }))
+    }
+
+    pub fn data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+        &self,
+        data_feed_id: &Bytes32,
+        reader: &str,
+        setter: &str,
+    ) -> Result<bool, ClientError> {
+        self.view(
+            "data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status",
+            json!({ "data_feed_id": data_feed_id, "reader": reader, "setter": setter }),
+        )
+    }
+
+    pub fn extend_whitelist_expiration(
+        &self,
+        service_id: &Bytes32,
+        user: &str,
+        expiration_timestamp: u64,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "extend_whitelist_expiration",
+            json!({
+                "service_id": service_id,
+                "user": user,
+                "expiration_timestamp": expiration_timestamp,
+            }),
+        )
+    }
+
+    pub fn set_whitelist_expiration(
+        &self,
+        service_id: &Bytes32,
+        user: &str,
+        expiration_timestamp: u64,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "set_whitelist_expiration",
+            json!({
+                "service_id": service_id,
+                "user": user,
+                "expiration_timestamp": expiration_timestamp,
+            }),
+        )
+    }
+
+    /// Sets the whitelist expiration with a permit the setter signed with
+    /// its ed25519 `setter_key`. The contract fills in its own `chain_id` and
+    /// `contract`, so the permit must name them for the signature to verify.
+    pub fn set_whitelist_expiration_with_permit(
+        &self,
+        permit: &WhitelistPermit,
+        setter_key: &[u8],
+        signature: &[u8],
+    ) -> Result<(), ClientError> {
+        self.call(
+            "set_whitelist_expiration_with_permit",
+            json!({
+                "service_id": permit.service_id,
+                "user": String::from_utf8_lossy(&permit.user),
+                "expiration_timestamp": permit.expiration_timestamp.as_secs(),
+                "nonce": permit.nonce,
+                "deadline": permit.deadline.as_secs(),
+                "setter_key": setter_key,
+                "signature": signature,
+            }),
+        )
+    }
+
+    pub fn whitelist_permit_nonce(&self, setter: &str) -> Result<u64, ClientError> {
+        self.view("whitelist_permit_nonce", json!({ "setter": setter }))
+    }
+
+    pub fn whitelist_permit_domain_separator(&self) -> Result<Bytes32, ClientError> {
+        self.view("whitelist_permit_domain_separator", json!({}))
+    }
+
+    /// Sets the indefinite whitelist status of the user, returns the number
+    /// of setters that indefinitely whitelist it
+    pub fn set_indefinite_whitelist_status(
+        &self,
+        service_id: &Bytes32,
+        user: &str,
+        status: bool,
+    ) -> Result<U256, ClientError> {
+        let count: Bytes32 = self.call(
+            "set_indefinite_whitelist_status",
+            json!({ "service_id": service_id, "user": user, "status": status }),
+        )?;
+        Ok(U256::from_big_endian(&count))
+    }
+
+    /// Revokes the indefinite whitelist status the setter gave, returns if
+    /// it was revoked and the number of setters that still indefinitely whitelist the user
+    pub fn revoke_indefinite_whitelist_status(
+        &self,
+        service_id: &Bytes32,
+        user: &str,
+        setter: &str,
+    ) -> Result<(bool, U256), ClientError> {
+        let (revoked, count): (bool, Bytes32) = self.call(
+            "revoke_indefinite_whitelist_status",
+            json!({ "service_id": service_id, "user": user, "setter": setter }),
+        )?;
+        Ok((revoked, U256::from_big_endian(&count)))
+    }
+
+    pub fn set_whitelist_merkle_root(
+        &self,
+        service_id: &Bytes32,
+        root: &Bytes32,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "set_whitelist_merkle_root",
+            json!({ "service_id": service_id, "root": root }),
+        )
+    }
+
+    pub fn whitelist_merkle_root(
+        &self,
+        service_id: &Bytes32,
+    ) -> Result<Option<Bytes32>, ClientError> {
+        self.view("whitelist_merkle_root", json!({ "service_id": service_id }))
+    }
+
+    pub fn whitelisted_user_count(&self, service_id: &Bytes32) -> Result<u64, ClientError> {
+        self.view(
+            "whitelisted_user_count",
+            json!({ "service_id": service_id }),
+        )
+    }
+
+    /// Returns up to `limit` users of the service starting at `from_index`
+    pub fn whitelisted_users_of(
+        &self,
+        service_id: &Bytes32,
+        from_index: u64,
+        limit: u64,
+    ) -> Result<Vec<String>, ClientError> {
+        self.view(
+            "whitelisted_users_of",
+            json!({ "service_id": service_id, "from_index": from_index, "limit": limit }),
+        )
+    }
+
+    pub fn indefinite_whitelist_setters_of(
+        &self,
+        service_id: &Bytes32,
+        user: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        self.view(
+            "indefinite_whitelist_setters_of",
+            json!({ "service_id": service_id, "user": user }),
+        )
+    }
+
+    /// Checks up to `limit` users of the service starting at `from_index` and
+    /// removes the expired whitelist entries among them, returns the number removed
+    pub fn sweep_expired(
+        &self,
+        service_id: &Bytes32,
+        from_index: u64,
+        limit: u64,
+    ) -> Result<u64, ClientError> {
+        self.call(
+            "sweep_expired",
+            json!({ "service_id": service_id, "from_index": from_index, "limit": limit }),
+        )
+    }
+
+    // ================== Usage Metering ====================
+    pub fn set_usage_epoch_length(&self, epoch_length: u64) -> Result<(), ClientError> {
+        self.call(
+            "set_usage_epoch_length",
+            json!({ "epoch_length": epoch_length }),
+        )
+    }
+
+    pub fn usage_epoch_length(&self) -> Result<u64, ClientError> {
+        self.view("usage_epoch_length", json!({}))
+    }
+
+    /// Returns the current usage epoch, `None` while metering is disabled
+    pub fn current_usage_epoch(&self) -> Result<Option<u64>, ClientError> {
+        self.view("current_usage_epoch", json!({}))
+    }
+
+    /// Sets the reads per epoch of the tier, `None` removes the quota
+    pub fn set_usage_quota(&self, tier: ReaderTier, quota: Option<u64>) -> Result<(), ClientError> {
+        self.call(
+            "set_usage_quota",
+            json!({ "tier": u8::from(tier), "quota": quota }),
+        )
+    }
+
+    pub fn usage_quota(&self, tier: ReaderTier) -> Result<Option<u64>, ClientError> {
+        self.view("usage_quota", json!({ "tier": u8::from(tier) }))
+    }
+
+    pub fn read_count(
+        &self,
+        data_point_id: &Bytes32,
+        reader: &str,
+        epoch: u64,
+    ) -> Result<u64, ClientError> {
+        self.view(
+            "read_count",
+            json!({ "data_point_id": data_point_id, "reader": reader, "epoch": epoch }),
+        )
+    }
+
+    pub fn prune_read_counts(
+        &self,
+        data_point_id: &Bytes32,
+        readers: &[&str],
+        epoch: u64,
+    ) -> Result<(), ClientError> {
+        self.call(
+            "prune_read_counts",
+            json!({ "data_point_id": data_point_id, "readers": readers, "epoch": epoch }),
+        )
+    }
+
+    // ================== Subscriptions ====================
+    pub fn set_subscription_price(&self, price: &SubscriptionPrice) -> Result<(), ClientError> {
+        self.call(
+            "set_subscription_price",
+            json!({
+                "amount_per_period": price.amount_per_period.to_string(),
+                "period": price.period,
+            }),
+        )
+    }
+
+    pub fn subscription_price(&self) -> Result<SubscriptionPrice, ClientError> {
+        let method = "subscription_price";
+        let (amount, period): (String, u64) = self.view(method, json!({}))?;
+        Ok(SubscriptionPrice::new(parse_u128(method, &amount)?, period))
+    }
+
+    /// Returns the subscription payments not withdrawn yet, in yoctoNEAR
+    pub fn subscription_balance(&self) -> Result<u128, ClientError> {
+        let method = "subscription_balance";
+        let balance: String = self.view(method, json!({}))?;
+        parse_u128(method, &balance)
+    }
+
+    /// Subscribes to the data feed for `periods`, paying with `deposit`
+    /// yoctoNEAR, the change is refunded. Returns when the subscription expires.
+    pub fn subscribe(
+        &self,
+        data_feed_id: &Bytes32,
+        periods: u64,
+        deposit: u128,
+    ) -> Result<u64, ClientError> {
+        let method = "subscribe";
+        let args = json!({ "data_feed_id": data_feed_id, "periods": periods });
+        decode(method, self.transport.call(method, args, deposit)?)
+    }
+
+    pub fn withdraw_subscription_balance(&self, amount: u128) -> Result<(), ClientError> {
+        self.call(
+            "withdraw_subscription_balance",
+            json!({ "amount": amount.to_string() }),
+        )
+    }
+
+    // ================== Migration ====================
+    /// Adds the `(role, account)` members granted before the upgrade to the
+    /// role index of a migrated deployment
+    pub fn reindex_role_members(&self, members: &[(Bytes32, &str)]) -> Result<(), ClientError> {
+        self.call("reindex_role_members", json!({ "members": members }))
+    }
+
+    /// Adds the `(service_id, user, setters)` users whitelisted before the
+    /// upgrade to the whitelist index of a migrated deployment
+    pub fn reindex_whitelist(
+        &self,
+        users: &[(Bytes32, &str, Vec<&str>)],
+    ) -> Result<(), ClientError> {
+        self.call("reindex_whitelist", json!({ "users": users }))
+    }
+
+    fn view<R: DeserializeOwned>(&self, method: &str, args: Value) -> Result<R, ClientError> {
+        decode(method, self.transport.view(method, args)?)
+    }
+
+    fn call<R: DeserializeOwned>(&self, method: &str, args: Value) -> Result<R, ClientError> {
+        decode(method, self.transport.call(method, args, 0)?)
+    }
+}
+
+impl BatchCall {
+    /// The `update_beacon_with_signed_data` call, for `DapiServer::batch`
+    pub fn update_beacon_with_signed_data(
+        airnode: &[u8],
+        template_id: &Bytes32,
+        timestamp: &Bytes32,
+        data: &[u8],
+        signature: &[u8],
+    ) -> Self {
+        Self {
+            method: String::from("update_beacon_with_signed_data"),
+            args: json!({
+                "airnode": airnode,
+                "template_id": template_id,
+                "timestamp": timestamp,
+                "data": data,
+                "signature": signature,
+            }),
+        }
+    }
+
+    /// The `update_dapi_with_signed_data` call, for `DapiServer::batch`
+    pub fn update_dapi_with_signed_data(
+        airnodes: &[Bytes],
+        template_ids: &[Bytes32],
+        timestamps: &[Bytes32],
+        data: &[Bytes],
+        signatures: &[Bytes],
+    ) -> Self {
+        Self {
+            method: String::from("update_dapi_with_signed_data"),
+            args: json!({
+                "airnodes": airnodes,
+                "template_ids": template_ids,
+                "timestamps": timestamps,
+                "data": data,
+                "signatures": signatures,
+            }),
+        }
+    }
+}
+
+fn decode<R: DeserializeOwned>(method: &str, result: Value) -> Result<R, ClientError> {
+    serde_json::from_value(result.clone())
+        .map_err(|e| ClientError::InvalidResult(format!("{} returned {}: {}", method, result, e)))
+}
+
+/// `U128` of near-sdk, a decimal string
+fn parse_u128(method: &str, amount: &str) -> Result<u128, ClientError> {
+    amount
+        .parse()
+        .map_err(|_| ClientError::InvalidResult(format!("{} returned {}", method, amount)))
+}
+
+fn pause_state(method: &str, state: u8) -> Result<PauseState, ClientError> {
+    PauseState::try_from(state)
+        .map_err(|_| ClientError::InvalidResult(format!("{} returned {}", method, state)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Call;
+    use crate::MockTransport;
+    use api3_common::Error;
+
+    fn int(value: i128) -> Bytes32 {
+        let mut raw = [if value < 0 { 0xff } else { 0 }; 32];
+        raw[16..].copy_from_slice(&value.to_be_bytes());
+        raw
+    }
+
+    #[test]
+    fn decodes_values() {
+        assert_eq!(decode_value(&int(123)), Some(123));
+        assert_eq!(decode_value(&int(-1)), Some(-1));
+        assert_eq!(decode_value(&int(i128::MIN)), Some(i128::MIN));
+        assert_eq!(decode_value(&int(i128::MAX)), Some(i128::MAX));
+
+        let mut too_large = int(i128::MAX);
+        too_large[15] = 1;
+        assert_eq!(decode_value(&too_large), None);
+        // the sign extension of i128::MIN is not that of a positive value
+        let mut too_small = int(i128::MIN);
+        too_small[..16].copy_from_slice(&[0; 16]);
+        assert_eq!(decode_value(&too_small), None);
+    }
+
+    #[test]
+    fn encodes_arguments() {
+        let client = DapiServer::new(MockTransport::new());
+        client.grant_role(&[1u8; 32], "reader.testnet").unwrap();
+        client
+            .update_dapi_with_signed_data(
+                &[vec![2u8; 20], vec![3u8; 20]],
+                &[[4u8; 32], [5u8; 32]],
+                &[int(1_650_000_000), int(1_650_000_001)],
+                &[int(-1).to_vec(), int(2).to_vec()],
+                &[vec![6u8; 65], vec![]],
+            )
+            .unwrap_err();
+        client.subscribe(&[7u8; 32], 3, 10).unwrap_err();
+        client
+            .reader_can_read_data_point(&[8u8; 32], "reader.testnet", None)
+            .unwrap_err();
+
+        let calls = client.transport().calls();
+        assert_eq!(
+            calls[0],
+            Call {
+                method: String::from("grant_role"),
+                args: json!({ "role": ([1u8; 32]), "who": "reader.testnet" }),
+                deposit: Some(0),
+            }
+        );
+        let args = &calls[1].args;
+        assert_eq!(args["airnodes"][1], json!(([3u8; 20])));
+        assert_eq!(args["template_ids"][0].as_array().unwrap().len(), 32);
+        assert_eq!(args["data"][0][31], json!(0xff));
+        assert_eq!(args["signatures"][1], json!([]));
+        assert_eq!(calls[2].deposit, Some(10));
+        assert_eq!(
+            calls[2].args,
+            json!({ "data_feed_id": ([7u8; 32]), "periods": 3 })
+        );
+        assert_eq!(calls[3].deposit, None);
+        assert_eq!(calls[3].args["proof"], Value::Null);
+    }
+
+    #[test]
+    fn decodes_results() {
+        let transport = MockTransport::new();
+        transport.respond(
+            "read_with_data_point_id",
+            json!([int(-5), 1_650_000_000u32]),
+        );
+        transport.respond("data_feed_pending_update", Value::Null);
+        transport.respond(
+            "subscription_price",
+            json!(["1000000000000000000000000", 86400]),
+        );
+        transport.respond(
+            "data_feed_id_to_whitelist_status",
+            json!([1_650_000_000u64, int(2)]),
+        );
+        transport.respond("revoke_indefinite_whitelist_status", json!([true, int(1)]));
+        transport.respond("pause_state", json!(2));
+        transport.respond("name_to_data_point_id", json!(([9u8; 32])));
+        let client = DapiServer::new(transport);
+
+        assert_eq!(
+            client
+                .read_with_data_point_id(&[1u8; 32], Some(&[[2u8; 32]]))
+                .unwrap(),
+            DataFeed {
+                value: -5,
+                timestamp: 1_650_000_000
+            }
+        );
+        assert_eq!(
+            client.transport().calls()[0].args["proof"],
+            json!([([2u8; 32])])
+        );
+        assert_eq!(client.data_feed_pending_update(&[1u8; 32]).unwrap(), None);
+        assert_eq!(
+            client.subscription_price().unwrap(),
+            SubscriptionPrice::new(10u128.pow(24), 86400)
+        );
+        let status = client
+            .data_feed_id_to_whitelist_status(&[1u8; 32], "reader.testnet")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            status.expiration_timestamp,
+            Timestamp::from_secs(1_650_000_000)
+        );
+        assert_eq!(status.indefinite_whitelist_count, U256::from(2));
+        assert_eq!(
+            client
+                .revoke_indefinite_whitelist_status(&[1u8; 32], "reader.testnet", "setter.testnet")
+                .unwrap(),
+            (true, U256::from(1))
+        );
+        assert_eq!(
+            client.pause_state().unwrap(),
+            PauseState::UpdatesAndReadsPaused
+        );
+        assert_eq!(
+            client.name_to_data_point_id(&[1u8; 32]).unwrap(),
+            Some([9u8; 32])
+        );
+        // nothing queued, the methods returned `null`
+        assert!(matches!(
+            client.read_with_name(&[1u8; 32], None),
+            Err(ClientError::InvalidResult(_))
+        ));
+        assert_eq!(client.name_to_data_point_id(&[1u8; 32]).unwrap(), None);
+    }
+
+    #[test]
+    fn maps_contract_panics() {
+        let transport = MockTransport::new();
+        transport.fail("read_with_data_point_id", &Error::AccessDenied);
+        transport.fail("update_dapi_with_beacons", &Error::UpdatedValueOutdated);
+        let client = DapiServer::new(transport);
+
+        assert!(matches!(
+            client.read_with_data_point_id(&[1u8; 32], None),
+            Err(ClientError::Api3(Error::AccessDenied))
+        ));
+        assert!(matches!(
+            client.update_dapi_with_beacons(&[[1u8; 32], [2u8; 32]]),
+            Err(ClientError::Api3(Error::UpdatedValueOutdated))
+        ));
+    }
+
+    #[test]
+    fn batches_calls() {
+        let transport = MockTransport::new();
+        transport.fail("update_dapi_with_signed_data", &Error::InvalidSignature);
+        let client = DapiServer::new(transport);
+        let beacon = BatchCall::update_beacon_with_signed_data(
+            &[2u8; 32],
+            &[4u8; 32],
+            &int(1_650_000_000),
+            &int(5),
+            &[6u8; 64],
+        );
+        let dapi = BatchCall::update_dapi_with_signed_data(
+            &[vec![2u8; 32], vec![3u8; 32]],
+            &[[4u8; 32], [5u8; 32]],
+            &[int(1_650_000_000), [0u8; 32]],
+            &[int(5).to_vec(), vec![]],
+            &[vec![6u8; 64], vec![]],
+        );
+        client.batch(&[]).unwrap();
+        assert!(matches!(
+            client.batch(&[beacon.clone(), dapi.clone(), beacon.clone()]),
+            Err(ClientError::Api3(Error::InvalidSignature))
+        ));
+
+        // the batch stops at the failed call, as the transaction does
+        let calls = client.transport().calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].method, beacon.method);
+        assert_eq!(calls[0].args["signature"], json!([6u8; 64].to_vec()));
+        assert_eq!(calls[1].args, dapi.args);
+        assert!(calls.iter().all(|c| c.deposit == Some(0)));
+    }
+}
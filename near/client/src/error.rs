@@ -0,0 +1,101 @@
+use api3_common::{Error, ErrorPayload};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request failed before it reached the contract
+    Transport(String),
+    /// The contract failed the call with an `api3_common::Error`
+    Api3(Error),
+    /// The call failed for another reason, e.g. it ran out of gas
+    Failure(String),
+    /// The contract returned a result of an unexpected shape
+    InvalidResult(String),
+    InvalidCredentials(String),
+}
+
+impl ClientError {
+    /// The error of a failed call, the `api3_common::Error` if the message
+    /// carries an `ErrorPayload`. The message can also be the JSON failure
+    /// of a transaction outcome, the panic message is nested in it.
+    pub fn from_failure(message: String) -> Self {
+        let error = ErrorPayload::parse(&message)
+            .and_then(|p| p.error())
+            .or_else(|| {
+                serde_json::from_str::<Value>(&message)
+                    .ok()
+                    .and_then(|failure| find_error(&failure))
+            });
+        match error {
+            Some(e) => ClientError::Api3(e),
+            None => ClientError::Failure(message),
+        }
+    }
+}
+
+fn find_error(value: &Value) -> Option<Error> {
+    match value {
+        Value::String(s) => ErrorPayload::parse(s).and_then(|p| p.error()),
+        Value::Array(values) => values.iter().find_map(find_error),
+        Value::Object(values) => values.values().find_map(find_error),
+        _ => None,
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "transport: {}", e),
+            ClientError::Api3(e) => write!(f, "{} ({})", e, e.name()),
+            ClientError::Failure(e) => write!(f, "call failed: {}", e),
+            ClientError::InvalidResult(e) => write!(f, "invalid result: {}", e),
+            ClientError::InvalidCredentials(e) => write!(f, "invalid credentials: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<Error> for ClientError {
+    fn from(e: Error) -> Self {
+        ClientError::Api3(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_panics_to_errors() {
+        let payload =
+            ErrorPayload::from(&Error::UpdatedValueOutdated).with_id("dapi_id", &[1u8; 2]);
+        let panic = format!("Smart contract panicked: {}", payload.to_json());
+        assert!(matches!(
+            ClientError::from_failure(panic.clone()),
+            ClientError::Api3(Error::UpdatedValueOutdated)
+        ));
+        let outcome = json!({
+            "ActionError": {
+                "index": 0,
+                "kind": { "FunctionCallError": { "ExecutionError": panic } }
+            }
+        });
+        assert!(matches!(
+            ClientError::from_failure(outcome.to_string()),
+            ClientError::Api3(Error::UpdatedValueOutdated)
+        ));
+
+        assert!(matches!(
+            ClientError::from_failure(String::from("Exceeded the prepaid gas.")),
+            ClientError::Failure(_)
+        ));
+        let unknown = ErrorPayload { code: 7, ..payload };
+        assert!(matches!(
+            ClientError::from_failure(unknown.to_json()),
+            ClientError::Failure(_)
+        ));
+    }
+}
@@ -0,0 +1,17 @@
+//! Rust client of the NEAR `DapiServer` contract, the counterpart of the
+//! JavaScript one in `client-test`. `DapiServer` builds the calls and decodes
+//! their results, a `Transport` makes them: `JsonRpcTransport` against a
+//! NEAR node or a `near-workspaces` sandbox, `MockTransport` in tests.
+
+mod client;
+mod error;
+pub mod mock;
+mod rpc;
+mod transaction;
+mod transport;
+
+pub use client::{decode_value, DapiServer, DataFeed};
+pub use error::ClientError;
+pub use mock::MockTransport;
+pub use rpc::JsonRpcTransport;
+pub use transport::{BatchCall, Transport};
@@ -0,0 +1,99 @@
+//! A transport that answers from memory, to test code using the client
+//! without a NEAR node
+
+use crate::{BatchCall, ClientError, Transport};
+use api3_common::{Error, ErrorPayload};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+/// A call the client made
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call {
+    pub method: String,
+    pub args: Value,
+    /// The attached deposit, `None` for view calls
+    pub deposit: Option<u128>,
+}
+
+/// Answers the calls with the results queued per method and records them.
+/// Calls without a queued result return `null`, as methods without a
+/// return value do.
+#[derive(Default)]
+pub struct MockTransport {
+    results: RefCell<HashMap<String, VecDeque<Result<Value, String>>>>,
+    calls: RefCell<Vec<Call>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers the next call of `method` with `result`
+    pub fn respond(&self, method: &str, result: Value) {
+        self.push(method, Ok(result));
+    }
+
+    /// Fails the next call of `method` with `error`, panicking the way the contract does
+    pub fn fail(&self, method: &str, error: &Error) {
+        let panic = format!(
+            "Smart contract panicked: {}",
+            ErrorPayload::from(error).to_json()
+        );
+        self.push(method, Err(panic));
+    }
+
+    /// The calls made so far, in order
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    fn push(&self, method: &str, result: Result<Value, String>) {
+        self.results
+            .borrow_mut()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(result);
+    }
+
+    fn answer(
+        &self,
+        method: &str,
+        args: Value,
+        deposit: Option<u128>,
+    ) -> Result<Value, ClientError> {
+        self.calls.borrow_mut().push(Call {
+            method: method.to_string(),
+            args,
+            deposit,
+        });
+        let result = self
+            .results
+            .borrow_mut()
+            .get_mut(method)
+            .and_then(VecDeque::pop_front);
+        match result {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(message)) => Err(ClientError::from_failure(message)),
+            None => Ok(Value::Null),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn view(&self, method: &str, args: Value) -> Result<Value, ClientError> {
+        self.answer(method, args, None)
+    }
+
+    fn call(&self, method: &str, args: Value, deposit: u128) -> Result<Value, ClientError> {
+        self.answer(method, args, Some(deposit))
+    }
+
+    /// Answers the calls in order, the first failure fails the batch
+    fn call_batch(&self, calls: &[BatchCall]) -> Result<(), ClientError> {
+        calls
+            .iter()
+            .try_for_each(|c| self.answer(&c.method, c.args.clone(), Some(0)).map(|_| ()))
+    }
+}
@@ -0,0 +1,231 @@
+use crate::transaction::{
+    parse_secret_key, sign_transaction, Ed25519Key, FunctionCall, Transaction,
+};
+use crate::{BatchCall, ClientError, Transport};
+use api3_common::{Bytes, Bytes32};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Gas attached to the change calls, the maximum a transaction can have.
+/// The calls of a batch share it.
+const GAS: u64 = 300_000_000_000_000;
+
+#[derive(Deserialize)]
+struct Credentials {
+    account_id: String,
+    #[serde(alias = "secret_key")]
+    private_key: String,
+}
+
+/// Calls the contract over the NEAR JSON-RPC API. Change calls are function
+/// call transactions signed with a full access key of the account.
+///
+/// A `near-workspaces` sandbox serves the same API, use its RPC address and
+/// the secret key of a sandbox account.
+pub struct JsonRpcTransport {
+    url: String,
+    contract_id: String,
+    account_id: String,
+    keypair: ed25519_dalek::Keypair,
+}
+
+impl JsonRpcTransport {
+    /// `secret_key` is the key of `account_id` as NEAR prints them, `ed25519:`
+    /// and the base58 key
+    pub fn new(
+        url: String,
+        contract_id: String,
+        account_id: String,
+        secret_key: &str,
+    ) -> Result<Self, ClientError> {
+        let keypair = parse_secret_key(secret_key).map_err(ClientError::InvalidCredentials)?;
+        Ok(Self {
+            url,
+            contract_id,
+            account_id,
+            keypair,
+        })
+    }
+
+    /// Signs with the account of the key file `near login` writes, e.g.
+    /// `~/.near-credentials/testnet/relayer.testnet.json`
+    pub fn from_credentials(
+        url: String,
+        contract_id: String,
+        credentials: &Path,
+    ) -> Result<Self, ClientError> {
+        let invalid = |e: String| {
+            ClientError::InvalidCredentials(format!("{}: {}", credentials.display(), e))
+        };
+        let json = std::fs::read_to_string(credentials).map_err(|e| invalid(e.to_string()))?;
+        let credentials: Credentials =
+            serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))?;
+        Ok(Self {
+            url,
+            contract_id,
+            account_id: credentials.account_id,
+            keypair: parse_secret_key(&credentials.private_key).map_err(invalid)?,
+        })
+    }
+
+    /// The keys and values of the contract state starting with `prefix`, the
+    /// node must serve `view_state` for the contract
+    pub fn view_state(&self, prefix: &[u8]) -> Result<Vec<(Bytes, Bytes)>, ClientError> {
+        let state = self.query(json!({
+            "request_type": "view_state",
+            "finality": "final",
+            "account_id": self.contract_id,
+            "prefix_base64": base64::encode(prefix),
+        }))?;
+        let decode = |v: &Value| v.as_str().and_then(|v| base64::decode(v).ok());
+        state["values"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|v| match (decode(&v["key"]), decode(&v["value"])) {
+                (Some(key), Some(value)) => Ok((key, value)),
+                _ => Err(ClientError::Transport(format!("invalid state entry {}", v))),
+            })
+            .collect()
+    }
+
+    /// Calls `method` of the node, returns the `result` of the response or
+    /// its `error` if the node answered with one
+    fn rpc(&self, method: &str, params: Value) -> Result<Result<Value, Value>, ClientError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "api3-near-client",
+            "method": method,
+            "params": params,
+        });
+        let response: Value = match ureq::post(&self.url).send_json(request) {
+            Ok(r) => r.into_json(),
+            // nodes also answer errors with a JSON-RPC error in the body
+            Err(ureq::Error::Status(_, r)) => r.into_json(),
+            Err(e) => return Err(ClientError::Transport(format!("{}: {}", self.url, e))),
+        }
+        .map_err(|e| ClientError::Transport(format!("{}: {}", self.url, e)))?;
+
+        match (response.get("result"), response.get("error")) {
+            (_, Some(error)) => Ok(Err(error.clone())),
+            (Some(result), None) => Ok(Ok(result.clone())),
+            (None, None) => Err(ClientError::Transport(format!(
+                "{}: no result in {}",
+                self.url, response
+            ))),
+        }
+    }
+
+    /// A `query` of the node state, a JSON-RPC error fails the call
+    fn query(&self, params: Value) -> Result<Value, ClientError> {
+        self.rpc("query", params)?
+            .map_err(|e| ClientError::Transport(format!("query failed: {}", e)))
+    }
+
+    /// Signs and sends a transaction with the function calls to the contract,
+    /// returns the result of the last one once the transaction is final
+    fn send_transaction(&self, actions: Vec<FunctionCall>) -> Result<Bytes, ClientError> {
+        let public_key = format!(
+            "ed25519:{}",
+            bs58::encode(self.keypair.public.as_bytes()).into_string()
+        );
+        let access_key = self.query(json!({
+            "request_type": "view_access_key",
+            "finality": "final",
+            "account_id": self.account_id,
+            "public_key": public_key,
+        }))?;
+        let nonce = access_key["nonce"]
+            .as_u64()
+            .ok_or_else(|| ClientError::Transport(format!("no nonce in {}", access_key)))?;
+        let block_hash = access_key["block_hash"]
+            .as_str()
+            .and_then(|h| bs58::decode(h).into_vec().ok())
+            .and_then(|h| Bytes32::try_from(h).ok())
+            .ok_or_else(|| ClientError::Transport(format!("no block hash in {}", access_key)))?;
+
+        let transaction = Transaction {
+            signer_id: self.account_id.clone(),
+            public_key: Ed25519Key(self.keypair.public.to_bytes()),
+            nonce: nonce + 1,
+            receiver_id: self.contract_id.clone(),
+            block_hash,
+            actions,
+        };
+        let signed = sign_transaction(&self.keypair, transaction);
+        let outcome = self
+            .rpc("broadcast_tx_commit", json!([base64::encode(signed)]))?
+            .map_err(|e| ClientError::from_failure(e.to_string()))?;
+        let status = &outcome["status"];
+        if let Some(failure) = status.get("Failure") {
+            return Err(ClientError::from_failure(failure.to_string()));
+        }
+        Ok(status["SuccessValue"]
+            .as_str()
+            .and_then(|v| base64::decode(v).ok())
+            .unwrap_or_default())
+    }
+}
+
+impl Transport for JsonRpcTransport {
+    fn view(&self, method: &str, args: Value) -> Result<Value, ClientError> {
+        let outcome = self
+            .rpc(
+                "query",
+                json!({
+                    "request_type": "call_function",
+                    "finality": "final",
+                    "account_id": self.contract_id,
+                    "method_name": method,
+                    "args_base64": base64::encode(args.to_string()),
+                }),
+            )?
+            .map_err(|e| ClientError::from_failure(e.to_string()))?;
+        // older nodes answer failed views with an `error` in the result
+        if let Some(error) = outcome.get("error") {
+            return Err(ClientError::from_failure(error.to_string()));
+        }
+        let result: Bytes = serde_json::from_value(outcome["result"].clone())
+            .map_err(|e| ClientError::Transport(format!("{}: {}", method, e)))?;
+        decode_result(method, &result)
+    }
+
+    fn call(&self, method: &str, args: Value, deposit: u128) -> Result<Value, ClientError> {
+        let result = self.send_transaction(vec![FunctionCall {
+            method_name: method.to_string(),
+            args: args.to_string().into_bytes(),
+            gas: GAS,
+            deposit,
+        }])?;
+        decode_result(method, &result)
+    }
+
+    fn call_batch(&self, calls: &[BatchCall]) -> Result<(), ClientError> {
+        if calls.is_empty() {
+            return Ok(());
+        }
+        let gas = GAS / calls.len() as u64;
+        let actions = calls
+            .iter()
+            .map(|c| FunctionCall {
+                method_name: c.method.clone(),
+                args: c.args.to_string().into_bytes(),
+                gas,
+                deposit: 0,
+            })
+            .collect();
+        self.send_transaction(actions)?;
+        Ok(())
+    }
+}
+
+/// The JSON a method returned, methods without a return value return nothing
+fn decode_result(method: &str, result: &[u8]) -> Result<Value, ClientError> {
+    if result.is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(result)
+        .map_err(|e| ClientError::InvalidResult(format!("{}: {}", method, e)))
+}
@@ -0,0 +1,141 @@
+//! The borsh layout of nearcore transactions, for function calls only, so
+//! that the client does not need `near-primitives`
+
+use api3_common::{Bytes, Bytes32};
+use borsh::BorshSerialize;
+use ed25519_dalek::Signer;
+use sha2::{Digest, Sha256};
+use std::io::{Result as IoResult, Write};
+
+/// `Transaction` of nearcore with function calls only, serializes the same
+#[derive(BorshSerialize)]
+pub(crate) struct Transaction {
+    pub signer_id: String,
+    pub public_key: Ed25519Key,
+    pub nonce: u64,
+    pub receiver_id: String,
+    pub block_hash: Bytes32,
+    pub actions: Vec<FunctionCall>,
+}
+
+/// The ed25519 variant of `PublicKey`
+pub(crate) struct Ed25519Key(pub [u8; 32]);
+
+impl BorshSerialize for Ed25519Key {
+    fn serialize<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        writer.write_all(&[0])?;
+        writer.write_all(&self.0)
+    }
+}
+
+/// The `FunctionCall` variant of `Action`
+pub(crate) struct FunctionCall {
+    pub method_name: String,
+    pub args: Bytes,
+    pub gas: u64,
+    pub deposit: u128,
+}
+
+impl BorshSerialize for FunctionCall {
+    fn serialize<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        writer.write_all(&[2])?;
+        self.method_name.serialize(writer)?;
+        self.args.serialize(writer)?;
+        self.gas.serialize(writer)?;
+        self.deposit.serialize(writer)
+    }
+}
+
+/// Serializes the `SignedTransaction`, the signature is over the hash of the transaction
+pub(crate) fn sign_transaction(
+    keypair: &ed25519_dalek::Keypair,
+    transaction: Transaction,
+) -> Bytes {
+    let mut signed = transaction
+        .try_to_vec()
+        .expect("serializing to memory does not fail");
+    let signature = keypair.sign(&Sha256::digest(&signed));
+    // the ed25519 variant of `Signature`
+    signed.push(0);
+    signed.extend_from_slice(&signature.to_bytes());
+    signed
+}
+
+/// Parses a NEAR secret key, `ed25519:` and the base58 secret key, with or without the public key
+pub(crate) fn parse_secret_key(key: &str) -> Result<ed25519_dalek::Keypair, String> {
+    let key = key
+        .strip_prefix("ed25519:")
+        .ok_or_else(|| String::from("only ed25519 keys are supported"))?;
+    let bytes = bs58::decode(key).into_vec().map_err(|e| e.to_string())?;
+    let secret = ed25519_dalek::SecretKey::from_bytes(&bytes[..bytes.len().min(32)])
+        .map_err(|e| e.to_string())?;
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    Ok(ed25519_dalek::Keypair { secret, public })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Verifier;
+
+    fn keypair() -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1u8; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    #[test]
+    fn parses_secret_keys() {
+        let keypair = keypair();
+        let encoded = format!("ed25519:{}", bs58::encode(keypair.to_bytes()).into_string());
+        assert_eq!(parse_secret_key(&encoded).unwrap().public, keypair.public);
+        let secret_only = format!("ed25519:{}", bs58::encode([1u8; 32]).into_string());
+        assert_eq!(
+            parse_secret_key(&secret_only).unwrap().public,
+            keypair.public
+        );
+
+        assert!(parse_secret_key(&secret_only.replace("ed25519", "secp256k1")).is_err());
+        assert!(parse_secret_key("ed25519:0OIl").is_err());
+    }
+
+    #[test]
+    fn signs_transactions() {
+        let keypair = keypair();
+        let transaction = Transaction {
+            signer_id: String::from("relayer.testnet"),
+            public_key: Ed25519Key(keypair.public.to_bytes()),
+            nonce: 2,
+            receiver_id: String::from("api3.testnet"),
+            block_hash: [3u8; 32],
+            actions: vec![FunctionCall {
+                method_name: String::from("update_dapi_with_beacons"),
+                args: b"{}".to_vec(),
+                gas: 300_000_000_000_000,
+                deposit: 5,
+            }],
+        };
+        let unsigned = transaction.try_to_vec().unwrap();
+        let signed = sign_transaction(&keypair, transaction);
+
+        // `relayer.testnet` as a borsh string, then the ed25519 key
+        assert_eq!(unsigned[..4], 15u32.to_le_bytes());
+        assert_eq!(&unsigned[4..19], b"relayer.testnet");
+        assert_eq!(unsigned[19], 0);
+        assert_eq!(unsigned[20..52], keypair.public.to_bytes());
+        assert_eq!(unsigned[52..60], 2u64.to_le_bytes());
+        // one action, the function call, with the deposit last
+        let actions = 60 + 4 + 12 + 32;
+        assert_eq!(unsigned[actions..actions + 5], [1, 0, 0, 0, 2]);
+        assert_eq!(unsigned.len(), actions + 5 + 4 + 24 + 4 + 2 + 8 + 16);
+        assert_eq!(unsigned[unsigned.len() - 16..], 5u128.to_le_bytes());
+
+        assert_eq!(signed[..unsigned.len()], unsigned);
+        assert_eq!(signed[unsigned.len()], 0);
+        let signature = ed25519_dalek::Signature::try_from(&signed[unsigned.len() + 1..]).unwrap();
+        assert!(keypair
+            .public
+            .verify(&Sha256::digest(&unsigned), &signature)
+            .is_ok());
+    }
+}
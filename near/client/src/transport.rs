@@ -0,0 +1,25 @@
+use crate::ClientError;
+use serde_json::Value;
+
+/// How the client reaches the contract. `JsonRpcTransport` talks to a NEAR
+/// node, e.g. of testnet or a `near-workspaces` sandbox, `MockTransport`
+/// answers from memory.
+pub trait Transport {
+    /// Calls the view method `method` with the JSON `args`, returns its JSON result
+    fn view(&self, method: &str, args: Value) -> Result<Value, ClientError>;
+
+    /// Calls the change method `method` in a transaction with `deposit`
+    /// yoctoNEAR attached, returns its JSON result
+    fn call(&self, method: &str, args: Value, deposit: u128) -> Result<Value, ClientError>;
+
+    /// Calls the change methods in one transaction, in order and without a
+    /// deposit. The transaction fails as a whole if one of them fails.
+    fn call_batch(&self, calls: &[BatchCall]) -> Result<(), ClientError>;
+}
+
+/// A change method call of `Transport::call_batch`, the method and its JSON args
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchCall {
+    pub method: String,
+    pub args: Value,
+}
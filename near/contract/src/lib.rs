@@ -1,21 +1,26 @@
+mod migration;
 mod types;
 mod utils;
 mod whitelist;
 
-use crate::types::{Address, NearDataPoint};
+use crate::types::{Address, NearDataPoint, NearFeedStatus};
 use crate::utils::{
-    msg_sender, Bytes32HashMap, DatapointHashMap, NearAccessControlRegistry, NearClock,
-    SignatureVerify,
+    msg_sender, near_fail, permit_domain, Bytes32HashMap, DatapointHashMap, FeedStatusHashMap,
+    NearAccessControlRegistry, NearClock, RoleMembership, SignatureVerify, U64HashMap,
 };
-use crate::whitelist::{NearWhitelist, WhitelistStatus};
+use crate::whitelist::{NearWhitelist, NearWhitelistStatus, WhitelistIndex};
 use api3_common::abi::{Token, Uint};
 use api3_common::{
-    keccak_packed, process_beacon_update, AccessControlRegistry, Bytes, Bytes32, Error,
-    SignatureManger, StaticRole, WhitelistRolesWithManager, WhitelistWithManager,
+    keccak_packed, process_beacon_update_with_circuit_breaker,
+    update_dapi_with_signed_data_with_circuit_breaker, AccessControlRegistry,
+    AccessControlRegistryEnumerable, AccessControlRegistryWithExpiration, Bytes, Bytes32,
+    DapiUpdateError, Error, ErrorPayload, FeedStatus, PauseState, ReaderTier, SignatureManger,
+    StaticRole, SubscriptionPrice, Timestamp, TimestampChecker, WhitelistEnumerable,
+    WhitelistPermit, WhitelistRolesWithManager, WhitelistWithManager,
 };
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{collections::LookupMap, near_bindgen};
-use std::fmt::Debug;
+use near_sdk::json_types::U128;
+use near_sdk::{collections::LookupMap, near_bindgen, Promise};
 
 near_sdk::setup_alloc!();
 
@@ -26,47 +31,102 @@ pub struct DapiServer {
     data_points: LookupMap<Bytes32, NearDataPoint>,
     name_hash_to_data_point_id: LookupMap<Bytes32, Bytes32>,
 
+    /// Circuit breaker related storage
+    data_feed_status: LookupMap<Bytes32, NearFeedStatus>,
+    circuit_breaker_threshold: u32,
+
+    /// Pause related storage
+    pause_state: u8,
+    data_feed_pause_state: LookupMap<Bytes32, u8>,
+
+    /// Subscription related storage, amounts are in yoctoNEAR
+    subscription_amount_per_period: u128,
+    subscription_period: u64,
+    subscription_balance: u128,
+
+    /// Usage metering related storage, metering is disabled while the epoch length is zero
+    usage_epoch_length: u64,
+    usage_quotas: LookupMap<u8, u64>,
+    read_counts: LookupMap<Bytes32, u64>,
+
     /// Access control related storage
     manager: Address,
+    pending_manager: Option<Address>,
+    previous_manager: Option<Address>,
     admin_role_description: String,
-    role_membership: LookupMap<Bytes32, bool>,
+    role_membership: RoleMembership,
     role_admin: LookupMap<Bytes32, Bytes32>,
 
-    service_id_to_user_to_whitelist_status: LookupMap<Bytes32, WhitelistStatus>,
+    service_id_to_user_to_whitelist_status: LookupMap<Bytes32, NearWhitelistStatus>,
     service_id_to_user_to_setter_to_indefinite_whitelist_status: LookupMap<Bytes32, bool>,
+    whitelist_index: WhitelistIndex,
+    service_id_to_whitelist_merkle_root: LookupMap<Bytes32, Bytes32>,
+    whitelist_permit_nonces: LookupMap<Bytes32, u64>,
 }
 
 impl Default for DapiServer {
     fn default() -> Self {
-        let data_points = LookupMap::new(b'd');
-        let name_hash_to_data_point_id = LookupMap::new(b'n');
-
-        let mut role_membership = LookupMap::new(b'm');
-        let mut role_admin = LookupMap::new(b'a');
-
-        let service_id_to_user_to_whitelist_status = LookupMap::new(b's');
-        let service_id_to_user_to_setter_to_indefinite_whitelist_status = LookupMap::new(b'b');
-
         let manager = msg_sender();
-        let admin_role_description = String::from("admin role");
+        let mut contract = Self::with_manager(manager.clone(), String::from("admin role"));
         let mut access = NearAccessControlRegistry::requires_write(
             manager.clone(),
-            admin_role_description.clone(),
-            &mut role_membership,
-            &mut role_admin,
+            contract.admin_role_description.clone(),
+            &mut contract.role_membership,
+            &mut contract.role_admin,
         );
         access
             .grant_role(&NearAccessControlRegistry::DEFAULT_ADMIN_ROLE, &manager)
             .expect("initialization failed");
+        contract
+    }
+}
+
+impl DapiServer {
+    /// The contract state with the storage of each field at its prefix, the
+    /// manager is not granted any role
+    fn with_manager(manager: Address, admin_role_description: String) -> Self {
+        let data_points = LookupMap::new(b'd');
+        let name_hash_to_data_point_id = LookupMap::new(b'n');
+
+        let data_feed_status = LookupMap::new(b'f');
+        let data_feed_pause_state = LookupMap::new(b'p');
+
+        let usage_quotas = LookupMap::new(b'u');
+        let read_counts = LookupMap::new(b'v');
+
+        let role_membership = RoleMembership::new();
+        let role_admin = LookupMap::new(b'a');
+
+        let service_id_to_user_to_whitelist_status = LookupMap::new(b's');
+        let service_id_to_user_to_setter_to_indefinite_whitelist_status = LookupMap::new(b'b');
+        let whitelist_index = WhitelistIndex::new();
+        let service_id_to_whitelist_merkle_root = LookupMap::new(b'w');
+        let whitelist_permit_nonces = LookupMap::new(b'c');
+
         Self {
             data_points,
             name_hash_to_data_point_id,
+            data_feed_status,
+            circuit_breaker_threshold: 0,
+            pause_state: PauseState::Unpaused.into(),
+            data_feed_pause_state,
+            subscription_amount_per_period: 0,
+            subscription_period: 0,
+            subscription_balance: 0,
+            usage_epoch_length: 0,
+            usage_quotas,
+            read_counts,
             manager,
+            pending_manager: None,
+            previous_manager: None,
             admin_role_description,
             role_membership,
             role_admin,
             service_id_to_user_to_whitelist_status,
             service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            whitelist_index,
+            service_id_to_whitelist_merkle_root,
+            whitelist_permit_nonces,
         }
     }
 }
@@ -144,6 +204,48 @@ impl DapiServer {
         near_check_result(r)
     }
 
+    /// Grants `role` to `who` until `expiration_timestamp` (in seconds), after
+    /// which `who` loses the role automatically
+    pub fn grant_role_until(&mut self, role: Bytes32, who: String, expiration_timestamp: u64) {
+        let mut access = NearAccessControlRegistry::requires_write(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &mut self.role_membership,
+            &mut self.role_admin,
+        );
+
+        ensure!(
+            access
+                .only_role(
+                    &NearAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                    &msg_sender()
+                )
+                .is_ok(),
+            Error::NotAuthorized
+        );
+
+        let r = access.grant_role_until(
+            &role,
+            &Address(who.as_bytes().to_vec()),
+            Timestamp::from_secs(expiration_timestamp),
+        );
+        near_check_result(r)
+    }
+
+    /// Returns the timestamp (in seconds) the grant of `role` to `who`
+    /// expires at, `None` if the grant does not expire
+    pub fn role_expiration(&self, role: Bytes32, who: String) -> Option<u64> {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        access
+            .role_expiration(&role, &Address(who.as_bytes().to_vec()))
+            .map(|t| t.as_secs())
+    }
+
     /// Checks if `who` has `role`
     pub fn has_role(&self, role: Bytes32, who: String) -> bool {
         let access = NearAccessControlRegistry::read_only(
@@ -155,6 +257,190 @@ impl DapiServer {
         access.has_role(&role, &Address(who.as_bytes().to_vec()))
     }
 
+    /// Returns the number of accounts that have `role`
+    pub fn get_role_member_count(&self, role: Bytes32) -> u64 {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        access.get_role_member_count(&role) as u64
+    }
+
+    /// Returns the member of `role` at `index`, members are not sorted and
+    /// their order may change when `role` is granted or revoked
+    pub fn get_role_member(&self, role: Bytes32, index: u64) -> Option<String> {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        access
+            .get_role_member(&role, index as usize)
+            .as_ref()
+            .map(address_to_string)
+    }
+
+    /// Returns the roles `account` has
+    pub fn roles_of(&self, account: String) -> Vec<Bytes32> {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        access.roles_of(&Address(account.as_bytes().to_vec()))
+    }
+
+    // ================== Manager Transfer ====================
+    /// Returns the manager of the contract
+    pub fn manager(&self) -> String {
+        address_to_string(&self.manager)
+    }
+
+    /// Returns the manager proposed with `propose_manager` that has not
+    /// accepted yet
+    pub fn pending_manager(&self) -> Option<String> {
+        self.pending_manager.as_ref().map(address_to_string)
+    }
+
+    /// Returns the manager before the last transfer, roles derived from it can
+    /// be moved to the current manager with `migrate_role_members`
+    pub fn previous_manager(&self) -> Option<String> {
+        self.previous_manager.as_ref().map(address_to_string)
+    }
+
+    /// Proposes `new_manager` as the manager of the contract. Only the
+    /// manager can propose and the transfer is completed once `new_manager`
+    /// calls `accept_manager`.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_manager` The account proposed as the new manager
+    pub fn propose_manager(&mut self, new_manager: String) {
+        let r = api3_common::propose_manager(
+            &self.manager,
+            Address(new_manager.as_bytes().to_vec()),
+            &msg_sender(),
+        );
+        self.pending_manager = Some(near_check_result(r));
+    }
+
+    /// Called by the pending manager to complete the transfer. The default
+    /// admin role is moved from the previous manager to the new one.
+    pub fn accept_manager(&mut self) {
+        let new_manager = near_check_result(api3_common::accept_manager(
+            self.pending_manager.take(),
+            &msg_sender(),
+        ));
+        let previous_manager = std::mem::replace(&mut self.manager, new_manager.clone());
+
+        let mut access = NearAccessControlRegistry::requires_write(
+            new_manager.clone(),
+            self.admin_role_description.clone(),
+            &mut self.role_membership,
+            &mut self.role_admin,
+        );
+        near_check_result(access.initialize_manager(&new_manager));
+        near_check_result(
+            access.grant_role(&NearAccessControlRegistry::DEFAULT_ADMIN_ROLE, &new_manager),
+        );
+        near_check_result(access.revoke_role(
+            &NearAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+            &previous_manager,
+        ));
+        self.previous_manager = Some(previous_manager);
+    }
+
+    /// Moves `members` of the role with `description` derived from the previous
+    /// manager to the same role derived from the current manager. Only the
+    /// manager can migrate roles. Returns the migrated role.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` Description of the role, e.g. `Pauser`
+    /// * `members` Accounts to migrate, non-members of the previous role are skipped
+    pub fn migrate_role_members(&mut self, description: String, members: Vec<String>) -> Bytes32 {
+        let previous_manager = match &self.previous_manager {
+            Some(m) => m.clone(),
+            None => near_fail(ErrorPayload::from(&Error::PreviousManagerNotFound)),
+        };
+        let mut access = NearAccessControlRegistry::requires_write(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &mut self.role_membership,
+            &mut self.role_admin,
+        );
+        let members = members
+            .iter()
+            .map(|m| Address(m.as_bytes().to_vec()))
+            .collect::<Vec<_>>();
+        let r =
+            access.migrate_role_members(&previous_manager, description, &members, &msg_sender());
+        near_check_result(r)
+    }
+
+    // ================== Pause ====================
+    /// Returns the pauser role
+    pub fn pauser_role(&self) -> Bytes32 {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        access.find_static_role(StaticRole::PauserRole)
+    }
+
+    /// Returns the pause state of the contract. `0` is unpaused, `1` blocks
+    /// updates and `2` blocks both updates and reads
+    pub fn pause_state(&self) -> u8 {
+        self.pause_state
+    }
+
+    /// Returns the pause state of the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pause_state(&self, data_feed_id: Bytes32) -> u8 {
+        self.data_feed_pause_state
+            .get(&data_feed_id)
+            .unwrap_or_else(|| PauseState::Unpaused.into())
+    }
+
+    /// Sets the pause state of the contract if the sender is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_pause_state(&mut self, state: u8) {
+        let state = near_check_result(PauseState::try_from(state));
+        self.ensure_can_pause();
+        self.pause_state = state.into();
+    }
+
+    /// Sets the pause state of the data feed if the sender is the manager or
+    /// has the pauser role. Unpausing removes the entry to free its storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_data_feed_pause_state(&mut self, data_feed_id: Bytes32, state: u8) {
+        let state = near_check_result(PauseState::try_from(state));
+        self.ensure_can_pause();
+        match state {
+            PauseState::Unpaused => self.data_feed_pause_state.remove(&data_feed_id),
+            _ => self
+                .data_feed_pause_state
+                .insert(&data_feed_id, &u8::from(state)),
+        };
+    }
+
     // ================== Datapoint ====================
     /// Updates a Beacon using data signed by the respective Airnode,
     /// without requiring a request or subscription
@@ -174,8 +460,13 @@ impl DapiServer {
         data: Vec<u8>,
         signature: Vec<u8>,
     ) {
+        let beacon_id =
+            near_check_result(api3_common::derive_beacon_id(airnode.to_vec(), template_id));
+        self.ensure_updates_allowed(&beacon_id);
+
         // create the utility structs
         let mut storage = DatapointHashMap::requires_write(&mut self.data_points);
+        let mut status_storage = FeedStatusHashMap::requires_write(&mut self.data_feed_status);
 
         // perform signature verification
         let message = keccak_packed(&[
@@ -185,17 +476,20 @@ impl DapiServer {
         ]);
 
         if !SignatureVerify::verify(&airnode, &message, &signature) {
-            near_sdk::env::panic("InvalidSignature".as_ref());
+            near_fail(
+                ErrorPayload::from(&Error::InvalidSignature).with_id("beacon_id", &beacon_id),
+            );
         }
 
-        let beacon_id = api3_common::derive_beacon_id(airnode.to_vec(), template_id);
-        let r = process_beacon_update(
+        let r = process_beacon_update_with_circuit_breaker(
             &mut storage,
+            &mut status_storage,
+            self.circuit_breaker_threshold,
             beacon_id,
             Uint::from_big_endian(&timestamp),
             data,
         );
-        near_check_result(r)
+        near_check_result_with(r, |p| p.with_id("beacon_id", &beacon_id));
     }
 
     /// Updates the dAPI that is specified by the beacon IDs
@@ -204,14 +498,17 @@ impl DapiServer {
     ///
     /// * `beacon_ids` Beacon IDs
     pub fn update_dapi_with_beacons(&mut self, beacon_ids: Vec<Bytes32>) -> Bytes32 {
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids);
         let mut storage = DatapointHashMap::requires_write(&mut self.data_points);
         let r = api3_common::update_dapi_with_beacons(&mut storage, &beacon_ids);
-        near_check_result(r)
+        near_check_dapi_update(r, &dapi_id, &beacon_ids)
     }
 
     /// Updates a dAPI using data signed by the respective Airnodes
     /// without requiring a request or subscription. The beacons for which the
-    /// signature is omitted will be read from the storage.
+    /// signature is omitted will be read from the storage. Fails if a Beacon is
+    /// paused by the circuit breaker or its signed data exceeds the threshold.
     ///
     /// # Arguments
     ///
@@ -228,11 +525,24 @@ impl DapiServer {
         data: Vec<Bytes>,
         signatures: Vec<Bytes>,
     ) -> Bytes32 {
+        let beacon_ids = airnodes
+            .iter()
+            .zip(template_ids.iter())
+            .map(|(airnode, template_id)| {
+                near_check_result(api3_common::derive_beacon_id(airnode.clone(), *template_id))
+            })
+            .collect::<Vec<_>>();
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids);
+
         let mut storage = DatapointHashMap::requires_write(&mut self.data_points);
-        let clock = NearClock::new(nanoseconds_to_seconds(near_sdk::env::block_timestamp()));
+        let status_storage = FeedStatusHashMap::read_only(&self.data_feed_status);
+        let clock = NearClock::now();
 
-        let r = api3_common::update_dapi_with_signed_data::<_, SignatureVerify, _>(
+        let r = update_dapi_with_signed_data_with_circuit_breaker::<_, _, SignatureVerify, _>(
             &mut storage,
+            &status_storage,
+            self.circuit_breaker_threshold,
             &clock,
             airnodes,
             template_ids,
@@ -240,6 +550,84 @@ impl DapiServer {
             data,
             signatures,
         );
+        near_check_dapi_update(r, &dapi_id, &beacon_ids)
+    }
+
+    // ================== Circuit Breaker ====================
+    /// Sets the maximum deviation a Beacon update can have from the current value
+    /// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` The deviation threshold
+    pub fn set_circuit_breaker_threshold(&mut self, threshold: u32) {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+
+        ensure!(
+            access
+                .only_role(
+                    &NearAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                    &msg_sender()
+                )
+                .is_ok(),
+            Error::NotAuthorized
+        );
+
+        self.circuit_breaker_threshold = threshold;
+    }
+
+    /// Returns the circuit breaker deviation threshold
+    pub fn circuit_breaker_threshold(&self) -> u32 {
+        self.circuit_breaker_threshold
+    }
+
+    /// Returns the pending update of the data feed if it is paused by the circuit breaker
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pending_update(&self, data_feed_id: Bytes32) -> Option<(Bytes32, u32)> {
+        match self
+            .data_feed_status
+            .get(&data_feed_id)
+            .map(FeedStatus::from)
+        {
+            Some(FeedStatus::Paused(d)) => {
+                let mut v = [0u8; 32];
+                d.value.to_big_endian(&mut v);
+                Some((v, d.timestamp.as_secs_u32()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Releases the data feed paused by the circuit breaker, the pending
+    /// update is stored as the data feed value
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn release_data_feed(&mut self, data_feed_id: Bytes32) {
+        let mut storage = DatapointHashMap::requires_write(&mut self.data_points);
+        let mut status_storage = FeedStatusHashMap::requires_write(&mut self.data_feed_status);
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let r = api3_common::release_data_feed(
+            &mut storage,
+            &mut status_storage,
+            data_feed_id,
+            &msg_sender(),
+            &access,
+        );
         near_check_result(r)
     }
 
@@ -291,16 +679,26 @@ impl DapiServer {
     /// * `airnode` Airnode address
     /// * `template_id` Template ID
     pub fn derive_beacon_id(&self, airnode: Bytes, template_id: Bytes32) -> Bytes32 {
-        api3_common::derive_beacon_id(airnode, template_id)
+        near_check_result(api3_common::derive_beacon_id(airnode, template_id))
     }
 
     /// Reads the data point with ID
+    /// Reads must be sent as transactions, not view calls: the sender must be
+    /// whitelisted and, while usage metering is enabled, the read is counted,
+    /// see `set_usage_epoch_length`.
     ///
     /// # Arguments
     ///
     /// * `data_point_id` Data point ID
-    pub fn read_with_data_point_id(&self, data_point_id: Bytes32) -> (Bytes32, u32) {
+    /// * `proof` Optional Merkle proof of the sender in the whitelist tree of the data point
+    pub fn read_with_data_point_id(
+        &mut self,
+        data_point_id: Bytes32,
+        proof: Option<Vec<Bytes32>>,
+    ) -> (Bytes32, u32) {
+        self.ensure_reads_allowed(&data_point_id);
         let storage = DatapointHashMap::read_only(&self.data_points);
+        let status_storage = FeedStatusHashMap::read_only(&self.data_feed_status);
         let access = NearAccessControlRegistry::read_only(
             self.manager.clone(),
             self.admin_role_description.clone(),
@@ -311,33 +709,43 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
-        );
+            &self.whitelist_index,
+        )
+        .with_merkle_proof(&self.service_id_to_whitelist_merkle_root, proof.clone());
 
         let r = api3_common::read_with_data_point_id(
             &data_point_id,
             &msg_sender(),
             &storage,
+            &status_storage,
             &access,
             &whitelist,
         )
         .map(|(a, n)| {
             let mut v = [0u8; 32];
             a.to_big_endian(&mut v);
-            (v, n)
+            (v, n.as_secs_u32())
         });
-        near_check_result(r)
+        let r = near_check_result_with(r, |p| p.with_id("data_point_id", &data_point_id));
+        self.meter_read(&data_point_id, &data_point_id, proof);
+        r
     }
 
     /// Reads the data point with name
     /// The read data point may belong to a Beacon or dAPI. The reader
     /// must be whitelisted for the hash of the data point name.
+    /// Reads must be sent as transactions, see `read_with_data_point_id`.
     ///
     /// # Arguments
     ///
     /// * `name` Data point name
-    pub fn read_with_name(&self, name: Bytes32) -> (Bytes32, u32) {
+    /// * `proof` Optional Merkle proof of the sender in the whitelist tree of the name hash
+    pub fn read_with_name(&mut self, name: Bytes32, proof: Option<Vec<Bytes32>>) -> (Bytes32, u32) {
+        let data_point_id = self.name_to_data_point_id(name).unwrap_or_default();
+        self.ensure_reads_allowed(&data_point_id);
         let dp_s = DatapointHashMap::read_only(&self.data_points);
         let nh_s = Bytes32HashMap::read_only(&self.name_hash_to_data_point_id);
+        let fs_s = FeedStatusHashMap::read_only(&self.data_feed_status);
         let access = NearAccessControlRegistry::read_only(
             self.manager.clone(),
             self.admin_role_description.clone(),
@@ -348,14 +756,27 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
-        );
-        let r = api3_common::read_with_name(name, &msg_sender(), &dp_s, &nh_s, &access, &whitelist)
-            .map(|(a, n)| {
-                let mut v = [0u8; 32];
-                a.to_big_endian(&mut v);
-                (v, n)
-            });
-        near_check_result(r)
+            &self.whitelist_index,
+        )
+        .with_merkle_proof(&self.service_id_to_whitelist_merkle_root, proof.clone());
+        let r = api3_common::read_with_name(
+            name,
+            &msg_sender(),
+            &dp_s,
+            &nh_s,
+            &fs_s,
+            &access,
+            &whitelist,
+        )
+        .map(|(a, n)| {
+            let mut v = [0u8; 32];
+            a.to_big_endian(&mut v);
+            (v, n.as_secs_u32())
+        });
+        let r = near_check_result_with(r, |p| p.with_id("name", &name));
+        let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+        self.meter_read(&name_hash, &data_point_id, proof);
+        r
     }
 
     /// Returns if a reader can read the data point
@@ -364,7 +785,13 @@ impl DapiServer {
     ///
     /// * `data_point_id` Data point ID (or data point name hash)
     /// * `reader` Reader address as raw bytes
-    pub fn reader_can_read_data_point(&self, data_point_id: Bytes32, reader: String) -> bool {
+    /// * `proof` Optional Merkle proof of the reader in the whitelist tree of the data point
+    pub fn reader_can_read_data_point(
+        &self,
+        data_point_id: Bytes32,
+        reader: String,
+        proof: Option<Vec<Bytes32>>,
+    ) -> bool {
         let access = NearAccessControlRegistry::read_only(
             self.manager.clone(),
             self.admin_role_description.clone(),
@@ -375,7 +802,9 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
-        );
+            &self.whitelist_index,
+        )
+        .with_merkle_proof(&self.service_id_to_whitelist_merkle_root, proof);
         let reader = Address(reader.as_bytes().to_vec());
         api3_common::reader_can_read_data_point(&data_point_id, &reader, &access, &whitelist)
     }
@@ -396,6 +825,7 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
         );
         whitelist
             .data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
@@ -427,6 +857,7 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
         );
         whitelist.data_feed_id_to_whitelist_status(&data_feed_id, reader.as_bytes())
     }
@@ -439,7 +870,7 @@ impl DapiServer {
     ///
     /// * `service_id` Service ID
     /// * `user` User address
-    /// * `expiration_timestamp` Timestamp at which the temporary whitelist will expire
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
     pub fn extend_whitelist_expiration(
         &mut self,
         service_id: Bytes32,
@@ -456,11 +887,12 @@ impl DapiServer {
             &access,
             &mut self.service_id_to_user_to_whitelist_status,
             &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
         );
         whitelist.extend_whitelist_expiration(
             &service_id,
             &Address(user.as_bytes().to_vec()),
-            expiration_timestamp,
+            Timestamp::from_secs(expiration_timestamp),
         )
     }
 
@@ -472,7 +904,7 @@ impl DapiServer {
     ///
     /// * `service_id` Service ID
     /// * `user` User address
-    /// * `expiration_timestamp` Timestamp at which the temporary whitelist will expire
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
     pub fn set_whitelist_expiration(
         &mut self,
         service_id: Bytes32,
@@ -489,14 +921,91 @@ impl DapiServer {
             &access,
             &mut self.service_id_to_user_to_whitelist_status,
             &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
         );
         whitelist.set_whitelist_expiration(
             &service_id,
             &Address(user.as_bytes().to_vec()),
-            expiration_timestamp,
+            Timestamp::from_secs(expiration_timestamp),
         )
     }
 
+    /// Sets the expiration of the temporary whitelist of `user` with a permit
+    /// signed off-chain by an account with the whitelist expiration setter role.
+    /// Anyone can submit the permit. The setter is the implicit account of
+    /// `setter_key`, i.e. the hex encoded ed25519 public key, and signs
+    /// `WhitelistPermit::message`. Each permit nonce of a setter can only be used once.
+    /// The permit names this contract and the chain, see `whitelist_permit_domain_separator`.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    /// * `nonce` The next permit nonce of the setter, see `whitelist_permit_nonce`
+    /// * `deadline` Timestamp (in seconds) after which the permit cannot be used
+    /// * `setter_key` The ed25519 public key of the setter
+    /// * `signature` The signature of the setter
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_whitelist_expiration_with_permit(
+        &mut self,
+        service_id: Bytes32,
+        user: String,
+        expiration_timestamp: u64,
+        nonce: u64,
+        deadline: u64,
+        setter_key: Bytes,
+        signature: Bytes,
+    ) {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let mut whitelist = NearWhitelist::requires_write(
+            &access,
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
+        );
+        let (chain_id, contract) = permit_domain();
+        let permit = WhitelistPermit {
+            chain_id,
+            contract,
+            service_id,
+            user: user.into_bytes(),
+            expiration_timestamp: Timestamp::from_secs(expiration_timestamp),
+            nonce,
+            deadline: Timestamp::from_secs(deadline),
+        };
+        near_check_result(whitelist.set_whitelist_expiration_with_permit(
+            &mut self.whitelist_permit_nonces,
+            &permit,
+            &setter_key,
+            &signature,
+        ))
+    }
+
+    /// Returns the domain separator of the whitelist permits of this contract,
+    /// see `api3_common::permit_domain_separator`. The chain ID is the
+    /// top-level account of the contract account, e.g. `testnet`.
+    pub fn whitelist_permit_domain_separator(&self) -> Bytes32 {
+        let (chain_id, contract) = permit_domain();
+        api3_common::permit_domain_separator(&chain_id, &contract)
+    }
+
+    /// Returns the nonce the next permit of `setter` must use
+    ///
+    /// # Arguments
+    ///
+    /// * `setter` The setter account
+    pub fn whitelist_permit_nonce(&self, setter: String) -> u64 {
+        self.whitelist_permit_nonces
+            .get(&api3_common::permit_nonce_key(setter.as_bytes()))
+            .unwrap_or_default()
+    }
+
     /// Sets the indefinite whitelist status of `user` to be able to
     /// use the service with `service_id` if the sender has the indefinite whitelister role
     ///
@@ -521,13 +1030,14 @@ impl DapiServer {
             &access,
             &mut self.service_id_to_user_to_whitelist_status,
             &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
         );
         let r = whitelist.set_indefinite_whitelist_status(
             &service_id,
             &Address(user.as_bytes().to_vec()),
             status,
         );
-        Bytes32::from(r)
+        Bytes32::from(near_check_result(r))
     }
 
     /// Revokes the indefinite whitelist status granted to the user for
@@ -554,13 +1064,15 @@ impl DapiServer {
             &access,
             &mut self.service_id_to_user_to_whitelist_status,
             &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
         );
-        let (revoked, r) = whitelist.revoke_indefinite_whitelist_status(
+        let r = whitelist.revoke_indefinite_whitelist_status(
             &service_id,
             &Address(user.as_bytes().to_vec()),
             &Address(setter.as_bytes().to_vec()),
         );
-        (revoked, Bytes32::from(r))
+        let (revoked, count) = near_check_result(r);
+        (revoked, Bytes32::from(count))
     }
 
     pub fn whitelist_expiration_extender_role(&self) -> Bytes32 {
@@ -574,6 +1086,7 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
         );
         whitelist.whitelist_expiration_extender_role()
     }
@@ -589,6 +1102,7 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
         );
         whitelist.whitelist_expiration_setter_role()
     }
@@ -604,19 +1118,473 @@ impl DapiServer {
             &access,
             &self.service_id_to_user_to_whitelist_status,
             &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
         );
         whitelist.indefinite_whitelister_role()
     }
+
+    /// Publishes the Merkle root of the readers whitelisted for the service.
+    /// Readers then pass their proof, built with `api3_common::MerkleTree`,
+    /// to the read functions. A zero root disables Merkle proof whitelisting.
+    /// Only the manager can publish roots.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `root` The Merkle root of the whitelisted readers
+    pub fn set_whitelist_merkle_root(&mut self, service_id: Bytes32, root: Bytes32) {
+        let mut storage =
+            Bytes32HashMap::requires_write(&mut self.service_id_to_whitelist_merkle_root);
+        near_check_result(api3_common::set_whitelist_merkle_root(
+            &mut storage,
+            &self.manager,
+            &msg_sender(),
+            &service_id,
+            root,
+        ))
+    }
+
+    /// Returns the Merkle root of the readers whitelisted for the service
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    pub fn whitelist_merkle_root(&self, service_id: Bytes32) -> Option<Bytes32> {
+        self.service_id_to_whitelist_merkle_root.get(&service_id)
+    }
+
+    /// Returns the number of users with a whitelist status for the service
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    pub fn whitelisted_user_count(&self, service_id: Bytes32) -> u64 {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let whitelist = NearWhitelist::read_only(
+            &access,
+            &self.service_id_to_user_to_whitelist_status,
+            &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
+        );
+        whitelist.whitelisted_user_count(&service_id) as u64
+    }
+
+    /// Returns up to `limit` users with a whitelist status for the service,
+    /// starting at `from_index`. Includes the users whose temporary whitelist
+    /// expired but were not swept yet. Sweeping changes the order of the users.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `from_index` Index of the first user to return
+    /// * `limit` Maximum number of users to return
+    pub fn whitelisted_users_of(
+        &self,
+        service_id: Bytes32,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<String> {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let whitelist = NearWhitelist::read_only(
+            &access,
+            &self.service_id_to_user_to_whitelist_status,
+            &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
+        );
+        whitelist
+            .whitelisted_users_of(
+                &service_id,
+                usize::try_from(from_index).unwrap_or(usize::MAX),
+                usize::try_from(limit).unwrap_or(usize::MAX),
+            )
+            .iter()
+            .map(address_to_string)
+            .collect()
+    }
+
+    /// Returns the accounts that indefinitely whitelisted `user` for the service
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    pub fn indefinite_whitelist_setters_of(
+        &self,
+        service_id: Bytes32,
+        user: String,
+    ) -> Vec<String> {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let whitelist = NearWhitelist::read_only(
+            &access,
+            &self.service_id_to_user_to_whitelist_status,
+            &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &self.whitelist_index,
+        );
+        whitelist
+            .indefinite_whitelist_setters_of(&service_id, &Address(user.into_bytes()))
+            .iter()
+            .map(address_to_string)
+            .collect()
+    }
+
+    /// Checks up to `limit` users of the service starting at `from_index` and
+    /// removes the whitelist status of the ones whose temporary whitelist
+    /// expired and who are not indefinitely whitelisted, along with their
+    /// revoked indefinite whitelist statuses, releasing their storage.
+    /// Anyone can call this. Returns the number of removed users.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `from_index` Index of the first user to check, see `whitelisted_users_of`
+    /// * `limit` Maximum number of users to check
+    pub fn sweep_expired(&mut self, service_id: Bytes32, from_index: u64, limit: u64) -> u64 {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let mut whitelist = NearWhitelist::requires_write(
+            &access,
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
+        );
+        whitelist.sweep_expired(
+            &service_id,
+            usize::try_from(from_index).unwrap_or(usize::MAX),
+            usize::try_from(limit).unwrap_or(usize::MAX),
+        ) as u64
+    }
+
+    // ================== Usage Metering ====================
+    /// Sets the length of the epochs reads are counted in. A zero length
+    /// disables usage metering. The read counts of closed epochs are kept for
+    /// invoicing until the manager prunes them with `prune_read_counts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch_length` Length of an epoch in seconds
+    pub fn set_usage_epoch_length(&mut self, epoch_length: u64) {
+        self.ensure_is_admin();
+        self.usage_epoch_length = epoch_length;
+    }
+
+    /// Returns the length of the usage epochs in seconds, zero if metering is disabled
+    pub fn usage_epoch_length(&self) -> u64 {
+        self.usage_epoch_length
+    }
+
+    /// Returns the current usage epoch, `None` if metering is disabled
+    pub fn current_usage_epoch(&self) -> Option<u64> {
+        if self.usage_epoch_length == 0 {
+            return None;
+        }
+        let now = NearClock::now().current_timestamp();
+        Some(api3_common::usage_epoch(now, self.usage_epoch_length))
+    }
+
+    /// Sets the maximum number of reads per data point and epoch of the readers
+    /// in the tier. Tiers without a quota are unlimited.
+    ///
+    /// # Arguments
+    ///
+    /// * `tier` The reader tier, see `api3_common::ReaderTier`
+    /// * `quota` Maximum reads per epoch, `None` to remove the quota
+    pub fn set_usage_quota(&mut self, tier: u8, quota: Option<u64>) {
+        self.ensure_is_admin();
+        near_check_result(ReaderTier::try_from(tier));
+        match quota {
+            Some(q) => self.usage_quotas.insert(&tier, &q),
+            None => self.usage_quotas.remove(&tier),
+        };
+    }
+
+    /// Returns the quota of the reader tier, `None` if unlimited
+    ///
+    /// # Arguments
+    ///
+    /// * `tier` The reader tier, see `api3_common::ReaderTier`
+    pub fn usage_quota(&self, tier: u8) -> Option<u64> {
+        self.usage_quotas.get(&tier)
+    }
+
+    /// Returns how many times the reader read the data point in the epoch
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID
+    /// * `reader` Reader address
+    /// * `epoch` Usage epoch, see `current_usage_epoch`
+    pub fn read_count(&self, data_point_id: Bytes32, reader: String, epoch: u64) -> u64 {
+        let key = api3_common::read_count_key(&data_point_id, reader.as_bytes(), epoch);
+        self.read_counts.get(&key).unwrap_or_default()
+    }
+
+    /// Removes the read counts of the readers of the data point in a closed
+    /// epoch, e.g. once the epoch is invoiced. Only the manager can prune.
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID
+    /// * `readers` Reader addresses
+    /// * `epoch` Usage epoch, before `current_usage_epoch`
+    pub fn prune_read_counts(&mut self, data_point_id: Bytes32, readers: Vec<String>, epoch: u64) {
+        near_check_result(api3_common::ensure_can_prune_read_counts(
+            &self.manager,
+            &msg_sender(),
+            &NearClock::now(),
+            self.usage_epoch_length,
+            epoch,
+        ));
+        for reader in readers {
+            let key = api3_common::read_count_key(&data_point_id, reader.as_bytes(), epoch);
+            self.read_counts.remove(&key);
+        }
+    }
+
+    // ================== Subscription ====================
+    /// Sets the price readers pay to whitelist themselves with `subscribe`.
+    /// A zero `period` disables subscriptions.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount_per_period` Amount in yoctoNEAR charged per period
+    /// * `period` Length of a period in seconds
+    pub fn set_subscription_price(&mut self, amount_per_period: U128, period: u64) {
+        self.ensure_is_admin();
+        self.subscription_amount_per_period = amount_per_period.into();
+        self.subscription_period = period;
+    }
+
+    /// Returns the subscription amount per period in yoctoNEAR and the period in seconds
+    pub fn subscription_price(&self) -> (U128, u64) {
+        (
+            self.subscription_amount_per_period.into(),
+            self.subscription_period,
+        )
+    }
+
+    /// Returns the subscription payments that were not withdrawn by the manager yet
+    pub fn subscription_balance(&self) -> U128 {
+        self.subscription_balance.into()
+    }
+
+    /// Whitelists the sender to read the data feed for `periods` more periods.
+    /// The attached deposit must cover the price of the periods, the excess is
+    /// refunded. Returns the new expiration timestamp in seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    /// * `periods` Number of periods to subscribe for
+    #[payable]
+    pub fn subscribe(&mut self, data_feed_id: Bytes32, periods: u64) -> u64 {
+        let price = SubscriptionPrice::new(
+            self.subscription_amount_per_period,
+            self.subscription_period,
+        );
+        let cost = near_check_result(price.cost(periods));
+        let deposit = near_sdk::env::attached_deposit();
+        ensure!(deposit >= cost, Error::InsufficientPayment);
+
+        let reader = msg_sender();
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let mut whitelist = NearWhitelist::requires_write(
+            &access,
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            &mut self.whitelist_index,
+        );
+        let expiration =
+            near_check_result(whitelist.subscribe(&price, &data_feed_id, &reader, periods));
+
+        self.subscription_balance += cost;
+        if deposit > cost {
+            Promise::new(address_to_string(&reader)).transfer(deposit - cost);
+        }
+        expiration.as_secs()
+    }
+
+    /// Transfers `amount` of the subscription payments to the manager. Only
+    /// the manager can withdraw.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` Amount in yoctoNEAR to withdraw
+    pub fn withdraw_subscription_balance(&mut self, amount: U128) -> Promise {
+        let amount = amount.into();
+        self.subscription_balance = near_check_result(api3_common::withdraw_subscription_balance(
+            &self.manager,
+            &msg_sender(),
+            self.subscription_balance,
+            amount,
+        ));
+        Promise::new(address_to_string(&self.manager)).transfer(amount)
+    }
 }
 
-fn nanoseconds_to_seconds(nano: u64) -> u32 {
-    (nano / (1e9 as u64)) as u32
+impl DapiServer {
+    fn ensure_is_admin(&self) {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        ensure!(
+            access
+                .only_role(
+                    &NearAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                    &msg_sender()
+                )
+                .is_ok(),
+            Error::NotAuthorized
+        );
+    }
+
+    /// Counts a read of the data point by the sender against the quota of
+    /// the tier the sender reads the service with
+    fn meter_read(
+        &mut self,
+        service_id: &Bytes32,
+        data_point_id: &Bytes32,
+        proof: Option<Vec<Bytes32>>,
+    ) {
+        if self.usage_epoch_length == 0 {
+            return;
+        }
+        let reader = msg_sender();
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        let tier = if access.has_role(
+            &access.find_static_role(StaticRole::UnlimitedReaderRole),
+            &reader,
+        ) {
+            Some(ReaderTier::UnlimitedReader)
+        } else {
+            NearWhitelist::read_only(
+                &access,
+                &self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &self.whitelist_index,
+            )
+            .with_merkle_proof(&self.service_id_to_whitelist_merkle_root, proof)
+            .reader_tier(service_id, &reader)
+        };
+        let quota = tier.and_then(|t| self.usage_quotas.get(&u8::from(t)));
+        near_check_result(api3_common::record_read(
+            &mut U64HashMap::requires_write(&mut self.read_counts),
+            &NearClock::now(),
+            self.usage_epoch_length,
+            quota,
+            data_point_id,
+            &reader.0,
+        ));
+    }
+
+    fn ensure_can_pause(&self) {
+        let access = NearAccessControlRegistry::read_only(
+            self.manager.clone(),
+            self.admin_role_description.clone(),
+            &self.role_membership,
+            &self.role_admin,
+        );
+        near_check_result(api3_common::ensure_can_pause(&access, &msg_sender()));
+    }
+
+    fn pause_states(&self, data_feed_id: &Bytes32) -> (PauseState, PauseState) {
+        let global = near_check_result(PauseState::try_from(self.pause_state));
+        let data_feed = near_check_result(PauseState::try_from(
+            self.data_feed_pause_state(*data_feed_id),
+        ));
+        (global, data_feed)
+    }
+
+    fn ensure_updates_allowed(&self, data_feed_id: &Bytes32) {
+        let (global, data_feed) = self.pause_states(data_feed_id);
+        near_check_result_with(
+            api3_common::ensure_updates_allowed(global, data_feed),
+            |p| p.with_id("data_feed_id", data_feed_id),
+        )
+    }
+
+    /// Checks updates are allowed for the dAPI and each of its Beacons
+    fn ensure_dapi_updates_allowed(&self, dapi_id: &Bytes32, beacon_ids: &[Bytes32]) {
+        self.ensure_updates_allowed(dapi_id);
+        beacon_ids
+            .iter()
+            .for_each(|beacon_id| self.ensure_updates_allowed(beacon_id));
+    }
+
+    fn ensure_reads_allowed(&self, data_feed_id: &Bytes32) {
+        let (global, data_feed) = self.pause_states(data_feed_id);
+        near_check_result_with(api3_common::ensure_reads_allowed(global, data_feed), |p| {
+            p.with_id("data_feed_id", data_feed_id)
+        })
+    }
+}
+
+fn near_check_result<T>(r: Result<T, Error>) -> T {
+    near_check_result_with(r, |p| p)
 }
 
-fn near_check_result<T: Debug>(r: Result<T, Error>) -> T {
-    if let Ok(v) = r {
-        v
-    } else {
-        near_sdk::env::panic(format!("Invalid request: {:?}", r.unwrap_err()).as_ref())
+/// Same as `near_check_result`, `context` adds details such as the offending
+/// beacon ID to the error payload
+fn near_check_result_with<T>(
+    r: Result<T, Error>,
+    context: impl FnOnce(ErrorPayload) -> ErrorPayload,
+) -> T {
+    match r {
+        Ok(v) => v,
+        Err(e) => near_fail(context(ErrorPayload::from(&e))),
     }
 }
+
+/// Same as `near_check_result` for dAPI updates, the error payload has the
+/// `dapi_id` and, if a Beacon failed, its `beacon_index` and `beacon_id`
+fn near_check_dapi_update(
+    r: Result<Bytes32, DapiUpdateError>,
+    dapi_id: &Bytes32,
+    beacon_ids: &[Bytes32],
+) -> Bytes32 {
+    match r {
+        Ok(v) => v,
+        Err(e) => {
+            let payload = ErrorPayload::from(&e).with_id("dapi_id", dapi_id);
+            match e.beacon_index.and_then(|i| beacon_ids.get(i)) {
+                Some(beacon_id) => near_fail(payload.with_id("beacon_id", beacon_id)),
+                None => near_fail(payload),
+            }
+        }
+    }
+}
+
+fn address_to_string(address: &Address) -> String {
+    String::from_utf8_lossy(&address.0).into_owned()
+}
@@ -0,0 +1,87 @@
+//! Upgrade of a deployment from the state layout before the role and whitelist
+//! indexes, metering and the pause states were added.
+//!
+//! The maps of the old layout keep their storage prefixes, so `migrate` only
+//! rewrites the contract struct. `LookupMap`s can not be iterated, the members
+//! and whitelisted users of the old deployment are added to the indexes by the
+//! manager with `reindex_role_members` and `reindex_whitelist`, in batches.
+use crate::types::{Address, NearDataPoint};
+use crate::utils::{msg_sender, near_fail, NearAccessControlRegistry};
+use crate::whitelist::NearWhitelistStatus;
+use crate::{ensure, DapiServer, DapiServerContract};
+use api3_common::{AccessControlRegistry, Bytes32, Error, ErrorPayload};
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::{collections::LookupMap, env, near_bindgen};
+
+/// The contract state before the upgrade, the field order must not change
+#[derive(BorshDeserialize)]
+struct DapiServerV0 {
+    #[allow(dead_code)]
+    data_points: LookupMap<Bytes32, NearDataPoint>,
+    #[allow(dead_code)]
+    name_hash_to_data_point_id: LookupMap<Bytes32, Bytes32>,
+    manager: Address,
+    admin_role_description: String,
+    #[allow(dead_code)]
+    role_membership: LookupMap<Bytes32, bool>,
+    #[allow(dead_code)]
+    role_admin: LookupMap<Bytes32, Bytes32>,
+    #[allow(dead_code)]
+    service_id_to_user_to_whitelist_status: LookupMap<Bytes32, NearWhitelistStatus>,
+    #[allow(dead_code)]
+    service_id_to_user_to_setter_to_indefinite_whitelist_status: LookupMap<Bytes32, bool>,
+}
+
+#[near_bindgen]
+impl DapiServer {
+    /// Reads the state of a deployment from before the upgrade. The stored
+    /// data points, roles and whitelist statuses are kept, the new fields
+    /// start empty. The manager is added to the role index, other members and
+    /// whitelisted users are added with `reindex_role_members` and
+    /// `reindex_whitelist`.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate() -> Self {
+        let old = match env::state_read::<DapiServerV0>() {
+            Some(old) => old,
+            None => near_fail(ErrorPayload::from(&Error::NotInitialized)),
+        };
+        let mut contract = Self::with_manager(old.manager.clone(), old.admin_role_description);
+        contract
+            .role_membership
+            .reindex(&NearAccessControlRegistry::DEFAULT_ADMIN_ROLE, &old.manager);
+        contract
+    }
+
+    /// Adds the `(role, account)` members granted before the upgrade to the
+    /// role index. Accounts that are not members of the role or are already
+    /// indexed are skipped. Only callable by the manager.
+    pub fn reindex_role_members(&mut self, members: Vec<(Bytes32, String)>) {
+        ensure!(msg_sender() == self.manager, Error::NotAuthorized);
+        for (role, who) in members {
+            self.role_membership
+                .reindex(&role, &Address(who.as_bytes().to_vec()));
+        }
+    }
+
+    /// Adds the `(service_id, user, setters)` users whitelisted before the
+    /// upgrade to the whitelist index, with the setters that have set an
+    /// indefinite whitelist status for the user. Expirations stored in
+    /// nanoseconds are converted to seconds. Only callable by the manager.
+    pub fn reindex_whitelist(&mut self, users: Vec<(Bytes32, String, Vec<String>)>) {
+        ensure!(msg_sender() == self.manager, Error::NotAuthorized);
+        for (service_id, user, setters) in users {
+            let setters: Vec<Address> = setters
+                .iter()
+                .map(|s| Address(s.as_bytes().to_vec()))
+                .collect();
+            self.whitelist_index.reindex_user(
+                &mut self.service_id_to_user_to_whitelist_status,
+                &self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                &service_id,
+                &Address(user.as_bytes().to_vec()),
+                &setters,
+            );
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use api3_common::abi::{Int, U256};
-use api3_common::{DataPoint, Zero};
+use api3_common::{DataPoint, FeedStatus, Timestamp, Zero};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 
-#[derive(BorshDeserialize, BorshSerialize, PartialEq, Clone, Default)]
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Clone, Debug, Default)]
 pub struct Address(pub Vec<u8>);
 
 impl Zero for Address {
@@ -33,7 +33,10 @@ impl From<NearDataPoint> for DataPoint {
     fn from(t: NearDataPoint) -> Self {
         let mut v = [0u8; 32];
         t.value.to_big_endian(&mut v);
-        DataPoint::new(Int::from_big_endian(&v), t.timestamp)
+        DataPoint::new(
+            Int::from_big_endian(&v),
+            Timestamp::from_secs(u64::from(t.timestamp)),
+        )
     }
 }
 
@@ -41,6 +44,30 @@ impl From<DataPoint> for NearDataPoint {
     fn from(t: DataPoint) -> Self {
         let mut v = [0u8; 32];
         t.value.to_big_endian(&mut v);
-        NearDataPoint::new(U256::from_big_endian(&v), t.timestamp)
+        NearDataPoint::new(U256::from_big_endian(&v), t.timestamp.as_secs_u32())
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub(crate) enum NearFeedStatus {
+    Active,
+    Paused(NearDataPoint),
+}
+
+impl From<NearFeedStatus> for FeedStatus {
+    fn from(t: NearFeedStatus) -> Self {
+        match t {
+            NearFeedStatus::Active => FeedStatus::Active,
+            NearFeedStatus::Paused(d) => FeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+impl From<FeedStatus> for NearFeedStatus {
+    fn from(t: FeedStatus) -> Self {
+        match t {
+            FeedStatus::Active => NearFeedStatus::Active,
+            FeedStatus::Paused(d) => NearFeedStatus::Paused(d.into()),
+        }
     }
 }
@@ -1,10 +1,13 @@
-use crate::types::{Address, NearDataPoint};
+use crate::types::{Address, NearDataPoint, NearFeedStatus};
 use api3_common::abi::Token;
 use api3_common::{
-    keccak_packed, AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32,
-    DataPoint, Error, RoleDeriver, SignatureManger, Storage, TimestampChecker,
+    keccak_packed, migrate_role_grant_with_expiration, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, AccessControlRegistryEnumerable,
+    AccessControlRegistryWithExpiration, Bytes32, DataPoint, Error, ErrorPayload, FeedStatus,
+    PermitSigner, RoleDeriver, SignatureManger, Storage, Timestamp, TimestampChecker,
 };
 use ed25519_dalek::Verifier;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LookupMap;
 
 /// Read write privilege
@@ -49,7 +52,7 @@ impl<'account> Storage<DataPoint> for DatapointHashMap<'account> {
 
     fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
         let m = match &mut self.map {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
+            ReadWrite::ReadOnly(_) => read_only_storage(),
             ReadWrite::Write(m) => m,
         };
         if (*m).contains_key(&k) {
@@ -89,7 +92,7 @@ impl<'account> Storage<Bytes32> for Bytes32HashMap<'account> {
 
     fn store(&mut self, k: Bytes32, data: Bytes32) {
         let m = match &mut self.map {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
+            ReadWrite::ReadOnly(_) => read_only_storage(),
             ReadWrite::Write(m) => m,
         };
         if (*m).contains_key(&k) {
@@ -99,6 +102,77 @@ impl<'account> Storage<Bytes32> for Bytes32HashMap<'account> {
     }
 }
 
+/// The utility struct for handling Near storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct U64HashMap<'account> {
+    map: ReadWrite<'account, LookupMap<Bytes32, u64>>,
+}
+
+impl<'account> U64HashMap<'account> {
+    pub fn requires_write(map: &'account mut LookupMap<Bytes32, u64>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+}
+
+impl<'account> Storage<u64> for U64HashMap<'account> {
+    fn get(&self, k: &Bytes32) -> Option<u64> {
+        match &self.map {
+            ReadWrite::ReadOnly(a) => (*a).get(k),
+            ReadWrite::Write(a) => (*a).get(k),
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, data: u64) {
+        let m = match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        };
+        (*m).insert(&k, &data);
+    }
+}
+
+/// The utility struct for handling Near storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusHashMap<'account> {
+    map: ReadWrite<'account, LookupMap<Bytes32, NearFeedStatus>>,
+}
+
+impl<'account> FeedStatusHashMap<'account> {
+    pub fn requires_write(map: &'account mut LookupMap<Bytes32, NearFeedStatus>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'account LookupMap<Bytes32, NearFeedStatus>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'account> Storage<FeedStatus> for FeedStatusHashMap<'account> {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        match &self.map {
+            ReadWrite::ReadOnly(a) => (*a).get(k).map(FeedStatus::from),
+            ReadWrite::Write(a) => (*a).get(k).map(FeedStatus::from),
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        let m = match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        };
+        if (*m).contains_key(&k) {
+            (*m).remove(&k);
+        }
+        (*m).insert(&k, &NearFeedStatus::from(status));
+    }
+}
+
 /// Utility function for signature verification for Near so that we can use
 /// `api3_common` package for the functions
 pub(crate) struct SignatureVerify;
@@ -114,18 +188,32 @@ impl SignatureManger for SignatureVerify {
     }
 }
 
+impl PermitSigner for SignatureVerify {
+    type Address = Address;
+
+    /// The implicit account of the ed25519 public key, i.e. the hex encoded key
+    fn address_of(key: &[u8]) -> Address {
+        Address(hex::encode(key).into_bytes())
+    }
+}
+
 pub(crate) struct NearClock {
-    current_timestamp: u32,
+    current_timestamp: Timestamp,
 }
 
 impl NearClock {
-    pub fn new(current_timestamp: u32) -> Self {
+    pub fn new(current_timestamp: Timestamp) -> Self {
         Self { current_timestamp }
     }
+
+    /// The clock at the current block, NEAR reports the block time in nanoseconds
+    pub fn now() -> Self {
+        Self::new(Timestamp::from_nanos(near_sdk::env::block_timestamp()))
+    }
 }
 
 impl TimestampChecker for NearClock {
-    fn current_timestamp(&self) -> u32 {
+    fn current_timestamp(&self) -> Timestamp {
         self.current_timestamp
     }
 }
@@ -135,10 +223,89 @@ pub(crate) fn msg_sender() -> Address {
     Address(sender)
 }
 
+/// The chain ID and the contract of the whitelist permits of this contract.
+/// NEAR has no chain ID, the top-level account of the contract account
+/// (`near`, `testnet`) names the network instead.
+pub(crate) fn permit_domain() -> (String, Vec<u8>) {
+    let contract = near_sdk::env::current_account_id();
+    let chain_id = contract.rsplit('.').next().unwrap_or_default();
+    (String::from(chain_id), contract.as_bytes().to_vec())
+}
+
+/// Role membership storage. Memberships are looked up by `hash_membership`, the
+/// members of each role and the roles of each account are kept as well so that
+/// they can be enumerated. Grants made with `grant_role_until` have their
+/// expiration stored under the same hash.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct RoleMembership {
+    membership: LookupMap<Bytes32, bool>,
+    role_members: LookupMap<Bytes32, Vec<Address>>,
+    account_roles: LookupMap<Address, Vec<Bytes32>>,
+    expirations: LookupMap<Bytes32, u64>,
+}
+
+impl RoleMembership {
+    pub fn new() -> Self {
+        Self {
+            membership: LookupMap::new(b'm'),
+            role_members: LookupMap::new(b'r'),
+            account_roles: LookupMap::new(b'o'),
+            expirations: LookupMap::new(b'e'),
+        }
+    }
+
+    fn contains(&self, hash: &Bytes32) -> bool {
+        self.membership.contains_key(hash)
+    }
+
+    fn insert(&mut self, hash: &Bytes32, role: &Bytes32, who: &Address) {
+        self.expirations.remove(hash);
+        if self.membership.insert(hash, &true).is_some() {
+            return;
+        }
+        let mut members = self.role_members.get(role).unwrap_or_default();
+        members.push(who.clone());
+        self.role_members.insert(role, &members);
+        let mut roles = self.account_roles.get(who).unwrap_or_default();
+        roles.push(*role);
+        self.account_roles.insert(who, &roles);
+    }
+
+    fn remove(&mut self, hash: &Bytes32, role: &Bytes32, who: &Address) {
+        self.expirations.remove(hash);
+        if self.membership.remove(hash).is_none() {
+            return;
+        }
+        let mut members = self.role_members.get(role).unwrap_or_default();
+        members.retain(|m| m != who);
+        self.role_members.insert(role, &members);
+        let mut roles = self.account_roles.get(who).unwrap_or_default();
+        roles.retain(|r| r != role);
+        self.account_roles.insert(who, &roles);
+    }
+
+    /// Adds a member granted before the index existed to the index, see
+    /// `DapiServer::reindex_role_members`. Returns if the member was added,
+    /// accounts that are not members or are already indexed are skipped.
+    pub fn reindex(&mut self, role: &Bytes32, who: &Address) -> bool {
+        let hash = NearAccessControlRegistry::hash_membership(role, who);
+        let mut members = self.role_members.get(role).unwrap_or_default();
+        if !self.contains(&hash) || members.contains(who) {
+            return false;
+        }
+        members.push(who.clone());
+        self.role_members.insert(role, &members);
+        let mut roles = self.account_roles.get(who).unwrap_or_default();
+        roles.push(*role);
+        self.account_roles.insert(who, &roles);
+        true
+    }
+}
+
 pub(crate) struct NearAccessControlRegistry<'a> {
     manager: Address,
     admin_role_description: String,
-    role_membership: ReadWrite<'a, LookupMap<Bytes32, bool>>,
+    role_membership: ReadWrite<'a, RoleMembership>,
     role_admin: ReadWrite<'a, LookupMap<Bytes32, Bytes32>>,
 }
 
@@ -146,7 +313,7 @@ impl<'a> NearAccessControlRegistry<'a> {
     pub fn requires_write(
         manager: Address,
         admin_role_description: String,
-        role_membership: &'a mut LookupMap<Bytes32, bool>,
+        role_membership: &'a mut RoleMembership,
         role_admin: &'a mut LookupMap<Bytes32, Bytes32>,
     ) -> Self {
         Self {
@@ -160,7 +327,7 @@ impl<'a> NearAccessControlRegistry<'a> {
     pub fn read_only(
         manager: Address,
         admin_role_description: String,
-        role_membership: &'a LookupMap<Bytes32, bool>,
+        role_membership: &'a RoleMembership,
         role_admin: &'a LookupMap<Bytes32, Bytes32>,
     ) -> Self {
         Self {
@@ -171,6 +338,19 @@ impl<'a> NearAccessControlRegistry<'a> {
         }
     }
 
+    fn membership(&self) -> &RoleMembership {
+        match &self.role_membership {
+            ReadWrite::ReadOnly(m) => m,
+            ReadWrite::Write(m) => m,
+        }
+    }
+
+    fn role_members(&self, role: &Bytes32) -> Vec<Address> {
+        let mut members = self.membership().role_members.get(role).unwrap_or_default();
+        members.retain(|m| !self.role_is_expired(role, m));
+        members
+    }
+
     fn hash_membership(role: &Bytes32, who: &Address) -> Bytes32 {
         keccak_packed(&[
             Token::FixedBytes(role.to_vec()),
@@ -205,20 +385,14 @@ impl<'a> AccessControlRegistryAdminnedWithManager for NearAccessControlRegistry<
 impl<'a> AccessControlRegistry for NearAccessControlRegistry<'a> {
     fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
         let hash = Self::hash_membership(role, who);
-        match &self.role_membership {
-            ReadWrite::ReadOnly(m) => m.contains_key(&hash),
-            ReadWrite::Write(m) => m.contains_key(&hash),
-        }
+        self.membership().contains(&hash) && !self.role_is_expired(role, who)
     }
 
     fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
         let hash = Self::hash_membership(role, who);
         match &mut self.role_membership {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => {
-                (*m).remove(&hash);
-                (*m).insert(&hash, &true);
-            }
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => (*m).insert(&hash, role, who),
         };
         Ok(())
     }
@@ -235,7 +409,7 @@ impl<'a> AccessControlRegistry for NearAccessControlRegistry<'a> {
 
     fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
         let a = match &mut self.role_admin {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
+            ReadWrite::ReadOnly(_) => read_only_storage(),
             ReadWrite::Write(a) => a,
         };
         (*a).remove(role);
@@ -253,12 +427,86 @@ impl<'a> AccessControlRegistry for NearAccessControlRegistry<'a> {
         let hash = Self::hash_membership(role, account);
 
         let m = match &mut self.role_membership {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
+            ReadWrite::ReadOnly(_) => read_only_storage(),
             ReadWrite::Write(m) => m,
         };
-        (*m).remove(&hash);
+        (*m).remove(&hash, role, account);
         Ok(())
     }
+
+    fn migrate_role_grant(
+        &mut self,
+        from: &Bytes32,
+        to: &Bytes32,
+        who: &Self::Address,
+    ) -> Result<(), Error> {
+        migrate_role_grant_with_expiration(self, from, to, who)
+    }
+}
+
+impl<'a> AccessControlRegistryEnumerable for NearAccessControlRegistry<'a> {
+    fn get_role_member_count(&self, role: &Bytes32) -> usize {
+        self.role_members(role).len()
+    }
+
+    fn get_role_member(&self, role: &Bytes32, index: usize) -> Option<Self::Address> {
+        self.role_members(role).get(index).cloned()
+    }
+
+    fn roles_of(&self, account: &Self::Address) -> Vec<Bytes32> {
+        let mut roles = self
+            .membership()
+            .account_roles
+            .get(account)
+            .unwrap_or_default();
+        roles.retain(|r| !self.role_is_expired(r, account));
+        roles
+    }
+}
+
+impl<'a> TimestampChecker for NearAccessControlRegistry<'a> {
+    fn current_timestamp(&self) -> Timestamp {
+        NearClock::now().current_timestamp()
+    }
+}
+
+impl<'a> AccessControlRegistryWithExpiration for NearAccessControlRegistry<'a> {
+    fn role_expiration(&self, role: &Bytes32, who: &Self::Address) -> Option<Timestamp> {
+        let hash = Self::hash_membership(role, who);
+        self.membership()
+            .expirations
+            .get(&hash)
+            .map(Timestamp::from_secs)
+    }
+
+    fn set_role_expiration(
+        &mut self,
+        role: &Bytes32,
+        who: &Self::Address,
+        expiration_timestamp: Option<Timestamp>,
+    ) {
+        let hash = Self::hash_membership(role, who);
+        let m = match &mut self.role_membership {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        };
+        match expiration_timestamp {
+            Some(e) => m.expirations.insert(&hash, &e.as_secs()),
+            None => m.expirations.remove(&hash),
+        };
+    }
+}
+
+/// Fails the call with the payload as the panic message, so that clients can
+/// parse it with `ErrorPayload::parse`
+pub(crate) fn near_fail(payload: ErrorPayload) -> ! {
+    near_sdk::env::panic(payload.to_json().as_bytes())
+}
+
+/// Fails the call for writing to storage opened with `read_only`, which is a
+/// bug in the contract rather than in the request
+pub(crate) fn read_only_storage() -> ! {
+    near_fail(ErrorPayload::from(&Error::ReadOnlyStorage))
 }
 
 /// NEAR contract calls on the panic interface for errors
@@ -266,15 +514,7 @@ impl<'a> AccessControlRegistry for NearAccessControlRegistry<'a> {
 macro_rules! ensure {
     ( $x:expr, $y:expr ) => {{
         if !$x {
-            near_sdk::env::panic(format!("{:?}", $y).as_bytes())
+            $crate::utils::near_fail(api3_common::ErrorPayload::from(&$y))
         }
     }};
 }
-
-/// a convenient way to call to the NEAR's blockchain panic
-#[macro_export]
-macro_rules! error_panic {
-    ( $y:expr ) => {{
-        near_sdk::env::panic(format!("{}", $y).as_bytes())
-    }};
-}
@@ -1,23 +1,32 @@
-use crate::utils::ReadWrite;
+use crate::utils::{
+    read_only_storage, Bytes32HashMap, NearClock, ReadWrite, SignatureVerify, U64HashMap,
+};
 use crate::{msg_sender, near_check_result, Address};
-use api3_common::abi::{Token, U256};
+use api3_common::abi::U256;
 use api3_common::{
-    ensure, keccak_packed, AccessControlRegistry, AccessControlRegistryAdminnedWithManager,
-    Bytes32, Error, Whitelist, WhitelistRoles, WhitelistRolesWithManager, WhitelistWithManager,
-    Zero, BYTES32_ZERO,
+    ensure, indefinite_whitelist_status_key, whitelist_status_key, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, Bytes32, Error, ReaderTier, Storage,
+    SubscriptionPrice, Timestamp, TimestampChecker, Whitelist, WhitelistEnumerable,
+    WhitelistPermit, WhitelistRoles, WhitelistRolesWithManager, WhitelistStatus,
+    WhitelistWithManager, Zero, BYTES32_ZERO,
 };
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
-use std::ops::{Add, Sub};
+use near_sdk::collections::{LookupMap, UnorderedSet};
+
+/// Expirations above this many seconds, about 30 million years, can only
+/// have been stored in nanoseconds
+const MAX_EXPIRATION_SECS: u64 = 1_000_000_000_000_000;
+const NANOS_PER_SEC: u64 = 1_000_000_000;
 
 #[derive(BorshDeserialize, BorshSerialize)]
-pub struct WhitelistStatus {
+pub struct NearWhitelistStatus {
+    /// in seconds
     expiration_timestamp: u64,
     /// originally uint192, that is u128 and u64 combined
     indefinite_whitelist_count: Bytes32,
 }
 
-impl Default for WhitelistStatus {
+impl Default for NearWhitelistStatus {
     fn default() -> Self {
         Self {
             expiration_timestamp: 0,
@@ -26,90 +35,381 @@ impl Default for WhitelistStatus {
     }
 }
 
+impl From<NearWhitelistStatus> for WhitelistStatus {
+    fn from(s: NearWhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: Timestamp::from_secs(s.expiration_timestamp),
+            indefinite_whitelist_count: U256::from_big_endian(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+impl From<WhitelistStatus> for NearWhitelistStatus {
+    fn from(s: WhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: s.expiration_timestamp.as_secs(),
+            indefinite_whitelist_count: Bytes32::from(&s.indefinite_whitelist_count),
+        }
+    }
+}
+
+/// The utility struct for handling Near storage so that
+/// we can use the code in `api3_common` for all the processing
+struct WhitelistStatusHashMap<'account> {
+    map: ReadWrite<'account, LookupMap<Bytes32, NearWhitelistStatus>>,
+}
+
+impl<'account> Storage<WhitelistStatus> for WhitelistStatusHashMap<'account> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        match &self.map {
+            ReadWrite::ReadOnly(a) => (*a).get(k).map(WhitelistStatus::from),
+            ReadWrite::Write(a) => (*a).get(k).map(WhitelistStatus::from),
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        let m = match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        };
+        (*m).insert(&k, &NearWhitelistStatus::from(status));
+    }
+}
+
+impl<'account> WhitelistStatusHashMap<'account> {
+    fn remove(&mut self, k: &Bytes32) {
+        match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => (*m).remove(k),
+        };
+    }
+}
+
+/// The utility struct for handling Near storage so that
+/// we can use the code in `api3_common` for all the processing
+struct IndefiniteWhitelistStatusHashMap<'account> {
+    map: ReadWrite<'account, LookupMap<Bytes32, bool>>,
+}
+
+impl<'account> Storage<bool> for IndefiniteWhitelistStatusHashMap<'account> {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        match &self.map {
+            ReadWrite::ReadOnly(a) => (*a).get(k),
+            ReadWrite::Write(a) => (*a).get(k),
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        let m = match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        };
+        (*m).insert(&k, &status);
+    }
+}
+
+impl<'account> IndefiniteWhitelistStatusHashMap<'account> {
+    fn remove(&mut self, k: &Bytes32) {
+        match &mut self.map {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => (*m).remove(k),
+        };
+    }
+}
+
+/// Index of the whitelist storage so that it can be enumerated: the users
+/// with a whitelist status for each service, and the setters with an
+/// indefinite whitelist status of each user keyed by `whitelist_status_key`,
+/// including the revoked ones so that sweeping can remove them
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct WhitelistIndex {
+    service_id_to_users: LookupMap<Bytes32, UnorderedSet<Address>>,
+    user_to_indefinite_whitelist_setters: LookupMap<Bytes32, Vec<Address>>,
+}
+
+impl WhitelistIndex {
+    pub fn new() -> Self {
+        Self {
+            service_id_to_users: LookupMap::new(b'i'),
+            user_to_indefinite_whitelist_setters: LookupMap::new(b'j'),
+        }
+    }
+
+    /// The users of the service, each service has its own storage prefix
+    fn users(&self, service_id: &Bytes32) -> UnorderedSet<Address> {
+        self.service_id_to_users.get(service_id).unwrap_or_else(|| {
+            let mut prefix = vec![b'k'];
+            prefix.extend_from_slice(service_id);
+            UnorderedSet::new(prefix)
+        })
+    }
+
+    fn insert_user(&mut self, service_id: &Bytes32, user: &Address) {
+        let mut users = self.users(service_id);
+        if users.insert(user) {
+            self.service_id_to_users.insert(service_id, &users);
+        }
+    }
+
+    fn insert_setter(&mut self, key: &Bytes32, setter: &Address) {
+        let mut setters = self
+            .user_to_indefinite_whitelist_setters
+            .get(key)
+            .unwrap_or_default();
+        setters.push(setter.clone());
+        self.user_to_indefinite_whitelist_setters
+            .insert(key, &setters);
+    }
+
+    /// Adds a user whitelisted before the index existed to the index, with
+    /// the `setters` that have set an indefinite whitelist status for the user,
+    /// see `DapiServer::reindex_whitelist`. The contract used to compare the
+    /// expirations with the block time in nanoseconds, an expiration stored in
+    /// nanoseconds is converted to seconds. Returns if the user was found.
+    pub(crate) fn reindex_user(
+        &mut self,
+        statuses: &mut LookupMap<Bytes32, NearWhitelistStatus>,
+        indefinite_statuses: &LookupMap<Bytes32, bool>,
+        service_id: &Bytes32,
+        user: &Address,
+        setters: &[Address],
+    ) -> bool {
+        let key = whitelist_status_key(service_id, &user.0);
+        let mut status = match statuses.get(&key) {
+            Some(s) => s,
+            None => return false,
+        };
+        if status.expiration_timestamp > MAX_EXPIRATION_SECS {
+            status.expiration_timestamp /= NANOS_PER_SEC;
+            statuses.insert(&key, &status);
+        }
+        self.insert_user(service_id, user);
+
+        let indexed = self
+            .user_to_indefinite_whitelist_setters
+            .get(&key)
+            .unwrap_or_default();
+        for setter in setters {
+            let indefinite_key = indefinite_whitelist_status_key(service_id, &user.0, &setter.0);
+            if indefinite_statuses.get(&indefinite_key).unwrap_or(false)
+                && !indexed.contains(setter)
+            {
+                self.insert_setter(&key, setter);
+            }
+        }
+        true
+    }
+}
+
 pub struct NearWhitelist<'a, Access: AccessControlRegistry<Address = Address>> {
     access: &'a Access,
-    service_id_to_user_to_whitelist_status: ReadWrite<'a, LookupMap<Bytes32, WhitelistStatus>>,
+    service_id_to_user_to_whitelist_status: WhitelistStatusHashMap<'a>,
     service_id_to_user_to_setter_to_indefinite_whitelist_status:
-        ReadWrite<'a, LookupMap<Bytes32, bool>>,
+        IndefiniteWhitelistStatusHashMap<'a>,
+    index: ReadWrite<'a, WhitelistIndex>,
+    /// The published Merkle roots and the proof of the user being checked
+    merkle_proof: Option<(Bytes32HashMap<'a>, Vec<Bytes32>)>,
 }
 
 impl<'a, Access: AccessControlRegistry<Address = Address>> NearWhitelist<'a, Access> {
     pub fn requires_write(
         access: &'a Access,
-        service_id_to_user_to_whitelist_status: &'a mut LookupMap<Bytes32, WhitelistStatus>,
+        service_id_to_user_to_whitelist_status: &'a mut LookupMap<Bytes32, NearWhitelistStatus>,
         service_id_to_user_to_setter_to_indefinite_whitelist_status: &'a mut LookupMap<
             Bytes32,
             bool,
         >,
+        index: &'a mut WhitelistIndex,
     ) -> Self {
         Self {
             access,
-            service_id_to_user_to_whitelist_status: ReadWrite::Write(
-                service_id_to_user_to_whitelist_status,
-            ),
-            service_id_to_user_to_setter_to_indefinite_whitelist_status: ReadWrite::Write(
-                service_id_to_user_to_setter_to_indefinite_whitelist_status,
-            ),
+            service_id_to_user_to_whitelist_status: WhitelistStatusHashMap {
+                map: ReadWrite::Write(service_id_to_user_to_whitelist_status),
+            },
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusHashMap {
+                    map: ReadWrite::Write(
+                        service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                    ),
+                },
+            index: ReadWrite::Write(index),
+            merkle_proof: None,
         }
     }
 
     pub fn read_only(
         access: &'a Access,
-        service_id_to_user_to_whitelist_status: &'a LookupMap<Bytes32, WhitelistStatus>,
+        service_id_to_user_to_whitelist_status: &'a LookupMap<Bytes32, NearWhitelistStatus>,
         service_id_to_user_to_setter_to_indefinite_whitelist_status: &'a LookupMap<Bytes32, bool>,
+        index: &'a WhitelistIndex,
     ) -> Self {
         Self {
             access,
-            service_id_to_user_to_whitelist_status: ReadWrite::ReadOnly(
-                service_id_to_user_to_whitelist_status,
-            ),
-            service_id_to_user_to_setter_to_indefinite_whitelist_status: ReadWrite::ReadOnly(
-                service_id_to_user_to_setter_to_indefinite_whitelist_status,
-            ),
+            service_id_to_user_to_whitelist_status: WhitelistStatusHashMap {
+                map: ReadWrite::ReadOnly(service_id_to_user_to_whitelist_status),
+            },
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusHashMap {
+                    map: ReadWrite::ReadOnly(
+                        service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                    ),
+                },
+            index: ReadWrite::ReadOnly(index),
+            merkle_proof: None,
+        }
+    }
+
+    /// Also treats the user as whitelisted if `proof` proves it is in the
+    /// Merkle tree published for the service. Without a proof this is a no-op.
+    pub fn with_merkle_proof(
+        mut self,
+        service_id_to_whitelist_merkle_root: &'a LookupMap<Bytes32, Bytes32>,
+        proof: Option<Vec<Bytes32>>,
+    ) -> Self {
+        self.merkle_proof = proof.map(|p| {
+            (
+                Bytes32HashMap::read_only(service_id_to_whitelist_merkle_root),
+                p,
+            )
+        });
+        self
+    }
+
+    fn index(&self) -> &WhitelistIndex {
+        match &self.index {
+            ReadWrite::ReadOnly(i) => i,
+            ReadWrite::Write(i) => i,
+        }
+    }
+
+    fn index_mut(&mut self) -> &mut WhitelistIndex {
+        match &mut self.index {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(i) => i,
         }
     }
 
+    /// Runs `f` and adds `user` to the users of the service if `f` created
+    /// its whitelist status
+    fn indexing_user<T>(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Address,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let key = whitelist_status_key(service_id, &user.0);
+        let existed = self
+            .service_id_to_user_to_whitelist_status
+            .get(&key)
+            .is_some();
+        let r = f(self);
+        if !existed
+            && self
+                .service_id_to_user_to_whitelist_status
+                .get(&key)
+                .is_some()
+        {
+            self.index_mut().insert_user(service_id, user);
+        }
+        r
+    }
+
     pub fn data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
         &self,
         data_feed_id: &Bytes32,
         reader: &[u8],
         setter: &[u8],
     ) -> Option<bool> {
-        let key = Self::triple_hash(data_feed_id, reader, setter);
-        let indefinite = match &self.service_id_to_user_to_setter_to_indefinite_whitelist_status {
-            ReadWrite::ReadOnly(m) => *m,
-            ReadWrite::Write(m) => *m,
-        };
-        indefinite.get(&key)
+        self.service_id_to_user_to_setter_to_indefinite_whitelist_status
+            .get(&indefinite_whitelist_status_key(
+                data_feed_id,
+                reader,
+                setter,
+            ))
     }
 
-    pub fn data_feed_id_to_whitelist_status(
-        &self,
+    /// Extends the whitelist of `reader` for the data feed by `periods`
+    /// periods of `price`, the payment is checked by the caller
+    pub fn subscribe(
+        &mut self,
+        price: &SubscriptionPrice,
         data_feed_id: &Bytes32,
-        reader: &[u8],
-    ) -> Option<(u64, Bytes32)> {
-        let key = Self::double_hash(data_feed_id, reader);
-        let s = match &self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(s) => *s,
-            ReadWrite::Write(s) => *s,
-        };
-        s.get(&key)
-            .map(|w| (w.expiration_timestamp, w.indefinite_whitelist_count))
+        reader: &Address,
+        periods: u64,
+    ) -> Result<Timestamp, Error> {
+        self.indexing_user(data_feed_id, reader, |w| {
+            api3_common::extend_subscription(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &NearClock::now(),
+                price,
+                data_feed_id,
+                &reader.0,
+                periods,
+            )
+        })
     }
 
-    fn double_hash(service_id: &Bytes32, address: &[u8]) -> Bytes32 {
-        keccak_packed(&[
-            Token::FixedBytes(service_id.to_vec()),
-            Token::FixedBytes(address.to_vec()),
-        ])
+    /// Sets the whitelist expiration of the permit if it is signed with
+    /// `setter_key`, see `api3_common::use_whitelist_permit`
+    pub fn set_whitelist_expiration_with_permit(
+        &mut self,
+        permit_nonces: &mut LookupMap<Bytes32, u64>,
+        permit: &WhitelistPermit,
+        setter_key: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        api3_common::use_whitelist_permit::<_, _, SignatureVerify, _>(
+            &*self,
+            &mut U64HashMap::requires_write(permit_nonces),
+            &NearClock::now(),
+            permit,
+            setter_key,
+            signature,
+        )?;
+        let user = Address(permit.user.clone());
+        self.indexing_user(&permit.service_id, &user, |w| {
+            api3_common::set_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &permit.service_id,
+                &permit.user,
+                permit.expiration_timestamp,
+            )
+        });
+        Ok(())
     }
 
-    fn triple_hash(service_id: &Bytes32, address0: &[u8], address1: &[u8]) -> Bytes32 {
-        keccak_packed(&[
-            Token::FixedBytes(service_id.to_vec()),
-            Token::FixedBytes(address0.to_vec()),
-            Token::FixedBytes(address1.to_vec()),
-        ])
+    /// Returns the tier the whitelist puts the user in, `None` if the user
+    /// is not whitelisted. Does not consider the unlimited reader role.
+    pub fn reader_tier(&self, service_id: &Bytes32, user: &Address) -> Option<ReaderTier> {
+        let now = NearClock::now().current_timestamp();
+        self.service_id_to_user_to_whitelist_status
+            .get(&whitelist_status_key(service_id, &user.0))
+            .and_then(|status| api3_common::whitelist_tier(&status, now))
+            .or_else(|| {
+                self.merkle_proof
+                    .as_ref()
+                    .filter(|(roots, proof)| {
+                        api3_common::user_is_whitelisted_with_proof(
+                            roots, service_id, &user.0, proof,
+                        )
+                    })
+                    .map(|_| ReaderTier::MerkleWhitelisted)
+            })
+    }
+
+    pub fn data_feed_id_to_whitelist_status(
+        &self,
+        data_feed_id: &Bytes32,
+        reader: &[u8],
+    ) -> Option<(u64, Bytes32)> {
+        self.service_id_to_user_to_whitelist_status
+            .get(&whitelist_status_key(data_feed_id, reader))
+            .map(|w| {
+                (
+                    w.expiration_timestamp.as_secs(),
+                    Bytes32::from(&w.indefinite_whitelist_count),
+                )
+            })
     }
 }
 
@@ -120,57 +420,48 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> Whitelist for NearWhi
     /// `service_id` Service ID
     /// `user` User address
     fn user_is_whitelisted(&self, service_id: &Bytes32, user: &Address) -> bool {
-        let hash = Self::double_hash(service_id, &user.0);
-        let s = match &self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(s) => *s,
-            ReadWrite::Write(s) => *s,
-        };
-        s.get(&hash)
-            .map(|status| {
-                let count = U256::from_big_endian(&status.indefinite_whitelist_count);
-                count > U256::from(0)
-                    || status.expiration_timestamp > near_sdk::env::block_timestamp()
-            })
-            .unwrap_or(false)
+        let clock = NearClock::now();
+        api3_common::user_is_whitelisted(
+            &self.service_id_to_user_to_whitelist_status,
+            &clock,
+            service_id,
+            &user.0,
+        ) || self.merkle_proof.as_ref().is_some_and(|(roots, proof)| {
+            api3_common::user_is_whitelisted_with_proof(roots, service_id, &user.0, proof)
+        })
     }
 
     fn extend_whitelist_expiration(
         &mut self,
         service_id: &Bytes32,
         user: &Self::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     ) {
-        let m = match &mut self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
-
-        let hash = Self::double_hash(service_id, &user.0);
-
-        let mut whitelist_status = (*m).remove(&hash).unwrap_or_default();
-        near_check_result(ensure!(
-            expiration_timestamp > whitelist_status.expiration_timestamp,
-            Error::DoesNotExtendExpiration
-        ));
-
-        whitelist_status.expiration_timestamp = expiration_timestamp;
-        (*m).insert(&hash, &whitelist_status);
+        let r = self.indexing_user(service_id, user, |w| {
+            api3_common::extend_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                service_id,
+                &user.0,
+                expiration_timestamp,
+            )
+        });
+        near_check_result(r)
     }
 
     fn set_whitelist_expiration(
         &mut self,
         service_id: &Bytes32,
         user: &Self::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     ) {
-        let m = match &mut self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
-        let hash = Self::double_hash(service_id, &user.0);
-        let mut whitelist_status = (*m).remove(&hash).unwrap_or_default();
-        whitelist_status.expiration_timestamp = expiration_timestamp;
-        (*m).insert(&hash, &whitelist_status);
+        self.indexing_user(service_id, user, |w| {
+            api3_common::set_whitelist_expiration(
+                &mut w.service_id_to_user_to_whitelist_status,
+                service_id,
+                &user.0,
+                expiration_timestamp,
+            )
+        })
     }
 
     fn set_indefinite_whitelist_status(
@@ -178,43 +469,28 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> Whitelist for NearWhi
         service_id: &Bytes32,
         user: &Self::Address,
         status: bool,
-    ) -> U256 {
-        let w_hash = Self::double_hash(service_id, &user.0);
-        let i_hash = Self::triple_hash(service_id, &user.0, &msg_sender().0);
-
-        let whitelist = match &mut self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
-        let indefinite = match &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status
-        {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
-
-        let mut indefinite_count = whitelist
-            .get(&w_hash)
-            .map(|f| U256::from_big_endian(&f.indefinite_whitelist_count))
-            .unwrap_or_else(|| U256::from(0u32));
-        let indefinite_status = indefinite.get(&i_hash).unwrap_or(false);
-
-        if status && !indefinite_status {
-            indefinite.remove(&i_hash);
-            indefinite.insert(&i_hash, &true);
-            let mut whitelist_status = whitelist.remove(&w_hash).unwrap_or_default();
-            indefinite_count = indefinite_count.add(U256::from(1u8));
-            whitelist_status.indefinite_whitelist_count = Bytes32::from(&indefinite_count);
-            whitelist.insert(&w_hash, &whitelist_status);
-        } else if !status && indefinite_status {
-            indefinite.remove(&i_hash);
-            indefinite.insert(&i_hash, &false);
-            let mut whitelist_status = whitelist.remove(&w_hash).unwrap_or_default();
-            indefinite_count = indefinite_count.sub(U256::from(1u8));
-            whitelist_status.indefinite_whitelist_count = Bytes32::from(&indefinite_count);
-            whitelist.insert(&w_hash, &whitelist_status);
+    ) -> Result<U256, Error> {
+        let setter = msg_sender();
+        let indefinite_key = indefinite_whitelist_status_key(service_id, &user.0, &setter.0);
+        let indexed = self
+            .service_id_to_user_to_setter_to_indefinite_whitelist_status
+            .get(&indefinite_key)
+            .is_some();
+        let count = self.indexing_user(service_id, user, |w| {
+            api3_common::set_indefinite_whitelist_status(
+                &mut w.service_id_to_user_to_whitelist_status,
+                &mut w.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+                service_id,
+                &user.0,
+                &setter.0,
+                status,
+            )
+        })?;
+        if !indexed {
+            let key = whitelist_status_key(service_id, &user.0);
+            self.index_mut().insert_setter(&key, &setter);
         }
-
-        indefinite_count
+        Ok(count)
     }
 
     fn revoke_indefinite_whitelist_status(
@@ -222,39 +498,106 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> Whitelist for NearWhi
         service_id: &Bytes32,
         user: &Self::Address,
         setter: &Self::Address,
-    ) -> (bool, U256) {
-        let user_hash = Self::double_hash(service_id, &user.0);
-        let setter_hash = Self::triple_hash(service_id, &user.0, &setter.0);
+    ) -> Result<(bool, U256), Error> {
+        api3_common::revoke_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            &user.0,
+            &setter.0,
+        )
+    }
+}
 
-        let indefinite = match &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status
-        {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
+impl<'a, Access: AccessControlRegistry<Address = Address>> WhitelistEnumerable
+    for NearWhitelist<'a, Access>
+{
+    fn whitelisted_user_count(&self, service_id: &Bytes32) -> usize {
+        self.index().users(service_id).len() as usize
+    }
 
-        let whitelist = match &mut self.service_id_to_user_to_whitelist_status {
-            ReadWrite::ReadOnly(_) => panic!("wrong privilege"),
-            ReadWrite::Write(m) => m,
-        };
+    fn whitelisted_users_of(
+        &self,
+        service_id: &Bytes32,
+        from_index: usize,
+        limit: usize,
+    ) -> Vec<Address> {
+        self.index()
+            .users(service_id)
+            .iter()
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+
+    fn indefinite_whitelist_setters_of(
+        &self,
+        service_id: &Bytes32,
+        user: &Address,
+    ) -> Vec<Address> {
+        self.index()
+            .user_to_indefinite_whitelist_setters
+            .get(&whitelist_status_key(service_id, &user.0))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|setter| {
+                self.data_feed_id_to_reader_to_setter_to_indefinite_whitelist_status(
+                    service_id, &user.0, &setter.0,
+                ) == Some(true)
+            })
+            .collect()
+    }
+
+    fn sweep_expired(&mut self, service_id: &Bytes32, from_index: usize, limit: usize) -> usize {
+        let now = NearClock::now().current_timestamp();
+        let mut users = self.index().users(service_id);
+        let mut index = from_index as u64;
+        let mut swept = 0;
+        for _ in 0..limit {
+            let user = match users.as_vector().get(index) {
+                Some(user) => user,
+                None => break,
+            };
+            let key = whitelist_status_key(service_id, &user.0);
+            let active = self
+                .service_id_to_user_to_whitelist_status
+                .get(&key)
+                .is_some_and(|s| s.is_active(now));
+            if active {
+                index += 1;
+                continue;
+            }
+
+            // without an indefinite whitelist all the setters revoked theirs
+            let setters = self
+                .index()
+                .user_to_indefinite_whitelist_setters
+                .get(&key)
+                .unwrap_or_default();
+            for setter in setters {
+                self.service_id_to_user_to_setter_to_indefinite_whitelist_status
+                    .remove(&indefinite_whitelist_status_key(
+                        service_id, &user.0, &setter.0,
+                    ));
+            }
+            self.index_mut()
+                .user_to_indefinite_whitelist_setters
+                .remove(&key);
+            self.service_id_to_user_to_whitelist_status.remove(&key);
+            // the last user takes the place of the removed one, check it next
+            users.remove(&user);
+            swept += 1;
+        }
 
-        let mut indefinite_count = whitelist
-            .get(&user_hash)
-            .map(|f| U256::from_big_endian(&f.indefinite_whitelist_count))
-            .unwrap_or_else(|| U256::from(0u32));
-
-        let indefinite_status = indefinite.get(&setter_hash).unwrap_or(false);
-        if indefinite_status {
-            indefinite.remove(&setter_hash);
-            indefinite.insert(&setter_hash, &false);
-
-            let mut whitelist_status = whitelist.remove(&user_hash).unwrap_or_default();
-            indefinite_count = indefinite_count.sub(U256::from(1u8));
-            whitelist_status.indefinite_whitelist_count = Bytes32::from(&indefinite_count);
-            whitelist.insert(&user_hash, &whitelist_status);
-            (true, indefinite_count)
-        } else {
-            (false, indefinite_count)
+        if swept > 0 {
+            let index = self.index_mut();
+            if users.is_empty() {
+                index.service_id_to_users.remove(service_id);
+            } else {
+                index.service_id_to_users.insert(service_id, &users);
+            }
         }
+        swept
     }
 }
 
@@ -320,7 +663,7 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> WhitelistWithManager
         &mut self,
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     ) {
         near_check_result(ensure!(
             self.has_whitelist_expiration_extender_role_or_is_manager(&msg_sender()),
@@ -335,7 +678,7 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> WhitelistWithManager
         &mut self,
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
-        expiration_timestamp: u64,
+        expiration_timestamp: Timestamp,
     ) {
         near_check_result(ensure!(
             self.has_whitelist_expiration_setter_role_or_is_manager(&msg_sender()),
@@ -353,7 +696,7 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> WhitelistWithManager
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
         status: bool,
-    ) -> U256 {
+    ) -> Result<U256, Error> {
         near_check_result(ensure!(
             self.has_indefinite_whitelister_role_or_is_manager(&msg_sender()),
             Error::AccessDenied
@@ -370,7 +713,7 @@ impl<'a, Access: AccessControlRegistry<Address = Address>> WhitelistWithManager
         service_id: &Bytes32,
         user: &<Self as Whitelist>::Address,
         setter: &<Self as Whitelist>::Address,
-    ) -> (bool, U256) {
+    ) -> Result<(bool, U256), Error> {
         near_check_result(ensure!(
             !self.has_indefinite_whitelister_role_or_is_manager(setter),
             Error::SetterCanSetIndefiniteStatus
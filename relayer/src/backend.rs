@@ -0,0 +1,34 @@
+use crate::RelayerError;
+use api3_common::signer::{SignedData, SignedDataBatch};
+use api3_common::{Bytes32, DataPoint};
+
+/// An update the relayer submits to the chain
+#[derive(Clone, Debug)]
+pub enum SignedUpdate {
+    /// `update_beacon_with_signed_data` with the signed data
+    Beacon(SignedData),
+    /// `update_dapi_with_signed_data` with the signed data of the Beacons of
+    /// the dAPI, in order. The Beacons without a signature are read from storage.
+    Dapi(SignedDataBatch),
+}
+
+/// A chain the relayer pushes signed data to
+pub trait ChainBackend {
+    /// Reads the data point with ID, the default data point if it was never updated
+    fn data_point(&self, data_point_id: &Bytes32) -> Result<DataPoint, RelayerError>;
+
+    /// Submits the updates in order, in as few transactions as the chain
+    /// takes. Returns the result of each update, the updates of a failed
+    /// transaction all fail with its error.
+    fn update_with_signed_data(
+        &mut self,
+        updates: &[SignedUpdate],
+    ) -> Vec<Result<(), RelayerError>>;
+
+    /// If `update_dapi_with_signed_data` takes signed data for the leading
+    /// Beacons of the dAPI only, the Beacons after the first one without
+    /// signed data are then read from storage
+    fn signed_beacons_first(&self) -> bool {
+        false
+    }
+}
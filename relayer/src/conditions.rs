@@ -0,0 +1,114 @@
+use api3_common::abi::U256;
+use api3_common::{calculate_update_in_percentage, DataPoint, HUNDRED_PERCENT};
+use serde::Deserialize;
+
+/// When a data feed is worth updating, same as the update conditions of Airnode feeds
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub struct UpdateConditions {
+    /// The deviation from the on-chain value that triggers an update,
+    /// `HUNDRED_PERCENT` is 100%. Zero updates on every new timestamp.
+    #[serde(default = "default_deviation_threshold")]
+    pub deviation_threshold: u32,
+    /// Seconds after which the feed is updated even if the value did not deviate
+    #[serde(default = "default_heartbeat_interval")]
+    pub heartbeat_interval: u64,
+}
+
+/// 1%
+fn default_deviation_threshold() -> u32 {
+    HUNDRED_PERCENT / 100
+}
+
+/// One day
+fn default_heartbeat_interval() -> u64 {
+    86_400
+}
+
+impl Default for UpdateConditions {
+    fn default() -> Self {
+        Self {
+            deviation_threshold: default_deviation_threshold(),
+            heartbeat_interval: default_heartbeat_interval(),
+        }
+    }
+}
+
+impl UpdateConditions {
+    /// Returns if the feed at `current` should be updated to `updated`
+    ///
+    /// # Arguments
+    ///
+    /// * `current` The data point on chain
+    /// * `updated` The data point the update would store
+    pub fn is_update_needed(&self, current: &DataPoint, updated: &DataPoint) -> bool {
+        if updated.timestamp <= current.timestamp {
+            return false;
+        }
+        current.timestamp.is_zero()
+            || updated.timestamp
+                >= current
+                    .timestamp
+                    .saturating_add_secs(self.heartbeat_interval)
+            || calculate_update_in_percentage(&current.value, &updated.value)
+                >= U256::from(self.deviation_threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api3_common::abi::Int;
+    use api3_common::Timestamp;
+
+    fn data_point(value: u64, timestamp: u64) -> DataPoint {
+        DataPoint::new(Int::from(value), Timestamp::from_secs(timestamp))
+    }
+
+    #[test]
+    fn updates_on_deviation_or_heartbeat() {
+        let conditions = UpdateConditions {
+            deviation_threshold: HUNDRED_PERCENT / 100,
+            heartbeat_interval: 100,
+        };
+        let current = data_point(1000, 1000);
+        assert!(!conditions.is_update_needed(&current, &data_point(1009, 1010)));
+        assert!(conditions.is_update_needed(&current, &data_point(1010, 1010)));
+        assert!(conditions.is_update_needed(&current, &data_point(990, 1010)));
+        assert!(conditions.is_update_needed(&current, &data_point(1000, 1100)));
+
+        // never with an older or the same timestamp
+        assert!(!conditions.is_update_needed(&current, &data_point(2000, 1000)));
+        assert!(!conditions.is_update_needed(&current, &data_point(2000, 999)));
+    }
+
+    #[test]
+    fn updates_feeds_never_updated() {
+        let conditions = UpdateConditions::default();
+        assert!(conditions.is_update_needed(&DataPoint::default(), &data_point(0, 1)));
+    }
+
+    #[test]
+    fn zero_threshold_updates_on_every_timestamp() {
+        let conditions = UpdateConditions {
+            deviation_threshold: 0,
+            heartbeat_interval: 100,
+        };
+        assert!(conditions.is_update_needed(&data_point(1000, 1000), &data_point(1000, 1001)));
+    }
+
+    #[test]
+    fn deviation_of_negative_values() {
+        let conditions = UpdateConditions::default();
+        let minus = |v: u64| DataPoint {
+            value: Int::from(v).overflowing_neg().0,
+            ..data_point(0, 1010)
+        };
+        let current = DataPoint {
+            timestamp: Timestamp::from_secs(1000),
+            ..minus(1000)
+        };
+        assert!(!conditions.is_update_needed(&current, &minus(1005)));
+        assert!(conditions.is_update_needed(&current, &minus(1010)));
+        assert!(conditions.is_update_needed(&current, &data_point(1, 1010)));
+    }
+}
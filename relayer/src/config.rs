@@ -0,0 +1,77 @@
+use crate::near::NearConfig;
+use crate::serde_hex;
+use crate::solana::SolanaConfig;
+use crate::{RelayerError, UpdateConditions};
+use api3_common::{derive_beacon_id, Bytes, Bytes32};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The config file of the relayer, in JSON
+#[derive(Deserialize)]
+pub struct Config {
+    /// The signed data source, an `http` or `https` URL or a file
+    pub source: String,
+    /// Seconds between polls of the source
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    #[serde(default)]
+    pub conditions: UpdateConditions,
+    #[serde(flatten)]
+    pub feeds: Feeds,
+    pub chain: ChainConfig,
+}
+
+fn default_poll_interval() -> u64 {
+    30
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, RelayerError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| RelayerError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&json).map_err(|e| RelayerError::InvalidConfig(e.to_string()))
+    }
+}
+
+/// The data feeds the relayer keeps updated
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Feeds {
+    /// Beacons updated with `update_beacon_with_signed_data`
+    #[serde(default)]
+    pub beacons: Vec<Beacon>,
+    /// dAPIs updated with `update_dapi_with_signed_data`, each is its Beacons in order.
+    /// Solana takes signed data for the leading Beacons only, see `SolanaConfig`.
+    #[serde(default)]
+    pub dapis: Vec<Vec<Beacon>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Beacon {
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    pub airnode: Bytes,
+    #[serde(deserialize_with = "serde_hex::bytes32")]
+    pub template_id: Bytes32,
+}
+
+impl Beacon {
+    pub fn new(airnode: Bytes, template_id: Bytes32) -> Self {
+        Self {
+            airnode,
+            template_id,
+        }
+    }
+
+    pub fn id(&self) -> Result<Bytes32, RelayerError> {
+        Ok(derive_beacon_id(self.airnode.clone(), self.template_id)?)
+    }
+}
+
+/// The chain backend to relay to
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainConfig {
+    Near(NearConfig),
+    Solana(SolanaConfig),
+    /// Dry run against a chain in memory that starts empty
+    Mock,
+}
@@ -0,0 +1,61 @@
+use api3_common::{Error, ErrorPayload};
+use api3_near_client::ClientError;
+use std::fmt;
+
+#[derive(Clone, Debug)]
+pub enum RelayerError {
+    /// The signed data could not be fetched or parsed
+    Source(String),
+    /// The request to the chain failed before it reached the contract
+    Rpc(String),
+    /// The contract rejected the call with an `api3_common::Error`
+    Api3(Error),
+    /// The chain rejected the call for another reason
+    Chain(String),
+    InvalidConfig(String),
+    /// The chain backend cannot make the call
+    Unsupported(&'static str),
+}
+
+impl RelayerError {
+    /// The error of a failed call, the `api3_common::Error` if the message
+    /// carries an `ErrorPayload`
+    pub fn from_failure(message: String) -> Self {
+        match ErrorPayload::parse(&message).and_then(|p| Error::from_code(p.code)) {
+            Some(e) => RelayerError::Api3(e),
+            None => RelayerError::Chain(message),
+        }
+    }
+}
+
+impl fmt::Display for RelayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayerError::Source(e) => write!(f, "signed data source: {}", e),
+            RelayerError::Rpc(e) => write!(f, "rpc: {}", e),
+            RelayerError::Api3(e) => write!(f, "{} ({})", e, e.name()),
+            RelayerError::Chain(e) => write!(f, "chain: {}", e),
+            RelayerError::InvalidConfig(e) => write!(f, "invalid config: {}", e),
+            RelayerError::Unsupported(e) => write!(f, "unsupported: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RelayerError {}
+
+impl From<Error> for RelayerError {
+    fn from(e: Error) -> Self {
+        RelayerError::Api3(e)
+    }
+}
+
+impl From<ClientError> for RelayerError {
+    fn from(e: ClientError) -> Self {
+        match e {
+            ClientError::Transport(e) => RelayerError::Rpc(e),
+            ClientError::Api3(e) => RelayerError::Api3(e),
+            ClientError::Failure(e) | ClientError::InvalidResult(e) => RelayerError::Chain(e),
+            ClientError::InvalidCredentials(e) => RelayerError::InvalidConfig(e),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Relays the signed data Airnodes publish off-chain to the data feeds on
+//! chain. Signed data is polled from a `SignedDataSource`, run against the
+//! on-chain state with the `api3_common` update logic and pushed to a
+//! `ChainBackend` if it meets the `UpdateConditions`.
+
+mod backend;
+mod conditions;
+pub mod config;
+mod error;
+pub mod mock;
+pub mod near;
+mod relayer;
+mod rpc;
+mod serde_hex;
+pub mod solana;
+pub mod source;
+
+pub use crate::backend::{ChainBackend, SignedUpdate};
+pub use crate::conditions::UpdateConditions;
+pub use crate::error::RelayerError;
+pub use crate::relayer::{AirnodeVerifier, RelayReport, Relayer, SystemClock};
+pub use crate::source::SignedDataSource;
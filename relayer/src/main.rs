@@ -0,0 +1,69 @@
+use api3_relayer::config::{ChainConfig, Config};
+use api3_relayer::mock::MockChain;
+use api3_relayer::near::NearBackend;
+use api3_relayer::solana::SolanaBackend;
+use api3_relayer::{source, ChainBackend, Relayer, RelayerError, SystemClock};
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Relays the signed data of Airnodes to the data feeds on chain that meet the update conditions
+#[derive(Parser, Debug)]
+#[command(name = "relayer", version)]
+struct Args {
+    /// The config file, in JSON
+    #[arg(long)]
+    config: PathBuf,
+    /// Runs a single round instead of polling
+    #[arg(long)]
+    once: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let r = Config::load(&args.config).and_then(|config| match config.chain {
+        ChainConfig::Near(ref c) => {
+            let chain = NearBackend::new(c)?;
+            relay(&config, chain, args.once)
+        }
+        ChainConfig::Solana(ref c) => {
+            let chain = SolanaBackend::new(c)?;
+            relay(&config, chain, args.once)
+        }
+        ChainConfig::Mock => relay(&config, MockChain::new(SystemClock), args.once),
+    });
+    match r {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn relay<C: ChainBackend>(config: &Config, chain: C, once: bool) -> Result<(), RelayerError> {
+    let source = source::from_location(&config.source);
+    let mut relayer = Relayer::new(source, chain, SystemClock, config.conditions, &config.feeds)?;
+    for warning in relayer.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    loop {
+        match relayer.run_once() {
+            Ok(report) => {
+                for id in report.updated {
+                    println!("updated 0x{}", hex::encode(id));
+                }
+                for (id, e) in report.failed {
+                    eprintln!("failed to update 0x{}: {}", hex::encode(id), e);
+                }
+            }
+            Err(e) if once => return Err(e),
+            Err(e) => eprintln!("error: {}", e),
+        }
+        if once {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(config.poll_interval));
+    }
+}
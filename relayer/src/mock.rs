@@ -0,0 +1,111 @@
+//! A chain kept in memory that runs the `api3_common` update logic, for tests
+//! and dry runs of the relayer
+
+use crate::{AirnodeVerifier, ChainBackend, RelayerError, SignedUpdate};
+use api3_common::abi::Uint;
+use api3_common::dummy::InMemoryStorage;
+use api3_common::signer::{SignedData, SignedDataBatch};
+use api3_common::{
+    ensure, process_beacon_update, update_dapi_with_signed_data, Bytes32, DataPoint, Error,
+    SignatureManger, Storage, TimestampChecker,
+};
+
+/// Data points that were never updated are the default one, as on chain
+#[derive(Clone, Default)]
+struct ChainStorage(InMemoryStorage<DataPoint>);
+
+impl Storage<DataPoint> for ChainStorage {
+    fn get(&self, key: &Bytes32) -> Option<DataPoint> {
+        Some(self.0.get(key).unwrap_or_default())
+    }
+
+    fn store(&mut self, key: Bytes32, t: DataPoint) {
+        self.0.store(key, t)
+    }
+}
+
+/// The chain backend in memory, it makes the updates of a round in one
+/// transaction that fails as a whole
+pub struct MockChain<T: TimestampChecker> {
+    data_points: ChainStorage,
+    clock: T,
+    updates: Vec<Bytes32>,
+}
+
+impl<T: TimestampChecker> MockChain<T> {
+    pub fn new(clock: T) -> Self {
+        Self {
+            data_points: ChainStorage::default(),
+            clock,
+            updates: vec![],
+        }
+    }
+
+    /// Stores the data point as if it was updated before
+    pub fn store(&mut self, data_point_id: Bytes32, data_point: DataPoint) {
+        self.data_points.store(data_point_id, data_point);
+    }
+
+    /// The IDs of the data points updated so far, in order
+    pub fn updates(&self) -> &[Bytes32] {
+        &self.updates
+    }
+}
+
+impl<T: TimestampChecker> ChainBackend for MockChain<T> {
+    fn data_point(&self, data_point_id: &Bytes32) -> Result<DataPoint, RelayerError> {
+        self.data_points
+            .get(data_point_id)
+            .ok_or(RelayerError::Api3(Error::BeaconDataNotFound))
+    }
+
+    fn update_with_signed_data(
+        &mut self,
+        updates: &[SignedUpdate],
+    ) -> Vec<Result<(), RelayerError>> {
+        let (data_points, applied) = (self.data_points.clone(), self.updates.len());
+        let r = updates.iter().try_for_each(|u| match u {
+            SignedUpdate::Beacon(signed) => self.update_beacon(signed),
+            SignedUpdate::Dapi(batch) => self.update_dapi(batch),
+        });
+        if r.is_err() {
+            self.data_points = data_points;
+            self.updates.truncate(applied);
+        }
+        vec![r; updates.len()]
+    }
+}
+
+impl<T: TimestampChecker> MockChain<T> {
+    fn update_beacon(&mut self, signed: &SignedData) -> Result<(), RelayerError> {
+        let beacon_id = signed.beacon_id()?;
+        ensure!(
+            AirnodeVerifier::verify(&signed.airnode, &signed.message(), &signed.signature),
+            Error::InvalidSignature
+        )?;
+        process_beacon_update(
+            &mut self.data_points,
+            beacon_id,
+            Uint::from_big_endian(&signed.timestamp),
+            signed.data.clone(),
+        )?;
+        self.updates.push(beacon_id);
+        Ok(())
+    }
+
+    fn update_dapi(&mut self, batch: &SignedDataBatch) -> Result<(), RelayerError> {
+        let batch = batch.clone();
+        let dapi_id = update_dapi_with_signed_data::<_, AirnodeVerifier, _>(
+            &mut self.data_points,
+            &self.clock,
+            batch.airnodes,
+            batch.template_ids,
+            batch.timestamps,
+            batch.data,
+            batch.signatures,
+        )
+        .map_err(Error::from)?;
+        self.updates.push(dapi_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Relays to the NEAR contract with `api3-near-client`, calls are signed with
+//! a full access key of the relayer account.
+
+use crate::{ChainBackend, RelayerError, SignedUpdate};
+use api3_common::abi::Int;
+use api3_common::{Bytes, Bytes32, DataPoint, Timestamp};
+use api3_near_client::{BatchCall, DapiServer, JsonRpcTransport};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// The prefix of the `data_points` map in the contract state
+const DATA_POINTS_PREFIX: u8 = b'd';
+
+#[derive(Deserialize)]
+pub struct NearConfig {
+    pub rpc_url: String,
+    /// The account of the contract
+    pub contract_id: String,
+    /// The key file of the relayer account, as `near login` writes them,
+    /// e.g. `~/.near-credentials/testnet/relayer.testnet.json`
+    pub credentials: PathBuf,
+    /// The most updates sent in one transaction, they share its 300 TGas
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_batch_size() -> usize {
+    10
+}
+
+pub struct NearBackend {
+    client: DapiServer<JsonRpcTransport>,
+    batch_size: usize,
+}
+
+impl NearBackend {
+    pub fn new(config: &NearConfig) -> Result<Self, RelayerError> {
+        let transport = JsonRpcTransport::from_credentials(
+            config.rpc_url.clone(),
+            config.contract_id.clone(),
+            &config.credentials,
+        )?;
+        if config.batch_size == 0 {
+            return Err(RelayerError::InvalidConfig(String::from(
+                "batch_size must be at least one",
+            )));
+        }
+        Ok(Self {
+            client: DapiServer::new(transport),
+            batch_size: config.batch_size,
+        })
+    }
+}
+
+impl ChainBackend for NearBackend {
+    /// Reads the data point from the contract state, the RPC node must serve
+    /// `view_state` for the contract
+    fn data_point(&self, data_point_id: &Bytes32) -> Result<DataPoint, RelayerError> {
+        let key = state_key(data_point_id);
+        let state = self.client.transport().view_state(&key)?;
+        match state.into_iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => decode_data_point(&value),
+            None => Ok(DataPoint::default()),
+        }
+    }
+
+    /// Makes up to `batch_size` updates in each transaction, a function call action per update
+    fn update_with_signed_data(
+        &mut self,
+        updates: &[SignedUpdate],
+    ) -> Vec<Result<(), RelayerError>> {
+        updates
+            .chunks(self.batch_size)
+            .flat_map(|chunk| {
+                let calls = chunk.iter().map(batch_call).collect::<Vec<_>>();
+                let r = self.client.batch(&calls).map_err(RelayerError::from);
+                vec![r; chunk.len()]
+            })
+            .collect()
+    }
+}
+
+fn batch_call(update: &SignedUpdate) -> BatchCall {
+    match update {
+        SignedUpdate::Beacon(s) => BatchCall::update_beacon_with_signed_data(
+            &s.airnode,
+            &s.template_id,
+            &s.timestamp,
+            &s.data,
+            &s.signature,
+        ),
+        SignedUpdate::Dapi(b) => BatchCall::update_dapi_with_signed_data(
+            &b.airnodes,
+            &b.template_ids,
+            &b.timestamps,
+            &b.data,
+            &b.signatures,
+        ),
+    }
+}
+
+/// The key of the data point in the contract state, the prefix of the
+/// `LookupMap` and the borsh serialized ID
+fn state_key(data_point_id: &Bytes32) -> Bytes {
+    let mut key = vec![DATA_POINTS_PREFIX];
+    key.extend_from_slice(data_point_id);
+    key
+}
+
+/// Decodes the borsh serialized `NearDataPoint` of the contract
+fn decode_data_point(raw: &[u8]) -> Result<DataPoint, RelayerError> {
+    if raw.len() != 36 {
+        return Err(RelayerError::Chain(format!(
+            "invalid data point {}",
+            hex::encode(raw)
+        )));
+    }
+    let timestamp = u32::from_le_bytes([raw[32], raw[33], raw[34], raw[35]]);
+    Ok(DataPoint::new(
+        Int::from_big_endian(&raw[..32]),
+        Timestamp::from_secs(u64::from(timestamp)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_data_points() {
+        let mut raw = vec![0xffu8; 32];
+        raw.extend_from_slice(&1_650_000_000u32.to_le_bytes());
+        assert_eq!(
+            decode_data_point(&raw).unwrap(),
+            DataPoint::new(Int::MAX, Timestamp::from_secs(1_650_000_000))
+        );
+        assert!(decode_data_point(&raw[1..]).is_err());
+        assert_eq!(state_key(&[7u8; 32])[..2], [b'd', 7]);
+    }
+}
@@ -0,0 +1,675 @@
+use crate::config::{Beacon, Feeds};
+use crate::{ChainBackend, RelayerError, SignedDataSource, SignedUpdate, UpdateConditions};
+use api3_common::abi::Uint;
+use api3_common::dummy::InMemoryStorage;
+use api3_common::signer::{Ed25519Verifier, Secp256k1Verifier, SignedData, SignedDataBatch};
+use api3_common::{
+    derive_dapi_id, process_beacon_update, update_dapi_with_signed_data, Bytes32, Error,
+    SignatureManger, Storage, Timestamp, TimestampChecker,
+};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Verifies the signatures of both kinds of Airnodes, ed25519 Airnodes are
+/// 32 byte public keys and secp256k1 Airnodes are 20 byte addresses
+pub struct AirnodeVerifier;
+
+impl SignatureManger for AirnodeVerifier {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        match key.len() {
+            32 => Ed25519Verifier::verify(key, message, signature),
+            20 => Secp256k1Verifier::verify(key, message, signature),
+            _ => false,
+        }
+    }
+}
+
+/// The clock of the machine the relayer runs on
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl TimestampChecker for SystemClock {
+    fn current_timestamp(&self) -> Timestamp {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock before the unix epoch");
+        Timestamp::from_secs(now.as_secs())
+    }
+}
+
+/// What a round of the relayer did
+#[derive(Debug, Default)]
+pub struct RelayReport {
+    /// The IDs of the data feeds updated
+    pub updated: Vec<Bytes32>,
+    /// The IDs of the data feeds that could not be updated and why
+    pub failed: Vec<(Bytes32, RelayerError)>,
+}
+
+/// Pushes the signed data of `source` to `chain` for the feeds that meet the update conditions
+pub struct Relayer<S: SignedDataSource, C: ChainBackend, T: TimestampChecker> {
+    source: S,
+    chain: C,
+    clock: T,
+    conditions: UpdateConditions,
+    beacons: Vec<(Bytes32, Beacon)>,
+    dapis: Vec<(Bytes32, Vec<(Bytes32, Beacon)>)>,
+}
+
+impl<S: SignedDataSource, C: ChainBackend, T: TimestampChecker> Relayer<S, C, T> {
+    /// The relayer of `feeds`, `clock` tells which signed data the chain still accepts
+    pub fn new(
+        source: S,
+        chain: C,
+        clock: T,
+        conditions: UpdateConditions,
+        feeds: &Feeds,
+    ) -> Result<Self, RelayerError> {
+        let with_ids = |beacons: &[Beacon]| {
+            beacons
+                .iter()
+                .map(|b| Ok((b.id()?, b.clone())))
+                .collect::<Result<Vec<_>, RelayerError>>()
+        };
+        let dapis = feeds
+            .dapis
+            .iter()
+            .map(|beacons| {
+                if beacons.len() < 2 {
+                    return Err(RelayerError::InvalidConfig(String::from(
+                        "dAPIs need at least two Beacons",
+                    )));
+                }
+                let beacons = with_ids(beacons)?;
+                let ids = beacons.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+                Ok((derive_dapi_id(&ids), beacons))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            source,
+            chain,
+            clock,
+            conditions,
+            beacons: with_ids(&feeds.beacons)?,
+            dapis,
+        })
+    }
+
+    pub fn chain(&self) -> &C {
+        &self.chain
+    }
+
+    /// What the operator should know about relaying the feeds to the chain,
+    /// reported once at startup
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.chain.signed_beacons_first() && !self.dapis.is_empty() {
+            warnings.push(String::from(
+                "the chain takes signed data for the leading Beacons of a dAPI only, \
+                 the Beacons after the first one without signed data are read from storage",
+            ));
+        }
+        warnings
+    }
+
+    /// Fetches the signed data once and updates the feeds that need it, in
+    /// as few transactions as the chain takes. Fails only if the source
+    /// fails, the feeds that fail are in the report.
+    pub fn run_once(&mut self) -> Result<RelayReport, RelayerError> {
+        let signed = self.fetch_signed_data()?;
+        let mut report = RelayReport::default();
+
+        let mut due = vec![];
+        for (beacon_id, _) in &self.beacons {
+            let Some(signed) = signed.get(beacon_id) else {
+                continue;
+            };
+            match beacon_update(&self.chain, &self.conditions, *beacon_id, signed) {
+                Ok(Some(update)) => due.push((*beacon_id, update)),
+                Ok(None) => {}
+                Err(e) => report.failed.push((*beacon_id, e)),
+            }
+        }
+        for (dapi_id, beacons) in &self.dapis {
+            let r = dapi_update(
+                &self.chain,
+                &self.clock,
+                &self.conditions,
+                *dapi_id,
+                beacons,
+                &signed,
+            );
+            match r {
+                Ok(Some(update)) => due.push((*dapi_id, update)),
+                Ok(None) => {}
+                Err(e) => report.failed.push((*dapi_id, e)),
+            }
+        }
+        if due.is_empty() {
+            return Ok(report);
+        }
+
+        let (ids, updates): (Vec<_>, Vec<_>) = due.into_iter().unzip();
+        let results = self.chain.update_with_signed_data(&updates);
+        for (id, r) in ids.into_iter().zip(results) {
+            match r {
+                Ok(()) => report.updated.push(id),
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// The latest signed data per Beacon, without the signed data the chain
+    /// would reject for its signature or timestamp
+    fn fetch_signed_data(&self) -> Result<BTreeMap<Bytes32, SignedData>, RelayerError> {
+        let mut latest = BTreeMap::<Bytes32, SignedData>::new();
+        for signed in self.source.fetch()? {
+            let (Ok(beacon_id), Ok(timestamp)) = (signed.beacon_id(), timestamp_of(&signed)) else {
+                continue;
+            };
+            if !self.clock.is_valid(timestamp)
+                || !AirnodeVerifier::verify(&signed.airnode, &signed.message(), &signed.signature)
+            {
+                continue;
+            }
+            match latest.get(&beacon_id) {
+                Some(l) if l.timestamp >= signed.timestamp => {}
+                _ => {
+                    latest.insert(beacon_id, signed);
+                }
+            }
+        }
+        Ok(latest)
+    }
+}
+
+/// The update of the Beacon if the signed data meets the update conditions
+fn beacon_update<C: ChainBackend>(
+    chain: &C,
+    conditions: &UpdateConditions,
+    beacon_id: Bytes32,
+    signed: &SignedData,
+) -> Result<Option<SignedUpdate>, RelayerError> {
+    let current = chain.data_point(&beacon_id)?;
+    let mut storage = InMemoryStorage::default();
+    storage.store(beacon_id, current.clone());
+    let r = process_beacon_update(
+        &mut storage,
+        beacon_id,
+        Uint::from_big_endian(&signed.timestamp),
+        signed.data.clone(),
+    );
+    match r {
+        Err(Error::FulfillmentOlderThanBeacon) => return Ok(None),
+        r => r?,
+    }
+    let updated = storage.get(&beacon_id).unwrap_or_default();
+    if !conditions.is_update_needed(&current, &updated) {
+        return Ok(None);
+    }
+    Ok(Some(SignedUpdate::Beacon(signed.clone())))
+}
+
+/// The update of the dAPI if the value it would be updated to meets the
+/// update conditions. The Beacons without signed data or with signed data
+/// older than on chain are read from storage, and so are the ones after
+/// them if the chain takes signed data for the leading Beacons only.
+fn dapi_update<C: ChainBackend, T: TimestampChecker>(
+    chain: &C,
+    clock: &T,
+    conditions: &UpdateConditions,
+    dapi_id: Bytes32,
+    beacons: &[(Bytes32, Beacon)],
+    signed: &BTreeMap<Bytes32, SignedData>,
+) -> Result<Option<SignedUpdate>, RelayerError> {
+    // the update is run against the on-chain data points first, so only
+    // updates the chain accepts are submitted
+    let mut storage = InMemoryStorage::default();
+    let mut entries = Vec::with_capacity(beacons.len());
+    let mut signing = true;
+    for (beacon_id, beacon) in beacons {
+        let current = chain.data_point(beacon_id)?;
+        let entry = match signed.get(beacon_id) {
+            Some(s) if signing && timestamp_of(s)? >= current.timestamp => s.clone(),
+            _ => {
+                signing &= !chain.signed_beacons_first();
+                SignedData::unsigned(beacon.airnode.clone(), beacon.template_id)
+            }
+        };
+        storage.store(*beacon_id, current);
+        entries.push(entry);
+    }
+    if entries.iter().all(|e| e.signature.is_empty()) {
+        return Ok(None);
+    }
+    let current = chain.data_point(&dapi_id)?;
+    storage.store(dapi_id, current.clone());
+
+    let batch = entries.into_iter().collect::<SignedDataBatch>();
+    let b = batch.clone();
+    let r = update_dapi_with_signed_data::<_, AirnodeVerifier, _>(
+        &mut storage,
+        clock,
+        b.airnodes,
+        b.template_ids,
+        b.timestamps,
+        b.data,
+        b.signatures,
+    )
+    .map_err(Error::from);
+    match r {
+        Err(Error::UpdatedValueOutdated) => return Ok(None),
+        r => r?,
+    };
+    let updated = storage.get(&dapi_id).unwrap_or_default();
+    if !conditions.is_update_needed(&current, &updated) {
+        return Ok(None);
+    }
+    Ok(Some(SignedUpdate::Dapi(batch)))
+}
+
+fn timestamp_of(signed: &SignedData) -> Result<Timestamp, Error> {
+    Timestamp::from_secs_u256(Uint::from_big_endian(&signed.timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockChain;
+    use crate::source::FileSource;
+    use api3_common::abi::Int;
+    use api3_common::dummy::FixedClock;
+    use api3_common::signer::{AirnodeSigner, Ed25519Signer, Secp256k1Signer};
+    use api3_common::{DataPoint, HUNDRED_PERCENT};
+    use std::cell::RefCell;
+
+    const TEMPLATE_ID: Bytes32 = [1u8; 32];
+    const NOW: u64 = 1_650_000_000;
+
+    /// Serves the signed data the test publishes
+    #[derive(Default)]
+    struct Published(RefCell<Vec<SignedData>>);
+
+    impl Published {
+        fn publish(&self, signed: Vec<SignedData>) {
+            *self.0.borrow_mut() = signed;
+        }
+    }
+
+    impl SignedDataSource for &Published {
+        fn fetch(&self) -> Result<Vec<SignedData>, RelayerError> {
+            Ok(self.0.borrow().clone())
+        }
+    }
+
+    fn signers() -> Vec<Ed25519Signer> {
+        (1..=3u8)
+            .map(|seed| Ed25519Signer::from_secret_key(&[seed; 32]).unwrap())
+            .collect()
+    }
+
+    fn beacon<S: AirnodeSigner>(signer: &S) -> Beacon {
+        Beacon::new(signer.airnode(), TEMPLATE_ID)
+    }
+
+    fn sign<S: AirnodeSigner>(signer: &S, timestamp: u64, value: u64) -> SignedData {
+        SignedData::sign(
+            signer,
+            TEMPLATE_ID,
+            Timestamp::from_secs(timestamp),
+            Int::from(value),
+        )
+    }
+
+    /// The mock chain that records what is submitted, `fail` fails the submissions
+    struct Recording {
+        chain: MockChain<FixedClock>,
+        signed_beacons_first: bool,
+        fail: Option<RelayerError>,
+        submitted: Vec<Vec<SignedUpdate>>,
+    }
+
+    impl ChainBackend for Recording {
+        fn data_point(&self, data_point_id: &Bytes32) -> Result<DataPoint, RelayerError> {
+            self.chain.data_point(data_point_id)
+        }
+
+        fn update_with_signed_data(
+            &mut self,
+            updates: &[SignedUpdate],
+        ) -> Vec<Result<(), RelayerError>> {
+            self.submitted.push(updates.to_vec());
+            match &self.fail {
+                Some(e) => vec![Err(e.clone()); updates.len()],
+                None => self.chain.update_with_signed_data(updates),
+            }
+        }
+
+        fn signed_beacons_first(&self) -> bool {
+            self.signed_beacons_first
+        }
+    }
+
+    fn relayer<'a>(
+        published: &'a Published,
+        feeds: &Feeds,
+    ) -> Relayer<&'a Published, MockChain<FixedClock>, FixedClock> {
+        let clock = FixedClock::new(Timestamp::from_secs(NOW));
+        relayer_on(published, MockChain::new(clock), feeds)
+    }
+
+    fn relayer_on<'a, C: ChainBackend>(
+        published: &'a Published,
+        chain: C,
+        feeds: &Feeds,
+    ) -> Relayer<&'a Published, C, FixedClock> {
+        let clock = FixedClock::new(Timestamp::from_secs(NOW));
+        let conditions = UpdateConditions {
+            deviation_threshold: HUNDRED_PERCENT / 100,
+            heartbeat_interval: 3600,
+        };
+        Relayer::new(published, chain, clock, conditions, feeds).unwrap()
+    }
+
+    fn recording(signed_beacons_first: bool) -> Recording {
+        Recording {
+            chain: MockChain::new(FixedClock::new(Timestamp::from_secs(NOW))),
+            signed_beacons_first,
+            fail: None,
+            submitted: vec![],
+        }
+    }
+
+    #[test]
+    fn updates_feeds_that_deviate() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: vec![beacon(&signers[0])],
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let beacon_id = feeds.beacons[0].id().unwrap();
+        let published = Published::default();
+        let mut relayer = relayer(&published, &feeds);
+        let dapi_id = relayer.dapis[0].0;
+
+        // nothing is published yet
+        let report = relayer.run_once().unwrap();
+        assert!(report.updated.is_empty() && report.failed.is_empty());
+
+        let values = [1000, 2000, 4000];
+        published.publish(
+            signers
+                .iter()
+                .zip(values)
+                .map(|(s, v)| sign(s, NOW - 60, v))
+                .collect(),
+        );
+        let report = relayer.run_once().unwrap();
+        assert_eq!(report.updated, vec![beacon_id, dapi_id]);
+        assert_eq!(
+            relayer.chain().data_point(&dapi_id).unwrap(),
+            DataPoint::new(Int::from(2000), Timestamp::from_secs(NOW - 60))
+        );
+
+        // the same data is not relayed twice
+        let report = relayer.run_once().unwrap();
+        assert!(report.updated.is_empty());
+
+        // within the deviation threshold
+        let values = [1005, 2010, 4000];
+        published.publish(
+            signers
+                .iter()
+                .zip(values)
+                .map(|(s, v)| sign(s, NOW - 30, v))
+                .collect(),
+        );
+        assert!(relayer.run_once().unwrap().updated.is_empty());
+
+        // the Beacon deviates, the median of the dAPI does not
+        let values = [1100, 2010, 4000];
+        published.publish(
+            signers
+                .iter()
+                .zip(values)
+                .map(|(s, v)| sign(s, NOW - 20, v))
+                .collect(),
+        );
+        assert_eq!(relayer.run_once().unwrap().updated, vec![beacon_id]);
+        assert_eq!(relayer.chain().updates(), [beacon_id, dapi_id, beacon_id]);
+    }
+
+    #[test]
+    fn updates_dapis_of_beacons_relayed_in_the_same_round() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: signers.iter().map(beacon).collect(),
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let published = Published::default();
+        let mut relayer = relayer(&published, &feeds);
+        let dapi_id = relayer.dapis[0].0;
+
+        published.publish(signers.iter().map(|s| sign(s, NOW - 10, 1000)).collect());
+        let report = relayer.run_once().unwrap();
+        assert_eq!(report.updated.len(), 4);
+        assert_eq!(report.updated[3], dapi_id);
+        assert!(relayer.run_once().unwrap().updated.is_empty());
+    }
+
+    #[test]
+    fn updates_feeds_on_heartbeat() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: vec![beacon(&signers[0])],
+            dapis: vec![],
+        };
+        let beacon_id = feeds.beacons[0].id().unwrap();
+        let published = Published::default();
+        let mut relayer = relayer(&published, &feeds);
+        relayer.chain.store(
+            beacon_id,
+            DataPoint::new(Int::from(1000), Timestamp::from_secs(NOW - 3600)),
+        );
+
+        published.publish(vec![sign(&signers[0], NOW - 1, 1000)]);
+        assert!(relayer.run_once().unwrap().updated.is_empty());
+        published.publish(vec![sign(&signers[0], NOW, 1000)]);
+        assert_eq!(relayer.run_once().unwrap().updated, vec![beacon_id]);
+    }
+
+    #[test]
+    fn skips_signed_data_the_chain_rejects() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: signers.iter().map(beacon).collect(),
+            dapis: vec![],
+        };
+        let published = Published::default();
+        let mut relayer = relayer(&published, &feeds);
+
+        let mut tampered = sign(&signers[0], NOW - 10, 1000);
+        tampered.data = sign(&signers[0], NOW - 10, 2000).data;
+        published.publish(vec![
+            tampered,
+            // older than an hour and too far in the future
+            sign(&signers[1], NOW - 3600, 1000),
+            sign(&signers[2], NOW + 900, 1000),
+        ]);
+        let report = relayer.run_once().unwrap();
+        assert!(report.updated.is_empty() && report.failed.is_empty());
+
+        // the latest of the valid signed data is relayed
+        let beacon_id = feeds.beacons[0].id().unwrap();
+        published.publish(vec![
+            sign(&signers[0], NOW - 10, 1000),
+            sign(&signers[0], NOW - 5, 3000),
+            sign(&signers[0], NOW - 20, 2000),
+        ]);
+        assert_eq!(relayer.run_once().unwrap().updated, vec![beacon_id]);
+        assert_eq!(
+            relayer.chain().data_point(&beacon_id).unwrap(),
+            DataPoint::new(Int::from(3000), Timestamp::from_secs(NOW - 5))
+        );
+    }
+
+    #[test]
+    fn reads_beacons_without_signed_data_from_the_chain() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: vec![],
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let published = Published::default();
+        let mut relayer = relayer(&published, &feeds);
+        let (dapi_id, beacons) = relayer.dapis[0].clone();
+        for ((beacon_id, _), v) in beacons.iter().zip([1000u64, 3000, 5000]) {
+            relayer.chain.store(
+                *beacon_id,
+                DataPoint::new(Int::from(v), Timestamp::from_secs(NOW - 100)),
+            );
+        }
+
+        // older than the Beacon on chain, so it is read from storage
+        published.publish(vec![
+            sign(&signers[0], NOW - 10, 4000),
+            sign(&signers[1], NOW - 200, 100),
+        ]);
+        assert_eq!(relayer.run_once().unwrap().updated, vec![dapi_id]);
+        assert_eq!(
+            relayer.chain().data_point(&dapi_id).unwrap(),
+            DataPoint::new(Int::from(4000), Timestamp::from_secs(NOW - 70))
+        );
+    }
+
+    #[test]
+    fn submits_the_updates_of_a_round_together() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: signers.iter().map(beacon).collect(),
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let published = Published::default();
+        let mut relayer = relayer_on(&published, recording(false), &feeds);
+        assert!(relayer.warnings().is_empty());
+
+        published.publish(signers.iter().map(|s| sign(s, NOW - 10, 1000)).collect());
+        relayer.chain.fail = Some(RelayerError::Chain(String::from("out of gas")));
+        let report = relayer.run_once().unwrap();
+        assert!(report.updated.is_empty());
+        assert_eq!(report.failed.len(), 4);
+        assert_eq!(relayer.chain().submitted.len(), 1);
+        assert_eq!(relayer.chain().submitted[0].len(), 4);
+
+        relayer.chain.fail = None;
+        let report = relayer.run_once().unwrap();
+        assert_eq!(report.updated.len(), 4);
+        assert_eq!(relayer.chain().submitted.len(), 2);
+        // nothing is submitted in rounds without updates
+        assert!(relayer.run_once().unwrap().updated.is_empty());
+        assert_eq!(relayer.chain().submitted.len(), 2);
+    }
+
+    #[test]
+    fn signs_the_leading_beacons_if_the_chain_needs_them_first() {
+        let signers = signers();
+        let feeds = Feeds {
+            beacons: vec![],
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let published = Published::default();
+        let mut relayer = relayer_on(&published, recording(true), &feeds);
+        assert_eq!(relayer.warnings().len(), 1);
+        let (dapi_id, beacons) = relayer.dapis[0].clone();
+        for (beacon_id, _) in &beacons {
+            relayer.chain.chain.store(
+                *beacon_id,
+                DataPoint::new(Int::from(1000), Timestamp::from_secs(NOW - 100)),
+            );
+        }
+
+        // the second Beacon has no signed data, so the third is read from storage
+        published.publish(vec![
+            sign(&signers[0], NOW - 10, 3000),
+            sign(&signers[2], NOW - 10, 5000),
+        ]);
+        assert_eq!(relayer.run_once().unwrap().updated, vec![dapi_id]);
+        let SignedUpdate::Dapi(batch) = &relayer.chain().submitted[0][0] else {
+            panic!("not a dAPI update");
+        };
+        let signed = batch.signatures.iter().map(|s| !s.is_empty());
+        assert_eq!(signed.collect::<Vec<_>>(), [true, false, false]);
+        assert_eq!(
+            relayer.chain().data_point(&dapi_id).unwrap(),
+            DataPoint::new(Int::from(1000), Timestamp::from_secs(NOW - 70))
+        );
+    }
+
+    #[test]
+    fn rejects_dapis_with_one_beacon() {
+        let feeds = Feeds {
+            beacons: vec![],
+            dapis: vec![vec![beacon(&signers()[0])]],
+        };
+        let clock = FixedClock::new(Timestamp::from_secs(NOW));
+        assert!(matches!(
+            Relayer::new(
+                &Published::default(),
+                MockChain::new(clock),
+                clock,
+                UpdateConditions::default(),
+                &feeds
+            ),
+            Err(RelayerError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn relays_from_a_file_end_to_end() {
+        let signers = [
+            Secp256k1Signer::from_secret_key(&[1u8; 32]).unwrap(),
+            Secp256k1Signer::from_secret_key(&[2u8; 32]).unwrap(),
+        ];
+        let hex = |b: &[u8]| format!("0x{}", hex::encode(b));
+        let json = signers
+            .iter()
+            .zip([100u64, 300])
+            .map(|(s, v)| {
+                let signed = sign(s, NOW - 10, v);
+                serde_json::json!({
+                    "airnode": hex(&signed.airnode),
+                    "template_id": hex(&signed.template_id),
+                    "timestamp": hex(&signed.timestamp),
+                    "data": hex(&signed.data),
+                    "signature": hex(&signed.signature),
+                })
+            })
+            .collect::<Vec<_>>();
+        let path = std::env::temp_dir().join(format!("api3-relayer-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&json).unwrap()).unwrap();
+
+        let feeds = Feeds {
+            beacons: vec![],
+            dapis: vec![signers.iter().map(beacon).collect()],
+        };
+        let clock = FixedClock::new(Timestamp::from_secs(NOW));
+        let mut relayer = Relayer::new(
+            FileSource::new(path.clone()),
+            MockChain::new(clock),
+            clock,
+            UpdateConditions::default(),
+            &feeds,
+        )
+        .unwrap();
+        let report = relayer.run_once();
+        std::fs::remove_file(&path).unwrap();
+
+        let dapi_id = relayer.dapis[0].0;
+        assert_eq!(report.unwrap().updated, vec![dapi_id]);
+        assert_eq!(
+            relayer.chain().data_point(&dapi_id).unwrap(),
+            DataPoint::new(Int::from(200), Timestamp::from_secs(NOW - 10))
+        );
+    }
+}
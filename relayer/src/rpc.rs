@@ -0,0 +1,46 @@
+use crate::RelayerError;
+use serde_json::{json, Value};
+
+/// A JSON-RPC 2.0 endpoint over HTTP, as NEAR and Solana nodes serve
+pub struct JsonRpc {
+    url: String,
+}
+
+impl JsonRpc {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+
+    /// Calls `method`, returns the `result` of the response or its `error`
+    /// if the node answered with one
+    pub fn call(&self, method: &str, params: Value) -> Result<Result<Value, Value>, RelayerError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "api3-relayer",
+            "method": method,
+            "params": params,
+        });
+        let response: Value = match ureq::post(&self.url).send_json(request) {
+            Ok(r) => r.into_json(),
+            // nodes also answer errors with a JSON-RPC error in the body
+            Err(ureq::Error::Status(_, r)) => r.into_json(),
+            Err(e) => return Err(RelayerError::Rpc(format!("{}: {}", self.url, e))),
+        }
+        .map_err(|e| RelayerError::Rpc(format!("{}: {}", self.url, e)))?;
+
+        match (response.get("result"), response.get("error")) {
+            (_, Some(error)) => Ok(Err(error.clone())),
+            (Some(result), None) => Ok(Ok(result.clone())),
+            (None, None) => Err(RelayerError::Rpc(format!(
+                "{}: no result in {}",
+                self.url, response
+            ))),
+        }
+    }
+
+    /// Same as `call`, a JSON-RPC error fails the call
+    pub fn query(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+        self.call(method, params)?
+            .map_err(|e| RelayerError::Rpc(format!("{} failed: {}", method, e)))
+    }
+}
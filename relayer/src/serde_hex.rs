@@ -0,0 +1,20 @@
+//! Hex strings with an optional `0x` prefix for `Bytes` and `Bytes32`, the
+//! format `api3-cli` prints
+
+use api3_common::{Bytes, Bytes32};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+pub fn decode(s: &str) -> Result<Bytes, hex::FromHexError> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+}
+
+pub fn bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    decode(&s).map_err(|e| D::Error::custom(format!("invalid hex {}: {}", s, e)))
+}
+
+pub fn bytes32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes32, D::Error> {
+    let b = bytes(deserializer)?;
+    Bytes32::try_from(b).map_err(|b| D::Error::invalid_length(b.len(), &"32 bytes"))
+}
@@ -0,0 +1,639 @@
+//! Relays to the Solana beacon server program over the Solana JSON-RPC API.
+//! The signatures of the Airnodes are verified by ed25519 program
+//! instructions in front of the updates, as the program expects.
+
+use crate::rpc::JsonRpc;
+use crate::{ChainBackend, RelayerError, SignedUpdate};
+use api3_common::signer::{SignedData, SignedDataBatch};
+use api3_common::{derive_dapi_id, Bytes, Bytes32, DataPoint, Error};
+use borsh::BorshSerialize;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use ed25519_dalek::Signer;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+const SYSVAR_INSTRUCTIONS_ID: &str = "Sysvar1nstructions1111111111111111111111111";
+const ED25519_PROGRAM_ID: &str = "Ed25519SigVerify111111111111111111111111111";
+/// The most bytes a transaction can have, `PACKET_DATA_SIZE` of solana-sdk
+const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// The program only takes signed data for the leading Beacons of a dAPI, as
+/// many as the first ed25519 instruction of the transaction verifies. The
+/// relayer reads the Beacons after the first one without signed data from
+/// storage, so list the Beacons whose Airnodes publish most reliably first.
+#[derive(Deserialize)]
+pub struct SolanaConfig {
+    pub rpc_url: String,
+    /// The beacon server program, base58
+    pub program_id: String,
+    /// The keypair file of the relayer, as `solana-keygen` writes them, which pays for the updates
+    pub keypair: PathBuf,
+}
+
+pub struct SolanaBackend {
+    rpc: JsonRpc,
+    program_id: Bytes32,
+    payer: ed25519_dalek::Keypair,
+}
+
+impl SolanaBackend {
+    pub fn new(config: &SolanaConfig) -> Result<Self, RelayerError> {
+        let invalid =
+            |e: String| RelayerError::InvalidConfig(format!("{}: {}", config.keypair.display(), e));
+        let json = std::fs::read_to_string(&config.keypair).map_err(|e| invalid(e.to_string()))?;
+        let keypair: Vec<u8> = serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))?;
+        let payer =
+            ed25519_dalek::Keypair::from_bytes(&keypair).map_err(|e| invalid(e.to_string()))?;
+        let program_id = decode_pubkey(&config.program_id).ok_or_else(|| {
+            RelayerError::InvalidConfig(format!("program ID {}", config.program_id))
+        })?;
+        Ok(Self {
+            rpc: JsonRpc::new(config.rpc_url.clone()),
+            program_id,
+            payer,
+        })
+    }
+
+    /// The PDA of the data point account
+    fn data_point_address(&self, data_point_id: &Bytes32) -> Bytes32 {
+        find_program_address(&[b"datapoint", data_point_id], &self.program_id)
+    }
+
+    /// Signs the message and sends the transaction. Returns once the node
+    /// accepted it, which simulates it first.
+    fn send(&self, message: Bytes) -> Result<(), RelayerError> {
+        let mut transaction = short_vec(1);
+        transaction.extend_from_slice(&self.payer.sign(&message).to_bytes());
+        transaction.extend_from_slice(&message);
+        self.rpc
+            .call(
+                "sendTransaction",
+                json!([base64::encode(transaction), {"encoding": "base64"}]),
+            )?
+            .map(|_| ())
+            .map_err(|e| failure(&e))
+    }
+
+    fn latest_blockhash(&self) -> Result<Bytes32, RelayerError> {
+        let r = self.rpc.query("getLatestBlockhash", json!([]))?;
+        r["value"]["blockhash"]
+            .as_str()
+            .and_then(decode_pubkey)
+            .ok_or_else(|| RelayerError::Rpc(format!("no blockhash in {}", r)))
+    }
+}
+
+impl ChainBackend for SolanaBackend {
+    fn data_point(&self, data_point_id: &Bytes32) -> Result<DataPoint, RelayerError> {
+        let address = bs58::encode(self.data_point_address(data_point_id)).into_string();
+        let r = self
+            .rpc
+            .query("getAccountInfo", json!([address, {"encoding": "base64"}]))?;
+        match r["value"]["data"][0].as_str() {
+            Some(data) => {
+                let data = base64::decode(data).map_err(|e| RelayerError::Rpc(e.to_string()))?;
+                decode_data_point(&data)
+            }
+            None => Ok(DataPoint::default()),
+        }
+    }
+
+    /// Updates the dAPIs in transactions of those with as many signed Beacons,
+    /// as the first ed25519 instruction tells the program how many are signed.
+    /// Beacons are not updated, their Airnode signs their transactions.
+    fn update_with_signed_data(
+        &mut self,
+        updates: &[SignedUpdate],
+    ) -> Vec<Result<(), RelayerError>> {
+        let mut results = vec![Ok(()); updates.len()];
+        let mut groups = BTreeMap::<usize, Vec<(usize, DapiUpdate)>>::new();
+        for (i, update) in updates.iter().enumerate() {
+            let r = match update {
+                SignedUpdate::Beacon(_) => Err(RelayerError::Unsupported(
+                    "Solana Beacons are updated in transactions signed by their Airnode",
+                )),
+                SignedUpdate::Dapi(batch) => DapiUpdate::new(batch),
+            };
+            match r {
+                Ok(u) => groups.entry(u.signed.len()).or_default().push((i, u)),
+                Err(e) => results[i] = Err(e),
+            }
+        }
+        if groups.is_empty() {
+            return results;
+        }
+        let recent_blockhash = match self.latest_blockhash() {
+            Ok(h) => h,
+            Err(e) => {
+                for (i, _) in groups.values().flatten() {
+                    results[*i] = Err(e.clone());
+                }
+                return results;
+            }
+        };
+        let payer = self.payer.public.to_bytes();
+        for group in groups.values() {
+            for transaction in fit_transactions(&self.program_id, &payer, group) {
+                let dapis = transaction.iter().map(|(_, u)| u).collect::<Vec<_>>();
+                let message =
+                    update_dapis_message(&self.program_id, &payer, &dapis, recent_blockhash);
+                let r = self.send(message);
+                for (i, _) in transaction {
+                    results[*i] = r.clone();
+                }
+            }
+        }
+        results
+    }
+
+    fn signed_beacons_first(&self) -> bool {
+        true
+    }
+}
+
+/// A dAPI update, the signatures its ed25519 instruction verifies and the
+/// data of the program instruction
+struct DapiUpdate {
+    dapi_id: Bytes32,
+    beacon_ids: Vec<Bytes32>,
+    signed: Vec<SignatureEntry>,
+    data: Bytes,
+}
+
+impl DapiUpdate {
+    fn new(batch: &SignedDataBatch) -> Result<Self, RelayerError> {
+        // the program takes the Beacons in the ed25519 instruction as the first ones
+        let signed_count = batch
+            .signatures
+            .iter()
+            .take_while(|s| !s.is_empty())
+            .count();
+        if batch.signatures[signed_count..]
+            .iter()
+            .any(|s| !s.is_empty())
+        {
+            return Err(RelayerError::Unsupported(
+                "Solana needs the Beacons with signed data before the ones read from storage",
+            ));
+        }
+        let signed = (0..signed_count)
+            .map(|i| {
+                Some(SignatureEntry {
+                    public_key: Bytes32::try_from(batch.airnodes[i].as_slice()).ok()?,
+                    signature: batch.signatures[i].as_slice().try_into().ok()?,
+                    message: message_of(batch, i),
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(RelayerError::Unsupported(
+                "Solana Airnodes sign with ed25519",
+            ))?;
+
+        let beacon_ids = batch
+            .airnodes
+            .iter()
+            .zip(&batch.template_ids)
+            .map(|(a, t)| api3_common::derive_beacon_id(a.clone(), *t))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let dapi_id = derive_dapi_id(&beacon_ids);
+        let mut data = anchor_discriminator("update_dapi_with_signed_data").to_vec();
+        (
+            dapi_id,
+            batch.airnodes.clone(),
+            beacon_ids.clone(),
+            batch.template_ids.clone(),
+            batch.timestamps.clone(),
+            batch.data.clone(),
+        )
+            .serialize(&mut data)
+            .expect("serializing to memory does not fail");
+        Ok(Self {
+            dapi_id,
+            beacon_ids,
+            signed,
+            data,
+        })
+    }
+}
+
+/// Splits the dAPI updates into transactions of at most `MAX_TRANSACTION_SIZE`
+/// bytes, in order. An update too large on its own is a transaction of its own.
+fn fit_transactions<'a>(
+    program_id: &Bytes32,
+    payer: &Bytes32,
+    updates: &'a [(usize, DapiUpdate)],
+) -> Vec<&'a [(usize, DapiUpdate)]> {
+    let size = |updates: &[(usize, DapiUpdate)]| {
+        let dapis = updates.iter().map(|(_, u)| u).collect::<Vec<_>>();
+        // the size does not depend on the blockhash or the signature
+        let message = update_dapis_message(program_id, payer, &dapis, [0u8; 32]);
+        1 + 64 + message.len()
+    };
+    let mut transactions = vec![];
+    let mut start = 0;
+    for end in 1..=updates.len() {
+        if end - start > 1 && size(&updates[start..end]) > MAX_TRANSACTION_SIZE {
+            transactions.push(&updates[start..end - 1]);
+            start = end - 1;
+        }
+    }
+    if start < updates.len() {
+        transactions.push(&updates[start..]);
+    }
+    transactions
+}
+
+/// The message of the transaction that verifies the signatures of the dAPI
+/// updates and makes them, the ed25519 instructions come first and in the
+/// same order as the updates
+fn update_dapis_message(
+    program_id: &Bytes32,
+    payer: &Bytes32,
+    dapis: &[&DapiUpdate],
+    recent_blockhash: Bytes32,
+) -> Bytes {
+    // the payer is the only signer, the dAPIs are the only other writable accounts
+    let pubkey = |s: &str| decode_pubkey(s).expect("valid program ID");
+    let mut keys = vec![*payer];
+    for dapi in dapis {
+        keys.push(find_program_address(
+            &[b"datapoint", &dapi.dapi_id],
+            program_id,
+        ));
+    }
+    let config = index_of(&mut keys, find_program_address(&[b"config"], program_id));
+    let system_program = index_of(&mut keys, pubkey(SYSTEM_PROGRAM_ID));
+    let instructions_sysvar = index_of(&mut keys, pubkey(SYSVAR_INSTRUCTIONS_ID));
+    let accounts = dapis
+        .iter()
+        .enumerate()
+        .map(|(i, dapi)| {
+            let mut accounts = vec![
+                (i + 1) as u8,
+                config,
+                0,
+                system_program,
+                instructions_sysvar,
+            ];
+            // the circuit breaker status of the signed Beacons is read from their accounts too
+            for beacon_id in &dapi.beacon_ids {
+                let address = find_program_address(&[b"datapoint", beacon_id], program_id);
+                accounts.push(index_of(&mut keys, address));
+            }
+            accounts
+        })
+        .collect::<Vec<_>>();
+    // program IDs come last, in the order of the instructions
+    let ed25519_index = index_of(&mut keys, pubkey(ED25519_PROGRAM_ID));
+    let program_index = index_of(&mut keys, *program_id);
+
+    let mut message = vec![1, 0, (keys.len() - 1 - dapis.len()) as u8];
+    message.extend(short_vec(keys.len()));
+    keys.iter().for_each(|k| message.extend_from_slice(k));
+    message.extend_from_slice(&recent_blockhash);
+    message.extend(short_vec(2 * dapis.len()));
+    let verifications = dapis.iter().map(|dapi| {
+        (
+            ed25519_index,
+            vec![],
+            ed25519_instruction_data(&dapi.signed),
+        )
+    });
+    let updates = dapis
+        .iter()
+        .zip(accounts)
+        .map(|(dapi, accounts)| (program_index, accounts, dapi.data.clone()));
+    for (program, accounts, data) in verifications.chain(updates) {
+        message.push(program);
+        message.extend(short_vec(accounts.len()));
+        message.extend(accounts);
+        message.extend(short_vec(data.len()));
+        message.extend(data);
+    }
+    message
+}
+
+struct SignatureEntry {
+    public_key: Bytes32,
+    signature: [u8; 64],
+    message: Bytes32,
+}
+
+fn message_of(batch: &SignedDataBatch, i: usize) -> Bytes32 {
+    let mut signed = SignedData::unsigned(batch.airnodes[i].clone(), batch.template_ids[i]);
+    signed.timestamp = batch.timestamps[i];
+    signed.data = batch.data[i].clone();
+    signed.message()
+}
+
+/// The data of the ed25519 program instruction that verifies the signatures,
+/// the offsets point into the instruction itself
+fn ed25519_instruction_data(entries: &[SignatureEntry]) -> Bytes {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+    const ENTRY_SIZE: usize = 32 + 64 + 32;
+    // `u16::MAX` is the instruction the offsets are in
+    let current = u16::MAX.to_le_bytes();
+
+    let mut data = vec![entries.len() as u8, 0];
+    let data_start = OFFSETS_START + OFFSETS_SIZE * entries.len();
+    for i in 0..entries.len() {
+        let public_key_offset = (data_start + ENTRY_SIZE * i) as u16;
+        let signature_offset = public_key_offset + 32;
+        let message_offset = signature_offset + 64;
+        data.extend_from_slice(&signature_offset.to_le_bytes());
+        data.extend_from_slice(&current);
+        data.extend_from_slice(&public_key_offset.to_le_bytes());
+        data.extend_from_slice(&current);
+        data.extend_from_slice(&message_offset.to_le_bytes());
+        data.extend_from_slice(&32u16.to_le_bytes());
+        data.extend_from_slice(&current);
+    }
+    for e in entries {
+        data.extend_from_slice(&e.public_key);
+        data.extend_from_slice(&e.signature);
+        data.extend_from_slice(&e.message);
+    }
+    data
+}
+
+/// Decodes the `WrappedDataPoint` account, the anchor discriminator and the borsh serialized raw data point
+fn decode_data_point(account: &[u8]) -> Result<DataPoint, RelayerError> {
+    let invalid = || {
+        RelayerError::Chain(format!(
+            "invalid data point account {}",
+            hex::encode(account)
+        ))
+    };
+    let len = account.get(8..12).ok_or_else(invalid)?;
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    match account.get(12..12 + len).ok_or_else(invalid)? {
+        // created by a failed or a pause state update, never updated
+        [] => Ok(DataPoint::default()),
+        raw => DataPoint::from(raw.to_vec()).map_err(|_| invalid()),
+    }
+}
+
+/// The error of a failed transaction, the `api3_common::Error` if the program
+/// failed with one of its codes
+fn failure(error: &Value) -> RelayerError {
+    let code = error["data"]["err"]["InstructionError"][1]["Custom"]
+        .as_u64()
+        .and_then(|c| u32::try_from(c).ok());
+    // the codes of anchor and of the Solana namespace are not `Error`s
+    match code.and_then(Error::from_code) {
+        Some(e) => RelayerError::Api3(e),
+        None => RelayerError::Chain(error.to_string()),
+    }
+}
+
+fn decode_pubkey(s: &str) -> Option<Bytes32> {
+    Bytes32::try_from(bs58::decode(s).into_vec().ok()?).ok()
+}
+
+/// Same as `Pubkey::find_program_address`
+fn find_program_address(seeds: &[&[u8]], program_id: &Bytes32) -> Bytes32 {
+    for bump in (0..=u8::MAX).rev() {
+        let mut hasher = Sha256::new();
+        seeds.iter().for_each(|s| hasher.update(s));
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(b"ProgramDerivedAddress");
+        let address: Bytes32 = hasher.finalize().into();
+        // program addresses are off the curve so that no key can sign for them
+        if CompressedEdwardsY(address).decompress().is_none() {
+            return address;
+        }
+    }
+    panic!("no program address for the seeds")
+}
+
+/// The 8 bytes anchor prefixes the data of the instruction with
+fn anchor_discriminator(instruction: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("global:{}", instruction).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// The compact length prefix of arrays in transactions
+fn short_vec(mut len: usize) -> Bytes {
+    let mut encoded = vec![];
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            encoded.push(byte);
+            return encoded;
+        }
+        encoded.push(byte | 0x80);
+    }
+}
+
+fn index_of(keys: &mut Vec<Bytes32>, key: Bytes32) -> u8 {
+    let index = keys.iter().position(|k| *k == key).unwrap_or_else(|| {
+        keys.push(key);
+        keys.len() - 1
+    });
+    index as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api3_common::abi::Int;
+    use api3_common::signer::{AirnodeSigner, Ed25519Signer, Secp256k1Signer};
+    use api3_common::{ErrorNamespace, Timestamp};
+
+    const PROGRAM_ID: &str = "FRoo7m8Sf6ZAirGgnn3KopQymDtujWx818kcnRxzi23b";
+
+    fn sign<S: AirnodeSigner>(signer: &S) -> SignedData {
+        SignedData::sign(
+            signer,
+            [1u8; 32],
+            Timestamp::from_secs(1_650_000_000),
+            Int::from(5),
+        )
+    }
+
+    fn batch() -> SignedDataBatch {
+        batch_of([1u8; 32], 2)
+    }
+
+    /// The dAPI of three Beacons of the template, the first `signed` with signed data
+    fn batch_of(template_id: Bytes32, signed: usize) -> SignedDataBatch {
+        (1..=3u8)
+            .map(|seed| Ed25519Signer::from_secret_key(&[seed; 32]).unwrap())
+            .enumerate()
+            .map(|(i, signer)| match i < signed {
+                true => SignedData::sign(
+                    &signer,
+                    template_id,
+                    Timestamp::from_secs(1_650_000_000),
+                    Int::from(5),
+                ),
+                false => SignedData::unsigned(signer.airnode(), template_id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_program_addresses() {
+        // same as `Pubkey::find_program_address` of solana-program
+        let program_id = decode_pubkey(PROGRAM_ID).unwrap();
+        assert_eq!(
+            hex::encode(find_program_address(&[b"config"], &program_id)),
+            "0a73471d76410634e52d4bb32ea3ec135dd24fe40ea4ba0d0048ba0f49dd9973"
+        );
+        assert_eq!(
+            hex::encode(find_program_address(
+                &[b"datapoint", &[1u8; 32]],
+                &program_id
+            )),
+            "7d03abb930166aa81b8340eccdaac82b9ffb97a226198701474942f002b5d79a"
+        );
+    }
+
+    #[test]
+    fn serializes_update_dapi_messages() {
+        let program_id = decode_pubkey(PROGRAM_ID).unwrap();
+        let update = DapiUpdate::new(&batch()).unwrap();
+        let message = update_dapis_message(&program_id, &[9u8; 32], &[&update], [7u8; 32]);
+        // the payer signs, the dAPI, config, system program, instructions
+        // sysvar, the three Beacons and the two programs follow
+        assert_eq!(message[..5], [1, 0, 8, 10, 9]);
+        // the message `Message::new` of solana-program serializes for the same instructions
+        assert_eq!(
+            hex::encode(Sha256::digest(&message)),
+            "ff62ec440e7e7172478accdc3e0401521f764dd73392a2893ff6f003b2985e33"
+        );
+    }
+
+    #[test]
+    fn batches_dapi_updates() {
+        let program_id = decode_pubkey(PROGRAM_ID).unwrap();
+        let updates = [[1u8; 32], [2u8; 32]].map(|t| DapiUpdate::new(&batch_of(t, 2)).unwrap());
+        let message = update_dapis_message(
+            &program_id,
+            &[9u8; 32],
+            &[&updates[0], &updates[1]],
+            [7u8; 32],
+        );
+        // the payer and the two dAPIs are writable, the config, system
+        // program, instructions sysvar, the six Beacons and the two programs follow
+        assert_eq!(message[..5], [1, 0, 11, 14, 9]);
+        let dapi = |u: &DapiUpdate| find_program_address(&[b"datapoint", &u.dapi_id], &program_id);
+        assert_eq!(message[36..68], dapi(&updates[0]));
+        assert_eq!(message[68..100], dapi(&updates[1]));
+        // the ed25519 instructions come first, the one of the first dAPI at index 0
+        let instructions = 4 + 14 * 32 + 32;
+        assert_eq!(
+            message[instructions..instructions + 5],
+            [4, 12, 0, 0x9e, 0x02]
+        );
+
+        // the updates are split into transactions that fit
+        let updates = (0..4u8)
+            .map(|t| {
+                (
+                    usize::from(t),
+                    DapiUpdate::new(&batch_of([t + 1; 32], 1)).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let transactions = fit_transactions(&program_id, &[9u8; 32], &updates);
+        assert!(transactions.len() > 1);
+        for transaction in &transactions {
+            let dapis = transaction.iter().map(|(_, u)| u).collect::<Vec<_>>();
+            let size = 65 + update_dapis_message(&program_id, &[9u8; 32], &dapis, [7u8; 32]).len();
+            assert!(size <= MAX_TRANSACTION_SIZE || transaction.len() == 1);
+        }
+        let order = transactions.iter().flat_map(|t| t.iter().map(|(i, _)| *i));
+        assert_eq!(order.collect::<Vec<_>>(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn verifies_the_signed_beacons_first() {
+        let mut batch = batch();
+        let data = ed25519_instruction_data(&[SignatureEntry {
+            public_key: batch.airnodes[0].clone().try_into().unwrap(),
+            signature: batch.signatures[0].clone().try_into().unwrap(),
+            message: message_of(&batch, 0),
+        }]);
+        // the signature, public key and message offsets, in this instruction
+        assert_eq!(hex::encode(&data[..16]), "01003000ffff1000ffff70002000ffff");
+        assert_eq!(data.len(), 16 + 32 + 64 + 32);
+        assert_eq!(data[16..48], batch.airnodes[0]);
+        assert_eq!(data[112..], message_of(&batch, 0));
+
+        batch.signatures.swap(1, 2);
+        assert!(matches!(
+            DapiUpdate::new(&batch),
+            Err(RelayerError::Unsupported(_))
+        ));
+        let secp256k1 = Secp256k1Signer::from_secret_key(&[1u8; 32]).unwrap();
+        let batch = vec![sign(&secp256k1), sign(&secp256k1)]
+            .into_iter()
+            .collect();
+        assert!(matches!(
+            DapiUpdate::new(&batch),
+            Err(RelayerError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_data_point_accounts() {
+        let mut account = vec![0u8; 8];
+        account.extend_from_slice(&36u32.to_le_bytes());
+        account.extend_from_slice(&[0xffu8; 32]);
+        account.extend_from_slice(&1_650_000_000u32.to_be_bytes());
+        // the bump, the pause state and the empty pending data point
+        account.extend_from_slice(&[255, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            decode_data_point(&account).unwrap(),
+            DataPoint::new(Int::MAX, Timestamp::from_secs(1_650_000_000))
+        );
+
+        let mut created = vec![0u8; 12];
+        created.extend_from_slice(&[255, 0]);
+        assert_eq!(decode_data_point(&created).unwrap(), DataPoint::default());
+        assert!(decode_data_point(&account[..40]).is_err());
+    }
+
+    #[test]
+    fn maps_program_errors() {
+        let error = |code: u32| {
+            json!({
+                "code": -32002,
+                "message": "Transaction simulation failed",
+                "data": {"err": {"InstructionError": [1, {"Custom": code}]}},
+            })
+        };
+        assert!(matches!(
+            failure(&error(Error::InvalidTimestamp.code())),
+            RelayerError::Api3(Error::InvalidTimestamp)
+        ));
+        assert!(matches!(
+            failure(&error(ErrorNamespace::Solana.base() + 5)),
+            RelayerError::Chain(_)
+        ));
+        // anchor's `AccountNotInitialized`
+        assert!(matches!(failure(&error(3012)), RelayerError::Chain(_)));
+        assert!(matches!(
+            failure(&json!({"code": -32002, "message": "Blockhash not found"})),
+            RelayerError::Chain(_)
+        ));
+    }
+
+    #[test]
+    fn encodes_short_vec_lengths() {
+        assert_eq!(short_vec(0), [0]);
+        assert_eq!(short_vec(0x7f), [0x7f]);
+        assert_eq!(short_vec(0x80), [0x80, 0x01]);
+        assert_eq!(short_vec(0x4000), [0x80, 0x80, 0x01]);
+    }
+}
@@ -0,0 +1,137 @@
+//! Where Airnodes publish their signed data. Both sources read a JSON array of
+//! signed data in the format `api3-cli sign` prints, e.g.
+//! `[{"airnode": "0x..", "template_id": "0x..", "timestamp": "0x..", "data": "0x..", "signature": "0x.."}]`
+
+use crate::serde_hex;
+use crate::RelayerError;
+use api3_common::signer::SignedData;
+use api3_common::{Bytes, Bytes32};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// A source of the signed data of Airnodes
+pub trait SignedDataSource {
+    /// Fetches the signed data currently published
+    fn fetch(&self) -> Result<Vec<SignedData>, RelayerError>;
+}
+
+impl<S: SignedDataSource + ?Sized> SignedDataSource for Box<S> {
+    fn fetch(&self) -> Result<Vec<SignedData>, RelayerError> {
+        (**self).fetch()
+    }
+}
+
+/// The source at `location`, an HTTP endpoint if it is an `http` or `https` URL and a file otherwise
+pub fn from_location(location: &str) -> Box<dyn SignedDataSource> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        Box::new(HttpSource::new(location.to_string()))
+    } else {
+        Box::new(FileSource::new(PathBuf::from(location)))
+    }
+}
+
+/// Reads the signed data from a local file, e.g. written by a mock Airnode
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl SignedDataSource for FileSource {
+    fn fetch(&self) -> Result<Vec<SignedData>, RelayerError> {
+        let json = std::fs::read_to_string(&self.path)
+            .map_err(|e| RelayerError::Source(format!("{}: {}", self.path.display(), e)))?;
+        parse(&json)
+    }
+}
+
+/// Polls the signed data from an HTTP endpoint with `GET`
+pub struct HttpSource {
+    url: String,
+}
+
+impl HttpSource {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+}
+
+impl SignedDataSource for HttpSource {
+    fn fetch(&self) -> Result<Vec<SignedData>, RelayerError> {
+        let error = |e: String| RelayerError::Source(format!("{}: {}", self.url, e));
+        let json = ureq::get(&self.url)
+            .call()
+            .map_err(|e| error(e.to_string()))?
+            .into_string()
+            .map_err(|e| error(e.to_string()))?;
+        parse(&json)
+    }
+}
+
+#[derive(Deserialize)]
+struct SignedDataJson {
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    airnode: Bytes,
+    #[serde(deserialize_with = "serde_hex::bytes32")]
+    template_id: Bytes32,
+    #[serde(deserialize_with = "serde_hex::bytes32")]
+    timestamp: Bytes32,
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    data: Bytes,
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    signature: Bytes,
+}
+
+impl From<SignedDataJson> for SignedData {
+    fn from(s: SignedDataJson) -> Self {
+        SignedData {
+            airnode: s.airnode,
+            template_id: s.template_id,
+            timestamp: s.timestamp,
+            data: s.data,
+            signature: s.signature,
+        }
+    }
+}
+
+fn parse(json: &str) -> Result<Vec<SignedData>, RelayerError> {
+    serde_json::from_str::<Vec<SignedDataJson>>(json)
+        .map(|v| v.into_iter().map(SignedData::from).collect())
+        .map_err(|e| RelayerError::Source(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signed_data() {
+        let json = r#"[{
+            "beacon_id": "0xad1b5c75a8b8e0d7dbc56c1e28aee9fabe285ad8fb61a256ddabd4523bfb284a",
+            "airnode": "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf",
+            "template_id": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "timestamp": "0x0000000000000000000000000000000000000000000000000000000062590080",
+            "data": "0x000000000000000000000000000000000000000000000000000000000000007b",
+            "signature": "0x0102"
+        }]"#;
+        let signed = parse(json).unwrap();
+        assert_eq!(signed.len(), 1);
+        assert_eq!(signed[0].airnode.len(), 20);
+        assert_eq!(signed[0].template_id[31], 1);
+        assert_eq!(signed[0].timestamp[28..], 1_650_000_000u32.to_be_bytes());
+        assert_eq!(signed[0].signature, vec![1, 2]);
+
+        assert!(parse(r#"[{"airnode": "0x00"}]"#).is_err());
+        assert!(matches!(
+            parse(&json.replace(
+                "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "0x01"
+            )),
+            Err(RelayerError::Source(_))
+        ));
+    }
+}
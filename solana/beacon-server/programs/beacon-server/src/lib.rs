@@ -1,26 +1,57 @@
 mod utils;
 
-use crate::utils::{DatapointHashMap, DummySignatureManger, NameHashHashMap, SolanaClock};
+use crate::utils::{
+    DatapointHashMap, DummySignatureManger, NameHashHashMap, SolanaClock, WhitelistStatusHashMap,
+};
 use anchor_lang::{prelude::borsh::maybestd::collections::HashMap, prelude::*};
-use api3_common::{abi::U256, derive_beacon_id, ensure, process_beacon_update, DataPoint};
+use api3_common::dummy::InMemoryStorage;
+use api3_common::{
+    abi::U256, derive_beacon_id, ensure, process_beacon_update_with_circuit_breaker, DataPoint,
+    ErrorNamespace, FeedStatus, PauseState, Storage, SubscriptionPrice, Timestamp,
+};
 
 declare_id!("FRoo7m8Sf6ZAirGgnn3KopQymDtujWx818kcnRxzi23b");
 
-// a bunch of error codes
-const ERROR_INVALID_BEACON_ID_KEY: u64 = 1u64;
-const ERROR_INVALID_SYSVAR_INSTRUCTIONS_KEY: u64 = 2u64;
-const ERROR_SIGNATURES_NOT_VALIDATED: u64 = 3u64;
-const ERROR_SIGNATURES_MORE_THAN_DATA: u64 = 4u64;
-const ERROR_NOT_ENOUGH_ACCOUNT: u64 = 5u64;
-const ERROR_INVALID_NAME_HASH: u64 = 6u64;
-const ERROR_DATA_LENGTH_NOT_MATCH: u64 = 7u64;
-const ERROR_INVALID_DERIVED_DAPI_ID_KEY: u64 = 8u64;
-const ERROR_INVALID_SYSTEM_PROGRAM_ID: u64 = 9u64;
+// a bunch of error codes, in the Solana namespace so that they do not collide
+// with the `api3_common::Error` codes returned through `map_error`
+const ERROR_INVALID_BEACON_ID_KEY: u64 = solana_error(1);
+const ERROR_INVALID_SYSVAR_INSTRUCTIONS_KEY: u64 = solana_error(2);
+const ERROR_SIGNATURES_NOT_VALIDATED: u64 = solana_error(3);
+const ERROR_SIGNATURES_MORE_THAN_DATA: u64 = solana_error(4);
+const ERROR_NOT_ENOUGH_ACCOUNT: u64 = solana_error(5);
+const ERROR_INVALID_NAME_HASH: u64 = solana_error(6);
+const ERROR_DATA_LENGTH_NOT_MATCH: u64 = solana_error(7);
+const ERROR_INVALID_DERIVED_DAPI_ID_KEY: u64 = solana_error(8);
+const ERROR_INVALID_SYSTEM_PROGRAM_ID: u64 = solana_error(9);
+const ERROR_NOT_AUTHORIZED: u64 = solana_error(10);
+const ERROR_INVALID_ROLE_KEY: u64 = solana_error(11);
+const ERROR_SUBSCRIPTION_COST_OVERFLOW: u64 = solana_error(12);
+const ERROR_NOT_LEGACY_ACCOUNT: u64 = solana_error(13);
+const ERROR_INVALID_WHITELIST_STATUS_KEY: u64 = solana_error(14);
+
+const fn solana_error(index: u32) -> u64 {
+    (ErrorNamespace::Solana.base() + index) as u64
+}
+
+/// The serialized size of `WrappedDataPoint`: the raw data point, the bump,
+/// the pause state and the pending data point of the circuit breaker
+const WRAPPED_DATAPOINT_SPACE: usize = 4 + 36 + 1 + 1 + 4 + 36;
+
+/// The admin role description of the manager, same as the NEAR contract
+const ADMIN_ROLE_DESCRIPTION: &str = "admin role";
 
 fn map_error(e: api3_common::Error) -> anchor_lang::error::Error {
     anchor_lang::error::Error::from(ProgramError::Custom(e.into()))
 }
 
+/// Same as `map_error`, logs the index of the Beacon that failed the dAPI update
+fn map_dapi_error(e: api3_common::DapiUpdateError) -> anchor_lang::error::Error {
+    if let Some(i) = e.beacon_index {
+        msg!("dAPI update failed at beacon {}", i);
+    }
+    map_error(e.error)
+}
+
 /// The DAPI server implementation.
 ///
 /// Note that for solana there is no need to perform signature verification as we are
@@ -33,8 +64,9 @@ pub mod beacon_server {
     use super::*;
 
     /// Updates a Beacon using data signed by the respective Airnode,
-    /// without requiring a request or subscription.
-    ///
+    /// without requiring a request or subscription. An update that deviates more
+    /// than the circuit breaker threshold is held and pauses the Beacon, see
+    /// `api3_common::process_beacon_update_with_circuit_breaker`.
     ///
     /// `datapoint_key` The PDA of the datapoint account
     /// `template_id` Template ID
@@ -48,19 +80,32 @@ pub mod beacon_server {
         data: Vec<u8>,
     ) -> Result<()> {
         let airnode = ctx.accounts.user.key.to_bytes().to_vec();
-        let beacon_id = derive_beacon_id(airnode, template_id);
+        let beacon_id = derive_beacon_id(airnode, template_id).map_err(map_error)?;
         ensure!(
             beacon_id == datapoint_key,
             Error::from(ProgramError::from(ERROR_INVALID_BEACON_ID_KEY))
         )?;
         utils::check_sys_program(ctx.accounts.system_program.key)?;
+        utils::check_updates_allowed(&ctx.accounts.config, &ctx.accounts.datapoint)?;
 
         let timestamp = U256::from(&timestamp);
+        let threshold = ctx.accounts.config.circuit_breaker_threshold;
+        let mut status_storage = InMemoryStorage::default();
+        status_storage.store(beacon_id, ctx.accounts.datapoint.feed_status()?);
         let mut s = DatapointHashMap::new(
             vec![(beacon_id, &mut ctx.accounts.datapoint)],
             HashMap::new(),
         );
-        process_beacon_update(&mut s, beacon_id, timestamp, data).map_err(map_error)?;
+        let status = process_beacon_update_with_circuit_breaker(
+            &mut s,
+            &mut status_storage,
+            threshold,
+            beacon_id,
+            timestamp,
+            data,
+        )
+        .map_err(map_error)?;
+        ctx.accounts.datapoint.set_feed_status(status);
 
         Ok(())
     }
@@ -77,6 +122,7 @@ pub mod beacon_server {
             "must provide beacon accounts"
         );
         utils::check_sys_program(ctx.accounts.system_program.key)?;
+        utils::check_updates_allowed(&ctx.accounts.config, &ctx.accounts.datapoint)?;
 
         let beacon_id_tuples = ctx
             .remaining_accounts
@@ -95,8 +141,8 @@ pub mod beacon_server {
         let write = vec![(datapoint_key, &mut ctx.accounts.datapoint)];
         let mut read = HashMap::new();
         for (_, wrapped) in beacon_id_tuples {
-            let datapoint =
-                DataPoint::from(wrapped.raw_datapoint.clone()).expect("cannot parse datapoint");
+            utils::check_updates_allowed(&ctx.accounts.config, &wrapped)?;
+            let datapoint = DataPoint::from(wrapped.raw_datapoint.clone()).map_err(map_error)?;
             read.insert(beacon_ids[idx], datapoint);
             idx += 1;
         }
@@ -107,13 +153,17 @@ pub mod beacon_server {
         )?;
 
         let mut s = DatapointHashMap::new(write, read);
-        api3_common::update_dapi_with_beacons(&mut s, &beacon_ids).map_err(map_error)?;
+        api3_common::update_dapi_with_beacons(&mut s, &beacon_ids).map_err(map_dapi_error)?;
         Ok(())
     }
 
     /// Updates a dAPI using data signed by the respective Airnodes
     /// without requiring a request or subscription. The beacons for which the
-    /// signature is omitted will be read from the storage.
+    /// signature is omitted will be read from the storage. Fails if a Beacon is
+    /// paused by the circuit breaker or its signed data exceeds the threshold.
+    ///
+    /// The instructions sysvar is the first remaining account, followed by the
+    /// data point accounts of all the Beacons, in the order of `beacon_ids`.
     pub fn update_dapi_with_signed_data<'b>(
         ctx: Context<'_, '_, '_, 'b, DataPointAccount<'b>>,
         datapoint_key: [u8; 32],
@@ -131,47 +181,56 @@ pub mod beacon_server {
         let sig_count = ensure_batch_signed(instruction_acc, &data)?;
 
         utils::check_sys_program(ctx.accounts.system_program.key)?;
+        utils::check_updates_allowed(&ctx.accounts.config, &ctx.accounts.datapoint)?;
 
         // Step 2. Check the beacon id accounts are correct
-        let mut idx = 0usize;
         let keys = account_iter
             .clone()
-            .map(|item| -> Pubkey {
-                idx += 1;
-                *item.key
-            })
+            .map(|item| *item.key)
             .collect::<Vec<Pubkey>>();
 
         utils::check_beacon_ids(&beacon_ids, &keys, ctx.program_id)?;
         utils::check_dapi_id(&datapoint_key, &beacon_ids)?;
 
-        // Step 3. Extract and prepare the data for beacon ids from storage
-        let mut idx = 0;
+        // Step 3. Extract and prepare the data and the circuit breaker status
+        // of the beacons from storage. A signed Beacon that was never updated
+        // on its own has no account yet and nothing to deviate from.
         let write = vec![(datapoint_key, &mut ctx.accounts.datapoint)];
         let mut read = HashMap::new();
-        for account in account_iter {
-            let beacon_offset = idx + sig_count;
+        let mut status_storage = InMemoryStorage::default();
+        for (idx, account) in account_iter.enumerate() {
+            if idx < sig_count && account.data_is_empty() {
+                continue;
+            }
             let wrapped: Account<WrappedDataPoint> = Account::try_from(account)?;
-            let datapoint =
-                DataPoint::from(wrapped.raw_datapoint.clone()).expect("cannot parse datapoint");
-            read.insert(beacon_ids[beacon_offset], datapoint);
-            idx += 1;
+            utils::check_updates_allowed(&ctx.accounts.config, &wrapped)?;
+            read.insert(beacon_ids[idx], wrapped.datapoint()?);
+            status_storage.store(beacon_ids[idx], wrapped.feed_status()?);
         }
-        idx += sig_count;
-
-        ensure!(
-            idx == beacon_ids.len(),
-            Error::from(ProgramError::from(ERROR_NOT_ENOUGH_ACCOUNT))
-        )?;
 
         // Step 4. Execute update_dapi_with_signed_data process
+        let threshold = ctx.accounts.config.circuit_breaker_threshold;
         let mut s = DatapointHashMap::new(write, read);
-        let clock = SolanaClock::new(Clock::get().unwrap().unix_timestamp as u32);
-
-        let mut sig = (0..sig_count).into_iter().map(|_| vec![0]).collect::<Vec<_>>();
-        (sig_count..idx).into_iter().for_each(|_| sig.push(vec![]));
-        api3_common::update_dapi_with_signed_data::<_, DummySignatureManger, _>(
+        let clock = SolanaClock::new(Timestamp::from_secs(
+            Clock::get().unwrap().unix_timestamp as u64,
+        ));
+
+        let mut sig = (0..sig_count)
+            .into_iter()
+            .map(|_| vec![0])
+            .collect::<Vec<_>>();
+        (sig_count..beacon_ids.len())
+            .into_iter()
+            .for_each(|_| sig.push(vec![]));
+        api3_common::update_dapi_with_signed_data_with_circuit_breaker::<
+            _,
+            _,
+            DummySignatureManger,
+            _,
+        >(
             &mut s,
+            &status_storage,
+            threshold,
             &clock,
             airnodes,
             template_ids,
@@ -179,7 +238,332 @@ pub mod beacon_server {
             data,
             sig,
         )
+        .map_err(map_dapi_error)?;
+        Ok(())
+    }
+
+    /// Initializes the program config with `manager` as the manager.
+    /// Only the upgrade authority of the program can initialize it, once.
+    pub fn initialize(ctx: Context<InitializeConfigAccount>, manager: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.manager = manager.to_bytes();
+        config.pending_manager = [0u8; 32];
+        config.previous_manager = [0u8; 32];
+        config.pause_state = PauseState::Unpaused.into();
+        config.subscription_amount_per_period = 0;
+        config.subscription_period = 0;
+        config.subscription_balance = 0;
+        config.circuit_breaker_threshold = 0;
+        config.bump = *ctx.bumps.get("config").unwrap();
+        Ok(())
+    }
+
+    /// Moves a data point account created by an earlier version of the program,
+    /// with a smaller `WrappedDataPoint`, to a migration account and closes it.
+    /// `restore_datapoint` then recreates it with the current layout. Solana
+    /// cannot resize accounts, so this takes two transactions. Only the manager
+    /// can migrate accounts.
+    pub fn migrate_datapoint(
+        ctx: Context<MigrateDataPointAccount>,
+        datapoint_key: [u8; 32],
+    ) -> Result<()> {
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        let legacy = &ctx.accounts.datapoint;
+        let (raw_datapoint, pause_state) = utils::read_legacy_datapoint(legacy, ctx.program_id)?;
+
+        let migration = &mut ctx.accounts.migration;
+        migration.raw_datapoint = raw_datapoint;
+        migration.pause_state = pause_state;
+        migration.bump = *ctx.bumps.get("migration").unwrap();
+
+        // the runtime removes the account once it holds no lamports
+        let user = ctx.accounts.user.to_account_info();
+        **user.try_borrow_mut_lamports()? += legacy.lamports();
+        **legacy.try_borrow_mut_lamports()? = 0;
+        legacy.try_borrow_mut_data()?.fill(0);
+        msg!("migrating data point {}", hex::encode(datapoint_key));
+        Ok(())
+    }
+
+    /// Recreates a data point account moved by `migrate_datapoint`. If the data
+    /// point was updated in between, the more recent data point is kept.
+    /// Only the manager can restore accounts.
+    pub fn restore_datapoint(
+        ctx: Context<RestoreDataPointAccount>,
+        datapoint_key: [u8; 32],
+    ) -> Result<()> {
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        let migrated = ctx.accounts.migration.datapoint()?;
+        let datapoint = &mut ctx.accounts.datapoint;
+        if migrated.timestamp >= datapoint.datapoint()?.timestamp {
+            datapoint.raw_datapoint = ctx.accounts.migration.raw_datapoint.clone();
+        }
+        datapoint.pause_state = ctx.accounts.migration.pause_state;
+        datapoint.bump = *ctx.bumps.get("datapoint").unwrap();
+        msg!("restored data point {}", hex::encode(datapoint_key));
+        Ok(())
+    }
+
+    /// Grants `role` to `who`. Only the manager can grant roles.
+    pub fn grant_role(ctx: Context<RoleAccount>, role: [u8; 32], who: Pubkey) -> Result<()> {
+        utils::check_sys_program(ctx.accounts.system_program.key)?;
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        let membership = &mut ctx.accounts.role_membership;
+        membership.role = role;
+        membership.who = who;
+        membership.granted = true;
+        membership.bump = *ctx.bumps.get("role_membership").unwrap();
+        msg!("granted role {} to {}", hex::encode(role), who);
+        Ok(())
+    }
+
+    /// Revokes `role` from `who`. Only the manager can revoke roles.
+    pub fn revoke_role(ctx: Context<RoleAccount>, role: [u8; 32], who: Pubkey) -> Result<()> {
+        utils::check_sys_program(ctx.accounts.system_program.key)?;
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        ctx.accounts.role_membership.granted = false;
+        msg!("revoked role {} from {}", hex::encode(role), who);
+        Ok(())
+    }
+
+    /// Proposes `new_manager` as the manager. Only the manager can propose and
+    /// the transfer is completed once `new_manager` calls `accept_manager`.
+    pub fn propose_manager(ctx: Context<ManagerAccount>, new_manager: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_manager = api3_common::propose_manager(
+            &config.manager,
+            new_manager.to_bytes(),
+            &ctx.accounts.user.key.to_bytes(),
+        )
+        .map_err(map_error)?;
+        msg!("proposed manager {}", new_manager);
+        Ok(())
+    }
+
+    /// Called by the pending manager to complete the manager transfer. Roles
+    /// derived from the previous manager can then be moved with `migrate_role_member`.
+    pub fn accept_manager(ctx: Context<ManagerAccount>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let pending_manager = Some(config.pending_manager).filter(|m| *m != [0u8; 32]);
+        let new_manager =
+            api3_common::accept_manager(pending_manager, &ctx.accounts.user.key.to_bytes())
+                .map_err(map_error)?;
+        config.previous_manager = config.manager;
+        config.manager = new_manager;
+        config.pending_manager = [0u8; 32];
+        Ok(())
+    }
+
+    /// Moves `who` from the role with `description` derived from the previous
+    /// manager to the same role derived from the current manager. Only the
+    /// manager can migrate roles. `previous_role` and `role` are the derived
+    /// roles, they are passed in to locate the role membership accounts and
+    /// are checked against `description`.
+    pub fn migrate_role_member(
+        ctx: Context<MigrateRoleAccount>,
+        description: String,
+        previous_role: [u8; 32],
+        role: [u8; 32],
+        who: Pubkey,
+    ) -> Result<()> {
+        utils::check_sys_program(ctx.accounts.system_program.key)?;
+        let config = &ctx.accounts.config;
+        utils::check_manager(config, ctx.accounts.user.key)?;
+        ensure!(
+            previous_role
+                == utils::derive_manager_role(&config.previous_manager, description.clone())
+                && role == utils::derive_manager_role(&config.manager, description),
+            Error::from(ProgramError::from(ERROR_INVALID_ROLE_KEY))
+        )?;
+
+        if ctx.accounts.previous_role_membership.granted {
+            ctx.accounts.previous_role_membership.granted = false;
+            let membership = &mut ctx.accounts.role_membership;
+            membership.role = role;
+            membership.who = who;
+            membership.granted = true;
+            membership.bump = *ctx.bumps.get("role_membership").unwrap();
+            msg!(
+                "migrated role {} to {} for {}",
+                hex::encode(previous_role),
+                hex::encode(role),
+                who
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the pause state of the program. `0` is unpaused, `1` blocks updates
+    /// and `2` blocks both updates and reads.
+    /// The sender must be the manager or have the pauser role, in the latter case
+    /// the role membership account of the sender must be the first remaining account.
+    pub fn set_pause_state(ctx: Context<PauseAccount>, state: u8) -> Result<()> {
+        let state = PauseState::try_from(state).map_err(map_error)?;
+        utils::check_can_pause(
+            &ctx.accounts.config,
+            ctx.accounts.user.key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        ctx.accounts.config.pause_state = state.into();
+        Ok(())
+    }
+
+    /// Sets the pause state of the data feed. `0` is unpaused, `1` blocks updates
+    /// and `2` blocks both updates and reads.
+    /// The sender must be the manager or have the pauser role, in the latter case
+    /// the role membership account of the sender must be the first remaining account.
+    pub fn set_data_feed_pause_state(
+        ctx: Context<DataFeedPauseAccount>,
+        datapoint_key: [u8; 32],
+        state: u8,
+    ) -> Result<()> {
+        let state = PauseState::try_from(state).map_err(map_error)?;
+        utils::check_can_pause(
+            &ctx.accounts.config,
+            ctx.accounts.user.key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        ctx.accounts.datapoint.pause_state = state.into();
+        msg!(
+            "data feed {} pause state {}",
+            hex::encode(datapoint_key),
+            u8::from(state)
+        );
+        Ok(())
+    }
+
+    /// Sets the maximum deviation a Beacon update can have from the current value
+    /// before the Beacon is paused. `100_000_000` is 100%, zero disables the
+    /// circuit breaker. Only the manager can set the threshold.
+    pub fn set_circuit_breaker_threshold(
+        ctx: Context<ManagerAccount>,
+        threshold: u32,
+    ) -> Result<()> {
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        ctx.accounts.config.circuit_breaker_threshold = threshold;
+        msg!("circuit breaker threshold {}", threshold);
+        Ok(())
+    }
+
+    /// Releases a Beacon paused by the circuit breaker by storing its held update.
+    /// Only the manager can release a Beacon.
+    pub fn release_data_feed(
+        ctx: Context<DataFeedPauseAccount>,
+        datapoint_key: [u8; 32],
+    ) -> Result<()> {
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        let datapoint = &mut ctx.accounts.datapoint;
+        match datapoint.feed_status()? {
+            FeedStatus::Paused(pending) => {
+                datapoint.raw_datapoint = Vec::from(pending);
+                datapoint.set_feed_status(FeedStatus::Active);
+            }
+            FeedStatus::Active => return Err(map_error(api3_common::Error::DataFeedNotPaused)),
+        }
+        msg!("released data feed {}", hex::encode(datapoint_key));
+        Ok(())
+    }
+
+    /// Sets the price readers pay in lamports to subscribe to a data feed for
+    /// `period` seconds. A zero `period` disables subscriptions.
+    /// Only the manager can set the price.
+    pub fn set_subscription_price(
+        ctx: Context<ManagerAccount>,
+        amount_per_period: u64,
+        period: u64,
+    ) -> Result<()> {
+        utils::check_manager(&ctx.accounts.config, ctx.accounts.user.key)?;
+        let config = &mut ctx.accounts.config;
+        config.subscription_amount_per_period = amount_per_period;
+        config.subscription_period = period;
+        msg!(
+            "subscription price {} lamports per {} seconds",
+            amount_per_period,
+            period
+        );
+        Ok(())
+    }
+
+    /// Whitelists the sender to read the data feed for `periods` periods of the
+    /// subscription price. The cost is transferred from the sender to the config
+    /// account and the whitelist is extended from the current expiration if the
+    /// sender is still whitelisted.
+    pub fn subscribe(
+        ctx: Context<SubscribeAccount>,
+        data_feed_id: [u8; 32],
+        periods: u64,
+    ) -> Result<()> {
+        utils::check_sys_program(ctx.accounts.system_program.key)?;
+        let price = SubscriptionPrice::new(
+            u128::from(ctx.accounts.config.subscription_amount_per_period),
+            ctx.accounts.config.subscription_period,
+        );
+        let cost = u64::try_from(price.cost(periods).map_err(map_error)?)
+            .map_err(|_| Error::from(ProgramError::from(ERROR_SUBSCRIPTION_COST_OVERFLOW)))?;
+
+        let reader = ctx.accounts.user.key.to_bytes();
+        let key = api3_common::whitelist_status_key(&data_feed_id, &reader);
+        ctx.accounts.whitelist_status.bump = *ctx.bumps.get("whitelist_status").unwrap();
+        let mut storage =
+            WhitelistStatusHashMap::new(vec![(key, &mut ctx.accounts.whitelist_status)]);
+        storage.check_key(&key)?;
+        let clock = SolanaClock::new(Timestamp::from_secs(Clock::get()?.unix_timestamp as u64));
+        let expiration = api3_common::extend_subscription(
+            &mut storage,
+            &clock,
+            &price,
+            &data_feed_id,
+            &reader,
+            periods,
+        )
         .map_err(map_error)?;
+
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.user.key,
+                &ctx.accounts.config.key(),
+                cost,
+            ),
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.config.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.subscription_balance = config
+            .subscription_balance
+            .checked_add(cost)
+            .ok_or_else(|| Error::from(ProgramError::from(ERROR_SUBSCRIPTION_COST_OVERFLOW)))?;
+
+        msg!(
+            "{} subscribed to {} until {}",
+            ctx.accounts.user.key,
+            hex::encode(data_feed_id),
+            expiration
+        );
+        Ok(())
+    }
+
+    /// Withdraws `amount` lamports of the subscription payments to the manager.
+    /// Only the manager can withdraw.
+    pub fn withdraw_subscription_balance(ctx: Context<WithdrawAccount>, amount: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let balance = api3_common::withdraw_subscription_balance(
+            &config.manager,
+            &ctx.accounts.user.key.to_bytes(),
+            u128::from(config.subscription_balance),
+            u128::from(amount),
+        )
+        .map_err(map_error)?;
+        // the balance only ever decreases from a u64, so this cannot truncate
+        config.subscription_balance = balance as u64;
+
+        let user = ctx.accounts.user.to_account_info();
+        **config.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **user.try_borrow_mut_lamports()? += amount;
         Ok(())
     }
 
@@ -228,20 +612,218 @@ pub struct DataPointAccount<'info> {
     #[account(
         init_if_needed,
         payer = user,
-        space = 8 + 41,
+        space = 8 + WRAPPED_DATAPOINT_SPACE,
+        seeds = [b"datapoint", datapoint_key.as_ref()],
+        bump
+    )]
+    pub datapoint: Account<'info, WrappedDataPoint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(datapoint_key: [u8; 32])]
+pub struct MigrateDataPointAccount<'info> {
+    /// CHECK: a legacy `WrappedDataPoint`, which does not deserialize with the current layout
+    #[account(mut, seeds = [b"datapoint", datapoint_key.as_ref()], bump)]
+    pub datapoint: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + WRAPPED_DATAPOINT_SPACE,
+        seeds = [b"migration", datapoint_key.as_ref()],
+        bump
+    )]
+    pub migration: Account<'info, WrappedDataPoint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(datapoint_key: [u8; 32])]
+pub struct RestoreDataPointAccount<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + WRAPPED_DATAPOINT_SPACE,
         seeds = [b"datapoint", datapoint_key.as_ref()],
         bump
     )]
     pub datapoint: Account<'info, WrappedDataPoint>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"migration", datapoint_key.as_ref()],
+        bump = migration.bump
+    )]
+    pub migration: Account<'info, WrappedDataPoint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfigAccount<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 126,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(constraint = program.programdata_address() == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::BeaconServer>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(user.key())
+            @ ProgramError::from(ERROR_NOT_AUTHORIZED)
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(role: [u8; 32], who: Pubkey)]
+pub struct RoleAccount<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 66,
+        seeds = [b"role", role.as_ref(), who.as_ref()],
+        bump
+    )]
+    pub role_membership: Account<'info, WrappedRoleMembership>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManagerAccount<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(description: String, previous_role: [u8; 32], role: [u8; 32], who: Pubkey)]
+pub struct MigrateRoleAccount<'info> {
+    #[account(
+        mut,
+        seeds = [b"role", previous_role.as_ref(), who.as_ref()],
+        bump = previous_role_membership.bump
+    )]
+    pub previous_role_membership: Account<'info, WrappedRoleMembership>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 66,
+        seeds = [b"role", role.as_ref(), who.as_ref()],
+        bump
+    )]
+    pub role_membership: Account<'info, WrappedRoleMembership>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
     #[account(mut)]
     pub user: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(data_feed_id: [u8; 32])]
+pub struct SubscribeAccount<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 9,
+        seeds = [b"whitelist", data_feed_id.as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub whitelist_status: Account<'info, WrappedWhitelistStatus>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawAccount<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauseAccount<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(datapoint_key: [u8; 32])]
+pub struct DataFeedPauseAccount<'info> {
+    #[account(mut, seeds = [b"datapoint", datapoint_key.as_ref()], bump)]
+    pub datapoint: Account<'info, WrappedDataPoint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, WrappedConfig>,
+    pub user: Signer<'info>,
+}
+
 #[account]
 pub struct WrappedDataPoint {
     pub raw_datapoint: Vec<u8>,
     pub bump: u8,
+    pub pause_state: u8,
+    /// The update held by the circuit breaker, empty unless the Beacon is paused
+    pub pending_datapoint: Vec<u8>,
+}
+
+#[account]
+pub struct WrappedConfig {
+    pub manager: [u8; 32],
+    pub pending_manager: [u8; 32],
+    pub previous_manager: [u8; 32],
+    pub pause_state: u8,
+    /// Subscription price in lamports, see `set_subscription_price`
+    pub subscription_amount_per_period: u64,
+    pub subscription_period: u64,
+    /// Lamports paid by subscribers that the manager has not withdrawn yet
+    pub subscription_balance: u64,
+    pub bump: u8,
+    /// See `set_circuit_breaker_threshold`
+    pub circuit_breaker_threshold: u32,
+}
+
+/// The membership of `who` in `role`. `role` and `who` are stored so that
+/// clients can enumerate the members of a role or the roles of an account.
+#[account]
+pub struct WrappedRoleMembership {
+    pub role: [u8; 32],
+    pub who: Pubkey,
+    pub granted: bool,
+    pub bump: u8,
+}
+
+/// The temporary whitelist of a reader for a data feed, bought with `subscribe`
+#[account]
+pub struct WrappedWhitelistStatus {
+    pub expiration_timestamp: u64,
+    pub bump: u8,
 }
 
 #[account]
@@ -1,16 +1,22 @@
 use crate::{
-    WrappedDataPoint, WrappedDataPointId, ERROR_DATA_LENGTH_NOT_MATCH, ERROR_INVALID_BEACON_ID_KEY,
-    ERROR_INVALID_DERIVED_DAPI_ID_KEY, ERROR_INVALID_NAME_HASH, ERROR_INVALID_SYSTEM_PROGRAM_ID,
+    map_error, WrappedConfig, WrappedDataPoint, WrappedDataPointId, WrappedRoleMembership,
+    WrappedWhitelistStatus, ADMIN_ROLE_DESCRIPTION, ERROR_DATA_LENGTH_NOT_MATCH,
+    ERROR_INVALID_BEACON_ID_KEY, ERROR_INVALID_DERIVED_DAPI_ID_KEY, ERROR_INVALID_NAME_HASH,
+    ERROR_INVALID_ROLE_KEY, ERROR_INVALID_SYSTEM_PROGRAM_ID, ERROR_INVALID_WHITELIST_STATUS_KEY,
+    ERROR_NOT_AUTHORIZED, ERROR_NOT_LEGACY_ACCOUNT, WRAPPED_DATAPOINT_SPACE,
 };
 use anchor_lang::accounts::account::Account;
 use anchor_lang::prelude::borsh::maybestd::collections::HashMap;
 use anchor_lang::prelude::*;
-use api3_common::abi::Token;
+use api3_common::abi::{Token, U256};
+use api3_common::dummy::DummyAccess;
 use api3_common::{
-    ensure, keccak_packed, Bytes32, DataPoint, SignatureManger, Storage, TimestampChecker,
+    ensure, keccak_packed, AccessControlRegistry, Bytes32, DataPoint, FeedStatus, PauseState,
+    RoleDeriver, SignatureManger, Storage, Timestamp, TimestampChecker, WhitelistStatus,
 };
 
 const DATAPOINT_SEED: &str = "datapoint";
+const ROLE_SEED: &str = "role";
 
 pub type NameHashAccountRef<'info> = Account<'info, WrappedDataPointId>;
 pub(crate) struct NameHashHashMap<'info, 'account> {
@@ -60,13 +66,15 @@ impl<'info, 'account> DatapointHashMap<'info, 'account> {
 
 impl<'info, 'account> Storage<DataPoint> for DatapointHashMap<'info, 'account> {
     fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        // An account that does not hold a valid data point reads as missing, so
+        // that the caller fails with `BeaconDataNotFound` instead of panicking
         match self.read.get(k) {
             Some(d) => Some(d.clone()),
-            None => self.write.get(k).map(|a| {
+            None => self.write.get(k).and_then(|a| {
                 if a.raw_datapoint.is_empty() {
-                    DataPoint::default()
+                    Some(DataPoint::default())
                 } else {
-                    DataPoint::from(a.raw_datapoint.clone()).expect("cannot load datapoint")
+                    DataPoint::from(a.raw_datapoint.clone()).ok()
                 }
             }),
         }
@@ -78,18 +86,88 @@ impl<'info, 'account> Storage<DataPoint> for DatapointHashMap<'info, 'account> {
     }
 }
 
+pub type WhitelistStatusAccountRef<'info> = Account<'info, WrappedWhitelistStatus>;
+/// Whitelist statuses of the readers. Solana only supports temporary whitelisting
+/// through subscriptions, so the indefinite whitelist count is always zero.
+pub(crate) struct WhitelistStatusHashMap<'info, 'account> {
+    write: HashMap<Bytes32, &'account mut WhitelistStatusAccountRef<'info>>,
+}
+
+impl<'info, 'account> WhitelistStatusHashMap<'info, 'account> {
+    pub fn new(accounts: Vec<(Bytes32, &'account mut WhitelistStatusAccountRef<'info>)>) -> Self {
+        let mut write = HashMap::new();
+        for (key, aref) in accounts {
+            write.insert(key, aref);
+        }
+        Self { write }
+    }
+
+    /// Checks that the whitelist status of `k` is backed by one of the accounts,
+    /// `store` does not write statuses of other keys anywhere
+    pub fn check_key(&self, k: &Bytes32) -> Result<()> {
+        if self.write.contains_key(k) {
+            Ok(())
+        } else {
+            Err(Error::from(ProgramError::from(
+                ERROR_INVALID_WHITELIST_STATUS_KEY,
+            )))
+        }
+    }
+}
+
+impl<'info, 'account> Storage<WhitelistStatus> for WhitelistStatusHashMap<'info, 'account> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        self.write.get(k).map(|a| WhitelistStatus {
+            expiration_timestamp: Timestamp::from_secs(a.expiration_timestamp),
+            indefinite_whitelist_count: U256::zero(),
+        })
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        if let Some(a) = self.write.get_mut(&k) {
+            (*a).expiration_timestamp = status.expiration_timestamp.as_secs();
+        }
+    }
+}
+
+impl WrappedDataPoint {
+    /// The stored data point, the default one if the account was created by a
+    /// pause state update and never updated
+    pub(crate) fn datapoint(&self) -> Result<DataPoint> {
+        if self.raw_datapoint.is_empty() {
+            return Ok(DataPoint::default());
+        }
+        DataPoint::from(self.raw_datapoint.clone()).map_err(map_error)
+    }
+
+    pub(crate) fn feed_status(&self) -> Result<FeedStatus> {
+        if self.pending_datapoint.is_empty() {
+            return Ok(FeedStatus::Active);
+        }
+        let pending = DataPoint::from(self.pending_datapoint.clone()).map_err(map_error)?;
+        Ok(FeedStatus::Paused(pending))
+    }
+
+    pub(crate) fn set_feed_status(&mut self, status: FeedStatus) {
+        self.pending_datapoint = match status {
+            FeedStatus::Active => vec![],
+            FeedStatus::Paused(pending) => Vec::from(pending),
+        };
+    }
+}
+
 pub(crate) struct SolanaClock {
-    current_timestamp: u32,
+    current_timestamp: Timestamp,
 }
 
 impl SolanaClock {
-    pub fn new(current_timestamp: u32) -> Self {
+    pub fn new(current_timestamp: Timestamp) -> Self {
         Self { current_timestamp }
     }
 }
 
 impl TimestampChecker for SolanaClock {
-    fn current_timestamp(&self) -> u32 {
+    fn current_timestamp(&self) -> Timestamp {
         self.current_timestamp
     }
 }
@@ -106,19 +184,19 @@ impl SignatureManger for DummySignatureManger {
     }
 }
 
+/// Checks `pdas` are the data point accounts of `beacon_ids`, in the same order
 pub(crate) fn check_beacon_ids(
     beacon_ids: &[Bytes32],
     pdas: &[Pubkey],
     program_id: &Pubkey,
 ) -> Result<()> {
     ensure!(
-        beacon_ids.len() >= pdas.len(),
+        beacon_ids.len() == pdas.len(),
         Error::from(ProgramError::from(ERROR_DATA_LENGTH_NOT_MATCH))
     )?;
-    let diff = beacon_ids.len() - pdas.len();
-    for i in 0..pdas.len() {
+    for (beacon_id, pda) in beacon_ids.iter().zip(pdas) {
         ensure!(
-            derive_datapoint_pubkey(&beacon_ids[diff + i], program_id) == pdas[i],
+            derive_datapoint_pubkey(beacon_id, program_id) == *pda,
             Error::from(ProgramError::from(ERROR_INVALID_BEACON_ID_KEY))
         )?;
     }
@@ -155,3 +233,96 @@ fn derive_datapoint_pubkey(datapoint_key: &[u8], program_id: &Pubkey) -> Pubkey
         Pubkey::find_program_address(&[DATAPOINT_SEED.as_bytes(), datapoint_key], program_id);
     key
 }
+
+/// Reads a data point account of an earlier version of the program. The
+/// `pause_state` was added after the first version, the pending data point of
+/// the circuit breaker after that. Returns the raw data point and the pause state.
+pub(crate) fn read_legacy_datapoint(
+    account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<(Vec<u8>, u8)> {
+    let not_legacy = || Error::from(ProgramError::from(ERROR_NOT_LEGACY_ACCOUNT));
+    ensure!(account.owner == program_id, not_legacy())?;
+    let data = account.try_borrow_data()?;
+    ensure!(
+        data.len() < 8 + WRAPPED_DATAPOINT_SPACE
+            && data.get(..8)
+                == Some(&<WrappedDataPoint as anchor_lang::Discriminator>::discriminator()[..]),
+        not_legacy()
+    )?;
+    let mut rest = &data[8..];
+    let raw_datapoint = Vec::<u8>::deserialize(&mut rest).map_err(|_| not_legacy())?;
+    // the bump, then the pause state if the account has one
+    let pause_state = rest.get(1).copied().unwrap_or_default();
+    Ok((raw_datapoint, pause_state))
+}
+
+/// Checks the sender is the manager in the config
+pub(crate) fn check_manager(config: &WrappedConfig, sender: &Pubkey) -> Result<()> {
+    ensure!(
+        config.manager == sender.to_bytes(),
+        Error::from(ProgramError::from(ERROR_NOT_AUTHORIZED))
+    )
+}
+
+/// Checks the sender is the manager or has the pauser role. The role membership
+/// account of the sender is expected to be the first of `remaining_accounts`.
+pub(crate) fn check_can_pause(
+    config: &WrappedConfig,
+    sender: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<()> {
+    if config.manager == sender.to_bytes() {
+        return Ok(());
+    }
+    let membership = remaining_accounts
+        .first()
+        .ok_or_else(|| Error::from(ProgramError::from(ERROR_NOT_AUTHORIZED)))?;
+    let role = derive_pauser_role(&config.manager);
+    ensure!(
+        derive_role_membership_pubkey(&role, sender, program_id) == *membership.key,
+        Error::from(ProgramError::from(ERROR_INVALID_ROLE_KEY))
+    )?;
+    let membership: Account<WrappedRoleMembership> = Account::try_from(membership)?;
+    ensure!(
+        membership.granted,
+        Error::from(ProgramError::from(ERROR_NOT_AUTHORIZED))
+    )
+}
+
+/// Checks neither the program nor the data feed is paused for updates
+pub(crate) fn check_updates_allowed(
+    config: &WrappedConfig,
+    datapoint: &WrappedDataPoint,
+) -> Result<()> {
+    let global = PauseState::try_from(config.pause_state).map_err(map_error)?;
+    let data_feed = PauseState::try_from(datapoint.pause_state).map_err(map_error)?;
+    api3_common::ensure_updates_allowed(global, data_feed).map_err(map_error)
+}
+
+/// Derives the pauser role of the manager, same as `StaticRole::PauserRole`
+/// of the access control registries in the other chains
+pub(crate) fn derive_pauser_role(manager: &[u8]) -> Bytes32 {
+    derive_manager_role(
+        manager,
+        <DummyAccess<Bytes32> as AccessControlRegistry>::PAUSER_ROLE_DESCRIPTION.to_string(),
+    )
+}
+
+/// Derives the role with `description` under the admin role of the manager
+pub(crate) fn derive_manager_role(manager: &[u8], description: String) -> Bytes32 {
+    let admin_role = RoleDeriver::derive_role(
+        RoleDeriver::derive_root_role(manager),
+        ADMIN_ROLE_DESCRIPTION.to_string(),
+    );
+    RoleDeriver::derive_role(admin_role, description)
+}
+
+fn derive_role_membership_pubkey(role: &Bytes32, who: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    let (key, _) = Pubkey::find_program_address(
+        &[ROLE_SEED.as_bytes(), role.as_ref(), who.as_ref()],
+        program_id,
+    );
+    key
+}
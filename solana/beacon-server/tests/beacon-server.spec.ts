@@ -55,6 +55,16 @@ describe("beacon-server", () => {
     await provider.connection.confirmTransaction(await provider.connection.requestAirdrop(airnode3.publicKey, anchor.web3.LAMPORTS_PER_SOL));
     await provider.connection.confirmTransaction(await provider.connection.requestAirdrop(airnode4.publicKey, anchor.web3.LAMPORTS_PER_SOL));
     await provider.connection.confirmTransaction(await provider.connection.requestAirdrop(messageRelayer.publicKey, anchor.web3.LAMPORTS_PER_SOL));
+
+    // only the upgrade authority, the provider wallet that deployed the program, can initialize
+    let failed = false;
+    try {
+      await dapiClient.initialize(messageRelayer.publicKey, messageRelayer);
+    } catch (err) {
+      failed = true;
+    }
+    expect(failed).to.be.true;
+    await dapiClient.initialize(messageRelayer.publicKey);
   }) 
 
   describe("update beacon with signed data", () => {
@@ -232,6 +242,159 @@ describe("beacon-server", () => {
     });
   });
 
+  describe("pause", () => {
+    it("blocks updates while paused", async () => {
+      const beaconId3 = dapiClient.deriveBeaconId(airnode3.publicKey.toBytes(), templateId3);
+      const beaconId4 = dapiClient.deriveBeaconId(airnode4.publicKey.toBytes(), templateId4);
+
+      await dapiClient.setPauseState(1, messageRelayer);
+      let failed = false;
+      try {
+        await dapiClient.updateDapiWithBeacons([beaconId3, beaconId4], messageRelayer);
+      } catch (err) {
+        expect(err).to.be.instanceOf(SendTransactionError);
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.setPauseState(0, messageRelayer);
+    });
+
+    it("blocks dAPI updates while a Beacon is paused", async () => {
+      const beaconId3 = dapiClient.deriveBeaconId(airnode3.publicKey.toBytes(), templateId3);
+      const beaconId4 = dapiClient.deriveBeaconId(airnode4.publicKey.toBytes(), templateId4);
+
+      await dapiClient.setDataFeedPauseState(beaconId4, 1, messageRelayer);
+      let failed = false;
+      try {
+        await dapiClient.updateDapiWithBeacons([beaconId3, beaconId4], messageRelayer);
+      } catch (err) {
+        expect(err).to.be.instanceOf(SendTransactionError);
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.setDataFeedPauseState(beaconId4, 0, messageRelayer);
+    });
+
+    it("only manager or pauser can pause", async () => {
+      let failed = false;
+      try {
+        await dapiClient.setPauseState(1, airnode1);
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+    });
+  });
+
+  describe("circuit breaker", () => {
+    it("holds extreme updates until released", async () => {
+      const beaconId4 = dapiClient.deriveBeaconId(airnode4.publicKey.toBytes(), templateId4);
+      // 10%
+      await dapiClient.setCircuitBreakerThreshold(10000000, messageRelayer);
+
+      const timestamp = timestamp4 + 1;
+      const [airnodeSignature, airnodeTxn] = await dapiClient.newUpdateBeaconWithSignedDataTxn(
+        templateId4,
+        timestamp,
+        data4 * 2 + 1,
+        airnode4,
+        messageRelayer.publicKey
+      );
+      const offlineTxn = await relayTxn(airnodeTxn, airnodeSignature, airnode4.publicKey, messageRelayer);
+      await provider.connection.confirmTransaction(await provider.connection.sendRawTransaction(offlineTxn));
+
+      let datapoint = await dapiClient.readWithDataPointId(beaconId4);
+      expect(datapoint.value).to.eq(data4);
+
+      await dapiClient.releaseDataFeed(beaconId4, messageRelayer);
+      datapoint = await dapiClient.readWithDataPointId(beaconId4);
+      expect(datapoint.value).to.eq(data4 * 2 + 1);
+      expect(datapoint.timestamp).to.eq(timestamp);
+
+      await dapiClient.setCircuitBreakerThreshold(0, messageRelayer);
+    });
+  });
+
+  describe("roles", () => {
+    it("enumerates role members", async () => {
+      const role = Buffer.alloc(32, 7);
+      await dapiClient.grantRole(role, airnode1.publicKey, messageRelayer);
+
+      let members = await dapiClient.roleMembers(role);
+      expect(members.map(m => m.toBase58())).to.deep.eq([airnode1.publicKey.toBase58()]);
+      const roles = await dapiClient.rolesOf(airnode1.publicKey);
+      expect(roles.map(r => r.toString("hex"))).to.include(role.toString("hex"));
+
+      await dapiClient.revokeRole(role, airnode1.publicKey, messageRelayer);
+      members = await dapiClient.roleMembers(role);
+      expect(members).to.be.empty;
+    });
+  });
+
+  describe("manager transfer", () => {
+    it("requires the pending manager to accept", async () => {
+      const newManager = anchor.web3.Keypair.generate();
+
+      let failed = false;
+      try {
+        await dapiClient.proposeManager(newManager.publicKey, airnode1);
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.proposeManager(newManager.publicKey, messageRelayer);
+      failed = false;
+      try {
+        await dapiClient.acceptManager(airnode1);
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.acceptManager(newManager);
+
+      // hand it back so the other tests keep working
+      await dapiClient.proposeManager(messageRelayer.publicKey, newManager);
+      await dapiClient.acceptManager(messageRelayer);
+    });
+  });
+
+  describe("subscription", () => {
+    it("whitelists paying readers and pays out the manager", async () => {
+      const dataFeedId = Buffer.alloc(32, 9);
+      let failed = false;
+      try {
+        await dapiClient.subscribe(dataFeedId, 1, airnode2);
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.setSubscriptionPrice(1000, 3600, messageRelayer);
+      await dapiClient.subscribe(dataFeedId, 2, airnode2);
+      const expiration = await dapiClient.subscriptionExpiration(dataFeedId, airnode2.publicKey);
+      expect(expiration).to.be.greaterThan(Math.floor(Date.now() / 1000) + 3600);
+
+      let price = await dapiClient.subscriptionPrice();
+      expect(price.balance).to.eq(2000);
+
+      failed = false;
+      try {
+        await dapiClient.withdrawSubscriptionBalance(2000, airnode2);
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      await dapiClient.withdrawSubscriptionBalance(2000, messageRelayer);
+      price = await dapiClient.subscriptionPrice();
+      expect(price.balance).to.eq(0);
+    });
+  });
+
   describe("setName", () => {
     it("should work", async () => {
       const beaconId = dapiClient.deriveBeaconId(airnode3.publicKey.toBytes(), templateId3);
@@ -1,7 +1,8 @@
 import * as anchor from "@project-serum/anchor";
 import { 
-    bufferU64BE, Datapoint, deriveBeaconId, deriveDApiId,
-    deriveDatapointPDA, deriveNameHashPDA, encodeData, keccak256Packed
+    bufferU64BE, Datapoint, deriveBeaconId, deriveConfigPDA, deriveDApiId,
+    deriveDatapointPDA, deriveNameHashPDA, deriveRolePDA, deriveWhitelistPDA, encodeData,
+    keccak256Packed
 } from "./utils";
 import nacl from 'tweetnacl';
 import { createInstructionWithPublicKey, SignatureParam } from "./sig";
@@ -45,6 +46,7 @@ export class DapiClient {
     ): Promise<[Uint8Array, Buffer]> {
         const beaconId = deriveBeaconId(storageFunder.publicKey.toBytes(), templateID);
         const beaconIdPDA = await deriveDatapointPDA(beaconId, this.program.programId);
+        const configPDA = await deriveConfigPDA(this.program.programId);
     
         const bufferedTimestamp = bufferU64BE(timestamp);
         const encodedData = encodeData(data);
@@ -57,6 +59,7 @@ export class DapiClient {
           {
             accounts: {
               datapoint: beaconIdPDA,
+              config: configPDA,
               user: storageFunder.publicKey,
               systemProgram: anchor.web3.SystemProgram.programId,
             }
@@ -75,6 +78,7 @@ export class DapiClient {
     public async updateDapiWithBeacons(beaconIds: Buffer[], sender: anchor.web3.Keypair) {
       const dataPointId = deriveDApiId(beaconIds);
       const dapiPDA = await deriveDatapointPDA(dataPointId, this.program.programId);
+      const configPDA = await deriveConfigPDA(this.program.programId);
 
       const remainingAccounts = [];
       for (const b of beaconIds) {
@@ -88,6 +92,7 @@ export class DapiClient {
         {
           accounts: {
             datapoint: dapiPDA,
+            config: configPDA,
             user: sender.publicKey,
             systemProgram: anchor.web3.SystemProgram.programId,
           },
@@ -121,9 +126,11 @@ export class DapiClient {
 
       const dataPointId = deriveDApiId(beaconIds);
       const dapiPDA = await deriveDatapointPDA(dataPointId, this.program.programId);
+      const configPDA = await deriveConfigPDA(this.program.programId);
 
+      // the accounts of the signed Beacons are needed for their circuit breaker status
       const remainingAccounts = [{ isSigner: false, isWritable: false, pubkey: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY }];
-      for (let i = sigWithMessages.length; i < beaconIds.length; i++) {
+      for (let i = 0; i < beaconIds.length; i++) {
         const id = beaconIds[i];
         const pda = await deriveDatapointPDA(id, this.program.programId);
         remainingAccounts.push({ isSigner: false, isWritable: false, pubkey: pda });
@@ -139,6 +146,7 @@ export class DapiClient {
         {
           accounts: {
             datapoint: dapiPDA,
+            config: configPDA,
             user: sender.publicKey,
             systemProgram: anchor.web3.SystemProgram.programId,
           },
@@ -195,4 +203,389 @@ export class DapiClient {
       const wrappedDataPointId = await this.program.account.wrappedDataPointId.fetch(nameHashPDA);
       return this.readWithDataPointId(wrappedDataPointId.datapointId);
     }
+
+    /**
+     * Initializes the program config
+     * @param manager The manager
+     * @param sender The upgrade authority of the program, the provider wallet if omitted
+     */
+    public async initialize(manager: anchor.web3.PublicKey, sender?: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const [programData] = await anchor.web3.PublicKey.findProgramAddress(
+        [this.program.programId.toBuffer()],
+        new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+      return await this.program.rpc.initialize(
+        manager,
+        {
+          accounts: {
+            config: configPDA,
+            program: this.program.programId,
+            programData,
+            user: sender === undefined ? this.provider.wallet.publicKey : sender.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: sender === undefined ? [] : [sender],
+        }
+      );
+    }
+
+    /**
+     * Moves a data point account of an earlier program version out of the way,
+     * `restoreDatapoint` recreates it with the current layout
+     * @param datapointId The data point id
+     * @param sender The manager
+     */
+    public async migrateDatapoint(datapointId: Buffer, sender: anchor.web3.Keypair) {
+      return await this.program.rpc.migrateDatapoint(
+        datapointId,
+        {
+          accounts: await this.migrationAccounts(datapointId, sender),
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Recreates a data point account moved by `migrateDatapoint`
+     * @param datapointId The data point id
+     * @param sender The manager
+     */
+    public async restoreDatapoint(datapointId: Buffer, sender: anchor.web3.Keypair) {
+      return await this.program.rpc.restoreDatapoint(
+        datapointId,
+        {
+          accounts: await this.migrationAccounts(datapointId, sender),
+          signers: [sender],
+        }
+      );
+    }
+
+    private async migrationAccounts(datapointId: Buffer, sender: anchor.web3.Keypair) {
+      const [migration] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("migration"), datapointId],
+        this.program.programId
+      );
+      return {
+        datapoint: await deriveDatapointPDA(datapointId, this.program.programId),
+        migration,
+        config: await deriveConfigPDA(this.program.programId),
+        user: sender.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      };
+    }
+
+    public async grantRole(role: Buffer, who: anchor.web3.PublicKey, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const rolePDA = await deriveRolePDA(role, who, this.program.programId);
+      return await this.program.rpc.grantRole(
+        role,
+        who,
+        {
+          accounts: {
+            roleMembership: rolePDA,
+            config: configPDA,
+            user: sender.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    public async revokeRole(role: Buffer, who: anchor.web3.PublicKey, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const rolePDA = await deriveRolePDA(role, who, this.program.programId);
+      return await this.program.rpc.revokeRole(
+        role,
+        who,
+        {
+          accounts: {
+            roleMembership: rolePDA,
+            config: configPDA,
+            user: sender.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Lists the accounts that currently have `role`
+     * @param role The role to check
+     */
+    public async roleMembers(role: Buffer): Promise<anchor.web3.PublicKey[]> {
+      // 8 bytes of account discriminator, then the role
+      const memberships = await this.program.account.wrappedRoleMembership.all([
+        { memcmp: { offset: 8, bytes: anchor.utils.bytes.bs58.encode(role) } }
+      ]);
+      return memberships.filter(m => m.account.granted).map(m => m.account.who);
+    }
+
+    /**
+     * Lists the roles `who` currently has
+     * @param who The account to check
+     */
+    public async rolesOf(who: anchor.web3.PublicKey): Promise<Buffer[]> {
+      // 8 bytes of account discriminator and 32 bytes of role, then the account
+      const memberships = await this.program.account.wrappedRoleMembership.all([
+        { memcmp: { offset: 40, bytes: who.toBase58() } }
+      ]);
+      return memberships.filter(m => m.account.granted).map(m => Buffer.from(m.account.role));
+    }
+
+    /**
+     * Proposes a new manager, the transfer completes once it calls `acceptManager`
+     * @param newManager The proposed manager
+     * @param sender The current manager
+     */
+    public async proposeManager(newManager: anchor.web3.PublicKey, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.proposeManager(
+        newManager,
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Completes the manager transfer
+     * @param sender The pending manager
+     */
+    public async acceptManager(sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.acceptManager(
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Moves `who` from a role of the previous manager to the same role of the current manager
+     * @param description The role description, e.g. "Pauser"
+     * @param previousRole The role derived from the previous manager
+     * @param role The role derived from the current manager
+     * @param who The role member
+     * @param sender The current manager
+     */
+    public async migrateRoleMember(
+      description: string,
+      previousRole: Buffer,
+      role: Buffer,
+      who: anchor.web3.PublicKey,
+      sender: anchor.web3.Keypair
+    ) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const previousRolePDA = await deriveRolePDA(previousRole, who, this.program.programId);
+      const rolePDA = await deriveRolePDA(role, who, this.program.programId);
+      return await this.program.rpc.migrateRoleMember(
+        description,
+        previousRole,
+        role,
+        who,
+        {
+          accounts: {
+            previousRoleMembership: previousRolePDA,
+            roleMembership: rolePDA,
+            config: configPDA,
+            user: sender.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Sets the pause state of the program
+     * @param state 0 is unpaused, 1 blocks updates and 2 blocks updates and reads
+     * @param sender The manager or a pauser
+     * @param pauserRole The pauser role, only needed if the sender is not the manager
+     */
+    public async setPauseState(state: number, sender: anchor.web3.Keypair, pauserRole?: Buffer) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.setPauseState(
+        state,
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          remainingAccounts: await this.pauserRoleAccounts(sender.publicKey, pauserRole),
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Sets the pause state of the data feed
+     * @param datapointId The data feed id
+     * @param state 0 is unpaused, 1 blocks updates and 2 blocks updates and reads
+     * @param sender The manager or a pauser
+     * @param pauserRole The pauser role, only needed if the sender is not the manager
+     */
+    public async setDataFeedPauseState(datapointId: Buffer, state: number, sender: anchor.web3.Keypair, pauserRole?: Buffer) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const datapointPDA = await deriveDatapointPDA(datapointId, this.program.programId);
+      return await this.program.rpc.setDataFeedPauseState(
+        datapointId,
+        state,
+        {
+          accounts: {
+            datapoint: datapointPDA,
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          remainingAccounts: await this.pauserRoleAccounts(sender.publicKey, pauserRole),
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Sets the deviation that pauses a Beacon, 100_000_000 is 100% and zero disables the circuit breaker
+     * @param threshold The deviation threshold
+     * @param sender The manager
+     */
+    public async setCircuitBreakerThreshold(threshold: number, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.setCircuitBreakerThreshold(
+        threshold,
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Stores the update held by the circuit breaker and resumes the Beacon
+     * @param datapointId The Beacon id
+     * @param sender The manager
+     */
+    public async releaseDataFeed(datapointId: Buffer, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const datapointPDA = await deriveDatapointPDA(datapointId, this.program.programId);
+      return await this.program.rpc.releaseDataFeed(
+        datapointId,
+        {
+          accounts: {
+            datapoint: datapointPDA,
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Sets the subscription price, a zero period disables subscriptions
+     * @param amountPerPeriod Lamports charged per period
+     * @param period Length of a period in seconds
+     * @param sender The manager
+     */
+    public async setSubscriptionPrice(amountPerPeriod: number, period: number, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.setSubscriptionPrice(
+        new anchor.BN(amountPerPeriod),
+        new anchor.BN(period),
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Returns the subscription price and the lamports not yet withdrawn by the manager
+     */
+    public async subscriptionPrice(): Promise<{ amountPerPeriod: number, period: number, balance: number }> {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const config = await this.program.account.wrappedConfig.fetch(configPDA);
+      return {
+        amountPerPeriod: config.subscriptionAmountPerPeriod.toNumber(),
+        period: config.subscriptionPeriod.toNumber(),
+        balance: config.subscriptionBalance.toNumber(),
+      };
+    }
+
+    /**
+     * Pays for `periods` periods of the subscription price to read the data feed
+     * @param dataFeedId The data feed id
+     * @param periods Number of periods to subscribe for
+     * @param sender The reader
+     */
+    public async subscribe(dataFeedId: Buffer, periods: number, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      const whitelistPDA = await deriveWhitelistPDA(dataFeedId, sender.publicKey, this.program.programId);
+      return await this.program.rpc.subscribe(
+        dataFeedId,
+        new anchor.BN(periods),
+        {
+          accounts: {
+            whitelistStatus: whitelistPDA,
+            config: configPDA,
+            user: sender.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    /**
+     * Returns the timestamp in seconds the subscription of `reader` expires at, 0 if never subscribed
+     * @param dataFeedId The data feed id
+     * @param reader The reader
+     */
+    public async subscriptionExpiration(dataFeedId: Buffer, reader: anchor.web3.PublicKey): Promise<number> {
+      const whitelistPDA = await deriveWhitelistPDA(dataFeedId, reader, this.program.programId);
+      const status = await this.program.account.wrappedWhitelistStatus.fetchNullable(whitelistPDA);
+      return status === null ? 0 : status.expirationTimestamp.toNumber();
+    }
+
+    /**
+     * Withdraws subscription payments to the manager
+     * @param amount Lamports to withdraw
+     * @param sender The manager
+     */
+    public async withdrawSubscriptionBalance(amount: number, sender: anchor.web3.Keypair) {
+      const configPDA = await deriveConfigPDA(this.program.programId);
+      return await this.program.rpc.withdrawSubscriptionBalance(
+        new anchor.BN(amount),
+        {
+          accounts: {
+            config: configPDA,
+            user: sender.publicKey,
+          },
+          signers: [sender],
+        }
+      );
+    }
+
+    private async pauserRoleAccounts(sender: anchor.web3.PublicKey, pauserRole?: Buffer) {
+      if (pauserRole === undefined) {
+        return [];
+      }
+      const rolePDA = await deriveRolePDA(pauserRole, sender, this.program.programId);
+      return [{ isSigner: false, isWritable: false, pubkey: rolePDA }];
+    }
 }
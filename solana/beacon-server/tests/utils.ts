@@ -111,6 +111,40 @@ export async function deriveNameHashPDA(nameHash: Buffer, programId: anchor.web3
     return pda;
 }
 
+export async function deriveConfigPDA(programId: anchor.web3.PublicKey): Promise<anchor.web3.PublicKey> {
+    const [pda] = await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("config"))
+        ],
+        programId
+    );
+    return pda;
+}
+
+export async function deriveRolePDA(role: Buffer, who: anchor.web3.PublicKey, programId: anchor.web3.PublicKey): Promise<anchor.web3.PublicKey> {
+    const [pda] = await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("role")),
+          role,
+          who.toBuffer()
+        ],
+        programId
+    );
+    return pda;
+}
+
+export async function deriveWhitelistPDA(dataFeedId: Buffer, reader: anchor.web3.PublicKey, programId: anchor.web3.PublicKey): Promise<anchor.web3.PublicKey> {
+    const [pda] = await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("whitelist")),
+          dataFeedId,
+          reader.toBuffer()
+        ],
+        programId
+    );
+    return pda;
+}
+
 export function median(values: number[]): number {
     if(values.length ===0) throw new Error("No inputs");
   
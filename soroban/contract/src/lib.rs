@@ -0,0 +1,921 @@
+#![no_std]
+
+extern crate alloc;
+
+mod types;
+mod utils;
+mod whitelist;
+
+#[cfg(test)]
+mod test;
+
+use crate::types::{Account, DataKey, SorobanFeedStatus, SorobanWhitelistStatus};
+use crate::utils::{
+    bytes32, contract_error, instance, msg_sender, persistent, soroban_fail, to_vec,
+    update_dapi_with_host_verified_signatures, verify_signed_data, DatapointStorage,
+    FeedStatusStorage, NameHashStorage, SorobanAccessControlRegistry,
+};
+use crate::whitelist::SorobanWhitelist;
+use alloc::vec::Vec as AllocVec;
+use api3_common::abi::{Int, Token, Uint};
+use api3_common::{
+    indefinite_whitelist_status_key, keccak_packed, process_beacon_update_with_circuit_breaker,
+    whitelist_status_key, AccessControlRegistry, Bytes32, Error, FeedStatus, PauseState,
+    StaticRole, Timestamp, Whitelist, WhitelistRolesWithManager, Zero,
+};
+use soroban_sdk::{contract, contractimpl, Address, Bytes, BytesN, Env, String, Vec};
+
+#[contract]
+pub struct DapiServer;
+
+#[contractimpl]
+impl DapiServer {
+    /// Initializes the contract with `manager`, who gets the default admin role.
+    /// Can only be called once.
+    pub fn initialize(env: Env, manager: Address) -> Result<(), soroban_sdk::Error> {
+        manager.require_auth();
+        if instance(&env).has(&DataKey::Manager) {
+            return Err(contract_error(Error::AlreadyInitialized));
+        }
+        instance(&env).set(&DataKey::Manager, &manager);
+        let manager = Account::new(&manager);
+        let mut access = SorobanAccessControlRegistry::new(&env, manager.clone());
+        access
+            .grant_role(&SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE, &manager)
+            .map_err(contract_error)
+    }
+
+    // ================== Access Control ====================
+    /// Returns the unlimited reader role and the name setter role
+    pub fn roles(env: Env) -> (BytesN<32>, BytesN<32>) {
+        let access = Self::access(&env);
+        (
+            bytes32(
+                &env,
+                &access.find_static_role(StaticRole::UnlimitedReaderRole),
+            ),
+            bytes32(&env, &access.find_static_role(StaticRole::NameSetterRole)),
+        )
+    }
+
+    /// Renounce `role` to `who`, `caller` must be `who`
+    pub fn renounce_role(
+        env: Env,
+        caller: Address,
+        role: BytesN<32>,
+        who: Address,
+    ) -> Result<(), soroban_sdk::Error> {
+        let mut access = Self::access(&env).with_sender(msg_sender(&caller));
+        access
+            .renounce_role(&role.to_array(), &Account::new(&who))
+            .map_err(contract_error)
+    }
+
+    /// Revoke `role` to `who`
+    pub fn revoke_role(
+        env: Env,
+        caller: Address,
+        role: BytesN<32>,
+        who: Address,
+    ) -> Result<(), soroban_sdk::Error> {
+        let mut access = Self::access(&env);
+        let role = role.to_array();
+        let role_admin = access
+            .get_role_admin(&role)
+            .unwrap_or(SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE);
+        access
+            .only_role(&role_admin, &msg_sender(&caller))
+            .map_err(|_| contract_error(Error::NotAuthorized))?;
+        access
+            .revoke_role(&role, &Account::new(&who))
+            .map_err(contract_error)
+    }
+
+    /// Grants `role` to `who`
+    pub fn grant_role(
+        env: Env,
+        caller: Address,
+        role: BytesN<32>,
+        who: Address,
+    ) -> Result<(), soroban_sdk::Error> {
+        let mut access = Self::access(&env);
+        access
+            .only_role(
+                &SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &msg_sender(&caller),
+            )
+            .map_err(|_| contract_error(Error::NotAuthorized))?;
+        access
+            .grant_role(&role.to_array(), &Account::new(&who))
+            .map_err(contract_error)
+    }
+
+    /// Checks if `who` has `role`
+    pub fn has_role(env: Env, role: BytesN<32>, who: Address) -> bool {
+        Self::access(&env).has_role(&role.to_array(), &Account::new(&who))
+    }
+
+    // ================== Manager Transfer ====================
+    /// Returns the manager of the contract
+    pub fn manager(env: Env) -> Address {
+        Self::manager_address(&env)
+    }
+
+    /// Returns the manager proposed with `propose_manager` that has not
+    /// accepted yet
+    pub fn pending_manager(env: Env) -> Option<Address> {
+        instance(&env).get(&DataKey::PendingManager)
+    }
+
+    /// Returns the manager before the last transfer
+    pub fn previous_manager(env: Env) -> Option<Address> {
+        instance(&env).get(&DataKey::PreviousManager)
+    }
+
+    /// Proposes `new_manager` as the manager of the contract. Only the
+    /// manager can propose and the transfer is completed once `new_manager`
+    /// calls `accept_manager`.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager
+    /// * `new_manager` The account proposed as the new manager
+    pub fn propose_manager(
+        env: Env,
+        caller: Address,
+        new_manager: Address,
+    ) -> Result<(), soroban_sdk::Error> {
+        api3_common::propose_manager(
+            &Account::new(&Self::manager_address(&env)),
+            Account::new(&new_manager),
+            &msg_sender(&caller),
+        )
+        .map_err(contract_error)?;
+        instance(&env).set(&DataKey::PendingManager, &new_manager);
+        Ok(())
+    }
+
+    /// Called by the pending manager to complete the transfer. The default
+    /// admin role is moved from the previous manager to the new one.
+    pub fn accept_manager(env: Env, caller: Address) -> Result<(), soroban_sdk::Error> {
+        let pending_manager: Option<Address> = instance(&env).get(&DataKey::PendingManager);
+        let new_manager = api3_common::accept_manager(
+            pending_manager.as_ref().map(Account::new),
+            &msg_sender(&caller),
+        )
+        .map_err(contract_error)?;
+        let previous_manager = Self::manager_address(&env);
+
+        let storage = instance(&env);
+        storage.set(&DataKey::Manager, &caller);
+        storage.set(&DataKey::PreviousManager, &previous_manager);
+        storage.remove(&DataKey::PendingManager);
+
+        let mut access = SorobanAccessControlRegistry::new(&env, new_manager.clone());
+        access
+            .initialize_manager(&new_manager)
+            .map_err(contract_error)?;
+        access
+            .grant_role(
+                &SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &new_manager,
+            )
+            .map_err(contract_error)?;
+        access
+            .revoke_role(
+                &SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &Account::new(&previous_manager),
+            )
+            .map_err(contract_error)
+    }
+
+    /// Moves `members` of the role with `description` derived from the previous
+    /// manager to the same role derived from the current manager. Only the
+    /// manager can migrate roles. Returns the migrated role.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager
+    /// * `description` Description of the role, e.g. `Pauser`
+    /// * `members` Accounts to migrate, non-members of the previous role are skipped
+    pub fn migrate_role_members(
+        env: Env,
+        caller: Address,
+        description: String,
+        members: Vec<Address>,
+    ) -> Result<BytesN<32>, soroban_sdk::Error> {
+        let previous_manager = Self::previous_manager(env.clone())
+            .map(|m| Account::new(&m))
+            .ok_or_else(|| contract_error(Error::PreviousManagerNotFound))?;
+        let mut description_bytes = alloc::vec![0u8; description.len() as usize];
+        description.copy_into_slice(&mut description_bytes);
+        let description = alloc::string::String::from_utf8(description_bytes)
+            .map_err(|_| contract_error(Error::InvalidData))?;
+        let members = members
+            .iter()
+            .map(|m| Account::new(&m))
+            .collect::<AllocVec<_>>();
+        let role = Self::access(&env)
+            .migrate_role_members(
+                &previous_manager,
+                description,
+                &members,
+                &msg_sender(&caller),
+            )
+            .map_err(contract_error)?;
+        Ok(bytes32(&env, &role))
+    }
+
+    // ================== Pause ====================
+    /// Returns the pauser role
+    pub fn pauser_role(env: Env) -> BytesN<32> {
+        bytes32(
+            &env,
+            &Self::access(&env).find_static_role(StaticRole::PauserRole),
+        )
+    }
+
+    /// Returns the pause state of the contract. `0` is unpaused, `1` blocks
+    /// updates and `2` blocks both updates and reads
+    pub fn pause_state(env: Env) -> u32 {
+        instance(&env)
+            .get(&DataKey::PauseState)
+            .unwrap_or_else(|| u8::from(PauseState::Unpaused).into())
+    }
+
+    /// Returns the pause state of the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pause_state(env: Env, data_feed_id: BytesN<32>) -> u32 {
+        persistent(&env)
+            .get(&DataKey::DataFeedPauseState(data_feed_id))
+            .unwrap_or_else(|| u8::from(PauseState::Unpaused).into())
+    }
+
+    /// Sets the pause state of the contract if the caller is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or a pauser
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_pause_state(
+        env: Env,
+        caller: Address,
+        state: u32,
+    ) -> Result<(), soroban_sdk::Error> {
+        let state = Self::parse_pause_state(state)?;
+        Self::ensure_can_pause(&env, &caller)?;
+        instance(&env).set(&DataKey::PauseState, &u32::from(u8::from(state)));
+        Ok(())
+    }
+
+    /// Sets the pause state of the data feed if the caller is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or a pauser
+    /// * `data_feed_id` The data feed ID
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_data_feed_pause_state(
+        env: Env,
+        caller: Address,
+        data_feed_id: BytesN<32>,
+        state: u32,
+    ) -> Result<(), soroban_sdk::Error> {
+        let state = Self::parse_pause_state(state)?;
+        Self::ensure_can_pause(&env, &caller)?;
+        persistent(&env).set(
+            &DataKey::DataFeedPauseState(data_feed_id),
+            &u32::from(u8::from(state)),
+        );
+        Ok(())
+    }
+
+    // ================== Datapoint ====================
+    /// Updates a Beacon using data signed by the respective Airnode,
+    /// without requiring a request or subscription
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode ed25519 public key
+    /// * `template_id` Template ID
+    /// * `timestamp` Timestamp used in the signature
+    /// * `data` Response data (an `int256` encoded in contract ABI)
+    /// * `signature` Template ID, a timestamp and the response data signed by the Airnode key
+    pub fn update_beacon_with_signed_data(
+        env: Env,
+        airnode: BytesN<32>,
+        template_id: BytesN<32>,
+        timestamp: BytesN<32>,
+        data: Bytes,
+        signature: BytesN<64>,
+    ) -> Result<(), soroban_sdk::Error> {
+        let template_id = template_id.to_array();
+        let timestamp = timestamp.to_array();
+        let data = to_vec(&data);
+        let beacon_id = api3_common::derive_beacon_id(airnode.to_array().to_vec(), template_id)
+            .map_err(contract_error)?;
+        Self::ensure_updates_allowed(&env, &beacon_id)?;
+        verify_signed_data(&env, &airnode, &template_id, &timestamp, &data, &signature);
+
+        process_beacon_update_with_circuit_breaker(
+            &mut DatapointStorage::new(&env),
+            &mut FeedStatusStorage::new(&env),
+            Self::circuit_breaker_threshold(env.clone()),
+            beacon_id,
+            Uint::from_big_endian(&timestamp),
+            data,
+        )
+        .map_err(contract_error)?;
+        Ok(())
+    }
+
+    /// Updates the dAPI that is specified by the beacon IDs
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    pub fn update_dapi_with_beacons(
+        env: Env,
+        beacon_ids: Vec<BytesN<32>>,
+    ) -> Result<BytesN<32>, soroban_sdk::Error> {
+        let beacon_ids = beacon_ids
+            .iter()
+            .map(|b| b.to_array())
+            .collect::<AllocVec<_>>();
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        Self::ensure_dapi_updates_allowed(&env, &dapi_id, &beacon_ids)?;
+        let dapi_id =
+            api3_common::update_dapi_with_beacons(&mut DatapointStorage::new(&env), &beacon_ids)
+                .map_err(|e| contract_error(e.error))?;
+        Ok(bytes32(&env, &dapi_id))
+    }
+
+    /// Updates a dAPI using data signed by the respective Airnodes
+    /// without requiring a request or subscription. The beacons for which the
+    /// signature is omitted will be read from the storage. Fails if a Beacon is
+    /// paused by the circuit breaker or its signed data exceeds the threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `airnodes` Airnode ed25519 public keys
+    /// * `template_ids` Template IDs
+    /// * `timestamps` Timestamps used in the signatures
+    /// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+    /// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode key per Beacon, empty to read the Beacon from storage
+    pub fn update_dapi_with_signed_data(
+        env: Env,
+        airnodes: Vec<BytesN<32>>,
+        template_ids: Vec<BytesN<32>>,
+        timestamps: Vec<BytesN<32>>,
+        data: Vec<Bytes>,
+        signatures: Vec<Bytes>,
+    ) -> Result<BytesN<32>, soroban_sdk::Error> {
+        let template_ids = template_ids
+            .iter()
+            .map(|t| t.to_array())
+            .collect::<AllocVec<_>>();
+        let timestamps = timestamps
+            .iter()
+            .map(|t| t.to_array())
+            .collect::<AllocVec<_>>();
+        let data = data.iter().map(|d| to_vec(&d)).collect::<AllocVec<_>>();
+        let signatures = signatures
+            .iter()
+            .map(|s| to_vec(&s))
+            .collect::<AllocVec<_>>();
+        api3_common::ensure!(
+            airnodes.len() as usize == template_ids.len(),
+            Error::ParameterLengthMismatch
+        )
+        .map_err(contract_error)?;
+
+        let beacon_ids = airnodes
+            .iter()
+            .zip(template_ids.iter())
+            .map(|(airnode, template_id)| {
+                api3_common::derive_beacon_id(airnode.to_array().to_vec(), *template_id)
+            })
+            .collect::<Result<AllocVec<_>, _>>()
+            .map_err(contract_error)?;
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        Self::ensure_dapi_updates_allowed(&env, &dapi_id, &beacon_ids)?;
+
+        let dapi_id = update_dapi_with_host_verified_signatures(
+            &env,
+            Self::circuit_breaker_threshold(env.clone()),
+            &airnodes,
+            template_ids,
+            timestamps,
+            data,
+            signatures,
+        )
+        .map_err(|e| contract_error(e.error))?;
+        Ok(bytes32(&env, &dapi_id))
+    }
+
+    // ================== Circuit Breaker ====================
+    /// Sets the maximum deviation a Beacon update can have from the current value
+    /// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` An account with the default admin role
+    /// * `threshold` The deviation threshold
+    pub fn set_circuit_breaker_threshold(
+        env: Env,
+        caller: Address,
+        threshold: u32,
+    ) -> Result<(), soroban_sdk::Error> {
+        Self::access(&env)
+            .only_role(
+                &SorobanAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &msg_sender(&caller),
+            )
+            .map_err(|_| contract_error(Error::NotAuthorized))?;
+        instance(&env).set(&DataKey::CircuitBreakerThreshold, &threshold);
+        Ok(())
+    }
+
+    /// Returns the circuit breaker deviation threshold
+    pub fn circuit_breaker_threshold(env: Env) -> u32 {
+        instance(&env)
+            .get(&DataKey::CircuitBreakerThreshold)
+            .unwrap_or(0)
+    }
+
+    /// Returns the pending update of the data feed if it is paused by the circuit breaker
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pending_update(
+        env: Env,
+        data_feed_id: BytesN<32>,
+    ) -> Option<(BytesN<32>, u32)> {
+        let status: Option<SorobanFeedStatus> =
+            persistent(&env).get(&DataKey::FeedStatus(data_feed_id));
+        match status.map(FeedStatus::from) {
+            Some(FeedStatus::Paused(d)) => {
+                Some(Self::value_and_timestamp(&env, d.value, d.timestamp))
+            }
+            _ => None,
+        }
+    }
+
+    /// Releases the data feed paused by the circuit breaker, the pending
+    /// update is stored as the data feed value
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or an account with the admin role
+    /// * `data_feed_id` The data feed ID
+    pub fn release_data_feed(
+        env: Env,
+        caller: Address,
+        data_feed_id: BytesN<32>,
+    ) -> Result<(), soroban_sdk::Error> {
+        api3_common::release_data_feed(
+            &mut DatapointStorage::new(&env),
+            &mut FeedStatusStorage::new(&env),
+            data_feed_id.to_array(),
+            &msg_sender(&caller),
+            &Self::access(&env),
+        )
+        .map_err(contract_error)
+    }
+
+    /// Sets the data point ID the name points to.
+    /// While a data point ID refers to a specific Beacon or dAPI, names
+    /// provide a more abstract interface for convenience. This means a name
+    /// that was pointing at a Beacon can be pointed to a dAPI, then another
+    /// dAPI, etc.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` An account with the name setter role
+    /// * `name` Human-readable name
+    /// * `datapoint_id` Data point ID the name will point to
+    pub fn set_name(
+        env: Env,
+        caller: Address,
+        name: BytesN<32>,
+        datapoint_id: BytesN<32>,
+    ) -> Result<(), soroban_sdk::Error> {
+        api3_common::set_name(
+            name.to_array(),
+            datapoint_id.to_array(),
+            &msg_sender(&caller),
+            &Self::access(&env),
+            &mut NameHashStorage::new(&env),
+        )
+        .map_err(contract_error)
+    }
+
+    /// Returns the data point ID the name is set to
+    /// `name` Name
+    pub fn name_to_data_point_id(env: Env, name: BytesN<32>) -> Option<BytesN<32>> {
+        let name_hash = keccak_packed(&[Token::FixedBytes(name.to_array().to_vec())]);
+        persistent(&env).get(&DataKey::NameHash(bytes32(&env, &name_hash)))
+    }
+
+    /// Derives the beacon set ID from the beacon IDs
+    /// Notice that `encode()` is used over `encode_packed()`
+    /// Returns the derived dapi id
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    pub fn derive_beacon_set_id(env: Env, beacon_ids: Vec<BytesN<32>>) -> BytesN<32> {
+        let beacon_ids = beacon_ids
+            .iter()
+            .map(|b| b.to_array())
+            .collect::<AllocVec<_>>();
+        bytes32(&env, &api3_common::derive_dapi_id(&beacon_ids))
+    }
+
+    /// Derives the beacon id based on the `airnode` and `templated_id`
+    /// Returns the beacon id
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode ed25519 public key
+    /// * `template_id` Template ID
+    pub fn derive_beacon_id(
+        env: Env,
+        airnode: BytesN<32>,
+        template_id: BytesN<32>,
+    ) -> Result<BytesN<32>, soroban_sdk::Error> {
+        let beacon_id =
+            api3_common::derive_beacon_id(airnode.to_array().to_vec(), template_id.to_array())
+                .map_err(contract_error)?;
+        Ok(bytes32(&env, &beacon_id))
+    }
+
+    /// Reads the data point with ID
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` The reader, who must authorize the read
+    /// * `data_point_id` Data point ID
+    pub fn read_with_data_point_id(
+        env: Env,
+        reader: Address,
+        data_point_id: BytesN<32>,
+    ) -> Result<(BytesN<32>, u32), soroban_sdk::Error> {
+        let data_point_id = data_point_id.to_array();
+        Self::ensure_reads_allowed(&env, &data_point_id)?;
+        let reader = msg_sender(&reader);
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, reader.clone());
+        let (value, timestamp) = api3_common::read_with_data_point_id(
+            &data_point_id,
+            &reader,
+            &DatapointStorage::new(&env),
+            &FeedStatusStorage::new(&env),
+            &access,
+            &whitelist,
+        )
+        .map_err(contract_error)?;
+        Ok(Self::value_and_timestamp(&env, value, timestamp))
+    }
+
+    /// Reads the data point with name
+    /// The read data point may belong to a Beacon or dAPI. The reader
+    /// must be whitelisted for the hash of the data point name.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` The reader, who must authorize the read
+    /// * `name` Data point name
+    pub fn read_with_name(
+        env: Env,
+        reader: Address,
+        name: BytesN<32>,
+    ) -> Result<(BytesN<32>, u32), soroban_sdk::Error> {
+        let data_point_id = Self::name_to_data_point_id(env.clone(), name.clone())
+            .map(|d| d.to_array())
+            .unwrap_or_default();
+        Self::ensure_reads_allowed(&env, &data_point_id)?;
+        let reader = msg_sender(&reader);
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, reader.clone());
+        let (value, timestamp) = api3_common::read_with_name(
+            name.to_array(),
+            &reader,
+            &DatapointStorage::new(&env),
+            &NameHashStorage::new(&env),
+            &FeedStatusStorage::new(&env),
+            &access,
+            &whitelist,
+        )
+        .map_err(contract_error)?;
+        Ok(Self::value_and_timestamp(&env, value, timestamp))
+    }
+
+    /// Returns if a reader can read the data point
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID (or data point name hash)
+    /// * `reader` Reader address
+    pub fn reader_can_read_data_point(
+        env: Env,
+        data_point_id: BytesN<32>,
+        reader: Address,
+    ) -> bool {
+        let reader = Account::new(&reader);
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, reader.clone());
+        api3_common::reader_can_read_data_point(
+            &data_point_id.to_array(),
+            &reader,
+            &access,
+            &whitelist,
+        )
+    }
+
+    // ================== Whitelist ====================
+    /// Returns the detailed whitelist status of the reader for the data feed,
+    /// the expiration timestamp and the indefinite whitelist count
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed id
+    /// * `reader` Reader address
+    pub fn data_feed_id_to_whitelist_status(
+        env: Env,
+        data_feed_id: BytesN<32>,
+        reader: Address,
+    ) -> Option<(u64, BytesN<32>)> {
+        let key = whitelist_status_key(&data_feed_id.to_array(), Account::new(&reader).as_ref());
+        let status: Option<SorobanWhitelistStatus> =
+            persistent(&env).get(&DataKey::WhitelistStatus(bytes32(&env, &key)));
+        status.map(|s| (s.expiration_timestamp, s.indefinite_whitelist_count))
+    }
+
+    /// Returns if `setter` has indefinitely whitelisted the reader for the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed id
+    /// * `reader` Reader address
+    /// * `setter` Setter address
+    pub fn indefinite_whitelist_status(
+        env: Env,
+        data_feed_id: BytesN<32>,
+        reader: Address,
+        setter: Address,
+    ) -> bool {
+        let key = indefinite_whitelist_status_key(
+            &data_feed_id.to_array(),
+            Account::new(&reader).as_ref(),
+            Account::new(&setter).as_ref(),
+        );
+        persistent(&env)
+            .get(&DataKey::IndefiniteWhitelistStatus(bytes32(&env, &key)))
+            .unwrap_or(false)
+    }
+
+    /// Extends the expiration of the temporary whitelist of `user` to
+    /// be able to use the service with `service_id` if the caller has the
+    /// whitelist expiration extender role
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or a whitelist expiration extender
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    pub fn extend_whitelist_expiration(
+        env: Env,
+        caller: Address,
+        service_id: BytesN<32>,
+        user: Address,
+        expiration_timestamp: u64,
+    ) -> Result<(), soroban_sdk::Error> {
+        let (service_id, user) = (service_id.to_array(), Account::new(&user));
+        let sender = msg_sender(&caller);
+        let access = Self::access(&env);
+        let mut whitelist = SorobanWhitelist::new(&env, &access, sender.clone());
+        api3_common::ensure!(
+            whitelist.has_whitelist_expiration_extender_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(contract_error)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        whitelist
+            .try_extend_whitelist_expiration(
+                &service_id,
+                &user,
+                Timestamp::from_secs(expiration_timestamp),
+            )
+            .map_err(contract_error)
+    }
+
+    /// Sets the expiration of the temporary whitelist of `user` to be
+    /// able to use the service with `service_id` if the caller has the
+    /// whitelist expiration setter role
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or a whitelist expiration setter
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    pub fn set_whitelist_expiration(
+        env: Env,
+        caller: Address,
+        service_id: BytesN<32>,
+        user: Address,
+        expiration_timestamp: u64,
+    ) -> Result<(), soroban_sdk::Error> {
+        let (service_id, user) = (service_id.to_array(), Account::new(&user));
+        let sender = msg_sender(&caller);
+        let access = Self::access(&env);
+        let mut whitelist = SorobanWhitelist::new(&env, &access, sender.clone());
+        api3_common::ensure!(
+            whitelist.has_whitelist_expiration_setter_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(contract_error)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        whitelist.set_whitelist_expiration(
+            &service_id,
+            &user,
+            Timestamp::from_secs(expiration_timestamp),
+        );
+        Ok(())
+    }
+
+    /// Sets the indefinite whitelist status of `user` to be able to
+    /// use the service with `service_id` if the caller has the indefinite whitelister role.
+    /// Returns the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` The manager or an indefinite whitelister
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `status` Indefinite whitelist status
+    pub fn set_indefinite_whitelist_status(
+        env: Env,
+        caller: Address,
+        service_id: BytesN<32>,
+        user: Address,
+        status: bool,
+    ) -> Result<BytesN<32>, soroban_sdk::Error> {
+        let (service_id, user) = (service_id.to_array(), Account::new(&user));
+        let sender = msg_sender(&caller);
+        let access = Self::access(&env);
+        let mut whitelist = SorobanWhitelist::new(&env, &access, sender.clone());
+        api3_common::ensure!(
+            whitelist.has_indefinite_whitelister_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(contract_error)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        let count = whitelist
+            .set_indefinite_whitelist_status(&service_id, &user, status)
+            .map_err(contract_error)?;
+        Ok(bytes32(&env, &Bytes32::from(count)))
+    }
+
+    /// Revokes the indefinite whitelist status granted to the user for
+    /// the service by a specific account. Anyone can revoke the status set by
+    /// an account that no longer has the indefinite whitelister role.
+    /// Returns if the status was revoked and the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `setter` Setter address
+    pub fn revoke_indefinite_whitelist(
+        env: Env,
+        service_id: BytesN<32>,
+        user: Address,
+        setter: Address,
+    ) -> Result<(bool, BytesN<32>), soroban_sdk::Error> {
+        let setter = Account::new(&setter);
+        let access = Self::access(&env);
+        let mut whitelist = SorobanWhitelist::new(&env, &access, setter.clone());
+        api3_common::ensure!(
+            !whitelist.has_indefinite_whitelister_role_or_is_manager(&setter),
+            Error::SetterCanSetIndefiniteStatus
+        )
+        .map_err(contract_error)?;
+        let (revoked, count) = whitelist
+            .revoke_indefinite_whitelist_status(
+                &service_id.to_array(),
+                &Account::new(&user),
+                &setter,
+            )
+            .map_err(contract_error)?;
+        Ok((revoked, bytes32(&env, &Bytes32::from(count))))
+    }
+
+    /// Returns the whitelist expiration extender role
+    pub fn whitelist_extender_role(env: Env) -> BytesN<32> {
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, Account::default());
+        bytes32(&env, &whitelist.whitelist_expiration_extender_role())
+    }
+
+    /// Returns the whitelist expiration setter role
+    pub fn whitelist_expiration_setter_role(env: Env) -> BytesN<32> {
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, Account::default());
+        bytes32(&env, &whitelist.whitelist_expiration_setter_role())
+    }
+
+    /// Returns the indefinite whitelister role
+    pub fn indefinite_whitelister_role(env: Env) -> BytesN<32> {
+        let access = Self::access(&env);
+        let whitelist = SorobanWhitelist::new(&env, &access, Account::default());
+        bytes32(&env, &whitelist.indefinite_whitelister_role())
+    }
+
+    /// Returns the admin role description, which derives the admin role of the manager
+    pub fn admin_role_description(env: Env) -> String {
+        String::from_str(&env, SorobanAccessControlRegistry::ADMIN_ROLE_DESCRIPTION)
+    }
+}
+
+impl DapiServer {
+    fn manager_address(env: &Env) -> Address {
+        instance(env)
+            .get(&DataKey::Manager)
+            .unwrap_or_else(|| soroban_fail(env, Error::NotInitialized))
+    }
+
+    fn access(env: &Env) -> SorobanAccessControlRegistry<'_> {
+        SorobanAccessControlRegistry::new(env, Account::new(&Self::manager_address(env)))
+    }
+
+    fn value_and_timestamp(env: &Env, value: Int, timestamp: Timestamp) -> (BytesN<32>, u32) {
+        let mut v = [0u8; 32];
+        value.to_big_endian(&mut v);
+        (bytes32(env, &v), timestamp.as_secs_u32())
+    }
+
+    fn ensure_whitelist_ids(
+        service_id: &Bytes32,
+        user: &Account,
+    ) -> Result<(), soroban_sdk::Error> {
+        api3_common::ensure!(!service_id.is_zero(), Error::ServiceIdZero)
+            .map_err(contract_error)?;
+        api3_common::ensure!(!user.is_zero(), Error::UserAddressZero).map_err(contract_error)
+    }
+
+    fn parse_pause_state(state: u32) -> Result<PauseState, soroban_sdk::Error> {
+        u8::try_from(state)
+            .map_err(|_| Error::InvalidPauseState)
+            .and_then(PauseState::try_from)
+            .map_err(contract_error)
+    }
+
+    fn ensure_can_pause(env: &Env, caller: &Address) -> Result<(), soroban_sdk::Error> {
+        api3_common::ensure_can_pause(&Self::access(env), &msg_sender(caller))
+            .map_err(contract_error)
+    }
+
+    fn pause_states(
+        env: &Env,
+        data_feed_id: &Bytes32,
+    ) -> Result<(PauseState, PauseState), soroban_sdk::Error> {
+        let global = Self::parse_pause_state(Self::pause_state(env.clone()))?;
+        let data_feed = Self::parse_pause_state(Self::data_feed_pause_state(
+            env.clone(),
+            bytes32(env, data_feed_id),
+        ))?;
+        Ok((global, data_feed))
+    }
+
+    fn ensure_updates_allowed(env: &Env, data_feed_id: &Bytes32) -> Result<(), soroban_sdk::Error> {
+        let (global, data_feed) = Self::pause_states(env, data_feed_id)?;
+        api3_common::ensure_updates_allowed(global, data_feed).map_err(contract_error)
+    }
+
+    /// Checks updates are allowed for the dAPI and each of its Beacons
+    fn ensure_dapi_updates_allowed(
+        env: &Env,
+        dapi_id: &Bytes32,
+        beacon_ids: &[Bytes32],
+    ) -> Result<(), soroban_sdk::Error> {
+        Self::ensure_updates_allowed(env, dapi_id)?;
+        for beacon_id in beacon_ids {
+            Self::ensure_updates_allowed(env, beacon_id)?;
+        }
+        Ok(())
+    }
+
+    fn ensure_reads_allowed(env: &Env, data_feed_id: &Bytes32) -> Result<(), soroban_sdk::Error> {
+        let (global, data_feed) = Self::pause_states(env, data_feed_id)?;
+        api3_common::ensure_reads_allowed(global, data_feed).map_err(contract_error)
+    }
+}
@@ -0,0 +1,451 @@
+extern crate std;
+
+use crate::types::DataKey;
+use crate::utils::{contract_error, BUMP_AMOUNT, DAY_IN_LEDGERS};
+use crate::{DapiServer, DapiServerClient};
+use api3_common::abi::{encode, Int, Token, Uint};
+use api3_common::{keccak_packed, Bytes32, Error};
+use ed25519_dalek::{Signer, SigningKey};
+use soroban_sdk::testutils::storage::{Instance as _, Persistent as _};
+use soroban_sdk::testutils::{Address as _, Ledger, MockAuth, MockAuthInvoke};
+use soroban_sdk::IntoVal;
+use soroban_sdk::{vec, Address, Bytes, BytesN, Env, String};
+use std::vec::Vec;
+
+const TEMPLATE_ID: Bytes32 = [1u8; 32];
+const NOW: u64 = 1_650_000_000;
+
+fn airnode_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+fn airnode(env: &Env, key: &SigningKey) -> BytesN<32> {
+    BytesN::from_array(env, &key.verifying_key().to_bytes())
+}
+
+/// Signs `value` at `timestamp` the way Airnode does, returns the
+/// timestamp, data and signature arguments of the update functions
+fn sign(
+    env: &Env,
+    key: &SigningKey,
+    timestamp: u64,
+    value: u128,
+) -> (BytesN<32>, Bytes, BytesN<64>) {
+    let mut ts = [0u8; 32];
+    Uint::from(timestamp).to_big_endian(&mut ts);
+    let data = encode(&[Token::Int(Int::from(value))]);
+    let message = keccak_packed(&[
+        Token::FixedBytes(TEMPLATE_ID.to_vec()),
+        Token::Uint(Uint::from(timestamp)),
+        Token::Bytes(data.clone()),
+    ]);
+    let signature = key.sign(&message).to_bytes();
+    (
+        BytesN::from_array(env, &ts),
+        Bytes::from_slice(env, &data),
+        BytesN::from_array(env, &signature),
+    )
+}
+
+fn value(env: &Env, v: u128) -> BytesN<32> {
+    let mut value = [0u8; 32];
+    Int::from(v).to_big_endian(&mut value);
+    BytesN::from_array(env, &value)
+}
+
+fn deploy(env: &Env) -> (DapiServerClient<'_>, Address) {
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.timestamp = NOW);
+    let client = DapiServerClient::new(env, &env.register_contract(None, DapiServer));
+    let manager = Address::generate(env);
+    client.initialize(&manager);
+    (client, manager)
+}
+
+#[test]
+fn initializes_once() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    assert_eq!(client.manager(), manager);
+    assert!(client.has_role(&BytesN::from_array(&env, &[0u8; 32]), &manager));
+    assert_eq!(
+        client.try_initialize(&Address::generate(&env)),
+        Err(Ok(contract_error(Error::AlreadyInitialized)))
+    );
+
+    let uninitialized = DapiServerClient::new(&env, &env.register_contract(None, DapiServer));
+    assert_eq!(
+        uninitialized.try_pauser_role(),
+        Err(Ok(contract_error(Error::NotInitialized)))
+    );
+}
+
+#[test]
+fn updates_beacon_with_signed_data() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let key = airnode_key(1);
+    let template_id = BytesN::from_array(&env, &TEMPLATE_ID);
+    let beacon_id = client.derive_beacon_id(&airnode(&env, &key), &template_id);
+
+    let (ts, data, signature) = sign(&env, &key, NOW - 10, 123);
+    client.update_beacon_with_signed_data(
+        &airnode(&env, &key),
+        &template_id,
+        &ts,
+        &data,
+        &signature,
+    );
+
+    // a signature of another Airnode traps in the host
+    let (ts, data, signature) = sign(&env, &airnode_key(2), NOW - 5, 456);
+    assert!(client
+        .try_update_beacon_with_signed_data(
+            &airnode(&env, &key),
+            &template_id,
+            &ts,
+            &data,
+            &signature
+        )
+        .is_err());
+
+    // the manager is not whitelisted, but can grant itself the unlimited reader role
+    assert_eq!(
+        client.try_read_with_data_point_id(&manager, &beacon_id),
+        Err(Ok(contract_error(Error::AccessDenied)))
+    );
+    let (unlimited_reader, _) = client.roles();
+    client.grant_role(&manager, &unlimited_reader, &manager);
+    assert_eq!(
+        client.read_with_data_point_id(&manager, &beacon_id),
+        (value(&env, 123), (NOW - 10) as u32)
+    );
+}
+
+#[test]
+fn initialize_requires_manager_auth() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, DapiServer);
+    let client = DapiServerClient::new(&env, &contract_id);
+    let manager = Address::generate(&env);
+
+    // anyone could otherwise front-run the deployment and make themselves the manager
+    assert!(client.try_initialize(&manager).is_err());
+    assert_eq!(
+        client.try_pauser_role(),
+        Err(Ok(contract_error(Error::NotInitialized)))
+    );
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &manager,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "initialize",
+                args: (&manager,).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .initialize(&manager);
+    assert_eq!(client.manager(), manager);
+}
+
+/// The arguments of `update_dapi_with_signed_data` for the signed data of each key
+#[allow(clippy::type_complexity)]
+fn dapi_update(
+    env: &Env,
+    signed: &[(&SigningKey, (BytesN<32>, Bytes, BytesN<64>))],
+) -> (
+    soroban_sdk::Vec<BytesN<32>>,
+    soroban_sdk::Vec<BytesN<32>>,
+    soroban_sdk::Vec<BytesN<32>>,
+    soroban_sdk::Vec<Bytes>,
+    soroban_sdk::Vec<Bytes>,
+) {
+    let mut airnodes = vec![env];
+    let mut template_ids = vec![env];
+    let mut timestamps = vec![env];
+    let mut data = vec![env];
+    let mut signatures = vec![env];
+    for (key, (ts, d, signature)) in signed {
+        airnodes.push_back(airnode(env, key));
+        template_ids.push_back(BytesN::from_array(env, &TEMPLATE_ID));
+        timestamps.push_back(ts.clone());
+        data.push_back(d.clone());
+        signatures.push_back(Bytes::from_array(env, &signature.to_array()));
+    }
+    (airnodes, template_ids, timestamps, data, signatures)
+}
+
+#[test]
+fn host_traps_on_invalid_dapi_signatures() {
+    let env = Env::default();
+    let (client, _) = deploy(&env);
+    let keys = [airnode_key(1), airnode_key(2)];
+    let signed = [
+        (&keys[0], sign(&env, &keys[0], NOW - 10, 100)),
+        (&keys[1], sign(&env, &keys[1], NOW - 10, 200)),
+    ];
+
+    // the second Beacon is signed by another key, ed25519_verify traps
+    // instead of returning false, so the whole invocation fails
+    let (airnodes, template_ids, timestamps, data, mut signatures) = dapi_update(&env, &signed);
+    let (_, _, forged) = sign(&env, &airnode_key(3), NOW - 10, 200);
+    signatures.set(1, Bytes::from_array(&env, &forged.to_array()));
+    assert!(client
+        .try_update_dapi_with_signed_data(&airnodes, &template_ids, &timestamps, &data, &signatures)
+        .is_err());
+
+    // a signature that is not 64 bytes never reaches the host
+    let mut truncated = signatures.get(0).unwrap();
+    truncated.pop_back();
+    signatures.set(1, truncated);
+    assert_eq!(
+        client.try_update_dapi_with_signed_data(
+            &airnodes,
+            &template_ids,
+            &timestamps,
+            &data,
+            &signatures
+        ),
+        Err(Ok(contract_error(Error::InvalidSignature)))
+    );
+
+    // nothing was stored by the failed updates
+    let beacon_id =
+        client.derive_beacon_id(&airnodes.get(0).unwrap(), &template_ids.get(0).unwrap());
+    let (unlimited_reader, _) = client.roles();
+    let reader = Address::generate(&env);
+    let manager = client.manager();
+    client.grant_role(&manager, &unlimited_reader, &reader);
+    assert_eq!(
+        client.read_with_data_point_id(&reader, &beacon_id),
+        (value(&env, 0), 0)
+    );
+
+    // beacons with an empty signature are read from storage, the rest
+    // is verified by the host
+    let (airnodes, template_ids, timestamps, data, mut signatures) = dapi_update(&env, &signed);
+    client.update_beacon_with_signed_data(
+        &airnodes.get(1).unwrap(),
+        &template_ids.get(1).unwrap(),
+        &timestamps.get(1).unwrap(),
+        &data.get(1).unwrap(),
+        &signed[1].1 .2,
+    );
+    signatures.set(1, Bytes::new(&env));
+    let dapi_id = client.update_dapi_with_signed_data(
+        &airnodes,
+        &template_ids,
+        &timestamps,
+        &data,
+        &signatures,
+    );
+    assert_eq!(
+        client.read_with_data_point_id(&reader, &dapi_id),
+        (value(&env, 150), (NOW - 10) as u32)
+    );
+}
+
+#[test]
+fn storage_ttl_is_extended_on_access() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let key = airnode_key(1);
+    let template_id = BytesN::from_array(&env, &TEMPLATE_ID);
+    let beacon_id = client.derive_beacon_id(&airnode(&env, &key), &template_id);
+    let (ts, data, signature) = sign(&env, &key, NOW - 10, 123);
+    client.update_beacon_with_signed_data(
+        &airnode(&env, &key),
+        &template_id,
+        &ts,
+        &data,
+        &signature,
+    );
+
+    let ttls = || {
+        env.as_contract(&client.address, || {
+            (
+                env.storage().instance().get_ttl(),
+                env.storage()
+                    .persistent()
+                    .get_ttl(&DataKey::DataPoint(beacon_id.clone())),
+            )
+        })
+    };
+    assert_eq!(ttls(), (BUMP_AMOUNT, BUMP_AMOUNT));
+
+    // entries are only extended once their TTL drops below the threshold
+    env.ledger()
+        .with_mut(|l| l.sequence_number += DAY_IN_LEDGERS / 2);
+    client.manager();
+    assert_eq!(
+        ttls(),
+        (
+            BUMP_AMOUNT - DAY_IN_LEDGERS / 2,
+            BUMP_AMOUNT - DAY_IN_LEDGERS / 2
+        )
+    );
+
+    // reading the Beacon keeps it and the instance alive
+    env.ledger()
+        .with_mut(|l| l.sequence_number += 2 * DAY_IN_LEDGERS);
+    let (unlimited_reader, _) = client.roles();
+    client.grant_role(&manager, &unlimited_reader, &manager);
+    assert_eq!(
+        client.read_with_data_point_id(&manager, &beacon_id),
+        (value(&env, 123), (NOW - 10) as u32)
+    );
+    assert_eq!(ttls(), (BUMP_AMOUNT, BUMP_AMOUNT));
+
+    // a contract left untouched for longer than its TTL is archived
+    // and cannot be called until it is restored
+    env.ledger()
+        .with_mut(|l| l.sequence_number += BUMP_AMOUNT + 1);
+    assert!(
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| client.try_manager())).is_err()
+    );
+}
+
+#[test]
+fn updates_dapi_with_signed_data() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let reader = Address::generate(&env);
+    let keys = [airnode_key(1), airnode_key(2), airnode_key(3)];
+    let signed = keys
+        .iter()
+        .zip([100u128, 200, 400])
+        .map(|(k, v)| sign(&env, k, NOW - 10, v))
+        .collect::<Vec<_>>();
+
+    let mut airnodes = vec![&env];
+    let mut template_ids = vec![&env];
+    let mut timestamps = vec![&env];
+    let mut data = vec![&env];
+    let mut signatures = vec![&env];
+    for (key, (ts, d, signature)) in keys.iter().zip(signed) {
+        airnodes.push_back(airnode(&env, key));
+        template_ids.push_back(BytesN::from_array(&env, &TEMPLATE_ID));
+        timestamps.push_back(ts);
+        data.push_back(d);
+        signatures.push_back(Bytes::from_array(&env, &signature.to_array()));
+    }
+    let dapi_id = client.update_dapi_with_signed_data(
+        &airnodes,
+        &template_ids,
+        &timestamps,
+        &data,
+        &signatures,
+    );
+
+    client.set_whitelist_expiration(&manager, &dapi_id, &reader, &(NOW + 60));
+    assert_eq!(
+        client.read_with_data_point_id(&reader, &dapi_id),
+        (value(&env, 200), (NOW - 10) as u32)
+    );
+
+    // the temporary whitelist expires
+    env.ledger().with_mut(|l| l.timestamp = NOW + 60);
+    assert!(!client.reader_can_read_data_point(&dapi_id, &reader));
+}
+
+#[test]
+fn names_and_whitelists() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let (bob, charlie) = (Address::generate(&env), Address::generate(&env));
+    let name = BytesN::from_array(&env, &[7u8; 32]);
+    let data_point_id = BytesN::from_array(&env, &[8u8; 32]);
+    let name_hash = BytesN::from_array(
+        &env,
+        &keccak_packed(&[Token::FixedBytes(name.to_array().to_vec())]),
+    );
+
+    assert_eq!(
+        client.try_set_name(&manager, &name, &data_point_id),
+        Err(Ok(contract_error(Error::AccessDenied)))
+    );
+    let (_, name_setter) = client.roles();
+    client.grant_role(&manager, &name_setter, &manager);
+    client.set_name(&manager, &name, &data_point_id);
+    assert_eq!(client.name_to_data_point_id(&name), Some(data_point_id));
+
+    // only the manager and indefinite whitelisters can whitelist indefinitely
+    assert_eq!(
+        client.try_set_indefinite_whitelist_status(&bob, &name_hash, &charlie, &true),
+        Err(Ok(contract_error(Error::AccessDenied)))
+    );
+    client.set_indefinite_whitelist_status(&manager, &name_hash, &charlie, &true);
+    assert!(client.indefinite_whitelist_status(&name_hash, &charlie, &manager));
+    assert_eq!(client.read_with_name(&charlie, &name), (value(&env, 0), 0));
+
+    // the manager can always set indefinite whitelist statuses, so they cannot be revoked
+    assert_eq!(
+        client.try_revoke_indefinite_whitelist(&name_hash, &charlie, &manager),
+        Err(Ok(contract_error(Error::SetterCanSetIndefiniteStatus)))
+    );
+}
+
+#[test]
+fn pause_blocks_updates_and_reads() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let bob = Address::generate(&env);
+    let beacon_ids = vec![
+        &env,
+        BytesN::from_array(&env, &[1u8; 32]),
+        BytesN::from_array(&env, &[2u8; 32]),
+    ];
+    let dapi_id = client.derive_beacon_set_id(&beacon_ids);
+
+    assert!(client.try_set_pause_state(&bob, &1).is_err());
+    assert_eq!(
+        client.try_set_pause_state(&manager, &3),
+        Err(Ok(contract_error(Error::InvalidPauseState)))
+    );
+    client.set_data_feed_pause_state(&manager, &dapi_id, &2);
+    assert!(client.try_update_dapi_with_beacons(&beacon_ids).is_err());
+    assert!(client
+        .try_read_with_data_point_id(&manager, &dapi_id)
+        .is_err());
+
+    client.set_data_feed_pause_state(&manager, &dapi_id, &0);
+    assert_eq!(client.update_dapi_with_beacons(&beacon_ids), dapi_id);
+}
+
+#[test]
+fn manager_transfer_moves_admin_role() {
+    let env = Env::default();
+    let (client, manager) = deploy(&env);
+    let (bob, pauser) = (Address::generate(&env), Address::generate(&env));
+    let default_admin_role = BytesN::from_array(&env, &[0u8; 32]);
+    let pauser_role = client.pauser_role();
+    client.grant_role(&manager, &pauser_role, &pauser);
+
+    client.propose_manager(&manager, &bob);
+    assert_eq!(client.pending_manager(), Some(bob.clone()));
+    assert_eq!(
+        client.try_accept_manager(&manager),
+        Err(Ok(contract_error(Error::NotAuthorized)))
+    );
+
+    client.accept_manager(&bob);
+    assert_eq!(client.manager(), bob);
+    assert_eq!(client.previous_manager(), Some(manager.clone()));
+    assert!(client.has_role(&default_admin_role, &bob));
+    assert!(!client.has_role(&default_admin_role, &manager));
+
+    // the roles are derived from the new manager, members are migrated by it
+    let members = vec![&env, pauser.clone()];
+    let description = String::from_str(&env, "Pauser");
+    assert_eq!(
+        client.try_migrate_role_members(&manager, &description, &members),
+        Err(Ok(contract_error(Error::NotAuthorized)))
+    );
+    let role = client.migrate_role_members(&bob, &description, &members);
+    assert_eq!(role, client.pauser_role());
+    assert_ne!(role, pauser_role);
+    assert!(client.has_role(&role, &pauser));
+    assert!(!client.has_role(&pauser_role, &pauser));
+}
@@ -0,0 +1,135 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use api3_common::abi::{Int, U256};
+use api3_common::{Bytes32, DataPoint, FeedStatus, Timestamp, WhitelistStatus, Zero};
+use soroban_sdk::{contracttype, BytesN, Env};
+
+/// The strkey of a Soroban address, e.g. `G...` for accounts and `C...` for
+/// contracts, as the address type of `api3_common`
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Account(pub Vec<u8>);
+
+impl Account {
+    pub fn new(address: &soroban_sdk::Address) -> Self {
+        let strkey = address.to_string();
+        let mut bytes = vec![0u8; strkey.len() as usize];
+        strkey.copy_into_slice(&mut bytes);
+        Self(bytes)
+    }
+}
+
+impl Zero for Account {
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl AsRef<[u8]> for Account {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Storage keys, the instance storage holds the contract wide settings and
+/// the persistent storage the entries keyed by ID or hash
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKey {
+    Manager,
+    PendingManager,
+    PreviousManager,
+    PauseState,
+    CircuitBreakerThreshold,
+    DataPoint(BytesN<32>),
+    NameHash(BytesN<32>),
+    FeedStatus(BytesN<32>),
+    DataFeedPauseState(BytesN<32>),
+    RoleMembership(BytesN<32>),
+    RoleAdmin(BytesN<32>),
+    WhitelistStatus(BytesN<32>),
+    IndefiniteWhitelistStatus(BytesN<32>),
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct SorobanDataPoint {
+    pub value: BytesN<32>,
+    pub timestamp: u32,
+}
+
+impl SorobanDataPoint {
+    pub fn new(env: &Env, t: DataPoint) -> Self {
+        let mut value = [0u8; 32];
+        t.value.to_big_endian(&mut value);
+        SorobanDataPoint {
+            value: BytesN::from_array(env, &value),
+            timestamp: t.timestamp.as_secs_u32(),
+        }
+    }
+}
+
+impl From<SorobanDataPoint> for DataPoint {
+    fn from(t: SorobanDataPoint) -> Self {
+        DataPoint::new(
+            Int::from_big_endian(&t.value.to_array()),
+            Timestamp::from_secs(u64::from(t.timestamp)),
+        )
+    }
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub enum SorobanFeedStatus {
+    Active,
+    Paused(SorobanDataPoint),
+}
+
+impl SorobanFeedStatus {
+    pub fn new(env: &Env, t: FeedStatus) -> Self {
+        match t {
+            FeedStatus::Active => SorobanFeedStatus::Active,
+            FeedStatus::Paused(d) => SorobanFeedStatus::Paused(SorobanDataPoint::new(env, d)),
+        }
+    }
+}
+
+impl From<SorobanFeedStatus> for FeedStatus {
+    fn from(t: SorobanFeedStatus) -> Self {
+        match t {
+            SorobanFeedStatus::Active => FeedStatus::Active,
+            SorobanFeedStatus::Paused(d) => FeedStatus::Paused(d.into()),
+        }
+    }
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct SorobanWhitelistStatus {
+    /// in seconds
+    pub expiration_timestamp: u64,
+    /// originally uint192, stored big endian
+    pub indefinite_whitelist_count: BytesN<32>,
+}
+
+impl SorobanWhitelistStatus {
+    pub fn new(env: &Env, s: WhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: s.expiration_timestamp.as_secs(),
+            indefinite_whitelist_count: BytesN::from_array(
+                env,
+                &Bytes32::from(&s.indefinite_whitelist_count),
+            ),
+        }
+    }
+}
+
+impl From<SorobanWhitelistStatus> for WhitelistStatus {
+    fn from(s: SorobanWhitelistStatus) -> Self {
+        Self {
+            expiration_timestamp: Timestamp::from_secs(s.expiration_timestamp),
+            indefinite_whitelist_count: U256::from_big_endian(
+                &s.indefinite_whitelist_count.to_array(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use crate::types::{Account, DataKey, SorobanDataPoint, SorobanFeedStatus, SorobanWhitelistStatus};
+use alloc::string::String;
+use alloc::vec::Vec;
+use api3_common::abi::Token;
+use api3_common::{
+    keccak_packed, update_dapi_with_signed_data_with_circuit_breaker, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, Bytes32, DapiUpdateError, DataPoint, Error,
+    FeedStatus, RoleDeriver, Storage, Timestamp, TimestampChecker, WhitelistStatus,
+};
+use soroban_sdk::storage::Instance;
+use soroban_sdk::{panic_with_error, Bytes, BytesN, Env, IntoVal, TryFromVal, Val};
+
+/// Ledgers closed in a day at five seconds per ledger
+pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
+/// The TTL entries are extended to whenever they are read or written
+pub(crate) const BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+/// Entries are only extended once their TTL drops below this, so that
+/// consecutive accesses do not pay for an extension each
+pub(crate) const LIFETIME_THRESHOLD: u32 = BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// The `soroban_sdk::Error` of an `api3_common::Error`. It is a contract
+/// error with the code of the error, which clients can map back with `Error::from_code`.
+pub(crate) fn contract_error(e: Error) -> soroban_sdk::Error {
+    soroban_sdk::Error::from_contract_error(e.code())
+}
+
+/// Fails the call where the common traits leave no room for an error.
+/// Functions return `contract_error` otherwise.
+pub(crate) fn soroban_fail(env: &Env, e: Error) -> ! {
+    panic_with_error!(env, contract_error(e))
+}
+
+pub(crate) fn bytes32(env: &Env, k: &Bytes32) -> BytesN<32> {
+    BytesN::from_array(env, k)
+}
+
+pub(crate) fn to_vec(bytes: &Bytes) -> Vec<u8> {
+    bytes.to_alloc_vec()
+}
+
+/// The instance storage of the contract with its TTL extended. Soroban
+/// archives the contract instance, and with it the manager and the pause
+/// states, once its TTL runs out.
+pub(crate) fn instance(env: &Env) -> Instance {
+    let storage = env.storage().instance();
+    storage.extend_ttl(LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    storage
+}
+
+/// The persistent storage of the contract, extending the TTL of the
+/// entries it reads and writes so that data points, whitelists and roles
+/// in use are not archived
+pub(crate) struct Persistent<'a> {
+    env: &'a Env,
+}
+
+pub(crate) fn persistent(env: &Env) -> Persistent<'_> {
+    Persistent { env }
+}
+
+impl<'a> Persistent<'a> {
+    fn extend<K: IntoVal<Env, Val>>(&self, key: &K) {
+        self.env
+            .storage()
+            .persistent()
+            .extend_ttl(key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    }
+
+    pub fn has<K: IntoVal<Env, Val>>(&self, key: &K) -> bool {
+        let has = self.env.storage().persistent().has(key);
+        if has {
+            self.extend(key);
+        }
+        has
+    }
+
+    pub fn get<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(&self, key: &K) -> Option<V> {
+        let value = self.env.storage().persistent().get(key);
+        if value.is_some() {
+            self.extend(key);
+        }
+        value
+    }
+
+    pub fn set<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(&self, key: &K, value: &V) {
+        self.env.storage().persistent().set(key, value);
+        self.extend(key);
+    }
+
+    pub fn remove<K: IntoVal<Env, Val>>(&self, key: &K) {
+        self.env.storage().persistent().remove(key);
+    }
+}
+
+/// The utility struct for handling Soroban storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct DatapointStorage<'a> {
+    env: &'a Env,
+}
+
+impl<'a> DatapointStorage<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self { env }
+    }
+}
+
+impl<'a> Storage<DataPoint> for DatapointStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        let key = DataKey::DataPoint(bytes32(self.env, k));
+        match persistent(self.env).get::<_, SorobanDataPoint>(&key) {
+            Some(d) => Some(d.into()),
+            None => Some(DataPoint::default()),
+        }
+    }
+
+    fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
+        let key = DataKey::DataPoint(bytes32(self.env, &k));
+        persistent(self.env).set(&key, &SorobanDataPoint::new(self.env, datapoint));
+    }
+}
+
+/// The utility struct for handling Soroban storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct NameHashStorage<'a> {
+    env: &'a Env,
+}
+
+impl<'a> NameHashStorage<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self { env }
+    }
+}
+
+impl<'a> Storage<Bytes32> for NameHashStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<Bytes32> {
+        let key = DataKey::NameHash(bytes32(self.env, k));
+        persistent(self.env)
+            .get::<_, BytesN<32>>(&key)
+            .map(|v| v.to_array())
+    }
+
+    fn store(&mut self, k: Bytes32, data: Bytes32) {
+        let key = DataKey::NameHash(bytes32(self.env, &k));
+        persistent(self.env).set(&key, &bytes32(self.env, &data));
+    }
+}
+
+/// The utility struct for handling Soroban storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusStorage<'a> {
+    env: &'a Env,
+}
+
+impl<'a> FeedStatusStorage<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self { env }
+    }
+}
+
+impl<'a> Storage<FeedStatus> for FeedStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        let key = DataKey::FeedStatus(bytes32(self.env, k));
+        persistent(self.env)
+            .get::<_, SorobanFeedStatus>(&key)
+            .map(FeedStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        let key = DataKey::FeedStatus(bytes32(self.env, &k));
+        persistent(self.env).set(&key, &SorobanFeedStatus::new(self.env, status));
+    }
+}
+
+/// The utility struct for handling Soroban storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct WhitelistStatusStorage<'a> {
+    env: &'a Env,
+}
+
+impl<'a> WhitelistStatusStorage<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self { env }
+    }
+}
+
+impl<'a> Storage<WhitelistStatus> for WhitelistStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        let key = DataKey::WhitelistStatus(bytes32(self.env, k));
+        persistent(self.env)
+            .get::<_, SorobanWhitelistStatus>(&key)
+            .map(WhitelistStatus::from)
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        let key = DataKey::WhitelistStatus(bytes32(self.env, &k));
+        persistent(self.env).set(&key, &SorobanWhitelistStatus::new(self.env, status));
+    }
+}
+
+/// The utility struct for handling Soroban storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct IndefiniteWhitelistStatusStorage<'a> {
+    env: &'a Env,
+}
+
+impl<'a> IndefiniteWhitelistStatusStorage<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self { env }
+    }
+}
+
+impl<'a> Storage<bool> for IndefiniteWhitelistStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        let key = DataKey::IndefiniteWhitelistStatus(bytes32(self.env, k));
+        persistent(self.env).get(&key)
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        let key = DataKey::IndefiniteWhitelistStatus(bytes32(self.env, &k));
+        persistent(self.env).set(&key, &status);
+    }
+}
+
+/// The message the Airnode signs, the template ID, the timestamp and the data
+fn signed_data_message(template_id: &Bytes32, timestamp: &Bytes32, data: &[u8]) -> Bytes32 {
+    keccak_packed(&[
+        Token::FixedBytes(template_id.to_vec()),
+        Token::Uint(api3_common::abi::Uint::from_big_endian(timestamp)),
+        Token::Bytes(data.to_vec()),
+    ])
+}
+
+/// Verifies the signature of the Airnode over the template ID, the timestamp
+/// and the data with the host, trapping if it is not valid
+pub(crate) fn verify_signed_data(
+    env: &Env,
+    airnode: &BytesN<32>,
+    template_id: &Bytes32,
+    timestamp: &Bytes32,
+    data: &[u8],
+    signature: &BytesN<64>,
+) {
+    let message = signed_data_message(template_id, timestamp, data);
+    env.crypto()
+        .ed25519_verify(airnode, &Bytes::from_slice(env, &message), signature);
+}
+
+/// What a signature is replaced with once the host has verified it, binding
+/// it to the key and the message it was verified for
+fn host_verification_receipt(key: &[u8], message: &[u8]) -> Bytes32 {
+    keccak_packed(&[
+        Token::String(String::from("soroban host verified")),
+        Token::Bytes(key.to_vec()),
+        Token::Bytes(message.to_vec()),
+    ])
+}
+
+/// Soroban has no ed25519 verification that returns `false`, the host traps
+/// on invalid signatures. `update_dapi_with_host_verified_signatures` has the
+/// host verify the signatures and hands `api3_common` a receipt for each, so
+/// any signature that did not go through the host fails here.
+struct HostVerified;
+
+impl api3_common::SignatureManger for HostVerified {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        signature == host_verification_receipt(key, message)
+    }
+}
+
+/// Updates a dAPI with signed data, the host verifies every non-empty
+/// signature, trapping on the invalid ones, before the common processing.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_dapi_with_host_verified_signatures(
+    env: &Env,
+    deviation_threshold: u32,
+    airnodes: &soroban_sdk::Vec<BytesN<32>>,
+    template_ids: Vec<Bytes32>,
+    timestamps: Vec<Bytes32>,
+    data: Vec<Vec<u8>>,
+    signatures: Vec<Vec<u8>>,
+) -> Result<Bytes32, DapiUpdateError> {
+    let mut receipts = Vec::with_capacity(signatures.len());
+    for (ind, signature) in signatures.into_iter().enumerate() {
+        let (airnode, template_id, timestamp, data) = match (
+            airnodes.get(ind as u32),
+            template_ids.get(ind),
+            timestamps.get(ind),
+            data.get(ind),
+        ) {
+            (Some(a), Some(t), Some(ts), Some(d)) if !signature.is_empty() => (a, t, ts, d),
+            _ => {
+                receipts.push(signature);
+                continue;
+            }
+        };
+        let signature = <[u8; 64]>::try_from(signature.as_slice())
+            .map_err(|_| DapiUpdateError::at(ind)(Error::InvalidSignature))?;
+        verify_signed_data(
+            env,
+            &airnode,
+            template_id,
+            timestamp,
+            data,
+            &BytesN::from_array(env, &signature),
+        );
+        let message = signed_data_message(template_id, timestamp, data);
+        receipts.push(host_verification_receipt(&airnode.to_array(), &message).to_vec());
+    }
+
+    update_dapi_with_signed_data_with_circuit_breaker::<_, _, HostVerified, _>(
+        &mut DatapointStorage::new(env),
+        &FeedStatusStorage::new(env),
+        deviation_threshold,
+        &SorobanClock::now(env),
+        airnodes.iter().map(|a| a.to_array().to_vec()).collect(),
+        template_ids,
+        timestamps,
+        data,
+        receipts,
+    )
+}
+
+pub(crate) struct SorobanClock {
+    current_timestamp: Timestamp,
+}
+
+impl SorobanClock {
+    pub fn new(current_timestamp: Timestamp) -> Self {
+        Self { current_timestamp }
+    }
+
+    /// The clock at the current ledger, Soroban reports the close time in seconds
+    pub fn now(env: &Env) -> Self {
+        Self::new(Timestamp::from_secs(env.ledger().timestamp()))
+    }
+}
+
+impl TimestampChecker for SorobanClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
+/// Soroban has no implicit sender, the caller is passed in and must authorize the call
+pub(crate) fn msg_sender(caller: &soroban_sdk::Address) -> Account {
+    caller.require_auth();
+    Account::new(caller)
+}
+
+pub(crate) struct SorobanAccessControlRegistry<'a> {
+    env: &'a Env,
+    manager: Account,
+    admin_role_description: String,
+    sender: Option<Account>,
+}
+
+impl<'a> SorobanAccessControlRegistry<'a> {
+    pub const ADMIN_ROLE_DESCRIPTION: &'static str = "admin role";
+
+    pub fn new(env: &'a Env, manager: Account) -> Self {
+        Self {
+            env,
+            manager,
+            admin_role_description: String::from(Self::ADMIN_ROLE_DESCRIPTION),
+            sender: None,
+        }
+    }
+
+    /// The registry for calls by `sender`, who can then renounce roles
+    pub fn with_sender(mut self, sender: Account) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    fn hash_membership(&self, role: &Bytes32, who: &Account) -> BytesN<32> {
+        bytes32(
+            self.env,
+            &keccak_packed(&[
+                Token::FixedBytes(role.to_vec()),
+                Token::FixedBytes(who.as_ref().to_vec()),
+            ]),
+        )
+    }
+}
+
+impl<'a> AccessControlRegistryAdminnedWithManager for SorobanAccessControlRegistry<'a> {
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        &self.manager
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.admin_role_description.clone()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        keccak_packed(&[Token::String(self.admin_role_description())])
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        RoleDeriver::derive_role(
+            RoleDeriver::derive_root_role(self.manager.as_ref()),
+            self.admin_role_description(),
+        )
+    }
+}
+
+impl<'a> AccessControlRegistry for SorobanAccessControlRegistry<'a> {
+    fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        let key = DataKey::RoleMembership(self.hash_membership(role, who));
+        persistent(self.env).has(&key)
+    }
+
+    fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+        let key = DataKey::RoleMembership(self.hash_membership(role, who));
+        persistent(self.env).set(&key, &true);
+        Ok(())
+    }
+
+    fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+        if *role == Self::DEFAULT_ADMIN_ROLE {
+            return Some(Self::DEFAULT_ADMIN_ROLE);
+        }
+        let key = DataKey::RoleAdmin(bytes32(self.env, role));
+        persistent(self.env)
+            .get::<_, BytesN<32>>(&key)
+            .map(|r| r.to_array())
+    }
+
+    fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+        let key = DataKey::RoleAdmin(bytes32(self.env, role));
+        persistent(self.env).set(&key, &bytes32(self.env, &role_admin));
+        Ok(())
+    }
+
+    fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        api3_common::ensure!(self.sender.as_ref() == Some(account), Error::NotAuthorized)?;
+        self.revoke_role(role, account)
+    }
+
+    fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        let key = DataKey::RoleMembership(self.hash_membership(role, account));
+        persistent(self.env).remove(&key);
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use crate::types::Account;
+use crate::utils::{
+    soroban_fail, IndefiniteWhitelistStatusStorage, SorobanClock, WhitelistStatusStorage,
+};
+use alloc::string::String;
+use api3_common::abi::U256;
+use api3_common::{
+    AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32, Error, Timestamp,
+    Whitelist, WhitelistRoles, WhitelistRolesWithManager,
+};
+use soroban_sdk::Env;
+
+pub(crate) struct SorobanWhitelist<'a, Access> {
+    env: &'a Env,
+    access: &'a Access,
+    /// The sender, who is the setter of indefinite whitelist statuses
+    sender: Account,
+    service_id_to_user_to_whitelist_status: WhitelistStatusStorage<'a>,
+    service_id_to_user_to_setter_to_indefinite_whitelist_status:
+        IndefiniteWhitelistStatusStorage<'a>,
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> SorobanWhitelist<'a, Access> {
+    pub fn new(env: &'a Env, access: &'a Access, sender: Account) -> Self {
+        Self {
+            env,
+            access,
+            sender,
+            service_id_to_user_to_whitelist_status: WhitelistStatusStorage::new(env),
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusStorage::new(env),
+        }
+    }
+
+    /// Same as `Whitelist::extend_whitelist_expiration`, but returns the error
+    /// so that functions can return it instead of failing the call
+    pub fn try_extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Account,
+        expiration_timestamp: Timestamp,
+    ) -> Result<(), Error> {
+        api3_common::extend_whitelist_expiration(
+            &mut self.service_id_to_user_to_whitelist_status,
+            service_id,
+            user.as_ref(),
+            expiration_timestamp,
+        )
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> Whitelist
+    for SorobanWhitelist<'a, Access>
+{
+    type Address = Account;
+
+    fn user_is_whitelisted(&self, service_id: &Bytes32, user: &Account) -> bool {
+        api3_common::user_is_whitelisted(
+            &self.service_id_to_user_to_whitelist_status,
+            &SorobanClock::now(self.env),
+            service_id,
+            user.as_ref(),
+        )
+    }
+
+    fn extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        if let Err(e) = self.try_extend_whitelist_expiration(service_id, user, expiration_timestamp)
+        {
+            soroban_fail(self.env, e)
+        }
+    }
+
+    fn set_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        api3_common::set_whitelist_expiration(
+            &mut self.service_id_to_user_to_whitelist_status,
+            service_id,
+            user.as_ref(),
+            expiration_timestamp,
+        )
+    }
+
+    fn set_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        status: bool,
+    ) -> Result<U256, Error> {
+        api3_common::set_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            user.as_ref(),
+            self.sender.as_ref(),
+            status,
+        )
+    }
+
+    fn revoke_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        setter: &Self::Address,
+    ) -> Result<(bool, U256), Error> {
+        api3_common::revoke_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            user.as_ref(),
+            setter.as_ref(),
+        )
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> WhitelistRoles
+    for SorobanWhitelist<'a, Access>
+{
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> AccessControlRegistryAdminnedWithManager
+    for SorobanWhitelist<'a, Access>
+{
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        self.access.manager()
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.access.admin_role_description()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        self.access.admin_role_description_hash()
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        self.access.admin_role()
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> WhitelistRolesWithManager
+    for SorobanWhitelist<'a, Access>
+{
+    fn has_whitelist_expiration_extender_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_extender_role(), account)
+    }
+
+    fn has_indefinite_whitelister_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.indefinite_whitelister_role(), account)
+    }
+
+    fn has_whitelist_expiration_setter_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_setter_role(), account)
+    }
+}
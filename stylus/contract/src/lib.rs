@@ -0,0 +1,978 @@
+#![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
+
+extern crate alloc;
+
+mod types;
+mod utils;
+mod whitelist;
+
+#[cfg(test)]
+mod test;
+
+use crate::types::{Account, StylusDataPoint, StylusFeedStatus, StylusWhitelistStatus};
+use crate::utils::{
+    ensure_int224, revert, storage_key, DatapointStorage, FeedStatusStorage, NameHashStorage,
+    SignatureVerify, StylusAccessControlRegistry, StylusClock,
+};
+use crate::whitelist::StylusWhitelist;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloy_primitives::aliases::{I224, U192, U224};
+use alloy_primitives::{Address, FixedBytes, U256, U32, U8};
+use api3_common::abi::{Int, Token, Uint};
+use api3_common::{
+    indefinite_whitelist_status_key, keccak_packed, process_beacon_update_with_circuit_breaker,
+    update_dapi_with_signed_data_with_circuit_breaker, whitelist_status_key, AccessControlRegistry,
+    Bytes32, Error, FeedStatus, PauseState, SignatureManger, StaticRole, Timestamp, Whitelist,
+    WhitelistRolesWithManager, Zero,
+};
+use stylus_sdk::abi::Bytes;
+use stylus_sdk::prelude::*;
+use stylus_sdk::storage::{
+    StorageAddress, StorageBool, StorageFixedBytes, StorageMap, StorageU32, StorageU8,
+};
+
+#[storage]
+#[entrypoint]
+pub struct DapiServer {
+    manager: StorageAddress,
+    pending_manager: StorageAddress,
+    previous_manager: StorageAddress,
+    pause_state: StorageU8,
+    data_feed_pause_states: StorageMap<FixedBytes<32>, StorageU8>,
+    circuit_breaker_threshold: StorageU32,
+    /// Data point IDs to the data points
+    data_points: StorageMap<FixedBytes<32>, StylusDataPoint>,
+    /// Data feed IDs to the circuit breaker statuses
+    feed_statuses: StorageMap<FixedBytes<32>, StylusFeedStatus>,
+    /// Name hashes to data point IDs
+    name_hashes: StorageMap<FixedBytes<32>, StorageFixedBytes<32>>,
+    whitelist_statuses: StorageMap<FixedBytes<32>, StylusWhitelistStatus>,
+    indefinite_whitelist_statuses: StorageMap<FixedBytes<32>, StorageBool>,
+    /// Hashes of the role and the member to the membership
+    role_members: StorageMap<FixedBytes<32>, StorageBool>,
+    role_admins: StorageMap<FixedBytes<32>, StorageFixedBytes<32>>,
+}
+
+/// The function names are converted to camel case, so the selectors are the
+/// ones of the Solidity DapiServer, e.g. `readWithDataPointId(bytes32)`
+#[public]
+impl DapiServer {
+    /// Deploys the contract with `manager`, who gets the default admin role
+    #[constructor]
+    pub fn constructor(&mut self, manager: Address) -> Result<(), Vec<u8>> {
+        let manager = Account(manager);
+        api3_common::ensure!(!manager.is_zero(), Error::InvalidAddress).map_err(revert)?;
+        self.manager.set(manager.0);
+        let mut access = StylusAccessControlRegistry::requires_write(
+            manager,
+            &mut self.role_members,
+            &mut self.role_admins,
+        );
+        access
+            .grant_role(&StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE, &manager)
+            .map_err(revert)
+    }
+
+    // ================== Access Control ====================
+    /// Returns the unlimited reader role
+    pub fn unlimited_reader_role(&self) -> FixedBytes<32> {
+        storage_key(
+            &self
+                .access()
+                .find_static_role(StaticRole::UnlimitedReaderRole),
+        )
+    }
+
+    /// Returns the name setter role
+    pub fn name_setter_role(&self) -> FixedBytes<32> {
+        storage_key(&self.access().find_static_role(StaticRole::NameSetterRole))
+    }
+
+    /// Renounce `role` to `who`, the sender must be `who`
+    pub fn renounce_role(&mut self, role: FixedBytes<32>, who: Address) -> Result<(), Vec<u8>> {
+        let sender = self.msg_sender();
+        let mut access = StylusAccessControlRegistry::requires_write(
+            self.manager_account(),
+            &mut self.role_members,
+            &mut self.role_admins,
+        )
+        .with_sender(sender);
+        access.renounce_role(&role.0, &Account(who)).map_err(revert)
+    }
+
+    /// Revoke `role` to `who`
+    pub fn revoke_role(&mut self, role: FixedBytes<32>, who: Address) -> Result<(), Vec<u8>> {
+        let sender = self.msg_sender();
+        let mut access = StylusAccessControlRegistry::requires_write(
+            self.manager_account(),
+            &mut self.role_members,
+            &mut self.role_admins,
+        );
+        let role_admin = access
+            .get_role_admin(&role.0)
+            .unwrap_or(StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE);
+        access
+            .only_role(&role_admin, &sender)
+            .map_err(|_| revert(Error::NotAuthorized))?;
+        access.revoke_role(&role.0, &Account(who)).map_err(revert)
+    }
+
+    /// Grants `role` to `who`
+    pub fn grant_role(&mut self, role: FixedBytes<32>, who: Address) -> Result<(), Vec<u8>> {
+        let sender = self.msg_sender();
+        let mut access = StylusAccessControlRegistry::requires_write(
+            self.manager_account(),
+            &mut self.role_members,
+            &mut self.role_admins,
+        );
+        access
+            .only_role(&StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE, &sender)
+            .map_err(|_| revert(Error::NotAuthorized))?;
+        access.grant_role(&role.0, &Account(who)).map_err(revert)
+    }
+
+    /// Checks if `who` has `role`
+    pub fn has_role(&self, role: FixedBytes<32>, who: Address) -> bool {
+        self.access().has_role(&role.0, &Account(who))
+    }
+
+    /// Returns the admin role of `role`
+    pub fn get_role_admin(&self, role: FixedBytes<32>) -> FixedBytes<32> {
+        storage_key(
+            &self
+                .access()
+                .get_role_admin(&role.0)
+                .unwrap_or(StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE),
+        )
+    }
+
+    // ================== Manager Transfer ====================
+    /// Returns the manager of the contract
+    pub fn manager(&self) -> Address {
+        self.manager.get()
+    }
+
+    /// Returns the manager proposed with `proposeManager` that has not
+    /// accepted yet, the zero address if there is none
+    pub fn pending_manager(&self) -> Address {
+        self.pending_manager.get()
+    }
+
+    /// Returns the manager before the last transfer, the zero address if
+    /// there is none
+    pub fn previous_manager(&self) -> Address {
+        self.previous_manager.get()
+    }
+
+    /// Proposes `new_manager` as the manager of the contract. Only the
+    /// manager can propose and the transfer is completed once `new_manager`
+    /// calls `acceptManager`.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_manager` The account proposed as the new manager
+    pub fn propose_manager(&mut self, new_manager: Address) -> Result<(), Vec<u8>> {
+        api3_common::propose_manager(
+            &self.manager_account(),
+            Account(new_manager),
+            &self.msg_sender(),
+        )
+        .map_err(revert)?;
+        self.pending_manager.set(new_manager);
+        Ok(())
+    }
+
+    /// Called by the pending manager to complete the transfer. The default
+    /// admin role is moved from the previous manager to the new one.
+    pub fn accept_manager(&mut self) -> Result<(), Vec<u8>> {
+        let pending_manager = Account(self.pending_manager.get());
+        let new_manager = api3_common::accept_manager(
+            (!pending_manager.is_zero()).then_some(pending_manager),
+            &self.msg_sender(),
+        )
+        .map_err(revert)?;
+        let previous_manager = self.manager_account();
+
+        self.manager.set(new_manager.0);
+        self.previous_manager.set(previous_manager.0);
+        self.pending_manager.set(Address::ZERO);
+
+        let mut access = StylusAccessControlRegistry::requires_write(
+            new_manager,
+            &mut self.role_members,
+            &mut self.role_admins,
+        );
+        access.initialize_manager(&new_manager).map_err(revert)?;
+        access
+            .grant_role(
+                &StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &new_manager,
+            )
+            .map_err(revert)?;
+        access
+            .revoke_role(
+                &StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &previous_manager,
+            )
+            .map_err(revert)
+    }
+
+    /// Moves `members` of the role with `description` derived from the previous
+    /// manager to the same role derived from the current manager. Only the
+    /// manager can migrate roles. Returns the migrated role.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` Description of the role, e.g. `Pauser`
+    /// * `members` Accounts to migrate, non-members of the previous role are skipped
+    pub fn migrate_role_members(
+        &mut self,
+        description: String,
+        members: Vec<Address>,
+    ) -> Result<FixedBytes<32>, Vec<u8>> {
+        let previous_manager = Account(self.previous_manager.get());
+        api3_common::ensure!(!previous_manager.is_zero(), Error::PreviousManagerNotFound)
+            .map_err(revert)?;
+        let sender = self.msg_sender();
+        let members = members.into_iter().map(Account).collect::<Vec<_>>();
+        let mut access = StylusAccessControlRegistry::requires_write(
+            self.manager_account(),
+            &mut self.role_members,
+            &mut self.role_admins,
+        );
+        let role = access
+            .migrate_role_members(&previous_manager, description, &members, &sender)
+            .map_err(revert)?;
+        Ok(storage_key(&role))
+    }
+
+    // ================== Pause ====================
+    /// Returns the pauser role
+    pub fn pauser_role(&self) -> FixedBytes<32> {
+        storage_key(&self.access().find_static_role(StaticRole::PauserRole))
+    }
+
+    /// Returns the pause state of the contract. `0` is unpaused, `1` blocks
+    /// updates and `2` blocks both updates and reads
+    pub fn pause_state(&self) -> u8 {
+        self.pause_state.get().to::<u8>()
+    }
+
+    /// Returns the pause state of the data feed
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pause_state(&self, data_feed_id: FixedBytes<32>) -> u8 {
+        self.data_feed_pause_states.get(data_feed_id).to::<u8>()
+    }
+
+    /// Sets the pause state of the contract if the sender is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_pause_state(&mut self, state: u8) -> Result<(), Vec<u8>> {
+        let state = PauseState::try_from(state).map_err(revert)?;
+        self.ensure_can_pause()?;
+        self.pause_state.set(U8::from(u8::from(state)));
+        Ok(())
+    }
+
+    /// Sets the pause state of the data feed if the sender is the manager or
+    /// has the pauser role
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    /// * `state` `0` is unpaused, `1` blocks updates and `2` blocks both updates and reads
+    pub fn set_data_feed_pause_state(
+        &mut self,
+        data_feed_id: FixedBytes<32>,
+        state: u8,
+    ) -> Result<(), Vec<u8>> {
+        let state = PauseState::try_from(state).map_err(revert)?;
+        self.ensure_can_pause()?;
+        self.data_feed_pause_states
+            .insert(data_feed_id, U8::from(u8::from(state)));
+        Ok(())
+    }
+
+    // ================== Datapoint ====================
+    /// Updates a Beacon using data signed by the respective Airnode,
+    /// without requiring a request or subscription
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode address
+    /// * `template_id` Template ID
+    /// * `timestamp` Timestamp used in the signature
+    /// * `data` Response data (an `int256` encoded in contract ABI)
+    /// * `signature` Template ID, a timestamp and the response data signed by the Airnode address
+    pub fn update_beacon_with_signed_data(
+        &mut self,
+        airnode: Address,
+        template_id: FixedBytes<32>,
+        timestamp: U256,
+        data: Bytes,
+        signature: Bytes,
+    ) -> Result<(), Vec<u8>> {
+        let timestamp = timestamp.to_be_bytes::<32>();
+        let beacon_id =
+            api3_common::derive_beacon_id(airnode.to_vec(), template_id.0).map_err(revert)?;
+        self.ensure_updates_allowed(&beacon_id)?;
+        Self::verify_signed_data(airnode, &template_id.0, &timestamp, &data, &signature)
+            .map_err(revert)?;
+        ensure_int224(&data).map_err(revert)?;
+
+        process_beacon_update_with_circuit_breaker(
+            &mut DatapointStorage::requires_write(&mut self.data_points),
+            &mut FeedStatusStorage::requires_write(&mut self.feed_statuses),
+            self.circuit_breaker_threshold.get().to::<u32>(),
+            beacon_id,
+            Uint::from_big_endian(&timestamp),
+            data.to_vec(),
+        )
+        .map_err(revert)?;
+        Ok(())
+    }
+
+    /// Updates the dAPI that is specified by the beacon IDs
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    pub fn update_dapi_with_beacons(
+        &mut self,
+        beacon_ids: Vec<FixedBytes<32>>,
+    ) -> Result<FixedBytes<32>, Vec<u8>> {
+        let beacon_ids = beacon_ids.iter().map(|b| b.0).collect::<Vec<_>>();
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids)?;
+        let dapi_id = api3_common::update_dapi_with_beacons(
+            &mut DatapointStorage::requires_write(&mut self.data_points),
+            &beacon_ids,
+        )
+        .map_err(|e| revert(e.error))?;
+        Ok(storage_key(&dapi_id))
+    }
+
+    /// Updates a dAPI using data signed by the respective Airnodes
+    /// without requiring a request or subscription. The beacons for which the
+    /// signature is omitted will be read from the storage. Fails if a Beacon is
+    /// paused by the circuit breaker or its signed data exceeds the threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `airnodes` Airnode addresses
+    /// * `template_ids` Template IDs
+    /// * `timestamps` Timestamps used in the signatures
+    /// * `data` Response data (an `int256` encoded in contract ABI per Beacon)
+    /// * `signatures` Template ID, a timestamp and the response data signed by the respective Airnode address per Beacon, empty to read the Beacon from storage
+    pub fn update_dapi_with_signed_data(
+        &mut self,
+        airnodes: Vec<Address>,
+        template_ids: Vec<FixedBytes<32>>,
+        timestamps: Vec<U256>,
+        data: Vec<Bytes>,
+        signatures: Vec<Bytes>,
+    ) -> Result<FixedBytes<32>, Vec<u8>> {
+        let template_ids = template_ids.iter().map(|t| t.0).collect::<Vec<_>>();
+        let timestamps = timestamps
+            .iter()
+            .map(|t| t.to_be_bytes::<32>())
+            .collect::<Vec<_>>();
+        let data = data.into_iter().map(|d| d.0).collect::<Vec<_>>();
+        let signatures = signatures.into_iter().map(|s| s.0).collect::<Vec<_>>();
+        api3_common::ensure!(
+            airnodes.len() == template_ids.len(),
+            Error::ParameterLengthMismatch
+        )
+        .map_err(revert)?;
+
+        let beacon_ids = airnodes
+            .iter()
+            .zip(template_ids.iter())
+            .map(|(airnode, template_id)| {
+                api3_common::derive_beacon_id(airnode.to_vec(), *template_id)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(revert)?;
+        let dapi_id = api3_common::derive_dapi_id(&beacon_ids);
+        self.ensure_dapi_updates_allowed(&dapi_id, &beacon_ids)?;
+
+        for (signature, data) in signatures.iter().zip(data.iter()) {
+            if !signature.is_empty() {
+                ensure_int224(data).map_err(revert)?;
+            }
+        }
+
+        let clock = self.clock();
+        let threshold = self.circuit_breaker_threshold();
+        let dapi_id =
+            update_dapi_with_signed_data_with_circuit_breaker::<_, _, SignatureVerify, _>(
+                &mut DatapointStorage::requires_write(&mut self.data_points),
+                &FeedStatusStorage::read_only(&self.feed_statuses),
+                threshold,
+                &clock,
+                airnodes.iter().map(|a| a.to_vec()).collect(),
+                template_ids,
+                timestamps,
+                data,
+                signatures,
+            )
+            .map_err(|e| revert(e.error))?;
+        Ok(storage_key(&dapi_id))
+    }
+
+    // ================== Circuit Breaker ====================
+    /// Sets the maximum deviation a Beacon update can have from the current value
+    /// before the feed is paused. `100_000_000` is 100%, zero disables the circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` The deviation threshold
+    pub fn set_circuit_breaker_threshold(&mut self, threshold: u32) -> Result<(), Vec<u8>> {
+        self.access()
+            .only_role(
+                &StylusAccessControlRegistry::DEFAULT_ADMIN_ROLE,
+                &self.msg_sender(),
+            )
+            .map_err(|_| revert(Error::NotAuthorized))?;
+        self.circuit_breaker_threshold.set(U32::from(threshold));
+        Ok(())
+    }
+
+    /// Returns the circuit breaker deviation threshold
+    pub fn circuit_breaker_threshold(&self) -> u32 {
+        self.circuit_breaker_threshold.get().to::<u32>()
+    }
+
+    /// Returns if the data feed is paused by the circuit breaker, and the
+    /// value and timestamp of the pending update if it is
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn data_feed_pending_update(&self, data_feed_id: FixedBytes<32>) -> (bool, I224, u32) {
+        match self.feed_statuses.getter(data_feed_id).get() {
+            FeedStatus::Paused(d) => {
+                let (value, timestamp) = Self::value_and_timestamp(d.value, d.timestamp);
+                (true, value, timestamp)
+            }
+            FeedStatus::Active => (false, I224::ZERO, 0),
+        }
+    }
+
+    /// Releases the data feed paused by the circuit breaker, the pending
+    /// update is stored as the data feed value
+    ///
+    /// # Arguments
+    ///
+    /// * `data_feed_id` The data feed ID
+    pub fn release_data_feed(&mut self, data_feed_id: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        let sender = self.msg_sender();
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        api3_common::release_data_feed(
+            &mut DatapointStorage::requires_write(&mut self.data_points),
+            &mut FeedStatusStorage::requires_write(&mut self.feed_statuses),
+            data_feed_id.0,
+            &sender,
+            &access,
+        )
+        .map_err(revert)
+    }
+
+    /// Sets the data point ID the name points to.
+    /// While a data point ID refers to a specific Beacon or dAPI, names
+    /// provide a more abstract interface for convenience. This means a name
+    /// that was pointing at a Beacon can be pointed to a dAPI, then another
+    /// dAPI, etc.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` Human-readable name
+    /// * `datapoint_id` Data point ID the name will point to
+    pub fn set_name(
+        &mut self,
+        name: FixedBytes<32>,
+        datapoint_id: FixedBytes<32>,
+    ) -> Result<(), Vec<u8>> {
+        let sender = self.msg_sender();
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        api3_common::set_name(
+            name.0,
+            datapoint_id.0,
+            &sender,
+            &access,
+            &mut NameHashStorage::requires_write(&mut self.name_hashes),
+        )
+        .map_err(revert)
+    }
+
+    /// Returns the data point ID the name is set to
+    /// `name` Name
+    pub fn name_to_data_point_id(&self, name: FixedBytes<32>) -> FixedBytes<32> {
+        let name_hash = keccak_packed(&[Token::FixedBytes(name.to_vec())]);
+        self.name_hashes.get(storage_key(&name_hash))
+    }
+
+    /// Derives the beacon set ID from the beacon IDs
+    /// Notice that `encode()` is used over `encode_packed()`
+    /// Returns the derived dapi id
+    ///
+    /// # Arguments
+    ///
+    /// * `beacon_ids` Beacon IDs
+    pub fn derive_beacon_set_id(&self, beacon_ids: Vec<FixedBytes<32>>) -> FixedBytes<32> {
+        let beacon_ids = beacon_ids.iter().map(|b| b.0).collect::<Vec<_>>();
+        storage_key(&api3_common::derive_dapi_id(&beacon_ids))
+    }
+
+    /// Derives the beacon id based on the `airnode` and `templated_id`
+    /// Returns the beacon id
+    ///
+    /// # Arguments
+    ///
+    /// * `airnode` Airnode address
+    /// * `template_id` Template ID
+    pub fn derive_beacon_id(
+        &self,
+        airnode: Address,
+        template_id: FixedBytes<32>,
+    ) -> Result<FixedBytes<32>, Vec<u8>> {
+        let beacon_id =
+            api3_common::derive_beacon_id(airnode.to_vec(), template_id.0).map_err(revert)?;
+        Ok(storage_key(&beacon_id))
+    }
+
+    /// Reads the data point with ID
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID
+    pub fn read_with_data_point_id(
+        &self,
+        data_point_id: FixedBytes<32>,
+    ) -> Result<(I224, u32), Vec<u8>> {
+        self.ensure_reads_allowed(&data_point_id.0)?;
+        let reader = self.msg_sender();
+        let access = self.access();
+        let whitelist = self.whitelist(&access, reader);
+        let (value, timestamp) = api3_common::read_with_data_point_id(
+            &data_point_id.0,
+            &reader,
+            &DatapointStorage::read_only(&self.data_points),
+            &FeedStatusStorage::read_only(&self.feed_statuses),
+            &access,
+            &whitelist,
+        )
+        .map_err(revert)?;
+        Ok(Self::value_and_timestamp(value, timestamp))
+    }
+
+    /// Reads the data point with name
+    /// The read data point may belong to a Beacon or dAPI. The reader
+    /// must be whitelisted for the hash of the data point name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` Data point name
+    pub fn read_with_name(&self, name: FixedBytes<32>) -> Result<(I224, u32), Vec<u8>> {
+        let data_point_id = self.name_to_data_point_id(name);
+        self.ensure_reads_allowed(&data_point_id.0)?;
+        let reader = self.msg_sender();
+        let access = self.access();
+        let whitelist = self.whitelist(&access, reader);
+        let (value, timestamp) = api3_common::read_with_name(
+            name.0,
+            &reader,
+            &DatapointStorage::read_only(&self.data_points),
+            &NameHashStorage::read_only(&self.name_hashes),
+            &FeedStatusStorage::read_only(&self.feed_statuses),
+            &access,
+            &whitelist,
+        )
+        .map_err(revert)?;
+        Ok(Self::value_and_timestamp(value, timestamp))
+    }
+
+    /// Returns if a reader can read the data point
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID (or data point name hash)
+    /// * `reader` Reader address
+    pub fn reader_can_read_data_point(
+        &self,
+        data_point_id: FixedBytes<32>,
+        reader: Address,
+    ) -> bool {
+        let reader = Account(reader);
+        let access = self.access();
+        let whitelist = self.whitelist(&access, reader);
+        api3_common::reader_can_read_data_point(&data_point_id.0, &reader, &access, &whitelist)
+    }
+
+    // ================== Whitelist ====================
+    /// Returns the detailed whitelist status of the reader for the data point,
+    /// the expiration timestamp and the indefinite whitelist count
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID (or data point name hash)
+    /// * `reader` Reader address
+    pub fn data_point_id_to_reader_to_whitelist_status(
+        &self,
+        data_point_id: FixedBytes<32>,
+        reader: Address,
+    ) -> (u64, U192) {
+        let key = whitelist_status_key(&data_point_id.0, reader.as_slice());
+        let status = self.whitelist_statuses.getter(storage_key(&key)).get();
+        (
+            status.expiration_timestamp.as_secs(),
+            Self::uint192(&status.indefinite_whitelist_count),
+        )
+    }
+
+    /// Returns if `setter` has indefinitely whitelisted the reader for the data point
+    ///
+    /// # Arguments
+    ///
+    /// * `data_point_id` Data point ID (or data point name hash)
+    /// * `reader` Reader address
+    /// * `setter` Setter address
+    pub fn data_point_id_to_reader_to_setter_to_indefinite_whitelist_status(
+        &self,
+        data_point_id: FixedBytes<32>,
+        reader: Address,
+        setter: Address,
+    ) -> bool {
+        let key =
+            indefinite_whitelist_status_key(&data_point_id.0, reader.as_slice(), setter.as_slice());
+        self.indefinite_whitelist_statuses.get(storage_key(&key))
+    }
+
+    /// Extends the expiration of the temporary whitelist of `user` to
+    /// be able to use the service with `service_id` if the sender has the
+    /// whitelist expiration extender role
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    pub fn extend_whitelist_expiration(
+        &mut self,
+        service_id: FixedBytes<32>,
+        user: Address,
+        expiration_timestamp: u64,
+    ) -> Result<(), Vec<u8>> {
+        let (service_id, user) = (service_id.0, Account(user));
+        let (sender, clock) = (self.msg_sender(), self.clock());
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        let mut whitelist = StylusWhitelist::requires_write(
+            &access,
+            clock,
+            sender,
+            &mut self.whitelist_statuses,
+            &mut self.indefinite_whitelist_statuses,
+        );
+        api3_common::ensure!(
+            whitelist.has_whitelist_expiration_extender_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(revert)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        whitelist
+            .try_extend_whitelist_expiration(
+                &service_id,
+                &user,
+                Timestamp::from_secs(expiration_timestamp),
+            )
+            .map_err(revert)
+    }
+
+    /// Sets the expiration of the temporary whitelist of `user` to be
+    /// able to use the service with `service_id` if the sender has the
+    /// whitelist expiration setter role
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `expiration_timestamp` Timestamp (in seconds) at which the temporary whitelist will expire
+    pub fn set_whitelist_expiration(
+        &mut self,
+        service_id: FixedBytes<32>,
+        user: Address,
+        expiration_timestamp: u64,
+    ) -> Result<(), Vec<u8>> {
+        let (service_id, user) = (service_id.0, Account(user));
+        let (sender, clock) = (self.msg_sender(), self.clock());
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        let mut whitelist = StylusWhitelist::requires_write(
+            &access,
+            clock,
+            sender,
+            &mut self.whitelist_statuses,
+            &mut self.indefinite_whitelist_statuses,
+        );
+        api3_common::ensure!(
+            whitelist.has_whitelist_expiration_setter_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(revert)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        whitelist.set_whitelist_expiration(
+            &service_id,
+            &user,
+            Timestamp::from_secs(expiration_timestamp),
+        );
+        Ok(())
+    }
+
+    /// Sets the indefinite whitelist status of `user` to be able to
+    /// use the service with `service_id` if the sender has the indefinite whitelister role.
+    /// Returns the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `status` Indefinite whitelist status
+    pub fn set_indefinite_whitelist_status(
+        &mut self,
+        service_id: FixedBytes<32>,
+        user: Address,
+        status: bool,
+    ) -> Result<U192, Vec<u8>> {
+        let (service_id, user) = (service_id.0, Account(user));
+        let (sender, clock) = (self.msg_sender(), self.clock());
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        let mut whitelist = StylusWhitelist::requires_write(
+            &access,
+            clock,
+            sender,
+            &mut self.whitelist_statuses,
+            &mut self.indefinite_whitelist_statuses,
+        );
+        api3_common::ensure!(
+            whitelist.has_indefinite_whitelister_role_or_is_manager(&sender),
+            Error::AccessDenied
+        )
+        .map_err(revert)?;
+        Self::ensure_whitelist_ids(&service_id, &user)?;
+        let count = whitelist
+            .set_indefinite_whitelist_status(&service_id, &user, status)
+            .map_err(revert)?;
+        Ok(Self::uint192(&count))
+    }
+
+    /// Revokes the indefinite whitelist status granted to the user for
+    /// the service by a specific account. Anyone can revoke the status set by
+    /// an account that no longer has the indefinite whitelister role.
+    /// Returns if the status was revoked and the indefinite whitelist count of the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id` Service ID
+    /// * `user` User address
+    /// * `setter` Setter address
+    pub fn revoke_indefinite_whitelist_status(
+        &mut self,
+        service_id: FixedBytes<32>,
+        user: Address,
+        setter: Address,
+    ) -> Result<(bool, U192), Vec<u8>> {
+        let (setter, clock) = (Account(setter), self.clock());
+        let access = StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        );
+        let mut whitelist = StylusWhitelist::requires_write(
+            &access,
+            clock,
+            setter,
+            &mut self.whitelist_statuses,
+            &mut self.indefinite_whitelist_statuses,
+        );
+        api3_common::ensure!(
+            !whitelist.has_indefinite_whitelister_role_or_is_manager(&setter),
+            Error::SetterCanSetIndefiniteStatus
+        )
+        .map_err(revert)?;
+        let (revoked, count) = whitelist
+            .revoke_indefinite_whitelist_status(&service_id.0, &Account(user), &setter)
+            .map_err(revert)?;
+        Ok((revoked, Self::uint192(&count)))
+    }
+
+    /// Returns the whitelist expiration extender role
+    pub fn whitelist_expiration_extender_role(&self) -> FixedBytes<32> {
+        let access = self.access();
+        storage_key(
+            &self
+                .whitelist(&access, Account::default())
+                .whitelist_expiration_extender_role(),
+        )
+    }
+
+    /// Returns the whitelist expiration setter role
+    pub fn whitelist_expiration_setter_role(&self) -> FixedBytes<32> {
+        let access = self.access();
+        storage_key(
+            &self
+                .whitelist(&access, Account::default())
+                .whitelist_expiration_setter_role(),
+        )
+    }
+
+    /// Returns the indefinite whitelister role
+    pub fn indefinite_whitelister_role(&self) -> FixedBytes<32> {
+        let access = self.access();
+        storage_key(
+            &self
+                .whitelist(&access, Account::default())
+                .indefinite_whitelister_role(),
+        )
+    }
+
+    /// Returns the admin role description, which derives the admin role of the manager
+    pub fn admin_role_description(&self) -> String {
+        String::from(StylusAccessControlRegistry::ADMIN_ROLE_DESCRIPTION)
+    }
+}
+
+impl DapiServer {
+    fn msg_sender(&self) -> Account {
+        Account(self.vm().msg_sender())
+    }
+
+    fn clock(&self) -> StylusClock {
+        StylusClock::new(self.vm().block_timestamp())
+    }
+
+    fn manager_account(&self) -> Account {
+        Account(self.manager.get())
+    }
+
+    fn access(&self) -> StylusAccessControlRegistry<'_> {
+        StylusAccessControlRegistry::read_only(
+            self.manager_account(),
+            &self.role_members,
+            &self.role_admins,
+        )
+    }
+
+    fn whitelist<'a>(
+        &'a self,
+        access: &'a StylusAccessControlRegistry<'a>,
+        sender: Account,
+    ) -> StylusWhitelist<'a, StylusAccessControlRegistry<'a>> {
+        StylusWhitelist::read_only(
+            access,
+            self.clock(),
+            sender,
+            &self.whitelist_statuses,
+            &self.indefinite_whitelist_statuses,
+        )
+    }
+
+    /// Checks the Airnode signed the template ID, the timestamp and the data
+    fn verify_signed_data(
+        airnode: Address,
+        template_id: &Bytes32,
+        timestamp: &Bytes32,
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        let message = keccak_packed(&[
+            Token::FixedBytes(template_id.to_vec()),
+            Token::Uint(Uint::from_big_endian(timestamp)),
+            Token::Bytes(data.to_vec()),
+        ]);
+        api3_common::ensure!(
+            SignatureVerify::verify(airnode.as_slice(), &message, signature),
+            Error::InvalidSignature
+        )
+    }
+
+    /// The value as the `int224` of the Solidity contract, updates with
+    /// values out of its range are rejected with `ensure_int224`
+    fn value_and_timestamp(value: Int, timestamp: Timestamp) -> (I224, u32) {
+        let mut v = [0u8; 32];
+        value.to_big_endian(&mut v);
+        (
+            I224::from_raw(U224::from_be_slice(&v[4..])),
+            timestamp.as_secs_u32(),
+        )
+    }
+
+    /// The indefinite whitelist count as the `uint192` of the Solidity contract
+    fn uint192(count: &api3_common::abi::U256) -> U192 {
+        U192::from_be_slice(&Bytes32::from(count)[8..])
+    }
+
+    fn ensure_whitelist_ids(service_id: &Bytes32, user: &Account) -> Result<(), Vec<u8>> {
+        api3_common::ensure!(!service_id.is_zero(), Error::ServiceIdZero).map_err(revert)?;
+        api3_common::ensure!(!user.is_zero(), Error::UserAddressZero).map_err(revert)
+    }
+
+    fn ensure_can_pause(&self) -> Result<(), Vec<u8>> {
+        api3_common::ensure_can_pause(&self.access(), &self.msg_sender()).map_err(revert)
+    }
+
+    fn pause_states(&self, data_feed_id: &Bytes32) -> Result<(PauseState, PauseState), Vec<u8>> {
+        let global = PauseState::try_from(self.pause_state()).map_err(revert)?;
+        let data_feed = PauseState::try_from(self.data_feed_pause_state(storage_key(data_feed_id)))
+            .map_err(revert)?;
+        Ok((global, data_feed))
+    }
+
+    fn ensure_updates_allowed(&self, data_feed_id: &Bytes32) -> Result<(), Vec<u8>> {
+        let (global, data_feed) = self.pause_states(data_feed_id)?;
+        api3_common::ensure_updates_allowed(global, data_feed).map_err(revert)
+    }
+
+    /// Checks updates are allowed for the dAPI and each of its Beacons
+    fn ensure_dapi_updates_allowed(
+        &self,
+        dapi_id: &Bytes32,
+        beacon_ids: &[Bytes32],
+    ) -> Result<(), Vec<u8>> {
+        self.ensure_updates_allowed(dapi_id)?;
+        for beacon_id in beacon_ids {
+            self.ensure_updates_allowed(beacon_id)?;
+        }
+        Ok(())
+    }
+
+    fn ensure_reads_allowed(&self, data_feed_id: &Bytes32) -> Result<(), Vec<u8>> {
+        let (global, data_feed) = self.pause_states(data_feed_id)?;
+        api3_common::ensure_reads_allowed(global, data_feed).map_err(revert)
+    }
+}
@@ -0,0 +1,375 @@
+use crate::utils::revert;
+use crate::DapiServer;
+use alloy_primitives::aliases::{I224, U192, U224};
+use alloy_primitives::{uint, Address, FixedBytes, U256};
+use api3_common::abi::{encode, keccak256, Int, Token, Uint};
+use api3_common::{keccak_packed, Bytes32, Error};
+use stylus_sdk::abi::Bytes;
+use stylus_sdk::testing::*;
+
+const TEMPLATE_ID: Bytes32 = [1u8; 32];
+const NOW: u64 = 1_650_000_000;
+
+fn airnode_key(seed: u8) -> libsecp256k1::SecretKey {
+    libsecp256k1::SecretKey::parse(&[seed; 32]).unwrap()
+}
+
+fn airnode(key: &libsecp256k1::SecretKey) -> Address {
+    let public_key = libsecp256k1::PublicKey::from_secret_key(key).serialize();
+    Address::from_slice(&keccak256(&public_key[1..])[12..])
+}
+
+/// Signs `data` at `timestamp` the way Airnode does, returns the timestamp,
+/// data and signature arguments of the update functions
+fn sign_data(key: &libsecp256k1::SecretKey, timestamp: u64, data: Vec<u8>) -> (U256, Bytes, Bytes) {
+    let message = keccak_packed(&[
+        Token::FixedBytes(TEMPLATE_ID.to_vec()),
+        Token::Uint(Uint::from(timestamp)),
+        Token::Bytes(data.clone()),
+    ]);
+    let mut prefixed = b"\x19Ethereum Signed Message:\n32".to_vec();
+    prefixed.extend_from_slice(&message);
+    let hash = keccak256(&prefixed);
+    let (signature, recovery_id) = libsecp256k1::sign(&libsecp256k1::Message::parse(&hash), key);
+    let mut rsv = signature.serialize().to_vec();
+    rsv.push(recovery_id.serialize() + 27);
+    (U256::from(timestamp), Bytes::from(data), Bytes::from(rsv))
+}
+
+fn sign(key: &libsecp256k1::SecretKey, timestamp: u64, value: u128) -> (U256, Bytes, Bytes) {
+    sign_data(key, timestamp, encode(&[Token::Int(Int::from(value))]))
+}
+
+fn value(v: u64) -> I224 {
+    I224::from_raw(U224::from(v))
+}
+
+fn deploy(vm: &TestVM, manager: Address) -> DapiServer {
+    vm.set_sender(manager);
+    vm.set_block_timestamp(NOW);
+    let mut contract = DapiServer::from(vm);
+    contract.constructor(manager).unwrap();
+    contract
+}
+
+#[test]
+fn constructor_grants_default_admin_role() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let contract = deploy(&vm, manager);
+    assert_eq!(contract.manager(), manager);
+    assert!(contract.has_role(FixedBytes::ZERO, manager));
+    assert_eq!(contract.admin_role_description(), "admin role");
+
+    let mut zero_manager = DapiServer::from(&vm);
+    assert_eq!(
+        zero_manager.constructor(Address::ZERO),
+        Err(revert(Error::InvalidAddress))
+    );
+}
+
+#[test]
+fn updates_beacon_with_signed_data() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let mut contract = deploy(&vm, manager);
+    let key = airnode_key(1);
+    let template_id = FixedBytes::from(TEMPLATE_ID);
+    let beacon_id = contract
+        .derive_beacon_id(airnode(&key), template_id)
+        .unwrap();
+
+    let (ts, data, signature) = sign(&key, NOW - 10, 123);
+    contract
+        .update_beacon_with_signed_data(airnode(&key), template_id, ts, data, signature)
+        .unwrap();
+
+    // the signature recovers to the address of another Airnode
+    let (ts, data, signature) = sign(&airnode_key(2), NOW - 5, 456);
+    assert_eq!(
+        contract.update_beacon_with_signed_data(airnode(&key), template_id, ts, data, signature),
+        Err(revert(Error::InvalidSignature))
+    );
+
+    // the manager is not whitelisted, but can grant itself the unlimited reader role
+    assert_eq!(
+        contract.read_with_data_point_id(beacon_id),
+        Err(revert(Error::AccessDenied))
+    );
+    let unlimited_reader = contract.unlimited_reader_role();
+    contract.grant_role(unlimited_reader, manager).unwrap();
+    assert_eq!(
+        contract.read_with_data_point_id(beacon_id),
+        Ok((value(123), (NOW - 10) as u32))
+    );
+}
+
+#[test]
+fn updates_dapi_with_signed_data() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let reader = Address::repeat_byte(2);
+    let mut contract = deploy(&vm, manager);
+    let keys = [airnode_key(1), airnode_key(2), airnode_key(3)];
+
+    let (mut timestamps, mut data, mut signatures) = (vec![], vec![], vec![]);
+    for (key, v) in keys.iter().zip([100u128, 200, 400]) {
+        let (ts, d, signature) = sign(key, NOW - 10, v);
+        timestamps.push(ts);
+        data.push(d);
+        signatures.push(signature);
+    }
+    let dapi_id = contract
+        .update_dapi_with_signed_data(
+            keys.iter().map(airnode).collect(),
+            vec![FixedBytes::from(TEMPLATE_ID); 3],
+            timestamps,
+            data,
+            signatures,
+        )
+        .unwrap();
+
+    contract
+        .set_whitelist_expiration(dapi_id, reader, NOW + 60)
+        .unwrap();
+    vm.set_sender(reader);
+    assert_eq!(
+        contract.read_with_data_point_id(dapi_id),
+        Ok((value(200), (NOW - 10) as u32))
+    );
+
+    // the temporary whitelist expires
+    vm.set_block_timestamp(NOW + 60);
+    assert!(!contract.reader_can_read_data_point(dapi_id, reader));
+}
+
+#[test]
+fn rejects_values_outside_int224() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let mut contract = deploy(&vm, manager);
+    let key = airnode_key(1);
+    let template_id = FixedBytes::from(TEMPLATE_ID);
+    let beacon_id = contract
+        .derive_beacon_id(airnode(&key), template_id)
+        .unwrap();
+    let unlimited_reader = contract.unlimited_reader_role();
+    contract.grant_role(unlimited_reader, manager).unwrap();
+
+    let int224_max = (Int::from(1) << 223) - 1;
+    let int224_min = !(Int::from(1) << 223) + 1;
+    let update = |contract: &mut DapiServer, timestamp: u64, v: Int| {
+        let (ts, data, signature) = sign_data(&key, timestamp, encode(&[Token::Int(v)]));
+        contract.update_beacon_with_signed_data(airnode(&key), template_id, ts, data, signature)
+    };
+
+    assert_eq!(
+        update(&mut contract, NOW - 30, int224_max + 1),
+        Err(revert(Error::ValueTypecasting))
+    );
+    update(&mut contract, NOW - 30, int224_max).unwrap();
+    assert_eq!(
+        contract.read_with_data_point_id(beacon_id),
+        Ok((I224::MAX, (NOW - 30) as u32))
+    );
+    assert_eq!(
+        update(&mut contract, NOW - 20, int224_min - 1),
+        Err(revert(Error::ValueTypecasting))
+    );
+    update(&mut contract, NOW - 20, int224_min).unwrap();
+    assert_eq!(
+        contract.read_with_data_point_id(beacon_id),
+        Ok((I224::MIN, (NOW - 20) as u32))
+    );
+
+    // signed data of dAPI updates is checked the same way
+    let keys = [airnode_key(2), airnode_key(3)];
+    let signed = [
+        sign(&keys[0], NOW - 10, 100),
+        sign_data(&keys[1], NOW - 10, encode(&[Token::Int(int224_max + 1)])),
+    ];
+    assert_eq!(
+        contract.update_dapi_with_signed_data(
+            keys.iter().map(airnode).collect(),
+            vec![template_id; 2],
+            signed.iter().map(|s| s.0).collect(),
+            signed.iter().map(|s| s.1.clone()).collect(),
+            signed.iter().map(|s| s.2.clone()).collect(),
+        ),
+        Err(revert(Error::ValueTypecasting))
+    );
+}
+
+#[test]
+fn recovers_signatures_like_openzeppelin() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let mut contract = deploy(&vm, manager);
+    let key = airnode_key(1);
+    let template_id = FixedBytes::from(TEMPLATE_ID);
+    let mut update = |timestamp: u64, signature: Vec<u8>| {
+        let (ts, data, _) = sign(&key, timestamp, 123);
+        contract.update_beacon_with_signed_data(
+            airnode(&key),
+            template_id,
+            ts,
+            data,
+            Bytes::from(signature),
+        )
+    };
+    let (_, _, signature) = sign(&key, NOW - 30, 123);
+    let signature = signature.0;
+
+    // `v` is 27 or 28, or the recovery ID itself
+    let mut recovery_id = signature.clone();
+    recovery_id[64] -= 27;
+    update(NOW - 30, recovery_id).unwrap();
+    let mut invalid_v = signature.clone();
+    invalid_v[64] += 2;
+    assert_eq!(
+        update(NOW - 20, invalid_v),
+        Err(revert(Error::InvalidSignature))
+    );
+
+    // ecrecover accepts the malleable signature with `s` in the upper half
+    // of the order and the other `v`, which is rejected
+    let (_, _, signature) = sign(&key, NOW - 20, 123);
+    let signature = signature.0;
+    const SECP256K1N: U256 =
+        uint!(0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141_U256);
+    let s = SECP256K1N - U256::from_be_slice(&signature[32..64]);
+    let mut malleable = signature[..32].to_vec();
+    malleable.extend_from_slice(&s.to_be_bytes::<32>());
+    malleable.push(if signature[64] == 27 { 28 } else { 27 });
+    assert_eq!(
+        update(NOW - 20, malleable),
+        Err(revert(Error::InvalidSignature))
+    );
+
+    // the signature is `r`, `s` and `v`
+    assert_eq!(
+        update(NOW - 20, signature[..64].to_vec()),
+        Err(revert(Error::InvalidSignature))
+    );
+    update(NOW - 20, signature.to_vec()).unwrap();
+}
+
+#[test]
+fn names_and_whitelists() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let (bob, charlie) = (Address::repeat_byte(2), Address::repeat_byte(3));
+    let mut contract = deploy(&vm, manager);
+    let name = FixedBytes::from([7u8; 32]);
+    let data_point_id = FixedBytes::from([8u8; 32]);
+    let name_hash = FixedBytes::from(keccak_packed(&[Token::FixedBytes(name.to_vec())]));
+
+    assert_eq!(
+        contract.set_name(name, data_point_id),
+        Err(revert(Error::AccessDenied))
+    );
+    let name_setter = contract.name_setter_role();
+    contract.grant_role(name_setter, manager).unwrap();
+    contract.set_name(name, data_point_id).unwrap();
+    assert_eq!(contract.name_to_data_point_id(name), data_point_id);
+
+    // only the manager and indefinite whitelisters can whitelist indefinitely
+    vm.set_sender(bob);
+    assert_eq!(
+        contract.set_indefinite_whitelist_status(name_hash, charlie, true),
+        Err(revert(Error::AccessDenied))
+    );
+    vm.set_sender(manager);
+    assert_eq!(
+        contract.set_indefinite_whitelist_status(name_hash, charlie, true),
+        Ok(U192::from(1))
+    );
+    assert!(
+        contract.data_point_id_to_reader_to_setter_to_indefinite_whitelist_status(
+            name_hash, charlie, manager
+        )
+    );
+    assert_eq!(
+        contract.data_point_id_to_reader_to_whitelist_status(name_hash, charlie),
+        (0, U192::from(1))
+    );
+    vm.set_sender(charlie);
+    assert_eq!(contract.read_with_name(name), Ok((value(0), 0)));
+
+    // the manager can always set indefinite whitelist statuses, so they cannot be revoked
+    assert_eq!(
+        contract.revoke_indefinite_whitelist_status(name_hash, charlie, manager),
+        Err(revert(Error::SetterCanSetIndefiniteStatus))
+    );
+}
+
+#[test]
+fn pause_blocks_updates_and_reads() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let bob = Address::repeat_byte(2);
+    let mut contract = deploy(&vm, manager);
+    let beacon_ids = vec![FixedBytes::from([1u8; 32]), FixedBytes::from([2u8; 32])];
+    let dapi_id = contract.derive_beacon_set_id(beacon_ids.clone());
+
+    vm.set_sender(bob);
+    assert_eq!(
+        contract.set_pause_state(1),
+        Err(revert(Error::AccessDenied))
+    );
+    vm.set_sender(manager);
+    assert_eq!(
+        contract.set_pause_state(3),
+        Err(revert(Error::InvalidPauseState))
+    );
+    contract.set_data_feed_pause_state(dapi_id, 2).unwrap();
+    assert_eq!(contract.data_feed_pause_state(dapi_id), 2);
+    assert_eq!(
+        contract.update_dapi_with_beacons(beacon_ids.clone()),
+        Err(revert(Error::UpdatesPaused))
+    );
+    assert_eq!(
+        contract.read_with_data_point_id(dapi_id),
+        Err(revert(Error::ReadsPaused))
+    );
+
+    contract.set_data_feed_pause_state(dapi_id, 0).unwrap();
+    assert_eq!(contract.update_dapi_with_beacons(beacon_ids), Ok(dapi_id));
+}
+
+#[test]
+fn manager_transfer_moves_admin_role() {
+    let vm = TestVM::default();
+    let manager = Address::repeat_byte(1);
+    let (bob, pauser) = (Address::repeat_byte(2), Address::repeat_byte(3));
+    let mut contract = deploy(&vm, manager);
+    let pauser_role = contract.pauser_role();
+    contract.grant_role(pauser_role, pauser).unwrap();
+
+    contract.propose_manager(bob).unwrap();
+    assert_eq!(contract.pending_manager(), bob);
+    assert_eq!(contract.accept_manager(), Err(revert(Error::NotAuthorized)));
+
+    vm.set_sender(bob);
+    contract.accept_manager().unwrap();
+    assert_eq!(contract.manager(), bob);
+    assert_eq!(contract.previous_manager(), manager);
+    assert_eq!(contract.pending_manager(), Address::ZERO);
+    assert!(contract.has_role(FixedBytes::ZERO, bob));
+    assert!(!contract.has_role(FixedBytes::ZERO, manager));
+
+    // the roles are derived from the new manager, members are migrated by it
+    vm.set_sender(manager);
+    assert_eq!(
+        contract.migrate_role_members("Pauser".into(), vec![pauser]),
+        Err(revert(Error::NotAuthorized))
+    );
+    vm.set_sender(bob);
+    let role = contract
+        .migrate_role_members("Pauser".into(), vec![pauser])
+        .unwrap();
+    assert_eq!(role, contract.pauser_role());
+    assert_ne!(role, pauser_role);
+    assert!(contract.has_role(role, pauser));
+    assert!(!contract.has_role(pauser_role, pauser));
+}
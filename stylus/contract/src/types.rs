@@ -0,0 +1,103 @@
+use alloy_primitives::{Address, FixedBytes, U256, U32, U64};
+use api3_common::abi::{Int, U256 as CommonU256};
+use api3_common::{Bytes32, DataPoint, FeedStatus, Timestamp, WhitelistStatus, Zero};
+use stylus_sdk::prelude::*;
+use stylus_sdk::storage::{StorageBool, StorageFixedBytes, StorageU256, StorageU32, StorageU64};
+
+/// The EVM address as the address type of `api3_common`
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Account(pub Address);
+
+impl Zero for Account {
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl AsRef<[u8]> for Account {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+#[storage]
+pub struct StylusDataPoint {
+    value: StorageFixedBytes<32>,
+    timestamp: StorageU32,
+}
+
+impl StylusDataPoint {
+    pub fn get(&self) -> DataPoint {
+        DataPoint::new(
+            Int::from_big_endian(self.value.get().as_slice()),
+            Timestamp::from_secs(u64::from(self.timestamp.get().to::<u32>())),
+        )
+    }
+
+    pub fn set(&mut self, datapoint: DataPoint) {
+        let mut value = [0u8; 32];
+        datapoint.value.to_big_endian(&mut value);
+        self.value.set(FixedBytes::from(value));
+        self.timestamp
+            .set(U32::from(datapoint.timestamp.as_secs_u32()));
+    }
+}
+
+/// The circuit breaker status of a data feed, `pending` is the update held
+/// back while the feed is paused
+#[storage]
+pub struct StylusFeedStatus {
+    paused: StorageBool,
+    pending: StylusDataPoint,
+}
+
+impl StylusFeedStatus {
+    pub fn get(&self) -> FeedStatus {
+        if self.paused.get() {
+            FeedStatus::Paused(self.pending.get())
+        } else {
+            FeedStatus::Active
+        }
+    }
+
+    pub fn set(&mut self, status: FeedStatus) {
+        match status {
+            FeedStatus::Active => {
+                self.paused.set(false);
+                self.pending.set(DataPoint::default());
+            }
+            FeedStatus::Paused(d) => {
+                self.paused.set(true);
+                self.pending.set(d);
+            }
+        }
+    }
+}
+
+#[storage]
+pub struct StylusWhitelistStatus {
+    /// in seconds
+    expiration_timestamp: StorageU64,
+    /// originally uint192
+    indefinite_whitelist_count: StorageU256,
+}
+
+impl StylusWhitelistStatus {
+    pub fn get(&self) -> WhitelistStatus {
+        WhitelistStatus {
+            expiration_timestamp: Timestamp::from_secs(self.expiration_timestamp.get().to::<u64>()),
+            indefinite_whitelist_count: CommonU256::from_big_endian(
+                &self.indefinite_whitelist_count.get().to_be_bytes::<32>(),
+            ),
+        }
+    }
+
+    pub fn set(&mut self, status: WhitelistStatus) {
+        self.expiration_timestamp
+            .set(U64::from(status.expiration_timestamp.as_secs()));
+        self.indefinite_whitelist_count
+            .set(U256::from_be_bytes(Bytes32::from(
+                &status.indefinite_whitelist_count,
+            )));
+    }
+}
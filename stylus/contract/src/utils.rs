@@ -0,0 +1,393 @@
+use crate::types::{Account, StylusDataPoint, StylusFeedStatus, StylusWhitelistStatus};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloy_primitives::{eip191_hash_message, uint, Address, FixedBytes, PrimitiveSignature, U256};
+use alloy_sol_types::{Revert, SolError};
+use api3_common::abi::Token;
+use api3_common::{
+    decode_fulfillment_data, keccak_packed, AccessControlRegistry,
+    AccessControlRegistryAdminnedWithManager, Bytes32, DataPoint, Error, FeedStatus, RoleDeriver,
+    SignatureManger, Storage, Timestamp, TimestampChecker, WhitelistStatus,
+};
+use stylus_sdk::storage::{StorageBool, StorageFixedBytes, StorageMap};
+
+/// The revert data of an `api3_common::Error`. The messages of the errors are
+/// the revert strings of the Solidity contract, so the contract reverts with
+/// `Error(string)` the same way.
+pub(crate) fn revert(e: Error) -> Vec<u8> {
+    Revert {
+        reason: e.to_string(),
+    }
+    .abi_encode()
+}
+
+/// Fails the call where the common traits leave no room for an error.
+/// Functions return `revert` otherwise.
+pub(crate) fn stylus_fail(e: Error) -> ! {
+    panic!("{}", e)
+}
+
+pub(crate) fn read_only_storage() -> ! {
+    stylus_fail(Error::ReadOnlyStorage)
+}
+
+pub(crate) fn storage_key(k: &Bytes32) -> FixedBytes<32> {
+    FixedBytes::from(*k)
+}
+
+/// Read write privilege
+pub(crate) enum ReadWrite<'a, T> {
+    ReadOnly(&'a T),
+    Write(&'a mut T),
+}
+
+impl<'a, T> ReadWrite<'a, T> {
+    fn read(&self) -> &T {
+        match self {
+            ReadWrite::ReadOnly(m) => m,
+            ReadWrite::Write(m) => m,
+        }
+    }
+
+    fn write(&mut self) -> &mut T {
+        match self {
+            ReadWrite::ReadOnly(_) => read_only_storage(),
+            ReadWrite::Write(m) => m,
+        }
+    }
+}
+
+/// The utility struct for handling Stylus storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct DatapointStorage<'a> {
+    map: ReadWrite<'a, StorageMap<FixedBytes<32>, StylusDataPoint>>,
+}
+
+impl<'a> DatapointStorage<'a> {
+    pub fn requires_write(map: &'a mut StorageMap<FixedBytes<32>, StylusDataPoint>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a StorageMap<FixedBytes<32>, StylusDataPoint>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a> Storage<DataPoint> for DatapointStorage<'a> {
+    /// Unset slots read as zero, which is the default data point
+    fn get(&self, k: &Bytes32) -> Option<DataPoint> {
+        Some(self.map.read().getter(storage_key(k)).get())
+    }
+
+    fn store(&mut self, k: Bytes32, datapoint: DataPoint) {
+        self.map.write().setter(storage_key(&k)).set(datapoint);
+    }
+}
+
+/// The utility struct for handling Stylus storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct NameHashStorage<'a> {
+    map: ReadWrite<'a, StorageMap<FixedBytes<32>, StorageFixedBytes<32>>>,
+}
+
+impl<'a> NameHashStorage<'a> {
+    pub fn requires_write(map: &'a mut StorageMap<FixedBytes<32>, StorageFixedBytes<32>>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a StorageMap<FixedBytes<32>, StorageFixedBytes<32>>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a> Storage<Bytes32> for NameHashStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<Bytes32> {
+        Some(self.map.read().get(storage_key(k)).0)
+    }
+
+    fn store(&mut self, k: Bytes32, data: Bytes32) {
+        self.map
+            .write()
+            .insert(storage_key(&k), FixedBytes::from(data));
+    }
+}
+
+/// The utility struct for handling Stylus storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct FeedStatusStorage<'a> {
+    map: ReadWrite<'a, StorageMap<FixedBytes<32>, StylusFeedStatus>>,
+}
+
+impl<'a> FeedStatusStorage<'a> {
+    pub fn requires_write(map: &'a mut StorageMap<FixedBytes<32>, StylusFeedStatus>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a StorageMap<FixedBytes<32>, StylusFeedStatus>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a> Storage<FeedStatus> for FeedStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<FeedStatus> {
+        Some(self.map.read().getter(storage_key(k)).get())
+    }
+
+    fn store(&mut self, k: Bytes32, status: FeedStatus) {
+        self.map.write().setter(storage_key(&k)).set(status);
+    }
+}
+
+/// The utility struct for handling Stylus storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct WhitelistStatusStorage<'a> {
+    map: ReadWrite<'a, StorageMap<FixedBytes<32>, StylusWhitelistStatus>>,
+}
+
+impl<'a> WhitelistStatusStorage<'a> {
+    pub fn requires_write(map: &'a mut StorageMap<FixedBytes<32>, StylusWhitelistStatus>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a StorageMap<FixedBytes<32>, StylusWhitelistStatus>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a> Storage<WhitelistStatus> for WhitelistStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<WhitelistStatus> {
+        Some(self.map.read().getter(storage_key(k)).get())
+    }
+
+    fn store(&mut self, k: Bytes32, status: WhitelistStatus) {
+        self.map.write().setter(storage_key(&k)).set(status);
+    }
+}
+
+/// The utility struct for handling Stylus storage so that
+/// we can use the code in `api3_common` for all the processing
+pub(crate) struct IndefiniteWhitelistStatusStorage<'a> {
+    map: ReadWrite<'a, StorageMap<FixedBytes<32>, StorageBool>>,
+}
+
+impl<'a> IndefiniteWhitelistStatusStorage<'a> {
+    pub fn requires_write(map: &'a mut StorageMap<FixedBytes<32>, StorageBool>) -> Self {
+        Self {
+            map: ReadWrite::Write(map),
+        }
+    }
+
+    pub fn read_only(map: &'a StorageMap<FixedBytes<32>, StorageBool>) -> Self {
+        Self {
+            map: ReadWrite::ReadOnly(map),
+        }
+    }
+}
+
+impl<'a> Storage<bool> for IndefiniteWhitelistStatusStorage<'a> {
+    fn get(&self, k: &Bytes32) -> Option<bool> {
+        Some(self.map.read().get(storage_key(k)))
+    }
+
+    fn store(&mut self, k: Bytes32, status: bool) {
+        self.map.write().insert(storage_key(&k), status);
+    }
+}
+
+/// Half the order of secp256k1, signatures with a larger `s` are malleable
+const SECP256K1N_HALF: U256 =
+    uint!(0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0_U256);
+
+/// Signature verification for Stylus. The Airnode signs the Ethereum signed
+/// message hash of the message, the signer is recovered in the contract the
+/// way OpenZeppelin's `ECDSA.recover` does and compared to the Airnode address.
+pub(crate) struct SignatureVerify;
+
+impl SignatureManger for SignatureVerify {
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        key.len() == 20 && recover_signer(message, signature) == Some(Address::from_slice(key))
+    }
+}
+
+/// Recovers the signer of `signature` over the Ethereum signed message hash of
+/// `message`. The signature is `r`, `s` and `v`, where `v` is 27 or 28 (0 or 1
+/// is accepted too) and `s` is in the lower half of the curve order.
+pub(crate) fn recover_signer(message: &[u8], signature: &[u8]) -> Option<Address> {
+    if signature.len() != 65 {
+        return None;
+    }
+    let parity = match signature[64] {
+        27 | 0 => false,
+        28 | 1 => true,
+        _ => return None,
+    };
+    let signature = PrimitiveSignature::from_bytes_and_parity(&signature[..64], parity);
+    if signature.s() > SECP256K1N_HALF {
+        return None;
+    }
+    signature
+        .recover_address_from_prehash(&eip191_hash_message(message))
+        .ok()
+}
+
+/// Checks the signed data decodes to a value in the range of `int224`, the
+/// type the Solidity contract stores data point values as
+pub(crate) fn ensure_int224(data: &[u8]) -> Result<(), Error> {
+    let mut value = [0u8; 32];
+    decode_fulfillment_data(&data.to_vec())?.to_big_endian(&mut value);
+    let sign = if value[4] & 0x80 == 0 { 0x00 } else { 0xff };
+    api3_common::ensure!(
+        value[..4].iter().all(|b| *b == sign),
+        Error::ValueTypecasting
+    )
+}
+
+pub(crate) struct StylusClock {
+    current_timestamp: Timestamp,
+}
+
+impl StylusClock {
+    /// The clock at `block.timestamp`, which is in seconds
+    pub fn new(block_timestamp: u64) -> Self {
+        Self {
+            current_timestamp: Timestamp::from_secs(block_timestamp),
+        }
+    }
+}
+
+impl TimestampChecker for StylusClock {
+    fn current_timestamp(&self) -> Timestamp {
+        self.current_timestamp
+    }
+}
+
+pub(crate) struct StylusAccessControlRegistry<'a> {
+    manager: Account,
+    admin_role_description: String,
+    sender: Option<Account>,
+    role_membership: ReadWrite<'a, StorageMap<FixedBytes<32>, StorageBool>>,
+    role_admin: ReadWrite<'a, StorageMap<FixedBytes<32>, StorageFixedBytes<32>>>,
+}
+
+impl<'a> StylusAccessControlRegistry<'a> {
+    pub const ADMIN_ROLE_DESCRIPTION: &'static str = "admin role";
+
+    pub fn requires_write(
+        manager: Account,
+        role_membership: &'a mut StorageMap<FixedBytes<32>, StorageBool>,
+        role_admin: &'a mut StorageMap<FixedBytes<32>, StorageFixedBytes<32>>,
+    ) -> Self {
+        Self {
+            manager,
+            admin_role_description: String::from(Self::ADMIN_ROLE_DESCRIPTION),
+            sender: None,
+            role_membership: ReadWrite::Write(role_membership),
+            role_admin: ReadWrite::Write(role_admin),
+        }
+    }
+
+    pub fn read_only(
+        manager: Account,
+        role_membership: &'a StorageMap<FixedBytes<32>, StorageBool>,
+        role_admin: &'a StorageMap<FixedBytes<32>, StorageFixedBytes<32>>,
+    ) -> Self {
+        Self {
+            manager,
+            admin_role_description: String::from(Self::ADMIN_ROLE_DESCRIPTION),
+            sender: None,
+            role_membership: ReadWrite::ReadOnly(role_membership),
+            role_admin: ReadWrite::ReadOnly(role_admin),
+        }
+    }
+
+    /// The registry for calls by `sender`, who can then renounce roles
+    pub fn with_sender(mut self, sender: Account) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    fn hash_membership(role: &Bytes32, who: &Account) -> FixedBytes<32> {
+        storage_key(&keccak_packed(&[
+            Token::FixedBytes(role.to_vec()),
+            Token::FixedBytes(who.as_ref().to_vec()),
+        ]))
+    }
+}
+
+impl<'a> AccessControlRegistryAdminnedWithManager for StylusAccessControlRegistry<'a> {
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        &self.manager
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.admin_role_description.clone()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        keccak_packed(&[Token::String(self.admin_role_description())])
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        RoleDeriver::derive_role(
+            RoleDeriver::derive_root_role(self.manager.as_ref()),
+            self.admin_role_description(),
+        )
+    }
+}
+
+impl<'a> AccessControlRegistry for StylusAccessControlRegistry<'a> {
+    fn has_role(&self, role: &Bytes32, who: &Self::Address) -> bool {
+        self.role_membership
+            .read()
+            .get(Self::hash_membership(role, who))
+    }
+
+    fn grant_role(&mut self, role: &Bytes32, who: &Self::Address) -> Result<(), Error> {
+        self.role_membership
+            .write()
+            .insert(Self::hash_membership(role, who), true);
+        Ok(())
+    }
+
+    /// Unset slots read as zero, which is the default admin role
+    fn get_role_admin(&self, role: &Bytes32) -> Option<Bytes32> {
+        Some(self.role_admin.read().get(storage_key(role)).0)
+    }
+
+    fn set_role_admin(&mut self, role: &Bytes32, role_admin: Bytes32) -> Result<(), Error> {
+        self.role_admin
+            .write()
+            .insert(storage_key(role), FixedBytes::from(role_admin));
+        Ok(())
+    }
+
+    fn renounce_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        api3_common::ensure!(self.sender.as_ref() == Some(account), Error::NotAuthorized)?;
+        self.revoke_role(role, account)
+    }
+
+    fn revoke_role(&mut self, role: &Bytes32, account: &Self::Address) -> Result<(), Error> {
+        self.role_membership
+            .write()
+            .delete(Self::hash_membership(role, account));
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use crate::types::{Account, StylusWhitelistStatus};
+use crate::utils::{
+    stylus_fail, IndefiniteWhitelistStatusStorage, StylusClock, WhitelistStatusStorage,
+};
+use alloc::string::String;
+use alloy_primitives::FixedBytes;
+use api3_common::abi::U256;
+use api3_common::{
+    AccessControlRegistry, AccessControlRegistryAdminnedWithManager, Bytes32, Error, Timestamp,
+    Whitelist, WhitelistRoles, WhitelistRolesWithManager,
+};
+use stylus_sdk::storage::{StorageBool, StorageMap};
+
+pub(crate) struct StylusWhitelist<'a, Access> {
+    access: &'a Access,
+    clock: StylusClock,
+    /// The sender, who is the setter of indefinite whitelist statuses
+    sender: Account,
+    service_id_to_user_to_whitelist_status: WhitelistStatusStorage<'a>,
+    service_id_to_user_to_setter_to_indefinite_whitelist_status:
+        IndefiniteWhitelistStatusStorage<'a>,
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> StylusWhitelist<'a, Access> {
+    pub fn requires_write(
+        access: &'a Access,
+        clock: StylusClock,
+        sender: Account,
+        whitelist_statuses: &'a mut StorageMap<FixedBytes<32>, StylusWhitelistStatus>,
+        indefinite_whitelist_statuses: &'a mut StorageMap<FixedBytes<32>, StorageBool>,
+    ) -> Self {
+        Self {
+            access,
+            clock,
+            sender,
+            service_id_to_user_to_whitelist_status: WhitelistStatusStorage::requires_write(
+                whitelist_statuses,
+            ),
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusStorage::requires_write(indefinite_whitelist_statuses),
+        }
+    }
+
+    pub fn read_only(
+        access: &'a Access,
+        clock: StylusClock,
+        sender: Account,
+        whitelist_statuses: &'a StorageMap<FixedBytes<32>, StylusWhitelistStatus>,
+        indefinite_whitelist_statuses: &'a StorageMap<FixedBytes<32>, StorageBool>,
+    ) -> Self {
+        Self {
+            access,
+            clock,
+            sender,
+            service_id_to_user_to_whitelist_status: WhitelistStatusStorage::read_only(
+                whitelist_statuses,
+            ),
+            service_id_to_user_to_setter_to_indefinite_whitelist_status:
+                IndefiniteWhitelistStatusStorage::read_only(indefinite_whitelist_statuses),
+        }
+    }
+
+    /// Same as `Whitelist::extend_whitelist_expiration`, but returns the error
+    /// so that functions can revert with it instead of failing the call
+    pub fn try_extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Account,
+        expiration_timestamp: Timestamp,
+    ) -> Result<(), Error> {
+        api3_common::extend_whitelist_expiration(
+            &mut self.service_id_to_user_to_whitelist_status,
+            service_id,
+            user.as_ref(),
+            expiration_timestamp,
+        )
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> Whitelist
+    for StylusWhitelist<'a, Access>
+{
+    type Address = Account;
+
+    fn user_is_whitelisted(&self, service_id: &Bytes32, user: &Account) -> bool {
+        api3_common::user_is_whitelisted(
+            &self.service_id_to_user_to_whitelist_status,
+            &self.clock,
+            service_id,
+            user.as_ref(),
+        )
+    }
+
+    fn extend_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        if let Err(e) = self.try_extend_whitelist_expiration(service_id, user, expiration_timestamp)
+        {
+            stylus_fail(e)
+        }
+    }
+
+    fn set_whitelist_expiration(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        expiration_timestamp: Timestamp,
+    ) {
+        api3_common::set_whitelist_expiration(
+            &mut self.service_id_to_user_to_whitelist_status,
+            service_id,
+            user.as_ref(),
+            expiration_timestamp,
+        )
+    }
+
+    fn set_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        status: bool,
+    ) -> Result<U256, Error> {
+        api3_common::set_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            user.as_ref(),
+            self.sender.as_ref(),
+            status,
+        )
+    }
+
+    fn revoke_indefinite_whitelist_status(
+        &mut self,
+        service_id: &Bytes32,
+        user: &Self::Address,
+        setter: &Self::Address,
+    ) -> Result<(bool, U256), Error> {
+        api3_common::revoke_indefinite_whitelist_status(
+            &mut self.service_id_to_user_to_whitelist_status,
+            &mut self.service_id_to_user_to_setter_to_indefinite_whitelist_status,
+            service_id,
+            user.as_ref(),
+            setter.as_ref(),
+        )
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> WhitelistRoles
+    for StylusWhitelist<'a, Access>
+{
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> AccessControlRegistryAdminnedWithManager
+    for StylusWhitelist<'a, Access>
+{
+    type Address = Account;
+
+    fn manager(&self) -> &Self::Address {
+        self.access.manager()
+    }
+
+    fn admin_role_description(&self) -> String {
+        self.access.admin_role_description()
+    }
+
+    fn admin_role_description_hash(&self) -> Bytes32 {
+        self.access.admin_role_description_hash()
+    }
+
+    fn admin_role(&self) -> Bytes32 {
+        self.access.admin_role()
+    }
+}
+
+impl<'a, Access: AccessControlRegistry<Address = Account>> WhitelistRolesWithManager
+    for StylusWhitelist<'a, Access>
+{
+    fn has_whitelist_expiration_extender_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_extender_role(), account)
+    }
+
+    fn has_indefinite_whitelister_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.indefinite_whitelister_role(), account)
+    }
+
+    fn has_whitelist_expiration_setter_role_or_is_manager(&self, account: &Account) -> bool {
+        self.manager() == account
+            || self
+                .access
+                .has_role(&self.whitelist_expiration_setter_role(), account)
+    }
+}